@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::config::{deserialize_duration, deserialize_opt_duration, deserialize_opt_regex};
+use crate::internals::{redact, redact_url, REDACTED};
+
+/// The `[alerts]` section of the configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// Rules to evaluate.
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+
+    /// Webhooks that rules can notify.
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+/// A rule like "group X, topic Y: offset lag > N for M minutes".
+///
+/// The lag is aggregated by Consumer Group and Topic: Offset Lag is summed across partitions,
+/// while Time Lag is the max across partitions.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    /// Name of the rule, used in notifications.
+    pub name: String,
+
+    /// Regex that Consumer Groups have to match. If not set, all groups match.
+    #[serde(default, deserialize_with = "deserialize_opt_regex")]
+    pub group: Option<Regex>,
+
+    /// Regex that Topics have to match. If not set, all topics match.
+    #[serde(default, deserialize_with = "deserialize_opt_regex")]
+    pub topic: Option<Regex>,
+
+    /// Fire if the Offset Lag is above this value.
+    pub offset_lag_above: Option<u64>,
+
+    /// Fire if the Time Lag is above this value (e.g. `2m`).
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub time_lag_above: Option<Duration>,
+
+    /// For how long the condition has to hold, before firing (e.g. `5m`). Default: `0s`.
+    #[serde(rename = "for", default, deserialize_with = "deserialize_duration")]
+    pub for_duration: Duration,
+
+    /// Names of the webhooks to notify.
+    pub webhooks: Vec<String>,
+}
+
+/// A webhook to notify, when a rule fires or resolves.
+///
+/// When formatted via [`fmt::Debug`], the secrets it holds are redacted.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Name of the webhook, referred to by rules.
+    pub name: String,
+
+    /// Kind of webhook, determining the payload.
+    pub kind: WebhookKind,
+
+    /// URL to `POST` to. Optional only for [`WebhookKind::Pagerduty`].
+    pub url: Option<String>,
+
+    /// Integration (routing) key. Required only for [`WebhookKind::Pagerduty`].
+    pub routing_key: Option<String>,
+
+    /// Additional HTTP headers to set (e.g. `Authorization`).
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("url", &self.url.as_deref().map(redact_url))
+            .field("routing_key", &self.routing_key.as_ref().map(|_| REDACTED))
+            .field(
+                "headers",
+                &self.headers.iter().map(|(k, v)| (k, redact(k, v))).collect::<HashMap<_, _>>(),
+            )
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookKind {
+    /// JSON serialization of [`super::Notification`].
+    Generic,
+
+    /// [Slack incoming webhook](https://api.slack.com/messaging/webhooks).
+    Slack,
+
+    /// [PagerDuty Events API v2](https://developer.pagerduty.com/docs/events-api-v2/overview/).
+    Pagerduty,
+}
+
+impl AlertsConfig {
+    /// Checks the consistency of rules and webhooks, returning a description of the first issue found.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut webhook_names = HashSet::new();
+        for w in self.webhooks.iter() {
+            if !webhook_names.insert(w.name.as_str()) {
+                return Err(format!("Duplicated webhook '{}'", w.name));
+            }
+            match w.kind {
+                WebhookKind::Generic | WebhookKind::Slack if w.url.is_none() => {
+                    return Err(format!("Webhook '{}' requires 'url'", w.name));
+                },
+                WebhookKind::Pagerduty if w.routing_key.is_none() => {
+                    return Err(format!("Webhook '{}' requires 'routing_key'", w.name));
+                },
+                _ => {},
+            }
+        }
+
+        let mut rule_names = HashSet::new();
+        for r in self.rules.iter() {
+            if !rule_names.insert(r.name.as_str()) {
+                return Err(format!("Duplicated rule '{}'", r.name));
+            }
+            if r.offset_lag_above.is_some() == r.time_lag_above.is_some() {
+                return Err(format!(
+                    "Rule '{}' requires exactly one of 'offset_lag_above' or 'time_lag_above'",
+                    r.name
+                ));
+            }
+            if r.webhooks.is_empty() {
+                return Err(format!("Rule '{}' has no 'webhooks'", r.name));
+            }
+            if let Some(w) = r.webhooks.iter().find(|w| !webhook_names.contains(w.as_str())) {
+                return Err(format!("Rule '{}' refers to unknown webhook '{w}'", r.name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(toml: &str) -> AlertsConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn parse_and_validate() {
+        let config = parse(
+            r#"
+            [[rules]]
+            name = "orders"
+            group = "^orders-.*$"
+            time_lag_above = "2m"
+            for = "5m"
+            webhooks = ["oncall"]
+
+            [[webhooks]]
+            name = "oncall"
+            kind = "pagerduty"
+            routing_key = "abc"
+            "#,
+        );
+
+        assert!(config.validate().is_ok());
+        assert_eq!(config.rules[0].time_lag_above, Some(Duration::from_secs(120)));
+        assert_eq!(config.rules[0].for_duration, Duration::from_secs(300));
+        assert!(config.rules[0].group.as_ref().unwrap().is_match("orders-eu"));
+        assert!(config.rules[0].topic.is_none());
+    }
+
+    #[test]
+    fn validate_catches_inconsistencies() {
+        let missing_webhook = parse(
+            r#"
+            [[rules]]
+            name = "r"
+            offset_lag_above = 10
+            webhooks = ["nope"]
+            "#,
+        );
+        assert_eq!(
+            missing_webhook.validate(),
+            Err("Rule 'r' refers to unknown webhook 'nope'".to_string())
+        );
+
+        let both_conditions = parse(
+            r#"
+            [[rules]]
+            name = "r"
+            offset_lag_above = 10
+            time_lag_above = "1s"
+            webhooks = []
+            "#,
+        );
+        assert!(both_conditions.validate().is_err());
+
+        let slack_without_url = parse(
+            r#"
+            [[webhooks]]
+            name = "s"
+            kind = "slack"
+            "#,
+        );
+        assert!(slack_without_url.validate().is_err());
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use prometheus::{register_int_gauge_vec_with_registry, IntGaugeVec, Registry};
+use serde::Serialize;
+
+use super::config::{AlertRule, AlertsConfig};
+use super::webhook::Webhook;
+
+use crate::internals::{LagSink, LagSinkError, LagSinkFuture, LagSinkResult};
+use crate::lag_register::{GroupTopicLag, LagSnapshot};
+use crate::prometheus_metrics::LABEL_RULE;
+
+const MET_FIRING_NAME: &str = "alerts_firing";
+const MET_FIRING_HELP: &str = "Alerts currently firing, per rule";
+
+/// How many undelivered [`Notification`]s to hold for retrying, before dropping the oldest.
+const MAX_UNDELIVERED: usize = 1000;
+
+/// Status of an alert, as reported in a [`Notification`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+/// What is sent to webhooks, when an alert fires or resolves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    pub status: AlertStatus,
+    pub rule: String,
+    pub cluster_id: String,
+    pub group: String,
+    pub topic: String,
+    pub offset_lag: u64,
+    pub time_lag_ms: i64,
+    pub at: DateTime<Utc>,
+
+    /// Names of the webhooks to send this to.
+    #[serde(skip)]
+    pub webhooks: Vec<String>,
+}
+
+/// Identifies an alert: a rule, applied to a (Consumer Group, Topic) pair.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct AlertKey {
+    rule: usize,
+    group: String,
+    topic: String,
+}
+
+#[derive(Debug, Clone)]
+struct AlertState {
+    pending_since: DateTime<Utc>,
+    firing: bool,
+    last_lag: GroupTopicLag,
+}
+
+/// Evaluates [`AlertRule`]s at every [`LagSnapshot`], notifying webhooks of alerts
+/// firing and resolving.
+///
+/// It's a [`LagSink`], so it's fed by the same reconcile tick as all other sinks.
+/// Notifications that fail to be delivered to a webhook are retried at the next evaluation.
+pub struct AlertsEngine {
+    rules: Vec<AlertRule>,
+    webhooks: HashMap<String, Webhook>,
+    state: Mutex<HashMap<AlertKey, AlertState>>,
+    undelivered: Mutex<VecDeque<(String, Notification)>>,
+    metric_firing: IntGaugeVec,
+}
+
+impl AlertsEngine {
+    pub fn new(config: AlertsConfig, metrics: Arc<Registry>) -> LagSinkResult<Self> {
+        let webhooks = config
+            .webhooks
+            .into_iter()
+            .map(|wc| Ok((wc.name.clone(), Webhook::new(wc)?)))
+            .collect::<LagSinkResult<HashMap<String, Webhook>>>()?;
+
+        let metric_firing = register_int_gauge_vec_with_registry!(
+            MET_FIRING_NAME,
+            MET_FIRING_HELP,
+            &[LABEL_RULE],
+            metrics
+        )
+        .unwrap_or_else(|_| panic!("Failed to create metric: {MET_FIRING_NAME}"));
+
+        Ok(Self {
+            rules: config.rules,
+            webhooks,
+            state: Mutex::new(HashMap::new()),
+            undelivered: Mutex::new(VecDeque::new()),
+            metric_firing,
+        })
+    }
+
+    /// Send each [`Notification`] to its webhook, in order.
+    ///
+    /// Those that fail are queued to be retried first, at the next delivery. Once delivery to a
+    /// webhook fails, the following notifications for it are queued without trying, so that
+    /// each webhook receives them in order.
+    async fn deliver(
+        &self,
+        deliveries: impl IntoIterator<Item = (String, Notification)>,
+    ) -> LagSinkResult<()> {
+        let mut failed_webhooks = HashSet::new();
+        let mut failed = Vec::new();
+        let mut last_err = None;
+
+        let queued = std::mem::take(
+            &mut *self.undelivered.lock().expect("Alerts undelivered lock poisoned"),
+        );
+        for (name, n) in queued.into_iter().chain(deliveries) {
+            let Some(w) = self.webhooks.get(&name) else {
+                continue;
+            };
+
+            if !failed_webhooks.contains(&name) {
+                match w.send(&n).await {
+                    Ok(()) => continue,
+                    Err(e) => {
+                        warn!("Failed to notify webhook '{}' (will retry): {e}", w.name());
+                        last_err = Some(e);
+                    },
+                }
+            }
+            failed_webhooks.insert(name.clone());
+            failed.push((name, n));
+        }
+
+        let mut undelivered = self.undelivered.lock().expect("Alerts undelivered lock poisoned");
+        undelivered.extend(failed);
+        while undelivered.len() > MAX_UNDELIVERED {
+            if let Some((name, n)) = undelivered.pop_front() {
+                warn!(
+                    "Dropping undelivered notification of alert '{}' for group '{}' on topic '{}' to webhook '{}'",
+                    n.rule, n.group, n.topic, name
+                );
+            }
+        }
+
+        match last_err {
+            Some(e) => Err(LagSinkError::Delivery(e.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl LagSink for AlertsEngine {
+    fn name(&self) -> &'static str {
+        "alerts"
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let notifications = {
+                let mut state = self.state.lock().expect("Alerts state lock poisoned");
+                let notifications = evaluate(&self.rules, &mut state, snapshot);
+
+                for (i, r) in self.rules.iter().enumerate() {
+                    let firing = state.iter().filter(|(k, s)| k.rule == i && s.firing).count();
+                    self.metric_firing.with_label_values(&[&r.name]).set(firing as i64);
+                }
+
+                notifications
+            };
+
+            for n in notifications.iter() {
+                info!(
+                    "Alert '{}' {:?} for group '{}' on topic '{}'",
+                    n.rule, n.status, n.group, n.topic
+                );
+            }
+
+            self.deliver(
+                notifications
+                    .into_iter()
+                    .flat_map(|n| n.webhooks.clone().into_iter().map(move |w| (w, n.clone()))),
+            )
+            .await
+        })
+    }
+}
+
+/// Evaluates all the rules against the snapshot, updating the state of each alert.
+///
+/// Returns a [`Notification`] for each alert that started firing, or got resolved.
+///
+/// While the lag of a (Consumer Group, Topic) pair is not known (e.g. after the committed offsets
+/// expired), its alerts are left as they are: they are resolved only once the pair is gone.
+fn evaluate(
+    rules: &[AlertRule],
+    state: &mut HashMap<AlertKey, AlertState>,
+    snapshot: &LagSnapshot,
+) -> Vec<Notification> {
+    let now = snapshot.taken_at;
+    let lag_by_group_topic = snapshot.lag_by_group_topic();
+    let tracked: HashSet<(&str, &str)> = snapshot
+        .iter_group_partitions()
+        .map(|(g, tp, _)| (g.as_ref(), tp.topic.as_ref()))
+        .collect();
+    let mut notifications = Vec::new();
+
+    let notification =
+        |status, rule: &AlertRule, key: &AlertKey, lag: &GroupTopicLag| Notification {
+            status,
+            rule: rule.name.clone(),
+            cluster_id: snapshot.cluster_id.clone(),
+            group: key.group.clone(),
+            topic: key.topic.clone(),
+            offset_lag: lag.offset_lag,
+            time_lag_ms: lag.time_lag.num_milliseconds(),
+            at: now,
+            webhooks: rule.webhooks.clone(),
+        };
+
+    for (i, rule) in rules.iter().enumerate() {
+        for ((group, topic), gtl) in lag_by_group_topic.iter() {
+            if !rule.group.as_ref().map(|re| re.is_match(group)).unwrap_or(true)
+                || !rule.topic.as_ref().map(|re| re.is_match(topic)).unwrap_or(true)
+            {
+                continue;
+            }
+
+            let key = AlertKey {
+                rule: i,
+                group: group.to_string(),
+                topic: topic.to_string(),
+            };
+
+            let breached = rule.offset_lag_above.map(|t| gtl.offset_lag > t).unwrap_or(false)
+                || rule
+                    .time_lag_above
+                    .map(|t| gtl.time_lag.to_std().map(|tl| tl > t).unwrap_or(false))
+                    .unwrap_or(false);
+
+            if breached {
+                let s = state.entry(key.clone()).or_insert_with(|| AlertState {
+                    pending_since: now,
+                    firing: false,
+                    last_lag: *gtl,
+                });
+                s.last_lag = *gtl;
+
+                let pending_for = (now - s.pending_since).to_std().unwrap_or_default();
+                if !s.firing && pending_for >= rule.for_duration {
+                    s.firing = true;
+                    notifications.push(notification(AlertStatus::Firing, rule, &key, gtl));
+                }
+            } else if let Some(s) = state.remove(&key) {
+                if s.firing {
+                    notifications.push(notification(AlertStatus::Resolved, rule, &key, gtl));
+                }
+            }
+        }
+    }
+
+    // Alerts whose (Consumer Group, Topic) pair is gone, are resolved too
+    state.retain(|key, s| {
+        if tracked.contains(&(key.group.as_str(), key.topic.as_str())) {
+            return true;
+        }
+        if s.firing {
+            notifications.push(notification(
+                AlertStatus::Resolved,
+                &rules[key.rule],
+                key,
+                &s.last_lag,
+            ));
+        }
+        false
+    });
+
+    notifications
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::alerts::{WebhookConfig, WebhookKind};
+    use crate::kafka_types::TopicPartition;
+    use crate::lag_register::{GroupWithLag, Lag, LagWithOwner};
+
+    fn snapshot(offset_lag: Option<u64>, at_secs: i64) -> LagSnapshot {
+        let mut snapshot = LagSnapshot {
+            taken_at: DateTime::from_timestamp(at_secs, 0).unwrap(),
+            ..Default::default()
+        };
+
+        if let Some(offset_lag) = offset_lag {
+            let mut gwl = GroupWithLag::default();
+            gwl.lag_by_topic_partition.insert(
+                TopicPartition::new("t".to_string(), 0),
+                LagWithOwner {
+                    lag: Some(Lag {
+                        offset_lag,
+                        time_lag: Duration::zero(),
+                        ..Default::default()
+                    }),
+                    owner: None,
+                },
+            );
+            snapshot.groups.insert("g".into(), Arc::new(gwl));
+        }
+
+        snapshot
+    }
+
+    fn rules() -> Vec<AlertRule> {
+        vec![AlertRule {
+            name: "r".to_string(),
+            group: None,
+            topic: None,
+            offset_lag_above: Some(100),
+            time_lag_above: None,
+            for_duration: std::time::Duration::from_secs(60),
+            webhooks: vec!["w".to_string()],
+        }]
+    }
+
+    #[test]
+    fn fires_after_for_duration_then_resolves() {
+        let rules = rules();
+        let mut state = HashMap::new();
+
+        // Breached, but not for long enough
+        assert!(evaluate(&rules, &mut state, &snapshot(Some(200), 0)).is_empty());
+        assert!(evaluate(&rules, &mut state, &snapshot(Some(200), 30)).is_empty());
+
+        // Fires only once
+        let n = evaluate(&rules, &mut state, &snapshot(Some(200), 60));
+        assert_eq!(n.len(), 1);
+        assert_eq!(n[0].status, AlertStatus::Firing);
+        assert_eq!(n[0].offset_lag, 200);
+        assert!(evaluate(&rules, &mut state, &snapshot(Some(300), 90)).is_empty());
+
+        // Resolves
+        let n = evaluate(&rules, &mut state, &snapshot(Some(50), 120));
+        assert_eq!(n.len(), 1);
+        assert_eq!(n[0].status, AlertStatus::Resolved);
+        assert!(state.is_empty());
+    }
+
+    #[test]
+    fn pending_is_reset_when_not_breached() {
+        let rules = rules();
+        let mut state = HashMap::new();
+
+        assert!(evaluate(&rules, &mut state, &snapshot(Some(200), 0)).is_empty());
+        assert!(evaluate(&rules, &mut state, &snapshot(Some(50), 30)).is_empty());
+        assert!(evaluate(&rules, &mut state, &snapshot(Some(200), 60)).is_empty());
+        assert_eq!(evaluate(&rules, &mut state, &snapshot(Some(200), 120)).len(), 1);
+    }
+
+    #[test]
+    fn resolves_when_group_is_gone() {
+        let rules = rules();
+        let mut state = HashMap::new();
+
+        evaluate(&rules, &mut state, &snapshot(Some(200), 0));
+        assert_eq!(evaluate(&rules, &mut state, &snapshot(Some(200), 60)).len(), 1);
+
+        let n = evaluate(&rules, &mut state, &snapshot(None, 90));
+        assert_eq!(n.len(), 1);
+        assert_eq!(n[0].status, AlertStatus::Resolved);
+        assert_eq!(n[0].offset_lag, 200);
+    }
+
+    #[test]
+    fn keeps_firing_while_lag_is_unknown() {
+        let rules = rules();
+        let mut state = HashMap::new();
+
+        evaluate(&rules, &mut state, &snapshot(Some(200), 0));
+        assert_eq!(evaluate(&rules, &mut state, &snapshot(Some(200), 60)).len(), 1);
+
+        // The group still consumes the topic, but its lag is not known
+        let mut unknown = snapshot(None, 90);
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition
+            .insert(TopicPartition::new("t".to_string(), 0), LagWithOwner::default());
+        unknown.groups.insert("g".into(), Arc::new(gwl));
+        assert!(evaluate(&rules, &mut state, &unknown).is_empty());
+        assert!(state.values().all(|s| s.firing));
+
+        // Neither resolved, nor fired again, once the lag is known again
+        assert!(evaluate(&rules, &mut state, &snapshot(Some(200), 120)).is_empty());
+        let n = evaluate(&rules, &mut state, &snapshot(Some(50), 150));
+        assert_eq!(n.len(), 1);
+        assert_eq!(n[0].status, AlertStatus::Resolved);
+    }
+
+    #[tokio::test]
+    async fn retries_undelivered_notifications_in_order() {
+        let config = AlertsConfig {
+            rules: vec![AlertRule {
+                for_duration: std::time::Duration::ZERO,
+                ..rules().remove(0)
+            }],
+            webhooks: vec![WebhookConfig {
+                name: "w".to_string(),
+                kind: WebhookKind::Generic,
+                url: Some("http://127.0.0.1:1/unreachable".to_string()),
+                routing_key: None,
+                headers: HashMap::new(),
+            }],
+        };
+        let engine = AlertsEngine::new(config, Arc::new(Registry::new())).unwrap();
+        let undelivered = |engine: &AlertsEngine| -> Vec<AlertStatus> {
+            engine.undelivered.lock().unwrap().iter().map(|(_, n)| n.status).collect()
+        };
+
+        assert!(engine.publish(&snapshot(Some(200), 0)).await.is_err());
+        assert_eq!(undelivered(&engine), vec![AlertStatus::Firing]);
+
+        // Retried at the next evaluation, without duplicates
+        assert!(engine.publish(&snapshot(Some(200), 30)).await.is_err());
+        assert_eq!(undelivered(&engine), vec![AlertStatus::Firing]);
+
+        // Queued behind the earlier ones
+        assert!(engine.publish(&snapshot(Some(50), 60)).await.is_err());
+        assert_eq!(undelivered(&engine), vec![AlertStatus::Firing, AlertStatus::Resolved]);
+    }
+}
@@ -0,0 +1,12 @@
+//! Alerting on lag thresholds, directly from Kommitted.
+//!
+//! Rules are defined in the configuration file (see [`crate::config`]), and evaluated at every
+//! reconcile tick: when a rule condition holds for long enough, its webhooks are notified; once
+//! the condition stops holding, they are notified again of the resolution.
+
+mod config;
+mod engine;
+mod webhook;
+
+pub use config::{AlertRule, AlertsConfig, WebhookConfig, WebhookKind};
+pub use engine::AlertsEngine;
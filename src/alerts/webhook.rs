@@ -0,0 +1,162 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{json, Value};
+use tokio::time::Duration;
+
+use super::config::{WebhookConfig, WebhookKind};
+use super::engine::{AlertStatus, Notification};
+
+use crate::internals::{LagSinkError, LagSinkResult};
+
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A configured [`WebhookConfig`], ready to send [`Notification`]s.
+pub struct Webhook {
+    config: WebhookConfig,
+    client: reqwest::Client,
+}
+
+impl Webhook {
+    pub fn new(config: WebhookConfig) -> LagSinkResult<Self> {
+        let mut headers = HeaderMap::new();
+        for (k, v) in config.headers.iter() {
+            headers.insert(
+                HeaderName::try_from(k).map_err(|e| LagSinkError::Config(e.to_string()))?,
+                HeaderValue::try_from(v).map_err(|e| LagSinkError::Config(e.to_string()))?,
+            );
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(SEND_TIMEOUT)
+            .build()
+            .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+        Ok(Self {
+            config,
+            client,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub async fn send(&self, n: &Notification) -> LagSinkResult<()> {
+        let url = match (self.config.kind, self.config.url.as_ref()) {
+            (_, Some(url)) => url.as_str(),
+            (WebhookKind::Pagerduty, None) => PAGERDUTY_EVENTS_URL,
+            (_, None) => {
+                return Err(LagSinkError::Delivery(format!("Webhook '{}' has no URL", self.name())))
+            },
+        };
+
+        let res = self
+            .client
+            .post(url)
+            .json(&body(&self.config, n))
+            .send()
+            .await
+            .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+        if !res.status().is_success() {
+            return Err(LagSinkError::Delivery(format!("'{url}' responded {}", res.status())));
+        }
+
+        Ok(())
+    }
+}
+
+fn summary(n: &Notification) -> String {
+    format!(
+        "Rule '{}': group '{}' on topic '{}' has offset lag {} and time lag {}ms (cluster '{}')",
+        n.rule, n.group, n.topic, n.offset_lag, n.time_lag_ms, n.cluster_id
+    )
+}
+
+/// JSON body to send, depending on the [`WebhookKind`].
+fn body(config: &WebhookConfig, n: &Notification) -> Value {
+    match config.kind {
+        WebhookKind::Generic => json!(n),
+        WebhookKind::Slack => {
+            let prefix = match n.status {
+                AlertStatus::Firing => ":rotating_light: *FIRING*",
+                AlertStatus::Resolved => ":white_check_mark: *RESOLVED*",
+            };
+            json!({ "text": format!("{prefix} {}", summary(n)) })
+        },
+        WebhookKind::Pagerduty => {
+            let dedup_key =
+                format!("kommitted/{}/{}/{}/{}", n.cluster_id, n.rule, n.group, n.topic);
+            match n.status {
+                AlertStatus::Firing => json!({
+                    "routing_key": config.routing_key,
+                    "event_action": "trigger",
+                    "dedup_key": dedup_key,
+                    "payload": {
+                        "summary": summary(n),
+                        "source": n.cluster_id,
+                        "severity": "error",
+                        "timestamp": n.at,
+                        "custom_details": n,
+                    },
+                }),
+                AlertStatus::Resolved => json!({
+                    "routing_key": config.routing_key,
+                    "event_action": "resolve",
+                    "dedup_key": dedup_key,
+                }),
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+
+    use super::*;
+
+    fn notification(status: AlertStatus) -> Notification {
+        Notification {
+            status,
+            rule: "r".to_string(),
+            cluster_id: "c".to_string(),
+            group: "g".to_string(),
+            topic: "t".to_string(),
+            offset_lag: 10,
+            time_lag_ms: 20,
+            at: DateTime::from_timestamp(0, 0).unwrap(),
+            webhooks: vec![],
+        }
+    }
+
+    fn config(kind: WebhookKind) -> WebhookConfig {
+        WebhookConfig {
+            name: "w".to_string(),
+            kind,
+            url: None,
+            routing_key: Some("key".to_string()),
+            headers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn generic_body() {
+        let b = body(&config(WebhookKind::Generic), &notification(AlertStatus::Firing));
+        assert_eq!(b["status"], "firing");
+        assert_eq!(b["offset_lag"], 10);
+        assert!(b.get("webhooks").is_none());
+    }
+
+    #[test]
+    fn pagerduty_body() {
+        let trigger = body(&config(WebhookKind::Pagerduty), &notification(AlertStatus::Firing));
+        let resolve = body(&config(WebhookKind::Pagerduty), &notification(AlertStatus::Resolved));
+
+        assert_eq!(trigger["event_action"], "trigger");
+        assert_eq!(resolve["event_action"], "resolve");
+        assert_eq!(trigger["dedup_key"], resolve["dedup_key"]);
+        assert_eq!(trigger["routing_key"], "key");
+    }
+}
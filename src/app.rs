@@ -0,0 +1,345 @@
+//! The `kommitted` command line application: see [`run_cli`].
+
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use rdkafka::ClientConfig;
+use std::error::Error;
+use std::io::IsTerminal;
+use tokio::task::JoinError;
+use tokio_util::sync::CancellationToken;
+
+use crate::cert_reload::CertFiles;
+use crate::cli::{Cli, Command};
+use crate::failover::{self, BootstrapEndpoints};
+use crate::http::{self, HttpServer};
+use crate::lag_register::LagSnapshot;
+use crate::lag_table::{self, LagTableFormat};
+use crate::logging;
+use crate::preflight::{self, Check};
+use crate::preview::MetricsPreview;
+use crate::report::{self, LagReport};
+use crate::service::KommittedState;
+use crate::top;
+use crate::validation::ValidationMode;
+
+/// Run Kommitted as the `kommitted` binary does: configured via the command line arguments
+/// (and environment variables), until it's told to shutdown via a termination signal.
+///
+/// Only for those that want to build their own binary (e.g. with a custom allocator):
+/// to embed Kommitted in another service, use [`crate::Kommitted::builder`] instead.
+pub fn run_cli() -> Result<(), Box<dyn Error>> {
+    let cli = parse_cli_and_init_logging();
+
+    // Built manually, instead of via `#[tokio::main]`, so that it can be sized via the CLI
+    let runtime = cli.build_runtime()?;
+    runtime.block_on(run(cli))
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let issues = cli.config_issues();
+    if cli.validate_config {
+        for i in issues.iter() {
+            println!("[FAIL] {i}");
+        }
+        // Loading the files validates them
+        let files_loaded = match cli.kommitted_builder() {
+            Ok(_) => true,
+            Err(e) => {
+                println!("[FAIL] {e}");
+                false
+            },
+        };
+
+        if issues.is_empty() && files_loaded {
+            println!("[ OK ] Configuration is valid");
+            std::process::exit(exit_code::SUCCESS);
+        }
+        std::process::exit(exit_code::FAILURE);
+    }
+    for i in issues.iter() {
+        match cli.config_validation {
+            ValidationMode::Strict => error!("Configuration issue {i}"),
+            ValidationMode::Permissive => warn!("Configuration issue {i}"),
+        }
+    }
+    if cli.config_validation == ValidationMode::Strict && !issues.is_empty() {
+        error!(
+            "Refusing to start with {} configuration issues ('--config-validation strict')",
+            issues.len()
+        );
+        std::process::exit(exit_code::FAILURE);
+    }
+
+    if let Some(Command::Report {
+        from,
+        to,
+        threshold,
+        format,
+    }) = cli.command
+    {
+        let dir = cli.dump_dir.clone().expect("'--dump-dir' is validated for 'report'");
+        let to = to.unwrap_or_else(chrono::Utc::now);
+        let records =
+            tokio::task::spawn_blocking(move || report::read_dump_dir(&dir, from, to)).await??;
+        print!("{}", LagReport::from_records(&records, from, to, threshold).render(format)?);
+
+        std::process::exit(exit_code::SUCCESS);
+    }
+
+    if let Some(Command::PreviewMetrics {
+        warm_up,
+    }) = cli.command
+    {
+        let shutdown_token = build_shutdown_token();
+        let handle = cli.kommitted_builder()?.build()?.start(shutdown_token.clone()).await?;
+
+        info!("Collecting lag data for {}...", humantime::format_duration(warm_up));
+        tokio::select! {
+            _ = tokio::time::sleep(warm_up) => {},
+            _ = shutdown_token.cancelled() => {},
+        }
+        print!("{}", MetricsPreview::from_exposition(&handle.render_metrics().await?));
+
+        shutdown_token.cancel();
+        handle.join().await;
+        std::process::exit(exit_code::SUCCESS);
+    }
+
+    if let Some(Command::Snapshot {
+        warm_up,
+        output,
+    }) = cli.command
+    {
+        let shutdown_token = build_shutdown_token();
+        let handle = cli.kommitted_builder()?.build()?.start(shutdown_token.clone()).await?;
+
+        info!("Collecting lag data for {}...", humantime::format_duration(warm_up));
+        tokio::select! {
+            _ = tokio::time::sleep(warm_up) => {},
+            _ = shutdown_token.cancelled() => {},
+        }
+        let snapshot = LagSnapshot::take(
+            &handle.cluster_status_register(),
+            &handle.partition_offsets_register(),
+            &handle.lag_register(),
+        )
+        .await;
+        let colored = output == LagTableFormat::Table
+            && std::io::stdout().is_terminal()
+            && std::env::var_os("NO_COLOR").is_none();
+        print!("{}", lag_table::render(&snapshot, output, colored)?);
+
+        shutdown_token.cancel();
+        handle.join().await;
+        std::process::exit(exit_code::SUCCESS);
+    }
+
+    if let Some(Command::Top {
+        refresh_interval,
+        sort,
+    }) = cli.command
+    {
+        let shutdown_token = build_shutdown_token();
+        let handle = cli.kommitted_builder()?.build()?.start(shutdown_token.clone()).await?;
+
+        let colored = std::env::var_os("NO_COLOR").is_none();
+        top::run(&handle, sort, refresh_interval, colored, shutdown_token.clone()).await;
+
+        shutdown_token.cancel();
+        handle.join().await;
+        std::process::exit(exit_code::SUCCESS);
+    }
+
+    if cli.command == Some(Command::Check) {
+        let client_config = cli.build_client_config();
+        let offsets_source = cli.offsets_source();
+        let checks =
+            tokio::task::spawn_blocking(move || preflight::run(&client_config, offsets_source))
+                .await?;
+        for c in checks.iter() {
+            println!("{c}");
+        }
+
+        std::process::exit(if checks.iter().any(Check::is_failed) {
+            exit_code::FAILURE
+        } else {
+            exit_code::SUCCESS
+        });
+    }
+
+    let shutdown_token = build_shutdown_token();
+
+    // A replay, or a simulated cluster, doesn't connect to Kafka: there are no clients to re-create
+    if !cli.is_offline()
+        && (cli.bootstrap_endpoints().has_alternates()
+            || cli.recycle_clients_every.is_some()
+            || !CertFiles::of(&cli.build_client_config()).is_empty())
+    {
+        run_recreating_clients(&cli, shutdown_token).await?;
+    } else {
+        cli.kommitted_builder()?.build()?.run(shutdown_token).await?;
+    }
+
+    info!("Shutdown!");
+    std::process::exit(exit_code::SUCCESS);
+}
+
+/// Run the service against the first reachable `--brokers` entry point, re-creating it
+/// against the next reachable one every time the cluster can't be reached via the current one.
+///
+/// It's also re-created against the same one periodically, if `--recycle-clients-every` is set,
+/// so that all the Kafka clients resolve the brokers addresses again, and when the certificate
+/// files they use change (e.g. rotated). The state of each instance is handed over to the next.
+///
+/// The HTTP server outlives the instances: until the next is started, the previous one's
+/// state is still served.
+async fn run_recreating_clients(
+    cli: &Cli,
+    shutdown_token: CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let http_token = shutdown_token.child_token();
+    let (http_server, http_join) = http::init(cli.listen_on(), http_token.clone());
+
+    let res = recreate_clients(cli, http_server, shutdown_token).await;
+    http_token.cancel();
+    http_join.await?;
+    res
+}
+
+/// See [`run_recreating_clients`].
+async fn recreate_clients(
+    cli: &Cli,
+    http_server: HttpServer,
+    shutdown_token: CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let probe_config = cli.build_client_config();
+    let mut endpoints = cli.bootstrap_endpoints();
+    endpoints = tokio::task::spawn_blocking({
+        let probe_config = probe_config.clone();
+        move || {
+            endpoints.first_reachable(|e| failover::is_reachable(&probe_config, e));
+            endpoints
+        }
+    })
+    .await?;
+
+    let mut cert_files = CertFiles::of(&probe_config);
+    let mut handover: Option<KommittedState> = None;
+    loop {
+        info!("Connecting to the cluster via '{}'", endpoints.current());
+        let mut client_config = cli.build_client_config();
+        client_config.set("bootstrap.servers", endpoints.current());
+
+        // Each run gets its own token, so that it can be shut down to re-create it
+        let run_token = shutdown_token.child_token();
+        let mut builder =
+            cli.kommitted_builder()?.client_config(client_config).http_server(http_server.clone());
+        if let Some(state) = handover.as_ref() {
+            builder = builder.handover(state.clone());
+        }
+
+        // Starting waits for the cluster to be reached: fail over if it can't be, meanwhile
+        let probe = {
+            let probe_config = probe_config.clone();
+            let endpoint = endpoints.current().to_string();
+            move || failover::is_reachable(&probe_config, &endpoint)
+        };
+        let handle = tokio::select! {
+            res = builder.build()?.start(run_token.clone()) => res?,
+            unreachable = failover::await_probe_unreachable(probe, cli.failover_after, shutdown_token.clone()) => {
+                run_token.cancel();
+                if !unreachable {
+                    return Ok(());
+                }
+                endpoints = fail_over(&probe_config, endpoints).await?;
+                continue;
+            },
+        };
+
+        let recycle = async {
+            match cli.recycle_clients_every {
+                Some(every) => tokio::time::sleep(every).await,
+                None => std::future::pending().await,
+            }
+        };
+        let cs_reg = handle.cluster_status_register();
+        let (unreachable, recreate) = tokio::select! {
+            unreachable = failover::await_unreachable(&cs_reg, cli.failover_after, shutdown_token.clone()) => {
+                (unreachable, unreachable)
+            },
+            _ = recycle => {
+                info!("Recycling Kafka clients, to resolve brokers addresses again");
+                (false, true)
+            },
+            changed = cert_files.await_changed(shutdown_token.clone()) => {
+                if changed {
+                    info!("Re-creating Kafka clients, to load the changed certificates");
+                }
+                (false, changed)
+            },
+        };
+
+        run_token.cancel();
+        if recreate {
+            handover = Some(handle.state().await);
+        }
+        handle.join().await;
+        if !recreate {
+            return Ok(());
+        }
+        if !unreachable {
+            continue;
+        }
+
+        endpoints = fail_over(&probe_config, endpoints).await?;
+    }
+}
+
+/// Fail over to the next reachable of the [`BootstrapEndpoints`] (see [`BootstrapEndpoints::fail_over`]).
+async fn fail_over(
+    probe_config: &ClientConfig,
+    mut endpoints: BootstrapEndpoints,
+) -> Result<BootstrapEndpoints, JoinError> {
+    let probe_config = probe_config.clone();
+    tokio::task::spawn_blocking(move || {
+        let failed = endpoints.current().to_string();
+        let next = endpoints.fail_over(|e| failover::is_reachable(&probe_config, e));
+        warn!("Cluster not reachable via '{failed}': failing over to '{next}'");
+        endpoints
+    })
+    .await
+}
+
+fn parse_cli_and_init_logging() -> Cli {
+    // Parse command line input and initialize logging
+    let cli = Cli::parse();
+    if let Err(e) = cli.validate() {
+        e.exit();
+    }
+    if let Err(e) = logging::init(cli.verbosity_level(), cli.log_target()) {
+        Cli::command().error(ErrorKind::Io, format!("Unable to set up logging: {e}")).exit();
+    }
+
+    trace!("Created:\n{:#?}", cli);
+
+    cli
+}
+
+fn build_shutdown_token() -> CancellationToken {
+    let shutdown_token = CancellationToken::new();
+
+    // Setup shutdown signal handler:
+    // when it's time to shutdown, cancels the token and all
+    // other holders of a clone will be notified to being shutdown sequence.
+    //
+    // NOTE: This handler will be listening on its own dedicated thread.
+    let shutdown_token_clone = shutdown_token.clone();
+    if let Err(e) = ctrlc::set_handler(move || {
+        info!("Beginning shutdown...");
+        shutdown_token_clone.cancel();
+    }) {
+        error!("Failed to register signal handler: {e}");
+    }
+
+    // Return a CancellationToken that can notify other parts of the system.
+    shutdown_token
+}
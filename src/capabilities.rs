@@ -0,0 +1,181 @@
+//! Detection of the optional capabilities of the Kafka cluster, gating the features depending on them.
+//!
+//! Brokers of older versions (or of partially-compatible Kafka distributions) don't support
+//! all the APIs the service uses: detecting that at startup, the features depending on them
+//! are disabled upfront, instead of failing (and logging about it) at every attempt.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use rdkafka::{
+    config::RDKafkaLogLevel,
+    consumer::{BaseConsumer, Consumer},
+    error::KafkaResult,
+    ClientConfig,
+};
+
+use crate::cluster_status::{describe_cluster, describe_topic_ids};
+use crate::internals::{is_unsupported, Subsystem, Subsystems};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Topic that exists only once the cluster has coordinated (at least) a transaction.
+const TRANSACTION_STATE_TOPIC: &str = "__transaction_state";
+
+/// Optional capability of the Kafka cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capability {
+    /// `DescribeCluster` API (Kafka 2.8+): controller and Brokers rack.
+    DescribeCluster,
+
+    /// Topic identifiers (Kafka 2.8+): needed to resolve the assignments of Consumer Groups
+    /// using the next-gen consumer rebalance protocol (KIP-848), that refer to Topics by identifier.
+    TopicIdentifiers,
+
+    /// Transactions: offsets might be committed by transactions, and so
+    /// `--konsumer-offsets-isolation-level` determines which are read.
+    Transactions,
+}
+
+impl Capability {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::DescribeCluster => "describe_cluster",
+            Capability::TopicIdentifiers => "topic_identifiers",
+            Capability::Transactions => "transactions",
+        }
+    }
+
+    /// [`Subsystem`] that depends on this [`Capability`], if any.
+    pub fn subsystem(&self) -> Option<Subsystem> {
+        match self {
+            Capability::DescribeCluster => Some(Subsystem::ClusterDescription),
+            Capability::TopicIdentifiers => Some(Subsystem::TopicIdentifiers),
+            Capability::Transactions => None,
+        }
+    }
+}
+
+/// Which [`Capability`] the Kafka cluster has.
+///
+/// A [`Capability`] whose detection failed for reasons other than lack of support
+/// (e.g. lack of permissions, or a timeout) is assumed to be supported: whatever the reason,
+/// the [`Subsystem`] depending on it will handle (and report) it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Capabilities {
+    supported: BTreeMap<Capability, bool>,
+}
+
+impl Capabilities {
+    /// Detect the [`Capabilities`] of the Kafka cluster, probing the APIs they depend on.
+    ///
+    /// This is blocking: call it via [`tokio::task::spawn_blocking`] from async code.
+    pub fn detect(client_config: &ClientConfig) -> Self {
+        let mut client_config = client_config.clone();
+        client_config.set_log_level(RDKafkaLogLevel::Critical);
+
+        let consumer: BaseConsumer = match client_config.create() {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Failed to create client to detect capabilities: {e}");
+                return Self::default();
+            },
+        };
+
+        let mut capabilities = Self::default();
+        capabilities.set_probed(
+            Capability::DescribeCluster,
+            &describe_cluster(consumer.client(), PROBE_TIMEOUT),
+        );
+        match consumer.fetch_metadata(None, PROBE_TIMEOUT) {
+            Ok(metadata) => {
+                let names = metadata.topics().iter().map(|t| t.name()).collect::<Vec<_>>();
+                capabilities.set_probed(
+                    Capability::TopicIdentifiers,
+                    &describe_topic_ids(consumer.client(), &names, PROBE_TIMEOUT),
+                );
+                capabilities
+                    .set(Capability::Transactions, names.contains(&TRANSACTION_STATE_TOPIC));
+            },
+            Err(e) => {
+                warn!("Failed to fetch metadata to detect capabilities: {e}");
+            },
+        }
+
+        capabilities
+    }
+
+    fn set(&mut self, capability: Capability, supported: bool) {
+        self.supported.insert(capability, supported);
+    }
+
+    fn set_probed<T>(&mut self, capability: Capability, probe: &KafkaResult<T>) {
+        match probe {
+            Err(e) if is_unsupported(e) => {
+                debug!("Capability '{}' not supported: {e}", capability.name());
+                self.set(capability, false);
+            },
+            _ => self.set(capability, true),
+        }
+    }
+
+    /// Whether the [`Capability`] is supported (or could not be detected).
+    pub fn is_supported(&self, capability: Capability) -> bool {
+        self.supported.get(&capability).copied().unwrap_or(true)
+    }
+
+    /// Log which [`Capability`] is active, and which isn't.
+    pub fn log(&self) {
+        let (active, inactive): (Vec<_>, Vec<_>) = self.supported.iter().partition(|(_, s)| **s);
+        let names = |caps: Vec<(&Capability, &bool)>| {
+            caps.into_iter().map(|(c, _)| c.name()).collect::<Vec<_>>().join(", ")
+        };
+        info!("Active capabilities: [{}]", names(active));
+        info!("Inactive capabilities: [{}]", names(inactive));
+    }
+
+    /// Disable the [`Subsystem`]s depending on an unsupported [`Capability`].
+    pub fn gate(&self, subsystems: &Subsystems) {
+        for c in self.supported.keys().filter(|c| !self.is_supported(**c)) {
+            if let Some(subsystem) = c.subsystem() {
+                subsystems.disable_unsupported(subsystem);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use prometheus::Registry;
+    use rdkafka::{error::KafkaError, types::RDKafkaErrorCode};
+
+    use super::*;
+
+    #[test]
+    fn gates_subsystems_of_unsupported_capabilities() {
+        let mut capabilities = Capabilities::default();
+        capabilities.set_probed(
+            Capability::DescribeCluster,
+            &KafkaResult::<()>::Err(KafkaError::AdminOp(RDKafkaErrorCode::UnsupportedVersion)),
+        );
+        capabilities.set_probed(
+            Capability::TopicIdentifiers,
+            &KafkaResult::<()>::Err(KafkaError::AdminOp(RDKafkaErrorCode::OperationTimedOut)),
+        );
+        capabilities.set(Capability::Transactions, false);
+
+        assert!(!capabilities.is_supported(Capability::DescribeCluster));
+        assert!(capabilities.is_supported(Capability::TopicIdentifiers));
+        assert!(!capabilities.is_supported(Capability::Transactions));
+        assert!(Capabilities::default().is_supported(Capability::DescribeCluster));
+
+        // Disabled even if not lenient
+        let subsystems = Subsystems::new(false, Arc::new(Registry::new()));
+        capabilities.gate(&subsystems);
+        assert!(!subsystems.is_enabled(Subsystem::ClusterDescription));
+        assert!(subsystems.is_enabled(Subsystem::TopicIdentifiers));
+        assert!(subsystems.is_live());
+    }
+}
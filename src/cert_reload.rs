@@ -0,0 +1,114 @@
+//! Detection of the rotation of client certificates (e.g. for mTLS), on disk.
+//!
+//! Kafka clients load certificates only when they are created: once the certificates are
+//! rotated (e.g. by cert-manager), clients have to be re-created to use the new ones,
+//! before the old ones expire and authentication starts failing.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use rdkafka::ClientConfig;
+use tokio_util::sync::CancellationToken;
+
+/// Kafka client configuration keys that point at certificate files.
+const CERT_LOCATION_KEYS: [&str; 3] =
+    ["ssl.certificate.location", "ssl.key.location", "ssl.ca.location"];
+
+const CHANGES_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Certificate files used by the Kafka clients, with when they were last modified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertFiles {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl CertFiles {
+    /// The certificate files set in the given Kafka client configuration, if any.
+    pub fn of(client_config: &ClientConfig) -> Self {
+        Self::new(CERT_LOCATION_KEYS.iter().filter_map(|k| client_config.get(k)).map(PathBuf::from))
+    }
+
+    fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            files: paths
+                .into_iter()
+                .map(|p| {
+                    let modified = modified(&p);
+                    (p, modified)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Whether any of the files was modified (or created, or removed) since the last time
+    /// this was called (or since creation).
+    pub fn changed(&mut self) -> bool {
+        let mut changed = false;
+        for (path, last_modified) in self.files.iter_mut() {
+            let modified = modified(path);
+            if modified != *last_modified {
+                info!("Certificate file '{}' changed", path.display());
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Wait until any of the files changes.
+    ///
+    /// Returns early (`false`) if the given [`CancellationToken`] is cancelled.
+    /// If there are no files, it waits for cancellation.
+    pub async fn await_changed(&mut self, shutdown_token: CancellationToken) -> bool {
+        if self.is_empty() {
+            shutdown_token.cancelled().await;
+            return false;
+        }
+
+        let mut interval = tokio::time::interval(CHANGES_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if self.changed() {
+                        return true;
+                    }
+                },
+                _ = shutdown_token.cancelled() => return false,
+            }
+        }
+    }
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+
+    use super::*;
+
+    #[test]
+    fn detects_modified_files() {
+        let path = std::env::temp_dir().join(format!("kommitted-cert-{}.pem", std::process::id()));
+        let file = File::create(&path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000)).unwrap();
+
+        let mut cert_files = CertFiles::new([path.clone()]);
+        assert!(!cert_files.is_empty());
+        assert!(!cert_files.changed());
+
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(2_000)).unwrap();
+        assert!(cert_files.changed());
+        assert!(!cert_files.changed());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(cert_files.changed());
+        assert!(CertFiles::of(&ClientConfig::new()).is_empty());
+    }
+}
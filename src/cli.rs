@@ -1,95 +1,1427 @@
+use std::fmt;
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::{ArgGroup, Parser};
+use chrono::{DateTime, Utc};
+use clap::{error::ErrorKind, ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
 use rdkafka::ClientConfig;
+use regex::Regex;
+use tokio::runtime::{self, Runtime};
 
+use crate::cluster_status::CLUSTER_STATUS_FETCH_INTERVAL;
+use crate::committed_offsets::OffsetsSource;
+use crate::config::{Config, ConfigResult};
+#[cfg(feature = "otlp")]
+use crate::constants::DEFAULT_OTLP_INTERVAL;
 use crate::constants::{
-    DEFAULT_HTTP_HOST, DEFAULT_HTTP_PORT, DEFAULT_OFFSETS_HISTORY, DEFAULT_OFFSETS_HISTORY_READY_AT,
+    DEFAULT_ADMIN_API_INTERVAL, DEFAULT_CLUSTER_CHANGES_PERSIST_INTERVAL, DEFAULT_DUMP_INTERVAL,
+    DEFAULT_FAILOVER_AFTER, DEFAULT_GRAPHITE_INTERVAL, DEFAULT_GROUND_TRUTH_PARTITIONS,
+    DEFAULT_GROUP_ABSENCE_GRACE, DEFAULT_HA_TOPIC, DEFAULT_HTTP_HOST, DEFAULT_HTTP_PORT,
+    DEFAULT_IDLE_WINDOW, DEFAULT_INFLUX_INTERVAL, DEFAULT_INTERVAL_JITTER,
+    DEFAULT_KONSUMER_OFFSETS_START, DEFAULT_LAGGING_GROUP_THRESHOLD,
+    DEFAULT_LAG_BYTES_SAMPLE_RECORDS, DEFAULT_LAG_EVENTS_THRESHOLD, DEFAULT_LAG_HISTORY_RESOLUTION,
+    DEFAULT_LAG_HISTORY_RETENTION, DEFAULT_LAG_PERSIST_INTERVAL, DEFAULT_LAG_SLO_OBJECTIVE,
+    DEFAULT_LAG_TOPIC_INTERVAL, DEFAULT_LAG_TREND_WINDOW, DEFAULT_LOG_FILE_KEEP,
+    DEFAULT_LOG_FILE_MAX_SIZE, DEFAULT_MIN_DATA_COVERAGE, DEFAULT_MIN_DATA_COVERAGE_TIMEOUT,
+    DEFAULT_OFFSETS_HISTORY, DEFAULT_OFFSETS_HISTORY_PERSIST_INTERVAL,
+    DEFAULT_OFFSETS_HISTORY_READY_AT, DEFAULT_PREVIEW_WARM_UP, DEFAULT_RDKAFKA_STATS_INTERVAL,
+    DEFAULT_REMOTE_WRITE_INTERVAL, DEFAULT_REPORT_THRESHOLD, DEFAULT_SNAPSHOT_WARM_UP,
+    DEFAULT_STATSD_INTERVAL, DEFAULT_STATUS_WINDOW, DEFAULT_STOPPED_COMMIT_INTERVALS,
+    DEFAULT_TOP_REFRESH_INTERVAL, DEFAULT_WATERMARKS_BATCH_SIZE, DEFAULT_WATERMARKS_CONCURRENCY,
 };
+#[cfg(feature = "cloudwatch")]
+use crate::constants::{DEFAULT_CLOUDWATCH_INTERVAL, DEFAULT_CLOUDWATCH_NAMESPACE};
+use crate::consumer_groups::{GroupExcludes, GroupLabels, GroupShard};
+use crate::failover::BootstrapEndpoints;
+use crate::internals::{redact, redacted_client_config, Jitter, PersistenceConfig, Secret};
+use crate::konsumer_offsets_data::{
+    IsolationLevel, KonsumerOffsetsConsumerConfig, KonsumerOffsetsStart,
+};
+use crate::lag_register::{LagHistoryConfig, LagTrendConfig};
+use crate::lag_table::LagTableFormat;
+use crate::leadership::HighAvailabilityConfig;
+use crate::log_targets::{LogFileConfig, LogRotation, LogTarget};
+use crate::mirror::MirrorConfig;
+use crate::partition_offsets::{HistoryRetention, TimeLagStrategyKind, WatermarksPolling};
+use crate::presets::{self, Preset};
+use crate::prometheus_metrics::MetricsGranularity;
+use crate::recording::RecordingConfig;
+use crate::report::ReportFormat;
+#[cfg(feature = "cloudwatch")]
+use crate::sinks::CloudWatchConfig;
+use crate::sinks::{
+    DumpConfig, DumpFormat, GraphiteConfig, GroundTruthConfig, InfluxConfig, KafkaTopicConfig,
+    RecordSizesConfig, RemoteWriteConfig, StatsdConfig, StatsdFlavor,
+};
+#[cfg(feature = "otlp")]
+use crate::sinks::{OtlpConfig, OtlpProtocol};
+use crate::top::TopSort;
+use crate::validation::{self, ConfigIssue, ValidationMode, MIN_INTERVAL};
+use crate::{Kommitted, KommittedBuilder};
+
+/// Subcommands: without one, the service runs.
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Check connectivity and permissions (ACLs) against the Kafka cluster, then exit.
+    ///
+    /// Each failed check comes with a hint on how to remedy it.
+    /// Exits with a non-zero code, if any check failed.
+    Check,
+
+    /// Run for a while without serving metrics, then print an estimate of their cardinality, and exit.
+    ///
+    /// Metrics are counted by family, and by Consumer Group and Topic: this helps
+    /// predicting the impact on Prometheus, before deploying.
+    PreviewMetrics {
+        /// For how long to collect lag data, before counting the metrics (e.g. '1m').
+        #[arg(
+            long = "warm-up",
+            value_name = "DURATION",
+            default_value = DEFAULT_PREVIEW_WARM_UP,
+            value_parser = duration_clap_value_parser,
+            verbatim_doc_comment
+        )]
+        warm_up: Duration,
+    },
+
+    /// Run for a while without serving metrics, then print the lag of each Consumer Group and Topic, and exit.
+    ///
+    /// For each (Consumer Group, Topic) pair: the partitions with committed offsets,
+    /// the sum of their Offset Lag and the max of their Time Lag.
+    Snapshot {
+        /// For how long to collect lag data, before printing it (e.g. '30s').
+        #[arg(
+            long = "warm-up",
+            value_name = "DURATION",
+            default_value = DEFAULT_SNAPSHOT_WARM_UP,
+            value_parser = duration_clap_value_parser,
+            verbatim_doc_comment
+        )]
+        warm_up: Duration,
+
+        /// Format to print the lag in.
+        ///
+        /// * 'table' = aligned columns, colored when printing to a terminal (unless `NO_COLOR` is set)
+        /// * 'json'  = array of objects, one per (Consumer Group, Topic)
+        /// * 'csv'   = one line per (Consumer Group, Topic), with a header
+        /// * 'consumer-groups' = one line per partition, in the layout of `kafka-consumer-groups.sh --describe`
+        #[arg(
+            long = "output",
+            value_name = "FORMAT",
+            value_enum,
+            default_value_t = LagTableFormat::Table,
+            verbatim_doc_comment
+        )]
+        output: LagTableFormat,
+    },
+
+    /// Run without serving metrics, showing the lag in a continuously refreshed terminal view.
+    ///
+    /// Keys: 's' to sort by the next column, 'r' to reverse the order, '/' to filter
+    /// Consumer Groups and Topics, 'c' to clear the filter, 'q' to quit.
+    Top {
+        /// How often to refresh the view (e.g. '2s').
+        #[arg(
+            long = "refresh-interval",
+            value_name = "DURATION",
+            default_value = DEFAULT_TOP_REFRESH_INTERVAL,
+            value_parser = duration_clap_value_parser,
+            verbatim_doc_comment
+        )]
+        refresh_interval: Duration,
+
+        /// Column to initially sort by: lag is sorted highest first, names alphabetically.
+        #[arg(
+            long = "sort",
+            value_name = "COLUMN",
+            value_enum,
+            default_value_t = TopSort::TimeLag,
+            verbatim_doc_comment
+        )]
+        sort: TopSort,
+    },
+
+    /// Summarize the lag of each Consumer Group over a time window, out of the lag dumped
+    /// to `--dump-dir` (CSV only), then exit.
+    ///
+    /// For each Consumer Group: peak lag, time over the threshold, and availability.
+    /// It doesn't connect to Kafka: `--brokers` is not required.
+    Report {
+        /// Beginning of the window (RFC 3339, e.g. '2024-06-01T00:00:00Z').
+        #[arg(long = "from", value_name = "DATETIME", verbatim_doc_comment)]
+        from: DateTime<Utc>,
+
+        /// End of the window (RFC 3339): defaults to now.
+        #[arg(long = "to", value_name = "DATETIME", verbatim_doc_comment)]
+        to: Option<DateTime<Utc>>,
+
+        /// Time Lag above which a Consumer Group is considered unavailable (e.g. '60s').
+        #[arg(
+            long = "threshold",
+            value_name = "DURATION",
+            default_value = DEFAULT_REPORT_THRESHOLD,
+            value_parser = duration_clap_value_parser,
+            verbatim_doc_comment
+        )]
+        threshold: Duration,
+
+        /// Format of the report.
+        #[arg(
+            long = "format",
+            value_name = "FORMAT",
+            value_enum,
+            default_value_t = ReportFormat::Markdown,
+            verbatim_doc_comment
+        )]
+        format: ReportFormat,
+    },
+}
+
+/// Command Line Interface, defined via the declarative,
+/// `derive` based functionality of the `clap` crate.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+#[command(group(
+    ArgGroup::new("logging_flags")
+        .required(false)
+        .multiple(false)
+        .args(["verbose", "quiet"]),
+))]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Configuration file, for what doesn't fit the command line (e.g. alerting rules).
+    ///
+    /// The file is in TOML format.
+    #[arg(short, long = "config", value_name = "FILE", verbatim_doc_comment)]
+    pub config: Option<PathBuf>,
+
+    /// How to handle issues with the configuration as a whole, beyond each argument on its own.
+    ///
+    /// Issues are: contradictory options, out-of-range intervals, unknown `KOMMITTED_*`
+    /// environment variables, and unknown (or invalid) Kafka client configuration keys.
+    /// Unknown keys in the configuration file are always rejected.
+    ///
+    /// * 'strict'     = refuse to start, logging all the issues found
+    /// * 'permissive' = log each issue as a warning, and start anyway
+    #[arg(
+        long = "config-validation",
+        value_name = "MODE",
+        value_enum,
+        default_value_t = ValidationMode::Permissive,
+        verbatim_doc_comment
+    )]
+    pub config_validation: ValidationMode,
+
+    /// Validate the configuration (see `--config-validation`), including the configuration file, and exit.
+    ///
+    /// Exits with a non-zero code, if any issue is found.
+    #[arg(long = "validate-config", verbatim_doc_comment)]
+    pub validate_config: bool,
+
+    // ------------------------------------------------------------------ Admin Client configuration
+    /// Initial Kafka Brokers to connect to (format: 'HOST:PORT,...').
+    ///
+    /// Equivalent to '--kafka-conf bootstrap.servers:host:port,...'.
+    /// Required, unless generating a `report`.
+    ///
+    /// Use this argument multiple times for alternate entry points to the same cluster
+    /// (e.g. via different load balancers or DNS names): the first reachable is used and,
+    /// once the cluster can't be reached via it (see `--failover-after`), all clients are
+    /// re-created against the next reachable one.
+    #[arg(short, long = "brokers", value_name = "BOOTSTRAP_BROKERS", verbatim_doc_comment)]
+    pub bootstrap_brokers: Vec<String>,
+
+    /// How long the cluster can't be reached via the current `--brokers` entry point (e.g. '90s', '5m'),
+    /// before failing over to the next one.
+    ///
+    /// Only applies if `--brokers` is used multiple times.
+    #[arg(
+        long = "failover-after",
+        value_name = "DURATION",
+        default_value = DEFAULT_FAILOVER_AFTER,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub failover_after: Duration,
+
+    /// Periodically re-create all the Kafka clients (e.g. '6h', '1d'), so that they resolve
+    /// the addresses of the brokers again.
+    ///
+    /// Long-lived clients keep connecting to the addresses they resolved at first:
+    /// where brokers change address (e.g. pods in Kubernetes), this recovers from it
+    /// without manual restarts. The offsets history and committed offsets collected so far
+    /// are handed over to the re-created clients.
+    #[arg(
+        long = "recycle-clients-every",
+        value_name = "INTERVAL",
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub recycle_clients_every: Option<Duration>,
+
+    /// Client identifier used by the internal Kafka (Admin) Client.
+    ///
+    /// Equivalent to '--kafka-conf client.id:my-client-id'.
+    #[arg(long = "client-id", value_name = "CLIENT_ID", default_value = env!("CARGO_PKG_NAME"))]
+    pub client_id: String,
+
+    /// Additional configuration used by the internal Kafka (Admin) Client (format: 'CONF_KEY:CONF_VAL').
+    ///
+    /// To set multiple configurations keys, use this argument multiple times.
+    /// See: https://github.com/edenhill/librdkafka/blob/master/CONFIGURATION.md.
+    #[arg(
+        long = "kafka-conf",
+        value_name = "CONF_KEY:CONF_VAL",
+        value_parser = kv_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub kafka_config: Vec<KVPair>,
+
+    /// Connect to a Confluent Cloud cluster, authenticating with `--api-key` and `--api-secret`.
+    ///
+    /// Expands into the required 'SASL_SSL'/'PLAIN' configuration, and the client configuration
+    /// recommended by Confluent: anything set via `--kafka-conf` takes precedence.
+    /// `--brokers` is validated to be a Confluent Cloud bootstrap server (format: 'HOST:PORT').
+    #[arg(
+        long = "confluent-cloud",
+        requires = "api_key",
+        requires = "api_secret",
+        verbatim_doc_comment
+    )]
+    pub confluent_cloud: bool,
+
+    /// API key to authenticate to Confluent Cloud with.
+    #[arg(
+        long = "api-key",
+        value_name = "KEY",
+        env = "KOMMITTED_API_KEY",
+        requires = "confluent_cloud",
+        verbatim_doc_comment
+    )]
+    pub api_key: Option<String>,
+
+    /// API secret to authenticate to Confluent Cloud with.
+    #[arg(
+        long = "api-secret",
+        value_name = "SECRET",
+        env = "KOMMITTED_API_SECRET",
+        hide_env_values = true,
+        requires = "confluent_cloud",
+        verbatim_doc_comment
+    )]
+    pub api_secret: Option<Secret>,
+
+    /// Connect to a Kafka distribution (or managed service) that is only partially compatible
+    /// with Apache Kafka, or that requires some tuning to connect to.
+    ///
+    /// * 'aiven'    = authenticate via client certificates, and source committed offsets via the Kafka API
+    /// * 'redpanda' = source committed offsets via the Kafka API, and do without unsupported Admin APIs
+    /// * 'strimzi'  = cope with the brokers changing address, as pods are rescheduled
+    ///
+    /// Each implies `--lenient`, also disabling the subsystems not supported by the brokers.
+    /// Anything set via `--kafka-conf` or `--source` takes precedence.
+    #[arg(long = "preset", value_name = "PRESET", value_enum, verbatim_doc_comment)]
+    pub preset: Option<Preset>,
+
+    /// Override identifier of the monitored Kafka Cluster.
+    ///
+    /// If set, it replaces the value `cluster.id` from the Brokers' configuration.
+    /// This can be useful when `cluster.id` is not actually set.
+    #[arg(long = "cluster-id", value_name = "CLUSTER_ID")]
+    pub cluster_id: Option<String>,
+
+    /// Exclude the Consumer Groups matching this regex from monitoring.
+    ///
+    /// To exclude multiple regexes, use this argument multiple times.
+    /// Excluded on top of the groups internal to Kafka and its ecosystem (see `--no-default-group-excludes`).
+    #[arg(long = "group-exclude", value_name = "REGEX", verbatim_doc_comment)]
+    pub group_exclude: Vec<Regex>,
+
+    /// Do not exclude by default the Consumer Groups internal to Kafka and its ecosystem.
+    ///
+    /// Those are: '__'-prefixed and '_confluent'-prefixed groups, 'connect-cluster'
+    /// (Kafka Connect workers) and 'schema-registry'. This service own consumer
+    /// is excluded regardless.
+    #[arg(long = "no-default-group-excludes", verbatim_doc_comment)]
+    pub no_default_group_excludes: bool,
+
+    /// Index of the shard of Consumer Groups to monitor, in [0, `--shard-count`).
+    ///
+    /// Consumer Groups are split across `--shard-count` shards by a hash of their name:
+    /// run one instance per shard index, all with the same `--shard-count`,
+    /// to monitor a very large cluster with a fleet of instances.
+    #[arg(
+        long = "shard-index",
+        value_name = "INDEX",
+        requires = "shard_count",
+        verbatim_doc_comment
+    )]
+    pub shard_index: Option<u32>,
+
+    /// Number of shards the Consumer Groups are split across (see `--shard-index`).
+    #[arg(
+        long = "shard-count",
+        value_name = "COUNT",
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "shard_index",
+        verbatim_doc_comment
+    )]
+    pub shard_count: Option<u32>,
+
+    /// File mapping Consumer Groups to extra labels (e.g. team, service, tier).
+    ///
+    /// The labels are attached to lag metrics and JSON responses, for example
+    /// to route lag alerts to the team owning each Consumer Group.
+    /// The file is in JSON format if its extension is '.json', in TOML format otherwise:
+    /// labels are set for groups by name ('groups'), or by regex ('rules').
+    #[arg(long = "group-labels-file", value_name = "FILE", verbatim_doc_comment)]
+    pub group_labels_file: Option<PathBuf>,
+
+    /// Label the Consumer Groups of Kafka Connect sink connectors with the connector name.
+    ///
+    /// Consumer Groups named 'connect-<CONNECTOR>' (the Kafka Connect default) get the
+    /// 'connector' label, attached to lag metrics and JSON responses like the other labels.
+    #[arg(long = "label-connectors", verbatim_doc_comment)]
+    pub label_connectors: bool,
+
+    /// How long to keep the lag of a Consumer Group that vanished, or whose Members all left (e.g. '1m').
+    ///
+    /// Transient disappearances (e.g. during a deploy, or a rebalance) won't create gaps
+    /// in the lag metrics: meanwhile, the time a Consumer Group has been absent for is exported.
+    /// The lag is dropped straight away, if '0s'.
+    #[arg(
+        long = "group-absence-grace",
+        value_name = "DURATION",
+        default_value = DEFAULT_GROUP_ABSENCE_GRACE,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub group_absence_grace: Duration,
+
+    /// Time Lag of a Consumer Group, for a Topic Partition, that is reported as crossed (e.g. '2m').
+    ///
+    /// Going above it, or back within it, is one of the lag events that internal features
+    /// (e.g. alerting, streaming) react to: see `kmtd_lag_events_total`.
+    #[arg(
+        long = "lag-events-threshold",
+        value_name = "DURATION",
+        default_value = DEFAULT_LAG_EVENTS_THRESHOLD,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub lag_events_threshold: Duration,
+
+    /// For each Topic Partition, how much history of offsets to track in memory.
+    ///
+    /// Offsets data points are collected every 500ms, on average: so, on average,
+    /// 30 minutes of data points is 3600 offsets, assuming partition offsets are
+    /// regularly produced to.
+    ///
+    /// Once this limit is reached, the oldest data points are discarded, realising
+    /// a "moving window" of offsets history.
+    #[arg(
+        long = "history",
+        value_name = "SIZE_PER_PARTITION",
+        default_value = DEFAULT_OFFSETS_HISTORY,
+        verbatim_doc_comment
+    )]
+    pub offsets_history: usize,
+
+    /// For each Topic Partition, how long to keep offsets history for.
+    ///
+    /// Offsets tracked earlier than this, compared to the latest tracked,
+    /// are discarded, even if `--history` is not full yet.
+    #[arg(
+        long = "history-ttl",
+        alias = "offsets-history-ttl",
+        value_name = "DURATION",
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub offsets_history_ttl: Option<Duration>,
+
+    /// Overall memory budget for the offsets history of all Topic Partitions.
+    ///
+    /// The budget is split equally among all Topic Partitions, and `--history`
+    /// becomes an upper bound: once reached, every other offset in the older half
+    /// of the history is discarded (i.e. downsampled), so that history still reaches
+    /// far back in time, with lower resolution.
+    ///
+    /// Supports units (e.g. `64MiB`, `1GB`); defaults to bytes.
+    #[arg(
+        long = "history-memory-budget",
+        value_name = "BYTES",
+        value_parser = bytes_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub offsets_history_memory_budget: Option<usize>,
+
+    /// Min interval between polls of the offset watermarks of a Topic Partition (e.g. '500ms').
+    ///
+    /// By default, the offset watermarks of all Topic Partitions are polled continuously.
+    #[arg(
+        long = "watermarks-min-interval",
+        value_name = "DURATION",
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub watermarks_min_interval: Option<Duration>,
+
+    /// Max interval between polls of the offset watermarks of a Topic Partition (e.g. '30s').
+    ///
+    /// If set, polling adapts to traffic: Topic Partitions whose latest offset didn't move
+    /// since the last poll are polled less and less frequently, doubling the interval up to this;
+    /// once the latest offset moves, they go back to `--watermarks-min-interval`.
+    /// On clusters with many quiet Topic Partitions, this considerably reduces the load on brokers.
+    #[arg(
+        long = "watermarks-max-interval",
+        value_name = "DURATION",
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub watermarks_max_interval: Option<Duration>,
+
+    /// Max amount of Topic Partitions to fetch the offset watermarks of, in a single batch.
+    ///
+    /// Each batch is fetched with a single pair of `ListOffsets` requests per broker,
+    /// instead of a pair per Topic Partition.
+    #[arg(
+        long = "watermarks-batch-size",
+        value_name = "PARTITIONS",
+        default_value = DEFAULT_WATERMARKS_BATCH_SIZE,
+        verbatim_doc_comment
+    )]
+    pub watermarks_batch_size: usize,
+
+    /// Max amount of batches of offset watermarks to fetch concurrently.
+    #[arg(
+        long = "watermarks-concurrency",
+        value_name = "BATCHES",
+        default_value = DEFAULT_WATERMARKS_CONCURRENCY,
+        verbatim_doc_comment
+    )]
+    pub watermarks_concurrency: usize,
+
+    /// File to persist the offsets history to, and to restore it from at startup.
+    ///
+    /// After a restart, Time Lag estimation is accurate right away,
+    /// instead of after enough offsets history is tracked again.
+    #[arg(long = "history-persist", value_name = "FILE", verbatim_doc_comment)]
+    pub offsets_history_persist: Option<PathBuf>,
+
+    /// How often to persist the offsets history to `--history-persist` (e.g. '30s', '5m').
+    #[arg(
+        long = "history-persist-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_OFFSETS_HISTORY_PERSIST_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "offsets_history_persist",
+        verbatim_doc_comment
+    )]
+    pub offsets_history_persist_interval: Duration,
+
+    /// File to persist the committed offsets of Consumer Groups to, and to restore them from at startup.
+    ///
+    /// After a restart, lag of Consumer Groups that commit infrequently is known right away,
+    /// instead of after their next commit.
+    #[arg(long = "lag-persist", value_name = "FILE", verbatim_doc_comment)]
+    pub lag_persist: Option<PathBuf>,
+
+    /// How often to persist the committed offsets of Consumer Groups to `--lag-persist` (e.g. '30s', '5m').
+    #[arg(
+        long = "lag-persist-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_LAG_PERSIST_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "lag_persist",
+        verbatim_doc_comment
+    )]
+    pub lag_persist_interval: Duration,
+
+    /// File to persist the audit log of cluster topology changes (served at `/cluster/changes`) to,
+    /// and to restore it from at startup.
+    ///
+    /// After a restart, topics created/deleted and partitions added before it are still listed.
+    #[arg(long = "cluster-changes-persist", value_name = "FILE", verbatim_doc_comment)]
+    pub cluster_changes_persist: Option<PathBuf>,
+
+    /// How often to persist the audit log of cluster topology changes to `--cluster-changes-persist` (e.g. '30s', '5m').
+    #[arg(
+        long = "cluster-changes-persist-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_CLUSTER_CHANGES_PERSIST_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "cluster_changes_persist",
+        verbatim_doc_comment
+    )]
+    pub cluster_changes_persist_interval: Duration,
+
+    /// Record what is fetched from the cluster (including the `__consumer_offsets` records) to this file.
+    ///
+    /// The recording (format: JSON Lines) can be replayed later via `--replay`,
+    /// to reproduce what was observed without access to the cluster.
+    #[arg(long = "record", value_name = "FILE", conflicts_with = "replay", verbatim_doc_comment)]
+    pub record: Option<PathBuf>,
+
+    /// Replay a recording made via `--record`, instead of connecting to Kafka.
+    ///
+    /// Records are replayed at the pace they were recorded at: `--brokers` is not required.
+    /// Features that can't do without Kafka (e.g. `--ha-group`, `--ground-truth-interval`),
+    /// if enabled, still connect to it.
+    #[arg(long = "replay", value_name = "FILE", verbatim_doc_comment)]
+    pub replay: Option<PathBuf>,
+
+    /// Simulate a Kafka cluster, instead of connecting to one: `--brokers` is not required.
+    ///
+    /// Its Topics are produced to, and its Consumer Groups commit, as time passes:
+    /// some keep up, some fall behind or stall. To try Kommitted out, build dashboards,
+    /// or develop sinks, without access to a Kafka cluster.
+    #[arg(long = "demo", conflicts_with = "replay", verbatim_doc_comment)]
+    pub demo: bool,
+
+    /// How to estimate the production time of offsets, on which Time Lag estimation is based.
+    ///
+    /// * 'nearest' = production time of the closest tracked offset (suits bursty traffic)
+    /// * 'linear'  = linear interpolation between the tracked offsets around it
+    /// * 'rate'    = extrapolation at the average rate of production (suits steady traffic)
+    #[arg(
+        long = "time-lag-strategy",
+        value_name = "STRATEGY",
+        value_enum,
+        default_value_t = TimeLagStrategyKind::Linear,
+        verbatim_doc_comment
+    )]
+    pub time_lag_strategy: TimeLagStrategyKind,
+
+    /// For how long a Topic Partition has to receive no new offsets, to be considered idle (e.g. '10s', '1m').
+    ///
+    /// The Time Lag of Consumer Groups caught up with an idle Topic Partition is zero:
+    /// until then, it's estimated as usual.
+    #[arg(
+        long = "idle-window",
+        value_name = "DURATION",
+        default_value = DEFAULT_IDLE_WINDOW,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub idle_window: Duration,
+
+    /// How full `--history` of Topic Partition offsets has to be (on average) for service to be ready.
+    ///
+    /// This value will be compared with the average "fullness" of each data structure containing
+    /// the offsets of Topic Partitions. Once passed, the service can start serving metrics.
+    ///
+    /// The value must be a percentage in the range `[0.0%, 100.0%]`.
+    #[arg(
+        long = "history-ready-at",
+        value_name = "FULLNESS_PERCENT_PER_PARTITION",
+        default_value = DEFAULT_OFFSETS_HISTORY_READY_AT,
+        value_parser = percent_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub offsets_history_ready_at: f64,
+
+    /// Where to start consuming `__consumer_offsets` from, at startup.
+    ///
+    /// Possible values are `earliest`, `latest`, or a timestamp (RFC 3339, or milliseconds
+    /// since UTC Epoch). Readiness is declared only after `__consumer_offsets` has been
+    /// consumed up to where it was at startup: starting from `earliest` reconstructs the
+    /// last-known commits of all groups, but can take a while on large clusters.
+    #[arg(
+        long = "konsumer-offsets-start",
+        value_name = "START",
+        default_value = DEFAULT_KONSUMER_OFFSETS_START,
+        verbatim_doc_comment
+    )]
+    pub konsumer_offsets_start: KonsumerOffsetsStart,
+
+    /// Consumer Group (`group.id`) of the consumer of `__consumer_offsets`.
+    ///
+    /// Defaults to '__kommitted__consumer_offsets_consumer' (unless 'group.id'
+    /// is set via `--kafka-conf`): whatever its value, it's excluded from monitoring.
+    /// Use it to grant ACLs to, or exclude from quotas, this service's own consumer.
+    #[arg(long = "konsumer-offsets-group-id", value_name = "GROUP_ID", verbatim_doc_comment)]
+    pub konsumer_offsets_group_id: Option<String>,
+
+    /// Client identifier (`client.id`) of the consumer of `__consumer_offsets`.
+    ///
+    /// Defaults to `--client-id`, suffixed with '-consumer-offsets'.
+    #[arg(long = "konsumer-offsets-client-id", value_name = "CLIENT_ID", verbatim_doc_comment)]
+    pub konsumer_offsets_client_id: Option<String>,
+
+    /// Max bytes per fetch request (`fetch.max.bytes`) of the consumer of `__consumer_offsets`.
+    #[arg(long = "konsumer-offsets-fetch-max-bytes", value_name = "BYTES", verbatim_doc_comment)]
+    pub konsumer_offsets_fetch_max_bytes: Option<u32>,
+
+    /// Max bytes per partition, per fetch request (`max.partition.fetch.bytes`)
+    /// of the consumer of `__consumer_offsets`.
+    #[arg(
+        long = "konsumer-offsets-max-partition-fetch-bytes",
+        value_name = "BYTES",
+        verbatim_doc_comment
+    )]
+    pub konsumer_offsets_max_partition_fetch_bytes: Option<u32>,
+
+    /// Isolation level (`isolation.level`) of the consumer of `__consumer_offsets`.
+    ///
+    /// With 'read-uncommitted', offsets committed by transactions that are ongoing,
+    /// or that were aborted, are read too. Defaults to 'read-committed'.
+    #[arg(
+        long = "konsumer-offsets-isolation-level",
+        value_name = "LEVEL",
+        value_enum,
+        verbatim_doc_comment
+    )]
+    pub konsumer_offsets_isolation_level: Option<IsolationLevel>,
+
+    /// Where to source the committed offsets of Consumer Groups from.
+    ///
+    /// * 'konsumer-offsets' = consume the `__consumer_offsets` internal topic
+    /// * 'admin-api'        = periodically fetch them, for each Consumer Group, via the Kafka API
+    ///
+    /// Use 'admin-api' when lacking `READ` ACL on `__consumer_offsets`:
+    /// Offset Lag is unaffected, but commit timestamps are not available,
+    /// so the time each committed offset is first fetched is used in their place.
+    /// Defaults to 'konsumer-offsets', unless `--preset` requires otherwise.
+    #[arg(long = "source", value_name = "SOURCE", value_enum, verbatim_doc_comment)]
+    pub offsets_source: Option<OffsetsSource>,
+
+    /// How often to fetch committed offsets, when `--source` is 'admin-api' (e.g. '30s', '1m').
+    #[arg(
+        long = "admin-api-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_ADMIN_API_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub admin_api_interval: Duration,
+
+    /// Carry on without the subsystems lacking the permissions (ACLs) they need.
+    ///
+    /// A subsystem failing for lack of permissions (e.g. listing consumer groups,
+    /// or reading `__consumer_offsets`) is disabled, with a one-off warning, instead of
+    /// failing over and over. Which subsystems are enabled is exposed by the
+    /// `kmtd_subsystem_enabled` metric, and served at `/ready`.
+    #[arg(long = "lenient", verbatim_doc_comment)]
+    pub lenient: bool,
+
+    /// Respond '503 Service Unavailable' at `/ready` until a Consumer Group matching this regex has lag data.
+    ///
+    /// To require multiple Consumer Groups (e.g. those alerts depend on), use this argument
+    /// multiple times: each regex must match at least one Consumer Group with known lag.
+    #[arg(long = "ready-requires-groups", value_name = "REGEX", verbatim_doc_comment)]
+    pub ready_requires_groups: Vec<Regex>,
+
+    /// Run in active/standby high-availability mode, with the instances sharing this group.
+    ///
+    /// Leadership is coordinated via Kafka: the instances join this consumer group, subscribed
+    /// to `--ha-topic`, and the one assigned its partition is the leader. Only the leader serves
+    /// `/metrics` and pushes to sinks (e.g. remote-write, StatsD): standby instances keep their
+    /// lag data warm, and respond '503 Service Unavailable' at `/metrics` and `/ready`.
+    #[arg(long = "ha-group", value_name = "GROUP", verbatim_doc_comment)]
+    pub ha_group: Option<String>,
+
+    /// Single-partition topic to coordinate leadership on, when `--ha-group` is set.
+    ///
+    /// It's created (compacted) if it doesn't exist: nothing is ever produced to it.
+    #[arg(
+        long = "ha-topic",
+        value_name = "TOPIC",
+        default_value = DEFAULT_HA_TOPIC,
+        verbatim_doc_comment
+    )]
+    pub ha_topic: String,
+
+    /// How often librdkafka emits the statistics of the Kafka clients (e.g. '30s', '1m').
+    ///
+    /// They are exported as `kmtd_rdkafka_*` metrics (e.g. brokers round-trip time,
+    /// bytes transmitted and received, queue sizes). '0s' disables them.
+    #[arg(
+        long = "rdkafka-stats-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_RDKAFKA_STATS_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub rdkafka_stats_interval: Duration,
+
+    /// Random jitter to apply to the intervals of the periodic work, as a percentage of them.
+    ///
+    /// Each wait (e.g. between fetches from the cluster, or lag snapshots) is randomly
+    /// adjusted by up to this percentage, either way: this keeps many instances started
+    /// at the same time (e.g. one per cluster, or HA pairs) from loading the brokers in lockstep.
+    ///
+    /// The value must be a percentage in the range `[0.0%, 100.0%]`.
+    #[arg(
+        long = "interval-jitter",
+        value_name = "PERCENT",
+        default_value = DEFAULT_INTERVAL_JITTER,
+        value_parser = percent_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub interval_jitter: f64,
+
+    /// For each Topic Partition, how many commits of a Consumer Group to evaluate its status over.
+    ///
+    /// The status (OK, WARN, ERR, STALLED or STOPPED) is derived from how the
+    /// committed offsets and the lag evolve over this "moving window" of commits:
+    /// it's exposed by the `kmtd_consumer_group_status` metric and at `/groups`.
+    #[arg(
+        long = "status-window",
+        value_name = "COMMITS_PER_PARTITION",
+        default_value = DEFAULT_STATUS_WINDOW,
+        verbatim_doc_comment
+    )]
+    pub status_window: usize,
+
+    /// Restart the status evaluation of a Consumer Group for a Topic Partition, when its committed offset is reset.
+    ///
+    /// A committed offset moving backwards (e.g. manual reset, 'auto.offset.reset')
+    /// is otherwise evaluated as ERR, until it leaves the `--status-window`.
+    /// Only the status evaluation is restarted: the lag history of the Consumer Group
+    /// (behind its lag trend, forecast and burn rate) keeps the lag from before the reset,
+    /// until it leaves the `--lag-history`.
+    /// Resets are counted by the `kmtd_offset_reset_events_total` metric either way.
+    #[arg(long = "forget-offset-resets", verbatim_doc_comment)]
+    pub forget_offset_resets: bool,
+
+    /// How many of its usual commit intervals a Topic Partition has to receive no commits for, for its consumer to be considered stopped.
+    ///
+    /// The usual commit interval is the mean interval between the commits in the `--status-window`.
+    /// A Consumer Group is reported as stopped by the `kmtd_kafka_consumer_group_stopped` metric,
+    /// once that's the case for all its Topic Partitions, whether it's lagging or not.
+    #[arg(
+        long = "stopped-commit-intervals",
+        value_name = "INTERVALS",
+        default_value = DEFAULT_STOPPED_COMMIT_INTERVALS,
+        value_parser = clap::value_parser!(u32).range(1..),
+        verbatim_doc_comment
+    )]
+    pub stopped_commit_intervals: u32,
+
+    /// How far back the in-memory history of the lag of each Consumer Group goes (e.g. '60m').
+    ///
+    /// The lag of each Consumer Group, aggregated across its Topic Partitions,
+    /// is served as a JSON time series at `/groups/{name}/history`.
+    #[arg(
+        long = "lag-history",
+        value_name = "RETENTION",
+        default_value = DEFAULT_LAG_HISTORY_RETENTION,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub lag_history_retention: Duration,
+
+    /// Interval between points of the in-memory history of the lag of each Consumer Group (e.g. '10s').
+    #[arg(
+        long = "lag-history-resolution",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_LAG_HISTORY_RESOLUTION,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub lag_history_resolution: Duration,
+
+    /// How far back in the lag history to evaluate the lag trend of each Consumer Group over (e.g. '5m').
+    ///
+    /// The rate at which the Offset Lag grows (or shrinks) is exposed by the
+    /// `kmtd_kafka_consumer_group_lag_trend` metric. Capped by `--lag-history`.
+    #[arg(
+        long = "lag-trend-window",
+        value_name = "WINDOW",
+        default_value = DEFAULT_LAG_TREND_WINDOW,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub lag_trend_window: Duration,
+
+    /// Time Lag each Consumer Group is expected to stay within (e.g. '30s').
+    ///
+    /// If set, the rate at which each Consumer Group burns the error budget of this target,
+    /// over the `--lag-trend-window`, is exposed by the `kmtd_kafka_consumer_group_lag_burn_rate`
+    /// metric: '1' means as fast as `--lag-slo-objective` allows, above '1' faster.
+    #[arg(
+        long = "lag-slo-target",
+        value_name = "TIME_LAG",
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub lag_slo_target: Option<Duration>,
+
+    /// Share of the time each Consumer Group is expected to stay within `--lag-slo-target` (e.g. '0.99').
+    #[arg(
+        long = "lag-slo-objective",
+        value_name = "RATIO",
+        default_value = DEFAULT_LAG_SLO_OBJECTIVE,
+        value_parser = ratio_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub lag_slo_objective: f64,
+
+    /// Report the Offset Lag of consumers that never committed an offset for an assigned Topic Partition, as the whole span of offsets available in it.
+    ///
+    /// Otherwise, it's reported as unknown ('-1'). Either way, those consumers are
+    /// exposed by the `kmtd_kafka_consumer_partition_never_committed` metric.
+    #[arg(long = "never-committed-lag-span", verbatim_doc_comment)]
+    pub never_committed_lag_span: bool,
+
+    /// Finest level of detail of the lag metrics served at `/metrics`.
+    ///
+    /// * 'partition' = lag of each consumer group for each topic partition, and offsets of each topic partition
+    /// * 'topic'     = lag of each consumer group for each topic, aggregated across partitions
+    /// * 'group'     = lag of each consumer group, aggregated across all its topic partitions
+    ///
+    /// Coarser levels cut the amount of series by orders of magnitude, on very large clusters.
+    #[arg(
+        long = "metrics-granularity",
+        value_name = "LEVEL",
+        value_enum,
+        default_value_t = MetricsGranularity::Partition,
+        verbatim_doc_comment
+    )]
+    pub metrics_granularity: MetricsGranularity,
+
+    /// Offset Lag above which a Consumer Group is counted as lagging, cluster-wide.
+    ///
+    /// The amount of lagging consumer groups is exposed by the `kmtd_cluster_lagging_groups` metric,
+    /// next to the `kmtd_cluster_total_offset_lag` of all consumer groups.
+    #[arg(
+        long = "lagging-group-threshold",
+        value_name = "OFFSETS",
+        default_value = DEFAULT_LAGGING_GROUP_THRESHOLD,
+        verbatim_doc_comment
+    )]
+    pub lagging_group_threshold: u64,
+
+    /// Percentage of group partitions whose lag must be known, before serving metrics.
+    ///
+    /// Until then, `/metrics` and `/ready` respond '503 Service Unavailable', so that a
+    /// freshly (re)started service doesn't feed misleading zero lag to alerting systems.
+    /// Once reached, it is not checked again. '0.0' disables this.
+    ///
+    /// The value must be a percentage in the range `[0.0%, 100.0%]`.
+    #[arg(
+        long = "min-data-coverage",
+        value_name = "PERCENT",
+        default_value = DEFAULT_MIN_DATA_COVERAGE,
+        value_parser = percent_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub min_data_coverage: f64,
+
+    /// Max time to wait for `--min-data-coverage`, before serving metrics anyway (e.g. '10m').
+    ///
+    /// Partitions consumer groups never committed offsets for have no lag data to ever become known:
+    /// this ensures metrics are eventually served, even if the coverage can't be reached.
+    #[arg(
+        long = "min-data-coverage-timeout",
+        value_name = "PERIOD",
+        default_value = DEFAULT_MIN_DATA_COVERAGE_TIMEOUT,
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub min_data_coverage_timeout: Duration,
+
+    /// How often to ground-truth the Time Lag estimates of the laggiest Topic Partitions (e.g. '1m').
+    ///
+    /// The actual production time of the committed offsets is found via `OffsetsForTimes`
+    /// requests: it narrows down the following estimates, and the estimation error is
+    /// tracked by the `kmtd_time_lag_estimation_error_milliseconds` metric.
+    #[arg(
+        long = "ground-truth-interval",
+        value_name = "INTERVAL",
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub ground_truth_interval: Option<Duration>,
+
+    /// How many of the laggiest Topic Partitions to ground-truth, every `--ground-truth-interval`.
+    #[arg(
+        long = "ground-truth-partitions",
+        value_name = "PARTITIONS",
+        default_value = DEFAULT_GROUND_TRUTH_PARTITIONS,
+        requires = "ground_truth_interval",
+        verbatim_doc_comment
+    )]
+    pub ground_truth_partitions: usize,
+
+    /// How often to sample the size of the latest records of the consumed Topics, to estimate the lag in bytes (e.g. '5m').
+    ///
+    /// The estimate is exported by the `kmtd_kafka_consumer_partition_lag_bytes` metric:
+    /// it's the Offset Lag, times the average size per offset of the sampled records.
+    /// Requires 'READ' access to the consumed Topics.
+    #[arg(
+        long = "lag-bytes-interval",
+        value_name = "INTERVAL",
+        value_parser = duration_clap_value_parser,
+        verbatim_doc_comment
+    )]
+    pub lag_bytes_interval: Option<Duration>,
+
+    /// How many of the latest records of each Topic to sample, every `--lag-bytes-interval`.
+    #[arg(
+        long = "lag-bytes-sample-records",
+        value_name = "RECORDS",
+        default_value = DEFAULT_LAG_BYTES_SAMPLE_RECORDS,
+        requires = "lag_bytes_interval",
+        verbatim_doc_comment
+    )]
+    pub lag_bytes_sample_records: usize,
+
+    /// MirrorMaker 2 offset-syncs topic to export the replication lag from (e.g. 'mm2-offset-syncs.target.internal').
+    ///
+    /// The monitored cluster has to be the source of the replication, where the topic is
+    /// by default ('offset-syncs.topic.location=source'): the lag is exported by the
+    /// `kmtd_mirror_replication_offset_lag` metric, for each replicated Topic Partition.
+    #[arg(long = "mirror-offset-syncs-topic", value_name = "TOPIC", verbatim_doc_comment)]
+    pub mirror_offset_syncs_topic: Option<String>,
+
+    /// Host address to listen on for HTTP requests.
+    ///
+    /// Supports both IPv4 and IPv6 addresses.
+    #[arg(long, default_value = DEFAULT_HTTP_HOST, verbatim_doc_comment)]
+    pub host: IpAddr,
+
+    /// Port to listen on for HTTP requests.
+    #[arg(long, default_value = DEFAULT_HTTP_PORT, verbatim_doc_comment)]
+    pub port: u16,
+
+    /// Accept offsets committed outside of Kafka, via the `POST /external-offsets` HTTP endpoint.
+    ///
+    /// For consumers that store their offsets elsewhere (e.g. Spark or Flink jobs):
+    /// their lag is then tracked alongside the Consumer Groups.
+    /// The body is a JSON array of '{"group", "topic", "partition", "offset", "committed_at_ms"}'.
+    #[arg(long = "external-offsets-endpoint", verbatim_doc_comment)]
+    pub external_offsets_endpoint: bool,
+
+    /// URL of a Prometheus remote-write endpoint to push metrics to.
+    ///
+    /// Useful where scraping the `/metrics` endpoint is not possible.
+    /// Example: 'http://prometheus:9090/api/v1/write'.
+    #[arg(long = "remote-write-url", value_name = "URL", verbatim_doc_comment)]
+    pub remote_write_url: Option<String>,
+
+    /// How often to push metrics to `--remote-write-url` (e.g. '15s', '1m').
+    #[arg(
+        long = "remote-write-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_REMOTE_WRITE_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "remote_write_url",
+        verbatim_doc_comment
+    )]
+    pub remote_write_interval: Duration,
+
+    /// Additional HTTP header to set when pushing to `--remote-write-url` (format: 'HEADER_NAME:HEADER_VAL').
+    ///
+    /// To set multiple headers, use this argument multiple times.
+    /// Example: 'Authorization:Bearer TOKEN'.
+    #[arg(
+        long = "remote-write-header",
+        value_name = "HEADER_NAME:HEADER_VAL",
+        value_parser = header_clap_value_parser,
+        requires = "remote_write_url",
+        verbatim_doc_comment
+    )]
+    pub remote_write_headers: Vec<KVPair>,
+
+    /// Address of a StatsD server to emit lag metrics to, as gauges (format: 'HOST:PORT').
+    ///
+    /// For example, the Datadog Agent.
+    /// Example: 'localhost:8125'.
+    #[arg(long = "statsd-address", value_name = "HOST:PORT", verbatim_doc_comment)]
+    pub statsd_address: Option<String>,
+
+    /// Dialect of the StatsD protocol to emit to `--statsd-address`.
+    ///
+    /// * 'dogstatsd' = labels are emitted as tags
+    /// * 'statsd'    = label values are appended to the metric name
+    #[arg(
+        long = "statsd-flavor",
+        value_name = "FLAVOR",
+        value_enum,
+        default_value_t = StatsdFlavor::Dogstatsd,
+        requires = "statsd_address",
+        verbatim_doc_comment
+    )]
+    pub statsd_flavor: StatsdFlavor,
+
+    /// How often to emit lag metrics to `--statsd-address` (e.g. '10s', '1m').
+    #[arg(
+        long = "statsd-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_STATSD_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "statsd_address",
+        verbatim_doc_comment
+    )]
+    pub statsd_interval: Duration,
+
+    /// Additional tag to set on each metric emitted to `--statsd-address` (format: 'TAG_KEY:TAG_VAL').
+    ///
+    /// Only supported by the 'dogstatsd' flavor.
+    /// To set multiple tags, use this argument multiple times.
+    #[arg(
+        long = "statsd-tag",
+        value_name = "TAG_KEY:TAG_VAL",
+        value_parser = kv_clap_value_parser,
+        requires = "statsd_address",
+        verbatim_doc_comment
+    )]
+    pub statsd_tags: Vec<KVPair>,
+
+    /// Base URL of an InfluxDB server to write lag measurements to, via its v2 HTTP API.
+    ///
+    /// Example: 'http://influxdb:8086'.
+    #[arg(
+        long = "influx-url",
+        value_name = "URL",
+        requires = "influx_org",
+        requires = "influx_bucket",
+        verbatim_doc_comment
+    )]
+    pub influx_url: Option<String>,
+
+    /// InfluxDB organization to write lag measurements to.
+    #[arg(long = "influx-org", value_name = "ORG", requires = "influx_url", verbatim_doc_comment)]
+    pub influx_org: Option<String>,
+
+    /// InfluxDB bucket to write lag measurements to.
+    #[arg(
+        long = "influx-bucket",
+        value_name = "BUCKET",
+        requires = "influx_url",
+        verbatim_doc_comment
+    )]
+    pub influx_bucket: Option<String>,
+
+    /// InfluxDB API token to authenticate with.
+    #[arg(
+        long = "influx-token",
+        value_name = "TOKEN",
+        env = "KOMMITTED_INFLUX_TOKEN",
+        hide_env_values = true,
+        requires = "influx_url",
+        verbatim_doc_comment
+    )]
+    pub influx_token: Option<Secret>,
+
+    /// How often to write lag measurements to `--influx-url` (e.g. '10s', '1m').
+    #[arg(
+        long = "influx-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_INFLUX_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "influx_url",
+        verbatim_doc_comment
+    )]
+    pub influx_interval: Duration,
+
+    /// Address of a Graphite plaintext receiver to write lag measurements to (format: 'HOST:PORT').
+    ///
+    /// Labels are written as tags: requires Graphite >= 1.1.
+    /// Example: 'graphite:2003'.
+    #[arg(long = "graphite-address", value_name = "HOST:PORT", verbatim_doc_comment)]
+    pub graphite_address: Option<String>,
+
+    /// Prefix prepended to the name of each metric written to `--graphite-address`.
+    #[arg(
+        long = "graphite-prefix",
+        value_name = "PREFIX",
+        requires = "graphite_address",
+        verbatim_doc_comment
+    )]
+    pub graphite_prefix: Option<String>,
+
+    /// How often to write lag measurements to `--graphite-address` (e.g. '10s', '1m').
+    #[arg(
+        long = "graphite-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_GRAPHITE_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "graphite_address",
+        verbatim_doc_comment
+    )]
+    pub graphite_interval: Duration,
+
+    /// Kafka topic to produce lag records to, one per Consumer Group and Topic Partition, as JSON.
+    ///
+    /// By default, records are produced to the monitored Kafka cluster:
+    /// use `--lag-topic-brokers` to produce to a separate one.
+    #[arg(long = "lag-topic", value_name = "TOPIC", verbatim_doc_comment)]
+    pub lag_topic: Option<String>,
+
+    /// Key lag records produced to `--lag-topic` by (group, topic, partition).
+    ///
+    /// Useful when `--lag-topic` is compacted, to retain only the latest lag record.
+    #[arg(long = "lag-topic-keyed", requires = "lag_topic", verbatim_doc_comment)]
+    pub lag_topic_keyed: bool,
+
+    /// How often to produce lag records to `--lag-topic` (e.g. '10s', '1m').
+    #[arg(
+        long = "lag-topic-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_LAG_TOPIC_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "lag_topic",
+        verbatim_doc_comment
+    )]
+    pub lag_topic_interval: Duration,
+
+    /// Kafka Brokers of a separate cluster to produce `--lag-topic` to (format: 'HOST:PORT,...').
+    #[arg(
+        long = "lag-topic-brokers",
+        value_name = "BOOTSTRAP_BROKERS",
+        requires = "lag_topic",
+        verbatim_doc_comment
+    )]
+    pub lag_topic_bootstrap_brokers: Option<String>,
+
+    /// Additional configuration used by the Kafka Producer of `--lag-topic-brokers` (format: 'CONF_KEY:CONF_VAL').
+    ///
+    /// To set multiple configurations keys, use this argument multiple times.
+    #[arg(
+        long = "lag-topic-kafka-conf",
+        value_name = "CONF_KEY:CONF_VAL",
+        value_parser = kv_clap_value_parser,
+        requires = "lag_topic_bootstrap_brokers",
+        verbatim_doc_comment
+    )]
+    pub lag_topic_kafka_config: Vec<KVPair>,
+
+    /// Directory to dump lag snapshots into, for offline analysis (e.g. with pandas or DuckDB).
+    ///
+    /// Files are named after the time they refer to, so they rotate naturally:
+    /// see `--dump-format`.
+    #[arg(long = "dump-dir", value_name = "DIR", verbatim_doc_comment)]
+    pub dump_dir: Option<PathBuf>,
+
+    /// Format of the files written to `--dump-dir`.
+    ///
+    /// * 'csv'     = one file per day (UTC), snapshots appended to it
+    /// * 'parquet' = one file per snapshot (requires the 'parquet' feature):
+    ///   bound how many are kept with `--dump-retention`
+    #[arg(
+        long = "dump-format",
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t = DumpFormat::Csv,
+        requires = "dump_dir",
+        verbatim_doc_comment
+    )]
+    pub dump_format: DumpFormat,
 
-/// Command Line Interface, defined via the declarative,
-/// `derive` based functionality of the `clap` crate.
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-#[command(group(
-    ArgGroup::new("logging_flags")
-        .required(false)
-        .multiple(false)
-        .args(["verbose", "quiet"]),
-))]
-pub struct Cli {
-    // ------------------------------------------------------------------ Admin Client configuration
-    /// Initial Kafka Brokers to connect to (format: 'HOST:PORT,...').
+    /// How often to dump lag snapshots to `--dump-dir` (e.g. '60s', '5m').
+    #[arg(
+        long = "dump-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_DUMP_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "dump_dir",
+        verbatim_doc_comment
+    )]
+    pub dump_interval: Duration,
+
+    /// How long to keep the files dumped to `--dump-dir` for (e.g. '7d'): older ones are deleted.
     ///
-    /// Equivalent to '--kafka-conf bootstrap.servers:host:port,...'.
-    #[arg(short, long = "brokers", value_name = "BOOTSTRAP_BROKERS")]
-    pub bootstrap_brokers: String,
+    /// If not set, they are kept forever.
+    #[arg(
+        long = "dump-retention",
+        value_name = "RETENTION",
+        value_parser = duration_clap_value_parser,
+        requires = "dump_dir",
+        verbatim_doc_comment
+    )]
+    pub dump_retention: Option<Duration>,
 
-    /// Client identifier used by the internal Kafka (Admin) Client.
+    /// Log what changed in the lag data at every snapshot, for debugging lag anomalies.
     ///
-    /// Equivalent to '--kafka-conf client.id:my-client-id'.
-    #[arg(long = "client-id", value_name = "CLIENT_ID", default_value = env!("CARGO_PKG_NAME"))]
-    pub client_id: String,
+    /// Logged are the consumer groups added and removed, and the topic partitions
+    /// whose offset lag changed by more than the given amount of offsets.
+    /// Nothing is logged when nothing changed.
+    #[arg(long = "log-snapshot-diff", value_name = "OFFSETS", verbatim_doc_comment)]
+    pub log_snapshot_diff: Option<u64>,
 
-    /// Additional configuration used by the internal Kafka (Admin) Client (format: 'CONF_KEY:CONF_VAL').
+    /// Endpoint of an OpenTelemetry Protocol (OTLP) receiver to export metrics to.
     ///
-    /// To set multiple configurations keys, use this argument multiple times.
-    /// See: https://github.com/edenhill/librdkafka/blob/master/CONFIGURATION.md.
+    /// For example, an OpenTelemetry Collector.
+    /// Example: 'http://otel-collector:4317'.
+    #[cfg(feature = "otlp")]
+    #[arg(long = "otlp-endpoint", value_name = "URL", verbatim_doc_comment)]
+    pub otlp_endpoint: Option<String>,
+
+    /// Transport protocol used to export metrics to `--otlp-endpoint`.
+    ///
+    /// When using 'http-protobuf', '/v1/metrics' is appended to the endpoint.
+    #[cfg(feature = "otlp")]
     #[arg(
-        long = "kafka-conf",
-        value_name = "CONF_KEY:CONF_VAL",
-        value_parser = kv_clap_value_parser,
+        long = "otlp-protocol",
+        value_name = "PROTOCOL",
+        value_enum,
+        default_value_t = OtlpProtocol::Grpc,
+        requires = "otlp_endpoint",
         verbatim_doc_comment
     )]
-    pub kafka_config: Vec<KVPair>,
+    pub otlp_protocol: OtlpProtocol,
 
-    /// Override identifier of the monitored Kafka Cluster.
+    /// How often to export metrics to `--otlp-endpoint` (e.g. '15s', '1m').
+    #[cfg(feature = "otlp")]
+    #[arg(
+        long = "otlp-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_OTLP_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "otlp_endpoint",
+        verbatim_doc_comment
+    )]
+    pub otlp_interval: Duration,
+
+    /// Additional header to set when exporting to `--otlp-endpoint` (format: 'HEADER_NAME:HEADER_VAL').
     ///
-    /// If set, it replaces the value `cluster.id` from the Brokers' configuration.
-    /// This can be useful when `cluster.id` is not actually set.
-    #[arg(long = "cluster-id", value_name = "CLUSTER_ID")]
-    pub cluster_id: Option<String>,
+    /// To set multiple headers, use this argument multiple times.
+    #[cfg(feature = "otlp")]
+    #[arg(
+        long = "otlp-header",
+        value_name = "HEADER_NAME:HEADER_VAL",
+        value_parser = header_clap_value_parser,
+        requires = "otlp_endpoint",
+        verbatim_doc_comment
+    )]
+    pub otlp_headers: Vec<KVPair>,
 
-    /// For each Topic Partition, how much history of offsets to track in memory.
+    /// Publish lag metrics to AWS CloudWatch, aggregated by Consumer Group and Topic.
     ///
-    /// Offsets data points are collected every 500ms, on average: so, on average,
-    /// 30 minutes of data points is 3600 offsets, assuming partition offsets are
-    /// regularly produced to.
+    /// AWS credentials and region are resolved via the standard AWS configuration chain
+    /// (environment variables, `~/.aws/config`, instance metadata, ...).
+    #[cfg(feature = "cloudwatch")]
+    #[arg(long = "cloudwatch", verbatim_doc_comment)]
+    pub cloudwatch: bool,
+
+    /// AWS CloudWatch namespace to publish lag metrics under.
+    #[cfg(feature = "cloudwatch")]
+    #[arg(
+        long = "cloudwatch-namespace",
+        value_name = "NAMESPACE",
+        default_value = DEFAULT_CLOUDWATCH_NAMESPACE,
+        requires = "cloudwatch",
+        verbatim_doc_comment
+    )]
+    pub cloudwatch_namespace: String,
+
+    /// AWS region to publish lag metrics to, overriding the one from the AWS configuration.
+    #[cfg(feature = "cloudwatch")]
+    #[arg(
+        long = "cloudwatch-region",
+        value_name = "REGION",
+        requires = "cloudwatch",
+        verbatim_doc_comment
+    )]
+    pub cloudwatch_region: Option<String>,
+
+    /// How often to publish lag metrics to AWS CloudWatch (e.g. '60s', '5m').
+    #[cfg(feature = "cloudwatch")]
+    #[arg(
+        long = "cloudwatch-interval",
+        value_name = "INTERVAL",
+        default_value = DEFAULT_CLOUDWATCH_INTERVAL,
+        value_parser = duration_clap_value_parser,
+        requires = "cloudwatch",
+        verbatim_doc_comment
+    )]
+    pub cloudwatch_interval: Duration,
+
+    /// Memory budget (MB) for the lag data: once over it, detail is shed instead of running out of memory.
     ///
-    /// Once this limit is reached, the oldest data points are discarded, realising
-    /// a "moving window" of offsets history.
+    /// The memory taken by the registers and the rendering of the metrics is estimated every 30s.
+    /// When over budget, the offsets history of the quietest topics is shed first, then the
+    /// metrics granularity is coarsened (see `--metrics-granularity`): what was shed is logged,
+    /// and counted by the `kmtd_memory_shed_total` metric.
     #[arg(
-        long = "history",
-        value_name = "SIZE_PER_PARTITION",
-        default_value = DEFAULT_OFFSETS_HISTORY,
+        long = "max-memory-mb",
+        value_name = "MB",
+        value_parser = clap::value_parser!(u32).range(1..),
         verbatim_doc_comment
     )]
-    pub offsets_history: usize,
+    pub max_memory_mb: Option<u32>,
 
-    /// How full `--history` of Topic Partition offsets has to be (on average) for service to be ready.
+    /// Threads of the async runtime, running the service (default: one per CPU core).
     ///
-    /// This value will be compared with the average "fullness" of each data structure containing
-    /// the offsets of Topic Partitions. Once passed, the service can start serving metrics.
+    /// Lower it to right-size small (e.g. sidecar) containers, where the CPU count
+    /// of the host is not what the container is allotted.
+    #[arg(
+        long = "worker-threads",
+        value_name = "THREADS",
+        value_parser = clap::value_parser!(u32).range(1..),
+        verbatim_doc_comment
+    )]
+    pub worker_threads: Option<u32>,
+
+    /// Max threads of the async runtime, for the blocking operations (default: 512).
     ///
-    /// The value must be a percentage in the range `[0.0%, 100.0%]`.
+    /// Those are mostly requests to Kafka: on very large clusters, raise it to
+    /// keep more of them in flight at once.
     #[arg(
-        long = "history-ready-at",
-        value_name = "FULLNESS_PERCENT_PER_PARTITION",
-        default_value = DEFAULT_OFFSETS_HISTORY_READY_AT,
-        value_parser = percent_clap_value_parser,
+        long = "blocking-threads",
+        value_name = "THREADS",
+        value_parser = clap::value_parser!(u32).range(1..),
         verbatim_doc_comment
     )]
-    pub offsets_history_ready_at: f64,
+    pub blocking_threads: Option<u32>,
 
-    /// Host address to listen on for HTTP requests.
+    /// Write logs to this file, instead of `stderr`.
     ///
-    /// Supports both IPv4 and IPv6 addresses.
-    #[arg(long, default_value = DEFAULT_HTTP_HOST, verbatim_doc_comment)]
-    pub host: IpAddr,
+    /// For deployments where nothing captures the output of the service (e.g. bare-metal).
+    /// The file is rotated as per `--log-file-rotation` and `--log-file-max-size`:
+    /// rotated files are next to it, suffixed by '.1', '.2' and so on (most recent first).
+    #[arg(long = "log-file", value_name = "PATH", conflicts_with = "syslog", verbatim_doc_comment)]
+    pub log_file: Option<PathBuf>,
 
-    /// Port to listen on for HTTP requests.
-    #[arg(long, default_value = DEFAULT_HTTP_PORT, verbatim_doc_comment)]
-    pub port: u16,
+    /// How often to rotate `--log-file`, regardless of its size.
+    ///
+    /// * 'never'  = only when it reaches `--log-file-max-size`
+    /// * 'hourly' = at the start of every hour (UTC)
+    /// * 'daily'  = at the start of every day (UTC)
+    #[arg(
+        long = "log-file-rotation",
+        value_name = "ROTATION",
+        value_enum,
+        default_value_t = LogRotation::Daily,
+        requires = "log_file",
+        verbatim_doc_comment
+    )]
+    pub log_file_rotation: LogRotation,
+
+    /// Size to rotate `--log-file` at, before it grows any bigger.
+    ///
+    /// Supports units (e.g. `64MiB`, `1GB`); defaults to bytes.
+    #[arg(
+        long = "log-file-max-size",
+        value_name = "BYTES",
+        default_value = DEFAULT_LOG_FILE_MAX_SIZE,
+        value_parser = bytes_clap_value_parser,
+        requires = "log_file",
+        verbatim_doc_comment
+    )]
+    pub log_file_max_size: usize,
+
+    /// How many rotated `--log-file` files to keep: the oldest are removed.
+    #[arg(
+        long = "log-file-keep",
+        value_name = "FILES",
+        default_value = DEFAULT_LOG_FILE_KEEP,
+        requires = "log_file",
+        verbatim_doc_comment
+    )]
+    pub log_file_keep: usize,
+
+    /// Send logs to the local syslog daemon (or journald), instead of `stderr`.
+    ///
+    /// Messages are sent to '/dev/log', with the 'daemon' facility.
+    #[arg(long = "syslog", verbatim_doc_comment)]
+    pub syslog: bool,
 
     /// Verbose logging.
     ///
@@ -118,26 +1450,609 @@ impl Cli {
         self.verbose as i8 - self.quiet as i8
     }
 
+    pub fn log_target(&self) -> LogTarget {
+        match &self.log_file {
+            Some(path) => LogTarget::File(LogFileConfig {
+                path: path.clone(),
+                rotation: self.log_file_rotation,
+                max_size: Some(self.log_file_max_size as u64),
+                keep: self.log_file_keep,
+            }),
+            None if self.syslog => LogTarget::Syslog,
+            None => LogTarget::Stderr,
+        }
+    }
+
     pub fn listen_on(&self) -> SocketAddr {
         SocketAddr::from((self.host, self.port))
     }
 
+    pub fn build_runtime(&self) -> std::io::Result<Runtime> {
+        let mut builder = runtime::Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(wt) = self.worker_threads {
+            builder.worker_threads(wt as usize);
+        }
+        if let Some(bt) = self.blocking_threads {
+            builder.max_blocking_threads(bt as usize);
+        }
+
+        builder.build()
+    }
+
     pub fn build_client_config(&self) -> ClientConfig {
         let mut config = ClientConfig::new();
         config
-            .set("bootstrap.servers", self.bootstrap_brokers.clone())
+            .set("bootstrap.servers", self.bootstrap_brokers.first().cloned().unwrap_or_default())
             .set("client.id", self.client_id.clone());
+        if let Some(preset) = self.preset {
+            for (k, v) in preset.client_config() {
+                config.set(k, v);
+            }
+        }
+        if let (true, Some(key), Some(secret)) =
+            (self.confluent_cloud, &self.api_key, &self.api_secret)
+        {
+            for (k, v) in presets::confluent_cloud(key, secret.expose()) {
+                config.set(k, v);
+            }
+        }
         for cfg in &self.kafka_config {
             config.set(cfg.0.clone(), cfg.1.clone());
         }
 
-        trace!("Created:\n{:#?}", config);
+        trace!("Created:\n{:#?}", redacted_client_config(&config));
         config
     }
+
+    /// Where to source the committed offsets of Consumer Groups from: `--source`, if set,
+    /// otherwise as required by `--preset`.
+    pub fn offsets_source(&self) -> OffsetsSource {
+        self.offsets_source
+            .or_else(|| self.preset.and_then(|p| p.offsets_source()))
+            .unwrap_or_default()
+    }
+
+    /// The `--brokers` entry points to the cluster, to fail over between.
+    pub fn bootstrap_endpoints(&self) -> BootstrapEndpoints {
+        BootstrapEndpoints::new(self.bootstrap_brokers.clone())
+    }
+
+    pub fn offsets_history_persistence(&self) -> Option<PersistenceConfig> {
+        self.offsets_history_persist.as_ref().map(|path| PersistenceConfig {
+            path: path.clone(),
+            interval: self.offsets_history_persist_interval,
+        })
+    }
+
+    pub fn lag_persistence(&self) -> Option<PersistenceConfig> {
+        self.lag_persist.as_ref().map(|path| PersistenceConfig {
+            path: path.clone(),
+            interval: self.lag_persist_interval,
+        })
+    }
+
+    pub fn cluster_changes_persistence(&self) -> Option<PersistenceConfig> {
+        self.cluster_changes_persist.as_ref().map(|path| PersistenceConfig {
+            path: path.clone(),
+            interval: self.cluster_changes_persist_interval,
+        })
+    }
+
+    /// Whether the service runs without connecting to Kafka (i.e. `--replay` or `--demo`).
+    pub fn is_offline(&self) -> bool {
+        self.replay.is_some() || self.demo
+    }
+
+    pub fn recording_config(&self) -> Option<RecordingConfig> {
+        match (&self.record, &self.replay) {
+            (Some(path), _) => Some(RecordingConfig::Record(path.clone())),
+            (_, Some(path)) => Some(RecordingConfig::Replay(path.clone())),
+            (None, None) => None,
+        }
+    }
+
+    pub fn ground_truth_config(&self) -> Option<GroundTruthConfig> {
+        self.ground_truth_interval.map(|interval| GroundTruthConfig {
+            partitions: self.ground_truth_partitions,
+            interval,
+        })
+    }
+
+    pub fn lag_bytes_config(&self) -> Option<RecordSizesConfig> {
+        self.lag_bytes_interval.map(|interval| RecordSizesConfig {
+            records: self.lag_bytes_sample_records,
+            interval,
+        })
+    }
+
+    pub fn mirror_config(&self) -> Option<MirrorConfig> {
+        self.mirror_offset_syncs_topic.as_ref().map(|topic| MirrorConfig {
+            offset_syncs_topic: topic.clone(),
+        })
+    }
+
+    pub fn remote_write_config(&self) -> Option<RemoteWriteConfig> {
+        self.remote_write_url.as_ref().map(|url| RemoteWriteConfig {
+            url: url.clone(),
+            interval: self.remote_write_interval,
+            headers: kv_pairs(&self.remote_write_headers),
+        })
+    }
+
+    pub fn statsd_config(&self) -> Option<StatsdConfig> {
+        self.statsd_address.as_ref().map(|address| StatsdConfig {
+            address: address.clone(),
+            flavor: self.statsd_flavor,
+            interval: self.statsd_interval,
+            tags: kv_pairs(&self.statsd_tags),
+        })
+    }
+
+    pub fn influx_config(&self) -> Option<InfluxConfig> {
+        self.influx_url.as_ref().map(|url| InfluxConfig {
+            url: url.clone(),
+            org: self.influx_org.clone().unwrap_or_default(),
+            bucket: self.influx_bucket.clone().unwrap_or_default(),
+            token: self.influx_token.as_ref().map(|t| t.expose().to_string()),
+            interval: self.influx_interval,
+        })
+    }
+
+    pub fn graphite_config(&self) -> Option<GraphiteConfig> {
+        self.graphite_address.as_ref().map(|address| GraphiteConfig {
+            address: address.clone(),
+            prefix: self.graphite_prefix.clone(),
+            interval: self.graphite_interval,
+        })
+    }
+
+    pub fn kafka_topic_config(&self) -> Option<KafkaTopicConfig> {
+        self.lag_topic.as_ref().map(|topic| KafkaTopicConfig {
+            topic: topic.clone(),
+            keyed: self.lag_topic_keyed,
+            client_config: self.lag_topic_bootstrap_brokers.as_ref().map(|brokers| {
+                let mut config = ClientConfig::new();
+                config
+                    .set("bootstrap.servers", brokers.clone())
+                    .set("client.id", self.client_id.clone());
+                for cfg in &self.lag_topic_kafka_config {
+                    config.set(cfg.0.clone(), cfg.1.clone());
+                }
+                config
+            }),
+            interval: self.lag_topic_interval,
+        })
+    }
+
+    /// Validate the command line input, beyond what each argument validates on its own.
+    pub fn validate(&self) -> Result<(), clap::Error> {
+        let is_report = matches!(self.command, Some(Command::Report { .. }));
+        if self.bootstrap_brokers.is_empty() && !is_report && !self.is_offline() {
+            return Err(Cli::command().error(
+                ErrorKind::MissingRequiredArgument,
+                "the following required arguments were not provided:\n  --brokers <BOOTSTRAP_BROKERS>",
+            ));
+        }
+        if self.confluent_cloud {
+            for brokers in self.bootstrap_brokers.iter() {
+                if let Err(e) = presets::validate_confluent_cloud_brokers(brokers) {
+                    return Err(Cli::command().error(
+                        ErrorKind::ValueValidation,
+                        format!("'--brokers' is not a Confluent Cloud bootstrap server: {e}"),
+                    ));
+                }
+            }
+        }
+        if is_report && self.dump_dir.is_none() {
+            return Err(Cli::command().error(
+                ErrorKind::MissingRequiredArgument,
+                "'report' reads the lag dumped to '--dump-dir': it has to be provided",
+            ));
+        }
+
+        if let Some(ttl) = self.offsets_history_ttl {
+            if chrono::Duration::from_std(ttl).is_err() {
+                return Err(Cli::command().error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "'--offsets-history-ttl' {} is out of range",
+                        humantime::format_duration(ttl)
+                    ),
+                ));
+            }
+        }
+
+        if let (Some(index), Some(count)) = (self.shard_index, self.shard_count) {
+            if GroupShard::new(index, count).is_none() {
+                return Err(Cli::command().error(
+                    ErrorKind::ValueValidation,
+                    format!("'--shard-index' {index} should be lower than '--shard-count' {count}"),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find the issues with the configuration as a whole (see [`crate::validation`]).
+    ///
+    /// The configuration file is not included: it's validated when loaded (see [`Cli::load_config`]).
+    pub fn config_issues(&self) -> Vec<ConfigIssue> {
+        let fmt = humantime::format_duration;
+
+        let known_env = Cli::command()
+            .get_arguments()
+            .filter_map(|a| a.get_env())
+            .map(|e| e.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let mut issues = validation::unknown_env_vars(
+            &known_env,
+            std::env::vars_os().map(|(k, _)| k.to_string_lossy().into_owned()),
+        );
+
+        issues.extend(validation::invalid_kafka_config(
+            "--kafka-conf",
+            self.kafka_config.iter().map(|KVPair(k, v)| (k, v)),
+        ));
+        issues.extend(validation::invalid_kafka_config(
+            "--lag-topic-kafka-conf",
+            self.lag_topic_kafka_config.iter().map(|KVPair(k, v)| (k, v)),
+        ));
+
+        // Sinks pushing the lag of each Topic Partition, regardless of `--metrics-granularity`
+        let mut pushing_sinks = vec![
+            (
+                "--remote-write-url",
+                self.remote_write_url.is_some(),
+                ("--remote-write-interval", self.remote_write_interval),
+            ),
+            (
+                "--statsd-address",
+                self.statsd_address.is_some(),
+                ("--statsd-interval", self.statsd_interval),
+            ),
+            (
+                "--influx-url",
+                self.influx_url.is_some(),
+                ("--influx-interval", self.influx_interval),
+            ),
+            (
+                "--graphite-address",
+                self.graphite_address.is_some(),
+                ("--graphite-interval", self.graphite_interval),
+            ),
+            (
+                "--lag-topic",
+                self.lag_topic.is_some(),
+                ("--lag-topic-interval", self.lag_topic_interval),
+            ),
+        ];
+        #[cfg(feature = "otlp")]
+        pushing_sinks.push((
+            "--otlp-endpoint",
+            self.otlp_endpoint.is_some(),
+            ("--otlp-interval", self.otlp_interval),
+        ));
+        #[cfg(feature = "cloudwatch")]
+        pushing_sinks.push((
+            "--cloudwatch",
+            self.cloudwatch,
+            ("--cloudwatch-interval", self.cloudwatch_interval),
+        ));
+        pushing_sinks.retain(|(_, enabled, _)| *enabled);
+
+        if let Some(granularity) = self
+            .metrics_granularity
+            .to_possible_value()
+            .filter(|_| self.metrics_granularity != MetricsGranularity::Partition)
+        {
+            for (sink, _, _) in pushing_sinks.iter() {
+                issues.push(ConfigIssue::new(
+                    "--metrics-granularity",
+                    format!(
+                        "'{}' only applies to `/metrics`: '{sink}' still pushes the lag of each topic partition",
+                        granularity.get_name()
+                    ),
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.watermarks_min_interval, self.watermarks_max_interval)
+        {
+            if min > max {
+                issues.push(ConfigIssue::new(
+                    "--watermarks-max-interval",
+                    format!(
+                        "{} is shorter than '--watermarks-min-interval' {}",
+                        fmt(max),
+                        fmt(min)
+                    ),
+                ));
+            }
+        }
+        for (option, interval) in [
+            ("--lag-history-resolution", self.lag_history_resolution),
+            ("--lag-trend-window", self.lag_trend_window),
+        ] {
+            issues.extend(validation::out_of_range(
+                option,
+                interval,
+                MIN_INTERVAL,
+                Some(self.lag_history_retention),
+            ));
+        }
+
+        // Intervals of what's enabled, that would spin (or panic) if too short
+        let mut intervals = pushing_sinks.iter().map(|(_, _, i)| *i).collect::<Vec<_>>();
+        for (option, enabled, interval) in [
+            ("--dump-interval", self.dump_dir.is_some(), self.dump_interval),
+            (
+                "--history-persist-interval",
+                self.offsets_history_persist.is_some(),
+                self.offsets_history_persist_interval,
+            ),
+            ("--lag-persist-interval", self.lag_persist.is_some(), self.lag_persist_interval),
+            (
+                "--cluster-changes-persist-interval",
+                self.cluster_changes_persist.is_some(),
+                self.cluster_changes_persist_interval,
+            ),
+            (
+                "--admin-api-interval",
+                self.offsets_source() == OffsetsSource::AdminApi,
+                self.admin_api_interval,
+            ),
+        ] {
+            if enabled {
+                intervals.push((option, interval));
+            }
+        }
+        for (option, interval) in intervals {
+            issues.extend(validation::out_of_range(option, interval, MIN_INTERVAL, None));
+        }
+
+        // Cluster status is fetched periodically: failing over any sooner would never stop
+        if self.bootstrap_brokers.len() > 1 {
+            issues.extend(validation::out_of_range(
+                "--failover-after",
+                self.failover_after,
+                CLUSTER_STATUS_FETCH_INTERVAL,
+                None,
+            ));
+        }
+
+        issues
+    }
+
+    pub fn group_shard(&self) -> Option<GroupShard> {
+        self.shard_index.zip(self.shard_count).and_then(|(i, c)| GroupShard::new(i, c))
+    }
+
+    pub fn high_availability_config(&self) -> Option<HighAvailabilityConfig> {
+        self.ha_group.as_ref().map(|group| HighAvailabilityConfig {
+            group: group.clone(),
+            topic: self.ha_topic.clone(),
+        })
+    }
+
+    pub fn dump_config(&self) -> Option<DumpConfig> {
+        self.dump_dir.as_ref().map(|dir| DumpConfig {
+            dir: dir.clone(),
+            format: self.dump_format,
+            interval: self.dump_interval,
+            retention: self.dump_retention,
+        })
+    }
+
+    #[cfg(feature = "otlp")]
+    pub fn otlp_config(&self) -> Option<OtlpConfig> {
+        self.otlp_endpoint.as_ref().map(|endpoint| OtlpConfig {
+            endpoint: endpoint.clone(),
+            protocol: self.otlp_protocol,
+            interval: self.otlp_interval,
+            headers: kv_pairs(&self.otlp_headers),
+        })
+    }
+
+    #[cfg(feature = "cloudwatch")]
+    pub fn cloudwatch_config(&self) -> Option<CloudWatchConfig> {
+        self.cloudwatch.then(|| CloudWatchConfig {
+            namespace: self.cloudwatch_namespace.clone(),
+            region: self.cloudwatch_region.clone(),
+            interval: self.cloudwatch_interval,
+        })
+    }
+
+    /// Load the configuration file, if one was given.
+    pub fn load_config(&self) -> ConfigResult<Config> {
+        match &self.config {
+            Some(path) => Config::from_file(path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Load the mapping of Consumer Groups to labels, if one was given.
+    pub fn load_group_labels(&self) -> ConfigResult<GroupLabels> {
+        match &self.group_labels_file {
+            Some(path) => GroupLabels::from_file(path),
+            None => Ok(GroupLabels::default()),
+        }
+        .map(|labels| labels.with_connectors(self.label_connectors))
+    }
+
+    /// Create a [`KommittedBuilder`], configured as per command line input
+    /// and configuration file.
+    pub fn kommitted_builder(&self) -> ConfigResult<KommittedBuilder> {
+        let config = self.load_config()?;
+
+        let mut group_excludes =
+            GroupExcludes::new(&self.group_exclude, !self.no_default_group_excludes);
+        if let Some(shard) = self.group_shard() {
+            group_excludes = group_excludes.with_shard(shard);
+        }
+
+        let mut builder = Kommitted::builder()
+            .client_config(self.build_client_config())
+            .cluster_id(self.cluster_id.clone())
+            .group_excludes(group_excludes)
+            .group_labels(self.load_group_labels()?)
+            .group_absence_grace(self.group_absence_grace)
+            .lag_events_threshold(self.lag_events_threshold)
+            .offsets_history(self.offsets_history)
+            .offsets_history_retention(HistoryRetention {
+                ttl: self.offsets_history_ttl,
+                memory_budget: self.offsets_history_memory_budget,
+            })
+            .offsets_polling(WatermarksPolling {
+                min_interval: self.watermarks_min_interval.unwrap_or_default(),
+                max_interval: self.watermarks_max_interval,
+                batch_size: self.watermarks_batch_size,
+                concurrency: self.watermarks_concurrency,
+            })
+            .time_lag_strategy(self.time_lag_strategy.strategy())
+            .idle_window(self.idle_window)
+            .offsets_history_ready_at(self.offsets_history_ready_at)
+            .status_window(self.status_window)
+            .lag_history(LagHistoryConfig {
+                retention: self.lag_history_retention,
+                resolution: self.lag_history_resolution,
+            })
+            .lag_trend(LagTrendConfig {
+                window: self.lag_trend_window,
+                slo_target: self.lag_slo_target,
+                slo_objective: self.lag_slo_objective,
+            })
+            .forget_offset_resets(self.forget_offset_resets)
+            .stopped_commit_intervals(self.stopped_commit_intervals)
+            .never_committed_lag_span(self.never_committed_lag_span)
+            .metrics_granularity(self.metrics_granularity)
+            .lagging_group_threshold(self.lagging_group_threshold)
+            .min_data_coverage(self.min_data_coverage, self.min_data_coverage_timeout)
+            .konsumer_offsets_start(self.konsumer_offsets_start)
+            .konsumer_offsets_consumer(KonsumerOffsetsConsumerConfig {
+                group_id: self.konsumer_offsets_group_id.clone(),
+                client_id: self.konsumer_offsets_client_id.clone(),
+                fetch_max_bytes: self.konsumer_offsets_fetch_max_bytes,
+                max_partition_fetch_bytes: self.konsumer_offsets_max_partition_fetch_bytes,
+                isolation_level: self.konsumer_offsets_isolation_level,
+            })
+            .offsets_source(self.offsets_source())
+            .admin_api_interval(self.admin_api_interval)
+            .lenient(self.lenient || self.preset.is_some())
+            .preset(self.preset)
+            .rdkafka_stats_interval(self.rdkafka_stats_interval)
+            .interval_jitter(Jitter::new(self.interval_jitter))
+            .external_offsets_endpoint(self.external_offsets_endpoint)
+            .ready_requires_groups(self.ready_requires_groups.clone())
+            .demo(self.demo)
+            .alerts(config.alerts);
+
+        if let Some(pc) = self.offsets_history_persistence() {
+            builder = builder.offsets_history_persistence(pc);
+        }
+
+        if let Some(pc) = self.lag_persistence() {
+            builder = builder.lag_persistence(pc);
+        }
+
+        if let Some(pc) = self.cluster_changes_persistence() {
+            builder = builder.cluster_changes_persistence(pc);
+        }
+
+        if let Some(gtc) = self.ground_truth_config() {
+            builder = builder.ground_truth(gtc);
+        }
+
+        if let Some(rc) = self.recording_config() {
+            builder = builder.recording(rc);
+        }
+
+        // Subcommands only run the service for a while: no point in serving it
+        if self.command.is_none() {
+            builder = builder.listen_on(self.listen_on());
+        }
+
+        if let Some(mb) = self.max_memory_mb {
+            builder = builder.max_memory(mb as usize * 1024 * 1024);
+        }
+
+        if let Some(lbc) = self.lag_bytes_config() {
+            builder = builder.lag_bytes(lbc);
+        }
+
+        if let Some(mc) = self.mirror_config() {
+            builder = builder.mirror(mc);
+        }
+
+        if let Some(hac) = self.high_availability_config() {
+            builder = builder.high_availability(hac);
+        }
+
+        if let Some(rwc) = self.remote_write_config() {
+            builder = builder.remote_write(rwc);
+        }
+
+        if let Some(sc) = self.statsd_config() {
+            builder = builder.statsd(sc);
+        }
+
+        if let Some(ic) = self.influx_config() {
+            builder = builder.influx(ic);
+        }
+
+        if let Some(gc) = self.graphite_config() {
+            builder = builder.graphite(gc);
+        }
+
+        if let Some(ktc) = self.kafka_topic_config() {
+            builder = builder.kafka_topic(ktc);
+        }
+
+        if let Some(dc) = self.dump_config() {
+            builder = builder.dump(dc);
+        }
+
+        if let Some(threshold) = self.log_snapshot_diff {
+            builder = builder.snapshot_diff(threshold);
+        }
+
+        #[cfg(feature = "otlp")]
+        if let Some(oc) = self.otlp_config() {
+            builder = builder.otlp(oc);
+        }
+
+        #[cfg(feature = "cloudwatch")]
+        if let Some(cwc) = self.cloudwatch_config() {
+            builder = builder.cloudwatch(cwc);
+        }
+
+        Ok(builder)
+    }
 }
 
 /// A simple (key,value) pair of `String`s, useful to be parsed from arguments via [`kv_clap_value_parser`].
-pub type KVPair = (String, String);
+///
+/// When formatted via [`fmt::Debug`], the value is redacted if the key holds a secret
+/// (e.g. `sasl.password`, or the `Authorization` header).
+#[derive(Clone, PartialEq, Eq)]
+pub struct KVPair(pub String, pub String);
+
+impl fmt::Debug for KVPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("").field(&self.0).field(&redact(&self.0, &self.1)).finish()
+    }
+}
+
+impl From<KVPair> for (String, String) {
+    fn from(kv: KVPair) -> Self {
+        (kv.0, kv.1)
+    }
+}
+
+/// Convert [`KVPair`]s into plain (key,value) pairs.
+fn kv_pairs(kvs: &[KVPair]) -> Vec<(String, String)> {
+    kvs.iter().cloned().map(Into::into).collect()
+}
 
 /// To be used as [`clap::value_parser`] function to create [`KVPair`] values.
 fn kv_clap_value_parser(kv: &str) -> Result<KVPair, String> {
@@ -148,7 +2063,20 @@ fn kv_clap_value_parser(kv: &str) -> Result<KVPair, String> {
         Some((k, v)) => (k, v),
     };
 
-    Ok((k.to_string(), v.to_string()))
+    Ok(KVPair(k.to_string(), v.to_string()))
+}
+
+/// To be used as [`clap::value_parser`] function to create [`KVPair`] values of HTTP headers,
+/// rejecting names and values that can't be sent.
+fn header_clap_value_parser(header: &str) -> Result<KVPair, String> {
+    let kv = kv_clap_value_parser(header)?;
+
+    reqwest::header::HeaderName::from_bytes(kv.0.as_bytes())
+        .map_err(|e| format!("Invalid header name '{}': {e}", kv.0))?;
+    reqwest::header::HeaderValue::from_str(&kv.1)
+        .map_err(|e| format!("Invalid value of header '{}': {e}", kv.0))?;
+
+    Ok(kv)
 }
 
 fn percent_clap_value_parser(percent_str: &str) -> Result<f64, String> {
@@ -161,3 +2089,47 @@ fn percent_clap_value_parser(percent_str: &str) -> Result<f64, String> {
 
     Ok(percent)
 }
+
+fn ratio_clap_value_parser(ratio_str: &str) -> Result<f64, String> {
+    let ratio =
+        ratio_str.parse::<f64>().map_err(|e| format!("Unable to parse {ratio_str}: {e}"))?;
+
+    if !(0.0..1.0).contains(&ratio) {
+        return Err(format!("Ratio value {ratio} should be between [0.0, 1.0)"));
+    }
+
+    Ok(ratio)
+}
+
+/// To be used as [`clap::value_parser`] function to parse an amount of bytes,
+/// from human-friendly strings like `512KB` or `64MiB`.
+fn bytes_clap_value_parser(bytes_str: &str) -> Result<usize, String> {
+    let s = bytes_str.trim();
+    let unit_at = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(unit_at);
+
+    let amount = amount
+        .parse::<usize>()
+        .map_err(|e| format!("Unable to parse amount of bytes {bytes_str}: {e}"))?;
+    let multiplier: usize = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "kib" => 1 << 10,
+        "mb" => 1_000_000,
+        "mib" => 1 << 20,
+        "gb" => 1_000_000_000,
+        "gib" => 1 << 30,
+        u => return Err(format!("Unknown unit '{u}' in {bytes_str}")),
+    };
+
+    amount
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Amount of bytes {bytes_str} is too large"))
+}
+
+/// To be used as [`clap::value_parser`] function to create [`Duration`] values,
+/// from human-friendly strings like `15s` or `1m 30s`.
+fn duration_clap_value_parser(duration_str: &str) -> Result<Duration, String> {
+    humantime::parse_duration(duration_str)
+        .map_err(|e| format!("Unable to parse duration {duration_str}: {e}"))
+}
@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{broadcast::error::RecvError, RwLock},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::register::ClusterStatusRegister;
+use super::topology::TopologyChange;
+
+use crate::internals::{Persistent, PersistentFuture};
+use crate::kafka_types::{Interned, TopicPartition};
+
+/// Max amount of [`TopologyAuditEntry`] kept: the oldest are discarded first.
+const TOPOLOGY_AUDIT_CAPACITY: usize = 1_000;
+
+/// Kind of a [`TopologyAuditEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopologyAuditKind {
+    TopicCreated,
+    TopicDeleted,
+    PartitionsAdded,
+    PartitionsRemoved,
+}
+
+/// A change to a Topic, as detected by the [`ClusterStatusRegister`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopologyAuditEntry {
+    /// When the change was detected (ms since UTC Epoch).
+    pub detected_at_ms: i64,
+
+    pub kind: TopologyAuditKind,
+
+    pub topic: Interned,
+
+    /// Partitions of the Topic affected by the change (all of them, if created or deleted).
+    pub partitions: Vec<u32>,
+}
+
+/// Keeps, in memory, an audit log of the most recent [`TopologyChange`]s of the Kafka cluster.
+///
+/// When the assignment of a Consumer Group suddenly changes, it's often because of a Topic
+/// it subscribes to (e.g. by pattern) being created, deleted or getting more Partitions:
+/// this keeps track of when that happened, to be served by the `/cluster/changes` HTTP endpoint.
+#[derive(Debug, Default)]
+pub struct TopologyAudit {
+    entries: RwLock<VecDeque<TopologyAuditEntry>>,
+}
+
+impl TopologyAudit {
+    /// Entries of the audit log, oldest first.
+    pub async fn entries(&self) -> Vec<TopologyAuditEntry> {
+        self.entries.read().await.iter().cloned().collect()
+    }
+
+    /// Record a [`TopologyChange`], detected at the given time.
+    pub async fn record(&self, change: &TopologyChange, detected_at: DateTime<Utc>) {
+        let mut w_guard = self.entries.write().await;
+        w_guard.extend(Self::entries_of(change, detected_at.timestamp_millis()));
+        let excess = w_guard.len().saturating_sub(TOPOLOGY_AUDIT_CAPACITY);
+        w_guard.drain(..excess);
+    }
+
+    /// Spawn a task that records the [`TopologyChange`]s detected by the [`ClusterStatusRegister`].
+    pub fn spawn(
+        self: &Arc<Self>,
+        cs_reg: &ClusterStatusRegister,
+        shutdown_token: CancellationToken,
+    ) -> JoinHandle<()> {
+        let audit = self.clone();
+        let mut topology_rx = cs_reg.subscribe_topology_changes();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    res = topology_rx.recv() => match res {
+                        Ok(change) => audit.record(&change, Utc::now()).await,
+                        Err(RecvError::Lagged(missed)) => {
+                            warn!("Topology audit missed {missed} cluster topology changes");
+                        },
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = shutdown_token.cancelled() => {
+                        info!("Shutting down");
+                        break;
+                    },
+                }
+            }
+        })
+    }
+
+    fn entries_of(change: &TopologyChange, detected_at_ms: i64) -> Vec<TopologyAuditEntry> {
+        let by_topic = |tps: &[TopicPartition]| -> BTreeMap<Interned, Vec<u32>> {
+            let mut by_topic: BTreeMap<Interned, Vec<u32>> = BTreeMap::new();
+            for tp in tps.iter() {
+                by_topic.entry(tp.topic.clone()).or_default().push(tp.partition);
+            }
+            by_topic
+        };
+        let added = by_topic(&change.added_partitions);
+        let removed = by_topic(&change.removed_partitions);
+
+        // Topics created (or deleted) come with all their Partitions added (or removed)
+        let (created, deleted) = (&change.created_topics, &change.deleted_topics);
+        let created_or_added = added.into_iter().map(|(topic, partitions)| {
+            let kind = if created.contains(&topic) {
+                TopologyAuditKind::TopicCreated
+            } else {
+                TopologyAuditKind::PartitionsAdded
+            };
+            (kind, topic, partitions)
+        });
+        let deleted_or_removed = removed.into_iter().map(|(topic, partitions)| {
+            let kind = if deleted.contains(&topic) {
+                TopologyAuditKind::TopicDeleted
+            } else {
+                TopologyAuditKind::PartitionsRemoved
+            };
+            (kind, topic, partitions)
+        });
+
+        created_or_added
+            .chain(deleted_or_removed)
+            .map(|(kind, topic, partitions)| TopologyAuditEntry {
+                detected_at_ms,
+                kind,
+                topic,
+                partitions,
+            })
+            .collect()
+    }
+}
+
+impl Persistent for TopologyAudit {
+    type State = Vec<TopologyAuditEntry>;
+
+    fn state(&self) -> PersistentFuture<'_, Self::State> {
+        Box::pin(self.entries())
+    }
+
+    /// Restored entries come before those recorded since startup, up to the capacity.
+    fn restore(&self, state: Self::State) -> PersistentFuture<'_, ()> {
+        Box::pin(async move {
+            let mut w_guard = self.entries.write().await;
+            let mut entries: VecDeque<TopologyAuditEntry> = state.into();
+            entries.append(&mut w_guard);
+            let excess = entries.len().saturating_sub(TOPOLOGY_AUDIT_CAPACITY);
+            entries.drain(..excess);
+            *w_guard = entries;
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tp(topic: &str, partition: u32) -> TopicPartition {
+        TopicPartition::new(Interned::new(topic), partition)
+    }
+
+    #[tokio::test]
+    async fn records_changes_per_topic() {
+        let audit = TopologyAudit::default();
+        let change = TopologyChange {
+            created_topics: vec![Interned::new("new")],
+            deleted_topics: vec![Interned::new("old")],
+            added_partitions: vec![tp("grown", 2), tp("grown", 3), tp("new", 0)],
+            removed_partitions: vec![tp("old", 0), tp("old", 1)],
+        };
+        let detected_at = DateTime::<Utc>::from_timestamp_millis(1_000).unwrap();
+        audit.record(&change, detected_at).await;
+
+        let entries = audit.entries().await;
+        assert!(entries.iter().all(|e| e.detected_at_ms == 1_000));
+        let summary: Vec<(TopologyAuditKind, &str, Vec<u32>)> =
+            entries.iter().map(|e| (e.kind, e.topic.as_ref(), e.partitions.clone())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (TopologyAuditKind::PartitionsAdded, "grown", vec![2, 3]),
+                (TopologyAuditKind::TopicCreated, "new", vec![0]),
+                (TopologyAuditKind::TopicDeleted, "old", vec![0, 1]),
+            ]
+        );
+
+        audit.restore(vec![entries[0].clone()]).await;
+        assert_eq!(audit.entries().await.len(), 4);
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_int, CStr},
+    slice,
+    time::Duration,
+};
+
+use rdkafka::{
+    bindings as rdsys,
+    client::{Client, ClientContext},
+    error::{KafkaError, KafkaResult},
+    types::RDKafkaErrorCode,
+};
+
+/// Extra time to wait for the `DescribeCluster` result, past the request timeout,
+/// so that the request can time out on its own.
+const POLL_GRACE: Duration = Duration::from_secs(1);
+
+/// Information about the Kafka cluster that is not part of its metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ClusterDescription {
+    /// Identifier of the Broker acting as controller of the Kafka cluster, if known.
+    pub controller_id: Option<u32>,
+
+    /// Rack (i.e. `broker.rack`) of each Broker, by identifier, if configured.
+    pub rack_by_broker: HashMap<u32, String>,
+}
+
+/// Describe the Kafka cluster via a `DescribeCluster` request.
+///
+/// This is not (yet) exposed by [`rdkafka`], so it's done directly via the `librdkafka` bindings.
+/// This is blocking, like [`Client::fetch_metadata`].
+///
+/// # Arguments
+///
+/// * `client` - Kafka client to send the request with
+/// * `timeout` - Request timeout
+pub(crate) fn describe_cluster<C: ClientContext>(
+    client: &Client<C>,
+    timeout: Duration,
+) -> KafkaResult<ClusterDescription> {
+    let timeout_ms = timeout.as_millis() as c_int;
+    let mut errstr: [c_char; 512] = [0; 512];
+
+    // SAFETY: All the pointers are obtained from `librdkafka`, checked for null where it can
+    // return null, and destroyed exactly once; the data borrowed from the result event
+    // is copied before the event is destroyed.
+    unsafe {
+        let rk = client.native_ptr();
+        let queue = rdsys::rd_kafka_queue_new(rk);
+        let opts = rdsys::rd_kafka_AdminOptions_new(
+            rk,
+            rdsys::rd_kafka_admin_op_t::RD_KAFKA_ADMIN_OP_DESCRIBECLUSTER,
+        );
+        rdsys::rd_kafka_AdminOptions_set_request_timeout(
+            opts,
+            timeout_ms,
+            errstr.as_mut_ptr(),
+            errstr.len(),
+        );
+
+        rdsys::rd_kafka_DescribeCluster(rk, opts, queue);
+        let event = rdsys::rd_kafka_queue_poll(queue, (timeout + POLL_GRACE).as_millis() as c_int);
+
+        let res = if event.is_null() {
+            Err(KafkaError::AdminOp(RDKafkaErrorCode::OperationTimedOut))
+        } else {
+            let res = parse_describe_cluster_event(event);
+            rdsys::rd_kafka_event_destroy(event);
+            res
+        };
+
+        rdsys::rd_kafka_AdminOptions_destroy(opts);
+        rdsys::rd_kafka_queue_destroy(queue);
+        res
+    }
+}
+
+/// Parse the `DescribeCluster` result `event` into a [`ClusterDescription`].
+///
+/// # Safety
+///
+/// The `event` must be a valid, not null, `DescribeCluster` result event.
+unsafe fn parse_describe_cluster_event(
+    event: *mut rdsys::rd_kafka_event_t,
+) -> KafkaResult<ClusterDescription> {
+    let err = rdsys::rd_kafka_event_error(event);
+    if err != rdsys::rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_NO_ERROR {
+        return Err(KafkaError::AdminOp(err.into()));
+    }
+
+    let result = rdsys::rd_kafka_event_DescribeCluster_result(event);
+    if result.is_null() {
+        return Err(KafkaError::AdminOp(RDKafkaErrorCode::Fail));
+    }
+
+    let controller = rdsys::rd_kafka_DescribeCluster_result_controller(result);
+    let controller_id = if controller.is_null() {
+        None
+    } else {
+        Some(rdsys::rd_kafka_Node_id(controller) as u32)
+    };
+
+    let mut nodes_count: usize = 0;
+    let nodes = rdsys::rd_kafka_DescribeCluster_result_nodes(result, &mut nodes_count);
+    let rack_by_broker = if nodes.is_null() {
+        HashMap::new()
+    } else {
+        slice::from_raw_parts(nodes, nodes_count)
+            .iter()
+            .filter_map(|node| {
+                let rack = rdsys::rd_kafka_Node_rack(*node);
+                if rack.is_null() {
+                    None
+                } else {
+                    Some((
+                        rdsys::rd_kafka_Node_id(*node) as u32,
+                        CStr::from_ptr(rack).to_string_lossy().into_owned(),
+                    ))
+                }
+            })
+            .collect()
+    };
+
+    Ok(ClusterDescription {
+        controller_id,
+        rack_by_broker,
+    })
+}
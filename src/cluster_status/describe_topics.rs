@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_int, CStr, CString},
+    slice,
+    time::Duration,
+};
+
+use rdkafka::{
+    bindings as rdsys,
+    client::{Client, ClientContext},
+    error::{KafkaError, KafkaResult},
+    types::RDKafkaErrorCode,
+};
+use uuid::Uuid;
+
+/// Extra time to wait for the `DescribeTopics` result, past the request timeout,
+/// so that the request can time out on its own.
+const POLL_GRACE: Duration = Duration::from_secs(1);
+
+/// Fetch the identifier (i.e. `topic.id`) of each Topic, via a `DescribeTopics` request.
+///
+/// This is not (yet) exposed by [`rdkafka`], so it's done directly via the `librdkafka` bindings.
+/// This is blocking, like [`Client::fetch_metadata`].
+/// Topics whose identifier can't be fetched are omitted.
+///
+/// # Arguments
+///
+/// * `client` - Kafka client to send the request with
+/// * `topics` - Names of the Topics to describe
+/// * `timeout` - Request timeout
+pub(crate) fn describe_topic_ids<C: ClientContext>(
+    client: &Client<C>,
+    topics: &[&str],
+    timeout: Duration,
+) -> KafkaResult<HashMap<String, Uuid>> {
+    if topics.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let names = topics
+        .iter()
+        .map(|t| {
+            CString::new(*t).map_err(|_| KafkaError::AdminOp(RDKafkaErrorCode::InvalidArgument))
+        })
+        .collect::<KafkaResult<Vec<CString>>>()?;
+    let mut names_ptrs: Vec<*const c_char> = names.iter().map(|n| n.as_ptr()).collect();
+
+    let timeout_ms = timeout.as_millis() as c_int;
+    let mut errstr: [c_char; 512] = [0; 512];
+
+    // SAFETY: All the pointers are obtained from `librdkafka`, checked for null where it can
+    // return null, and destroyed exactly once; the Topic names outlive the request, and the data
+    // borrowed from the result event is copied before the event is destroyed.
+    unsafe {
+        let rk = client.native_ptr();
+        let queue = rdsys::rd_kafka_queue_new(rk);
+        let opts = rdsys::rd_kafka_AdminOptions_new(
+            rk,
+            rdsys::rd_kafka_admin_op_t::RD_KAFKA_ADMIN_OP_DESCRIBETOPICS,
+        );
+        rdsys::rd_kafka_AdminOptions_set_request_timeout(
+            opts,
+            timeout_ms,
+            errstr.as_mut_ptr(),
+            errstr.len(),
+        );
+        let collection = rdsys::rd_kafka_TopicCollection_of_topic_names(
+            names_ptrs.as_mut_ptr(),
+            names_ptrs.len(),
+        );
+
+        rdsys::rd_kafka_DescribeTopics(rk, collection, opts, queue);
+        let event = rdsys::rd_kafka_queue_poll(queue, (timeout + POLL_GRACE).as_millis() as c_int);
+
+        let res = if event.is_null() {
+            Err(KafkaError::AdminOp(RDKafkaErrorCode::OperationTimedOut))
+        } else {
+            let res = parse_describe_topics_event(event);
+            rdsys::rd_kafka_event_destroy(event);
+            res
+        };
+
+        rdsys::rd_kafka_TopicCollection_destroy(collection);
+        rdsys::rd_kafka_AdminOptions_destroy(opts);
+        rdsys::rd_kafka_queue_destroy(queue);
+        res
+    }
+}
+
+/// Parse the `DescribeTopics` result `event` into the identifier of each Topic, by name.
+///
+/// # Safety
+///
+/// The `event` must be a valid, not null, `DescribeTopics` result event.
+unsafe fn parse_describe_topics_event(
+    event: *mut rdsys::rd_kafka_event_t,
+) -> KafkaResult<HashMap<String, Uuid>> {
+    let err = rdsys::rd_kafka_event_error(event);
+    if err != rdsys::rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_NO_ERROR {
+        return Err(KafkaError::AdminOp(err.into()));
+    }
+
+    let result = rdsys::rd_kafka_event_DescribeTopics_result(event);
+    if result.is_null() {
+        return Err(KafkaError::AdminOp(RDKafkaErrorCode::Fail));
+    }
+
+    let mut topics_count: usize = 0;
+    let topics = rdsys::rd_kafka_DescribeTopics_result_topics(result, &mut topics_count);
+    if topics.is_null() {
+        return Ok(HashMap::new());
+    }
+
+    Ok(slice::from_raw_parts(topics, topics_count)
+        .iter()
+        .filter_map(|td| {
+            let name = CStr::from_ptr(rdsys::rd_kafka_TopicDescription_name(*td))
+                .to_string_lossy()
+                .into_owned();
+            let td_err = rdsys::rd_kafka_TopicDescription_error(*td);
+            if !td_err.is_null()
+                && rdsys::rd_kafka_error_code(td_err)
+                    != rdsys::rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_NO_ERROR
+            {
+                debug!("Failed to describe topic '{name}'");
+                return None;
+            }
+
+            let id = rdsys::rd_kafka_TopicDescription_topic_id(*td);
+            if id.is_null() {
+                return None;
+            }
+            let id = Uuid::from_u64_pair(
+                rdsys::rd_kafka_Uuid_most_significant_bits(id) as u64,
+                rdsys::rd_kafka_Uuid_least_significant_bits(id) as u64,
+            );
+
+            // Brokers that don't support Topic identifiers report the "zero" one
+            (!id.is_nil()).then_some((name, id))
+        })
+        .collect())
+}
@@ -1,25 +1,35 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
+use chrono::{DateTime, Utc};
 use prometheus::{
     register_histogram_with_registry, register_int_gauge_with_registry, Histogram, IntGauge,
     Registry,
 };
-use rdkafka::{admin::AdminClient, client::DefaultClientContext, metadata::Metadata, ClientConfig};
-use tokio::{
-    sync::mpsc,
-    task::JoinHandle,
-    time::{interval, Duration},
+use rdkafka::{
+    admin::{AdminClient, AdminOptions, OwnedResourceSpecifier, ResourceSpecifier},
+    client::ClientContext,
+    error::KafkaResult,
+    ClientConfig,
 };
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, task::JoinHandle, time::Duration};
 use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::describe_cluster::{describe_cluster, ClusterDescription};
+use super::describe_topics::describe_topic_ids;
 
 use crate::constants::{DEFAULT_CLUSTER_ID, KONSUMER_OFFSETS_DATA_TOPIC};
-use crate::internals::Emitter;
-use crate::kafka_types::{Broker, TopicPartitionsStatus};
+use crate::internals::{
+    ClientStats, ClientStatsContext, ClusterMetadata, Emitter, Jitter, KafkaClientFuture,
+    MetadataClient, Subsystem, Subsystems,
+};
+use crate::kafka_types::{Broker, TopicConfig, TopicPartitionsStatus};
 
 const CHANNEL_SIZE: usize = 5;
 
 const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
-const FETCH_INTERVAL: Duration = Duration::from_secs(60);
+pub(crate) const FETCH_INTERVAL: Duration = Duration::from_secs(60);
 
 const MET_FETCH_NAME: &str = "cluster_status_emitter_fetch_time_milliseconds";
 const MET_FETCH_HELP: &str = "Time (ms) taken to fetch cluster status metadata";
@@ -28,7 +38,7 @@ const MET_CH_CAP_HELP: &str =
     "Capacity of internal channel used to send cluster status metadata to rest of the service";
 
 /// This is a `Send`-able struct to carry Kafka Cluster status across thread boundaries.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
 pub struct ClusterStatus {
     /// Cluster identifier, defined as `cluster.id` in Brokers' configuration.
     /// It will be `__none__` if not set on Brokers.
@@ -43,32 +53,57 @@ pub struct ClusterStatus {
     ///
     /// It reflects the status of Brokers as reported by the Kafka cluster.
     pub brokers: Vec<Broker>,
+
+    /// Identifier of the [`Broker`] acting as controller of the Kafka cluster, if known.
+    pub controller_id: Option<u32>,
+
+    /// [`DateTime<Utc>`] when this status was fetched from the Kafka cluster.
+    pub fetched_at: DateTime<Utc>,
 }
 
 impl ClusterStatus {
-    fn from(id: Option<String>, m: Metadata) -> Self {
+    fn from(id: Option<String>, m: ClusterMetadata) -> Self {
+        let ClusterMetadata {
+            mut topics,
+            mut brokers,
+        } = m;
+
+        // Ignore `__consumer_offsets` topic
+        topics.retain(|t| t.name != KONSUMER_OFFSETS_DATA_TOPIC);
+
+        // Sorted, so that everything derived from it (e.g. metrics) comes in a stable order
+        topics.sort_by(|a, b| a.name.cmp(&b.name));
+        for t in topics.iter_mut() {
+            t.partitions.sort_by_key(|p| p.id);
+        }
+        brokers.sort_by_key(|b| b.id);
+
         Self {
             id: id.unwrap_or_else(|| DEFAULT_CLUSTER_ID.to_string()),
-            topics: m
-                .topics()
-                .iter()
-                // Ignore `__consumer_offsets` topic
-                .filter(|mt| mt.name() != KONSUMER_OFFSETS_DATA_TOPIC)
-                .map(TopicPartitionsStatus::from)
-                .collect(),
-            brokers: m.brokers().iter().map(Broker::from).collect(),
+            topics,
+            brokers,
+            controller_id: None,
+            fetched_at: Utc::now(),
         }
     }
 }
 
 /// Emits [`ClusterStatus`] via a provided [`mpsc::channel`].
 ///
-/// It wraps an Admin Kafka Client, regularly requests it for the cluster metadata,
-/// and then emits it as [`ClusterStatus`].
+/// It wraps an Admin Kafka Client (or any other [`MetadataClient`]), regularly requests it
+/// for the cluster metadata, and then emits it as [`ClusterStatus`]. This is shared behind an [`Arc`], as with
+/// thousands of Topics it's expensive to clone, and it's never modified once emitted.
+///
+/// If not authorized to describe the cluster, and [`Subsystem::ClusterDescription`]
+/// gets disabled as a result, it carries on without describing it.
 ///
 /// It shuts down when the provided [`CancellationToken`] is cancelled.
 pub struct ClusterStatusEmitter {
     admin_client_config: ClientConfig,
+    subsystems: Arc<Subsystems>,
+    client_stats: Arc<ClientStats>,
+    jitter: Jitter,
+    client: Option<Arc<dyn MetadataClient>>,
 
     // Prometheus Metrics
     metric_fetch: Histogram,
@@ -81,9 +116,22 @@ impl ClusterStatusEmitter {
     /// # Arguments
     ///
     /// * `client_config` - Kafka admin client configuration, used to fetch the Cluster current status
-    pub fn new(client_config: ClientConfig, metrics: Arc<Registry>) -> Self {
+    /// * `subsystems` - Where to report the lack of permissions to describe the Cluster
+    /// * `client_stats` - Where to export the statistics of the Admin Client
+    /// * `jitter` - Random jitter to apply to the interval between fetches
+    pub fn new(
+        client_config: ClientConfig,
+        subsystems: Arc<Subsystems>,
+        client_stats: Arc<ClientStats>,
+        jitter: Jitter,
+        metrics: Arc<Registry>,
+    ) -> Self {
         Self {
             admin_client_config: client_config,
+            subsystems,
+            client_stats,
+            jitter,
+            client: None,
             metric_fetch: register_histogram_with_registry!(
                 MET_FETCH_NAME,
                 MET_FETCH_HELP,
@@ -98,10 +146,17 @@ impl ClusterStatusEmitter {
             .unwrap_or_else(|_| panic!("Failed to create metric: {MET_CH_CAP_NAME}")),
         }
     }
+
+    /// Fetch the cluster metadata via the given [`MetadataClient`],
+    /// instead of an Admin Kafka Client created out of the configuration.
+    pub fn with_client(mut self, client: Arc<dyn MetadataClient>) -> Self {
+        self.client = Some(client);
+        self
+    }
 }
 
 impl Emitter for ClusterStatusEmitter {
-    type Emitted = ClusterStatus;
+    type Emitted = Arc<ClusterStatus>;
 
     /// Spawn a new async task to run the business logic of this struct.
     ///
@@ -117,8 +172,13 @@ impl Emitter for ClusterStatusEmitter {
         &self,
         shutdown_token: CancellationToken,
     ) -> (mpsc::Receiver<Self::Emitted>, JoinHandle<()>) {
-        let admin_client: AdminClient<DefaultClientContext> =
-            self.admin_client_config.create().expect("Failed to allocate Admin Client");
+        let client = self.client.clone().unwrap_or_else(|| {
+            let admin_client: AdminClient<ClientStatsContext> = self
+                .client_stats
+                .create(&self.admin_client_config, "cluster_status")
+                .expect("Failed to allocate Admin Client");
+            Arc::new(admin_client)
+        });
 
         let (sx, rx) = mpsc::channel::<Self::Emitted>(CHANNEL_SIZE);
 
@@ -126,25 +186,80 @@ impl Emitter for ClusterStatusEmitter {
         let metric_fetch = self.metric_fetch.clone();
         let metric_ch_cap = self.metric_ch_cap.clone();
 
-        let join_handle = tokio::spawn(async move {
-            let mut interval = interval(FETCH_INTERVAL);
+        let subsystems = self.subsystems.clone();
+        let mut interval = self.jitter.interval(FETCH_INTERVAL);
 
+        let join_handle = tokio::spawn(async move {
             loop {
                 // Fetch metadata and update timer metric
                 let timer = metric_fetch.start_timer();
-                let res_status =
-                    admin_client.inner().fetch_metadata(None, FETCH_TIMEOUT).map(|m| {
-                        Self::Emitted::from(admin_client.inner().fetch_cluster_id(FETCH_TIMEOUT), m)
-                    });
+                let res_status = client
+                    .fetch_metadata(FETCH_TIMEOUT)
+                    .map(|m| ClusterStatus::from(client.fetch_cluster_id(FETCH_TIMEOUT), m));
                 timer.observe_duration();
 
                 match res_status {
-                    Ok(status) => {
+                    Ok(mut status) => {
+                        // Fetch Topics configuration: without it, carry on with the default
+                        let names =
+                            status.topics.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+                        match client.fetch_topic_configs(&names, FETCH_TIMEOUT).await {
+                            Ok(mut configs) => {
+                                for t in status.topics.iter_mut() {
+                                    t.config = configs.remove(t.name.as_str()).unwrap_or_default();
+                                }
+                            },
+                            Err(e) => {
+                                warn!("Failed to fetch topics configuration: {e}");
+                            },
+                        }
+
+                        // Fetch Topics identifier (to resolve Topics by it): without it, carry on
+                        if subsystems.is_enabled(Subsystem::TopicIdentifiers) {
+                            let names =
+                                status.topics.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+                            match client.describe_topic_ids(&names, FETCH_TIMEOUT) {
+                                Ok(mut ids) => {
+                                    for t in status.topics.iter_mut() {
+                                        t.id = ids.remove(t.name.as_str());
+                                    }
+                                },
+                                Err(e)
+                                    if subsystems.disable_if_unauthorized(
+                                        Subsystem::TopicIdentifiers,
+                                        &e,
+                                    ) => {},
+                                Err(e) => {
+                                    warn!("Failed to fetch topics identifier: {e}");
+                                },
+                            }
+                        }
+
+                        // Describe cluster (controller, brokers rack): without it, carry on
+                        if subsystems.is_enabled(Subsystem::ClusterDescription) {
+                            match client.describe_cluster(FETCH_TIMEOUT) {
+                                Ok(mut desc) => {
+                                    status.controller_id = desc.controller_id;
+                                    for b in status.brokers.iter_mut() {
+                                        b.rack = desc.rack_by_broker.remove(&b.id);
+                                    }
+                                },
+                                Err(e)
+                                    if subsystems.disable_if_unauthorized(
+                                        Subsystem::ClusterDescription,
+                                        &e,
+                                    ) => {},
+                                Err(e) => {
+                                    warn!("Failed to describe cluster: {e}");
+                                },
+                            }
+                        }
+
                         // Update channel capacity metric
                         metric_ch_cap.set(sx.capacity() as i64);
 
                         tokio::select! {
-                            res = Self::emit_with_interval(&sx, status, &mut interval) => {
+                            res = Self::emit_with_interval(&sx, Arc::new(status), &mut interval) => {
                                 if let Err(e) = res {
                                     error!("Failed to emit {}: {e}", std::any::type_name::<ClusterStatus>());
                                 }
@@ -157,6 +272,15 @@ impl Emitter for ClusterStatusEmitter {
                     },
                     Err(e) => {
                         error!("Failed to fetch cluster metadata: {e}");
+
+                        // Retry at the next tick: the cluster might be unreachable for long
+                        tokio::select! {
+                            _ = interval.tick() => {},
+                            _ = shutdown_token.cancelled() => {
+                                info!("Shutting down");
+                                break;
+                            },
+                        }
                     },
                 }
             }
@@ -165,3 +289,151 @@ impl Emitter for ClusterStatusEmitter {
         (rx, join_handle)
     }
 }
+
+impl<C: ClientContext + 'static> MetadataClient for AdminClient<C> {
+    fn fetch_metadata(&self, timeout: Duration) -> KafkaResult<ClusterMetadata> {
+        let m = self.inner().fetch_metadata(None, timeout)?;
+        Ok(ClusterMetadata {
+            topics: m.topics().iter().map(TopicPartitionsStatus::from).collect(),
+            brokers: m.brokers().iter().map(Broker::from).collect(),
+        })
+    }
+
+    fn fetch_cluster_id(&self, timeout: Duration) -> Option<String> {
+        self.inner().fetch_cluster_id(timeout)
+    }
+
+    fn describe_cluster(&self, timeout: Duration) -> KafkaResult<ClusterDescription> {
+        describe_cluster(self.inner(), timeout)
+    }
+
+    fn describe_topic_ids(
+        &self,
+        topics: &[&str],
+        timeout: Duration,
+    ) -> KafkaResult<HashMap<String, Uuid>> {
+        describe_topic_ids(self.inner(), topics, timeout)
+    }
+
+    /// Fetch the [`TopicConfig`] of each Topic, in a single `DescribeConfigs` request.
+    ///
+    /// Topics whose configuration can't be fetched are omitted (and logged).
+    fn fetch_topic_configs<'a>(
+        &'a self,
+        topics: &'a [&'a str],
+        timeout: Duration,
+    ) -> KafkaClientFuture<'a, HashMap<String, TopicConfig>> {
+        Box::pin(async move {
+            if topics.is_empty() {
+                return Ok(HashMap::new());
+            }
+
+            let specifiers: Vec<ResourceSpecifier> =
+                topics.iter().map(|t| ResourceSpecifier::Topic(t)).collect();
+            let opts = AdminOptions::new().request_timeout(Some(timeout));
+
+            Ok(self
+                .describe_configs(specifiers.iter(), &opts)
+                .await?
+                .into_iter()
+                .filter_map(|res| match res {
+                    Ok(cr) => match &cr.specifier {
+                        OwnedResourceSpecifier::Topic(t) => {
+                            Some((t.clone(), TopicConfig::from(&cr)))
+                        },
+                        _ => None,
+                    },
+                    Err(e) => {
+                        error!("Failed to fetch topic configuration: {e}");
+                        None
+                    },
+                })
+                .collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+
+    use crate::kafka_types::Interned;
+
+    use super::*;
+
+    struct FakeMetadataClient;
+
+    impl MetadataClient for FakeMetadataClient {
+        fn fetch_metadata(&self, _timeout: Duration) -> KafkaResult<ClusterMetadata> {
+            let topic = |name: &str| TopicPartitionsStatus {
+                name: Interned::new(name),
+                ..Default::default()
+            };
+            Ok(ClusterMetadata {
+                topics: vec![topic("orders"), topic(KONSUMER_OFFSETS_DATA_TOPIC), topic("clicks")],
+                brokers: vec![Broker {
+                    id: 1,
+                    ..Default::default()
+                }],
+            })
+        }
+    }
+
+    struct UnreachableMetadataClient;
+
+    impl MetadataClient for UnreachableMetadataClient {
+        fn fetch_metadata(&self, _timeout: Duration) -> KafkaResult<ClusterMetadata> {
+            Err(KafkaError::MetadataFetch(RDKafkaErrorCode::BrokerTransportFailure))
+        }
+    }
+
+    #[tokio::test]
+    async fn shuts_down_while_the_cluster_is_unreachable() {
+        let metrics = Arc::new(Registry::new());
+        let emitter = ClusterStatusEmitter::new(
+            ClientConfig::new(),
+            Arc::new(Subsystems::new(false, metrics.clone())),
+            Arc::new(ClientStats::new(Duration::from_secs(60), metrics.clone())),
+            Jitter::default(),
+            metrics,
+        )
+        .with_client(Arc::new(UnreachableMetadataClient));
+
+        let shutdown_token = CancellationToken::new();
+        let (mut rx, join) = emitter.spawn(shutdown_token.clone());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(rx.try_recv().is_err());
+
+        shutdown_token.cancel();
+        tokio::time::timeout(Duration::from_secs(5), join)
+            .await
+            .expect("Emitter did not shut down")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn emits_the_status_fetched_via_the_client() {
+        let metrics = Arc::new(Registry::new());
+        let emitter = ClusterStatusEmitter::new(
+            ClientConfig::new(),
+            Arc::new(Subsystems::new(false, metrics.clone())),
+            Arc::new(ClientStats::new(Duration::from_secs(60), metrics.clone())),
+            Jitter::default(),
+            metrics,
+        )
+        .with_client(Arc::new(FakeMetadataClient));
+
+        let shutdown_token = CancellationToken::new();
+        let (mut rx, join) = emitter.spawn(shutdown_token.clone());
+        let status = rx.recv().await.expect("Cluster status not emitted");
+        assert_eq!(status.id, DEFAULT_CLUSTER_ID);
+        assert_eq!(
+            status.topics.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+            vec!["clicks", "orders"]
+        );
+        assert_eq!(status.brokers.len(), 1);
+
+        shutdown_token.cancel();
+        join.await.unwrap();
+    }
+}
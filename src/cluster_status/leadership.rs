@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+
+use super::emitter::ClusterStatus;
+
+/// Distribution of Partitions leadership across the Brokers of a [`ClusterStatus`].
+///
+/// When leadership is skewed (e.g. after a Broker restart, before leaders are re-elected),
+/// some Brokers serve more traffic than others, and the lag of the Consumers reading
+/// from them often grows unevenly.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Leadership {
+    /// Amount of Partitions led by each Broker, including those leading none.
+    pub leader_count: BTreeMap<u32, usize>,
+
+    /// Amount of Partitions led by a Broker other than their preferred leader
+    /// (i.e. the first of their replicas).
+    pub non_preferred_leaders: usize,
+
+    /// Amount of Partitions in the Cluster.
+    pub partitions: usize,
+}
+
+impl Leadership {
+    pub fn of(cs: &ClusterStatus) -> Self {
+        let mut leadership = Self {
+            leader_count: cs.brokers.iter().map(|b| (b.id, 0)).collect(),
+            ..Default::default()
+        };
+
+        for p in cs.topics.iter().flat_map(|t| t.partitions.iter()) {
+            leadership.partitions += 1;
+            if let Some(count) = leadership.leader_count.get_mut(&p.leader_broker) {
+                *count += 1;
+            }
+            if p.replica_brokers.first() != Some(&p.leader_broker) {
+                leadership.non_preferred_leaders += 1;
+            }
+        }
+
+        leadership
+    }
+
+    /// Ratio of Partitions not led by their preferred leader (`0` if there are no Partitions).
+    pub fn preferred_leader_imbalance_ratio(&self) -> f64 {
+        if self.partitions == 0 {
+            return 0.0;
+        }
+        self.non_preferred_leaders as f64 / self.partitions as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kafka_types::{Broker, Interned, PartitionStatus, TopicPartitionsStatus};
+
+    fn partition(id: u32, leader_broker: u32, replica_brokers: &[u32]) -> PartitionStatus {
+        PartitionStatus {
+            id,
+            leader_broker,
+            replica_brokers: replica_brokers.to_vec(),
+            in_sync_replica_brokers: replica_brokers.to_vec(),
+        }
+    }
+
+    #[test]
+    fn counts_leaders_and_non_preferred_ones() {
+        let cs = ClusterStatus {
+            topics: vec![TopicPartitionsStatus {
+                name: Interned::new("orders"),
+                partitions: vec![
+                    partition(0, 1, &[1, 2]),
+                    partition(1, 1, &[2, 1]),
+                    partition(2, 2, &[2, 1]),
+                    partition(3, 1, &[3, 1]),
+                ],
+                ..Default::default()
+            }],
+            brokers: [1, 2, 3]
+                .into_iter()
+                .map(|id| Broker {
+                    id,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let leadership = Leadership::of(&cs);
+        assert_eq!(leadership.leader_count, BTreeMap::from([(1, 3), (2, 1), (3, 0)]));
+        assert_eq!(leadership.non_preferred_leaders, 2);
+        assert_eq!(leadership.preferred_leader_imbalance_ratio(), 0.5);
+        assert_eq!(Leadership::default().preferred_leader_imbalance_ratio(), 0.0);
+    }
+}
@@ -1,12 +1,25 @@
 // Inner module
+mod audit;
+mod describe_cluster;
+mod describe_topics;
 mod emitter;
+mod leadership;
 mod register;
+mod topology;
 
 use std::sync::Arc;
 
 // Exports
-pub use emitter::ClusterStatusEmitter;
+pub use audit::{TopologyAudit, TopologyAuditEntry};
+pub use emitter::{ClusterStatus, ClusterStatusEmitter};
 pub use register::ClusterStatusRegister;
+pub use topology::TopologyChange;
+
+pub use describe_cluster::ClusterDescription;
+
+pub(crate) use describe_cluster::describe_cluster;
+pub(crate) use describe_topics::describe_topic_ids;
+pub(crate) use emitter::FETCH_INTERVAL as CLUSTER_STATUS_FETCH_INTERVAL;
 
 // Imports
 use prometheus::Registry;
@@ -14,17 +27,35 @@ use rdkafka::ClientConfig;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
-use crate::internals::Emitter;
+use crate::internals::{ClientStats, Emitter, Jitter, MetadataClient, Subsystems};
+use crate::recording::Recording;
 
+#[allow(clippy::too_many_arguments)]
 pub fn init(
     admin_client_config: ClientConfig,
     cluster_id_override: Option<String>,
+    subsystems: Arc<Subsystems>,
+    client_stats: Arc<ClientStats>,
+    jitter: Jitter,
+    client: Option<Arc<dyn MetadataClient>>,
+    recording: &Recording,
     shutdown_token: CancellationToken,
     metrics: Arc<Registry>,
 ) -> (ClusterStatusRegister, JoinHandle<()>) {
     // Cluster Status: emitter and register
-    let (cs_rx, cse_join) =
-        ClusterStatusEmitter::new(admin_client_config, metrics.clone()).spawn(shutdown_token);
+    let (cs_rx, cse_join) = recording.emitted(|| {
+        let mut emitter = ClusterStatusEmitter::new(
+            admin_client_config,
+            subsystems,
+            client_stats,
+            jitter,
+            metrics.clone(),
+        );
+        if let Some(client) = client {
+            emitter = emitter.with_client(client);
+        }
+        emitter.spawn(shutdown_token)
+    });
     let cs_reg = ClusterStatusRegister::new(cluster_id_override, cs_rx, metrics);
 
     debug!("Initialized");
@@ -1,17 +1,31 @@
 use std::sync::Arc;
 
+use chrono::Utc;
 use prometheus::{
-    register_int_gauge_vec_with_registry, register_int_gauge_with_registry, IntGauge, IntGaugeVec,
-    Registry,
+    register_gauge_with_registry, register_int_counter_vec_with_registry,
+    register_int_gauge_vec_with_registry, register_int_gauge_with_registry, Gauge, IntCounterVec,
+    IntGauge, IntGaugeVec, Registry,
 };
-use tokio::sync::{mpsc::Receiver, RwLock};
+use tokio::{
+    sync::{broadcast, mpsc::Receiver, RwLock},
+    time::{interval, Duration},
+};
+use uuid::Uuid;
 
 use super::emitter::ClusterStatus;
+use super::leadership::Leadership;
+use super::topology::{IsrChange, TopologyChange};
 
 use crate::constants::DEFAULT_CLUSTER_ID;
 use crate::internals::Awaitable;
-use crate::kafka_types::{Broker, TopicPartition};
-use crate::prometheus_metrics::LABEL_TOPIC;
+use crate::kafka_types::{Broker, Interned, TopicConfig, TopicPartition};
+use crate::prometheus_metrics::{
+    LABEL_BROKER_HOST, LABEL_BROKER_ID, LABEL_BROKER_RACK, LABEL_CONTROLLER, LABEL_KIND,
+    LABEL_PARTITION, LABEL_TOPIC, UNKNOWN_VAL,
+};
+
+const TOPOLOGY_CHANGES_CHANNEL_SIZE: usize = 16;
+const METADATA_AGE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
 
 const MET_BROKERS_TOT_NAME: &str = "cluster_brokers_total";
 const MET_BROKERS_TOT_HELP: &str = "Brokers currently in cluster";
@@ -21,30 +35,71 @@ const MET_PARTITIONS_TOT_NAME: &str = "cluster_partitions_total";
 const MET_PARTITIONS_TOT_HELP: &str = "Partitions currently in cluster";
 const MET_TOPIC_PARTITIONS_TOT_NAME: &str = "cluster_topic_partitions_total";
 const MET_TOPIC_PARTITIONS_TOT_HELP: &str = "Topic's Partitions currently in cluster";
+const MET_TOPIC_RETENTION_MS_NAME: &str = "topic_retention_milliseconds";
+const MET_TOPIC_RETENTION_MS_HELP: &str =
+    "Topic's retention (ms) of records, as configured by 'retention.ms' (-1 if unlimited)";
+const MET_TOPIC_RETENTION_BYTES_NAME: &str = "topic_retention_bytes";
+const MET_TOPIC_RETENTION_BYTES_HELP: &str =
+    "Topic's retention (bytes) of records per partition, as configured by 'retention.bytes' (-1 if unlimited)";
+const MET_TOPIC_COMPACTED_NAME: &str = "topic_compacted";
+const MET_TOPIC_COMPACTED_HELP: &str =
+    "Whether Topic's records are compacted, as configured by 'cleanup.policy' (1 if compacted, 0 otherwise)";
+const MET_BROKER_INFO_NAME: &str = "cluster_broker_info";
+const MET_BROKER_INFO_HELP: &str =
+    "Brokers currently in cluster, with their host, rack and whether they are the controller (always 1)";
+const MET_BROKER_LEADER_COUNT_NAME: &str = "cluster_broker_leader_count";
+const MET_BROKER_LEADER_COUNT_HELP: &str = "Partitions currently led by each Broker in cluster";
+const MET_PREFERRED_LEADER_IMBALANCE_NAME: &str = "cluster_preferred_leader_imbalance_ratio";
+const MET_PREFERRED_LEADER_IMBALANCE_HELP: &str =
+    "Ratio of partitions in cluster not currently led by their preferred leader (i.e. first replica)";
+const MET_METADATA_AGE_NAME: &str = "cluster_metadata_age_seconds";
+const MET_METADATA_AGE_HELP: &str = "Time (s) since cluster status metadata was last fetched";
+const MET_TOPOLOGY_CHANGES_NAME: &str = "cluster_topology_changes_total";
+const MET_TOPOLOGY_CHANGES_HELP: &str =
+    "Topics created/deleted and partitions added/removed in cluster, by kind of change";
+const MET_ISR_SHRINK_NAME: &str = "cluster_partition_isr_shrink_total";
+const MET_ISR_SHRINK_HELP: &str =
+    "Times the In-Sync Replicas of a partition shrunk (i.e. brokers left it), between cluster status updates";
+const MET_ISR_EXPAND_NAME: &str = "cluster_partition_isr_expand_total";
+const MET_ISR_EXPAND_HELP: &str =
+    "Times the In-Sync Replicas of a partition expanded (i.e. brokers joined it), between cluster status updates";
 
 /// Registers and exposes the latest [`ClusterStatus`].
 ///
 /// It exposes the accessor methods via an async interface,
 /// while dealing internally with concurrency and synchronization.
+/// The [`ClusterStatus`] is held (and handed out) as received, behind an [`Arc`].
 #[derive(Debug)]
 pub struct ClusterStatusRegister {
-    latest_status: Arc<RwLock<Option<ClusterStatus>>>,
+    latest_status: Arc<RwLock<Option<Arc<ClusterStatus>>>>,
+    topology_changes: broadcast::Sender<TopologyChange>,
 
     // Prometheus Metrics
     metric_brokers: IntGauge,
     metric_topics: IntGauge,
     metric_partitions: IntGauge,
     metric_topic_partitions: IntGaugeVec,
+    metric_topic_retention_ms: IntGaugeVec,
+    metric_topic_retention_bytes: IntGaugeVec,
+    metric_topic_compacted: IntGaugeVec,
+    metric_broker_info: IntGaugeVec,
+    metric_broker_leader_count: IntGaugeVec,
+    metric_preferred_leader_imbalance: Gauge,
+    metric_metadata_age: Gauge,
+    metric_topology_changes: IntCounterVec,
+    metric_isr_shrink: IntCounterVec,
+    metric_isr_expand: IntCounterVec,
 }
 
 impl ClusterStatusRegister {
-    pub fn new(
+    pub(crate) fn new(
         cluster_id_override: Option<String>,
-        mut rx: Receiver<ClusterStatus>,
+        mut rx: Receiver<Arc<ClusterStatus>>,
         metrics: Arc<Registry>,
     ) -> Self {
         let csr = Self {
             latest_status: Arc::new(RwLock::new(None)),
+            topology_changes: broadcast::channel(TOPOLOGY_CHANGES_CHANNEL_SIZE).0,
             metric_brokers: register_int_gauge_with_registry!(
                 MET_BROKERS_TOT_NAME,
                 MET_BROKERS_TOT_HELP,
@@ -70,17 +125,100 @@ impl ClusterStatusRegister {
                 metrics
             )
             .unwrap_or_else(|_| panic!("Failed to create metric: {MET_TOPIC_PARTITIONS_TOT_NAME}")),
+            metric_topic_retention_ms: register_int_gauge_vec_with_registry!(
+                MET_TOPIC_RETENTION_MS_NAME,
+                MET_TOPIC_RETENTION_MS_HELP,
+                &[LABEL_TOPIC],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_TOPIC_RETENTION_MS_NAME}")),
+            metric_topic_retention_bytes: register_int_gauge_vec_with_registry!(
+                MET_TOPIC_RETENTION_BYTES_NAME,
+                MET_TOPIC_RETENTION_BYTES_HELP,
+                &[LABEL_TOPIC],
+                metrics
+            )
+            .unwrap_or_else(|_| {
+                panic!("Failed to create metric: {MET_TOPIC_RETENTION_BYTES_NAME}")
+            }),
+            metric_topic_compacted: register_int_gauge_vec_with_registry!(
+                MET_TOPIC_COMPACTED_NAME,
+                MET_TOPIC_COMPACTED_HELP,
+                &[LABEL_TOPIC],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_TOPIC_COMPACTED_NAME}")),
+            metric_broker_info: register_int_gauge_vec_with_registry!(
+                MET_BROKER_INFO_NAME,
+                MET_BROKER_INFO_HELP,
+                &[LABEL_BROKER_ID, LABEL_BROKER_HOST, LABEL_BROKER_RACK, LABEL_CONTROLLER],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_BROKER_INFO_NAME}")),
+            metric_broker_leader_count: register_int_gauge_vec_with_registry!(
+                MET_BROKER_LEADER_COUNT_NAME,
+                MET_BROKER_LEADER_COUNT_HELP,
+                &[LABEL_BROKER_ID],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_BROKER_LEADER_COUNT_NAME}")),
+            metric_preferred_leader_imbalance: register_gauge_with_registry!(
+                MET_PREFERRED_LEADER_IMBALANCE_NAME,
+                MET_PREFERRED_LEADER_IMBALANCE_HELP,
+                metrics
+            )
+            .unwrap_or_else(|_| {
+                panic!("Failed to create metric: {MET_PREFERRED_LEADER_IMBALANCE_NAME}")
+            }),
+            metric_metadata_age: register_gauge_with_registry!(
+                MET_METADATA_AGE_NAME,
+                MET_METADATA_AGE_HELP,
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_METADATA_AGE_NAME}")),
+            metric_topology_changes: register_int_counter_vec_with_registry!(
+                MET_TOPOLOGY_CHANGES_NAME,
+                MET_TOPOLOGY_CHANGES_HELP,
+                &[LABEL_KIND],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_TOPOLOGY_CHANGES_NAME}")),
+            metric_isr_shrink: register_int_counter_vec_with_registry!(
+                MET_ISR_SHRINK_NAME,
+                MET_ISR_SHRINK_HELP,
+                &[LABEL_TOPIC, LABEL_PARTITION],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_ISR_SHRINK_NAME}")),
+            metric_isr_expand: register_int_counter_vec_with_registry!(
+                MET_ISR_EXPAND_NAME,
+                MET_ISR_EXPAND_HELP,
+                &[LABEL_TOPIC, LABEL_PARTITION],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_ISR_EXPAND_NAME}")),
         };
 
         // A clone of the `csr.latest_status` will be moved into the async task
         // that updates the register.
         let latest_status_arc_clone = csr.latest_status.clone();
+        let topology_changes = csr.topology_changes.clone();
 
         // Clone metrics so they can be used in the spawned future
         let metric_brokers = csr.metric_brokers.clone();
         let metric_topics = csr.metric_topics.clone();
         let metric_partitions = csr.metric_partitions.clone();
         let metric_topic_partitions = csr.metric_topic_partitions.clone();
+        let metric_topic_retention_ms = csr.metric_topic_retention_ms.clone();
+        let metric_topic_retention_bytes = csr.metric_topic_retention_bytes.clone();
+        let metric_topic_compacted = csr.metric_topic_compacted.clone();
+        let metric_broker_info = csr.metric_broker_info.clone();
+        let metric_broker_leader_count = csr.metric_broker_leader_count.clone();
+        let metric_preferred_leader_imbalance = csr.metric_preferred_leader_imbalance.clone();
+        let metric_metadata_age = csr.metric_metadata_age.clone();
+        let metric_topology_changes = csr.metric_topology_changes.clone();
+        let metric_isr_shrink = csr.metric_isr_shrink.clone();
+        let metric_isr_expand = csr.metric_isr_expand.clone();
 
         // The Register is essentially "self updating" its data, by listening
         // on a channel for updates.
@@ -91,14 +229,27 @@ impl ClusterStatusRegister {
         tokio::spawn(async move {
             debug!("Begin receiving ClusterStatus updates");
 
+            let mut metadata_age_interval = interval(METADATA_AGE_UPDATE_INTERVAL);
             loop {
                 tokio::select! {
-                    Some(mut cs) = rx.recv() => {
+                    // Keep the age of the metadata up to date, in between updates
+                    _ = metadata_age_interval.tick() => {
+                        if let Some(cs) = &*(latest_status_arc_clone.read().await) {
+                            let age = Utc::now() - cs.fetched_at;
+                            metric_metadata_age.set(age.num_milliseconds() as f64 / 1000.0);
+                        }
+                    },
+                    res = rx.recv() => {
+                        let Some(mut cs) = res else {
+                            info!("Emitters stopping: breaking (internal) loop");
+                            break;
+                        };
                         trace!("Received:\n{:#?}", cs);
 
                         // Override cluster identifier, if present
+                        // (the emitter doesn't keep a reference, so this doesn't clone it)
                         if let Some(c_id_over) = &cluster_id_override {
-                            cs.id = c_id_over.to_string();
+                            Arc::make_mut(&mut cs).id = c_id_over.to_string();
                         }
 
                         info!(
@@ -115,16 +266,100 @@ impl ClusterStatusRegister {
                                 .with_label_values(&[&t.name])
                                 .set(t.partitions.len() as i64);
                             partitions_total += t.partitions.len();
+
+                            // Update topic configuration metrics, if it was fetched
+                            let config_values = [
+                                (&metric_topic_retention_ms, t.config.retention_ms),
+                                (&metric_topic_retention_bytes, t.config.retention_bytes),
+                                (&metric_topic_compacted, t.config.cleanup_policy.as_ref().map(|cp| i64::from(cp.contains("compact")))),
+                            ];
+                            for (metric, value) in config_values {
+                                match value {
+                                    Some(v) => metric.with_label_values(&[&t.name]).set(v),
+                                    None => {
+                                        let _ = metric.remove_label_values(&[&t.name]);
+                                    },
+                                }
+                            }
                         }
                         metric_partitions.set(partitions_total as i64);
 
-                        // Set the latest cluster status
-                        *(latest_status_arc_clone.write().await) = Some(cs);
+                        // Update brokers info metrics, dropping Brokers no longer in cluster
+                        metric_broker_info.reset();
+                        for b in cs.brokers.iter() {
+                            metric_broker_info
+                                .with_label_values(&[
+                                    &b.id.to_string(),
+                                    &format!("{}:{}", b.host, b.port),
+                                    b.rack.as_deref().unwrap_or(UNKNOWN_VAL),
+                                    &(cs.controller_id == Some(b.id)).to_string(),
+                                ])
+                                .set(1);
+                        }
+
+                        // Update partitions leadership metrics, dropping Brokers no longer in cluster
+                        let leadership = Leadership::of(&cs);
+                        metric_broker_leader_count.reset();
+                        for (broker_id, count) in leadership.leader_count.iter() {
+                            metric_broker_leader_count
+                                .with_label_values(&[&broker_id.to_string()])
+                                .set(*count as i64);
+                        }
+                        metric_preferred_leader_imbalance.set(leadership.preferred_leader_imbalance_ratio());
+
+                        metric_metadata_age.set((Utc::now() - cs.fetched_at).num_milliseconds() as f64 / 1000.0);
+
+                        // Set the latest cluster status, and diff it with the previous one
+                        let prev = latest_status_arc_clone.write().await.replace(cs.clone());
+                        if let Some(prev) = prev {
+                            // Count ISR shrinks/expands, as they are the most likely cause of lag
+                            for isr_change in IsrChange::between(&prev, &cs) {
+                                debug!("ISR changed: {:?}", isr_change);
+                                let labels = [isr_change.tp.topic.as_ref(), &isr_change.tp.partition.to_string()];
+                                if !isr_change.left.is_empty() {
+                                    metric_isr_shrink.with_label_values(&labels).inc();
+                                }
+                                if !isr_change.joined.is_empty() {
+                                    metric_isr_expand.with_label_values(&labels).inc();
+                                }
+                            }
+
+                            let change = TopologyChange::between(&prev, &cs);
+                            if !change.is_empty() {
+                                info!(
+                                    "Cluster topology changed: {} topics created, {} topics deleted, {} partitions added, {} partitions removed",
+                                    change.created_topics.len(),
+                                    change.deleted_topics.len(),
+                                    change.added_partitions.len(),
+                                    change.removed_partitions.len()
+                                );
+                                debug!("Cluster topology change:\n{:#?}", change);
+
+                                for t in change.deleted_topics.iter() {
+                                    for metric in [&metric_topic_partitions, &metric_topic_retention_ms, &metric_topic_retention_bytes, &metric_topic_compacted] {
+                                        let _ = metric.remove_label_values(&[t]);
+                                    }
+                                }
+                                for tp in change.removed_partitions.iter() {
+                                    let labels = [tp.topic.as_ref(), &tp.partition.to_string()];
+                                    for metric in [&metric_isr_shrink, &metric_isr_expand] {
+                                        let _ = metric.remove_label_values(&labels);
+                                    }
+                                }
+                                for (kind, count) in [
+                                    ("topic_created", change.created_topics.len()),
+                                    ("topic_deleted", change.deleted_topics.len()),
+                                    ("partition_added", change.added_partitions.len()),
+                                    ("partition_removed", change.removed_partitions.len()),
+                                ] {
+                                    metric_topology_changes.with_label_values(&[kind]).inc_by(count as u64);
+                                }
+
+                                // Notify subscribers (if any)
+                                let _ = topology_changes.send(change);
+                            }
+                        }
                     },
-                    else => {
-                        info!("Emitters stopping: breaking (internal) loop");
-                        break;
-                    }
                 }
             }
         });
@@ -132,6 +367,12 @@ impl ClusterStatusRegister {
         csr
     }
 
+    /// Subscribe to the [`TopologyChange`]s of the Kafka cluster (i.e. Topics created/deleted,
+    /// Partitions added/removed), as they are detected from now on.
+    pub fn subscribe_topology_changes(&self) -> broadcast::Receiver<TopologyChange> {
+        self.topology_changes.subscribe()
+    }
+
     /// Current identifier of the Kafka cluster.
     pub async fn get_cluster_id(&self) -> String {
         match &*(self.latest_status.read().await) {
@@ -141,13 +382,21 @@ impl ClusterStatusRegister {
     }
 
     /// Current Topics present in the Kafka cluster.
-    pub async fn get_topics(&self) -> Vec<String> {
+    pub async fn get_topics(&self) -> Vec<Interned> {
         match &*(self.latest_status.read().await) {
             None => Vec::new(),
             Some(cs) => cs.topics.iter().map(|t| t.name.clone()).collect(),
         }
     }
 
+    /// Amount of Partitions currently in the Kafka cluster, across all Topics.
+    pub async fn get_partitions_count(&self) -> usize {
+        match &*(self.latest_status.read().await) {
+            None => 0,
+            Some(cs) => cs.topics.iter().map(|t| t.partitions.len()).sum(),
+        }
+    }
+
     /// Current Partitions for a Topic present in the Kafka cluster.
     ///
     /// # Arguments
@@ -182,6 +431,37 @@ impl ClusterStatusRegister {
         }
     }
 
+    /// Current [`TopicConfig`] of a Topic present in the Kafka cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - Topic we want to know the configuration of.
+    pub async fn get_topic_config(&self, topic: &str) -> Option<TopicConfig> {
+        match &*(self.latest_status.read().await) {
+            None => None,
+            Some(cs) => cs.topics.iter().find(|t| t.name == topic).map(|t| t.config.clone()),
+        }
+    }
+
+    /// Name of the Topic present in the Kafka cluster, with the given identifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Identifier (i.e. `topic.id`) of the Topic we want to know the name of.
+    pub async fn get_topic_by_id(&self, id: &Uuid) -> Option<Interned> {
+        match &*(self.latest_status.read().await) {
+            None => None,
+            Some(cs) => {
+                cs.topics.iter().find(|t| t.id.as_ref() == Some(id)).map(|t| t.name.clone())
+            },
+        }
+    }
+
+    /// Latest [`ClusterStatus`], if any was received yet.
+    pub async fn get_status(&self) -> Option<Arc<ClusterStatus>> {
+        self.latest_status.read().await.clone()
+    }
+
     /// Current Brokers constituting the Kafka cluster.
     #[allow(unused)]
     pub async fn get_brokers(&self) -> Vec<Broker> {
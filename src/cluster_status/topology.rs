@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::emitter::ClusterStatus;
+
+use crate::kafka_types::{Interned, TopicPartition};
+
+/// Difference in Topics and Partitions between 2 consecutive [`ClusterStatus`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TopologyChange {
+    /// Topics that were created.
+    pub created_topics: Vec<Interned>,
+
+    /// Topics that were deleted.
+    pub deleted_topics: Vec<Interned>,
+
+    /// Topic Partitions that were added, including those of created Topics.
+    pub added_partitions: Vec<TopicPartition>,
+
+    /// Topic Partitions that were removed, including those of deleted Topics.
+    pub removed_partitions: Vec<TopicPartition>,
+}
+
+impl TopologyChange {
+    /// Diff the Topics and Partitions of the `prev` and `next` [`ClusterStatus`].
+    pub fn between(prev: &ClusterStatus, next: &ClusterStatus) -> Self {
+        let partitions_by_topic = |cs: &ClusterStatus| -> BTreeMap<Interned, BTreeSet<u32>> {
+            cs.topics
+                .iter()
+                .map(|t| (t.name.clone(), t.partitions.iter().map(|p| p.id).collect()))
+                .collect()
+        };
+        let prev = partitions_by_topic(prev);
+        let next = partitions_by_topic(next);
+        let none = BTreeSet::new();
+
+        let mut change = Self::default();
+        for (t, next_ps) in next.iter() {
+            let prev_ps = prev.get(t).unwrap_or_else(|| {
+                change.created_topics.push(t.clone());
+                &none
+            });
+            change
+                .added_partitions
+                .extend(next_ps.difference(prev_ps).map(|p| TopicPartition::new(t.clone(), *p)));
+        }
+        for (t, prev_ps) in prev.iter() {
+            let next_ps = next.get(t).unwrap_or_else(|| {
+                change.deleted_topics.push(t.clone());
+                &none
+            });
+            change
+                .removed_partitions
+                .extend(prev_ps.difference(next_ps).map(|p| TopicPartition::new(t.clone(), *p)));
+        }
+
+        change
+    }
+
+    /// Whether Topics and Partitions are unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.added_partitions.is_empty() && self.removed_partitions.is_empty()
+    }
+}
+
+/// Difference in the In-Sync Replicas (ISR) of a Topic Partition between 2 consecutive [`ClusterStatus`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsrChange {
+    pub tp: TopicPartition,
+
+    /// Brokers that left the ISR (i.e. the ISR shrunk).
+    pub left: Vec<u32>,
+
+    /// Brokers that joined the ISR (i.e. the ISR expanded).
+    pub joined: Vec<u32>,
+}
+
+impl IsrChange {
+    /// Diff the ISR of the Topic Partitions present in both the `prev` and `next` [`ClusterStatus`].
+    ///
+    /// Topic Partitions added or removed are not considered: see [`TopologyChange`].
+    pub fn between(prev: &ClusterStatus, next: &ClusterStatus) -> Vec<Self> {
+        let isr_by_partition = |cs: &ClusterStatus| -> BTreeMap<TopicPartition, BTreeSet<u32>> {
+            cs.topics
+                .iter()
+                .flat_map(|t| {
+                    t.partitions.iter().map(|p| {
+                        (
+                            TopicPartition::new(t.name.clone(), p.id),
+                            p.in_sync_replica_brokers.iter().copied().collect(),
+                        )
+                    })
+                })
+                .collect()
+        };
+        let prev = isr_by_partition(prev);
+
+        isr_by_partition(next)
+            .into_iter()
+            .filter_map(|(tp, next_isr)| {
+                let prev_isr = prev.get(&tp)?;
+                let left: Vec<u32> = prev_isr.difference(&next_isr).copied().collect();
+                let joined: Vec<u32> = next_isr.difference(prev_isr).copied().collect();
+                (!left.is_empty() || !joined.is_empty()).then_some(Self {
+                    tp,
+                    left,
+                    joined,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kafka_types::{PartitionStatus, TopicPartitionsStatus};
+
+    fn cluster_status(topics: &[(&str, u32)]) -> ClusterStatus {
+        ClusterStatus {
+            topics: topics
+                .iter()
+                .map(|(name, partitions)| TopicPartitionsStatus {
+                    name: Interned::new(name),
+                    partitions: (0..*partitions)
+                        .map(|id| PartitionStatus {
+                            id,
+                            ..Default::default()
+                        })
+                        .collect(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn tp(topic: &str, partition: u32) -> TopicPartition {
+        TopicPartition::new(topic.to_string(), partition)
+    }
+
+    #[test]
+    fn unchanged() {
+        let cs = cluster_status(&[("a", 2), ("b", 1)]);
+        assert!(TopologyChange::between(&cs, &cs).is_empty());
+    }
+
+    #[test]
+    fn topics_and_partitions_changes() {
+        let prev = cluster_status(&[("a", 2), ("b", 1), ("c", 1)]);
+        let next = cluster_status(&[("a", 3), ("b", 1), ("d", 2)]);
+
+        let change = TopologyChange::between(&prev, &next);
+        assert_eq!(change.created_topics, vec!["d".to_string()]);
+        assert_eq!(change.deleted_topics, vec!["c".to_string()]);
+        assert_eq!(change.added_partitions, vec![tp("a", 2), tp("d", 0), tp("d", 1)]);
+        assert_eq!(change.removed_partitions, vec![tp("c", 0)]);
+    }
+
+    #[test]
+    fn isr_shrinks_and_expands() {
+        let with_isr = |isr: &[&[u32]]| {
+            let mut cs = cluster_status(&[("a", isr.len() as u32)]);
+            for (p, isr) in cs.topics[0].partitions.iter_mut().zip(isr) {
+                p.in_sync_replica_brokers = isr.to_vec();
+            }
+            cs
+        };
+        let prev = with_isr(&[&[1, 2, 3], &[1, 2, 3], &[1, 2]]);
+        let next = with_isr(&[&[3, 1, 2], &[1, 3], &[2, 3], &[1, 2, 3]]);
+
+        assert_eq!(
+            IsrChange::between(&prev, &next),
+            vec![
+                IsrChange {
+                    tp: tp("a", 1),
+                    left: vec![2],
+                    joined: vec![],
+                },
+                IsrChange {
+                    tp: tp("a", 2),
+                    left: vec![1],
+                    joined: vec![3],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use konsumer_offsets::{KonsumerOffsetsData, OffsetCommit};
+use prometheus::{register_histogram_with_registry, Histogram, Registry};
+use rdkafka::{
+    config::RDKafkaLogLevel,
+    consumer::{BaseConsumer, Consumer},
+    error::KafkaResult,
+    ClientConfig, Offset, TopicPartitionList,
+};
+use tokio::{sync::mpsc, task::JoinHandle, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+use crate::cluster_status::ClusterStatusRegister;
+use crate::constants::KOMMITTED_CONSUMER_OFFSETS_CONSUMER;
+use crate::consumer_groups::{ConsumerGroups, GroupExcludes};
+use crate::internals::{CommittedOffsetsClient, Emitter, Jitter, Subsystem, Subsystems};
+use crate::kafka_types::{Interned, TopicPartition};
+
+const CHANNEL_SIZE: usize = 10_000;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many fetches apart all the Topic Partitions are queried for the groups without members,
+/// in case they committed offsets for new Topics (e.g. via an offsets reset tool).
+const REDISCOVER_EVERY_FETCHES: u32 = 10;
+
+/// How many [`BaseConsumer`]s [`KafkaCommittedOffsetsClient`] keeps across fetches.
+const MAX_CACHED_CONSUMERS: usize = 64;
+
+const MET_FETCH_NAME: &str = "committed_offsets_emitter_fetch_time_milliseconds";
+const MET_FETCH_HELP: &str =
+    "Time (ms) taken to fetch the committed offsets of all consumer groups in cluster";
+
+/// Where to source the committed offsets of Consumer Groups from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OffsetsSource {
+    /// Consume the `__consumer_offsets` internal topic: requires `READ` ACL on it.
+    #[default]
+    KonsumerOffsets,
+
+    /// Periodically fetch the committed offsets of each Consumer Group via the Kafka API.
+    ///
+    /// Commit timestamps are not available: the time each offset is first fetched is used instead.
+    AdminApi,
+}
+
+/// Emits [`KonsumerOffsetsData`] via a provided [`mpsc::channel`].
+///
+/// An alternative to [`crate::konsumer_offsets_data::KonsumerOffsetsDataEmitter`], for when
+/// `__consumer_offsets` cannot be consumed (e.g. lack of `READ` ACL): it regularly lists all the
+/// Consumer Groups, fetches the offsets each has committed via a [`CommittedOffsetsClient`],
+/// and emits those that changed since the previous fetch as [`KonsumerOffsetsData::OffsetCommit`].
+///
+/// If not authorized to list the Consumer Groups, and [`Subsystem::CommittedOffsets`]
+/// gets disabled as a result, it stops fetching altogether.
+///
+/// It shuts down when the provided [`CancellationToken`] is cancelled.
+pub struct CommittedOffsetsEmitter {
+    client: Arc<dyn CommittedOffsetsClient>,
+    fetch_interval: Duration,
+    jitter: Jitter,
+    excludes: GroupExcludes,
+    cs_reg: Arc<ClusterStatusRegister>,
+    subsystems: Arc<Subsystems>,
+
+    // Prometheus Metrics
+    metric_fetch: Histogram,
+}
+
+impl CommittedOffsetsEmitter {
+    /// Create a new [`CommittedOffsetsEmitter`]
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - Used to fetch the committed offsets (e.g. [`KafkaCommittedOffsetsClient`])
+    /// * `fetch_interval` - How often to fetch the committed offsets of all Consumer Groups
+    /// * `jitter` - Random jitter to apply to `fetch_interval`
+    /// * `excludes` - Consumer Groups to not fetch the committed offsets of
+    /// * `cs_reg` - Source of the Topic Partitions to query, for groups without members
+    /// * `subsystems` - Where to report the lack of permissions to fetch the committed offsets
+    /// * `metrics` - Where to register the internal metrics
+    pub fn new(
+        client: Arc<dyn CommittedOffsetsClient>,
+        fetch_interval: Duration,
+        jitter: Jitter,
+        excludes: GroupExcludes,
+        cs_reg: Arc<ClusterStatusRegister>,
+        subsystems: Arc<Subsystems>,
+        metrics: Arc<Registry>,
+    ) -> Self {
+        Self {
+            client,
+            fetch_interval,
+            jitter,
+            excludes,
+            cs_reg,
+            subsystems,
+            metric_fetch: register_histogram_with_registry!(
+                MET_FETCH_NAME,
+                MET_FETCH_HELP,
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_FETCH_NAME}")),
+        }
+    }
+}
+
+/// The offsets committed by each Consumer Group, as of the previous fetches.
+#[derive(Debug, Default)]
+struct FetchedOffsets {
+    /// Offset last fetched for each Topic Partition, with when it was first fetched.
+    by_group: HashMap<Interned, HashMap<TopicPartition, (i64, DateTime<Utc>)>>,
+
+    /// Fetches done so far.
+    fetches: u32,
+}
+
+impl FetchedOffsets {
+    /// Fetch the offsets committed by all the Consumer Groups, and return those that changed
+    /// since the previous fetch, timestamped at `now` (i.e. when they were first fetched).
+    ///
+    /// For groups with members, only the Topic Partitions assigned to them are queried.
+    /// For the others, only the Topic Partitions (among the given `cluster_tps`) of the Topics
+    /// they committed offsets for before: all of them, the first time a group is fetched, and
+    /// every [`REDISCOVER_EVERY_FETCHES`] fetches. Excluded groups are skipped.
+    ///
+    /// This is blocking: call it via [`tokio::task::spawn_blocking`].
+    fn fetch(
+        &mut self,
+        client: &dyn CommittedOffsetsClient,
+        excludes: &GroupExcludes,
+        cluster_tps: &[TopicPartition],
+        now: DateTime<Utc>,
+    ) -> KafkaResult<Vec<OffsetCommit>> {
+        let groups = client.list_groups(FETCH_TIMEOUT)?;
+
+        // Forget the groups gone, or excluded since
+        self.by_group.retain(|g, _| groups.groups.contains_key(g) && !excludes.is_excluded(g));
+        let rediscover = self.fetches.is_multiple_of(REDISCOVER_EVERY_FETCHES);
+        self.fetches = self.fetches.wrapping_add(1);
+
+        let mut commits = Vec::new();
+        for (group, gwm) in groups.groups.iter() {
+            if excludes.is_excluded(group) {
+                continue;
+            }
+
+            let mut tps = gwm
+                .members
+                .values()
+                .flat_map(|mwa| mwa.assignment.iter().cloned())
+                .collect::<Vec<TopicPartition>>();
+            if tps.is_empty() {
+                tps = match self.by_group.get(group) {
+                    Some(fetched) if !rediscover => {
+                        let topics = fetched.keys().map(|tp| &tp.topic).collect::<HashSet<_>>();
+                        cluster_tps
+                            .iter()
+                            .filter(|tp| topics.contains(&tp.topic))
+                            .cloned()
+                            .collect()
+                    },
+                    _ => cluster_tps.to_vec(),
+                };
+            }
+            if tps.is_empty() {
+                continue;
+            }
+
+            let committed = match client.fetch_committed_offsets(group, &tps, FETCH_TIMEOUT) {
+                Ok(committed) => committed,
+                Err(e) => {
+                    warn!("Failed to fetch committed offsets of Group '{group}': {e}");
+                    continue;
+                },
+            };
+
+            let fetched = self.by_group.entry(group.clone()).or_default();
+            for (tp, offset) in committed {
+                if fetched.get(&tp).is_some_and(|(o, _)| *o == offset) {
+                    continue;
+                }
+
+                commits.push(OffsetCommit {
+                    group: group.to_string(),
+                    topic: tp.topic.to_string(),
+                    partition: tp.partition as i32,
+                    offset,
+                    commit_timestamp: now,
+                    ..Default::default()
+                });
+                fetched.insert(tp, (offset, now));
+            }
+        }
+
+        Ok(commits)
+    }
+}
+
+/// A [`CommittedOffsetsClient`] querying the Group Coordinators, via a [`BaseConsumer`]
+/// acting on behalf of each Consumer Group: they never subscribe, nor commit.
+///
+/// Up to [`MAX_CACHED_CONSUMERS`] consumers are kept across fetches, so that they don't have
+/// to be re-created (with their threads and broker connections) every time: beyond that,
+/// they are created for each fetch. Those of the groups no longer listed are dropped.
+pub struct KafkaCommittedOffsetsClient {
+    client_config: ClientConfig,
+    consumers: Mutex<HashMap<String, Arc<BaseConsumer>>>,
+}
+
+impl KafkaCommittedOffsetsClient {
+    pub fn new(client_config: ClientConfig) -> Self {
+        Self {
+            client_config,
+            consumers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The [`BaseConsumer`] acting on behalf of the given Consumer Group.
+    fn consumer(&self, group: &str) -> KafkaResult<Arc<BaseConsumer>> {
+        let mut consumers = self.consumers.lock().expect("Consumers lock poisoned");
+        if let Some(consumer) = consumers.get(group) {
+            return Ok(consumer.clone());
+        }
+
+        let mut client_config = self.client_config.clone();
+        client_config.set("group.id", group);
+        client_config.set("enable.auto.commit", "false");
+        client_config.set_log_level(RDKafkaLogLevel::Warning);
+        let consumer = Arc::new(client_config.create::<BaseConsumer>()?);
+
+        if consumers.len() < MAX_CACHED_CONSUMERS {
+            consumers.insert(group.to_string(), consumer.clone());
+        }
+        Ok(consumer)
+    }
+}
+
+impl CommittedOffsetsClient for KafkaCommittedOffsetsClient {
+    fn list_groups(&self, timeout: Duration) -> KafkaResult<ConsumerGroups> {
+        let groups: ConsumerGroups = self
+            .consumer(KOMMITTED_CONSUMER_OFFSETS_CONSUMER)?
+            .fetch_group_list(None, timeout)?
+            .into();
+
+        self.consumers.lock().expect("Consumers lock poisoned").retain(|g, _| {
+            g == KOMMITTED_CONSUMER_OFFSETS_CONSUMER || groups.groups.contains_key(g.as_str())
+        });
+        Ok(groups)
+    }
+
+    fn fetch_committed_offsets(
+        &self,
+        group: &str,
+        tps: &[TopicPartition],
+        timeout: Duration,
+    ) -> KafkaResult<Vec<(TopicPartition, i64)>> {
+        let mut tpl = TopicPartitionList::new();
+        for tp in tps {
+            tpl.add_partition(&tp.topic, tp.partition as i32);
+        }
+
+        Ok(self
+            .consumer(group)?
+            .committed_offsets(tpl, timeout)?
+            .elements()
+            .iter()
+            .filter_map(|elem| match elem.offset() {
+                Offset::Offset(offset) => Some((
+                    TopicPartition::new(elem.topic().to_string(), elem.partition() as u32),
+                    offset,
+                )),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+impl Emitter for CommittedOffsetsEmitter {
+    type Emitted = KonsumerOffsetsData;
+
+    /// Spawn a new async task to run the business logic of this struct.
+    ///
+    /// When this emitter gets spawned, it returns a [`mpsc::Receiver`] for [`KonsumerOffsetsData`],
+    /// and a [`JoinHandle`] to help join on the task spawned internally.
+    /// The task concludes (joins) only ones the inner task of the emitter terminates.
+    ///
+    /// # Arguments
+    ///
+    /// * `shutdown_token`: A [`CancellationToken`] that, when cancelled, will make the internal loop terminate.
+    ///
+    fn spawn(
+        &self,
+        shutdown_token: CancellationToken,
+    ) -> (mpsc::Receiver<Self::Emitted>, JoinHandle<()>) {
+        let (sx, rx) = mpsc::channel::<Self::Emitted>(CHANNEL_SIZE);
+
+        let client = self.client.clone();
+        let excludes = self.excludes.clone();
+        let cs_reg = self.cs_reg.clone();
+        let subsystems = self.subsystems.clone();
+        let metric_fetch = self.metric_fetch.clone();
+        let mut interval = self.jitter.interval(self.fetch_interval);
+
+        let join_handle = tokio::spawn(async move {
+            let mut fetched = FetchedOffsets::default();
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = shutdown_token.cancelled() => {
+                        info!("Shutting down");
+                        break;
+                    },
+                }
+
+                let cluster_tps = cs_reg.get_topic_partitions().await;
+                let client = client.clone();
+                let excludes = excludes.clone();
+
+                let timer = metric_fetch.start_timer();
+                let res_commits;
+                (fetched, res_commits) = tokio::task::spawn_blocking(move || {
+                    let res = fetched.fetch(client.as_ref(), &excludes, &cluster_tps, Utc::now());
+                    (fetched, res)
+                })
+                .await
+                .expect("Task fetching committed offsets panicked");
+                timer.observe_duration();
+
+                match res_commits {
+                    Ok(commits) => {
+                        debug!("Fetched {} committed offsets", commits.len());
+                        for oc in commits {
+                            if let Err(e) =
+                                Self::emit(&sx, KonsumerOffsetsData::OffsetCommit(oc)).await
+                            {
+                                error!(
+                                    "Failed to emit {}: {e}",
+                                    std::any::type_name::<OffsetCommit>()
+                                );
+                            }
+                        }
+                    },
+                    Err(e)
+                        if subsystems.disable_if_unauthorized(Subsystem::CommittedOffsets, &e) =>
+                    {
+                        shutdown_token.cancelled().await;
+                        info!("Shutting down");
+                        break;
+                    },
+                    Err(e) => {
+                        error!("Failed to fetch committed offsets: {e}");
+                    },
+                }
+            }
+        });
+
+        (rx, join_handle)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration as ChronoDuration;
+
+    use super::*;
+    use crate::kafka_types::{Group, GroupWithMembers, Member, MemberWithAssignment};
+
+    fn tp(topic: &str, partition: u32) -> TopicPartition {
+        TopicPartition::new(topic, partition)
+    }
+
+    /// Lists Group `active` (with a Member assigned `t1:0`), the empty Group `idle`,
+    /// and Group `excluded-audit`: each committed the offsets in `committed`.
+    #[derive(Default)]
+    struct FakeCommittedOffsetsClient {
+        committed: Mutex<HashMap<(String, TopicPartition), i64>>,
+        queried: Mutex<Vec<(String, Vec<TopicPartition>)>>,
+    }
+
+    impl FakeCommittedOffsetsClient {
+        fn commit(&self, group: &str, tp: TopicPartition, offset: i64) {
+            self.committed.lock().unwrap().insert((group.to_string(), tp), offset);
+        }
+
+        fn queried(&self) -> Vec<(String, Vec<TopicPartition>)> {
+            let mut queried = std::mem::take(&mut *self.queried.lock().unwrap());
+            queried.sort();
+            queried
+        }
+    }
+
+    impl CommittedOffsetsClient for FakeCommittedOffsetsClient {
+        fn list_groups(&self, _timeout: Duration) -> KafkaResult<ConsumerGroups> {
+            let group = |name: &str, assignment: Vec<TopicPartition>| GroupWithMembers {
+                group: Group {
+                    name: Interned::new(name),
+                    ..Default::default()
+                },
+                members: assignment
+                    .into_iter()
+                    .map(|tp| {
+                        let member = Member {
+                            id: format!("{name}-1"),
+                            ..Default::default()
+                        };
+                        let mwa = MemberWithAssignment {
+                            member,
+                            assignment: HashSet::from([tp]),
+                        };
+                        (mwa.member.id.clone(), mwa)
+                    })
+                    .collect(),
+            };
+
+            Ok([
+                group("active", vec![tp("t1", 0)]),
+                group("idle", vec![]),
+                group("excluded-audit", vec![]),
+            ]
+            .into_iter()
+            .collect())
+        }
+
+        fn fetch_committed_offsets(
+            &self,
+            group: &str,
+            tps: &[TopicPartition],
+            _timeout: Duration,
+        ) -> KafkaResult<Vec<(TopicPartition, i64)>> {
+            let mut tps = tps.to_vec();
+            tps.sort();
+            self.queried.lock().unwrap().push((group.to_string(), tps.clone()));
+
+            let committed = self.committed.lock().unwrap();
+            Ok(tps
+                .into_iter()
+                .filter_map(|tp| {
+                    committed.get(&(group.to_string(), tp.clone())).map(|offset| (tp, *offset))
+                })
+                .collect())
+        }
+    }
+
+    fn excludes() -> GroupExcludes {
+        GroupExcludes::new(&[regex::Regex::new("^excluded-").unwrap()], false)
+    }
+
+    #[test]
+    fn emits_only_changed_offsets_timestamped_when_first_fetched() {
+        let client = FakeCommittedOffsetsClient::default();
+        client.commit("active", tp("t1", 0), 10);
+        client.commit("idle", tp("t2", 0), 20);
+        let cluster_tps = vec![tp("t1", 0), tp("t2", 0)];
+        let mut fetched = FetchedOffsets::default();
+        let t0 = Utc::now();
+        let at = |secs| t0 + ChronoDuration::seconds(secs);
+
+        let commits = fetched.fetch(&client, &excludes(), &cluster_tps, at(0)).unwrap();
+        let mut offsets: Vec<(String, i64)> =
+            commits.iter().map(|oc| (oc.group.clone(), oc.offset)).collect();
+        offsets.sort();
+        assert_eq!(offsets, vec![("active".to_string(), 10), ("idle".to_string(), 20)]);
+        assert!(commits.iter().all(|oc| oc.commit_timestamp == at(0)));
+
+        // Nothing committed since: nothing to emit
+        assert!(fetched.fetch(&client, &excludes(), &cluster_tps, at(30)).unwrap().is_empty());
+
+        client.commit("active", tp("t1", 0), 15);
+        let commits = fetched.fetch(&client, &excludes(), &cluster_tps, at(60)).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!((commits[0].offset, commits[0].commit_timestamp), (15, at(60)));
+    }
+
+    #[test]
+    fn queries_only_the_topics_committed_to_before() {
+        let client = FakeCommittedOffsetsClient::default();
+        client.commit("idle", tp("t2", 0), 20);
+        let cluster_tps = vec![tp("t1", 0), tp("t2", 0), tp("t2", 1), tp("t3", 0)];
+        let mut fetched = FetchedOffsets::default();
+
+        // First time: all the Topic Partitions, for the group without members
+        fetched.fetch(&client, &excludes(), &cluster_tps, Utc::now()).unwrap();
+        assert_eq!(
+            client.queried(),
+            vec![
+                ("active".to_string(), vec![tp("t1", 0)]),
+                ("idle".to_string(), cluster_tps.clone()),
+            ]
+        );
+
+        // Then, only those of the Topics it committed to
+        for _ in 1..REDISCOVER_EVERY_FETCHES {
+            fetched.fetch(&client, &excludes(), &cluster_tps, Utc::now()).unwrap();
+            assert_eq!(client.queried()[1], ("idle".to_string(), vec![tp("t2", 0), tp("t2", 1)]));
+        }
+
+        // Until it's time to look for new ones
+        fetched.fetch(&client, &excludes(), &cluster_tps, Utc::now()).unwrap();
+        assert_eq!(client.queried()[1], ("idle".to_string(), cluster_tps));
+    }
+}
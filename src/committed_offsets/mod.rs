@@ -0,0 +1,44 @@
+// Inner module
+mod emitter;
+
+use std::sync::Arc;
+
+use konsumer_offsets::KonsumerOffsetsData;
+use prometheus::Registry;
+use rdkafka::ClientConfig;
+use tokio::sync::mpsc::Receiver;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::cluster_status::ClusterStatusRegister;
+use crate::consumer_groups::GroupExcludes;
+use crate::internals::{Emitter, Jitter, Subsystems};
+
+pub use emitter::{CommittedOffsetsEmitter, KafkaCommittedOffsetsClient, OffsetsSource};
+
+#[allow(clippy::too_many_arguments)]
+pub fn init(
+    admin_client_config: ClientConfig,
+    fetch_interval: Duration,
+    jitter: Jitter,
+    excludes: GroupExcludes,
+    cs_reg: Arc<ClusterStatusRegister>,
+    subsystems: Arc<Subsystems>,
+    shutdown_token: CancellationToken,
+    metrics: Arc<Registry>,
+) -> (Receiver<KonsumerOffsetsData>, JoinHandle<()>) {
+    let committed_offsets_emitter = CommittedOffsetsEmitter::new(
+        Arc::new(KafkaCommittedOffsetsClient::new(admin_client_config)),
+        fetch_interval,
+        jitter,
+        excludes,
+        cs_reg,
+        subsystems,
+        metrics,
+    );
+    let (co_rx, co_join) = committed_offsets_emitter.spawn(shutdown_token);
+
+    debug!("Initialized");
+    (co_rx, co_join)
+}
@@ -0,0 +1,82 @@
+//! Configuration file, for what doesn't fit the command line (e.g. alerting rules).
+//!
+//! The file is in [TOML](https://toml.io/) format, and it's passed via `--config`.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+use crate::alerts::AlertsConfig;
+
+/// Possible errors when loading the configuration file.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Unable to read configuration file '{0}': {1}")]
+    Read(PathBuf, std::io::Error),
+
+    #[error("Unable to parse configuration file '{0}': {1}")]
+    Parse(PathBuf, toml::de::Error),
+
+    #[error("Invalid configuration file '{0}': {1}")]
+    Invalid(PathBuf, String),
+}
+
+pub type ConfigResult<T> = Result<T, ConfigError>;
+
+/// Content of the configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Alerting rules, and the webhooks they notify.
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+}
+
+impl Config {
+    /// Load and validate the configuration file at the given path.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> ConfigResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let content =
+            std::fs::read_to_string(&path).map_err(|e| ConfigError::Read(path.clone(), e))?;
+        let config: Config =
+            toml::from_str(&content).map_err(|e| ConfigError::Parse(path.clone(), e))?;
+        config.alerts.validate().map_err(|e| ConfigError::Invalid(path.clone(), e))?;
+
+        trace!("Loaded configuration from '{}':\n{:#?}", path.display(), config);
+        Ok(config)
+    }
+}
+
+/// Deserialize a [`Duration`] from a human-friendly string, like `15s` or `1m 30s`.
+pub(crate) fn deserialize_duration<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+    let s = String::deserialize(d)?;
+    humantime::parse_duration(&s).map_err(serde::de::Error::custom)
+}
+
+/// Deserialize an optional [`Duration`] from a human-friendly string, like `15s` or `1m 30s`.
+pub(crate) fn deserialize_opt_duration<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Option<Duration>, D::Error> {
+    Option::<String>::deserialize(d)?
+        .map(|s| humantime::parse_duration(&s).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Deserialize a [`Regex`].
+pub(crate) fn deserialize_regex<'de, D: Deserializer<'de>>(d: D) -> Result<Regex, D::Error> {
+    let s = String::deserialize(d)?;
+    Regex::new(&s).map_err(serde::de::Error::custom)
+}
+
+/// Deserialize an optional [`Regex`].
+pub(crate) fn deserialize_opt_regex<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Option<Regex>, D::Error> {
+    Option::<String>::deserialize(d)?
+        .map(|s| Regex::new(&s).map_err(serde::de::Error::custom))
+        .transpose()
+}
@@ -7,6 +7,24 @@ pub(crate) const KONSUMER_OFFSETS_DATA_TOPIC: &str = "__consumer_offsets";
 pub(crate) const KOMMITTED_CONSUMER_OFFSETS_CONSUMER: &str =
     "__kommitted__consumer_offsets_consumer";
 
+/// This is appended to the Kafka Client identifier (`client.id`), to identify
+/// the Consumer inside the `konsumer_offsets_data` module.
+pub(crate) const KOMMITTED_CONSUMER_OFFSETS_CLIENT_ID_SUFFIX: &str = "-consumer-offsets";
+
+/// This is the Consumer Group (`group.id`) value used by
+/// the Consumer of the MirrorMaker 2 offset-syncs topic, inside the `mirror` module.
+pub(crate) const KOMMITTED_MIRROR_OFFSET_SYNCS_CONSUMER: &str =
+    "__kommitted__mirror_offset_syncs_consumer";
+
+/// This is the Consumer Group (`group.id`) value used by
+/// the Consumer sampling the size of the latest records of the consumed Topics.
+pub(crate) const KOMMITTED_RECORD_SIZES_CONSUMER: &str = "__kommitted__record_sizes_consumer";
+
+/// The default topic to coordinate leadership on, in high-availability mode.
+///
+/// See [`crate::cli::Cli`]'s `ha_topic`.
+pub(crate) const DEFAULT_HA_TOPIC: &str = "__kommitted_leadership";
+
 /// The default host to listen on when launching the HTTP server.
 pub(crate) const DEFAULT_HTTP_HOST: &str = "127.0.0.1";
 
@@ -25,5 +43,210 @@ pub(crate) const DEFAULT_OFFSETS_HISTORY: &str = "3600"; //< `usize` after parsi
 /// See [`crate::Cli`]'s `offsets_history_ready_at`.
 pub(crate) const DEFAULT_OFFSETS_HISTORY_READY_AT: &str = "0.3"; //< `f64` after parsing
 
+/// The default position to start consuming `__consumer_offsets` from.
+///
+/// See [`crate::cli::Cli`]'s `konsumer_offsets_start`.
+pub(crate) const DEFAULT_KONSUMER_OFFSETS_START: &str = "earliest"; //< `KonsumerOffsetsStart` after parsing
+
+/// The default interval to persist the offsets history to disk.
+///
+/// See [`crate::cli::Cli`]'s `offsets_history_persist_interval`.
+pub(crate) const DEFAULT_OFFSETS_HISTORY_PERSIST_INTERVAL: &str = "60s"; //< `Duration` after parsing
+
+/// The default interval to persist the committed offsets of Consumer Groups to disk.
+///
+/// See [`crate::cli::Cli`]'s `lag_persist_interval`.
+pub(crate) const DEFAULT_LAG_PERSIST_INTERVAL: &str = "60s"; //< `Duration` after parsing
+
+/// The default time the cluster can't be reached via a `--brokers` endpoint, before failing over.
+///
+/// See [`crate::cli::Cli`]'s `failover_after`.
+pub(crate) const DEFAULT_FAILOVER_AFTER: &str = "2m"; //< `Duration` after parsing
+
+/// The default interval to persist the audit log of cluster topology changes to disk.
+///
+/// See [`crate::cli::Cli`]'s `cluster_changes_persist_interval`.
+pub(crate) const DEFAULT_CLUSTER_CHANGES_PERSIST_INTERVAL: &str = "60s"; //< `Duration` after parsing
+
+/// The default interval to fetch committed offsets, when sourcing them via the Admin API.
+///
+/// See [`crate::cli::Cli`]'s `admin_api_interval`.
+pub(crate) const DEFAULT_ADMIN_API_INTERVAL: &str = "30s"; //< `Duration` after parsing
+
+/// The default interval librdkafka emits the statistics of the Kafka clients at.
+///
+/// See [`crate::cli::Cli`]'s `rdkafka_stats_interval`.
+pub(crate) const DEFAULT_RDKAFKA_STATS_INTERVAL: &str = "30s"; //< `Duration` after parsing
+
+/// The default random jitter, applied to the intervals of the periodic work of the service.
+///
+/// See [`crate::cli::Cli`]'s `interval_jitter`.
+pub(crate) const DEFAULT_INTERVAL_JITTER: &str = "0.0"; //< `f64` after parsing
+
+/// The default max amount of Topic Partitions to fetch the offset watermarks of, in a single batch.
+///
+/// See [`crate::cli::Cli`]'s `watermarks_batch_size`.
+pub(crate) const DEFAULT_WATERMARKS_BATCH_SIZE: &str = "1000"; //< `usize` after parsing
+
+/// The default max amount of batches of offset watermarks to fetch concurrently.
+///
+/// See [`crate::cli::Cli`]'s `watermarks_concurrency`.
+pub(crate) const DEFAULT_WATERMARKS_CONCURRENCY: &str = "4"; //< `usize` after parsing
+
+/// The default time a Topic Partition has to receive no new offsets for, to be considered idle.
+///
+/// See [`crate::cli::Cli`]'s `idle_window`.
+pub(crate) const DEFAULT_IDLE_WINDOW: &str = "10s"; //< `Duration` after parsing
+
+/// The Consumer Groups excluded by default (regexes), as internal to Kafka, to its ecosystem
+/// (e.g. Kafka Connect workers, Schema Registry), or to this service.
+///
+/// See [`crate::cli::Cli`]'s `no_default_group_excludes`.
+pub(crate) const DEFAULT_GROUP_EXCLUDES: &[&str] =
+    &["^__", "^_confluent", "^connect-cluster$", "^schema-registry$"];
+
+/// The default time the lag of a vanished, or empty, Consumer Group is kept for, in case it comes back.
+///
+/// See [`crate::cli::Cli`]'s `group_absence_grace`.
+pub(crate) const DEFAULT_GROUP_ABSENCE_GRACE: &str = "0s"; //< `Duration` after parsing
+
+/// The default Time Lag that, when crossed, is reported as a lag event.
+///
+/// See [`crate::cli::Cli`]'s `lag_events_threshold`.
+pub(crate) const DEFAULT_LAG_EVENTS_THRESHOLD: &str = "60s"; //< `Duration` after parsing
+
+/// The default amount of commits, per Topic Partition, to evaluate a Consumer Group status over.
+///
+/// See [`crate::cli::Cli`]'s `status_window`.
+pub(crate) const DEFAULT_STATUS_WINDOW: &str = "10"; //< `usize` after parsing
+
+/// The default amount of usual commit intervals without commits, for a consumer to be considered stopped.
+///
+/// See [`crate::cli::Cli`]'s `stopped_commit_intervals`.
+pub(crate) const DEFAULT_STOPPED_COMMIT_INTERVALS: &str = "10"; //< `u32` after parsing
+
+/// The default Offset Lag above which a Consumer Group is counted as lagging, cluster-wide.
+///
+/// See [`crate::cli::Cli`]'s `lagging_group_threshold`.
+pub(crate) const DEFAULT_LAGGING_GROUP_THRESHOLD: &str = "0"; //< `u64` after parsing
+
+/// The default percentage of group partitions whose lag must be known, before serving metrics.
+///
+/// See [`crate::cli::Cli`]'s `min_data_coverage`.
+pub(crate) const DEFAULT_MIN_DATA_COVERAGE: &str = "0.0"; //< `f64` after parsing
+
+/// The default max time to wait for `min_data_coverage`, before serving metrics anyway.
+///
+/// See [`crate::cli::Cli`]'s `min_data_coverage_timeout`.
+pub(crate) const DEFAULT_MIN_DATA_COVERAGE_TIMEOUT: &str = "10m"; //< `Duration` after parsing
+
+/// The default time span of the lag history kept in memory, for each Consumer Group.
+///
+/// See [`crate::cli::Cli`]'s `lag_history_retention`.
+pub(crate) const DEFAULT_LAG_HISTORY_RETENTION: &str = "60m"; //< `Duration` after parsing
+
+/// The default interval between points of the lag history of each Consumer Group.
+///
+/// See [`crate::cli::Cli`]'s `lag_history_resolution`.
+pub(crate) const DEFAULT_LAG_HISTORY_RESOLUTION: &str = "10s"; //< `Duration` after parsing
+
+/// The default time span of the lag history to evaluate the lag trend of each Consumer Group over.
+///
+/// See [`crate::cli::Cli`]'s `lag_trend_window`.
+pub(crate) const DEFAULT_LAG_TREND_WINDOW: &str = "5m"; //< `Duration` after parsing
+
+/// The default share of the time each Consumer Group is expected to stay within its Time Lag target.
+///
+/// See [`crate::cli::Cli`]'s `lag_slo_objective`.
+pub(crate) const DEFAULT_LAG_SLO_OBJECTIVE: &str = "0.99"; //< `f64` after parsing
+
+/// The default amount of the laggiest Topic Partitions to ground-truth the Time Lag estimates of.
+///
+/// See [`crate::cli::Cli`]'s `ground_truth_partitions`.
+pub(crate) const DEFAULT_GROUND_TRUTH_PARTITIONS: &str = "10"; //< `usize` after parsing
+
+/// The default amount of the latest records of each Topic to sample, to estimate the lag in bytes.
+///
+/// See [`crate::cli::Cli`]'s `lag_bytes_sample_records`.
+pub(crate) const DEFAULT_LAG_BYTES_SAMPLE_RECORDS: &str = "100"; //< `usize` after parsing
+
+/// The default time to collect lag data for, before previewing the metrics.
+///
+/// See [`crate::cli::Command::PreviewMetrics`].
+pub(crate) const DEFAULT_PREVIEW_WARM_UP: &str = "60s"; //< `Duration` after parsing
+
+/// The default time to collect lag data for, before printing the lag snapshot.
+///
+/// See [`crate::cli::Command::Snapshot`].
+pub(crate) const DEFAULT_SNAPSHOT_WARM_UP: &str = "30s"; //< `Duration` after parsing
+
+/// The default interval to refresh the terminal view of the lag.
+///
+/// See [`crate::cli::Command::Top`].
+pub(crate) const DEFAULT_TOP_REFRESH_INTERVAL: &str = "2s"; //< `Duration` after parsing
+
+/// The default Time Lag above which a Consumer Group is reported as unavailable.
+///
+/// See [`crate::cli::Command::Report`].
+pub(crate) const DEFAULT_REPORT_THRESHOLD: &str = "60s"; //< `Duration` after parsing
+
+/// The default interval to push metrics to a Prometheus remote-write endpoint.
+///
+/// See [`crate::cli::Cli`]'s `remote_write_interval`.
+pub(crate) const DEFAULT_REMOTE_WRITE_INTERVAL: &str = "15s"; //< `Duration` after parsing
+
+/// The default interval to emit metrics to a StatsD server.
+///
+/// See [`crate::cli::Cli`]'s `statsd_interval`.
+pub(crate) const DEFAULT_STATSD_INTERVAL: &str = "10s"; //< `Duration` after parsing
+
+/// The default interval to write lag measurements to InfluxDB.
+///
+/// See [`crate::cli::Cli`]'s `influx_interval`.
+pub(crate) const DEFAULT_INFLUX_INTERVAL: &str = "10s"; //< `Duration` after parsing
+
+/// The default interval to write lag measurements to Graphite.
+///
+/// See [`crate::cli::Cli`]'s `graphite_interval`.
+pub(crate) const DEFAULT_GRAPHITE_INTERVAL: &str = "10s"; //< `Duration` after parsing
+
+/// The default interval to produce lag records to a Kafka topic.
+///
+/// See [`crate::cli::Cli`]'s `lag_topic_interval`.
+pub(crate) const DEFAULT_LAG_TOPIC_INTERVAL: &str = "10s"; //< `Duration` after parsing
+
+/// The default interval to dump lag snapshots to files.
+///
+/// See [`crate::cli::Cli`]'s `dump_interval`.
+pub(crate) const DEFAULT_DUMP_INTERVAL: &str = "60s"; //< `Duration` after parsing
+
+/// The default interval to export metrics to an OpenTelemetry Protocol (OTLP) endpoint.
+///
+/// See [`crate::cli::Cli`]'s `otlp_interval`.
+#[cfg(feature = "otlp")]
+pub(crate) const DEFAULT_OTLP_INTERVAL: &str = "15s"; //< `Duration` after parsing
+
+/// The default interval to publish metrics to AWS CloudWatch.
+///
+/// See [`crate::cli::Cli`]'s `cloudwatch_interval`.
+#[cfg(feature = "cloudwatch")]
+pub(crate) const DEFAULT_CLOUDWATCH_INTERVAL: &str = "60s"; //< `Duration` after parsing
+
+/// The default AWS CloudWatch namespace to publish metrics under.
+///
+/// See [`crate::cli::Cli`]'s `cloudwatch_namespace`.
+#[cfg(feature = "cloudwatch")]
+pub(crate) const DEFAULT_CLOUDWATCH_NAMESPACE: &str = "Kommitted";
+
+/// The default size the log file is rotated at.
+///
+/// See [`crate::cli::Cli`]'s `log_file_max_size`.
+pub(crate) const DEFAULT_LOG_FILE_MAX_SIZE: &str = "100MiB"; //< `usize` after parsing
+
+/// The default amount of rotated log files to keep.
+///
+/// See [`crate::cli::Cli`]'s `log_file_keep`.
+pub(crate) const DEFAULT_LOG_FILE_KEEP: &str = "7"; //< `usize` after parsing
+
 /// The default `cluster_id` value, if none is provided (either via CLI override, nor Cluster configuration).
 pub(crate) const DEFAULT_CLUSTER_ID: &str = "__not-set__";
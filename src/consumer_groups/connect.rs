@@ -0,0 +1,37 @@
+/// Prefix of the Consumer Groups of Kafka Connect sink connectors (i.e. `connect-<connector>`),
+/// unless overridden via `consumer.override.group.id`.
+const CONNECT_GROUP_PREFIX: &str = "connect-";
+
+/// Protocol Type of the groups of Kafka Connect workers: they coordinate the workers,
+/// and don't consume anything themselves (e.g. the default `connect-cluster`).
+const CONNECT_WORKERS_PROTOCOL_TYPE: &str = "connect";
+
+/// Name of the Kafka Connect sink connector, that the given Consumer Group belongs to.
+///
+/// Returns `None` if the Consumer Group doesn't follow the naming of Kafka Connect,
+/// or if it's a group of Kafka Connect workers.
+///
+/// # Arguments
+///
+/// * `group` - Name of the Consumer Group
+/// * `protocol_type` - Protocol Type of the Consumer Group, if known (empty otherwise)
+pub fn connector_of<'a>(group: &'a str, protocol_type: &str) -> Option<&'a str> {
+    if protocol_type == CONNECT_WORKERS_PROTOCOL_TYPE {
+        return None;
+    }
+    group.strip_prefix(CONNECT_GROUP_PREFIX).filter(|c| !c.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detects_sink_connectors() {
+        assert_eq!(connector_of("connect-s3-sink", "consumer"), Some("s3-sink"));
+        assert_eq!(connector_of("connect-s3-sink", ""), Some("s3-sink"));
+        assert_eq!(connector_of("connect-cluster", "connect"), None);
+        assert_eq!(connector_of("connect-", "consumer"), None);
+        assert_eq!(connector_of("payments", "consumer"), None);
+    }
+}
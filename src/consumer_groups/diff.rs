@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::emitter::ConsumerGroups;
+
+use crate::kafka_types::{GroupWithMembers, Interned};
+
+/// Difference between 2 consecutive [`ConsumerGroups`].
+///
+/// Applying it to the former, results in the latter.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ConsumerGroupsDiff {
+    /// Consumer Groups that were added.
+    pub(crate) added: HashMap<Interned, GroupWithMembers>,
+
+    /// Consumer Groups that changed (e.g. state, members, assignments).
+    pub(crate) changed: HashMap<Interned, GroupWithMembers>,
+
+    /// Names of the Consumer Groups that were removed.
+    pub(crate) removed: Vec<Interned>,
+}
+
+impl ConsumerGroupsDiff {
+    /// Diff the `prev` and `next` [`ConsumerGroups`].
+    pub fn between(prev: &ConsumerGroups, next: &ConsumerGroups) -> Self {
+        let mut diff = Self::default();
+
+        for (g, gwm) in next.groups.iter() {
+            match prev.groups.get(g) {
+                None => {
+                    diff.added.insert(g.clone(), gwm.clone());
+                },
+                Some(prev_gwm) if prev_gwm != gwm => {
+                    diff.changed.insert(g.clone(), gwm.clone());
+                },
+                Some(_) => {},
+            }
+        }
+        diff.removed =
+            prev.groups.keys().filter(|g| !next.groups.contains_key(*g)).cloned().collect();
+
+        diff
+    }
+
+    /// Whether the Consumer Groups are unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// Amount of Consumer Groups added, changed and removed.
+    pub fn len(&self) -> usize {
+        self.added.len() + self.changed.len() + self.removed.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kafka_types::Group;
+
+    fn groups(groups: &[(&str, &str)]) -> ConsumerGroups {
+        ConsumerGroups {
+            groups: groups
+                .iter()
+                .map(|(name, state)| {
+                    (
+                        Interned::new(name),
+                        GroupWithMembers {
+                            group: Group {
+                                name: Interned::new(name),
+                                state: state.to_string(),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn unchanged() {
+        let cg = groups(&[("a", "Stable"), ("b", "Empty")]);
+        assert!(ConsumerGroupsDiff::between(&cg, &cg).is_empty());
+    }
+
+    #[test]
+    fn added_changed_removed() {
+        let prev = groups(&[("a", "Stable"), ("b", "Stable"), ("c", "Empty")]);
+        let next = groups(&[("a", "Stable"), ("b", "PreparingRebalance"), ("d", "Stable")]);
+
+        let diff = ConsumerGroupsDiff::between(&prev, &next);
+        assert_eq!(diff.len(), 3);
+        assert_eq!(diff.added.keys().collect::<Vec<_>>(), vec!["d"]);
+        assert_eq!(diff.changed.keys().collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(diff.changed["b"].group.state, "PreparingRebalance");
+        assert_eq!(diff.removed, vec!["c".to_string()]);
+
+        // From nothing, everything is added
+        let diff = ConsumerGroupsDiff::between(&ConsumerGroups::default(), &next);
+        assert_eq!(diff.added.len(), 3);
+        assert!(diff.changed.is_empty() && diff.removed.is_empty());
+    }
+}
@@ -3,7 +3,14 @@ use prometheus::{
     register_histogram_with_registry, register_int_gauge_vec_with_registry,
     register_int_gauge_with_registry, Histogram, IntGauge, IntGaugeVec, Registry,
 };
-use rdkafka::{admin::AdminClient, client::DefaultClientContext, groups::GroupList, ClientConfig};
+use rdkafka::{
+    admin::AdminClient,
+    bindings as rdsys,
+    client::ClientContext,
+    error::KafkaResult,
+    groups::{GroupInfo, GroupList},
+    ClientConfig,
+};
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
@@ -11,24 +18,43 @@ use std::{
 use tokio::{
     sync::mpsc,
     task::JoinHandle,
-    time::{interval, Duration},
+    time::{Duration, MissedTickBehavior},
 };
 use tokio_util::sync::CancellationToken;
 
-use crate::constants::KOMMITTED_CONSUMER_OFFSETS_CONSUMER;
-use crate::internals::Emitter;
-use crate::kafka_types::{Group, GroupWithMembers, Member, MemberWithAssignment, TopicPartition};
-use crate::prometheus_metrics::LABEL_GROUP;
+use super::diff::ConsumerGroupsDiff;
+use super::excludes::GroupExcludes;
+
+use crate::internals::{
+    ClientStats, ClientStatsContext, Emitter, GroupsClient, Jitter, Subsystem, Subsystems,
+};
+use crate::kafka_types::{
+    Group, GroupProtocol, GroupWithMembers, Interned, Member, MemberWithAssignment, TopicPartition,
+};
+use crate::prometheus_metrics::{
+    LABEL_BROKER_ID, LABEL_GROUP, LABEL_PROTOCOL, LABEL_PROTOCOL_TYPE,
+};
 
 const CHANNEL_SIZE: usize = 5;
 
 const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
 const FETCH_INTERVAL: Duration = Duration::from_secs(60);
+const DESCRIBE_INTERVAL: Duration = Duration::from_secs(5);
+
+const GROUP_STATE_STABLE: &str = "Stable";
+const GROUP_STATE_PREPARING_REBALANCE: &str = "PreparingRebalance";
+const GROUP_STATE_COMPLETING_REBALANCE: &str = "CompletingRebalance";
+const GROUP_STATE_DEAD: &str = "Dead";
 
 const MET_TOT_NAME: &str = "consumer_groups_total";
 const MET_TOT_HELP: &str = "Consumer groups currently in the cluster";
 const MET_MEMBERS_TOT_NAME: &str = "consumer_groups_members_total";
 const MET_MEMBERS_TOT_HELP: &str = "Members of consumer groups currently in the cluster";
+const MET_COORDINATOR_NAME: &str = "consumer_groups_coordinator";
+const MET_COORDINATOR_HELP: &str = "Broker coordinating the consumer group (always 1)";
+const MET_INFO_NAME: &str = "consumer_groups_info";
+const MET_INFO_HELP: &str =
+    "Protocol type (e.g. 'consumer', 'connect') and protocol (e.g. 'range') of the consumer group (always 1)";
 const MET_FETCH_NAME: &str = "consumer_groups_emitter_fetch_time_milliseconds";
 const MET_FETCH_HELP: &str =
     "Time (ms) taken to fetch information about all consumer groups in cluster";
@@ -41,7 +67,7 @@ const MET_CH_CAP_HELP: &str =
 /// This reflects the internal state of Kafka, and it's active Consumer Groups.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct ConsumerGroups {
-    pub(crate) groups: HashMap<String, GroupWithMembers>,
+    pub(crate) groups: HashMap<Interned, GroupWithMembers>,
 }
 
 impl From<GroupList> for ConsumerGroups {
@@ -51,11 +77,6 @@ impl From<GroupList> for ConsumerGroups {
         };
 
         for g in gl.groups() {
-            // Ignore own consumer of `__consumer_offsets` topic
-            if g.name() == KOMMITTED_CONSUMER_OFFSETS_CONSUMER {
-                continue;
-            }
-
             let mut res_members = HashMap::with_capacity(g.members().len());
 
             for m in g.members() {
@@ -66,6 +87,7 @@ impl From<GroupList> for ConsumerGroups {
                             id: m.id().to_string(),
                             client_id: m.client_id().to_string(),
                             client_host: m.client_host().to_string(),
+                            group_instance_id: None,
                         },
                         assignment: if let Some(assignment_bytes) = m.assignment() {
                             match ConsumerProtocolAssignment::try_from(assignment_bytes) {
@@ -86,14 +108,22 @@ impl From<GroupList> for ConsumerGroups {
                 );
             }
 
+            let name = Interned::new(g.name());
             res.groups.insert(
-                g.name().to_string(),
+                name.clone(),
                 GroupWithMembers {
                     group: Group {
-                        name: g.name().to_string(),
+                        name,
                         protocol: g.protocol().to_string(),
                         protocol_type: g.protocol_type().to_string(),
                         state: g.state().to_string(),
+                        group_protocol: if is_next_gen(g) {
+                            GroupProtocol::Consumer
+                        } else {
+                            GroupProtocol::Classic
+                        },
+                        coordinator_id: coordinator_id(g),
+                        ..Default::default()
                     },
                     members: res_members,
                 },
@@ -104,18 +134,55 @@ impl From<GroupList> for ConsumerGroups {
     }
 }
 
-/// Emits [`ConsumerGroups`] via a provided [`mpsc::channel`].
+impl FromIterator<GroupWithMembers> for ConsumerGroups {
+    fn from_iter<I: IntoIterator<Item = GroupWithMembers>>(iter: I) -> Self {
+        Self {
+            groups: iter.into_iter().map(|gwm| (gwm.group.name.clone(), gwm)).collect(),
+        }
+    }
+}
+
+impl<C: ClientContext + 'static> GroupsClient for AdminClient<C> {
+    fn fetch_group_list(
+        &self,
+        group: Option<&str>,
+        timeout: Duration,
+    ) -> KafkaResult<ConsumerGroups> {
+        self.inner().fetch_group_list(group, timeout).map(ConsumerGroups::from)
+    }
+}
+
+/// Emits [`ConsumerGroupsDiff`] via a provided [`mpsc::channel`].
+///
+/// It wraps an Admin Kafka Client (or any other [`GroupsClient`]), regularly requests it
+/// for the cluster consumer groups list,
+/// and then emits how it differs from the previous one as [`ConsumerGroupsDiff`]
+/// (i.e. the first one emitted adds all the [`ConsumerGroups`]).
+///
+/// Consumer Groups that are settling (e.g. rebalancing) are described again more frequently,
+/// until they settle: this way, which Member owns which Topic Partition is known promptly,
+/// without waiting for the next list, or for a `GroupMetadata` in `__consumer_offsets`.
 ///
-/// It wraps an Admin Kafka Client, regularly requests it for the cluster consumer groups list,
-/// and then emits it as [`ConsumerGroups`].
+/// Consumer Groups using the next-gen consumer rebalance protocol (KIP-848) are emitted
+/// with [`GroupProtocol::Consumer`] and no Members, as those can't be described when listing.
+///
+/// If not authorized to list the Consumer Groups, and [`Subsystem::ConsumerGroups`] gets disabled
+/// as a result, it stops fetching them altogether.
 ///
 /// It shuts down when the provided [`CancellationToken`] is cancelled.
 pub struct ConsumerGroupsEmitter {
     admin_client_config: ClientConfig,
+    excludes: GroupExcludes,
+    subsystems: Arc<Subsystems>,
+    client_stats: Arc<ClientStats>,
+    jitter: Jitter,
+    client: Option<Arc<dyn GroupsClient>>,
 
     // Prometheus Metrics
     metric_tot: IntGauge,
     metric_members_tot: IntGaugeVec,
+    metric_coordinator: IntGaugeVec,
+    metric_info: IntGaugeVec,
     metric_fetch: Histogram,
     metric_ch_cap: IntGauge,
 }
@@ -126,9 +193,25 @@ impl ConsumerGroupsEmitter {
     /// # Arguments
     ///
     /// * `admin_client_config` - Kafka admin client configuration, used to fetch Consumer Groups
-    pub fn new(admin_client_config: ClientConfig, metrics: Arc<Registry>) -> Self {
+    /// * `excludes` - Consumer Groups to not emit
+    /// * `subsystems` - Where to report the lack of permissions to list the Consumer Groups
+    /// * `client_stats` - Where to export the statistics of the Admin Client
+    /// * `jitter` - Random jitter to apply to the intervals between fetches
+    pub fn new(
+        admin_client_config: ClientConfig,
+        excludes: GroupExcludes,
+        subsystems: Arc<Subsystems>,
+        client_stats: Arc<ClientStats>,
+        jitter: Jitter,
+        metrics: Arc<Registry>,
+    ) -> Self {
         Self {
             admin_client_config,
+            excludes,
+            subsystems,
+            client_stats,
+            jitter,
+            client: None,
             metric_tot: register_int_gauge_with_registry!(MET_TOT_NAME, MET_TOT_HELP, metrics)
                 .unwrap_or_else(|_| panic!("Failed to create metric: {MET_TOT_NAME}")),
             metric_members_tot: register_int_gauge_vec_with_registry!(
@@ -138,6 +221,20 @@ impl ConsumerGroupsEmitter {
                 metrics
             )
             .unwrap_or_else(|_| panic!("Failed to create metric: {MET_MEMBERS_TOT_NAME}")),
+            metric_coordinator: register_int_gauge_vec_with_registry!(
+                MET_COORDINATOR_NAME,
+                MET_COORDINATOR_HELP,
+                &[LABEL_GROUP, LABEL_BROKER_ID],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_COORDINATOR_NAME}")),
+            metric_info: register_int_gauge_vec_with_registry!(
+                MET_INFO_NAME,
+                MET_INFO_HELP,
+                &[LABEL_GROUP, LABEL_PROTOCOL_TYPE, LABEL_PROTOCOL],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_INFO_NAME}")),
             metric_fetch: register_histogram_with_registry!(
                 MET_FETCH_NAME,
                 MET_FETCH_HELP,
@@ -152,14 +249,21 @@ impl ConsumerGroupsEmitter {
             .unwrap_or_else(|_| panic!("Failed to create metric: {MET_CH_CAP_NAME}")),
         }
     }
+
+    /// Fetch the Consumer Groups via the given [`GroupsClient`],
+    /// instead of an Admin Kafka Client created out of the configuration.
+    pub fn with_client(mut self, client: Arc<dyn GroupsClient>) -> Self {
+        self.client = Some(client);
+        self
+    }
 }
 
 impl Emitter for ConsumerGroupsEmitter {
-    type Emitted = ConsumerGroups;
+    type Emitted = ConsumerGroupsDiff;
 
     /// Spawn a new async task to run the business logic of this struct.
     ///
-    /// When this emitter gets spawned, it returns a [`mpsc::Receiver`] for [`ConsumerGroups`],
+    /// When this emitter gets spawned, it returns a [`mpsc::Receiver`] for [`ConsumerGroupsDiff`],
     /// and a [`JoinHandle`] to help join on the task spawned internally.
     /// The task concludes (joins) only ones the inner task of the emitter terminates.
     ///
@@ -171,53 +275,154 @@ impl Emitter for ConsumerGroupsEmitter {
         &self,
         shutdown_token: CancellationToken,
     ) -> (mpsc::Receiver<Self::Emitted>, JoinHandle<()>) {
-        let admin_client: AdminClient<DefaultClientContext> =
-            self.admin_client_config.create().expect("Failed to allocate Admin Client");
+        let client = self.client.clone().unwrap_or_else(|| {
+            let admin_client: AdminClient<ClientStatsContext> = self
+                .client_stats
+                .create(&self.admin_client_config, "consumer_groups")
+                .expect("Failed to allocate Admin Client");
+            Arc::new(admin_client)
+        });
 
         let (sx, rx) = mpsc::channel::<Self::Emitted>(CHANNEL_SIZE);
 
         // Clone metrics so they can be used in the spawned future
         let metric_cg = self.metric_tot.clone();
         let metric_cg_members = self.metric_members_tot.clone();
+        let metric_cg_coordinator = self.metric_coordinator.clone();
+        let metric_cg_info = self.metric_info.clone();
         let metric_cg_fetch = self.metric_fetch.clone();
         let metric_cg_ch_cap = self.metric_ch_cap.clone();
 
+        let excludes = self.excludes.clone();
+        let subsystems = self.subsystems.clone();
+        let mut fetch_interval = self.jitter.interval(FETCH_INTERVAL);
+        let mut describe_interval = self.jitter.interval(DESCRIBE_INTERVAL);
+        describe_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
         let join_handle = tokio::spawn(async move {
-            let mut interval = interval(FETCH_INTERVAL);
+            let mut latest = ConsumerGroups::default();
 
             loop {
-                // Fetch Consumer Groups and update timer metrics
-                let timer = metric_cg_fetch.start_timer();
-                let res_cg = admin_client
-                    .inner()
-                    .fetch_group_list(None, FETCH_TIMEOUT)
-                    .map(Self::Emitted::from);
-                timer.observe_duration();
-
-                match res_cg {
-                    Ok(cg) => {
-                        // Update group and group member metrics
-                        metric_cg.set(cg.groups.len() as i64);
-                        for (g, gm) in cg.groups.iter() {
-                            metric_cg_members.with_label_values(&[g]).set(gm.members.len() as i64);
-                        }
-                        // Update channel capacity metric
-                        metric_cg_ch_cap.set(sx.capacity() as i64);
-
-                        tokio::select! {
-                            res = Self::emit_with_interval(&sx, cg, &mut interval) => {
-                                if let Err(e) = res {
-                                    error!("Failed to emit {}: {e}", std::any::type_name::<ConsumerGroups>());
-                                }
-                            },
-                            _ = shutdown_token.cancelled() => {
+                let settling = latest
+                    .groups
+                    .iter()
+                    .filter(|(_, gwm)| is_settling(gwm))
+                    .map(|(g, _)| g.clone())
+                    .collect::<Vec<Interned>>();
+
+                let mut cg = tokio::select! {
+                    _ = fetch_interval.tick() => {
+                        // Fetch Consumer Groups and update timer metrics
+                        let timer = metric_cg_fetch.start_timer();
+                        let res_cg = client.fetch_group_list(None, FETCH_TIMEOUT);
+                        timer.observe_duration();
+
+                        match res_cg {
+                            Ok(cg) => cg,
+                            Err(e) if subsystems.disable_if_unauthorized(Subsystem::ConsumerGroups, &e) => {
+                                shutdown_token.cancelled().await;
                                 info!("Shutting down");
                                 break;
                             },
+                            Err(e) => {
+                                error!("Failed to fetch consumer groups: {e}");
+                                continue;
+                            },
                         }
                     },
-                    Err(e) => {
-                        error!("Failed to fetch consumer groups: {e}");
+                    _ = describe_interval.tick(), if !settling.is_empty() => {
+                        // Describe again the Consumer Groups that were settling
+                        let mut cg = latest.clone();
+                        for g in settling.iter() {
+                            trace!("Describing settling Consumer Group '{g}'");
+                            match client.fetch_group_list(Some(g), FETCH_TIMEOUT) {
+                                Ok(described) => cg.groups.extend(described.groups),
+                                Err(e) => {
+                                    warn!("Failed to describe consumer group '{g}': {e}");
+                                },
+                            }
+                        }
+                        cg
+                    },
+                    _ = shutdown_token.cancelled() => {
+                        info!("Shutting down");
+                        break;
+                    },
+                };
+
+                cg.groups.retain(|g, _| !excludes.is_excluded(g));
+
+                // Emit only what changed since the latest Consumer Groups, if anything
+                let diff = ConsumerGroupsDiff::between(&latest, &cg);
+                let prev = std::mem::replace(&mut latest, cg);
+                if diff.is_empty() {
+                    continue;
+                }
+                debug!(
+                    "Consumer Groups changed: {} added, {} changed, {} removed",
+                    diff.added.len(),
+                    diff.changed.len(),
+                    diff.removed.len()
+                );
+
+                // Update group and group member metrics
+                metric_cg.set(latest.groups.len() as i64);
+                for (g, gm) in diff.added.iter().chain(diff.changed.iter()) {
+                    metric_cg_members.with_label_values(&[g]).set(gm.members.len() as i64);
+                }
+                for g in diff.removed.iter() {
+                    let _ = metric_cg_members.remove_label_values(&[g]);
+                }
+
+                // Update group coordinator metric, dropping the previous coordinator (if any)
+                for (g, prev_gm) in prev.groups.iter() {
+                    let coordinator_id =
+                        latest.groups.get(g).and_then(|gm| gm.group.coordinator_id);
+                    if let Some(prev_id) =
+                        prev_gm.group.coordinator_id.filter(|id| Some(*id) != coordinator_id)
+                    {
+                        let _ =
+                            metric_cg_coordinator.remove_label_values(&[g, &prev_id.to_string()]);
+                    }
+                }
+                for (g, gm) in diff.added.iter().chain(diff.changed.iter()) {
+                    if let Some(id) = gm.group.coordinator_id {
+                        metric_cg_coordinator.with_label_values(&[g, &id.to_string()]).set(1);
+                    }
+                }
+
+                // Update group info metric, dropping the previous protocol (if changed)
+                for (g, prev_gm) in prev.groups.iter() {
+                    let prev_group = &prev_gm.group;
+                    let unchanged = latest.groups.get(g).is_some_and(|gm| {
+                        gm.group.protocol_type == prev_group.protocol_type
+                            && gm.group.protocol == prev_group.protocol
+                    });
+                    if !unchanged {
+                        let _ = metric_cg_info.remove_label_values(&[
+                            g,
+                            &prev_group.protocol_type,
+                            &prev_group.protocol,
+                        ]);
+                    }
+                }
+                for (g, gm) in diff.added.iter().chain(diff.changed.iter()) {
+                    metric_cg_info
+                        .with_label_values(&[g, &gm.group.protocol_type, &gm.group.protocol])
+                        .set(1);
+                }
+                // Update channel capacity metric
+                metric_cg_ch_cap.set(sx.capacity() as i64);
+
+                tokio::select! {
+                    res = Self::emit(&sx, diff) => {
+                        if let Err(e) = res {
+                            error!("Failed to emit {}: {e}", std::any::type_name::<ConsumerGroupsDiff>());
+                        }
+                    },
+                    _ = shutdown_token.cancelled() => {
+                        info!("Shutting down");
+                        break;
                     },
                 }
             }
@@ -226,3 +431,250 @@ impl Emitter for ConsumerGroupsEmitter {
         (rx, join_handle)
     }
 }
+
+/// Whether the listed Consumer Group uses the next-gen consumer rebalance protocol (KIP-848).
+///
+/// Describing such a Group via the classic protocol fails, as it's not a classic Group:
+/// it's reported as `Dead` (or with no state at all) and no Members, despite having been listed.
+fn is_next_gen(g: &GroupInfo) -> bool {
+    g.members().is_empty() && (g.state() == GROUP_STATE_DEAD || g.state().is_empty())
+}
+
+/// Identifier of the Broker coordinating the listed Consumer Group, if known.
+///
+/// Groups are listed by asking each Broker for the Groups it coordinates, so the Broker
+/// that reported the Group is its coordinator. This is not exposed by [`GroupInfo`],
+/// so it's read directly from the `librdkafka` struct it wraps.
+fn coordinator_id(g: &GroupInfo) -> Option<i32> {
+    // SAFETY: `GroupInfo` is a single-field wrapper of `rd_kafka_group_info`, sharing its layout:
+    // `rdkafka` itself relies on it, casting the `librdkafka` array of `rd_kafka_group_info`
+    // into a slice of `GroupInfo` (see `GroupList::groups`). So the pointer is valid, aligned
+    // and lives as long as `g`; the `broker.id` read is a plain `i32`, with no pointer followed.
+    let id = unsafe { (*(g as *const GroupInfo as *const rdsys::rd_kafka_group_info)).broker.id };
+
+    (id >= 0).then_some(id)
+}
+
+/// Whether the Consumer Group is settling, and so worth describing again before the next fetch.
+///
+/// That's the case while it's rebalancing, or when it's stable but some of its members
+/// assignment is not known (yet).
+fn is_settling(gwm: &GroupWithMembers) -> bool {
+    match gwm.group.state.as_str() {
+        GROUP_STATE_PREPARING_REBALANCE | GROUP_STATE_COMPLETING_REBALANCE => true,
+        GROUP_STATE_STABLE => gwm.members.values().any(|mwa| mwa.assignment.is_empty()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ffi::{c_char, CStr};
+    use std::ptr::NonNull;
+
+    struct FakeGroupsClient;
+
+    impl GroupsClient for FakeGroupsClient {
+        fn fetch_group_list(
+            &self,
+            _group: Option<&str>,
+            _timeout: Duration,
+        ) -> KafkaResult<ConsumerGroups> {
+            let group = |name: &str| GroupWithMembers {
+                group: Group {
+                    name: Interned::new(name),
+                    state: GROUP_STATE_STABLE.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            Ok(["billing", "excluded-audit"].into_iter().map(group).collect())
+        }
+    }
+
+    fn group_info(broker_id: i32, state: &CStr) -> rdsys::rd_kafka_group_info {
+        rdsys::rd_kafka_group_info {
+            broker: rdsys::rd_kafka_metadata_broker {
+                id: broker_id,
+                host: c"broker".as_ptr() as *mut c_char,
+                port: 9092,
+            },
+            group: c"g".as_ptr() as *mut c_char,
+            err: rdsys::rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_NO_ERROR,
+            state: state.as_ptr() as *mut c_char,
+            protocol_type: c"consumer".as_ptr() as *mut c_char,
+            protocol: c"range".as_ptr() as *mut c_char,
+            members: NonNull::dangling().as_ptr(),
+            member_cnt: 0,
+        }
+    }
+
+    #[test]
+    fn reads_the_coordinator_of_the_group_info() {
+        for (broker_id, expected) in [(3, Some(3)), (0, Some(0)), (-1, None)] {
+            let info = group_info(broker_id, c"Stable");
+            // SAFETY: Same layout, as `coordinator_id` relies on; `info` outlives `g`,
+            // and all its pointers are valid for as long as it's borrowed.
+            let g = unsafe { &*(&info as *const rdsys::rd_kafka_group_info as *const GroupInfo) };
+            assert_eq!(g.name(), "g");
+            assert_eq!(coordinator_id(g), expected);
+        }
+
+        let info = group_info(1, c"Dead");
+        // SAFETY: As above.
+        let g = unsafe { &*(&info as *const rdsys::rd_kafka_group_info as *const GroupInfo) };
+        assert!(is_next_gen(g));
+    }
+
+    #[tokio::test]
+    async fn emits_the_groups_fetched_via_the_client() {
+        let metrics = Arc::new(Registry::new());
+        let emitter = ConsumerGroupsEmitter::new(
+            ClientConfig::new(),
+            GroupExcludes::new(&[regex::Regex::new("^excluded-").unwrap()], false),
+            Arc::new(Subsystems::new(false, metrics.clone())),
+            Arc::new(ClientStats::new(Duration::from_secs(60), metrics.clone())),
+            Jitter::default(),
+            metrics,
+        )
+        .with_client(Arc::new(FakeGroupsClient));
+
+        let shutdown_token = CancellationToken::new();
+        let (mut rx, join) = emitter.spawn(shutdown_token.clone());
+        let diff = rx.recv().await.expect("Consumer groups not emitted");
+        assert_eq!(diff.added.keys().map(|g| g.as_str()).collect::<Vec<_>>(), vec!["billing"]);
+        assert!(diff.changed.is_empty() && diff.removed.is_empty());
+
+        shutdown_token.cancel();
+        join.await.unwrap();
+    }
+
+    fn group_with_members(name: &str, state: &str, members: &[(&str, &[u32])]) -> GroupWithMembers {
+        GroupWithMembers {
+            group: Group {
+                name: Interned::new(name),
+                state: state.to_string(),
+                ..Default::default()
+            },
+            members: members
+                .iter()
+                .map(|(id, partitions)| {
+                    let mwa = MemberWithAssignment {
+                        member: Member {
+                            id: id.to_string(),
+                            ..Default::default()
+                        },
+                        assignment: partitions
+                            .iter()
+                            .map(|p| TopicPartition::new("t", *p))
+                            .collect(),
+                    };
+                    (id.to_string(), mwa)
+                })
+                .collect(),
+        }
+    }
+
+    /// Lists a rebalancing, a stable and an empty Consumer Group: the rebalancing one settles
+    /// at the second time it's described.
+    struct SettlingGroupsClient {
+        described: std::sync::Mutex<Vec<(String, tokio::time::Instant)>>,
+    }
+
+    impl GroupsClient for SettlingGroupsClient {
+        fn fetch_group_list(
+            &self,
+            group: Option<&str>,
+            _timeout: Duration,
+        ) -> KafkaResult<ConsumerGroups> {
+            let Some(g) = group else {
+                return Ok([
+                    group_with_members(
+                        "rebalancing",
+                        GROUP_STATE_PREPARING_REBALANCE,
+                        &[("m1", &[])],
+                    ),
+                    group_with_members("stable", GROUP_STATE_STABLE, &[("m2", &[0])]),
+                    group_with_members("empty", "Empty", &[]),
+                ]
+                .into_iter()
+                .collect());
+            };
+
+            let mut described = self.described.lock().unwrap();
+            described.push((g.to_string(), tokio::time::Instant::now()));
+            Ok(if described.len() < 2 {
+                [group_with_members(g, GROUP_STATE_COMPLETING_REBALANCE, &[("m1", &[])])]
+            } else {
+                [group_with_members(g, GROUP_STATE_STABLE, &[("m1", &[1])])]
+            }
+            .into_iter()
+            .collect())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn describes_settling_groups_until_settled() {
+        let metrics = Arc::new(Registry::new());
+        let client = Arc::new(SettlingGroupsClient {
+            described: Default::default(),
+        });
+        let emitter = ConsumerGroupsEmitter::new(
+            ClientConfig::new(),
+            GroupExcludes::default(),
+            Arc::new(Subsystems::new(false, metrics.clone())),
+            Arc::new(ClientStats::new(Duration::from_secs(60), metrics.clone())),
+            Jitter::default(),
+            metrics,
+        )
+        .with_client(client.clone());
+
+        let start = tokio::time::Instant::now();
+        let shutdown_token = CancellationToken::new();
+        let (mut rx, join) = emitter.spawn(shutdown_token.clone());
+
+        let diff = rx.recv().await.expect("Consumer groups not emitted");
+        assert_eq!(diff.added.len(), 3);
+
+        // Described right away, but still rebalancing
+        let diff = rx.recv().await.expect("Described consumer group not emitted");
+        assert_eq!(
+            diff.changed.keys().map(|g| g.as_str()).collect::<Vec<_>>(),
+            vec!["rebalancing"]
+        );
+        assert_eq!(diff.changed["rebalancing"].group.state, GROUP_STATE_COMPLETING_REBALANCE);
+
+        // Described again after a while, settled: its assignment is merged in
+        let diff = rx.recv().await.expect("Described consumer group not emitted");
+        assert_eq!(
+            diff.changed.keys().map(|g| g.as_str()).collect::<Vec<_>>(),
+            vec!["rebalancing"]
+        );
+        assert_eq!(
+            diff.changed["rebalancing"],
+            group_with_members("rebalancing", GROUP_STATE_STABLE, &[("m1", &[1])])
+        );
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+
+        // Not described anymore, once settled
+        tokio::time::sleep(FETCH_INTERVAL / 2).await;
+        let described = client
+            .described
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(g, at)| (g.clone(), at.duration_since(start)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            described,
+            vec![
+                ("rebalancing".to_string(), Duration::ZERO),
+                ("rebalancing".to_string(), DESCRIBE_INTERVAL)
+            ]
+        );
+
+        shutdown_token.cancel();
+        join.await.unwrap();
+    }
+}
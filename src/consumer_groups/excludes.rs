@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use regex::{Regex, RegexSet};
+
+use crate::constants::{DEFAULT_GROUP_EXCLUDES, KOMMITTED_CONSUMER_OFFSETS_CONSUMER};
+
+use super::shard::GroupShard;
+
+/// Consumer Groups excluded from monitoring, by name.
+///
+/// Excluded Consumer Groups are not emitted, and their committed offsets are ignored:
+/// they are absent from all metrics and sinks.
+/// When monitoring is sharded, Consumer Groups of other shards are excluded too.
+///
+/// The Consumer Groups of this service own consumers are always ignored, by exact name,
+/// on top of the given patterns.
+#[derive(Debug, Clone)]
+pub struct GroupExcludes {
+    set: RegexSet,
+    ignored: HashSet<String>,
+    shard: Option<GroupShard>,
+}
+
+impl GroupExcludes {
+    /// Create a new [`GroupExcludes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - Regexes that excluded Consumer Groups match
+    /// * `with_defaults` - Whether to also exclude the [`DEFAULT_GROUP_EXCLUDES`]
+    pub fn new(patterns: &[Regex], with_defaults: bool) -> Self {
+        let defaults = if with_defaults {
+            DEFAULT_GROUP_EXCLUDES
+        } else {
+            &[]
+        };
+
+        Self {
+            set: RegexSet::new(defaults.iter().copied().chain(patterns.iter().map(Regex::as_str)))
+                .expect("Invalid Consumer Group exclude"),
+            ignored: HashSet::from([KOMMITTED_CONSUMER_OFFSETS_CONSUMER.to_string()]),
+            shard: None,
+        }
+    }
+
+    /// Also ignore the given Consumer Group, by exact name (e.g. when this service own
+    /// consumer uses a `group.id` other than the default).
+    pub fn with_ignored(mut self, group: impl Into<String>) -> Self {
+        self.ignored.insert(group.into());
+        self
+    }
+
+    /// Only monitor the Consumer Groups of the given [`GroupShard`], excluding all others.
+    pub fn with_shard(mut self, shard: GroupShard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    /// Whether the given Consumer Group is excluded.
+    pub fn is_excluded(&self, group: &str) -> bool {
+        self.ignored.contains(group)
+            || self.set.is_match(group)
+            || self.shard.is_some_and(|s| !s.owns(group))
+    }
+}
+
+impl Default for GroupExcludes {
+    fn default() -> Self {
+        Self::new(&[], true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        let excludes = GroupExcludes::default();
+        assert!(excludes.is_excluded("__kommitted__consumer_offsets_consumer"));
+        assert!(excludes.is_excluded("_confluent-controlcenter"));
+        assert!(excludes.is_excluded("connect-cluster"));
+        assert!(excludes.is_excluded("schema-registry"));
+        assert!(!excludes.is_excluded("connect-my-sink-connector"));
+        assert!(!excludes.is_excluded("my-app"));
+    }
+
+    #[test]
+    fn without_defaults() {
+        let excludes = GroupExcludes::new(&[Regex::new("^test-").unwrap()], false);
+        assert!(excludes.is_excluded("test-app"));
+        assert!(!excludes.is_excluded("schema-registry"));
+        assert!(!excludes.is_excluded("my-app"));
+
+        // Own consumers are ignored regardless
+        let excludes = excludes.with_ignored("kommitted-offsets");
+        assert!(excludes.is_excluded("__kommitted__consumer_offsets_consumer"));
+        assert!(excludes.is_excluded("kommitted-offsets"));
+    }
+
+    #[test]
+    fn sharded() {
+        let shards: Vec<GroupExcludes> = (0..2)
+            .map(|i| GroupExcludes::default().with_shard(GroupShard::new(i, 2).unwrap()))
+            .collect();
+        for g in ["my-app", "other-app", "payments", "billing"] {
+            assert_eq!(shards.iter().filter(|e| !e.is_excluded(g)).count(), 1);
+        }
+        assert!(shards.iter().all(|e| e.is_excluded("schema-registry")));
+    }
+}
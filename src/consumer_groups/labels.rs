@@ -0,0 +1,229 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use super::connect::connector_of;
+
+use crate::config::{deserialize_regex, ConfigError, ConfigResult};
+use crate::prometheus_metrics::{
+    LABEL_CLUSTER_ID, LABEL_CONNECTOR, LABEL_GROUP, LABEL_GROUP_INSTANCE_ID,
+    LABEL_MEMBER_CLIENT_ID, LABEL_MEMBER_HOST, LABEL_MEMBER_ID, LABEL_PARTITION, LABEL_TOPIC,
+};
+
+/// Labels already attached to the lag metrics, that Consumer Group labels can't override.
+const RESERVED_LABELS: &[&str] = &[
+    LABEL_CLUSTER_ID,
+    LABEL_GROUP,
+    LABEL_TOPIC,
+    LABEL_PARTITION,
+    LABEL_MEMBER_ID,
+    LABEL_MEMBER_HOST,
+    LABEL_MEMBER_CLIENT_ID,
+    LABEL_GROUP_INSTANCE_ID,
+];
+
+/// Content of the `--group-labels-file`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GroupLabelsFile {
+    /// Labels of specific Consumer Groups, by name.
+    #[serde(default)]
+    groups: HashMap<String, BTreeMap<String, String>>,
+
+    /// Labels of the Consumer Groups matching a regex.
+    #[serde(default)]
+    rules: Vec<GroupLabelsRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GroupLabelsRule {
+    /// Regex that Consumer Groups have to match.
+    #[serde(deserialize_with = "deserialize_regex")]
+    group: Regex,
+
+    /// Labels of the matching Consumer Groups.
+    labels: BTreeMap<String, String>,
+}
+
+/// Extra labels (e.g. team, service, tier) attached to Consumer Groups, in lag metrics and
+/// JSON responses.
+///
+/// Every Consumer Group gets all the labels that appear anywhere in the mapping, so that all
+/// lag metrics have the same labels: the value is empty for Consumer Groups it doesn't apply to.
+#[derive(Debug, Clone, Default)]
+pub struct GroupLabels {
+    names: BTreeSet<String>,
+    groups: HashMap<String, BTreeMap<String, String>>,
+    rules: Vec<GroupLabelsRule>,
+    connectors: bool,
+}
+
+impl GroupLabels {
+    /// Load the mapping of Consumer Groups to labels from the given file.
+    ///
+    /// The file is in JSON format if its extension is `.json`, in TOML format otherwise:
+    /// labels are set for specific Consumer Groups by name (`groups`), or for those matching
+    /// a regex (`rules`). When multiple apply, those by name win over rules, and later rules
+    /// win over earlier ones.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> ConfigResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let content =
+            std::fs::read_to_string(&path).map_err(|e| ConfigError::Read(path.clone(), e))?;
+        let file: GroupLabelsFile = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content)
+                .map_err(|e| ConfigError::Invalid(path.clone(), e.to_string()))?
+        } else {
+            toml::from_str(&content).map_err(|e| ConfigError::Parse(path.clone(), e))?
+        };
+
+        let labels = Self::new(file).map_err(|e| ConfigError::Invalid(path.clone(), e))?;
+        trace!("Loaded Consumer Group labels from '{}':\n{:#?}", path.display(), labels);
+        Ok(labels)
+    }
+
+    fn new(file: GroupLabelsFile) -> Result<Self, String> {
+        let names: BTreeSet<String> = file
+            .groups
+            .values()
+            .chain(file.rules.iter().map(|r| &r.labels))
+            .flat_map(|labels| labels.keys().cloned())
+            .collect();
+
+        for name in names.iter() {
+            if !is_valid_label_name(name) {
+                return Err(format!("Invalid label name '{name}'"));
+            }
+            if RESERVED_LABELS.contains(&name.as_str()) {
+                return Err(format!("Label name '{name}' is reserved"));
+            }
+        }
+
+        Ok(Self {
+            names,
+            groups: file.groups,
+            rules: file.rules,
+            connectors: false,
+        })
+    }
+
+    /// Label the Consumer Groups of Kafka Connect sink connectors with the name of the
+    /// connector (see [`connector_of`]), as the `connector` label.
+    ///
+    /// It wins over a `connector` label set by the mapping, for the Consumer Groups it applies to.
+    pub fn with_connectors(mut self, connectors: bool) -> Self {
+        self.connectors = connectors;
+        if connectors {
+            self.names.insert(LABEL_CONNECTOR.to_string());
+        }
+        self
+    }
+
+    /// Names of all the labels, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// Labels of the given Consumer Group: all the label names, with an empty value for
+    /// those that don't apply to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - Name of the Consumer Group
+    /// * `protocol_type` - Protocol Type of the Consumer Group, if known (empty otherwise)
+    pub fn labels_for(&self, group: &str, protocol_type: &str) -> BTreeMap<String, String> {
+        let mut labels: BTreeMap<String, String> =
+            self.names.iter().map(|n| (n.clone(), String::new())).collect();
+
+        for rule in self.rules.iter().filter(|r| r.group.is_match(group)) {
+            labels.extend(rule.labels.clone());
+        }
+        if let Some(group_labels) = self.groups.get(group) {
+            labels.extend(group_labels.clone());
+        }
+        if let Some(connector) = connector_of(group, protocol_type).filter(|_| self.connectors) {
+            labels.insert(LABEL_CONNECTOR.to_string(), connector.to_string());
+        }
+
+        labels
+    }
+}
+
+/// Whether `name` is a valid Prometheus label name (i.e. `[a-zA-Z_][a-zA-Z0-9_]*`),
+/// not reserved for internal use (i.e. starting with `__`).
+fn is_valid_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !name.starts_with("__")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn group_labels(toml: &str) -> Result<GroupLabels, String> {
+        GroupLabels::new(toml::from_str(toml).unwrap())
+    }
+
+    #[test]
+    fn labels_by_group_and_rules() {
+        let labels = group_labels(
+            r#"
+            [groups.payments-settlement]
+            team = "payments"
+            tier = "1"
+
+            [[rules]]
+            group = "^payments-"
+            labels = { team = "payments", tier = "2" }
+
+            [[rules]]
+            group = "^billing-"
+            labels = { team = "billing", service = "invoicing" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(labels.names().collect::<Vec<_>>(), vec!["service", "team", "tier"]);
+
+        let expected = |service: &str, team: &str, tier: &str| {
+            BTreeMap::from([
+                ("service".to_string(), service.to_string()),
+                ("team".to_string(), team.to_string()),
+                ("tier".to_string(), tier.to_string()),
+            ])
+        };
+        assert_eq!(labels.labels_for("payments-settlement", ""), expected("", "payments", "1"));
+        assert_eq!(labels.labels_for("payments-refunds", ""), expected("", "payments", "2"));
+        assert_eq!(labels.labels_for("billing-eu", ""), expected("invoicing", "billing", ""));
+        assert_eq!(labels.labels_for("other", ""), expected("", "", ""));
+
+        assert!(GroupLabels::default().labels_for("other", "").is_empty());
+    }
+
+    #[test]
+    fn labels_connectors() {
+        let labels = GroupLabels::default().with_connectors(true);
+        assert_eq!(labels.names().collect::<Vec<_>>(), vec![LABEL_CONNECTOR]);
+
+        let connector = |c: &str| BTreeMap::from([(LABEL_CONNECTOR.to_string(), c.to_string())]);
+        assert_eq!(labels.labels_for("connect-s3-sink", "consumer"), connector("s3-sink"));
+        assert_eq!(labels.labels_for("connect-cluster", "connect"), connector(""));
+        assert_eq!(labels.labels_for("payments", "consumer"), connector(""));
+
+        assert!(GroupLabels::default().labels_for("connect-s3-sink", "consumer").is_empty());
+    }
+
+    #[test]
+    fn invalid_label_names() {
+        assert!(group_labels("[groups.g]\n\"0team\" = \"a\"").is_err());
+        assert!(group_labels("[groups.g]\n\"my-team\" = \"a\"").is_err());
+        assert!(group_labels("[groups.g]\n__team = \"a\"").is_err());
+        assert!(group_labels("[groups.g]\ntopic = \"a\"").is_err());
+        assert!(group_labels("[groups.g]\n_team = \"a\"").is_ok());
+    }
+}
@@ -1,5 +1,10 @@
-// Inner module
+// Inner modules
+mod connect;
+mod diff;
 mod emitter;
+mod excludes;
+mod labels;
+mod shard;
 
 use std::sync::Arc;
 
@@ -9,16 +14,37 @@ use tokio::sync::mpsc::Receiver;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
-use crate::internals::Emitter;
+use crate::internals::{ClientStats, Emitter, GroupsClient, Jitter, Subsystems};
 
+pub use connect::connector_of;
+pub use diff::ConsumerGroupsDiff;
 pub use emitter::{ConsumerGroups, ConsumerGroupsEmitter};
+pub use excludes::GroupExcludes;
+pub use labels::GroupLabels;
+pub use shard::GroupShard;
 
+#[allow(clippy::too_many_arguments)]
 pub fn init(
     admin_client_config: ClientConfig,
+    excludes: GroupExcludes,
+    subsystems: Arc<Subsystems>,
+    client_stats: Arc<ClientStats>,
+    jitter: Jitter,
+    client: Option<Arc<dyn GroupsClient>>,
     shutdown_token: CancellationToken,
     metrics: Arc<Registry>,
-) -> (Receiver<ConsumerGroups>, JoinHandle<()>) {
-    let consumer_groups_emitter = ConsumerGroupsEmitter::new(admin_client_config, metrics);
+) -> (Receiver<ConsumerGroupsDiff>, JoinHandle<()>) {
+    let mut consumer_groups_emitter = ConsumerGroupsEmitter::new(
+        admin_client_config,
+        excludes,
+        subsystems,
+        client_stats,
+        jitter,
+        metrics,
+    );
+    if let Some(client) = client {
+        consumer_groups_emitter = consumer_groups_emitter.with_client(client);
+    }
     let (cg_rx, cg_join) = consumer_groups_emitter.spawn(shutdown_token);
 
     debug!("Initialized");
@@ -0,0 +1,67 @@
+use std::fmt;
+
+/// A shard of the Consumer Groups, when monitoring is split across multiple instances.
+///
+/// Each Consumer Group belongs to exactly one shard, determined by a hash of its name:
+/// instances configured with the same `count`, and each a different `index`,
+/// monitor disjoint sets of Consumer Groups, that together cover them all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupShard {
+    index: u32,
+    count: u32,
+}
+
+impl GroupShard {
+    /// Create a new [`GroupShard`].
+    ///
+    /// Returns `None` if `index` is not within `[0, count)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of this shard
+    /// * `count` - Total number of shards
+    pub fn new(index: u32, count: u32) -> Option<Self> {
+        (index < count).then_some(Self {
+            index,
+            count,
+        })
+    }
+
+    /// Whether the given Consumer Group belongs to this shard.
+    pub fn owns(&self, group: &str) -> bool {
+        fnv1a(group.as_bytes()) % self.count as u64 == self.index as u64
+    }
+}
+
+impl fmt::Display for GroupShard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// 64-bit FNV-1a hash.
+///
+/// Unlike the hashers of the standard library, it's guaranteed not to change across
+/// builds and versions: instances must agree on which shard each Consumer Group belongs to.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shards_are_disjoint_and_complete() {
+        assert!(GroupShard::new(3, 3).is_none());
+
+        let shards: Vec<GroupShard> = (0..3).map(|i| GroupShard::new(i, 3).unwrap()).collect();
+        for g in (0..1000).map(|i| format!("group-{i}")) {
+            assert_eq!(shards.iter().filter(|s| s.owns(&g)).count(), 1);
+        }
+
+        // Stable across builds
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+}
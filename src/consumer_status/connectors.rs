@@ -0,0 +1,105 @@
+use serde::Serialize;
+
+use super::evaluator::ConsumerStatus;
+use super::sink::GroupStatus;
+
+use crate::consumer_groups::connector_of;
+use crate::kafka_types::Interned;
+
+/// Lag of a Kafka Connect sink connector, aggregated over the Topic Partitions it consumes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConnectorStatus {
+    /// Name of the connector.
+    pub connector: String,
+
+    /// Consumer Group of the connector (i.e. `connect-<connector>`).
+    pub group: Interned,
+
+    /// [`ConsumerStatus`] of the Consumer Group of the connector.
+    pub status: ConsumerStatus,
+
+    /// Amount of Topic Partitions consumed by the connector.
+    pub partitions: usize,
+
+    /// Sum of the Offset Lag of all the Topic Partitions.
+    pub offset_lag: u64,
+
+    /// Max of the Time Lag (ms) of all the Topic Partitions.
+    pub max_time_lag_ms: i64,
+}
+
+/// The [`ConnectorStatus`] of each Kafka Connect sink connector, among the given [`GroupStatus`]es.
+///
+/// Consumer Groups that are not of a Kafka Connect sink connector (see [`connector_of`])
+/// are skipped. Connectors are in the same order as their Consumer Groups.
+pub fn connector_statuses(groups: &[GroupStatus]) -> Vec<ConnectorStatus> {
+    groups
+        .iter()
+        .filter_map(|gs| {
+            connector_of(&gs.group, &gs.protocol_type).map(|connector| ConnectorStatus {
+                connector: connector.to_string(),
+                group: gs.group.clone(),
+                status: gs.status,
+                partitions: gs.partitions.len(),
+                offset_lag: gs.partitions.iter().map(|ps| ps.offset_lag).sum(),
+                max_time_lag_ms: gs.partitions.iter().map(|ps| ps.time_lag_ms).max().unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::consumer_status::sink::PartitionStatus;
+
+    fn group_status(group: &str, protocol_type: &str, lags: &[(u64, i64)]) -> GroupStatus {
+        GroupStatus {
+            group: group.into(),
+            labels: BTreeMap::new(),
+            coordinator_id: None,
+            protocol_type: protocol_type.to_string(),
+            protocol: String::new(),
+            state: String::new(),
+            status: ConsumerStatus::Warn,
+            partitions: lags
+                .iter()
+                .enumerate()
+                .map(|(p, (offset_lag, time_lag_ms))| PartitionStatus {
+                    topic: "t".into(),
+                    partition: p as u32,
+                    host: None,
+                    status: ConsumerStatus::Ok,
+                    offset: 0,
+                    offset_lag: *offset_lag,
+                    time_lag_ms: *time_lag_ms,
+                })
+                .collect(),
+            orphaned_partitions: Vec::new(),
+            stopped_committing: false,
+        }
+    }
+
+    #[test]
+    fn aggregates_lag_per_connector() {
+        let groups = [
+            group_status("connect-cluster", "connect", &[]),
+            group_status("connect-s3-sink", "consumer", &[(10, 500), (5, 1500)]),
+            group_status("payments", "consumer", &[(100, 9000)]),
+        ];
+
+        assert_eq!(
+            connector_statuses(&groups),
+            vec![ConnectorStatus {
+                connector: "s3-sink".to_string(),
+                group: "connect-s3-sink".into(),
+                status: ConsumerStatus::Warn,
+                partitions: 2,
+                offset_lag: 15,
+                max_time_lag_ms: 1500,
+            }]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::lag_register::Lag;
+
+/// Status of a Consumer Group, or of one of its Topic Partitions.
+///
+/// Variants are ordered by severity: the status of a Consumer Group is the most severe
+/// of the statuses of its Topic Partitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConsumerStatus {
+    /// Consumer is keeping up, or catching up.
+    Ok,
+
+    /// Consumer is committing, but lag has not decreased over the whole window.
+    Warn,
+
+    /// Consumer committed an offset lower than a previous one (i.e. it rewound).
+    Err,
+
+    /// Consumer is committing, but the offset has not moved over the whole window, while lagging.
+    Stalled,
+
+    /// Consumer stopped committing, while lagging.
+    Stopped,
+}
+
+impl ConsumerStatus {
+    /// Numeric code, as used by the `consumer_group_status` metric.
+    pub fn code(&self) -> i64 {
+        *self as i64
+    }
+}
+
+/// Evaluates the [`ConsumerStatus`] of a Topic Partition consumed by a Consumer Group,
+/// following the same sliding-window rules as [Burrow](https://github.com/linkedin/Burrow/wiki/Consumer-Lag-Evaluation-Rules).
+///
+/// Rules are checked in order, and the first that applies determines the status:
+///
+/// 1. if the window contains less than 2 commits, there is not enough data: `OK`
+/// 2. if any offset is lower than the one before it: `ERR`
+/// 3. if the time since the last commit is greater than the time the window spans,
+///    and the consumer is lagging: `STOPPED`
+/// 4. if the window is not full yet, or the lag was zero at any point within it: `OK`
+/// 5. if the offset never moved within the window: `STALLED`
+/// 6. if the lag never decreased within the window: `WARN`
+/// 7. otherwise: `OK`
+///
+/// # Arguments
+///
+/// * `window` - The last (up to) `window_size` [`Lag`]s, one per commit, oldest first
+/// * `window_size` - Size the window is meant to reach
+/// * `now` - [`DateTime<Utc>`] of the evaluation
+pub fn evaluate(window: &VecDeque<Lag>, window_size: usize, now: DateTime<Utc>) -> ConsumerStatus {
+    let (first, last) = match (window.front(), window.back()) {
+        (Some(first), Some(last)) if window.len() >= 2 => (first, last),
+        _ => return ConsumerStatus::Ok,
+    };
+
+    let pairs = || window.iter().zip(window.iter().skip(1));
+
+    if pairs().any(|(prev, next)| next.offset < prev.offset) {
+        return ConsumerStatus::Err;
+    }
+
+    if last.offset_lag > 0
+        && now - last.offset_timestamp > last.offset_timestamp - first.offset_timestamp
+    {
+        return ConsumerStatus::Stopped;
+    }
+
+    if window.len() < window_size || window.iter().any(|l| l.offset_lag == 0) {
+        return ConsumerStatus::Ok;
+    }
+
+    if first.offset == last.offset {
+        return ConsumerStatus::Stalled;
+    }
+
+    if pairs().all(|(prev, next)| next.offset_lag >= prev.offset_lag) {
+        return ConsumerStatus::Warn;
+    }
+
+    ConsumerStatus::Ok
+}
+
+/// Whether the consumer of a Topic Partition stopped committing: the time since the last commit
+/// is greater than `intervals` times the mean interval between the commits in the window.
+///
+/// Unlike [`ConsumerStatus::Stopped`], this doesn't depend on the consumer lagging, so it catches
+/// dead consumers of quiet Topics, whose lag grows slowly. Returns `None` if the window
+/// contains less than 2 commits, as there is no commit interval to speak of.
+///
+/// # Arguments
+///
+/// * `window` - The last [`Lag`]s, one per commit, oldest first
+/// * `intervals` - How many mean commit intervals without a commit mean the consumer stopped
+/// * `now` - [`DateTime<Utc>`] of the evaluation
+pub fn stopped_committing(
+    window: &VecDeque<Lag>,
+    intervals: u32,
+    now: DateTime<Utc>,
+) -> Option<bool> {
+    let (first, last) = match (window.front(), window.back()) {
+        (Some(first), Some(last)) if window.len() >= 2 => (first, last),
+        _ => return None,
+    };
+
+    let mean_interval =
+        (last.offset_timestamp - first.offset_timestamp) / (window.len() - 1) as i32;
+    Some(now - last.offset_timestamp > mean_interval * intervals as i32)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+
+    const WINDOW_SIZE: usize = 5;
+
+    /// A window of commits, 10 seconds apart, starting at [`DateTime::UNIX_EPOCH`].
+    fn window(commits: &[(u64, u64)]) -> VecDeque<Lag> {
+        commits
+            .iter()
+            .enumerate()
+            .map(|(i, (offset, offset_lag))| Lag {
+                offset: *offset,
+                offset_timestamp: DateTime::UNIX_EPOCH + Duration::seconds(i as i64 * 10),
+                offset_lag: *offset_lag,
+                time_lag: Duration::zero(),
+            })
+            .collect()
+    }
+
+    /// Shortly after the last commit of a window of `len` commits.
+    fn now(len: usize) -> DateTime<Utc> {
+        DateTime::UNIX_EPOCH + Duration::seconds(len as i64 * 10)
+    }
+
+    #[test]
+    fn not_enough_data() {
+        assert_eq!(evaluate(&window(&[]), WINDOW_SIZE, now(0)), ConsumerStatus::Ok);
+        assert_eq!(evaluate(&window(&[(10, 100)]), WINDOW_SIZE, now(10)), ConsumerStatus::Ok);
+        assert_eq!(
+            evaluate(&window(&[(10, 100), (10, 100)]), WINDOW_SIZE, now(2)),
+            ConsumerStatus::Ok
+        );
+    }
+
+    #[test]
+    fn rewind() {
+        let w = window(&[(10, 0), (20, 0), (5, 15)]);
+        assert_eq!(evaluate(&w, WINDOW_SIZE, now(3)), ConsumerStatus::Err);
+    }
+
+    #[test]
+    fn stopped() {
+        let w = window(&[(10, 5), (20, 5), (30, 5)]);
+        assert_eq!(evaluate(&w, WINDOW_SIZE, now(3)), ConsumerStatus::Ok);
+        assert_eq!(evaluate(&w, WINDOW_SIZE, now(10)), ConsumerStatus::Stopped);
+
+        // Not lagging: consumer stopped committing because there is nothing to consume
+        let w = window(&[(10, 5), (20, 5), (30, 0)]);
+        assert_eq!(evaluate(&w, WINDOW_SIZE, now(10)), ConsumerStatus::Ok);
+    }
+
+    #[test]
+    fn stopped_committing_after_intervals() {
+        // Commits every 10s: stopped once more than 5 intervals (50s) passed since the last one
+        let w = window(&[(10, 0), (20, 0), (30, 0)]);
+        assert_eq!(stopped_committing(&w, 5, now(3)), Some(false));
+        assert_eq!(stopped_committing(&w, 5, now(7)), Some(false));
+        assert_eq!(stopped_committing(&w, 5, now(8)), Some(true));
+
+        assert_eq!(stopped_committing(&window(&[(10, 0)]), 5, now(100)), None);
+    }
+
+    #[test]
+    fn stalled() {
+        let w = window(&[(10, 5), (10, 8), (10, 8), (10, 9), (10, 12)]);
+        assert_eq!(evaluate(&w, WINDOW_SIZE, now(5)), ConsumerStatus::Stalled);
+
+        // Lag was zero at some point in the window
+        let w = window(&[(10, 0), (10, 8), (10, 8), (10, 9), (10, 12)]);
+        assert_eq!(evaluate(&w, WINDOW_SIZE, now(5)), ConsumerStatus::Ok);
+    }
+
+    #[test]
+    fn warn() {
+        let w = window(&[(10, 5), (20, 8), (30, 8), (40, 9), (50, 12)]);
+        assert_eq!(evaluate(&w, WINDOW_SIZE, now(5)), ConsumerStatus::Warn);
+
+        // Window not full yet
+        let w = window(&[(10, 5), (20, 8), (30, 8), (40, 9)]);
+        assert_eq!(evaluate(&w, WINDOW_SIZE, now(4)), ConsumerStatus::Ok);
+    }
+
+    #[test]
+    fn catching_up() {
+        let w = window(&[(10, 5), (20, 8), (30, 7), (40, 9), (50, 12)]);
+        assert_eq!(evaluate(&w, WINDOW_SIZE, now(5)), ConsumerStatus::Ok);
+    }
+
+    #[test]
+    fn group_status_is_most_severe() {
+        let statuses = [ConsumerStatus::Ok, ConsumerStatus::Stalled, ConsumerStatus::Warn];
+        assert_eq!(statuses.into_iter().max(), Some(ConsumerStatus::Stalled));
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use super::evaluator::ConsumerStatus;
+use super::sink::GroupStatus;
+
+use crate::kafka_types::Interned;
+
+/// Lag of the Consumers running on a host, aggregated across all the Consumer Groups.
+///
+/// A single degraded host (e.g. VM or pod) drags behind all the Consumer Groups
+/// with Members on it: aggregating by host makes it stand out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HostStatus {
+    /// Host of the Consumers, as reported by the Consumer Group coordinator.
+    pub host: String,
+
+    /// Consumer Groups with Members on the host.
+    pub groups: Vec<Interned>,
+
+    /// Most severe of the statuses of the Topic Partitions assigned to the host.
+    pub status: ConsumerStatus,
+
+    /// Amount of Topic Partitions assigned to the host.
+    pub partitions: usize,
+
+    /// Sum of the Offset Lag of all the Topic Partitions.
+    pub offset_lag: u64,
+
+    /// Max of the Time Lag (ms) of all the Topic Partitions.
+    pub max_time_lag_ms: i64,
+}
+
+/// The [`HostStatus`] of each host that Consumers run on, among the given [`GroupStatus`]es.
+///
+/// Topic Partitions not assigned to any Member are skipped. Hosts are sorted by name.
+pub fn host_statuses(groups: &[GroupStatus]) -> Vec<HostStatus> {
+    let mut hosts: BTreeMap<&str, (BTreeSet<&Interned>, HostStatus)> = BTreeMap::new();
+
+    for gs in groups.iter() {
+        for ps in gs.partitions.iter() {
+            let Some(host) = ps.host.as_deref() else {
+                continue;
+            };
+
+            let (host_groups, hs) = hosts.entry(host).or_insert_with(|| {
+                (
+                    BTreeSet::new(),
+                    HostStatus {
+                        host: host.to_string(),
+                        groups: Vec::new(),
+                        status: ConsumerStatus::Ok,
+                        partitions: 0,
+                        offset_lag: 0,
+                        max_time_lag_ms: 0,
+                    },
+                )
+            });
+            host_groups.insert(&gs.group);
+            hs.status = hs.status.max(ps.status);
+            hs.partitions += 1;
+            hs.offset_lag += ps.offset_lag;
+            hs.max_time_lag_ms = hs.max_time_lag_ms.max(ps.time_lag_ms);
+        }
+    }
+
+    hosts
+        .into_values()
+        .map(|(host_groups, mut hs)| {
+            hs.groups = host_groups.into_iter().cloned().collect();
+            hs
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consumer_status::sink::PartitionStatus;
+
+    fn group_status(
+        group: &str,
+        partitions: &[(Option<&str>, ConsumerStatus, u64, i64)],
+    ) -> GroupStatus {
+        GroupStatus {
+            group: group.into(),
+            labels: BTreeMap::new(),
+            coordinator_id: None,
+            protocol_type: "consumer".to_string(),
+            protocol: String::new(),
+            state: String::new(),
+            status: ConsumerStatus::Ok,
+            partitions: partitions
+                .iter()
+                .enumerate()
+                .map(|(p, (host, status, offset_lag, time_lag_ms))| PartitionStatus {
+                    topic: "t".into(),
+                    partition: p as u32,
+                    host: host.map(str::to_string),
+                    status: *status,
+                    offset: 0,
+                    offset_lag: *offset_lag,
+                    time_lag_ms: *time_lag_ms,
+                })
+                .collect(),
+            orphaned_partitions: Vec::new(),
+            stopped_committing: false,
+        }
+    }
+
+    #[test]
+    fn aggregates_lag_per_host() {
+        let groups = [
+            group_status(
+                "billing",
+                &[
+                    (Some("/10.0.0.1"), ConsumerStatus::Ok, 10, 500),
+                    (Some("/10.0.0.2"), ConsumerStatus::Ok, 0, 0),
+                ],
+            ),
+            group_status(
+                "orders",
+                &[
+                    (Some("/10.0.0.1"), ConsumerStatus::Stalled, 500, 90_000),
+                    (None, ConsumerStatus::Err, 1_000, 120_000),
+                ],
+            ),
+        ];
+
+        let hosts = host_statuses(&groups);
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(
+            hosts[0],
+            HostStatus {
+                host: "/10.0.0.1".to_string(),
+                groups: vec!["billing".into(), "orders".into()],
+                status: ConsumerStatus::Stalled,
+                partitions: 2,
+                offset_lag: 510,
+                max_time_lag_ms: 90_000,
+            }
+        );
+        assert_eq!(hosts[1].groups, vec![Interned::from("billing")]);
+        assert_eq!(hosts[1].status, ConsumerStatus::Ok);
+    }
+}
@@ -0,0 +1,16 @@
+//! Threshold-free evaluation of the status of Consumer Groups.
+//!
+//! Instead of comparing lag against thresholds, the status of each Consumer Group is derived
+//! from how its committed offsets and lag evolve over a sliding window of commits,
+//! in the same way [Burrow](https://github.com/linkedin/Burrow) does.
+
+mod connectors;
+mod evaluator;
+mod hosts;
+mod query;
+mod sink;
+
+pub use connectors::{connector_statuses, ConnectorStatus};
+pub use hosts::{host_statuses, HostStatus};
+pub use query::GroupsQuery;
+pub use sink::{ConsumerStatusSink, GroupStatus};
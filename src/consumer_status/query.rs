@@ -0,0 +1,157 @@
+use serde::Deserialize;
+
+use super::sink::GroupStatus;
+
+/// Max amount of [`GroupStatus`] per page, whatever is requested.
+const MAX_PER_PAGE: usize = 1_000;
+
+/// What to sort [`GroupStatus`]es by, in a [`GroupsQuery`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupsSort {
+    /// Name of the Consumer Group.
+    #[default]
+    Name,
+
+    /// Offset Lag, summed across Topic Partitions.
+    Lag,
+
+    /// Time Lag, max across Topic Partitions.
+    TimeLag,
+}
+
+/// Order to sort [`GroupStatus`]es in, in a [`GroupsQuery`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Filtering, sorting and pagination of [`GroupStatus`]es (e.g. the query string of `/groups`),
+/// so that thousands of Consumer Groups can be browsed page by page.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct GroupsQuery {
+    pub sort: GroupsSort,
+
+    /// Defaults to ascending by name, and descending by lag (i.e. the most lagging first).
+    pub order: Option<SortOrder>,
+
+    /// Page to return, starting from `1`: only applies if `per_page` is set.
+    pub page: Option<usize>,
+
+    /// Amount of Consumer Groups per page (up to 1000). If not set, all are returned.
+    pub per_page: Option<usize>,
+
+    /// Only the Consumer Groups in this state (e.g. `Stable`), case-insensitive.
+    pub state: Option<String>,
+
+    /// Only the Consumer Groups with at least this Offset Lag, summed across Topic Partitions.
+    pub min_lag: Option<u64>,
+}
+
+impl GroupsQuery {
+    /// Filter and sort the given [`GroupStatus`]es, returning the requested page of them,
+    /// and the total amount of those that passed the filters.
+    pub fn apply(&self, mut groups: Vec<GroupStatus>) -> (Vec<GroupStatus>, usize) {
+        groups.retain(|gs| {
+            self.state.as_deref().is_none_or(|s| gs.state.eq_ignore_ascii_case(s))
+                && self.min_lag.is_none_or(|min| offset_lag(gs) >= min)
+        });
+
+        match self.sort {
+            GroupsSort::Name => groups.sort_by(|a, b| a.group.cmp(&b.group)),
+            GroupsSort::Lag => groups.sort_by_key(offset_lag),
+            GroupsSort::TimeLag => groups.sort_by_key(max_time_lag_ms),
+        }
+        let descending = match (self.order, self.sort) {
+            (Some(order), _) => order == SortOrder::Desc,
+            (None, sort) => sort != GroupsSort::Name,
+        };
+        if descending {
+            groups.reverse();
+        }
+
+        let total = groups.len();
+        if let Some(per_page) = self.per_page {
+            let per_page = per_page.clamp(1, MAX_PER_PAGE);
+            let skip = self.page.unwrap_or(1).saturating_sub(1).saturating_mul(per_page);
+            groups = groups.into_iter().skip(skip).take(per_page).collect();
+        }
+
+        (groups, total)
+    }
+}
+
+fn offset_lag(gs: &GroupStatus) -> u64 {
+    gs.partitions.iter().map(|ps| ps.offset_lag).sum()
+}
+
+fn max_time_lag_ms(gs: &GroupStatus) -> i64 {
+    gs.partitions.iter().map(|ps| ps.time_lag_ms).max().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::consumer_status::evaluator::ConsumerStatus;
+    use crate::consumer_status::sink::PartitionStatus;
+
+    fn group_status(group: &str, state: &str, offset_lag: u64, time_lag_ms: i64) -> GroupStatus {
+        GroupStatus {
+            group: group.into(),
+            labels: BTreeMap::new(),
+            coordinator_id: None,
+            protocol_type: "consumer".to_string(),
+            protocol: String::new(),
+            state: state.to_string(),
+            status: ConsumerStatus::Ok,
+            partitions: vec![PartitionStatus {
+                topic: "t".into(),
+                partition: 0,
+                host: None,
+                status: ConsumerStatus::Ok,
+                offset: 0,
+                offset_lag,
+                time_lag_ms,
+            }],
+            orphaned_partitions: Vec::new(),
+            stopped_committing: false,
+        }
+    }
+
+    fn names(groups: &[GroupStatus]) -> Vec<&str> {
+        groups.iter().map(|gs| gs.group.as_ref()).collect()
+    }
+
+    #[test]
+    fn filters_sorts_and_paginates() {
+        let groups = vec![
+            group_status("a", "Stable", 10, 5_000),
+            group_status("b", "Empty", 5_000, 1_000),
+            group_status("c", "Stable", 2_000, 90_000),
+            group_status("d", "Stable", 1_000, 2_000),
+        ];
+
+        let (page, total) = GroupsQuery::default().apply(groups.clone());
+        assert_eq!((names(&page), total), (vec!["a", "b", "c", "d"], 4));
+
+        let query: GroupsQuery =
+            serde_json::from_str(r#"{"sort":"lag","state":"stable","min_lag":1000}"#).unwrap();
+        let (page, total) = query.apply(groups.clone());
+        assert_eq!((names(&page), total), (vec!["c", "d"], 2));
+
+        let query = GroupsQuery {
+            sort: GroupsSort::TimeLag,
+            order: Some(SortOrder::Asc),
+            page: Some(2),
+            per_page: Some(3),
+            ..Default::default()
+        };
+        let (page, total) = query.apply(groups);
+        assert_eq!((names(&page), total), (vec!["c"], 4));
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use prometheus::{register_int_gauge_vec_with_registry, IntGaugeVec, Registry};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use super::evaluator::{evaluate, stopped_committing, ConsumerStatus};
+use super::hosts::host_statuses;
+
+use crate::constants::DEFAULT_STOPPED_COMMIT_INTERVALS;
+use crate::internals::{LagSink, LagSinkFuture};
+use crate::kafka_types::{Interned, TopicPartition};
+use crate::lag_register::{Lag, LagSnapshot};
+use crate::prometheus_metrics::{LABEL_GROUP, LABEL_MEMBER_HOST};
+
+const MET_STATUS_NAME: &str = "consumer_group_status";
+const MET_STATUS_HELP: &str =
+    "Status of the consumer group (0=OK, 1=WARN, 2=ERR, 3=STALLED, 4=STOPPED)";
+const MET_STOPPED_NAME: &str = "kafka_consumer_group_stopped";
+const MET_STOPPED_HELP: &str =
+    "Whether the consumer group stopped committing: '1' if no partition received a commit for many times its usual commit interval, '0' otherwise";
+const MET_ORPHANED_NAME: &str = "consumer_group_orphaned_partitions";
+const MET_ORPHANED_HELP: &str =
+    "Topic partitions the consumer group has committed offsets for, that no longer exist in the cluster";
+const MET_HOST_OFFSET_LAG_NAME: &str = "kafka_consumer_host_offset_lag_sum";
+const MET_HOST_OFFSET_LAG_HELP: &str =
+    "Sum of the offset lag of all the topic partitions assigned to members on the host, across all consumer groups";
+const MET_HOST_TIME_LAG_NAME: &str = "kafka_consumer_host_lag_milliseconds_max";
+const MET_HOST_TIME_LAG_HELP: &str =
+    "Max of the time lag (ms) of all the topic partitions assigned to members on the host, across all consumer groups";
+
+/// [`ConsumerStatus`] of a Consumer Group, and of each of its Topic Partitions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupStatus {
+    pub group: Interned,
+
+    /// Extra labels of the Consumer Group (see [`crate::kafka_types::Group::labels`]).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+
+    /// Identifier of the Broker coordinating the Consumer Group, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coordinator_id: Option<i32>,
+
+    /// Type of protocol of the Consumer Group (e.g. `consumer`, `connect`), if known.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub protocol_type: String,
+
+    /// Protocol of the Consumer Group, of its `protocol_type` (e.g. `range`), if known.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub protocol: String,
+
+    /// State of the Consumer Group (e.g. `Stable`, `Empty`), if known.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub state: String,
+
+    /// Most severe of the statuses of the Topic Partitions.
+    pub status: ConsumerStatus,
+
+    pub partitions: Vec<PartitionStatus>,
+
+    /// Topic Partitions the Consumer Group has committed offsets for, that no longer exist
+    /// in the Cluster (e.g. the Topic was deleted).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub orphaned_partitions: Vec<OrphanedPartition>,
+
+    /// Whether the Consumer Group stopped committing, for all its Topic Partitions
+    /// (see [`stopped_committing`]).
+    #[serde(skip)]
+    pub stopped_committing: bool,
+}
+
+/// A Topic Partition that a Consumer Group has committed offsets for, but no longer exists.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct OrphanedPartition {
+    pub topic: Interned,
+    pub partition: u32,
+}
+
+/// [`ConsumerStatus`] of a Topic Partition consumed by a Consumer Group, with its latest lag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PartitionStatus {
+    pub topic: Interned,
+    pub partition: u32,
+
+    /// Host of the Member the Topic Partition is assigned to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+
+    pub status: ConsumerStatus,
+    pub offset: u64,
+    pub offset_lag: u64,
+    pub time_lag_ms: i64,
+}
+
+/// Evaluates the [`ConsumerStatus`] of all Consumer Groups at every [`LagSnapshot`].
+///
+/// For each (Consumer Group, Topic Partition) pair, it keeps a sliding window of the last
+/// commits seen, and evaluates it with [`evaluate`].
+/// The latest evaluation is kept in memory, ready to be served by the `/groups` HTTP endpoint.
+pub struct ConsumerStatusSink {
+    window_size: usize,
+    forget_resets: bool,
+    stopped_intervals: u32,
+    windows: Mutex<HashMap<Interned, HashMap<TopicPartition, VecDeque<Lag>>>>,
+    latest: RwLock<Vec<GroupStatus>>,
+    metric_status: IntGaugeVec,
+    metric_stopped: IntGaugeVec,
+    metric_orphaned: IntGaugeVec,
+    metric_host_offset_lag: IntGaugeVec,
+    metric_host_time_lag: IntGaugeVec,
+}
+
+impl ConsumerStatusSink {
+    /// Create a new [`ConsumerStatusSink`].
+    ///
+    /// # Arguments
+    ///
+    /// * `window_size` - Amount of commits, per Topic Partition, to evaluate the status over
+    /// * `metrics` - Where to register the Consumer Group status metrics
+    pub fn new(window_size: usize, metrics: Arc<Registry>) -> Self {
+        let metric_status = register_int_gauge_vec_with_registry!(
+            MET_STATUS_NAME,
+            MET_STATUS_HELP,
+            &[LABEL_GROUP],
+            metrics
+        )
+        .unwrap_or_else(|_| panic!("Failed to create metric: {MET_STATUS_NAME}"));
+        let metric_stopped = register_int_gauge_vec_with_registry!(
+            MET_STOPPED_NAME,
+            MET_STOPPED_HELP,
+            &[LABEL_GROUP],
+            metrics
+        )
+        .unwrap_or_else(|_| panic!("Failed to create metric: {MET_STOPPED_NAME}"));
+        let metric_orphaned = register_int_gauge_vec_with_registry!(
+            MET_ORPHANED_NAME,
+            MET_ORPHANED_HELP,
+            &[LABEL_GROUP],
+            metrics
+        )
+        .unwrap_or_else(|_| panic!("Failed to create metric: {MET_ORPHANED_NAME}"));
+        let metric_host_offset_lag = register_int_gauge_vec_with_registry!(
+            MET_HOST_OFFSET_LAG_NAME,
+            MET_HOST_OFFSET_LAG_HELP,
+            &[LABEL_MEMBER_HOST],
+            metrics
+        )
+        .unwrap_or_else(|_| panic!("Failed to create metric: {MET_HOST_OFFSET_LAG_NAME}"));
+        let metric_host_time_lag = register_int_gauge_vec_with_registry!(
+            MET_HOST_TIME_LAG_NAME,
+            MET_HOST_TIME_LAG_HELP,
+            &[LABEL_MEMBER_HOST],
+            metrics
+        )
+        .unwrap_or_else(|_| panic!("Failed to create metric: {MET_HOST_TIME_LAG_NAME}"));
+
+        Self {
+            window_size,
+            forget_resets: false,
+            stopped_intervals: DEFAULT_STOPPED_COMMIT_INTERVALS.parse().expect("Invalid default"),
+            windows: Mutex::new(HashMap::new()),
+            latest: RwLock::new(Vec::new()),
+            metric_status,
+            metric_stopped,
+            metric_orphaned,
+            metric_host_offset_lag,
+            metric_host_time_lag,
+        }
+    }
+
+    /// Restart the window of a (Consumer Group, Topic Partition) pair, when its committed offset
+    /// is reset (i.e. moves backwards), instead of evaluating it as `ERR` until the reset leaves it.
+    ///
+    /// Only the window is restarted: the [`Lag`] is estimated from the latest committed offset
+    /// regardless, while the lag history of the Consumer Group is left untouched.
+    pub fn with_forget_resets(mut self, forget_resets: bool) -> Self {
+        self.forget_resets = forget_resets;
+        self
+    }
+
+    /// How many of its usual commit intervals a Topic Partition has to receive no commits for,
+    /// for the consumer to be considered stopped (see [`stopped_committing`]).
+    pub fn with_stopped_intervals(mut self, stopped_intervals: u32) -> Self {
+        self.stopped_intervals = stopped_intervals;
+        self
+    }
+
+    /// The latest [`GroupStatus`] of each Consumer Group, sorted by Consumer Group name.
+    ///
+    /// It will be empty until the first [`LagSnapshot`] is published.
+    pub async fn latest(&self) -> Vec<GroupStatus> {
+        self.latest.read().await.clone()
+    }
+
+    /// The latest [`GroupStatus`] of the given Consumer Group, if tracked.
+    pub async fn latest_of(&self, group: &str) -> Option<GroupStatus> {
+        self.latest.read().await.iter().find(|gs| gs.group == group).cloned()
+    }
+
+    fn update(&self, snapshot: &LagSnapshot) -> Vec<GroupStatus> {
+        let mut windows = self.windows.lock().expect("Consumer status windows lock poisoned");
+
+        // Forget about the (Consumer Group, Topic Partition) pairs no longer tracked
+        windows.retain(|g, by_tp| match snapshot.groups.get(g) {
+            Some(gwl) => {
+                by_tp.retain(|tp, _| gwl.lag_by_topic_partition.contains_key(tp));
+                true
+            },
+            None => false,
+        });
+
+        // Slide the windows forward, for each new commit
+        for (g, tp, lwo) in snapshot.iter_group_partitions() {
+            if let Some(lag) = lwo.lag() {
+                let window = windows
+                    .entry(g.clone())
+                    .or_default()
+                    .entry(tp.clone())
+                    .or_insert_with(|| VecDeque::with_capacity(self.window_size));
+
+                if self.forget_resets && window.back().is_some_and(|l| l.offset > lag.offset) {
+                    window.clear();
+                }
+                if window.back() != Some(lag) {
+                    window.push_back(lag.clone());
+                    if window.len() > self.window_size {
+                        window.pop_front();
+                    }
+                }
+            }
+        }
+
+        let mut orphaned: HashMap<&str, BTreeSet<OrphanedPartition>> = HashMap::new();
+        for (g, tp) in snapshot.orphaned_partitions() {
+            orphaned.entry(g).or_default().insert(OrphanedPartition {
+                topic: tp.topic.clone(),
+                partition: tp.partition,
+            });
+        }
+
+        let mut statuses: Vec<GroupStatus> = windows
+            .iter()
+            .map(|(g, by_tp)| {
+                let mut partitions: Vec<PartitionStatus> = by_tp
+                    .iter()
+                    .filter_map(|(tp, window)| {
+                        window.back().map(|lag| PartitionStatus {
+                            topic: tp.topic.clone(),
+                            partition: tp.partition,
+                            status: evaluate(window, self.window_size, snapshot.taken_at),
+                            host: snapshot
+                                .groups
+                                .get(g)
+                                .and_then(|gwl| gwl.lag_by_topic_partition.get(tp))
+                                .and_then(|lwo| lwo.owner())
+                                .map(|m| m.client_host.clone()),
+                            offset: lag.offset(),
+                            offset_lag: lag.offset_lag(),
+                            time_lag_ms: lag.time_lag().num_milliseconds(),
+                        })
+                    })
+                    .collect();
+                partitions.sort_by(|a, b| (&a.topic, a.partition).cmp(&(&b.topic, b.partition)));
+                let stopped: Vec<bool> = by_tp
+                    .values()
+                    .filter_map(|w| {
+                        stopped_committing(w, self.stopped_intervals, snapshot.taken_at)
+                    })
+                    .collect();
+                let group = snapshot.groups.get(g).map(|gwl| &gwl.group);
+
+                GroupStatus {
+                    group: g.clone(),
+                    labels: group.map(|group| group.labels.clone()).unwrap_or_default(),
+                    coordinator_id: group.and_then(|group| group.coordinator_id),
+                    protocol_type: group
+                        .map(|group| group.protocol_type.clone())
+                        .unwrap_or_default(),
+                    protocol: group.map(|group| group.protocol.clone()).unwrap_or_default(),
+                    state: group.map(|group| group.state.clone()).unwrap_or_default(),
+                    status: partitions
+                        .iter()
+                        .map(|ps| ps.status)
+                        .max()
+                        .unwrap_or(ConsumerStatus::Ok),
+                    partitions,
+                    orphaned_partitions: orphaned
+                        .remove(g.as_str())
+                        .map(|ops| ops.into_iter().collect())
+                        .unwrap_or_default(),
+                    stopped_committing: !stopped.is_empty() && stopped.iter().all(|s| *s),
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.group.cmp(&b.group));
+
+        statuses
+    }
+}
+
+impl LagSink for ConsumerStatusSink {
+    fn name(&self) -> &'static str {
+        "consumer_status"
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let statuses = self.update(snapshot);
+
+            let mut latest = self.latest.write().await;
+
+            // Remove the metrics of the Consumer Groups no longer tracked
+            for gs in latest.iter() {
+                if !statuses.iter().any(|s| s.group == gs.group) {
+                    let _ = self.metric_status.remove_label_values(&[&gs.group]);
+                    let _ = self.metric_stopped.remove_label_values(&[&gs.group]);
+                    let _ = self.metric_orphaned.remove_label_values(&[&gs.group]);
+                }
+            }
+            for gs in statuses.iter() {
+                self.metric_status.with_label_values(&[&gs.group]).set(gs.status.code());
+                self.metric_stopped
+                    .with_label_values(&[&gs.group])
+                    .set(i64::from(gs.stopped_committing));
+                self.metric_orphaned
+                    .with_label_values(&[&gs.group])
+                    .set(gs.orphaned_partitions.len() as i64);
+
+                // Warn only about the Topic Partitions that just became orphaned
+                let prev = latest.iter().find(|prev| prev.group == gs.group);
+                for op in gs.orphaned_partitions.iter() {
+                    if !prev.is_some_and(|prev| prev.orphaned_partitions.contains(op)) {
+                        warn!(
+                            "Group '{}' has committed offsets for '{}:{}', that no longer exists",
+                            gs.group, op.topic, op.partition
+                        );
+                    }
+                }
+            }
+
+            // Remove the metrics of the hosts no Topic Partition is assigned to anymore
+            let hosts = host_statuses(&statuses);
+            for prev in host_statuses(&latest).iter() {
+                if !hosts.iter().any(|hs| hs.host == prev.host) {
+                    let _ = self.metric_host_offset_lag.remove_label_values(&[&prev.host]);
+                    let _ = self.metric_host_time_lag.remove_label_values(&[&prev.host]);
+                }
+            }
+            for hs in hosts.iter() {
+                self.metric_host_offset_lag
+                    .with_label_values(&[&hs.host])
+                    .set(hs.offset_lag as i64);
+                self.metric_host_time_lag.with_label_values(&[&hs.host]).set(hs.max_time_lag_ms);
+            }
+
+            *latest = statuses;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::lag_register::{GroupWithLag, LagWithOwner};
+
+    /// A [`LagSnapshot`] of Group `g`, committing the given offset for Topic Partition `t:0`.
+    fn snapshot(offset: u64, secs: i64) -> LagSnapshot {
+        let taken_at = Utc::now() + Duration::seconds(secs);
+        let lag = Lag {
+            offset,
+            offset_timestamp: taken_at,
+            offset_lag: 10,
+            time_lag: Duration::seconds(1),
+        };
+        let gwl = GroupWithLag {
+            lag_by_topic_partition: BTreeMap::from([(
+                TopicPartition::new("t".to_string(), 0),
+                LagWithOwner {
+                    lag: Some(lag),
+                    owner: None,
+                },
+            )]),
+            ..Default::default()
+        };
+
+        LagSnapshot {
+            cluster_id: "c".to_string(),
+            taken_at,
+            groups: BTreeMap::from([("g".into(), Arc::new(gwl))]),
+            partitions: Vec::new(),
+        }
+    }
+
+    fn status_after(sink: &ConsumerStatusSink, offsets: &[u64]) -> ConsumerStatus {
+        offsets
+            .iter()
+            .enumerate()
+            .map(|(i, o)| sink.update(&snapshot(*o, i as i64)))
+            .last()
+            .map(|statuses| statuses[0].partitions[0].status)
+            .unwrap()
+    }
+
+    #[test]
+    fn resets_are_errors_until_they_leave_the_window() {
+        let sink = ConsumerStatusSink::new(5, Arc::new(Registry::new()));
+
+        assert_eq!(status_after(&sink, &[100, 150, 20, 30]), ConsumerStatus::Err);
+    }
+
+    #[test]
+    fn forgotten_resets_restart_the_window() {
+        let sink = ConsumerStatusSink::new(5, Arc::new(Registry::new())).with_forget_resets(true);
+
+        assert_eq!(status_after(&sink, &[100, 150, 20, 30]), ConsumerStatus::Ok);
+        let windows = sink.windows.lock().unwrap();
+        let offsets: Vec<u64> = windows["g"][&TopicPartition::new("t".to_string(), 0)]
+            .iter()
+            .map(Lag::offset)
+            .collect();
+        assert_eq!(offsets, vec![20, 30]);
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::{BTreeMap, HashMap};
+use std::f64::consts::TAU;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rdkafka::error::KafkaResult;
+
+use super::DEMO_CLUSTER_ID;
+
+use crate::cluster_status::ClusterDescription;
+use crate::consumer_groups::ConsumerGroups;
+use crate::internals::{
+    Clock, ClusterMetadata, GroupsClient, KafkaClientFuture, MetadataClient, WatermarksClient,
+};
+use crate::kafka_types::{
+    Broker, Group, GroupWithMembers, Interned, Member, MemberWithAssignment, PartitionStatus,
+    TopicConfig, TopicPartition, TopicPartitionsStatus,
+};
+
+/// How long the simulated cluster has been running for, when the simulation starts.
+const UPTIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long records are retained for, in all Topics.
+const RETENTION: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Period of the (sinusoidal) variation of the production rate.
+const PRODUCTION_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+const BROKERS: u32 = 3;
+const RACKS: [&str; 3] = ["rack-a", "rack-b", "rack-c"];
+
+/// A simulated Topic: each of its Partitions is produced to at the same (average) rate.
+struct DemoTopic {
+    name: &'static str,
+    partitions: u32,
+
+    /// Average records produced per second, to each Partition: `0` for idle Topics.
+    rate: f64,
+}
+
+const TOPICS: [DemoTopic; 5] = [
+    DemoTopic {
+        name: "orders",
+        partitions: 6,
+        rate: 50.0,
+    },
+    DemoTopic {
+        name: "payments",
+        partitions: 3,
+        rate: 10.0,
+    },
+    DemoTopic {
+        name: "clickstream",
+        partitions: 12,
+        rate: 200.0,
+    },
+    DemoTopic {
+        name: "search-updates",
+        partitions: 4,
+        rate: 20.0,
+    },
+    DemoTopic {
+        name: "audit-log",
+        partitions: 1,
+        rate: 0.0,
+    },
+];
+
+/// How a simulated Consumer Group keeps up with what is produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DemoBehaviour {
+    /// Consumes records within a couple of seconds.
+    CaughtUp,
+
+    /// Periodically builds up a backlog (e.g. a batch job), then catches up.
+    Backlogged,
+
+    /// Consumes slower than records are produced: it falls further behind, the longer it runs.
+    FallingBehind,
+
+    /// Periodically stops committing for a while (e.g. stuck on a poison pill).
+    Stalling,
+
+    /// No longer consuming: no Members, and its committed offsets never move.
+    Stopped,
+}
+
+/// A simulated Consumer Group.
+struct DemoGroup {
+    name: &'static str,
+    topics: &'static [&'static str],
+    members: usize,
+    behaviour: DemoBehaviour,
+}
+
+const GROUPS: [DemoGroup; 6] = [
+    DemoGroup {
+        name: "orders-processor",
+        topics: &["orders", "payments"],
+        members: 3,
+        behaviour: DemoBehaviour::CaughtUp,
+    },
+    DemoGroup {
+        name: "payments-reconciler",
+        topics: &["payments"],
+        members: 1,
+        behaviour: DemoBehaviour::Backlogged,
+    },
+    DemoGroup {
+        name: "clickstream-analytics",
+        topics: &["clickstream"],
+        members: 4,
+        behaviour: DemoBehaviour::FallingBehind,
+    },
+    DemoGroup {
+        name: "search-indexer",
+        topics: &["search-updates"],
+        members: 2,
+        behaviour: DemoBehaviour::Stalling,
+    },
+    DemoGroup {
+        name: "audit-archiver",
+        topics: &["audit-log"],
+        members: 1,
+        behaviour: DemoBehaviour::CaughtUp,
+    },
+    DemoGroup {
+        name: "legacy-reporting",
+        topics: &["orders"],
+        members: 0,
+        behaviour: DemoBehaviour::Stopped,
+    },
+];
+
+/// A simulated Kafka cluster, with a fixed set of Topics and Consumer Groups.
+///
+/// It implements the client traits the Emitters fetch from the cluster with (metadata,
+/// Consumer Groups, watermarks): offsets are a function of the time (as read from the [`Clock`]),
+/// so that the cluster evolves as time passes, the same way whoever looks at it.
+/// Committed offsets are emitted by [`super::DemoCommitsEmitter`].
+#[derive(Debug)]
+pub struct DemoCluster {
+    clock: Arc<dyn Clock>,
+
+    /// Time the simulation started at.
+    started_at: DateTime<Utc>,
+}
+
+impl DemoCluster {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        let started_at = clock.now();
+        Self {
+            clock,
+            started_at,
+        }
+    }
+
+    pub(super) fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Seconds the simulated cluster has been running for, at the given time.
+    fn uptime_secs(&self, at: DateTime<Utc>) -> f64 {
+        let since_start = (at - self.started_at).num_milliseconds() as f64 / 1_000.0;
+        (UPTIME.as_secs_f64() + since_start).max(0.0)
+    }
+
+    /// Latest offset of the Topic Partition, at the given time.
+    ///
+    /// The production rate oscillates around the Topic average (+/- 50%): this is its integral.
+    fn latest_offset(&self, tp: &TopicPartition, at: DateTime<Utc>) -> u64 {
+        let Some(topic) = TOPICS.iter().find(|t| t.name == tp.topic.as_str()) else {
+            return 0;
+        };
+
+        // Idle Topics were produced to, a while ago
+        if topic.rate == 0.0 {
+            return 12_345;
+        }
+
+        let t = self.uptime_secs(at);
+        let period = PRODUCTION_PERIOD.as_secs_f64();
+        let phase = tp.partition as f64;
+        let oscillation = 0.5 * period / TAU * (phase.cos() - (TAU * t / period + phase).cos());
+        (topic.rate * (t + oscillation)) as u64
+    }
+
+    /// Earliest offset of the Topic Partition, at the given time: older records were deleted.
+    fn earliest_offset(&self, tp: &TopicPartition, at: DateTime<Utc>) -> u64 {
+        match chrono::Duration::from_std(RETENTION) {
+            Ok(retention) => self.latest_offset(tp, at - retention),
+            Err(_) => 0,
+        }
+    }
+
+    /// Offset committed by the Consumer Group for the Topic Partition, at the given time, if any.
+    pub(super) fn committed_offset(
+        &self,
+        group: &str,
+        tp: &TopicPartition,
+        at: DateTime<Utc>,
+    ) -> Option<u64> {
+        let group = GROUPS.iter().find(|g| g.name == group)?;
+        let since_start = (at - self.started_at).num_seconds();
+
+        // How far behind the latest offset (in time) the Group is
+        let behind_secs = match group.behaviour {
+            DemoBehaviour::CaughtUp => 2,
+            // Builds up to 7.5 minutes of backlog over 15 minutes, then starts over
+            DemoBehaviour::Backlogged => 5 + (since_start % 900) / 2,
+            // Consumes at ~80% of the production rate
+            DemoBehaviour::FallingBehind => 60 + since_start / 5,
+            // Stops committing for 2 minutes, every 10 minutes
+            DemoBehaviour::Stalling if since_start % 600 >= 480 => return None,
+            DemoBehaviour::Stalling => 3,
+            // Stopped an hour before the simulation started
+            DemoBehaviour::Stopped => 3_600 + since_start,
+        };
+
+        let consumed_at = at - chrono::Duration::seconds(behind_secs);
+        Some(self.latest_offset(tp, consumed_at).max(self.earliest_offset(tp, at)))
+    }
+
+    /// Topic Partitions consumed by the Consumer Group.
+    pub(super) fn group_topic_partitions(&self, group: &str) -> Vec<TopicPartition> {
+        let Some(group) = GROUPS.iter().find(|g| g.name == group) else {
+            return Vec::new();
+        };
+        TOPICS
+            .iter()
+            .filter(|t| group.topics.contains(&t.name))
+            .flat_map(|t| (0..t.partitions).map(|p| TopicPartition::new(t.name, p)))
+            .collect()
+    }
+
+    /// Names of all the Consumer Groups.
+    pub(super) fn group_names(&self) -> impl Iterator<Item = &'static str> {
+        GROUPS.iter().map(|g| g.name)
+    }
+
+    fn group_with_members(&self, group: &DemoGroup) -> GroupWithMembers {
+        // Topic Partitions are assigned round-robin to the Members
+        let tps = self.group_topic_partitions(group.name);
+        let members = (0..group.members)
+            .map(|i| {
+                let id = format!("consumer-{}-{i}", group.name);
+                let member = MemberWithAssignment {
+                    member: Member {
+                        id: id.clone(),
+                        client_id: format!("{}-client", group.name),
+                        client_host: format!("/10.0.{}.{}", i / 250, i % 250 + 2),
+                        group_instance_id: None,
+                    },
+                    assignment: tps.iter().skip(i).step_by(group.members).cloned().collect(),
+                };
+                (id, member)
+            })
+            .collect::<HashMap<_, _>>();
+
+        GroupWithMembers {
+            group: Group {
+                name: Interned::new(group.name),
+                protocol_type: "consumer".to_string(),
+                protocol: if members.is_empty() {
+                    ""
+                } else {
+                    "range"
+                }
+                .to_string(),
+                state: if members.is_empty() {
+                    "Empty"
+                } else {
+                    "Stable"
+                }
+                .to_string(),
+                coordinator_id: Some(1 + group.name.len() as i32 % BROKERS as i32),
+                labels: BTreeMap::new(),
+                ..Default::default()
+            },
+            members,
+        }
+    }
+}
+
+impl MetadataClient for DemoCluster {
+    fn fetch_metadata(&self, _timeout: Duration) -> KafkaResult<ClusterMetadata> {
+        let topics = TOPICS
+            .iter()
+            .map(|t| TopicPartitionsStatus {
+                name: Interned::new(t.name),
+                id: None,
+                partitions: (0..t.partitions)
+                    .map(|p| PartitionStatus {
+                        id: p,
+                        leader_broker: 1 + p % BROKERS,
+                        replica_brokers: (1..=BROKERS).collect(),
+                        in_sync_replica_brokers: (1..=BROKERS).collect(),
+                    })
+                    .collect(),
+                config: TopicConfig::default(),
+            })
+            .collect();
+        let brokers = (1..=BROKERS)
+            .map(|id| Broker {
+                id,
+                host: format!("demo-broker-{id}"),
+                port: 9092,
+                rack: None,
+            })
+            .collect();
+
+        Ok(ClusterMetadata {
+            topics,
+            brokers,
+        })
+    }
+
+    fn fetch_cluster_id(&self, _timeout: Duration) -> Option<String> {
+        Some(DEMO_CLUSTER_ID.to_string())
+    }
+
+    fn describe_cluster(&self, _timeout: Duration) -> KafkaResult<ClusterDescription> {
+        Ok(ClusterDescription {
+            controller_id: Some(1),
+            rack_by_broker: (1..=BROKERS).zip(RACKS.map(str::to_string)).collect(),
+        })
+    }
+
+    fn fetch_topic_configs<'a>(
+        &'a self,
+        topics: &'a [&'a str],
+        _timeout: Duration,
+    ) -> KafkaClientFuture<'a, HashMap<String, TopicConfig>> {
+        let config = TopicConfig {
+            retention_ms: Some(RETENTION.as_millis() as i64),
+            retention_bytes: Some(-1),
+            cleanup_policy: Some("delete".to_string()),
+        };
+        Box::pin(
+            async move { Ok(topics.iter().map(|t| (t.to_string(), config.clone())).collect()) },
+        )
+    }
+}
+
+impl GroupsClient for DemoCluster {
+    fn fetch_group_list(
+        &self,
+        group: Option<&str>,
+        _timeout: Duration,
+    ) -> KafkaResult<ConsumerGroups> {
+        Ok(GROUPS
+            .iter()
+            .filter(|g| group.is_none_or(|name| name == g.name))
+            .map(|g| self.group_with_members(g))
+            .collect())
+    }
+}
+
+impl WatermarksClient for DemoCluster {
+    fn fetch_watermarks(
+        &self,
+        batch: &[TopicPartition],
+        _timeout: Duration,
+    ) -> KafkaResult<Vec<(TopicPartition, u64, u64)>> {
+        let now = self.clock.now();
+        Ok(batch
+            .iter()
+            .map(|tp| (tp.clone(), self.earliest_offset(tp, now), self.latest_offset(tp, now)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::internals::ManualClock;
+
+    #[test]
+    fn offsets_evolve_plausibly() {
+        let clock = Arc::new(ManualClock::default());
+        let cluster = DemoCluster::new(clock.clone());
+        let tps = TOPICS
+            .iter()
+            .flat_map(|t| (0..t.partitions).map(|p| TopicPartition::new(t.name, p)))
+            .collect::<Vec<_>>();
+
+        let mut prev = cluster.fetch_watermarks(&tps, Duration::ZERO).unwrap();
+        for _ in 0..120 {
+            clock.advance(Duration::from_secs(10));
+            let next = cluster.fetch_watermarks(&tps, Duration::ZERO).unwrap();
+            for ((tp, prev_earliest, prev_latest), (_, earliest, latest)) in prev.iter().zip(&next)
+            {
+                assert!(earliest <= latest);
+                assert!(prev_earliest <= earliest && prev_latest <= latest, "{tp} went back");
+            }
+            prev = next;
+        }
+
+        // Committed offsets are within the watermarks
+        let now = clock.now();
+        for g in GROUPS.iter() {
+            let tps = cluster.group_topic_partitions(g.name);
+            assert!(!tps.is_empty());
+            for tp in tps.iter() {
+                if let Some(committed) = cluster.committed_offset(g.name, tp, now) {
+                    assert!(committed >= cluster.earliest_offset(tp, now));
+                    assert!(committed <= cluster.latest_offset(tp, now));
+                }
+            }
+        }
+
+        // Each Topic Partition is assigned to exactly one Member
+        let groups = cluster.fetch_group_list(None, Duration::ZERO).unwrap();
+        let processor = &groups.groups[&Interned::new("orders-processor")];
+        let assigned = processor
+            .members
+            .values()
+            .flat_map(|mwa| mwa.assignment.iter().cloned())
+            .collect::<HashSet<_>>();
+        assert_eq!(assigned.len(), 9);
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use konsumer_offsets::{KonsumerOffsetsData, OffsetCommit};
+use tokio::{sync::mpsc, task::JoinHandle, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+use super::DemoCluster;
+
+use crate::internals::{Emitter, Jitter};
+
+const CHANNEL_SIZE: usize = 10_000;
+
+/// How often each simulated Consumer Group commits.
+const COMMIT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Emits the offsets committed by the Consumer Groups of a [`DemoCluster`],
+/// as [`KonsumerOffsetsData::OffsetCommit`], via a provided [`mpsc::channel`].
+///
+/// It stands in for [`crate::konsumer_offsets_data::KonsumerOffsetsDataEmitter`],
+/// as if `__consumer_offsets` of the simulated cluster was consumed.
+///
+/// It shuts down when the provided [`CancellationToken`] is cancelled.
+pub struct DemoCommitsEmitter {
+    cluster: Arc<DemoCluster>,
+    jitter: Jitter,
+}
+
+impl DemoCommitsEmitter {
+    /// Creates a new [`DemoCommitsEmitter`].
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The simulated cluster the Consumer Groups commit to
+    /// * `jitter` - Jitter applied to the interval between commits
+    pub fn new(cluster: Arc<DemoCluster>, jitter: Jitter) -> Self {
+        Self {
+            cluster,
+            jitter,
+        }
+    }
+}
+
+impl Emitter for DemoCommitsEmitter {
+    type Emitted = KonsumerOffsetsData;
+
+    /// Spawn a new async task to run the business logic of this struct.
+    ///
+    /// When this emitter gets spawned, it returns a [`mpsc::Receiver`] for [`KonsumerOffsetsData`],
+    /// and a [`JoinHandle`] to help join on the task spawned internally.
+    /// The task concludes (joins) only ones the inner task of the emitter terminates.
+    ///
+    /// # Arguments
+    ///
+    /// * `shutdown_token`: A [`CancellationToken`] that, when cancelled, will make the internal loop terminate.
+    ///
+    fn spawn(
+        &self,
+        shutdown_token: CancellationToken,
+    ) -> (mpsc::Receiver<Self::Emitted>, JoinHandle<()>) {
+        let (sx, rx) = mpsc::channel::<Self::Emitted>(CHANNEL_SIZE);
+
+        let cluster = self.cluster.clone();
+        let mut interval = self.jitter.interval(COMMIT_INTERVAL);
+
+        let join_handle = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = shutdown_token.cancelled() => {
+                        info!("Shutting down");
+                        break;
+                    },
+                }
+
+                let now = cluster.now();
+                for group in cluster.group_names() {
+                    for tp in cluster.group_topic_partitions(group) {
+                        let Some(offset) = cluster.committed_offset(group, &tp, now) else {
+                            continue;
+                        };
+                        let oc = OffsetCommit {
+                            group: group.to_string(),
+                            topic: tp.topic.to_string(),
+                            partition: tp.partition as i32,
+                            offset: offset as i64,
+                            commit_timestamp: now,
+                            ..Default::default()
+                        };
+                        if let Err(e) = Self::emit(&sx, KonsumerOffsetsData::OffsetCommit(oc)).await
+                        {
+                            error!("Failed to emit {}: {e}", std::any::type_name::<OffsetCommit>());
+                        }
+                    }
+                }
+            }
+        });
+
+        (rx, join_handle)
+    }
+}
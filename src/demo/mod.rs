@@ -0,0 +1,16 @@
+//! A simulated Kafka cluster, for demos and development (`--demo`).
+//!
+//! Its Topics are produced to, and its Consumer Groups commit, as time passes: some keep up,
+//! some fall behind or stall. It drives the same Emitters (and so Registers, sinks and HTTP
+//! endpoints) a real cluster would, without connecting to one.
+
+// Inner modules
+mod cluster;
+mod emitter;
+
+// Exports
+pub use cluster::DemoCluster;
+pub use emitter::DemoCommitsEmitter;
+
+/// Identifier of the simulated cluster.
+pub const DEMO_CLUSTER_ID: &str = "kommitted-demo";
@@ -0,0 +1,80 @@
+//! Offsets committed outside of Kafka.
+//!
+//! Some consumers don't commit their offsets to Kafka, but store them elsewhere (e.g. Spark or
+//! Flink jobs, in a database or in their checkpoints). Their offsets can be fed to the
+//! [`crate::lag_register::LagRegister`] as [`ExternalOffset`]s, either fetched periodically from
+//! [`ExternalOffsetsSource`]s, or pushed via the `POST /external-offsets` HTTP endpoint.
+
+// Inner modules
+mod source;
+
+// Exports
+pub use source::*;
+
+// Imports
+use std::sync::Arc;
+
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+use crate::internals::Jitter;
+
+const CHANNEL_SIZE: usize = 10_000;
+
+/// Protocol Type of the Consumer Groups known only from their [`ExternalOffset`]s.
+pub const EXTERNAL_PROTOCOL_TYPE: &str = "external";
+
+/// Create the channel [`ExternalOffset`]s are fed to the [`crate::lag_register::LagRegister`]
+/// through, and spawn a task per [`ExternalOffsetsSource`], that fetches from it periodically.
+///
+/// The returned [`mpsc::Sender`] can feed further [`ExternalOffset`]s (e.g. from HTTP requests):
+/// once it's dropped, and all the tasks terminated, the [`mpsc::Receiver`] is closed.
+pub fn init(
+    sources: Vec<Arc<dyn ExternalOffsetsSource>>,
+    jitter: Jitter,
+    shutdown_token: CancellationToken,
+) -> (mpsc::Sender<ExternalOffset>, mpsc::Receiver<ExternalOffset>, Vec<JoinHandle<()>>) {
+    let (sx, rx) = mpsc::channel::<ExternalOffset>(CHANNEL_SIZE);
+
+    let joins = sources
+        .into_iter()
+        .map(|source| {
+            let sx = sx.clone();
+            let shutdown_token = shutdown_token.clone();
+
+            tokio::spawn(async move {
+                debug!("Begin fetching from '{}' external offsets source", source.name());
+                let mut interval = jitter.interval(source.fetch_interval());
+
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {
+                            let offsets = match source.fetch().await {
+                                Ok(offsets) => offsets,
+                                Err(e) => {
+                                    error!("Failed to fetch from '{}' external offsets source: {e}", source.name());
+                                    continue;
+                                },
+                            };
+
+                            trace!("Fetched {} offsets from '{}' external offsets source", offsets.len(), source.name());
+                            for eo in offsets {
+                                if sx.send(eo).await.is_err() {
+                                    warn!("Receiver of external offsets dropped: stopping '{}' source", source.name());
+                                    return;
+                                }
+                            }
+                        },
+                        _ = shutdown_token.cancelled() => {
+                            info!("Shutting down");
+                            break;
+                        },
+                    }
+                }
+            })
+        })
+        .collect();
+
+    debug!("Initialized");
+    (sx, rx, joins)
+}
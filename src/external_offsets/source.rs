@@ -0,0 +1,76 @@
+use std::{future::Future, pin::Pin};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::time::Duration;
+
+use crate::kafka_types::Interned;
+
+/// Future returned by [`ExternalOffsetsSource::fetch`].
+pub type ExternalOffsetsFuture<'a> =
+    Pin<Box<dyn Future<Output = ExternalOffsetsResult<Vec<ExternalOffset>>> + Send + 'a>>;
+
+/// Offset committed outside of Kafka, by a consumer that stores its offsets elsewhere
+/// (e.g. a Spark or Flink job, storing them in a database or in its checkpoints).
+///
+/// It's tracked like the offsets committed by Consumer Groups: the `group` is whatever
+/// identifies the consumer, and shows up alongside the Consumer Groups listed by Kafka.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalOffset {
+    pub group: Interned,
+    pub topic: Interned,
+    pub partition: u32,
+    pub offset: u64,
+
+    /// UTC timestamp in milliseconds, of when the offset was committed.
+    ///
+    /// If missing, the time the offset is received at is used instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committed_at_ms: Option<i64>,
+}
+
+/// A [`Self`] is somewhere offsets committed outside of Kafka can be fetched from.
+///
+/// Every [`Self::fetch_interval`], [`Self::fetch`] is called, and the returned
+/// [`ExternalOffset`]s are fed to the [`crate::lag_register::LagRegister`].
+///
+/// The method returns a boxed [`Future`], so that [`Self`] can be used as a trait object
+/// (i.e. `Arc<dyn ExternalOffsetsSource>`), and multiple different sources can be configured
+/// at the same time.
+pub trait ExternalOffsetsSource: Send + Sync {
+    /// Name of [`Self`], used in logs.
+    fn name(&self) -> &'static str;
+
+    /// How often [`Self`] wants to be fetched from.
+    fn fetch_interval(&self) -> Duration;
+
+    /// Fetch the latest offsets committed to [`Self`].
+    fn fetch(&self) -> ExternalOffsetsFuture<'_>;
+}
+
+/// Possible errors returned by [`ExternalOffsetsSource::fetch`].
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum ExternalOffsetsError {
+    /// The offsets could not be fetched from the source.
+    #[error("Failed to fetch external offsets: {0}")]
+    Fetch(String),
+}
+
+pub type ExternalOffsetsResult<T> = Result<T, ExternalOffsetsError>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deserialize_without_commit_time() {
+        let eo: ExternalOffset = serde_json::from_str(
+            r#"{"group": "spark-job", "topic": "events", "partition": 3, "offset": 1234}"#,
+        )
+        .unwrap();
+        assert_eq!(eo.group, "spark-job");
+        assert_eq!(eo.partition, 3);
+        assert_eq!(eo.offset, 1234);
+        assert_eq!(eo.committed_at_ms, None);
+    }
+}
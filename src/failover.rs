@@ -0,0 +1,207 @@
+//! Failover between alternate entry points (i.e. `bootstrap.servers`) to the same Kafka cluster.
+//!
+//! Clients keep connecting to the brokers they discovered via the entry point they were
+//! created with: if that becomes unreachable (e.g. a load balancer or DNS issue), the only way
+//! to recover is to re-create them, against one of the alternates.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use rdkafka::{
+    config::RDKafkaLogLevel,
+    consumer::{BaseConsumer, Consumer},
+    ClientConfig,
+};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::cluster_status::ClusterStatusRegister;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+const UNREACHABLE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Alternate entry points to the same Kafka cluster, in order of preference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootstrapEndpoints {
+    endpoints: Vec<String>,
+    current: usize,
+}
+
+impl BootstrapEndpoints {
+    /// Create a new [`BootstrapEndpoints`], starting from the first (i.e. the primary).
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoints` - Each is a `bootstrap.servers` (format: 'HOST:PORT,...'): must not be empty
+    pub fn new(endpoints: Vec<String>) -> Self {
+        assert!(!endpoints.is_empty(), "At least one bootstrap endpoint is required");
+        Self {
+            endpoints,
+            current: 0,
+        }
+    }
+
+    /// Whether there are alternates to fail over to.
+    pub fn has_alternates(&self) -> bool {
+        self.endpoints.len() > 1
+    }
+
+    /// Endpoint currently in use.
+    pub fn current(&self) -> &str {
+        &self.endpoints[self.current]
+    }
+
+    /// Move on to the first reachable endpoint, trying all of them in order,
+    /// starting from (and including) the current one.
+    ///
+    /// If none is reachable, it stays on the current one.
+    pub fn first_reachable(&mut self, is_reachable: impl Fn(&str) -> bool) -> &str {
+        self.select_from(self.current, is_reachable)
+    }
+
+    /// Fail over to the next reachable endpoint, trying all the others in order,
+    /// wrapping around to the primary after the last one.
+    ///
+    /// If none is reachable, it moves on to the next one anyway.
+    pub fn fail_over(&mut self, is_reachable: impl Fn(&str) -> bool) -> &str {
+        let next = (self.current + 1) % self.endpoints.len();
+        let failed = self.current;
+        let failed_endpoint = self.endpoints[failed].clone();
+        self.select_from(next, |e| e != failed_endpoint && is_reachable(e));
+        if self.current == failed {
+            self.current = next;
+        }
+        self.current()
+    }
+
+    fn select_from(&mut self, from: usize, is_reachable: impl Fn(&str) -> bool) -> &str {
+        let len = self.endpoints.len();
+        if let Some(i) =
+            (0..len).map(|offset| (from + offset) % len).find(|i| is_reachable(&self.endpoints[*i]))
+        {
+            self.current = i;
+        }
+        self.current()
+    }
+}
+
+/// Whether the Kafka cluster is reachable via the given endpoint, with the given client configuration.
+///
+/// This is blocking: call it via [`tokio::task::spawn_blocking`] from async code.
+pub fn is_reachable(client_config: &ClientConfig, endpoint: &str) -> bool {
+    let mut client_config = client_config.clone();
+    client_config.set("bootstrap.servers", endpoint);
+    client_config.set_log_level(RDKafkaLogLevel::Critical);
+
+    let consumer: BaseConsumer = match client_config.create() {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("Failed to create client to probe '{endpoint}': {e}");
+            return false;
+        },
+    };
+    match consumer.fetch_metadata(None, PROBE_TIMEOUT) {
+        Ok(_) => true,
+        Err(e) => {
+            debug!("Endpoint '{endpoint}' not reachable: {e}");
+            false
+        },
+    }
+}
+
+/// Wait until the cluster status has not been fetched for longer than `after`:
+/// that is, the Kafka cluster is no longer reachable via the current endpoint.
+///
+/// Returns early (`false`) if the given [`CancellationToken`] is cancelled.
+pub async fn await_unreachable(
+    cs_reg: &ClusterStatusRegister,
+    after: Duration,
+    shutdown_token: CancellationToken,
+) -> bool {
+    let mut interval = tokio::time::interval(UNREACHABLE_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let Some(cs) = cs_reg.get_status().await else {
+                    continue;
+                };
+                let age = (Utc::now() - cs.fetched_at).to_std().unwrap_or_default();
+                if age > after {
+                    warn!("Cluster status not fetched for {}", humantime::format_duration(age));
+                    return true;
+                }
+            },
+            _ = shutdown_token.cancelled() => return false,
+        }
+    }
+}
+
+/// Wait until the Kafka cluster has not been reachable for longer than `after`, probing it
+/// periodically via `is_reachable` (e.g. [`is_reachable`] on the current endpoint).
+///
+/// Unlike [`await_unreachable`], this doesn't need a cluster status to have ever been fetched:
+/// it's meant for while the service is starting against an endpoint.
+///
+/// Returns early (`false`) if the given [`CancellationToken`] is cancelled.
+pub async fn await_probe_unreachable(
+    is_reachable: impl Fn() -> bool + Clone + Send + 'static,
+    after: Duration,
+    shutdown_token: CancellationToken,
+) -> bool {
+    let mut reachable_at = Instant::now();
+    let mut interval = tokio::time::interval(UNREACHABLE_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if tokio::task::spawn_blocking(is_reachable.clone()).await.unwrap_or(false) {
+                    reachable_at = Instant::now();
+                } else if reachable_at.elapsed() > after {
+                    warn!(
+                        "Cluster not reachable for {}",
+                        humantime::format_duration(reachable_at.elapsed())
+                    );
+                    return true;
+                }
+            },
+            _ = shutdown_token.cancelled() => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn endpoints() -> BootstrapEndpoints {
+        BootstrapEndpoints::new(vec!["a:9092".into(), "b:9092".into(), "c:9092".into()])
+    }
+
+    #[test]
+    fn fails_over_to_next_reachable() {
+        let mut e = endpoints();
+        assert!(e.has_alternates());
+        assert_eq!(e.first_reachable(|e| e != "a:9092"), "b:9092");
+        assert_eq!(e.fail_over(|e| e == "a:9092"), "a:9092");
+        assert_eq!(e.fail_over(|_| true), "b:9092");
+
+        // None reachable (or only the one that just failed): move on anyway
+        assert_eq!(e.fail_over(|_| false), "c:9092");
+        assert_eq!(e.fail_over(|e| e == "c:9092"), "a:9092");
+        assert_eq!(e.first_reachable(|_| false), "a:9092");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn probe_unreachable_after_a_while() {
+        let after = Duration::from_secs(60);
+        let started = Instant::now();
+        assert!(await_probe_unreachable(|| false, after, CancellationToken::new()).await);
+        assert!(started.elapsed() > after);
+
+        let shutdown_token = CancellationToken::new();
+        let probe = tokio::spawn(await_probe_unreachable(|| true, after, shutdown_token.clone()));
+        tokio::time::sleep(after * 10).await;
+        assert!(!probe.is_finished());
+        shutdown_token.cancel();
+        assert!(!probe.await.unwrap());
+    }
+}
@@ -1,21 +1,39 @@
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{collections::BTreeMap, net::SocketAddr, sync::Arc, time::Duration};
 
 use axum::{
-    extract::State,
+    extract::{FromRef, Path, Query, Request, State},
     http::{header, HeaderMap, HeaderValue, StatusCode},
-    response::IntoResponse,
-    routing::get,
-    Router,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
 };
 use prometheus::{Registry, TextEncoder};
-use tokio::net::TcpListener;
+use regex::Regex;
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
 use tokio_util::sync::CancellationToken;
 use tower_http::timeout::TimeoutLayer;
 
-use crate::cluster_status::ClusterStatusRegister;
-use crate::lag_register::LagRegister;
-use crate::partition_offsets::PartitionOffsetsRegister;
-use crate::prometheus_metrics::bespoke::*;
+use crate::cluster_status::{
+    ClusterStatus, ClusterStatusRegister, TopologyAudit, TopologyAuditEntry,
+};
+use crate::consumer_status::{
+    connector_statuses, host_statuses, ConnectorStatus, ConsumerStatusSink, GroupStatus,
+    GroupsQuery, HostStatus,
+};
+use crate::external_offsets::ExternalOffset;
+use crate::internals::{Subsystem, Subsystems};
+use crate::lag_register::{GroupLagPoint, LagHistorySink};
+use crate::leadership::Leadership;
+use crate::prometheus_metrics::PrometheusSink;
+
+mod request_id;
+
+pub use request_id::current_request_id;
 
 // TODO https://github.com/kafkesc/kommitted/issues/47
 // TODO https://github.com/kafkesc/kommitted/issues/48
@@ -28,197 +46,162 @@ const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
 #[derive(Clone)]
 struct HttpServiceState {
     cs_reg: Arc<ClusterStatusRegister>,
-    po_reg: Arc<PartitionOffsetsRegister>,
-    lag_reg: Arc<LagRegister>,
+    topology_audit: Arc<TopologyAudit>,
+    prom_sink: Arc<PrometheusSink>,
+    status_sink: Arc<ConsumerStatusSink>,
+    history_sink: Arc<LagHistorySink>,
+    external_offsets: Option<mpsc::Sender<ExternalOffset>>,
+    ready_requires_groups: Arc<Vec<Regex>>,
+    subsystems: Arc<Subsystems>,
+    leadership: Arc<Leadership>,
     metrics: Arc<Registry>,
 }
 
-pub async fn init(
+/// Binds the given address, and serves the HTTP endpoints until the given token is cancelled.
+///
+/// Returns the [`HttpServer`] to set the state to serve with, and the [`JoinHandle`] of the task.
+pub fn init(
     listen_on: SocketAddr,
-    cs_reg: Arc<ClusterStatusRegister>,
-    po_reg: Arc<PartitionOffsetsRegister>,
-    lag_reg: Arc<LagRegister>,
     shutdown_token: CancellationToken,
-    metrics: Arc<Registry>,
-) {
-    // Assemble the HTTP Service State object, that will be passed to the routes
-    let state = HttpServiceState {
-        cs_reg,
-        po_reg,
-        lag_reg,
-        metrics,
-    };
+) -> (HttpServer, JoinHandle<()>) {
+    let server = HttpServer::default();
+    let join = tokio::spawn(server.clone().serve(listen_on, shutdown_token));
 
-    // Setup Router
-    let app = Router::new()
-        // `GET /` goes to `root`
-        .route("/", get(root))
-        .route("/metrics", get(prometheus_metrics))
-        // In addition to handling shutdown gracefully (see below),
-        // enforce a request timeout just to avoid requests hanging forever.
-        .layer(TimeoutLayer::new(REQUEST_TIMEOUT))
-        .with_state(state);
+    (server, join)
+}
 
-    // Setup Connections Listener
-    info!("Begin listening on '{}'...", listen_on);
-    let listener = TcpListener::bind(listen_on).await.expect("Failed to bind to address (fatal)");
+/// HTTP server of the endpoints of a running [`crate::Kommitted`].
+///
+/// It keeps serving across instances (e.g. when re-created to renew their Kafka clients):
+/// the state of each instance is served once it's started, in place of the previous instance's.
+/// Until the first instance is, all the endpoints respond `503 Service Unavailable`.
+#[derive(Clone)]
+pub struct HttpServer {
+    state: Arc<watch::Sender<Option<HttpServiceState>>>,
+}
 
-    // Setup Server
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_token.cancelled_owned())
-        .await
-        .expect("Failed to start Server (fatal)");
+impl Default for HttpServer {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(watch::channel(None).0),
+        }
+    }
 }
 
-async fn root() -> &'static str {
-    "Hello, World!"
+impl FromRef<HttpServer> for HttpServiceState {
+    fn from_ref(server: &HttpServer) -> Self {
+        server.state.borrow().clone().expect("State is set before serving the routes")
+    }
 }
 
-async fn prometheus_metrics(State(state): State<HttpServiceState>) -> impl IntoResponse {
-    let mut status = StatusCode::OK;
-    let mut headers = HeaderMap::new();
+impl HttpServer {
+    /// Serve the state of a started instance, from now on.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_state(
+        &self,
+        cs_reg: Arc<ClusterStatusRegister>,
+        topology_audit: Arc<TopologyAudit>,
+        prom_sink: Arc<PrometheusSink>,
+        status_sink: Arc<ConsumerStatusSink>,
+        history_sink: Arc<LagHistorySink>,
+        external_offsets: Option<mpsc::Sender<ExternalOffset>>,
+        ready_requires_groups: Vec<Regex>,
+        subsystems: Arc<Subsystems>,
+        leadership: Arc<Leadership>,
+        metrics: Arc<Registry>,
+    ) {
+        // Assemble the HTTP Service State object, that will be passed to the routes
+        self.state.send_replace(Some(HttpServiceState {
+            cs_reg,
+            topology_audit,
+            prom_sink,
+            status_sink,
+            history_sink,
+            external_offsets,
+            ready_requires_groups: Arc::new(ready_requires_groups),
+            subsystems,
+            leadership,
+            metrics,
+        }));
+    }
 
-    // Procure the Cluster ID once and reuse it in all metrics that get generated
-    let cluster_id = state.cs_reg.get_cluster_id().await;
+    async fn serve(self, listen_on: SocketAddr, shutdown_token: CancellationToken) {
+        let app = self.router();
 
-    // Procure the TopicPartitions once and reuse it in all metrics that need it
-    let tps = state.cs_reg.get_topic_partitions().await;
+        // Setup Connections Listener
+        info!("Begin listening on '{}'...", listen_on);
+        let listener =
+            TcpListener::bind(listen_on).await.expect("Failed to bind to address (fatal)");
 
-    // As defined by Prometheus: https://github.com/prometheus/docs/blob/main/content/docs/instrumenting/exposition_formats.md#basic-info
-    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain; version=0.0.4"));
+        // Setup Server
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown_token.cancelled_owned())
+            .await
+            .expect("Failed to start Server (fatal)");
+    }
 
-    // Allocate a Vector of Strings to build the body of the output.
-    // The capacity is pre-calculated to try to do as little mem-alloc as possible.
-    //
-    // The capacity is necessarily a function of the number of metric types produced,
-    // and the number of topic partitions.
-    let tp_count: usize = state
-        .lag_reg
-        .lag_by_group
-        .read()
-        .await
-        .iter()
-        .map(|(_, gwl)| gwl.lag_by_topic_partition.len())
-        .sum();
-    let metric_types_count: usize = 3;
-    let headers_footers_count: usize = metric_types_count * 2;
-    let metrics_count: usize = tp_count * metric_types_count;
-    let mut body: Vec<String> = Vec::with_capacity(metrics_count + headers_footers_count);
-
-    // ----------------------------------------------------------- METRIC: consumer_partition_offset
-    consumer_partition_offset::append_headers(&mut body);
-    iter_lag_reg(&state.lag_reg, &mut body, &cluster_id, consumer_partition_offset::append_metric)
-        .await;
-
-    // ------------------------------------------------------- METRIC: consumer_partition_lag_offset
-    consumer_partition_lag_offset::append_headers(&mut body);
-    iter_lag_reg(
-        &state.lag_reg,
-        &mut body,
-        &cluster_id,
-        consumer_partition_lag_offset::append_metric,
-    )
-    .await;
-
-    // ------------------------------------------------- METRIC: consumer_partition_lag_milliseconds
-    consumer_partition_lag_milliseconds::append_headers(&mut body);
-    iter_lag_reg(
-        &state.lag_reg,
-        &mut body,
-        &cluster_id,
-        consumer_partition_lag_milliseconds::append_metric,
-    )
-    .await;
-
-    // ------------------------------------------------- METRIC: partition_earliest_available_offset
-    partition_earliest_available_offset::append_headers(&mut body);
-    for tp in tps.iter() {
-        match state.po_reg.get_earliest_available_offset(tp).await {
-            Ok(eao) => {
-                partition_earliest_available_offset::append_metric(
-                    &cluster_id,
-                    &tp.topic,
-                    tp.partition,
-                    eao,
-                    &mut body,
-                );
-            },
-            Err(e) => {
-                warn!("Unable to generate 'partition_earliest_available_offset': {e}");
-            },
-        }
+    fn router(self) -> Router {
+        Router::new()
+            // `POST /external-offsets` responds `404 Not Found`, unless enabled
+            .route("/external-offsets", post(ingest_external_offsets))
+            // `GET /` goes to `root`
+            .route("/", get(root))
+            .route("/metrics", get(prometheus_metrics))
+            .route("/groups", get(groups))
+            .route("/groups/:name", get(group))
+            .route("/groups/:name/history", get(group_history))
+            .route("/connectors", get(connectors))
+            .route("/hosts", get(hosts))
+            .route("/cluster", get(cluster))
+            .route("/cluster/changes", get(cluster_changes))
+            .route("/ready", get(ready))
+            // Until an instance is started, there is no state to serve
+            .layer(middleware::from_fn_with_state(self.clone(), unavailable_until_started))
+            // In addition to handling shutdown gracefully (see `serve`),
+            // enforce a request timeout just to avoid requests hanging forever.
+            .layer(TimeoutLayer::new(REQUEST_TIMEOUT))
+            // Identify each request, so that the logs emitted while serving it can be correlated
+            .layer(middleware::from_fn(request_id::with_request_id))
+            .with_state(self)
     }
+}
 
-    // ------------------------------------------------- METRIC: partition_latest_available_offset
-    partition_latest_available_offset::append_headers(&mut body);
-    for tp in tps.iter() {
-        match state.po_reg.get_latest_available_offset(tp).await {
-            Ok(lao) => {
-                partition_latest_available_offset::append_metric(
-                    &cluster_id,
-                    &tp.topic,
-                    tp.partition,
-                    lao,
-                    &mut body,
-                );
-            },
-            Err(e) => {
-                warn!("Unable to generate 'partition_latest_available_offset': {e}");
-            },
-        }
+/// Middleware responding `503 Service Unavailable` until the [`HttpServer`] has a state to serve.
+async fn unavailable_until_started(
+    State(server): State<HttpServer>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if server.state.borrow().is_none() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Starting").into_response();
     }
 
-    // ------------------------------------------------- METRIC: partition_earliest_tracked_offset
-    partition_earliest_tracked_offset::append_headers(&mut body);
-    for tp in tps.iter() {
-        match state.po_reg.get_earliest_tracked_offset(tp).await {
-            Ok(eto) => {
-                partition_earliest_tracked_offset::append_metric(
-                    &cluster_id,
-                    &tp.topic,
-                    tp.partition,
-                    eto.offset,
-                    eto.at.timestamp_millis(),
-                    &mut body,
-                );
-            },
-            Err(e) => {
-                warn!("Unable to generate 'partition_earliest_tracked_offset': {e}");
-            },
-        }
+    next.run(req).await
+}
+
+async fn root() -> &'static str {
+    "Hello, World!"
+}
+
+async fn prometheus_metrics(State(state): State<HttpServiceState>) -> impl IntoResponse {
+    // Until rendered and warmed up, serving no or partial lag data would feed misleading zero-lag
+    if !state.prom_sink.is_warmed_up() {
+        return (StatusCode::SERVICE_UNAVAILABLE, HeaderMap::new(), "Warming up".to_string());
     }
 
-    // ------------------------------------------------- METRIC: partition_latest_tracked_offset
-    partition_latest_tracked_offset::append_headers(&mut body);
-    for tp in tps.iter() {
-        match state.po_reg.get_latest_tracked_offset(tp).await {
-            Ok(lto) => {
-                partition_latest_tracked_offset::append_metric(
-                    &cluster_id,
-                    &tp.topic,
-                    tp.partition,
-                    lto.offset,
-                    lto.at.timestamp_millis(),
-                    &mut body,
-                );
-            },
-            Err(e) => {
-                warn!("Unable to generate 'partition_latest_tracked_offset': {e}");
-            },
-        }
+    // In high-availability mode, only the leader serves metrics
+    if !state.leadership.is_leader() {
+        return (StatusCode::SERVICE_UNAVAILABLE, HeaderMap::new(), "Standby".to_string());
     }
 
-    // --- CLUSTER METRICS ---
-    //
-    // TODO https://github.com/kafkesc/kommitted/issues/54
+    let mut status = StatusCode::OK;
+    let mut headers = HeaderMap::new();
 
-    // --- KOMMITTED INTERNAL METRICS ---
-    //
-    // TODO https://github.com/kafkesc/kommitted/issues/56
-    // TODO https://github.com/kafkesc/kommitted/issues/57
+    // As defined by Prometheus: https://github.com/prometheus/docs/blob/main/content/docs/instrumenting/exposition_formats.md#basic-info
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain; version=0.0.4"));
 
-    // Turn the bespoke metrics created so far, into a single String
-    let mut body = body.join("\n") + "\n";
+    // Begin with the bespoke metrics, rendered at the latest reconcile tick
+    let mut body = state.prom_sink.latest().await;
 
     // Append to the bespoke metrics, classic Prometheus Metrics
     let metrics_family = state.metrics.gather();
@@ -229,3 +212,159 @@ async fn prometheus_metrics(State(state): State<HttpServiceState>) -> impl IntoR
 
     (status, headers, body)
 }
+
+async fn groups(
+    State(state): State<HttpServiceState>,
+    Query(query): Query<GroupsQuery>,
+) -> (HeaderMap, Json<Vec<GroupStatus>>) {
+    let (groups, total) = query.apply(state.status_sink.latest().await);
+
+    // Total amount of Consumer Groups matching the filters, across all pages
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+
+    (headers, Json(groups))
+}
+
+async fn group(
+    State(state): State<HttpServiceState>,
+    Path(name): Path<String>,
+) -> Result<Json<GroupStatus>, StatusCode> {
+    state.status_sink.latest_of(&name).await.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn group_history(
+    State(state): State<HttpServiceState>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<GroupLagPoint>>, StatusCode> {
+    // Consumer Groups that are not tracked have no history
+    state.history_sink.history(&name).await.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn connectors(State(state): State<HttpServiceState>) -> Json<Vec<ConnectorStatus>> {
+    Json(connector_statuses(&state.status_sink.latest().await))
+}
+
+async fn hosts(State(state): State<HttpServiceState>) -> Json<Vec<HostStatus>> {
+    Json(host_statuses(&state.status_sink.latest().await))
+}
+
+async fn cluster(
+    State(state): State<HttpServiceState>,
+) -> Result<Json<Arc<ClusterStatus>>, StatusCode> {
+    // Until the first cluster status is fetched, there is nothing to serve
+    state.cs_reg.get_status().await.map(Json).ok_or(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+async fn cluster_changes(State(state): State<HttpServiceState>) -> Json<Vec<TopologyAuditEntry>> {
+    Json(state.topology_audit.entries().await)
+}
+
+async fn ingest_external_offsets(
+    State(state): State<HttpServiceState>,
+    Json(offsets): Json<Vec<ExternalOffset>>,
+) -> StatusCode {
+    let Some(sx) = state.external_offsets else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    for eo in offsets {
+        if sx.send(eo).await.is_err() {
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+    }
+    StatusCode::ACCEPTED
+}
+
+async fn ready(
+    State(state): State<HttpServiceState>,
+) -> (StatusCode, Json<BTreeMap<Subsystem, bool>>) {
+    // Not ready if any essential subsystem was disabled: the others only degrade the service.
+    // Nor until warmed up, or while a standby, as the lag metrics are not served then.
+    // Nor until the lag of the required Consumer Groups is known, as alerts depend on it.
+    let status = if state.subsystems.is_live()
+        && state.prom_sink.is_warmed_up()
+        && state.leadership.is_leader()
+        && has_required_groups(&state).await
+    {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(state.subsystems.status()))
+}
+
+/// Whether each of the `--ready-requires-groups` regexes matches a Consumer Group with known lag.
+async fn has_required_groups(state: &HttpServiceState) -> bool {
+    if state.ready_requires_groups.is_empty() {
+        return true;
+    }
+
+    let groups = state.status_sink.latest().await;
+    let with_lag: Vec<&str> =
+        groups.iter().filter(|gs| !gs.partitions.is_empty()).map(|gs| gs.group.as_ref()).collect();
+
+    state.ready_requires_groups.iter().all(|re| {
+        let found = with_lag.iter().any(|g| re.is_match(g));
+        if !found {
+            debug!("Not ready: no Group matching '{re}' with known lag yet");
+        }
+        found
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    use crate::lag_register::{LagHistoryConfig, LagTrendConfig};
+
+    use super::*;
+
+    async fn status_of(server: &HttpServer, req: Request) -> StatusCode {
+        server.clone().router().oneshot(req).await.unwrap().status()
+    }
+
+    fn get(uri: &str) -> Request {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn unavailable_until_started() {
+        let server = HttpServer::default();
+        assert_eq!(
+            status_of(&server, get("/cluster/changes")).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let metrics = Arc::new(Registry::new());
+        let (_cs_sx, cs_rx) = mpsc::channel(1);
+        server.set_state(
+            Arc::new(ClusterStatusRegister::new(None, cs_rx, metrics.clone())),
+            Arc::new(TopologyAudit::default()),
+            Arc::new(PrometheusSink::new()),
+            Arc::new(ConsumerStatusSink::new(10, metrics.clone())),
+            Arc::new(LagHistorySink::new(
+                LagHistoryConfig::default(),
+                LagTrendConfig::default(),
+                metrics.clone(),
+            )),
+            None,
+            Vec::new(),
+            Arc::new(Subsystems::new(false, metrics.clone())),
+            Arc::new(Leadership::new(false, metrics.clone())),
+            metrics,
+        );
+        assert_eq!(status_of(&server, get("/cluster/changes")).await, StatusCode::OK);
+        assert_eq!(status_of(&server, get("/cluster")).await, StatusCode::SERVICE_UNAVAILABLE);
+
+        // Not enabled
+        let post = Request::post("/external-offsets")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("[]"))
+            .unwrap();
+        assert_eq!(status_of(&server, post).await, StatusCode::NOT_FOUND);
+    }
+}
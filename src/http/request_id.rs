@@ -0,0 +1,111 @@
+use std::time::Instant;
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+/// Header carrying the identifier of the request: honored if set, and always set on the response.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// [W3C Trace Context](https://www.w3.org/TR/trace-context/) header: its trace-id identifies the request.
+const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Longest identifier honored: longer ones are replaced by a generated one.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Identifier of the HTTP request being served by the current task, if any.
+///
+/// Included in the log lines emitted while serving it, so that they can be correlated
+/// with the client that sent it (e.g. a specific Prometheus scrape).
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Middleware serving each request with its identifier (see [`current_request_id`]),
+/// and returning it in the `X-Request-Id` response header.
+pub(super) async fn with_request_id(req: Request, next: Next) -> Response {
+    let id = request_id_of(req.headers());
+    let (method, path) = (req.method().clone(), req.uri().path().to_string());
+
+    REQUEST_ID
+        .scope(id.clone(), async move {
+            let started_at = Instant::now();
+            let mut res = next.run(req).await;
+            debug!("Served {method} {path}: {} in {:?}", res.status(), started_at.elapsed());
+
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            res
+        })
+        .await
+}
+
+/// Identifier of the request: the trace-id of `traceparent`, or `X-Request-Id`, if valid;
+/// otherwise, a newly generated one.
+fn request_id_of(headers: &HeaderMap) -> String {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    let trace_id = header(TRACEPARENT_HEADER).and_then(|tp| {
+        // Format: '{version}-{trace-id}-{parent-id}-{trace-flags}'
+        let trace_id = tp.split('-').nth(1)?;
+        let valid = trace_id.len() == 32
+            && trace_id.chars().all(|c| c.is_ascii_hexdigit())
+            && trace_id.chars().any(|c| c != '0');
+        valid.then_some(trace_id)
+    });
+    let request_id = header(REQUEST_ID_HEADER).filter(|id| {
+        !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.chars().all(|c| c.is_ascii_graphic())
+    });
+
+    trace_id
+        .or(request_id)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs.iter().map(|(k, v)| (k.parse().unwrap(), HeaderValue::from_static(v))).collect()
+    }
+
+    #[test]
+    fn honors_incoming_identifiers() {
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        assert_eq!(
+            request_id_of(&headers(&[
+                (TRACEPARENT_HEADER, traceparent),
+                (REQUEST_ID_HEADER, "abc")
+            ])),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(request_id_of(&headers(&[(REQUEST_ID_HEADER, "scrape-42")])), "scrape-42");
+
+        // Invalid ones are replaced by a generated one
+        let invalid_traceparent = "00-00000000000000000000000000000000-00f067aa0ba902b7-01";
+        let generated = request_id_of(&headers(&[
+            (TRACEPARENT_HEADER, invalid_traceparent),
+            (REQUEST_ID_HEADER, "with spaces"),
+        ]));
+        assert_eq!(generated.len(), 32);
+        assert_ne!(generated, request_id_of(&HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn scopes_identifier_to_the_request() {
+        assert_eq!(current_request_id(), None);
+        let id = REQUEST_ID.scope("abc".to_string(), async { current_request_id() }).await;
+        assert_eq!(id.as_deref(), Some("abc"));
+    }
+}
@@ -9,6 +9,8 @@ const READYNESS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 /// An [`Self`] is ready once [`Self::is_ready`] returns `true`.
 /// The trait is built on _async/await_. The waiting is done by calling [`Self::await_ready`]:
 /// a [`CancellationToken`] is provided in case the _awaiting loop_ has to be interrupted.
+// Only implemented within this crate, where the `Send`-ness of the futures is known.
+#[allow(async_fn_in_trait)]
 pub trait Awaitable {
     /// Returns `true` if [`Self`] is ready, `false` otherwise.
     ///
@@ -0,0 +1,88 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::mem;
+use std::sync::Mutex;
+
+/// Keys (e.g. Consumer Groups, Topic Partitions) of a Register that changed since last taken:
+/// only those need reading again, to update a copy of it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Changes<K: Eq + Hash> {
+    /// Only these keys changed (i.e. were added, updated or removed).
+    Some(HashSet<K>),
+
+    /// Any key might have changed (e.g. nothing was taken yet).
+    #[default]
+    All,
+}
+
+impl<K: Eq + Hash> Changes<K> {
+    /// No key changed.
+    pub fn none() -> Self {
+        Self::Some(HashSet::new())
+    }
+
+    /// Whether no key changed.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Self::Some(keys) if keys.is_empty())
+    }
+
+    /// Whether the given key might have changed.
+    pub fn contains(&self, key: &K) -> bool {
+        match self {
+            Self::Some(keys) => keys.contains(key),
+            Self::All => true,
+        }
+    }
+}
+
+/// Tracks the [`Changes`] of a Register, from when they are made to when they are taken.
+///
+/// Changes are meant to be taken by a single reader (e.g. the one updating the lag snapshot):
+/// taking them resets the tracking.
+#[derive(Debug, Default)]
+pub struct ChangesTracker<K: Eq + Hash> {
+    changes: Mutex<Changes<K>>,
+}
+
+impl<K: Eq + Hash> ChangesTracker<K> {
+    /// Mark the given keys as changed: call it once the change is applied.
+    pub fn mark(&self, keys: impl IntoIterator<Item = K>) {
+        if let Changes::Some(changed) = &mut *self.changes.lock().expect("Changes lock poisoned") {
+            changed.extend(keys);
+        }
+    }
+
+    /// Mark any key as changed.
+    pub fn mark_all(&self) {
+        *self.changes.lock().expect("Changes lock poisoned") = Changes::All;
+    }
+
+    /// Take the [`Changes`] marked since last taken.
+    pub fn take(&self) -> Changes<K> {
+        mem::replace(&mut *self.changes.lock().expect("Changes lock poisoned"), Changes::none())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn takes_the_changes_marked_since_last_taken() {
+        let tracker = ChangesTracker::<&str>::default();
+        assert_eq!(tracker.take(), Changes::All);
+        assert!(tracker.take().is_empty());
+
+        tracker.mark(["a", "b"]);
+        tracker.mark(["a"]);
+        assert_eq!(tracker.take(), Changes::Some(HashSet::from(["a", "b"])));
+        assert!(tracker.take().is_empty());
+
+        tracker.mark(["a"]);
+        tracker.mark_all();
+        tracker.mark(["b"]);
+        let changes = tracker.take();
+        assert_eq!(changes, Changes::All);
+        assert!(changes.contains(&"c"));
+    }
+}
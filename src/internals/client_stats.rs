@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use prometheus::{
+    register_gauge_vec_with_registry, register_int_gauge_vec_with_registry, GaugeVec, IntGaugeVec,
+    Registry,
+};
+use rdkafka::{
+    config::FromClientConfigAndContext, consumer::ConsumerContext, error::KafkaResult,
+    statistics::Statistics, ClientConfig, ClientContext,
+};
+
+use crate::prometheus_metrics::{LABEL_BROKER_ID, LABEL_CLIENT};
+
+const MET_BROKER_RTT_NAME: &str = "rdkafka_broker_rtt_milliseconds";
+const MET_BROKER_RTT_HELP: &str =
+    "Average round-trip time (ms) of the requests of the client to the broker, over the last statistics interval";
+const MET_BROKER_OUTBUF_NAME: &str = "rdkafka_broker_outbuf_requests";
+const MET_BROKER_OUTBUF_HELP: &str = "Requests of the client waiting to be sent to the broker";
+const MET_BROKER_WAITRESP_NAME: &str = "rdkafka_broker_waitresp_requests";
+const MET_BROKER_WAITRESP_HELP: &str =
+    "Requests of the client sent to the broker, and waiting for a response";
+const MET_BROKER_THROTTLE_NAME: &str = "rdkafka_broker_throttle_time_milliseconds";
+const MET_BROKER_THROTTLE_HELP: &str =
+    "Average time (ms) the broker throttled the requests of the client for, over the last statistics interval";
+const MET_TX_BYTES_NAME: &str = "rdkafka_tx_bytes";
+const MET_TX_BYTES_HELP: &str =
+    "Bytes transmitted by the client to the brokers, since it was created";
+const MET_RX_BYTES_NAME: &str = "rdkafka_rx_bytes";
+const MET_RX_BYTES_HELP: &str =
+    "Bytes received by the client from the brokers, since it was created";
+const MET_REPLYQ_NAME: &str = "rdkafka_replyq";
+const MET_REPLYQ_HELP: &str =
+    "Operations (callbacks, events, etc.) of the client waiting in its queue to be served";
+const MET_FETCHQ_NAME: &str = "rdkafka_consumer_fetch_queue_messages";
+const MET_FETCHQ_HELP: &str =
+    "Messages fetched by the consumer client, waiting in its queue to be consumed";
+
+/// Exports the statistics of the librdkafka clients, as Prometheus Metrics.
+///
+/// librdkafka emits its statistics, every `statistics.interval.ms`, only to the clients
+/// that were created with a [`ClientStatsContext`]: see [`ClientStats::create`].
+/// An interval of zero disables them.
+pub struct ClientStats {
+    interval: Duration,
+
+    /// (Client, Broker id) pairs currently throttled, to only warn when throttling starts.
+    throttled: Mutex<HashSet<(String, i32)>>,
+
+    // Prometheus Metrics
+    metric_broker_rtt: GaugeVec,
+    metric_broker_outbuf: IntGaugeVec,
+    metric_broker_waitresp: IntGaugeVec,
+    metric_broker_throttle: GaugeVec,
+    metric_tx_bytes: IntGaugeVec,
+    metric_rx_bytes: IntGaugeVec,
+    metric_replyq: IntGaugeVec,
+    metric_fetchq: IntGaugeVec,
+}
+
+impl ClientStats {
+    /// Create a new [`ClientStats`].
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How often librdkafka emits the statistics of each client
+    /// * `metrics` - Where to register the metrics of the clients
+    pub fn new(interval: Duration, metrics: Arc<Registry>) -> Self {
+        Self {
+            interval,
+            throttled: Mutex::new(HashSet::new()),
+            metric_broker_rtt: register_gauge_vec_with_registry!(
+                MET_BROKER_RTT_NAME,
+                MET_BROKER_RTT_HELP,
+                &[LABEL_CLIENT, LABEL_BROKER_ID],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_BROKER_RTT_NAME}")),
+            metric_broker_outbuf: register_int_gauge_vec_with_registry!(
+                MET_BROKER_OUTBUF_NAME,
+                MET_BROKER_OUTBUF_HELP,
+                &[LABEL_CLIENT, LABEL_BROKER_ID],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_BROKER_OUTBUF_NAME}")),
+            metric_broker_waitresp: register_int_gauge_vec_with_registry!(
+                MET_BROKER_WAITRESP_NAME,
+                MET_BROKER_WAITRESP_HELP,
+                &[LABEL_CLIENT, LABEL_BROKER_ID],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_BROKER_WAITRESP_NAME}")),
+            metric_broker_throttle: register_gauge_vec_with_registry!(
+                MET_BROKER_THROTTLE_NAME,
+                MET_BROKER_THROTTLE_HELP,
+                &[LABEL_CLIENT, LABEL_BROKER_ID],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_BROKER_THROTTLE_NAME}")),
+            metric_tx_bytes: register_int_gauge_vec_with_registry!(
+                MET_TX_BYTES_NAME,
+                MET_TX_BYTES_HELP,
+                &[LABEL_CLIENT],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_TX_BYTES_NAME}")),
+            metric_rx_bytes: register_int_gauge_vec_with_registry!(
+                MET_RX_BYTES_NAME,
+                MET_RX_BYTES_HELP,
+                &[LABEL_CLIENT],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_RX_BYTES_NAME}")),
+            metric_replyq: register_int_gauge_vec_with_registry!(
+                MET_REPLYQ_NAME,
+                MET_REPLYQ_HELP,
+                &[LABEL_CLIENT],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_REPLYQ_NAME}")),
+            metric_fetchq: register_int_gauge_vec_with_registry!(
+                MET_FETCHQ_NAME,
+                MET_FETCHQ_HELP,
+                &[LABEL_CLIENT],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_FETCHQ_NAME}")),
+        }
+    }
+
+    /// Set `statistics.interval.ms` on the given [`ClientConfig`].
+    ///
+    /// Only for clients created with a [`ClientStatsContext`]: the default context
+    /// logs the statistics instead.
+    pub fn configure(&self, client_config: &mut ClientConfig) {
+        client_config.set("statistics.interval.ms", self.interval.as_millis().to_string());
+    }
+
+    /// A [`ClientStatsContext`], for the client of the given name.
+    pub fn context(self: &Arc<Self>, client: &'static str) -> ClientStatsContext {
+        ClientStatsContext {
+            client,
+            stats: self.clone(),
+        }
+    }
+
+    /// Create a client, whose statistics are exported under the given name.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_config` - Kafka client configuration: it's cloned, and statistics enabled on it
+    /// * `client` - Name of the client, used as the `client` label of the metrics
+    pub fn create<T>(
+        self: &Arc<Self>,
+        client_config: &ClientConfig,
+        client: &'static str,
+    ) -> KafkaResult<T>
+    where
+        T: FromClientConfigAndContext<ClientStatsContext>,
+    {
+        let mut client_config = client_config.clone();
+        self.configure(&mut client_config);
+        client_config.create_with_context(self.context(client))
+    }
+
+    /// Update the metrics of the client of the given name, with its latest [`Statistics`].
+    pub fn record(&self, client: &str, stats: &Statistics) {
+        self.metric_tx_bytes.with_label_values(&[client]).set(stats.tx_bytes);
+        self.metric_rx_bytes.with_label_values(&[client]).set(stats.rx_bytes);
+        self.metric_replyq.with_label_values(&[client]).set(stats.replyq);
+        self.metric_fetchq.with_label_values(&[client]).set(
+            stats.topics.values().flat_map(|t| t.partitions.values()).map(|p| p.fetchq_cnt).sum(),
+        );
+
+        // Bootstrap and internal brokers have a negative id: only the actual ones are reported
+        for broker in stats.brokers.values().filter(|b| b.nodeid >= 0) {
+            let broker_id = broker.nodeid.to_string();
+            let labels = [client, broker_id.as_str()];
+
+            if let Some(rtt) = broker.rtt.as_ref() {
+                // librdkafka reports it in microseconds
+                self.metric_broker_rtt.with_label_values(&labels).set(rtt.avg as f64 / 1000_f64);
+            }
+            self.metric_broker_outbuf.with_label_values(&labels).set(broker.outbuf_cnt);
+            self.metric_broker_waitresp.with_label_values(&labels).set(broker.waitresp_cnt);
+
+            if let Some(throttle) = broker.throttle.as_ref() {
+                self.metric_broker_throttle.with_label_values(&labels).set(throttle.avg as f64);
+                self.track_throttling(client, broker.nodeid, throttle.max);
+            }
+        }
+    }
+
+    /// Warn when the broker starts throttling the client, and log when it stops.
+    ///
+    /// Throttling (i.e. quotas) delays the requests for metadata and offsets: nothing fails,
+    /// but the lag data goes stale, so it's worth making it loud.
+    fn track_throttling(&self, client: &str, broker_id: i32, max_throttle_ms: i64) {
+        let mut throttled = self.throttled.lock().expect("Throttled clients lock poisoned");
+        let key = (client.to_string(), broker_id);
+
+        if max_throttle_ms > 0 {
+            if throttled.insert(key) {
+                warn!(
+                    "Client '{client}' is being throttled by broker {broker_id} (up to {max_throttle_ms}ms): its requests are delayed by quotas, and lag data will be stale"
+                );
+            }
+        } else if throttled.remove(&key) {
+            info!("Client '{client}' is no longer throttled by broker {broker_id}");
+        }
+    }
+}
+
+/// [`ClientContext`] that hands the [`Statistics`] of a client over to [`ClientStats`].
+///
+/// It can also be wrapped by another context, that delegates [`ClientContext::stats`] to it.
+#[derive(Clone)]
+pub struct ClientStatsContext {
+    client: &'static str,
+    stats: Arc<ClientStats>,
+}
+
+impl ClientContext for ClientStatsContext {
+    fn stats(&self, statistics: Statistics) {
+        trace!("Statistics of client '{}' received", self.client);
+        self.stats.record(self.client, &statistics);
+    }
+}
+
+impl ConsumerContext for ClientStatsContext {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rdkafka::statistics::{Broker, Partition, Topic, Window};
+    use std::collections::HashMap;
+
+    #[test]
+    fn records_statistics() {
+        let broker = |nodeid: i32, rtt_avg: i64, throttle_avg: i64| Broker {
+            nodeid,
+            rtt: Some(Window {
+                avg: rtt_avg,
+                ..Default::default()
+            }),
+            throttle: Some(Window {
+                avg: throttle_avg,
+                max: throttle_avg * 2,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let partition = |fetchq_cnt: i64| Partition {
+            fetchq_cnt,
+            ..Default::default()
+        };
+        let stats = Statistics {
+            tx_bytes: 1024,
+            rx_bytes: 4096,
+            brokers: HashMap::from([
+                ("bootstrap".to_string(), broker(-1, 9000, 0)),
+                ("broker1".to_string(), broker(1, 2500, 150)),
+            ]),
+            topics: HashMap::from([(
+                "t".to_string(),
+                Topic {
+                    partitions: HashMap::from([(0, partition(3)), (1, partition(4))]),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let client_stats = ClientStats::new(Duration::from_secs(1), Arc::new(Registry::new()));
+        client_stats.record("test", &stats);
+
+        assert_eq!(client_stats.metric_tx_bytes.with_label_values(&["test"]).get(), 1024);
+        assert_eq!(client_stats.metric_rx_bytes.with_label_values(&["test"]).get(), 4096);
+        assert_eq!(client_stats.metric_fetchq.with_label_values(&["test"]).get(), 7);
+        assert_eq!(client_stats.metric_broker_rtt.with_label_values(&["test", "1"]).get(), 2.5);
+        assert!(client_stats.metric_broker_rtt.remove_label_values(&["test", "-1"]).is_err());
+        assert_eq!(
+            client_stats.metric_broker_throttle.with_label_values(&["test", "1"]).get(),
+            150.0
+        );
+        assert!(client_stats.throttled.lock().unwrap().contains(&("test".to_string(), 1)));
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+/// Source of the current time, for the logic that depends on it (e.g. expiring Groups,
+/// or telling recent commits from old ones).
+///
+/// Injected, instead of reading the system clock directly, so that such logic can be tested
+/// (see [`ManualClock`]).
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Current wall clock time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Current monotonic time, to measure elapsed time with.
+    fn instant(&self) -> Instant;
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to (see [`ManualClock::advance`]).
+#[derive(Debug)]
+pub struct ManualClock {
+    started_at: (DateTime<Utc>, Instant),
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Creates a new [`ManualClock`], stopped at the given wall clock time.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            started_at: (now, Instant::now()),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().expect("Clock lock poisoned") += by;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().expect("Clock lock poisoned")
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.started_at.0 + chrono::Duration::from_std(self.elapsed()).unwrap_or_default()
+    }
+
+    fn instant(&self) -> Instant {
+        self.started_at.1 + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn manual_clock_moves_only_when_told() {
+        let t0 = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = ManualClock::new(t0);
+        let i0 = clock.instant();
+        assert_eq!(clock.now(), t0);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), t0 + chrono::Duration::seconds(90));
+        assert_eq!(clock.instant() - i0, Duration::from_secs(90));
+    }
+}
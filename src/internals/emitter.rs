@@ -1,12 +1,16 @@
-use tokio::{sync::mpsc, task::JoinHandle, time::Interval};
+use tokio::{sync::mpsc, task::JoinHandle};
 use tokio_util::sync::CancellationToken;
 
+use super::JitteredInterval;
+
 /// Type that emits an [`Send`]-able object via a [`mpsc::Receiver`].
 /// Use this when you expect to have a single receiver.
 ///
 /// It terminates itself when [`CancellationToken`] is cancelled (elsewhere).
 ///
 /// Awaiting for its termination should be done via the returned [`JoinHandle`].
+// Only implemented within this crate, where the `Send`-ness of the futures is known.
+#[allow(async_fn_in_trait)]
 pub trait Emitter {
     type Emitted: Send;
 
@@ -21,11 +25,11 @@ pub trait Emitter {
     ///
     /// * `sender` - The [`mpsc::Sender`] side of the [`mpsc::Receiver`] returned by `spawn()`
     /// * `emitted` - The [`Self::Emitted`] that implementors of this trait emit
-    /// * `interval` - For emitting, await for the next [`Interval::tick`]
+    /// * `interval` - For emitting, await for the next [`JitteredInterval::tick`]
     async fn emit_with_interval(
         sender: &mpsc::Sender<Self::Emitted>,
         emitted: Self::Emitted,
-        interval: &mut Interval,
+        interval: &mut JitteredInterval,
     ) -> Result<(), mpsc::error::SendError<Self::Emitted>> {
         // Wait for the next tick.
         // This is here so we can allow preemption inside a `select!` case
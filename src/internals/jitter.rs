@@ -0,0 +1,91 @@
+use tokio::time::{interval, Duration, Instant, Interval, MissedTickBehavior};
+
+/// Random jitter, applied to the periodic work of the service (e.g. fetching from the cluster).
+///
+/// Many instances starting at the same time (e.g. one per cluster, or HA pairs) would otherwise
+/// keep doing their periodic work in lockstep, synchronizing load spikes against the brokers.
+/// A jitter of zero (the default) leaves periods untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Jitter {
+    pct: f64,
+}
+
+impl Jitter {
+    /// Create a new [`Jitter`], randomly adjusting periods by up to `pct` percent, either way.
+    pub fn new(pct: f64) -> Self {
+        Self {
+            pct: pct.clamp(0_f64, 100_f64),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.pct > 0_f64
+    }
+
+    /// Randomly adjust the `period`, by up to the percent of this [`Jitter`], either way.
+    pub fn apply(&self, period: Duration) -> Duration {
+        if !self.is_enabled() {
+            return period;
+        }
+
+        let factor = 1_f64 + (fastrand::f64() * 2_f64 - 1_f64) * self.pct / 100_f64;
+        period.mul_f64(factor)
+    }
+
+    /// Create a new [`JitteredInterval`], ticking every `period` (adjusted by this [`Jitter`]).
+    pub(crate) fn interval(&self, period: Duration) -> JitteredInterval {
+        JitteredInterval {
+            inner: interval(period),
+            period,
+            jitter: *self,
+        }
+    }
+}
+
+/// An [`Interval`], whose every tick is scheduled after a period adjusted by a [`Jitter`].
+///
+/// Like [`Interval`], the first tick completes immediately.
+#[derive(Debug)]
+pub struct JitteredInterval {
+    inner: Interval,
+    period: Duration,
+    jitter: Jitter,
+}
+
+impl JitteredInterval {
+    /// See [`Interval::tick`].
+    pub async fn tick(&mut self) -> Instant {
+        let at = self.inner.tick().await;
+        if self.jitter.is_enabled() {
+            self.inner.reset_after(self.jitter.apply(self.period));
+        }
+        at
+    }
+
+    /// See [`Interval::reset`].
+    pub fn reset(&mut self) {
+        self.inner.reset_after(self.jitter.apply(self.period));
+    }
+
+    /// See [`Interval::set_missed_tick_behavior`].
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.inner.set_missed_tick_behavior(behavior);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn applies_within_bounds() {
+        let period = Duration::from_secs(10);
+        assert_eq!(Jitter::default().apply(period), period);
+
+        let jitter = Jitter::new(20_f64);
+        for _ in 0..1000 {
+            let jittered = jitter.apply(period);
+            assert!(jittered >= Duration::from_secs(8) && jittered <= Duration::from_secs(12));
+        }
+    }
+}
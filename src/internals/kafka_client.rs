@@ -0,0 +1,106 @@
+use std::{collections::HashMap, future::Future, pin::Pin, time::Duration};
+
+use rdkafka::error::KafkaResult;
+use uuid::Uuid;
+
+use crate::cluster_status::ClusterDescription;
+use crate::consumer_groups::ConsumerGroups;
+use crate::kafka_types::{Broker, TopicConfig, TopicPartition, TopicPartitionsStatus};
+
+/// Future returned by the asynchronous methods of [`MetadataClient`].
+pub type KafkaClientFuture<'a, T> = Pin<Box<dyn Future<Output = KafkaResult<T>> + Send + 'a>>;
+
+/// Topics and Brokers of the Kafka cluster, as reported by its metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ClusterMetadata {
+    pub topics: Vec<TopicPartitionsStatus>,
+    pub brokers: Vec<Broker>,
+}
+
+/// Source of the metadata of the Kafka cluster, driving
+/// [`crate::cluster_status::ClusterStatusEmitter`].
+///
+/// Implemented by the Kafka clients of [`rdkafka`]; other implementations (e.g. fakes, for testing)
+/// can be used in their place. Only [`Self::fetch_metadata`] is required: by default, the other
+/// methods have nothing to add to it.
+///
+/// The synchronous methods are blocking, like [`rdkafka::client::Client::fetch_metadata`].
+pub trait MetadataClient: Send + Sync {
+    /// Fetch the Topics (with their Partitions) and Brokers of the cluster.
+    fn fetch_metadata(&self, timeout: Duration) -> KafkaResult<ClusterMetadata>;
+
+    /// Fetch the identifier (i.e. `cluster.id`) of the cluster, if set.
+    fn fetch_cluster_id(&self, _timeout: Duration) -> Option<String> {
+        None
+    }
+
+    /// Describe the cluster: controller and Brokers rack.
+    fn describe_cluster(&self, _timeout: Duration) -> KafkaResult<ClusterDescription> {
+        Ok(ClusterDescription::default())
+    }
+
+    /// Fetch the identifier (i.e. `topic.id`) of each of the given Topics.
+    fn describe_topic_ids(
+        &self,
+        _topics: &[&str],
+        _timeout: Duration,
+    ) -> KafkaResult<HashMap<String, Uuid>> {
+        Ok(HashMap::new())
+    }
+
+    /// Fetch the configuration of each of the given Topics.
+    fn fetch_topic_configs<'a>(
+        &'a self,
+        _topics: &'a [&'a str],
+        _timeout: Duration,
+    ) -> KafkaClientFuture<'a, HashMap<String, TopicConfig>> {
+        Box::pin(async { Ok(HashMap::new()) })
+    }
+}
+
+/// Source of the Consumer Groups of the Kafka cluster, driving
+/// [`crate::consumer_groups::ConsumerGroupsEmitter`].
+///
+/// This is blocking, like [`rdkafka::client::Client::fetch_group_list`].
+pub trait GroupsClient: Send + Sync {
+    /// List all the Consumer Groups, or describe only the given one.
+    fn fetch_group_list(
+        &self,
+        group: Option<&str>,
+        timeout: Duration,
+    ) -> KafkaResult<ConsumerGroups>;
+}
+
+/// Source of the offsets committed by Consumer Groups, driving
+/// [`crate::committed_offsets::CommittedOffsetsEmitter`].
+///
+/// This is blocking: call it via [`tokio::task::spawn_blocking`].
+pub trait CommittedOffsetsClient: Send + Sync {
+    /// List all the Consumer Groups, with their Members.
+    fn list_groups(&self, timeout: Duration) -> KafkaResult<ConsumerGroups>;
+
+    /// Fetch the offsets committed by the given Consumer Group, for the given Topic Partitions.
+    ///
+    /// Topic Partitions the Consumer Group never committed an offset for are omitted.
+    fn fetch_committed_offsets(
+        &self,
+        group: &str,
+        tps: &[TopicPartition],
+        timeout: Duration,
+    ) -> KafkaResult<Vec<(TopicPartition, i64)>>;
+}
+
+/// Source of the offset watermarks of Topic Partitions, driving
+/// [`crate::partition_offsets::PartitionOffsetsEmitter`].
+///
+/// This is blocking: call it via [`tokio::task::spawn_blocking`].
+pub trait WatermarksClient: Send + Sync {
+    /// Fetch the earliest and latest offset (watermarks) of a batch of Topic Partitions.
+    ///
+    /// Topic Partitions whose watermarks can't be fetched are omitted.
+    fn fetch_watermarks(
+        &self,
+        batch: &[TopicPartition],
+        timeout: Duration,
+    ) -> KafkaResult<Vec<(TopicPartition, u64, u64)>>;
+}
@@ -1,5 +1,28 @@
 mod awaitable;
+mod changes;
+mod client_stats;
+mod clock;
 mod emitter;
+mod jitter;
+mod kafka_client;
+mod persistence;
+mod secret;
+mod sink;
+mod subsystems;
 
 pub use awaitable::*;
+pub use changes::{Changes, ChangesTracker};
+pub use client_stats::{ClientStats, ClientStatsContext};
+pub use clock::{Clock, ManualClock, SystemClock};
 pub use emitter::Emitter;
+pub use jitter::{Jitter, JitteredInterval};
+pub use kafka_client::{
+    ClusterMetadata, CommittedOffsetsClient, GroupsClient, KafkaClientFuture, MetadataClient,
+    WatermarksClient,
+};
+pub use persistence::*;
+pub use secret::{redact, redact_url, redacted_client_config, Secret, REDACTED};
+pub use sink::*;
+pub use subsystems::{Subsystem, Subsystems};
+
+pub(crate) use subsystems::{error_code, is_unauthorized, is_unsupported};
@@ -0,0 +1,105 @@
+use std::{future::Future, io, path::Path, path::PathBuf, pin::Pin, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{task::JoinHandle, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+/// Future returned by the methods of [`Persistent`].
+pub type PersistentFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Configuration of where, and how often, to persist a [`Persistent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PersistenceConfig {
+    /// File to persist to, and to restore from at startup.
+    pub path: PathBuf,
+
+    /// How often to persist.
+    pub interval: Duration,
+}
+
+/// A [`Self`] has a state that can be persisted to disk, and restored from it.
+///
+/// This is used to "warm restart" the service: what it took a while to collect before
+/// a restart, is immediately available after it.
+pub trait Persistent: Send + Sync {
+    /// Serializable copy of the state of [`Self`].
+    type State: Serialize + DeserializeOwned + Send + 'static;
+
+    /// Take a copy of the current state of [`Self`].
+    fn state(&self) -> PersistentFuture<'_, Self::State>;
+
+    /// Restore the given state into [`Self`].
+    ///
+    /// As [`Self`] might have already collected new data by the time this is called,
+    /// implementors should merge the given state with the current one, giving precedence
+    /// to the latter.
+    fn restore(&self, state: Self::State) -> PersistentFuture<'_, ()>;
+}
+
+/// Restore the state of a [`Persistent`], from the file at `path`.
+///
+/// Returns `false` if the file does not exist (e.g. first ever launch).
+pub async fn restore_from<P: Persistent>(persistent: &P, path: &Path) -> io::Result<bool> {
+    let bytes = match tokio::fs::read(path).await {
+        Ok(b) => b,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+
+    let state = tokio::task::spawn_blocking(move || serde_json::from_slice::<P::State>(&bytes))
+        .await
+        .map_err(io::Error::other)??;
+    persistent.restore(state).await;
+
+    Ok(true)
+}
+
+/// Persist the state of a [`Persistent`] to the file at `path`.
+///
+/// The state is first written to a temporary file, then moved in place: this way,
+/// a crash mid-write never leaves a corrupted file behind.
+pub async fn persist_to<P: Persistent>(persistent: &P, path: &Path) -> io::Result<()> {
+    let state = persistent.state().await;
+    let bytes = tokio::task::spawn_blocking(move || serde_json::to_vec(&state))
+        .await
+        .map_err(io::Error::other)??;
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    tokio::fs::write(&tmp_path, bytes).await?;
+    tokio::fs::rename(&tmp_path, path).await
+}
+
+/// Spawn a task that periodically persists a [`Persistent`], as configured.
+///
+/// When the given [`CancellationToken`] is cancelled, it persists one last time, then terminates.
+pub fn spawn_persisting<P: Persistent + 'static>(
+    persistent: Arc<P>,
+    config: PersistenceConfig,
+    shutdown_token: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let name = std::any::type_name::<P>();
+        let mut interval = tokio::time::interval(config.interval);
+
+        // The first tick completes immediately: skip it, as there is nothing new to persist yet
+        interval.tick().await;
+
+        loop {
+            let shutting_down = tokio::select! {
+                _ = interval.tick() => false,
+                _ = shutdown_token.cancelled() => true,
+            };
+
+            match persist_to(persistent.as_ref(), &config.path).await {
+                Ok(()) => debug!("Persisted {name} to '{}'", config.path.display()),
+                Err(e) => warn!("Failed to persist {name} to '{}': {e}", config.path.display()),
+            }
+
+            if shutting_down {
+                info!("Shutting down: stopped persisting {name}");
+                break;
+            }
+        }
+    })
+}
@@ -0,0 +1,55 @@
+use std::{future::Future, pin::Pin};
+
+use thiserror::Error;
+use tokio::time::Duration;
+
+use crate::lag_register::LagSnapshot;
+
+/// Future returned by [`LagSink::publish`].
+pub type LagSinkFuture<'a> = Pin<Box<dyn Future<Output = LagSinkResult<()>> + Send + 'a>>;
+
+/// A [`Self`] is somewhere lag data can be published to.
+///
+/// At every reconcile tick, a [`LagSnapshot`] is taken and handed to each [`Self`]:
+/// what to do with it (render it, push it to a remote endpoint, write it to disk, ...)
+/// is up to the implementor.
+///
+/// The method returns a boxed [`Future`], so that [`Self`] can be used as a trait object
+/// (i.e. `Arc<dyn LagSink>`), and multiple different sinks can be configured at the same time.
+pub trait LagSink: Send + Sync {
+    /// Name of [`Self`], used in logs and metrics.
+    fn name(&self) -> &'static str;
+
+    /// How often [`Self`] wants to publish.
+    ///
+    /// If `None` (default), [`Self`] publishes at every reconcile tick; otherwise, the snapshots
+    /// taken before the interval has elapsed since the last publishing are skipped.
+    fn publish_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Publish the given [`LagSnapshot`].
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The latest [`LagSnapshot`] taken
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a>;
+}
+
+/// Possible errors returned by [`LagSink::publish`].
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum LagSinkError {
+    /// The sink configuration is invalid (e.g. an HTTP header that can't be sent).
+    #[error("Invalid sink configuration: {0}")]
+    Config(String),
+
+    /// The [`LagSnapshot`] could not be encoded in the format expected by the sink.
+    #[error("Failed to encode snapshot: {0}")]
+    Encode(String),
+
+    /// The encoded [`LagSnapshot`] could not be delivered to its destination.
+    #[error("Failed to deliver snapshot: {0}")]
+    Delivery(String),
+}
+
+pub type LagSinkResult<T> = Result<T, LagSinkError>;
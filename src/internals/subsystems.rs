@@ -0,0 +1,211 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use prometheus::{register_int_gauge_vec_with_registry, IntGaugeVec, Registry};
+use rdkafka::{error::KafkaError, types::RDKafkaErrorCode};
+use serde::Serialize;
+
+use crate::prometheus_metrics::LABEL_SUBSYSTEM;
+
+const MET_ENABLED_NAME: &str = "subsystem_enabled";
+const MET_ENABLED_HELP: &str =
+    "Whether the subsystem is enabled (1), or was disabled for lack of permissions or support (0)";
+
+/// Parts of the service that depend on permissions (ACLs) that might not be granted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    /// Listing and describing the Consumer Groups (and their Members).
+    ConsumerGroups,
+
+    /// Fetching the offsets committed by the Consumer Groups,
+    /// from `__consumer_offsets` or via the Admin API.
+    CommittedOffsets,
+
+    /// Describing the Cluster (controller and Brokers rack).
+    ClusterDescription,
+
+    /// Describing the Topics identifier (to resolve the assignments of next-gen Consumer Groups).
+    TopicIdentifiers,
+}
+
+impl Subsystem {
+    const ALL: [Subsystem; 4] = [
+        Subsystem::ConsumerGroups,
+        Subsystem::CommittedOffsets,
+        Subsystem::ClusterDescription,
+        Subsystem::TopicIdentifiers,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Subsystem::ConsumerGroups => "consumer_groups",
+            Subsystem::CommittedOffsets => "committed_offsets",
+            Subsystem::ClusterDescription => "cluster_description",
+            Subsystem::TopicIdentifiers => "topic_identifiers",
+        }
+    }
+
+    /// Whether lag can't be reported at all, without this [`Subsystem`].
+    pub fn is_essential(&self) -> bool {
+        matches!(self, Subsystem::CommittedOffsets)
+    }
+}
+
+/// Keeps track of which [`Subsystem`]s are enabled.
+///
+/// Unless lenient, all [`Subsystem`]s stay enabled, and their failures are logged
+/// at every attempt. When lenient instead, a [`Subsystem`] failing for lack of permissions
+/// (or because the brokers don't support it, e.g. partially-compatible Kafka distributions)
+/// gets disabled: this is logged once, and the [`Subsystem`] makes no further attempts.
+pub struct Subsystems {
+    lenient: bool,
+    disabled: Mutex<HashSet<Subsystem>>,
+
+    // Prometheus Metrics
+    metric_enabled: IntGaugeVec,
+}
+
+impl Subsystems {
+    /// Create a new [`Subsystems`], with all the [`Subsystem`]s enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `lenient` - Whether to disable the [`Subsystem`]s that fail for lack of permissions
+    /// * `metrics` - Where to register the metric reporting which [`Subsystem`]s are enabled
+    pub fn new(lenient: bool, metrics: Arc<Registry>) -> Self {
+        let metric_enabled = register_int_gauge_vec_with_registry!(
+            MET_ENABLED_NAME,
+            MET_ENABLED_HELP,
+            &[LABEL_SUBSYSTEM],
+            metrics
+        )
+        .unwrap_or_else(|_| panic!("Failed to create metric: {MET_ENABLED_NAME}"));
+        for s in Subsystem::ALL {
+            metric_enabled.with_label_values(&[s.name()]).set(1);
+        }
+
+        Self {
+            lenient,
+            disabled: Mutex::new(HashSet::new()),
+            metric_enabled,
+        }
+    }
+
+    pub fn is_enabled(&self, subsystem: Subsystem) -> bool {
+        !self.disabled.lock().expect("Subsystems lock poisoned").contains(&subsystem)
+    }
+
+    /// Whether each [`Subsystem`] is enabled.
+    pub fn status(&self) -> BTreeMap<Subsystem, bool> {
+        let disabled = self.disabled.lock().expect("Subsystems lock poisoned");
+        Subsystem::ALL.into_iter().map(|s| (s, !disabled.contains(&s))).collect()
+    }
+
+    /// Whether all the essential [`Subsystem`]s are enabled (see [`Subsystem::is_essential`]).
+    pub fn is_live(&self) -> bool {
+        Subsystem::ALL.into_iter().filter(Subsystem::is_essential).all(|s| self.is_enabled(s))
+    }
+
+    /// Handle the failure of a [`Subsystem`]: if lenient and the failure is for lack
+    /// of permissions (or of support by the brokers), the [`Subsystem`] is disabled.
+    ///
+    /// Returns `true` if the [`Subsystem`] is (now) disabled, and so it should stop trying.
+    pub fn disable_if_unauthorized(&self, subsystem: Subsystem, e: &KafkaError) -> bool {
+        if !self.lenient || !(is_unauthorized(e) || is_unsupported(e)) {
+            return false;
+        }
+
+        if self.disabled.lock().expect("Subsystems lock poisoned").insert(subsystem) {
+            if is_unsupported(e) {
+                warn!(
+                    "Disabling subsystem '{}', as not supported by the brokers ({e})",
+                    subsystem.name()
+                );
+            } else {
+                warn!(
+                    "Disabling subsystem '{}', as not authorized ({e}): grant the missing ACLs and restart to enable it",
+                    subsystem.name()
+                );
+            }
+            self.metric_enabled.with_label_values(&[subsystem.name()]).set(0);
+        }
+        true
+    }
+
+    /// Disable a [`Subsystem`] upfront, as the brokers are known not to support it
+    /// (see [`crate::capabilities`]): this is regardless of being lenient, as it could only fail.
+    pub fn disable_unsupported(&self, subsystem: Subsystem) {
+        if self.disabled.lock().expect("Subsystems lock poisoned").insert(subsystem) {
+            info!("Disabling subsystem '{}', as not supported by the brokers", subsystem.name());
+            self.metric_enabled.with_label_values(&[subsystem.name()]).set(0);
+        }
+    }
+}
+
+/// Whether the [`KafkaError`] is due to missing permissions (ACLs).
+pub(crate) fn is_unauthorized(e: &KafkaError) -> bool {
+    matches!(
+        error_code(e),
+        Some(
+            RDKafkaErrorCode::TopicAuthorizationFailed
+                | RDKafkaErrorCode::GroupAuthorizationFailed
+                | RDKafkaErrorCode::ClusterAuthorizationFailed
+        )
+    )
+}
+
+/// Whether the [`KafkaError`] is due to the brokers not supporting the request.
+pub(crate) fn is_unsupported(e: &KafkaError) -> bool {
+    matches!(
+        error_code(e),
+        Some(
+            RDKafkaErrorCode::UnsupportedVersion
+                | RDKafkaErrorCode::UnsupportedFeature
+                | RDKafkaErrorCode::InvalidRequest
+        )
+    )
+}
+
+/// The [`RDKafkaErrorCode`] of the [`KafkaError`], including those of failed Admin operations.
+pub(crate) fn error_code(e: &KafkaError) -> Option<RDKafkaErrorCode> {
+    match e {
+        KafkaError::AdminOp(code) => Some(*code),
+        _ => e.rdkafka_error_code(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn disables_only_when_lenient_and_unauthorized() {
+        let unauthorized =
+            KafkaError::MessageConsumption(RDKafkaErrorCode::TopicAuthorizationFailed);
+        let timed_out = KafkaError::AdminOp(RDKafkaErrorCode::OperationTimedOut);
+
+        let strict = Subsystems::new(false, Arc::new(Registry::new()));
+        assert!(!strict.disable_if_unauthorized(Subsystem::CommittedOffsets, &unauthorized));
+        assert!(strict.is_live());
+
+        let lenient = Subsystems::new(true, Arc::new(Registry::new()));
+        assert!(!lenient.disable_if_unauthorized(Subsystem::ConsumerGroups, &timed_out));
+        assert!(lenient.disable_if_unauthorized(
+            Subsystem::ConsumerGroups,
+            &KafkaError::AdminOp(RDKafkaErrorCode::ClusterAuthorizationFailed)
+        ));
+        assert!(!lenient.is_enabled(Subsystem::ConsumerGroups));
+        assert!(lenient.is_live());
+
+        assert!(lenient.disable_if_unauthorized(
+            Subsystem::ClusterDescription,
+            &KafkaError::AdminOp(RDKafkaErrorCode::UnsupportedVersion)
+        ));
+        assert!(!lenient.is_enabled(Subsystem::ClusterDescription));
+
+        assert!(lenient.disable_if_unauthorized(Subsystem::CommittedOffsets, &unauthorized));
+        assert!(!lenient.is_live());
+        assert_eq!(lenient.metric_enabled.with_label_values(&["committed_offsets"]).get(), 0);
+    }
+}
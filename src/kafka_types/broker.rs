@@ -1,10 +1,11 @@
 use rdkafka::metadata::MetadataBroker;
+use serde::{Deserialize, Serialize};
 
 /// A Brokers that is part of a Kafka cluster.
 ///
 /// It is identified by a unique identifier for the given Cluster,
 /// and the host and port to connect to it.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
 pub struct Broker {
     /// Broker unique identifier, as configured at the Kafka Cluster level.
     /// Note that uniqueness is "expected" by Brokers,
@@ -16,6 +17,10 @@ pub struct Broker {
 
     /// Port the Broker listens on, from the perspective of the Admin Client
     pub port: u16,
+
+    /// Rack the Broker is in, as configured by `broker.rack` (if configured).
+    /// It's not part of the metadata: it's fetched separately (i.e. it's initially `None`).
+    pub rack: Option<String>,
 }
 
 impl From<&MetadataBroker> for Broker {
@@ -24,6 +29,7 @@ impl From<&MetadataBroker> for Broker {
             id: b.id() as u32,
             host: b.host().to_owned(),
             port: b.port() as u16,
+            rack: None,
         }
     }
 }
@@ -1,9 +1,11 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use crate::kafka_types::TopicPartition;
+use serde::{Deserialize, Serialize};
+
+use crate::kafka_types::{Interned, TopicPartition};
 
 /// Consumer Group Member
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
 pub struct Member {
     /// Identifier
     pub id: String,
@@ -13,10 +15,24 @@ pub struct Member {
 
     /// Host where the Consumer is running
     pub client_host: String,
+
+    /// Value of `group.instance.id` set by the Consumer, if a static Member.
+    ///
+    /// Only known from the `GroupMetadata` in `__consumer_offsets`: listing the Consumer Groups
+    /// doesn't report it.
+    pub group_instance_id: Option<String>,
+}
+
+impl Member {
+    /// Identifier of the Member that is stable across restarts, when static membership is used:
+    /// the `group.instance.id` if set, the (ephemeral) `id` otherwise.
+    pub fn stable_id(&self) -> &str {
+        self.group_instance_id.as_deref().unwrap_or(&self.id)
+    }
 }
 
 /// Consumer Group Member, paired with the set of [`TopicPartition`] assigned to it
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct MemberWithAssignment {
     /// The [`Member`] itself
     pub member: Member,
@@ -25,11 +41,23 @@ pub struct MemberWithAssignment {
     pub assignment: HashSet<TopicPartition>,
 }
 
+/// Rebalance protocol used by a Consumer Group (i.e. `group.protocol` of its Consumers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Serialize, Deserialize)]
+pub enum GroupProtocol {
+    /// Classic protocol: Members, and their assignment, are known when listing Consumer Groups.
+    #[default]
+    Classic,
+
+    /// Next-gen protocol (KIP-848): Members, and their assignment, are not known when listing
+    /// Consumer Groups, only from `__consumer_offsets`.
+    Consumer,
+}
+
 /// Consumer Group
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct Group {
     /// Group name
-    pub name: String,
+    pub name: Interned,
 
     /// Type of Protocol used by this Group
     pub protocol_type: String,
@@ -39,10 +67,19 @@ pub struct Group {
 
     /// Group state
     pub state: String,
+
+    /// Rebalance protocol used by this Group
+    pub group_protocol: GroupProtocol,
+
+    /// Extra labels (e.g. team, service, tier), from the `--group-labels-file`
+    pub labels: BTreeMap<String, String>,
+
+    /// Identifier of the Broker coordinating this Group, if known
+    pub coordinator_id: Option<i32>,
 }
 
 /// Consumer Group, paired with a map of [`MemberWithAssignment`] indexed by [`Member::id`]
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct GroupWithMembers {
     pub group: Group,
     pub members: HashMap<String, MemberWithAssignment>,
@@ -0,0 +1,151 @@
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Deref;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Minimum size the [`INTERNER`] has to reach, before it's pruned.
+const INTERNER_PRUNE_MIN_LEN: usize = 1024;
+
+/// Cache of the [`Interned`] strings currently in use, and the size it has to reach to be pruned.
+static INTERNER: LazyLock<Mutex<(HashSet<Arc<str>>, usize)>> =
+    LazyLock::new(|| Mutex::new((HashSet::new(), INTERNER_PRUNE_MIN_LEN)));
+
+/// An interned string, like the name of a Topic or of a Consumer Group.
+///
+/// All the [`Interned`] strings of equal content share the same allocation: names repeated
+/// for each Topic Partition, or across registers, take no extra memory.
+/// Cloning is cheap, and it derefs to [`str`].
+///
+/// Strings no longer in use are dropped from the cache, once it has doubled in size since
+/// it was last pruned.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Interned(Arc<str>);
+
+impl Interned {
+    pub fn new(s: &str) -> Self {
+        let mut interner = INTERNER.lock().expect("Interner lock poisoned");
+        let (cache, prune_at) = &mut *interner;
+
+        if let Some(i) = cache.get(s) {
+            return Self(i.clone());
+        }
+
+        if cache.len() >= *prune_at {
+            // Strings only referenced by the cache are no longer in use
+            cache.retain(|i| Arc::strong_count(i) > 1);
+            *prune_at = (cache.len() * 2).max(INTERNER_PRUNE_MIN_LEN);
+        }
+
+        let i: Arc<str> = Arc::from(s);
+        cache.insert(i.clone());
+        Self(i)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Interned {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Interned {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Interned {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Interned {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<String> for Interned {
+    fn from(s: String) -> Self {
+        Self::new(&s)
+    }
+}
+
+impl From<&String> for Interned {
+    fn from(s: &String) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<Interned> for String {
+    fn from(i: Interned) -> Self {
+        i.0.to_string()
+    }
+}
+
+impl PartialEq<str> for Interned {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Interned {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Interned {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl Display for Interned {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&*self.0, f)
+    }
+}
+
+impl Debug for Interned {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for Interned {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interned {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shares_allocation() {
+        let a = Interned::new("interned-test-topic");
+        let b = Interned::from("interned-test-topic".to_string());
+        assert_eq!(a, b);
+        assert!(Arc::ptr_eq(&a.0, &b.0));
+        assert_eq!(a, "interned-test-topic");
+        assert_eq!(format!("{a}:{b:?}"), "interned-test-topic:\"interned-test-topic\"");
+    }
+}
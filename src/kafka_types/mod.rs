@@ -5,10 +5,14 @@
 
 mod broker;
 mod group;
+mod interned;
+mod topic_config;
 mod topic_partition;
 mod topic_partitions_status;
 
 pub use broker::*;
 pub use group::*;
+pub use interned::*;
+pub use topic_config::*;
 pub use topic_partition::*;
 pub use topic_partitions_status::*;
@@ -0,0 +1,35 @@
+use rdkafka::admin::ConfigResource;
+use serde::{Deserialize, Serialize};
+
+const CONFIG_RETENTION_MS: &str = "retention.ms";
+const CONFIG_RETENTION_BYTES: &str = "retention.bytes";
+const CONFIG_CLEANUP_POLICY: &str = "cleanup.policy";
+
+/// For a given Topic, the subset of its configuration relevant to Consumers lag.
+///
+/// Each field is `None` if it could not be fetched from the Kafka cluster.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
+pub struct TopicConfig {
+    /// How long (ms) records are retained, as configured by `retention.ms`: `-1` means unlimited.
+    pub retention_ms: Option<i64>,
+
+    /// How many bytes (per Partition) are retained, as configured by `retention.bytes`:
+    /// `-1` means unlimited.
+    pub retention_bytes: Option<i64>,
+
+    /// How old records are discarded, as configured by `cleanup.policy`
+    /// (i.e. `delete`, `compact` or `compact,delete`).
+    pub cleanup_policy: Option<String>,
+}
+
+impl From<&ConfigResource> for TopicConfig {
+    fn from(cr: &ConfigResource) -> Self {
+        let value = |name: &str| cr.get(name).and_then(|e| e.value.clone());
+
+        TopicConfig {
+            retention_ms: value(CONFIG_RETENTION_MS).and_then(|v| v.parse().ok()),
+            retention_bytes: value(CONFIG_RETENTION_BYTES).and_then(|v| v.parse().ok()),
+            cleanup_policy: value(CONFIG_CLEANUP_POLICY),
+        }
+    }
+}
@@ -1,26 +1,32 @@
 use konsumer_offsets::TopicPartitions;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
+use super::Interned;
+
 /// Represents a single Topic-Partition pair
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+///
+/// The Topic name is [`Interned`]: it's shared by all the Partitions of the Topic.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
 pub struct TopicPartition {
-    pub topic: String,
+    pub topic: Interned,
     pub partition: u32,
 }
 
 impl TopicPartition {
-    pub(crate) fn new(topic: String, partition: u32) -> Self {
+    pub(crate) fn new(topic: impl Into<Interned>, partition: u32) -> Self {
         Self {
-            topic,
+            topic: topic.into(),
             partition,
         }
     }
 
     pub(crate) fn vec_from(topic_partitions: TopicPartitions) -> Vec<Self> {
+        let topic = Interned::new(&topic_partitions.topic);
         topic_partitions
             .partitions
             .into_iter()
-            .map(|p| TopicPartition::new(topic_partitions.topic.clone(), p as u32))
+            .map(|p| TopicPartition::new(topic.clone(), p as u32))
             .collect()
     }
 }
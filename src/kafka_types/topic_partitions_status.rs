@@ -1,20 +1,31 @@
 use rdkafka::metadata::{MetadataPartition, MetadataTopic};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{Interned, TopicConfig};
 
 /// For a given Topic, it describes its status as reported by the Kafka cluster.
 ///
 /// In details, it describes where each partition is, which broker leads each partition,
 /// and which follower broker is in sync with each partition.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+/// The [`TopicConfig`] and the identifier are fetched separately from the metadata
+/// (i.e. they are initially the default).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
 pub struct TopicPartitionsStatus {
-    pub name: String,
+    pub name: Interned,
+    /// Identifier (i.e. `topic.id`), if supported by the Kafka cluster.
+    pub id: Option<Uuid>,
     pub partitions: Vec<PartitionStatus>,
+    pub config: TopicConfig,
 }
 
 impl From<&MetadataTopic> for TopicPartitionsStatus {
     fn from(t: &MetadataTopic) -> Self {
         TopicPartitionsStatus {
-            name: t.name().to_owned(),
+            name: Interned::new(t.name()),
+            id: None,
             partitions: t.partitions().iter().map(PartitionStatus::from).collect(),
+            config: TopicConfig::default(),
         }
     }
 }
@@ -22,7 +33,7 @@ impl From<&MetadataTopic> for TopicPartitionsStatus {
 /// For a given Partition, it describes its status as reported by the Kafka cluster.
 ///
 /// The details make sense only in the context of the containing Topic.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
 pub struct PartitionStatus {
     pub id: u32,
     pub leader_broker: u32,
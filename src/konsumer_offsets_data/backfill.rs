@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use prometheus::{register_gauge_with_registry, Gauge, Registry};
+use rdkafka::{Offset, TopicPartitionList};
+
+use crate::internals::Awaitable;
+
+const MET_PROGRESS_NAME: &str = "konsumer_offsets_backfill_progress";
+const MET_PROGRESS_HELP: &str =
+    "Progress of consuming __consumer_offsets, up to where it was at startup (0.0 to 1.0)";
+
+/// Offsets of a partition of `__consumer_offsets`, relevant to the backfill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PartitionBackfill {
+    /// Offset consumption started from.
+    start: i64,
+
+    /// Next offset to consume.
+    position: i64,
+
+    /// High watermark at startup: backfill of the partition is complete once reached.
+    target: i64,
+}
+
+/// Tracks the backfill phase of [`super::KonsumerOffsetsDataEmitter`]: at startup, it
+/// (re)consumes `__consumer_offsets` up to where it was at startup, to reconstruct the
+/// last-known commits of all Consumer Groups.
+///
+/// It's an [`Awaitable`]: ready once the backfill is complete.
+#[derive(Debug)]
+pub struct Backfill {
+    partitions: Mutex<Option<HashMap<i32, PartitionBackfill>>>,
+    metric_progress: Gauge,
+}
+
+impl Backfill {
+    pub fn new(metrics: Arc<Registry>) -> Self {
+        Self {
+            partitions: Mutex::new(None),
+            metric_progress: register_gauge_with_registry!(
+                MET_PROGRESS_NAME,
+                MET_PROGRESS_HELP,
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_PROGRESS_NAME}")),
+        }
+    }
+
+    /// Set where consumption of each partition starts from, and the high watermark to reach.
+    ///
+    /// # Arguments
+    ///
+    /// * `bounds` - For each partition, the `(start, target)` offsets
+    pub(super) fn begin(&self, bounds: HashMap<i32, (i64, i64)>) {
+        let partitions = bounds
+            .into_iter()
+            .map(|(p, (start, target))| {
+                (
+                    p,
+                    PartitionBackfill {
+                        start,
+                        position: start,
+                        target,
+                    },
+                )
+            })
+            .collect();
+
+        *self.partitions.lock().expect("Backfill lock poisoned") = Some(partitions);
+        self.metric_progress.set(self.progress());
+    }
+
+    /// Update the next offsets to consume, with the positions of the consumer.
+    pub(super) fn update(&self, positions: &TopicPartitionList) {
+        if let Some(partitions) = self.partitions.lock().expect("Backfill lock poisoned").as_mut() {
+            for elem in positions.elements() {
+                if let (Some(pb), Offset::Offset(o)) =
+                    (partitions.get_mut(&elem.partition()), elem.offset())
+                {
+                    pb.position = pb.position.max(o);
+                }
+            }
+        }
+        self.metric_progress.set(self.progress());
+    }
+
+    /// Progress of the backfill, between `0.0` and `1.0`.
+    ///
+    /// It's `0.0` until the backfill has begun.
+    pub fn progress(&self) -> f64 {
+        match self.partitions.lock().expect("Backfill lock poisoned").as_ref() {
+            None => 0_f64,
+            Some(partitions) => {
+                let (done, total) = partitions.values().fold((0, 0), |(done, total), pb| {
+                    let total_p = (pb.target - pb.start).max(0);
+                    (done + (pb.position - pb.start).clamp(0, total_p), total + total_p)
+                });
+
+                if total == 0 {
+                    1_f64
+                } else {
+                    done as f64 / total as f64
+                }
+            },
+        }
+    }
+
+    /// `true` once all the partitions have been consumed up to their target.
+    pub fn is_complete(&self) -> bool {
+        match self.partitions.lock().expect("Backfill lock poisoned").as_ref() {
+            None => false,
+            Some(partitions) => partitions.values().all(|pb| pb.position >= pb.target),
+        }
+    }
+}
+
+impl Awaitable for Backfill {
+    async fn is_ready(&self) -> bool {
+        let is_ready = self.is_complete();
+
+        info!(
+            "Backfill of __consumer_offsets: {:3.2}% (ready: {is_ready})",
+            self.progress() * 100_f64
+        );
+
+        is_ready
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn progress_and_completion() {
+        let backfill = Backfill::new(Arc::new(Registry::new()));
+        assert_eq!(backfill.progress(), 0_f64);
+        assert!(!backfill.is_complete());
+
+        backfill.begin(HashMap::from([(0, (0, 100)), (1, (50, 150)), (2, (10, 10))]));
+        assert_eq!(backfill.progress(), 0_f64);
+        assert!(!backfill.is_complete());
+
+        let mut positions = TopicPartitionList::new();
+        positions.add_partition_offset("t", 0, Offset::Offset(100)).unwrap();
+        positions.add_partition_offset("t", 1, Offset::Offset(100)).unwrap();
+        positions.add_partition_offset("t", 2, Offset::Invalid).unwrap();
+        backfill.update(&positions);
+        assert_eq!(backfill.progress(), 0.75);
+        assert!(!backfill.is_complete());
+
+        let mut positions = TopicPartitionList::new();
+        positions.add_partition_offset("t", 1, Offset::Offset(150)).unwrap();
+        backfill.update(&positions);
+        assert_eq!(backfill.progress(), 1_f64);
+        assert!(backfill.is_complete());
+    }
+}
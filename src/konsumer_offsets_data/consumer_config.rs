@@ -0,0 +1,128 @@
+use rdkafka::ClientConfig;
+
+use crate::constants::{
+    KOMMITTED_CONSUMER_OFFSETS_CLIENT_ID_SUFFIX, KOMMITTED_CONSUMER_OFFSETS_CONSUMER,
+};
+
+/// Which records of transactions the `__consumer_offsets` consumer reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum IsolationLevel {
+    /// Only offsets committed by transactions that completed.
+    #[default]
+    ReadCommitted,
+
+    /// Also offsets committed by transactions that are ongoing, or that were aborted.
+    ReadUncommitted,
+}
+
+impl IsolationLevel {
+    /// Value of the `isolation.level` Kafka Consumer configuration.
+    fn as_config_value(&self) -> &'static str {
+        match self {
+            Self::ReadCommitted => "read_committed",
+            Self::ReadUncommitted => "read_uncommitted",
+        }
+    }
+}
+
+/// Settings of the Kafka Consumer of `__consumer_offsets`,
+/// used by [`super::KonsumerOffsetsDataEmitter`].
+///
+/// Settings left unset fall back to the Kafka Client configuration (e.g. `--kafka-conf`),
+/// then to the defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KonsumerOffsetsConsumerConfig {
+    /// Consumer Group (`group.id`) of the consumer: defaults to
+    /// `__kommitted__consumer_offsets_consumer`.
+    pub group_id: Option<String>,
+
+    /// Client identifier (`client.id`) of the consumer: defaults to the `client.id`
+    /// of the Kafka Client, suffixed with `-consumer-offsets`.
+    pub client_id: Option<String>,
+
+    /// Max amount of bytes per fetch request (`fetch.max.bytes`).
+    pub fetch_max_bytes: Option<u32>,
+
+    /// Max amount of bytes per Topic Partition, per fetch request (`max.partition.fetch.bytes`).
+    pub max_partition_fetch_bytes: Option<u32>,
+
+    /// Isolation level (`isolation.level`) of the consumer.
+    pub isolation_level: Option<IsolationLevel>,
+}
+
+impl KonsumerOffsetsConsumerConfig {
+    /// Consumer Group (`group.id`) the consumer uses, given the [`ClientConfig`] it's applied to.
+    pub fn effective_group_id(&self, client_config: &ClientConfig) -> String {
+        self.group_id
+            .as_deref()
+            .or(client_config.get("group.id"))
+            .unwrap_or(KOMMITTED_CONSUMER_OFFSETS_CONSUMER)
+            .to_string()
+    }
+
+    /// Sets these settings on the given [`ClientConfig`] object.
+    pub(super) fn apply(&self, client_config: &mut ClientConfig) {
+        client_config.set("group.id", self.effective_group_id(client_config));
+
+        let client_id = self.client_id.clone().unwrap_or_else(|| {
+            format!(
+                "{}{KOMMITTED_CONSUMER_OFFSETS_CLIENT_ID_SUFFIX}",
+                client_config.get("client.id").unwrap_or("rdkafka")
+            )
+        });
+        client_config.set("client.id", client_id);
+
+        if let Some(fetch_max_bytes) = self.fetch_max_bytes {
+            client_config.set("fetch.max.bytes", fetch_max_bytes.to_string());
+        }
+        if let Some(max_partition_fetch_bytes) = self.max_partition_fetch_bytes {
+            client_config.set("max.partition.fetch.bytes", max_partition_fetch_bytes.to_string());
+        }
+        if let Some(isolation_level) = self.isolation_level {
+            client_config.set("isolation.level", isolation_level.as_config_value());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        let mut client_config = ClientConfig::new();
+        client_config.set("client.id", "kommitted");
+        KonsumerOffsetsConsumerConfig::default().apply(&mut client_config);
+
+        assert_eq!(client_config.get("group.id"), Some(KOMMITTED_CONSUMER_OFFSETS_CONSUMER));
+        assert_eq!(client_config.get("client.id"), Some("kommitted-consumer-offsets"));
+        assert_eq!(client_config.get("fetch.max.bytes"), None);
+        assert_eq!(client_config.get("isolation.level"), None);
+
+        // A `group.id` set on the Kafka Client is kept
+        let mut client_config = ClientConfig::new();
+        client_config.set("group.id", "my-group");
+        KonsumerOffsetsConsumerConfig::default().apply(&mut client_config);
+        assert_eq!(client_config.get("group.id"), Some("my-group"));
+    }
+
+    #[test]
+    fn overrides() {
+        let mut client_config = ClientConfig::new();
+        client_config.set("client.id", "kommitted").set("group.id", "my-group");
+        KonsumerOffsetsConsumerConfig {
+            group_id: Some("kommitted-offsets".into()),
+            client_id: Some("kommitted-offsets-consumer".into()),
+            fetch_max_bytes: Some(1_048_576),
+            max_partition_fetch_bytes: Some(65_536),
+            isolation_level: Some(IsolationLevel::ReadUncommitted),
+        }
+        .apply(&mut client_config);
+
+        assert_eq!(client_config.get("group.id"), Some("kommitted-offsets"));
+        assert_eq!(client_config.get("client.id"), Some("kommitted-offsets-consumer"));
+        assert_eq!(client_config.get("fetch.max.bytes"), Some("1048576"));
+        assert_eq!(client_config.get("max.partition.fetch.bytes"), Some("65536"));
+        assert_eq!(client_config.get("isolation.level"), Some("read_uncommitted"));
+    }
+}
@@ -1,92 +1,312 @@
-use konsumer_offsets::KonsumerOffsetsData;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use konsumer_offsets::{GroupMetadata, KonsumerOffsetsData, KonsumerOffsetsError};
+use prometheus::{register_int_counter_vec_with_registry, IntCounterVec, Registry};
 use rdkafka::error::KafkaError;
 use rdkafka::{
     config::RDKafkaLogLevel,
     consumer::{Consumer, ConsumerContext, Rebalance, StreamConsumer},
     error::KafkaResult,
+    message::BorrowedMessage,
+    statistics::Statistics,
     ClientConfig, ClientContext, Message, Offset, TopicPartitionList,
 };
-use tokio::{sync::mpsc, task::JoinHandle, time::Duration};
+use tokio::{
+    sync::{broadcast::error::RecvError, mpsc},
+    task::JoinHandle,
+    time::Duration,
+};
 use tokio_util::sync::CancellationToken;
 
-use crate::constants::{KOMMITTED_CONSUMER_OFFSETS_CONSUMER, KONSUMER_OFFSETS_DATA_TOPIC};
-use crate::internals::Emitter;
+use super::backfill::Backfill;
+use super::consumer_config::KonsumerOffsetsConsumerConfig;
+use super::next_gen::{NextGenGroups, NextGenUpdate};
+
+use crate::cluster_status::ClusterStatusRegister;
+use crate::constants::KONSUMER_OFFSETS_DATA_TOPIC;
+use crate::consumer_groups::GroupExcludes;
+use crate::internals::{ClientStats, ClientStatsContext, Emitter, Subsystem, Subsystems};
+use crate::prometheus_metrics::LABEL_KIND;
 
 const CHANNEL_SIZE: usize = 10_000;
+const BACKFILL_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Of all the parse errors of a given kind, only 1 every this many is logged.
+const PARSE_ERROR_LOG_SAMPLING: u64 = 1000;
+
+const MET_PARSE_ERRORS_NAME: &str = "konsumer_offsets_parse_errors_total";
+const MET_PARSE_ERRORS_HELP: &str = "Records of __consumer_offsets that failed to be parsed";
+
+/// Where to start consuming `__consumer_offsets` from, at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KonsumerOffsetsStart {
+    /// From the earliest offsets available: reconstructs the last-known commits of all groups.
+    #[default]
+    Earliest,
+
+    /// From the latest offsets: only commits done after startup are known.
+    Latest,
+
+    /// From the first offsets at, or after, the given [`DateTime<Utc>`].
+    Timestamp(DateTime<Utc>),
+}
+
+impl FromStr for KonsumerOffsetsStart {
+    type Err = String;
+
+    /// Parses `earliest`, `latest`, or a timestamp (RFC 3339, or milliseconds since UTC Epoch).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "earliest" => Ok(Self::Earliest),
+            "latest" => Ok(Self::Latest),
+            ts => ts
+                .parse::<i64>()
+                .ok()
+                .and_then(DateTime::<Utc>::from_timestamp_millis)
+                .or_else(|| DateTime::parse_from_rfc3339(ts).ok().map(|dt| dt.to_utc()))
+                .map(Self::Timestamp)
+                .ok_or_else(|| {
+                    format!("Should be 'earliest', 'latest' or a timestamp (RFC 3339 or ms): {ts}")
+                }),
+        }
+    }
+}
 
 /// Emits [`KonsumerOffsetsData`] via a provided [`mpsc::channel`].
 ///
 /// It wraps a Kafka Client, consumes the `__consumer_offsets` topic, and emits its records
 /// parsed into [`KonsumerOffsetsData`].
 ///
+/// Groups using the next-gen consumer rebalance protocol (KIP-848) don't have a `GroupMetadata`
+/// record: one is emitted for them every time their Members (or assignment) change, resolving
+/// the Topic identifiers of the assignment via the [`ClusterStatusRegister`].
+///
+/// Records of excluded Consumer Groups (see [`GroupExcludes`]), including this service own
+/// consumer, are dropped as soon as parsed: they are never emitted.
+///
+/// If not authorized to consume `__consumer_offsets`, and [`Subsystem::CommittedOffsets`]
+/// gets disabled as a result, it stops consuming altogether (and the [`Backfill`] is over).
+///
 /// It shuts down when the provided [`CancellationToken`] is cancelled.
 pub struct KonsumerOffsetsDataEmitter {
     consumer_client_config: ClientConfig,
+    start: KonsumerOffsetsStart,
+    consumer_config: KonsumerOffsetsConsumerConfig,
+    excludes: GroupExcludes,
+    backfill: Arc<Backfill>,
+    cs_reg: Arc<ClusterStatusRegister>,
+    subsystems: Arc<Subsystems>,
+    client_stats: Arc<ClientStats>,
+
+    // Prometheus Metrics
+    metric_parse_errors: IntCounterVec,
 }
 
 impl KonsumerOffsetsDataEmitter {
-    pub fn new(client_config: ClientConfig) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        client_config: ClientConfig,
+        start: KonsumerOffsetsStart,
+        consumer_config: KonsumerOffsetsConsumerConfig,
+        excludes: GroupExcludes,
+        backfill: Arc<Backfill>,
+        cs_reg: Arc<ClusterStatusRegister>,
+        subsystems: Arc<Subsystems>,
+        client_stats: Arc<ClientStats>,
+        metrics: Arc<Registry>,
+    ) -> Self {
         Self {
             consumer_client_config: client_config,
+            start,
+            consumer_config,
+            excludes,
+            backfill,
+            cs_reg,
+            subsystems,
+            client_stats,
+            metric_parse_errors: register_int_counter_vec_with_registry!(
+                MET_PARSE_ERRORS_NAME,
+                MET_PARSE_ERRORS_HELP,
+                &[LABEL_KIND],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_PARSE_ERRORS_NAME}")),
         }
     }
 
-    /// Sets the desired Kafka Configuration on the given [`ClientConfig`] object.
+    /// Sets the desired Kafka Configuration on the given [`ClientConfig`] object,
+    /// including the [`KonsumerOffsetsConsumerConfig`].
     ///
     /// Ref: https://github.com/confluentinc/librdkafka/blob/master/CONFIGURATION.md.
-    fn set_kafka_consumer_config(mut client_config: ClientConfig) -> ClientConfig {
+    fn set_kafka_consumer_config(
+        mut client_config: ClientConfig,
+        consumer_config: &KonsumerOffsetsConsumerConfig,
+    ) -> ClientConfig {
         client_config.set("enable.auto.commit", "true");
         client_config.set("auto.commit.interval.ms", "5000");
         client_config.set("session.timeout.ms", "10000"); //< must be greater than `auto.commit.interval.ms`
         client_config.set("auto.offset.reset", "earliest");
         client_config.set("enable.partition.eof", "false");
 
-        if client_config.get("group.id").is_none() {
-            client_config.set("group.id", KOMMITTED_CONSUMER_OFFSETS_CONSUMER);
-        }
+        consumer_config.apply(&mut client_config);
 
         client_config.set_log_level(RDKafkaLogLevel::Warning);
 
         client_config
     }
 
-    async fn assign_and_seek_to_earliest_all_partitions(
+    /// Self-assign all partitions of `topic`, starting from the offsets indicated by `start`.
+    ///
+    /// Returns, for each partition, the `(start, high watermark)` offsets.
+    async fn assign_all_partitions(
         consumer: &KonsumerOffsetsDataConsumer,
         topic: &str,
-    ) -> KafkaResult<()> {
+        start: KonsumerOffsetsStart,
+    ) -> KafkaResult<HashMap<i32, (i64, i64)>> {
         // Fetch topic metadata
         let meta = consumer.fetch_metadata(Some(topic), Duration::from_secs(5))?;
         let topic_meta = meta.topics().first().ok_or(KafkaError::Subscription(format!(
-            "Unable to (self)assign '{}' and seek to earliest offsets",
-            topic
+            "Unable to (self)assign '{}' and seek to {:?} offsets",
+            topic, start
         )))?;
 
-        // Prepare desired assignment, setting offset to earliest available for each partition
-        let mut desired_assignment =
-            TopicPartitionList::with_capacity(topic_meta.partitions().len());
+        // Fetch watermarks of each partition
+        let mut watermarks = HashMap::with_capacity(topic_meta.partitions().len());
         for partition_meta in topic_meta.partitions().iter() {
-            let (earliest, _) = consumer.fetch_watermarks(
+            let wm = consumer.fetch_watermarks(
                 topic,
                 partition_meta.id(),
                 Duration::from_millis(500),
             )?;
-            desired_assignment.add_partition_offset(
-                topic,
-                partition_meta.id(),
-                Offset::Offset(earliest),
-            )?;
+            watermarks.insert(partition_meta.id(), wm);
+        }
+
+        // Determine the offset to start from, for each partition
+        let mut bounds: HashMap<i32, (i64, i64)> = watermarks
+            .iter()
+            .map(|(p, (earliest, latest))| match start {
+                KonsumerOffsetsStart::Latest => (*p, (*latest, *latest)),
+                _ => (*p, (*earliest, *latest)),
+            })
+            .collect();
+        if let KonsumerOffsetsStart::Timestamp(ts) = start {
+            let mut tpl = TopicPartitionList::with_capacity(watermarks.len());
+            for p in watermarks.keys() {
+                tpl.add_partition_offset(topic, *p, Offset::Offset(ts.timestamp_millis()))?;
+            }
+            for elem in consumer.offsets_for_times(tpl, Duration::from_secs(5))?.elements() {
+                if let Some((start, latest)) = bounds.get_mut(&elem.partition()) {
+                    *start = match elem.offset() {
+                        Offset::Offset(o) => o,
+                        _ => *latest,
+                    };
+                }
+            }
+        }
+
+        // Prepare desired assignment, setting offset to where to start from for each partition
+        let mut desired_assignment = TopicPartitionList::with_capacity(bounds.len());
+        for (p, (start, _)) in bounds.iter() {
+            desired_assignment.add_partition_offset(topic, *p, Offset::Offset(*start))?;
         }
 
         // Finally, self-assign
         consumer.assign(&desired_assignment)?;
 
-        Ok(())
+        Ok(bounds)
+    }
+}
+
+/// Short, metric-label friendly, identifier of the kind of a [`KonsumerOffsetsError`].
+fn parse_error_kind(e: &KonsumerOffsetsError) -> &'static str {
+    #[allow(unreachable_patterns)]
+    match e {
+        KonsumerOffsetsError::MessageKeyMissing => "key_missing",
+        KonsumerOffsetsError::ByteParsingError(_) => "bytes_parsing",
+        KonsumerOffsetsError::ChronoDateTimeUtcParsingError(_) => "timestamp",
+        KonsumerOffsetsError::UnsupportedMessageVersion(_) => "unsupported_message_version",
+        KonsumerOffsetsError::UnsupportedOffsetCommitSchema(_) => {
+            "unsupported_offset_commit_schema"
+        },
+        KonsumerOffsetsError::UnsupportedGroupMetadataSchema(_) => {
+            "unsupported_group_metadata_schema"
+        },
+        KonsumerOffsetsError::UnsupportedConsumerProtocolSubscriptionVersion(_) => {
+            "unsupported_subscription_version"
+        },
+        KonsumerOffsetsError::UnsupportedConsumerProtocolAssignmentVersion(_) => {
+            "unsupported_assignment_version"
+        },
+        KonsumerOffsetsError::UnableToParseForVersion(..) => "unparsable_for_version",
+        _ => "other",
+    }
+}
+
+/// Count (and sample log) a `__consumer_offsets` record that failed to be parsed.
+fn count_parse_error(
+    metric_parse_errors: &IntCounterVec,
+    kind: &str,
+    e: impl Display,
+    m: &BorrowedMessage,
+) {
+    // Count and skip: a single unparsable record should not stop the service
+    let counter = metric_parse_errors.with_label_values(&[kind]);
+    counter.inc();
+    if counter.get() % PARSE_ERROR_LOG_SAMPLING == 1 {
+        debug!(
+            "Failed to parse record {}:{}@{} (key: {} bytes, version {:?}; payload: {:?} bytes), {} so far of kind '{kind}': {e}",
+            KONSUMER_OFFSETS_DATA_TOPIC,
+            m.partition(),
+            m.offset(),
+            m.key_len(),
+            m.key().and_then(key_version),
+            m.payload().map(|p| p.len()),
+            counter.get(),
+        );
+    }
+}
+
+/// The [`GroupMetadata`] of a Group using the next-gen consumer rebalance protocol,
+/// resolving the Topic identifiers of its assignment via the [`ClusterStatusRegister`].
+///
+/// Returns also whether all the Topic identifiers were resolved.
+async fn next_gen_group_metadata(
+    next_gen: &NextGenGroups,
+    group: &str,
+    cs_reg: &ClusterStatusRegister,
+) -> (GroupMetadata, bool) {
+    let mut topic_names = HashMap::new();
+    let mut resolved = true;
+    for id in next_gen.topic_ids(group) {
+        match cs_reg.get_topic_by_id(&id).await {
+            Some(name) => {
+                topic_names.insert(id, name);
+            },
+            None => resolved = false,
+        }
     }
+
+    (next_gen.group_metadata(group, &topic_names), resolved)
 }
 
-struct KonsumerOffsetsDataContext;
+/// Version of a `__consumer_offsets` record, as encoded in the first 2 bytes of its key.
+fn key_version(key: &[u8]) -> Option<i16> {
+    key.get(..2).map(|v| i16::from_be_bytes([v[0], v[1]]))
+}
 
-impl ClientContext for KonsumerOffsetsDataContext {}
+struct KonsumerOffsetsDataContext {
+    stats: ClientStatsContext,
+}
+
+impl ClientContext for KonsumerOffsetsDataContext {
+    fn stats(&self, statistics: Statistics) {
+        self.stats.stats(statistics);
+    }
+}
 
 impl ConsumerContext for KonsumerOffsetsDataContext {
     fn post_rebalance(&self, rebalance: &Rebalance) {
@@ -141,45 +361,151 @@ impl Emitter for KonsumerOffsetsDataEmitter {
         &self,
         shutdown_token: CancellationToken,
     ) -> (mpsc::Receiver<Self::Emitted>, JoinHandle<()>) {
-        let consumer_context = KonsumerOffsetsDataContext;
+        let consumer_context = KonsumerOffsetsDataContext {
+            stats: self.client_stats.context("konsumer_offsets_data"),
+        };
 
-        let consumer_client: KonsumerOffsetsDataConsumer =
-            Self::set_kafka_consumer_config(self.consumer_client_config.clone())
-                .create_with_context(consumer_context)
-                .expect("Failed to create Consumer Client");
+        let mut consumer_client_config = Self::set_kafka_consumer_config(
+            self.consumer_client_config.clone(),
+            &self.consumer_config,
+        );
+        self.client_stats.configure(&mut consumer_client_config);
+        let consumer_client: KonsumerOffsetsDataConsumer = consumer_client_config
+            .create_with_context(consumer_context)
+            .expect("Failed to create Consumer Client");
 
         let (sx, rx) = mpsc::channel::<KonsumerOffsetsData>(CHANNEL_SIZE);
 
+        let start = self.start;
+        let excludes = self.excludes.clone();
+        let backfill = self.backfill.clone();
+        let metric_parse_errors = self.metric_parse_errors.clone();
+        let subsystems = self.subsystems.clone();
+
+        let cs_reg = self.cs_reg.clone();
+        let mut topology_rx = cs_reg.subscribe_topology_changes();
+        let mut topology_open = true;
+
         let join_handle = tokio::spawn(async move {
-            match Self::assign_and_seek_to_earliest_all_partitions(&consumer_client, KONSUMER_OFFSETS_DATA_TOPIC).await
+            match Self::assign_all_partitions(&consumer_client, KONSUMER_OFFSETS_DATA_TOPIC, start)
+                .await
             {
-                Ok(_) => info!(
-                    "(Self) Assigned all partitions of {KONSUMER_OFFSETS_DATA_TOPIC} and sought offsets to earliest"
-                ),
+                Ok(bounds) => {
+                    info!(
+                        "(Self) Assigned all partitions of {KONSUMER_OFFSETS_DATA_TOPIC} and sought offsets to {start:?}"
+                    );
+                    backfill.begin(bounds);
+                },
+                Err(e) if subsystems.disable_if_unauthorized(Subsystem::CommittedOffsets, &e) => {
+                    // Nothing to backfill: let the rest of the service start
+                    backfill.begin(HashMap::new());
+                    shutdown_token.cancelled().await;
+                    info!("Shutting down");
+                    return;
+                },
                 Err(e) => panic!("Failed to (self) assign '{KONSUMER_OFFSETS_DATA_TOPIC}': {e}"),
             }
 
+            let mut backfill_interval = tokio::time::interval(BACKFILL_UPDATE_INTERVAL);
+            let mut backfill_complete = false;
+
+            // Groups using the next-gen consumer rebalance protocol, and those of them
+            // with an assignment of Topics not known (yet) to the cluster status
+            let mut next_gen = NextGenGroups::default();
+            let mut next_gen_unresolved: HashSet<String> = HashSet::new();
+
             loop {
                 tokio::select! {
+                    _ = backfill_interval.tick(), if !backfill_complete => {
+                        match consumer_client.position() {
+                            Ok(positions) => backfill.update(&positions),
+                            Err(e) => debug!("Failed to fetch position of {KONSUMER_OFFSETS_DATA_TOPIC} consumer: {e}"),
+                        }
+                        if backfill.is_complete() {
+                            info!("Backfill of {KONSUMER_OFFSETS_DATA_TOPIC} complete");
+                            backfill_complete = true;
+                        }
+                    },
                     r_msg = consumer_client.recv() => {
                         match r_msg {
                             Ok(m) => {
-                                match konsumer_offsets::KonsumerOffsetsData::try_from_bytes(m.key(), m.payload()) {
-                                    Ok(kod) => {
-                                        if let Err(e) = Self::emit(&sx, kod).await {
-                                            error!("Failed to emit {}: {e}", std::any::type_name::<KonsumerOffsetsData>());
+                                let kod = match m.key() {
+                                    Some(key) if NextGenGroups::is_next_gen(key) => {
+                                        match next_gen.apply(key, m.payload()) {
+                                            Ok(NextGenUpdate::Changed(g)) => {
+                                                let (gm, resolved) = next_gen_group_metadata(&next_gen, &g, &cs_reg).await;
+                                                if resolved {
+                                                    next_gen_unresolved.remove(&g);
+                                                } else {
+                                                    next_gen_unresolved.insert(g);
+                                                }
+                                                KonsumerOffsetsData::GroupMetadata(gm)
+                                            },
+                                            Ok(NextGenUpdate::Deleted(g)) => {
+                                                next_gen_unresolved.remove(&g);
+                                                KonsumerOffsetsData::GroupMetadata(next_gen.group_metadata(&g, &HashMap::new()))
+                                            },
+                                            Ok(NextGenUpdate::Unchanged) => continue,
+                                            Err(e) => {
+                                                count_parse_error(&metric_parse_errors, e.kind(), e, &m);
+                                                continue;
+                                            },
                                         }
-                                    }
-                                    Err(e) => {
-                                        error!("Failed to consume from {}: {e}", KONSUMER_OFFSETS_DATA_TOPIC);
-                                    }
+                                    },
+                                    _ => match KonsumerOffsetsData::try_from_bytes(m.key(), m.payload()) {
+                                        Ok(kod) => kod,
+                                        Err(e) => {
+                                            count_parse_error(&metric_parse_errors, parse_error_kind(&e), e, &m);
+                                            continue;
+                                        },
+                                    },
+                                };
+
+                                // Ignore excluded Groups (i.e. never emitted as Consumer Groups either)
+                                let group = match &kod {
+                                    KonsumerOffsetsData::OffsetCommit(oc) => &oc.group,
+                                    KonsumerOffsetsData::GroupMetadata(gm) => &gm.group,
+                                };
+                                if excludes.is_excluded(group) {
+                                    continue;
+                                }
+
+                                if let Err(e) = Self::emit(&sx, kod).await {
+                                    error!("Failed to emit {}: {e}", std::any::type_name::<KonsumerOffsetsData>());
                                 }
                             },
+                            Err(e) if subsystems.disable_if_unauthorized(Subsystem::CommittedOffsets, &e) => {
+                                // Nothing (more) to backfill: let the rest of the service start
+                                backfill.begin(HashMap::new());
+                                shutdown_token.cancelled().await;
+                                info!("Shutting down");
+                                break;
+                            },
                             Err(e) => {
                                 error!("Failed to fetch cluster metadata: {e}");
                             }
                         }
                     }
+                    res = topology_rx.recv(), if topology_open => {
+                        match res {
+                            Ok(_) | Err(RecvError::Lagged(_)) => {
+                                // Topics might have been created: try again to resolve those
+                                // assigned to Groups using the next-gen consumer rebalance protocol
+                                for g in std::mem::take(&mut next_gen_unresolved) {
+                                    let (gm, resolved) = next_gen_group_metadata(&next_gen, &g, &cs_reg).await;
+                                    if !resolved {
+                                        next_gen_unresolved.insert(g);
+                                    }
+                                    if let Err(e) = Self::emit(&sx, KonsumerOffsetsData::GroupMetadata(gm)).await {
+                                        error!("Failed to emit {}: {e}", std::any::type_name::<KonsumerOffsetsData>());
+                                    }
+                                }
+                            },
+                            Err(RecvError::Closed) => {
+                                topology_open = false;
+                            },
+                        }
+                    }
                     _ = shutdown_token.cancelled() => {
                         info!("Shutting down");
                         break;
@@ -191,3 +517,20 @@ impl Emitter for KonsumerOffsetsDataEmitter {
         (rx, join_handle)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_error_kind_and_key_version() {
+        assert_eq!(parse_error_kind(&KonsumerOffsetsError::MessageKeyMissing), "key_missing");
+        assert_eq!(
+            parse_error_kind(&KonsumerOffsetsError::UnsupportedMessageVersion(3)),
+            "unsupported_message_version"
+        );
+
+        assert_eq!(key_version(&[0, 3, 0, 1]), Some(3));
+        assert_eq!(key_version(&[1]), None);
+    }
+}
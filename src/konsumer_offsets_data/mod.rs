@@ -1,22 +1,52 @@
+mod backfill;
+mod consumer_config;
 mod emitter;
+mod next_gen;
+
+use std::sync::Arc;
 
 use konsumer_offsets::KonsumerOffsetsData;
+use prometheus::Registry;
 use rdkafka::ClientConfig;
 use tokio::sync::mpsc::Receiver;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
-use crate::internals::Emitter;
+use crate::cluster_status::ClusterStatusRegister;
+use crate::consumer_groups::GroupExcludes;
+use crate::internals::{ClientStats, Emitter, Subsystems};
 
-pub use emitter::KonsumerOffsetsDataEmitter;
+pub use backfill::Backfill;
+pub use consumer_config::{IsolationLevel, KonsumerOffsetsConsumerConfig};
+pub use emitter::{KonsumerOffsetsDataEmitter, KonsumerOffsetsStart};
+pub use next_gen::is_next_gen_group_metadata;
 
+#[allow(clippy::too_many_arguments)]
 pub fn init(
     admin_client_config: ClientConfig,
+    start: KonsumerOffsetsStart,
+    consumer_config: KonsumerOffsetsConsumerConfig,
+    excludes: GroupExcludes,
+    cs_reg: Arc<ClusterStatusRegister>,
+    subsystems: Arc<Subsystems>,
+    client_stats: Arc<ClientStats>,
     shutdown_token: CancellationToken,
-) -> (Receiver<KonsumerOffsetsData>, JoinHandle<()>) {
-    let konsumer_offsets_data_emitter = KonsumerOffsetsDataEmitter::new(admin_client_config);
+    metrics: Arc<Registry>,
+) -> (Receiver<KonsumerOffsetsData>, Arc<Backfill>, JoinHandle<()>) {
+    let backfill = Arc::new(Backfill::new(metrics.clone()));
+    let konsumer_offsets_data_emitter = KonsumerOffsetsDataEmitter::new(
+        admin_client_config,
+        start,
+        consumer_config,
+        excludes,
+        backfill.clone(),
+        cs_reg,
+        subsystems,
+        client_stats,
+        metrics,
+    );
     let (kod_rx, kod_join) = konsumer_offsets_data_emitter.spawn(shutdown_token);
 
     debug!("Initialized");
-    (kod_rx, kod_join)
+    (kod_rx, backfill, kod_join)
 }
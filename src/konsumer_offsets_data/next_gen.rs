@@ -0,0 +1,496 @@
+//! Consumer Groups using the next-gen consumer rebalance protocol ([KIP-848]).
+//!
+//! Those Groups don't write a `GroupMetadata` record to `__consumer_offsets`: their Members and
+//! assignments are written as separate records, one per Member, of types (i.e. key versions)
+//! that [`konsumer_offsets`] doesn't parse. Here those records are parsed, accumulated per Group,
+//! and turned into an equivalent [`GroupMetadata`], so they are handled like any other Group.
+//!
+//! Only the record types needed to know which Member owns which Topic Partition are parsed,
+//! as written by Kafka 3.8+ (3.7 shipped an early access version of the protocol).
+//!
+//! [KIP-848]: https://cwiki.apache.org/confluence/display/KAFKA/KIP-848%3A+The+Next+Generation+of+the+Consumer+Rebalance+Protocol
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use konsumer_offsets::{
+    ConsumerProtocolAssignment, GroupMetadata, MemberMetadata, TopicPartitions,
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::kafka_types::Interned;
+
+/// Protocol type of the Groups, as reported for Groups using the classic protocol.
+const PROTOCOL_TYPE_CONSUMER: &str = "consumer";
+
+/// Record type of `ConsumerGroupMetadata`: only its tombstone is used, to know the Group is deleted.
+const RECORD_GROUP_METADATA: i16 = 3;
+/// Record type of `ConsumerGroupMemberMetadata`: client details of a Member.
+const RECORD_MEMBER_METADATA: i16 = 5;
+/// Record type of `ConsumerGroupCurrentMemberAssignment`: Topic Partitions owned by a Member.
+const RECORD_CURRENT_MEMBER_ASSIGNMENT: i16 = 8;
+
+/// Range of record types of the next-gen consumer rebalance protocol.
+///
+/// The ones not listed above (i.e. partition metadata, target assignment) are not needed.
+const RECORD_TYPES: std::ops::RangeInclusive<i16> = 3..=8;
+
+/// Only supported value version of the parsed record types.
+const VALUE_VERSION: i16 = 0;
+
+/// Possible errors when parsing records of the next-gen consumer rebalance protocol.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum NextGenError {
+    /// Record key or value ended before all the expected fields were read.
+    #[error("Record of type {0} is truncated")]
+    Truncated(i16),
+
+    /// Record value is of a version not supported (yet).
+    #[error("Record of type {0} has unsupported value version {1}")]
+    UnsupportedValueVersion(i16, i16),
+}
+
+impl NextGenError {
+    /// Short, metric-label friendly, identifier of the kind of this error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NextGenError::Truncated(_) => "next_gen_bytes_parsing",
+            NextGenError::UnsupportedValueVersion(..) => "next_gen_unsupported_value_version",
+        }
+    }
+}
+
+/// Outcome of applying a record to [`NextGenGroups`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NextGenUpdate {
+    /// The Group Members (or their assignment) changed.
+    Changed(String),
+
+    /// The Group was deleted.
+    Deleted(String),
+
+    /// Nothing relevant changed.
+    Unchanged,
+}
+
+/// Member of a Group using the next-gen consumer rebalance protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct NextGenMember {
+    instance_id: Option<String>,
+    client_id: String,
+    client_host: String,
+
+    /// Partitions owned, by Topic identifier.
+    assignment: BTreeMap<Uuid, Vec<i32>>,
+}
+
+/// Groups using the next-gen consumer rebalance protocol, accumulated from `__consumer_offsets`.
+///
+/// Members are indexed by Member identifier, and Groups by name.
+#[derive(Debug, Default)]
+pub struct NextGenGroups {
+    groups: HashMap<String, BTreeMap<String, NextGenMember>>,
+}
+
+impl NextGenGroups {
+    /// Whether the `__consumer_offsets` record with the given key is of the next-gen
+    /// consumer rebalance protocol (i.e. its type is not known to [`konsumer_offsets`]).
+    pub fn is_next_gen(key: &[u8]) -> bool {
+        key.get(..2).is_some_and(|v| RECORD_TYPES.contains(&i16::from_be_bytes([v[0], v[1]])))
+    }
+
+    /// Apply a `__consumer_offsets` record of the next-gen consumer rebalance protocol.
+    ///
+    /// A missing `payload` is a tombstone (i.e. the record was deleted).
+    pub fn apply(
+        &mut self,
+        key: &[u8],
+        payload: Option<&[u8]>,
+    ) -> Result<NextGenUpdate, NextGenError> {
+        let mut key_reader = Reader::new(key, 0);
+        let record_type = key_reader.i16()?;
+        key_reader.record_type = record_type;
+        let group = key_reader.string()?;
+
+        let value = match payload {
+            Some(p) => {
+                let mut value_reader = Reader::new(p, record_type);
+                let version = value_reader.i16()?;
+                if version != VALUE_VERSION {
+                    return Err(NextGenError::UnsupportedValueVersion(record_type, version));
+                }
+                Some(value_reader)
+            },
+            None => None,
+        };
+
+        match record_type {
+            RECORD_GROUP_METADATA => {
+                if value.is_some() {
+                    return Ok(NextGenUpdate::Unchanged);
+                }
+                self.groups.remove(&group);
+                Ok(NextGenUpdate::Deleted(group))
+            },
+            RECORD_MEMBER_METADATA => {
+                let member_id = key_reader.string()?;
+                let details = match value {
+                    None => None,
+                    Some(mut v) => {
+                        let instance_id = v.compact_nullable_string()?;
+                        let _rack_id = v.compact_nullable_string()?;
+                        Some((instance_id, v.compact_string()?, v.compact_string()?))
+                    },
+                };
+
+                let members = self.group_members(&group);
+                match details {
+                    None => {
+                        members.remove(&member_id);
+                    },
+                    Some((instance_id, client_id, client_host)) => {
+                        let member = members.entry(member_id).or_default();
+                        member.instance_id = instance_id;
+                        member.client_id = client_id;
+                        member.client_host = client_host;
+                    },
+                }
+                Ok(NextGenUpdate::Changed(group))
+            },
+            RECORD_CURRENT_MEMBER_ASSIGNMENT => {
+                let member_id = key_reader.string()?;
+                let assignment = match value {
+                    None => BTreeMap::new(),
+                    Some(mut v) => {
+                        let _member_epoch = v.i32()?;
+                        let _previous_member_epoch = v.i32()?;
+                        let _state = v.i8()?;
+
+                        // Partitions pending revocation are still owned, until revoked
+                        let mut assignment = v.topic_partitions()?;
+                        for (topic_id, partitions) in v.topic_partitions()? {
+                            assignment.entry(topic_id).or_default().extend(partitions);
+                        }
+                        assignment
+                    },
+                };
+
+                // A tombstone of the Member metadata might have already removed it
+                let members = self.group_members(&group);
+                if let Some(member) = members.get_mut(&member_id) {
+                    member.assignment = assignment;
+                } else if !assignment.is_empty() {
+                    members.insert(
+                        member_id,
+                        NextGenMember {
+                            assignment,
+                            ..Default::default()
+                        },
+                    );
+                }
+                Ok(NextGenUpdate::Changed(group))
+            },
+            _ => Ok(NextGenUpdate::Unchanged),
+        }
+    }
+
+    /// Members of the given Group, that is created if not known yet.
+    fn group_members(&mut self, group: &str) -> &mut BTreeMap<String, NextGenMember> {
+        if !self.groups.contains_key(group) {
+            info!("Group '{group}' uses the next-gen consumer rebalance protocol");
+        }
+        self.groups.entry(group.to_string()).or_default()
+    }
+
+    /// Identifiers of the Topics assigned to the Members of the given Group.
+    pub fn topic_ids(&self, group: &str) -> HashSet<Uuid> {
+        self.groups
+            .get(group)
+            .map(|members| members.values().flat_map(|m| m.assignment.keys().copied()).collect())
+            .unwrap_or_default()
+    }
+
+    /// The [`GroupMetadata`] equivalent to what is known about the given Group.
+    ///
+    /// Partitions of Topics not in `topic_names` are omitted, as they can't be identified.
+    /// A Group not known (e.g. deleted) results in a tombstone.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - Name of the Group
+    /// * `topic_names` - Names of the Topics, by identifier
+    pub fn group_metadata(
+        &self,
+        group: &str,
+        topic_names: &HashMap<Uuid, Interned>,
+    ) -> GroupMetadata {
+        let Some(members) = self.groups.get(group) else {
+            return GroupMetadata {
+                message_version: RECORD_GROUP_METADATA,
+                group: group.to_string(),
+                is_tombstone: true,
+                ..Default::default()
+            };
+        };
+
+        GroupMetadata {
+            message_version: RECORD_GROUP_METADATA,
+            group: group.to_string(),
+            is_tombstone: false,
+            schema_version: VALUE_VERSION,
+            protocol_type: PROTOCOL_TYPE_CONSUMER.to_string(),
+            members: members
+                .iter()
+                .map(|(id, m)| MemberMetadata {
+                    id: id.clone(),
+                    group_instance_id: m.instance_id.clone().unwrap_or_default(),
+                    client_id: m.client_id.clone(),
+                    client_host: m.client_host.clone(),
+                    assignment: ConsumerProtocolAssignment {
+                        assigned_topic_partitions: m
+                            .assignment
+                            .iter()
+                            .filter_map(|(topic_id, partitions)| {
+                                topic_names.get(topic_id).map(|topic| TopicPartitions {
+                                    topic: topic.to_string(),
+                                    partitions: partitions.clone(),
+                                })
+                            })
+                            .collect(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Whether the [`GroupMetadata`] was produced by [`NextGenGroups::group_metadata`].
+pub fn is_next_gen_group_metadata(gm: &GroupMetadata) -> bool {
+    RECORD_TYPES.contains(&gm.message_version)
+}
+
+/// Reads the fields of a `__consumer_offsets` record of the given type, in the Kafka protocol
+/// encoding: keys use the classic one, values the "flexible" one (i.e. compact strings/arrays).
+struct Reader<'a> {
+    bytes: &'a [u8],
+    record_type: i16,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], record_type: i16) -> Self {
+        Self {
+            bytes,
+            record_type,
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], NextGenError> {
+        if self.bytes.len() < n {
+            return Err(NextGenError::Truncated(self.record_type));
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn i8(&mut self) -> Result<i8, NextGenError> {
+        Ok(self.take(1)?[0] as i8)
+    }
+
+    fn i16(&mut self) -> Result<i16, NextGenError> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().expect("2 bytes")))
+    }
+
+    fn i32(&mut self) -> Result<i32, NextGenError> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn uuid(&mut self) -> Result<Uuid, NextGenError> {
+        Ok(Uuid::from_bytes(self.take(16)?.try_into().expect("16 bytes")))
+    }
+
+    fn unsigned_varint(&mut self) -> Result<u32, NextGenError> {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let b = self.take(1)?[0];
+            value |= ((b & 0x7f) as u32) << (i * 7);
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(NextGenError::Truncated(self.record_type))
+    }
+
+    /// Classic string: `i16` length, followed by UTF-8 bytes.
+    fn string(&mut self) -> Result<String, NextGenError> {
+        let len = self.i16()?.max(0) as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    /// Compact nullable string: unsigned varint length + 1 (`0` for null), followed by UTF-8 bytes.
+    fn compact_nullable_string(&mut self) -> Result<Option<String>, NextGenError> {
+        match self.unsigned_varint()? {
+            0 => Ok(None),
+            n => Ok(Some(String::from_utf8_lossy(self.take(n as usize - 1)?).into_owned())),
+        }
+    }
+
+    fn compact_string(&mut self) -> Result<String, NextGenError> {
+        Ok(self.compact_nullable_string()?.unwrap_or_default())
+    }
+
+    /// Compact array length: unsigned varint length + 1 (`0` for null, read as empty).
+    fn compact_array_len(&mut self) -> Result<usize, NextGenError> {
+        Ok(self.unsigned_varint()?.saturating_sub(1) as usize)
+    }
+
+    /// Skip the tagged fields at the end of a "flexible" structure.
+    fn skip_tagged_fields(&mut self) -> Result<(), NextGenError> {
+        for _ in 0..self.unsigned_varint()? {
+            let _tag = self.unsigned_varint()?;
+            let size = self.unsigned_varint()?;
+            self.take(size as usize)?;
+        }
+        Ok(())
+    }
+
+    /// Array of `TopicPartitions` structures: Topic identifier, and its Partitions.
+    fn topic_partitions(&mut self) -> Result<BTreeMap<Uuid, Vec<i32>>, NextGenError> {
+        let mut res = BTreeMap::new();
+        for _ in 0..self.compact_array_len()? {
+            let topic_id = self.uuid()?;
+            let partitions = (0..self.compact_array_len()?)
+                .map(|_| self.i32())
+                .collect::<Result<Vec<i32>, NextGenError>>()?;
+            self.skip_tagged_fields()?;
+            res.insert(topic_id, partitions);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOPIC_ID: Uuid = Uuid::from_u128(0x41b6e637_130d_4375_be65_5e177e52ae9a);
+
+    fn key(record_type: i16, group: &str, member: Option<&str>) -> Vec<u8> {
+        let mut k = record_type.to_be_bytes().to_vec();
+        for s in std::iter::once(group).chain(member) {
+            k.extend((s.len() as i16).to_be_bytes());
+            k.extend(s.as_bytes());
+        }
+        k
+    }
+
+    fn compact_string(v: &mut Vec<u8>, s: Option<&str>) {
+        match s {
+            None => v.push(0),
+            Some(s) => {
+                v.push(s.len() as u8 + 1);
+                v.extend(s.as_bytes());
+            },
+        }
+    }
+
+    fn member_metadata(instance_id: Option<&str>, client_id: &str, client_host: &str) -> Vec<u8> {
+        let mut v = VALUE_VERSION.to_be_bytes().to_vec();
+        compact_string(&mut v, instance_id);
+        compact_string(&mut v, None);
+        compact_string(&mut v, Some(client_id));
+        compact_string(&mut v, Some(client_host));
+        // Fields not parsed: subscribed topic names, regex, rebalance timeout, assignor, tags
+        v.extend([1, 0, 0xff, 0xff, 0xff, 0xff, 0, 0]);
+        v
+    }
+
+    fn current_assignment(assigned: &[i32], pending_revocation: &[i32]) -> Vec<u8> {
+        let mut v = VALUE_VERSION.to_be_bytes().to_vec();
+        v.extend(5i32.to_be_bytes());
+        v.extend(4i32.to_be_bytes());
+        v.push(0);
+        for partitions in [assigned, pending_revocation] {
+            v.push(2);
+            v.extend(TOPIC_ID.as_bytes());
+            v.push(partitions.len() as u8 + 1);
+            for p in partitions {
+                v.extend(p.to_be_bytes());
+            }
+            v.push(0);
+        }
+        v.push(0);
+        v
+    }
+
+    #[test]
+    fn next_gen_record_types() {
+        assert!(NextGenGroups::is_next_gen(&key(5, "g", Some("m"))));
+        assert!(NextGenGroups::is_next_gen(&key(8, "g", Some("m"))));
+        assert!(!NextGenGroups::is_next_gen(&key(1, "g", None)));
+        assert!(!NextGenGroups::is_next_gen(&key(2, "g", None)));
+        assert!(!NextGenGroups::is_next_gen(&[0]));
+    }
+
+    #[test]
+    fn members_and_assignment() {
+        let mut groups = NextGenGroups::default();
+        let topic_names = HashMap::from([(TOPIC_ID, Interned::new("topic"))]);
+
+        let update = groups
+            .apply(&key(5, "g", Some("m1")), Some(&member_metadata(Some("i1"), "c1", "/h1")))
+            .unwrap();
+        assert_eq!(update, NextGenUpdate::Changed("g".to_string()));
+        groups.apply(&key(8, "g", Some("m1")), Some(&current_assignment(&[0, 1], &[2]))).unwrap();
+        assert_eq!(groups.topic_ids("g"), HashSet::from([TOPIC_ID]));
+
+        let gm = groups.group_metadata("g", &topic_names);
+        assert!(is_next_gen_group_metadata(&gm));
+        assert_eq!(gm.protocol_type, PROTOCOL_TYPE_CONSUMER);
+        assert_eq!(gm.members.len(), 1);
+        assert_eq!(gm.members[0].id, "m1");
+        assert_eq!(gm.members[0].group_instance_id, "i1");
+        assert_eq!(gm.members[0].client_id, "c1");
+        assert_eq!(gm.members[0].client_host, "/h1");
+        assert_eq!(
+            gm.members[0].assignment.assigned_topic_partitions,
+            vec![TopicPartitions {
+                topic: "topic".to_string(),
+                partitions: vec![0, 1, 2],
+            }]
+        );
+
+        // Unknown Topics are omitted
+        let gm = groups.group_metadata("g", &HashMap::new());
+        assert!(gm.members[0].assignment.assigned_topic_partitions.is_empty());
+
+        // Member leaves
+        groups.apply(&key(8, "g", Some("m1")), None).unwrap();
+        groups.apply(&key(5, "g", Some("m1")), None).unwrap();
+        assert!(groups.group_metadata("g", &topic_names).members.is_empty());
+
+        // Group deleted
+        let update = groups.apply(&key(3, "g", None), None).unwrap();
+        assert_eq!(update, NextGenUpdate::Deleted("g".to_string()));
+        assert!(groups.group_metadata("g", &topic_names).is_tombstone);
+    }
+
+    #[test]
+    fn errors() {
+        let mut groups = NextGenGroups::default();
+
+        let value = member_metadata(None, "c1", "/h1");
+        assert_eq!(
+            groups.apply(&key(5, "g", Some("m1")), Some(&value[..5])),
+            Err(NextGenError::Truncated(5))
+        );
+        assert_eq!(
+            groups.apply(&key(8, "g", Some("m1")), Some(&[0, 1])),
+            Err(NextGenError::UnsupportedValueVersion(8, 1))
+        );
+        assert_eq!(groups.apply(&[0, 5, 0], None), Err(NextGenError::Truncated(5)));
+    }
+}
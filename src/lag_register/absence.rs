@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use prometheus::{register_gauge_vec_with_registry, GaugeVec, Registry};
+
+use super::register::GroupWithLag;
+
+use crate::kafka_types::Interned;
+use crate::prometheus_metrics::LABEL_GROUP;
+
+const MET_ABSENT_NAME: &str = "consumer_group_absent_seconds";
+const MET_ABSENT_HELP: &str =
+    "Time (s) the consumer group has been vanished or empty for, while its lag is kept for the grace period";
+
+/// Why a Consumer Group is considered absent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Absence {
+    /// No longer listed by the Cluster.
+    Vanished,
+
+    /// Still listed by the Cluster, but with no Members.
+    Empty,
+}
+
+/// Consumer Groups that vanished, or whose Members all left, whose lag data is kept
+/// for a grace period, in case they come back (e.g. during a deploy, or a rebalance).
+///
+/// Once the grace period is over, the lag data is dropped: all of it for a vanished
+/// Consumer Group, only the lag of the Topic Partitions it no longer owns for an empty one.
+/// A grace period of zero disables this, and the lag data is dropped straight away.
+pub(super) struct AbsentGroups {
+    grace: Duration,
+    since: HashMap<Interned, (Absence, Instant)>,
+
+    // Prometheus Metrics
+    metric_absent: GaugeVec,
+}
+
+impl AbsentGroups {
+    pub(super) fn new(grace: Duration, metrics: Arc<Registry>) -> Self {
+        Self {
+            grace,
+            since: HashMap::new(),
+            metric_absent: register_gauge_vec_with_registry!(
+                MET_ABSENT_NAME,
+                MET_ABSENT_HELP,
+                &[LABEL_GROUP],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_ABSENT_NAME}")),
+        }
+    }
+
+    /// Whether a grace period applies at all.
+    pub(super) fn is_enabled(&self) -> bool {
+        !self.grace.is_zero()
+    }
+
+    /// Whether any Consumer Group is currently absent.
+    pub(super) fn is_empty(&self) -> bool {
+        self.since.is_empty()
+    }
+
+    /// Mark the Consumer Group as absent, beginning its grace period if it wasn't already.
+    pub(super) fn mark(&mut self, group: &str, absence: Absence, now: Instant) {
+        match self.since.get_mut(group) {
+            Some((a, _)) => *a = absence,
+            None => {
+                debug!(
+                    "Group '{group}' absent ({absence:?}): keeping its lag for {:?}",
+                    self.grace
+                );
+                self.since.insert(Interned::new(group), (absence, now));
+                self.metric_absent.with_label_values(&[group]).set(0_f64);
+            },
+        }
+    }
+
+    /// Mark the Consumer Group as present again, ending its grace period (if any).
+    pub(super) fn clear(&mut self, group: &str) {
+        if self.since.remove(group).is_some() {
+            debug!("Group '{group}' back before the end of its grace period");
+            let _ = self.metric_absent.remove_label_values(&[group]);
+        }
+    }
+
+    /// Drop the lag data of the Consumer Groups whose grace period is over,
+    /// and update for how long the others have been absent.
+    ///
+    /// Returns the Consumer Groups removed altogether.
+    pub(super) fn expire(
+        &mut self,
+        groups: &mut BTreeMap<Interned, GroupWithLag>,
+        now: Instant,
+    ) -> Vec<Interned> {
+        let mut removed = Vec::new();
+        self.since.retain(|group, (absence, since)| {
+            let absent_for = now.saturating_duration_since(*since);
+            if absent_for < self.grace {
+                self.metric_absent.with_label_values(&[group]).set(absent_for.as_secs_f64());
+                return true;
+            }
+
+            match absence {
+                Absence::Vanished => {
+                    if groups.remove(group).is_some() {
+                        debug!("Group '{group}' removed, at the end of its grace period");
+                        removed.push(group.clone());
+                    }
+                },
+                Absence::Empty => {
+                    if let Some(gwl) = groups.get_mut(group) {
+                        debug!("Group '{group}' still empty, at the end of its grace period");
+                        gwl.lag_by_topic_partition.retain(|_, lwo| lwo.owner.is_some());
+                    }
+                },
+            }
+            let _ = self.metric_absent.remove_label_values(&[group]);
+            false
+        });
+        removed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kafka_types::{Member, TopicPartition};
+    use crate::lag_register::{Lag, LagWithOwner};
+
+    fn group_with_lag(owned: bool) -> GroupWithLag {
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition.insert(
+            TopicPartition::new("t".to_string(), 0),
+            LagWithOwner {
+                lag: Some(Lag::default()),
+                owner: owned.then(Member::default),
+            },
+        );
+        gwl
+    }
+
+    #[test]
+    fn drops_lag_at_the_end_of_the_grace_period() {
+        let grace = Duration::from_secs(30);
+        let mut absent = AbsentGroups::new(grace, Arc::new(Registry::new()));
+        let mut groups = BTreeMap::from([
+            (Interned::new("vanished"), group_with_lag(true)),
+            (Interned::new("empty"), group_with_lag(false)),
+            (Interned::new("back"), group_with_lag(true)),
+        ]);
+
+        let t0 = Instant::now();
+        absent.mark("vanished", Absence::Vanished, t0);
+        absent.mark("empty", Absence::Empty, t0);
+        absent.mark("back", Absence::Vanished, t0);
+
+        absent.expire(&mut groups, t0 + Duration::from_secs(10));
+        assert_eq!(groups.len(), 3);
+        assert_eq!(absent.metric_absent.with_label_values(&["vanished"]).get(), 10_f64);
+
+        absent.clear("back");
+        absent.expire(&mut groups, t0 + grace);
+        assert!(absent.is_empty());
+        assert!(!groups.contains_key("vanished"));
+        assert!(groups["empty"].lag_by_topic_partition.is_empty());
+        assert_eq!(groups["back"].lag_by_topic_partition.len(), 1);
+    }
+
+    #[test]
+    fn grace_period_begins_when_first_absent() {
+        let grace = Duration::from_secs(30);
+        let mut absent = AbsentGroups::new(grace, Arc::new(Registry::new()));
+        let mut groups = BTreeMap::from([(Interned::new("g"), group_with_lag(false))]);
+
+        let t0 = Instant::now();
+        absent.mark("g", Absence::Empty, t0);
+        absent.mark("g", Absence::Vanished, t0 + Duration::from_secs(20));
+        absent.expire(&mut groups, t0 + grace);
+        assert!(groups.is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use chrono::Duration;
+use prometheus::{register_int_counter_vec_with_registry, IntCounterVec, Registry};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use super::register::Lag;
+
+use crate::kafka_types::{Interned, Member, TopicPartition};
+use crate::prometheus_metrics::LABEL_KIND;
+
+const EVENTS_CHANNEL_SIZE: usize = 1_024;
+
+const MET_EVENTS_NAME: &str = "lag_events_total";
+const MET_EVENTS_HELP: &str =
+    "Events of the lag register (e.g. consumer group appeared, lag threshold crossed), per kind";
+
+/// Structured change to the lag of the Consumer Groups, as detected by the [`super::LagRegister`].
+///
+/// Features that react to the lag changing (e.g. alerting, streaming to clients) subscribe
+/// to these (see [`super::LagRegister::subscribe_events`]), instead of each diffing the lag
+/// register on their own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LagEvent {
+    /// A Consumer Group started being tracked.
+    GroupAppeared {
+        group: Interned,
+    },
+
+    /// A Consumer Group stopped being tracked (e.g. deleted, or at the end of its grace period).
+    GroupDisappeared {
+        group: Interned,
+    },
+
+    /// The Time Lag of a Consumer Group, for a Topic Partition, went above the threshold,
+    /// or back within it.
+    LagThresholdCrossed {
+        group: Interned,
+        topic: Interned,
+        partition: u32,
+        above: bool,
+        offset_lag: u64,
+        time_lag_ms: i64,
+    },
+
+    /// The Member of a Consumer Group owning a Topic Partition changed, if any owns it.
+    OwnerChanged {
+        group: Interned,
+        topic: Interned,
+        partition: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        previous: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        current: Option<String>,
+    },
+}
+
+impl LagEvent {
+    /// Kind of the event, as it's labeled in metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::GroupAppeared {
+                ..
+            } => "group_appeared",
+            Self::GroupDisappeared {
+                ..
+            } => "group_disappeared",
+            Self::LagThresholdCrossed {
+                ..
+            } => "lag_threshold_crossed",
+            Self::OwnerChanged {
+                ..
+            } => "owner_changed",
+        }
+    }
+
+    /// Consumer Group the event is about.
+    pub fn group(&self) -> &Interned {
+        match self {
+            Self::GroupAppeared {
+                group,
+            }
+            | Self::GroupDisappeared {
+                group,
+            }
+            | Self::LagThresholdCrossed {
+                group,
+                ..
+            }
+            | Self::OwnerChanged {
+                group,
+                ..
+            } => group,
+        }
+    }
+}
+
+/// Broadcasts the [`LagEvent`]s, as the lag register is updated.
+///
+/// Events are only sent if anyone is subscribed: slow subscribers miss the oldest events.
+pub(super) struct LagEvents {
+    sender: broadcast::Sender<LagEvent>,
+    threshold: Duration,
+
+    // Prometheus Metrics
+    metric_events: IntCounterVec,
+}
+
+impl LagEvents {
+    /// Create a new [`LagEvents`].
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Time Lag that, when crossed, sends a [`LagEvent::LagThresholdCrossed`]
+    /// * `metrics` - Registry to register metrics with
+    pub(super) fn new(threshold: std::time::Duration, metrics: Arc<Registry>) -> Self {
+        Self {
+            sender: broadcast::channel(EVENTS_CHANNEL_SIZE).0,
+            threshold: Duration::from_std(threshold).unwrap_or_else(|_| Duration::max_value()),
+            metric_events: register_int_counter_vec_with_registry!(
+                MET_EVENTS_NAME,
+                MET_EVENTS_HELP,
+                &[LABEL_KIND],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_EVENTS_NAME}")),
+        }
+    }
+
+    pub(super) fn sender(&self) -> broadcast::Sender<LagEvent> {
+        self.sender.clone()
+    }
+
+    pub(super) fn group_appeared(&self, group: &Interned) {
+        self.send(LagEvent::GroupAppeared {
+            group: group.clone(),
+        });
+    }
+
+    pub(super) fn group_disappeared(&self, group: &Interned) {
+        self.send(LagEvent::GroupDisappeared {
+            group: group.clone(),
+        });
+    }
+
+    /// Send a [`LagEvent::OwnerChanged`], if the owner is a different Member.
+    pub(super) fn owner_changed(
+        &self,
+        group: &Interned,
+        tp: &TopicPartition,
+        previous: Option<&Member>,
+        current: Option<&Member>,
+    ) {
+        let (previous, current) = (previous.map(|m| &m.id), current.map(|m| &m.id));
+        if previous != current {
+            self.send(LagEvent::OwnerChanged {
+                group: group.clone(),
+                topic: tp.topic.clone(),
+                partition: tp.partition,
+                previous: previous.cloned(),
+                current: current.cloned(),
+            });
+        }
+    }
+
+    /// Send a [`LagEvent::LagThresholdCrossed`], if the Time Lag crossed the threshold.
+    ///
+    /// A Topic Partition with no previous [`Lag`] is considered within the threshold.
+    pub(super) fn lag_changed(
+        &self,
+        group: &Interned,
+        tp: &TopicPartition,
+        previous: Option<&Lag>,
+        current: &Lag,
+    ) {
+        let was_above = previous.is_some_and(|l| l.time_lag > self.threshold);
+        let above = current.time_lag > self.threshold;
+        if was_above != above {
+            self.send(LagEvent::LagThresholdCrossed {
+                group: group.clone(),
+                topic: tp.topic.clone(),
+                partition: tp.partition,
+                above,
+                offset_lag: current.offset_lag,
+                time_lag_ms: current.time_lag.num_milliseconds(),
+            });
+        }
+    }
+
+    fn send(&self, event: LagEvent) {
+        self.metric_events.with_label_values(&[event.kind()]).inc();
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lag(time_lag_s: i64) -> Lag {
+        Lag {
+            time_lag: Duration::seconds(time_lag_s),
+            ..Default::default()
+        }
+    }
+
+    fn member(id: &str) -> Member {
+        Member {
+            id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sends_only_actual_changes() {
+        let events = LagEvents::new(std::time::Duration::from_secs(60), Arc::new(Registry::new()));
+        let mut rx = events.sender().subscribe();
+        let group = Interned::new("my-app");
+        let tp = TopicPartition::new(Interned::new("orders"), 0);
+
+        events.lag_changed(&group, &tp, None, &lag(10));
+        events.lag_changed(&group, &tp, Some(&lag(10)), &lag(90));
+        events.lag_changed(&group, &tp, Some(&lag(90)), &lag(120));
+        events.lag_changed(&group, &tp, Some(&lag(120)), &lag(0));
+        events.owner_changed(&group, &tp, Some(&member("a")), Some(&member("a")));
+        events.owner_changed(&group, &tp, Some(&member("a")), None);
+
+        let received: Vec<LagEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(received.len(), 3);
+        assert!(matches!(
+            received[0],
+            LagEvent::LagThresholdCrossed {
+                above: true,
+                time_lag_ms: 90_000,
+                ..
+            }
+        ));
+        assert!(matches!(
+            received[1],
+            LagEvent::LagThresholdCrossed {
+                above: false,
+                ..
+            }
+        ));
+        assert_eq!(
+            received[2],
+            LagEvent::OwnerChanged {
+                group,
+                topic: "orders".into(),
+                partition: 0,
+                previous: Some("a".to_string()),
+                current: None,
+            }
+        );
+    }
+}
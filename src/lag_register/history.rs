@@ -0,0 +1,308 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use prometheus::{register_gauge_vec_with_registry, GaugeVec, Registry};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use super::snapshot::LagSnapshot;
+use super::trend::{offset_lag_forecast, offset_lag_trend, time_lag_burn_rate, LagTrendConfig};
+
+use crate::constants::{DEFAULT_LAG_HISTORY_RESOLUTION, DEFAULT_LAG_HISTORY_RETENTION};
+use crate::internals::{LagSink, LagSinkFuture};
+use crate::kafka_types::Interned;
+use crate::prometheus_metrics::LABEL_GROUP;
+
+const MET_TREND_NAME: &str = "kafka_consumer_group_lag_trend";
+const MET_TREND_HELP: &str =
+    "Rate (offsets/s) at which the offset lag of the consumer group is growing (positive) or shrinking (negative)";
+const MET_FORECAST_NAME: &str = "kafka_consumer_group_offset_lag_forecast_15m";
+const MET_FORECAST_HELP: &str =
+    "Offset lag of the consumer group forecast in 15 minutes, if the current production and consumption rates hold";
+const MET_BURN_RATE_NAME: &str = "kafka_consumer_group_lag_burn_rate";
+const MET_BURN_RATE_HELP: &str =
+    "Rate at which the consumer group burns the error budget of its time lag target (1 = as fast as allowed)";
+
+/// How far ahead the Offset Lag is forecast.
+const FORECAST_HORIZON: Duration = Duration::from_secs(15 * 60);
+
+/// How much history of the lag of each Consumer Group to keep in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LagHistoryConfig {
+    /// How far back the history goes.
+    pub retention: Duration,
+
+    /// Interval between 2 consecutive points of the history.
+    pub resolution: Duration,
+}
+
+impl LagHistoryConfig {
+    /// Max amount of points kept in the history of each Consumer Group.
+    fn capacity(&self) -> usize {
+        (self.retention.as_millis() / self.resolution.as_millis().max(1)).max(1) as usize
+    }
+}
+
+impl Default for LagHistoryConfig {
+    fn default() -> Self {
+        Self {
+            retention: humantime::parse_duration(DEFAULT_LAG_HISTORY_RETENTION)
+                .expect("Invalid default"),
+            resolution: humantime::parse_duration(DEFAULT_LAG_HISTORY_RESOLUTION)
+                .expect("Invalid default"),
+        }
+    }
+}
+
+/// Lag of a Consumer Group at a point in time, aggregated across all its Topic Partitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GroupLagPoint {
+    /// When the [`LagSnapshot`] this point comes from was taken (ms since UTC Epoch).
+    pub timestamp_ms: i64,
+
+    /// Sum of the Offset Lag of all the Topic Partitions.
+    pub offset_lag: u64,
+
+    /// Max of the Time Lag of all the Topic Partitions.
+    pub time_lag_ms: i64,
+}
+
+/// Keeps, in memory, a history of the aggregate lag of each Consumer Group.
+///
+/// A point is appended to the history of each Consumer Group at every
+/// [`LagHistoryConfig::resolution`], and the oldest points are discarded once they are
+/// older than [`LagHistoryConfig::retention`]: the history of a Consumer Group is forgotten,
+/// once it's no longer tracked. The history is ready to be served by the
+/// `/groups/{name}/history` HTTP endpoint.
+///
+/// At every new point, the trend of the lag of each Consumer Group is evaluated over
+/// its history, as per [`LagTrendConfig`], and exported as metrics (with a short-horizon
+/// forecast of the Offset Lag).
+pub struct LagHistorySink {
+    config: LagHistoryConfig,
+    trend: LagTrendConfig,
+    history: RwLock<HashMap<Interned, VecDeque<GroupLagPoint>>>,
+
+    // Prometheus Metrics
+    metric_trend: GaugeVec,
+    metric_forecast: GaugeVec,
+    metric_burn_rate: GaugeVec,
+}
+
+impl LagHistorySink {
+    /// Create a new [`LagHistorySink`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - How much history to keep
+    /// * `trend` - How to evaluate the trend of the lag over the history
+    /// * `metrics` - Where to register the lag trend metrics
+    pub fn new(config: LagHistoryConfig, trend: LagTrendConfig, metrics: Arc<Registry>) -> Self {
+        Self {
+            config,
+            trend,
+            history: RwLock::new(HashMap::new()),
+            metric_trend: register_gauge_vec_with_registry!(
+                MET_TREND_NAME,
+                MET_TREND_HELP,
+                &[LABEL_GROUP],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_TREND_NAME}")),
+            metric_forecast: register_gauge_vec_with_registry!(
+                MET_FORECAST_NAME,
+                MET_FORECAST_HELP,
+                &[LABEL_GROUP],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_FORECAST_NAME}")),
+            metric_burn_rate: register_gauge_vec_with_registry!(
+                MET_BURN_RATE_NAME,
+                MET_BURN_RATE_HELP,
+                &[LABEL_GROUP],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_BURN_RATE_NAME}")),
+        }
+    }
+
+    /// History of the aggregate lag of the given Consumer Group, oldest point first.
+    ///
+    /// Returns `None` if the Consumer Group is not tracked.
+    pub async fn history(&self, group: &str) -> Option<Vec<GroupLagPoint>> {
+        self.history.read().await.get(group).map(|points| points.iter().copied().collect())
+    }
+
+    /// Append a point to the history of each Consumer Group in the given [`LagSnapshot`].
+    ///
+    /// Consumer Groups whose lag is not known yet, for any Topic Partition, get no point.
+    fn update(
+        &self,
+        history: &mut HashMap<Interned, VecDeque<GroupLagPoint>>,
+        snapshot: &LagSnapshot,
+    ) {
+        let capacity = self.config.capacity();
+        let timestamp_ms = snapshot.taken_at.timestamp_millis();
+
+        // Forget about the Consumer Groups no longer tracked, and start tracking the new ones
+        history.retain(|g, _| {
+            let tracked = snapshot.groups.contains_key(g);
+            if !tracked {
+                let _ = self.metric_trend.remove_label_values(&[g]);
+                let _ = self.metric_forecast.remove_label_values(&[g]);
+                let _ = self.metric_burn_rate.remove_label_values(&[g]);
+            }
+            tracked
+        });
+        for g in snapshot.groups.keys() {
+            if !history.contains_key(g) {
+                history.insert(g.clone(), VecDeque::with_capacity(capacity));
+            }
+        }
+
+        let mut points: HashMap<&str, GroupLagPoint> = HashMap::new();
+        for ((group, _), gtl) in snapshot.lag_by_group_topic() {
+            let point = points.entry(group).or_insert(GroupLagPoint {
+                timestamp_ms,
+                offset_lag: 0,
+                time_lag_ms: 0,
+            });
+            point.offset_lag += gtl.offset_lag;
+            point.time_lag_ms = point.time_lag_ms.max(gtl.time_lag.num_milliseconds());
+        }
+
+        for (group, point) in points {
+            if let Some(points) = history.get_mut(group) {
+                points.push_back(point);
+                while points.len() > capacity {
+                    points.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Evaluate the trend of the lag of each Consumer Group, over its history.
+    fn update_trend(&self, history: &mut HashMap<Interned, VecDeque<GroupLagPoint>>) {
+        for (g, points) in history.iter_mut() {
+            let points = points.make_contiguous();
+
+            match offset_lag_trend(points, self.trend.window) {
+                Some(trend) => self.metric_trend.with_label_values(&[g]).set(trend),
+                None => {
+                    let _ = self.metric_trend.remove_label_values(&[g]);
+                },
+            }
+
+            match offset_lag_forecast(points, self.trend.window, FORECAST_HORIZON) {
+                Some(forecast) => self.metric_forecast.with_label_values(&[g]).set(forecast),
+                None => {
+                    let _ = self.metric_forecast.remove_label_values(&[g]);
+                },
+            }
+
+            let burn_rate = self.trend.slo_target.and_then(|target| {
+                time_lag_burn_rate(points, self.trend.window, target, self.trend.slo_objective)
+            });
+            match burn_rate {
+                Some(burn_rate) => self.metric_burn_rate.with_label_values(&[g]).set(burn_rate),
+                None => {
+                    let _ = self.metric_burn_rate.remove_label_values(&[g]);
+                },
+            }
+        }
+    }
+}
+
+impl LagSink for LagHistorySink {
+    fn name(&self) -> &'static str {
+        "lag_history"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.resolution)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let mut history = self.history.write().await;
+            self.update(&mut history, snapshot);
+            self.update_trend(&mut history);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Duration as ChronoDuration};
+
+    use super::*;
+    use crate::kafka_types::TopicPartition;
+    use crate::lag_register::{GroupWithLag, Lag, LagWithOwner};
+
+    /// `(partition, offset lag, time lag ms)` of each Topic Partition consumed by a Consumer Group.
+    type PartitionsLag<'a> = &'a [(u32, u64, i64)];
+
+    fn snapshot(taken_at_ms: i64, groups: &[(&str, PartitionsLag)]) -> LagSnapshot {
+        let mut snapshot = LagSnapshot {
+            taken_at: DateTime::from_timestamp_millis(taken_at_ms).unwrap(),
+            ..Default::default()
+        };
+        for (g, partitions) in groups {
+            let mut gwl = GroupWithLag::default();
+            for (p, offset_lag, time_lag_ms) in partitions.iter() {
+                gwl.lag_by_topic_partition.insert(
+                    TopicPartition::new("t".to_string(), *p),
+                    LagWithOwner {
+                        lag: Some(Lag {
+                            offset_lag: *offset_lag,
+                            time_lag: ChronoDuration::milliseconds(*time_lag_ms),
+                            ..Default::default()
+                        }),
+                        owner: None,
+                    },
+                );
+            }
+            snapshot.groups.insert((*g).into(), Arc::new(gwl));
+        }
+        snapshot
+    }
+
+    #[tokio::test]
+    async fn keeps_retention_and_forgets_untracked_groups() {
+        let sink = LagHistorySink::new(
+            LagHistoryConfig {
+                retention: Duration::from_secs(30),
+                resolution: Duration::from_secs(10),
+            },
+            LagTrendConfig::default(),
+            Arc::new(Registry::new()),
+        );
+
+        for i in 0..5 {
+            let lag = i as u64 * 10;
+            sink.publish(&snapshot(
+                i * 10_000,
+                &[("a", &[(0, lag, 100), (1, lag, 200)]), ("b", &[])],
+            ))
+            .await
+            .unwrap();
+        }
+
+        let history = sink.history("a").await.unwrap();
+        assert_eq!(
+            history.iter().map(|p| (p.timestamp_ms, p.offset_lag)).collect::<Vec<_>>(),
+            vec![(20_000, 40), (30_000, 60), (40_000, 80)]
+        );
+        assert!(history.iter().all(|p| p.time_lag_ms == 200));
+
+        // Tracked, but with no lag known yet
+        assert_eq!(sink.history("b").await, Some(vec![]));
+        assert_eq!(sink.history("c").await, None);
+
+        sink.publish(&snapshot(50_000, &[("b", &[(0, 1, 1)])])).await.unwrap();
+        assert_eq!(sink.history("a").await, None);
+        assert_eq!(sink.history("b").await.unwrap().len(), 1);
+    }
+}
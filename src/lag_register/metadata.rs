@@ -0,0 +1,86 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use konsumer_offsets::{GroupMetadata, TopicPartitions};
+
+/// Digest of the parts of a [`GroupMetadata`] that the [`super::LagRegister`] applies.
+///
+/// During stable operation, the same `GroupMetadata` of a Consumer Group is read over and over
+/// (e.g. re-written on every rebalance that changes nothing, or read again after compaction):
+/// comparing digests, unchanged records are skipped.
+///
+/// Timestamps are left out: a record that only differs by those changes nothing.
+pub(super) fn digest(gm: &GroupMetadata) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    gm.message_version.hash(&mut hasher);
+    gm.group.hash(&mut hasher);
+    gm.protocol_type.hash(&mut hasher);
+    gm.protocol.hash(&mut hasher);
+    gm.generation.hash(&mut hasher);
+    gm.leader.hash(&mut hasher);
+
+    gm.members.len().hash(&mut hasher);
+    for m in gm.members.iter() {
+        m.id.hash(&mut hasher);
+        m.group_instance_id.hash(&mut hasher);
+        m.client_id.hash(&mut hasher);
+        m.client_host.hash(&mut hasher);
+        hash_topic_partitions(&m.assignment.assigned_topic_partitions, &mut hasher);
+        hash_topic_partitions(&m.subscription.owned_topic_partitions, &mut hasher);
+    }
+
+    hasher.finish()
+}
+
+fn hash_topic_partitions(tps: &[TopicPartitions], hasher: &mut DefaultHasher) {
+    tps.len().hash(hasher);
+    for tp in tps.iter() {
+        tp.topic.hash(hasher);
+        tp.partitions.hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Utc};
+    use konsumer_offsets::{ConsumerProtocolAssignment, MemberMetadata};
+
+    use super::*;
+
+    fn group_metadata(partitions: Vec<i32>, timestamp_ms: i64) -> GroupMetadata {
+        GroupMetadata {
+            group: "my-app".to_string(),
+            protocol_type: "consumer".to_string(),
+            generation: 3,
+            current_state_timestamp: DateTime::<Utc>::from_timestamp_millis(timestamp_ms).unwrap(),
+            members: vec![MemberMetadata {
+                id: "member-1".to_string(),
+                client_host: "/10.0.0.1".to_string(),
+                assignment: ConsumerProtocolAssignment {
+                    assigned_topic_partitions: vec![TopicPartitions {
+                        topic: "orders".to_string(),
+                        partitions,
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ignores_timestamps_only() {
+        let gm = group_metadata(vec![0, 1], 1_000);
+        assert_eq!(digest(&gm), digest(&group_metadata(vec![0, 1], 2_000)));
+        assert_ne!(digest(&gm), digest(&group_metadata(vec![0, 1, 2], 1_000)));
+        assert_ne!(
+            digest(&gm),
+            digest(&GroupMetadata {
+                generation: 4,
+                ..gm.clone()
+            })
+        );
+    }
+}
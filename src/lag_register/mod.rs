@@ -1,21 +1,58 @@
+mod absence;
+mod events;
+mod history;
+mod metadata;
+mod pending;
 mod register;
+mod snapshot;
+mod trend;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use konsumer_offsets::KonsumerOffsetsData;
-use tokio::sync::mpsc::Receiver;
+use prometheus::Registry;
+use tokio::sync::{broadcast, mpsc::Receiver};
 
-use crate::consumer_groups::ConsumerGroups;
+use crate::cluster_status::TopologyChange;
+use crate::consumer_groups::{ConsumerGroupsDiff, GroupExcludes, GroupLabels};
+use crate::external_offsets::ExternalOffset;
+use crate::internals::Clock;
 use crate::partition_offsets::PartitionOffsetsRegister;
 
-pub use register::{Lag, LagRegister};
+pub use events::LagEvent;
+pub use history::{GroupLagPoint, LagHistoryConfig, LagHistorySink};
+pub use register::{GroupWithLag, Lag, LagRegister, LagWithOwner};
+pub use snapshot::{ClusterLag, GroupTopicLag, LagSnapshot};
+pub use trend::LagTrendConfig;
 
+#[allow(clippy::too_many_arguments)]
 pub fn init(
-    cg_rx: Receiver<ConsumerGroups>,
+    cg_rx: Receiver<ConsumerGroupsDiff>,
     kod_rx: Receiver<KonsumerOffsetsData>,
+    eo_rx: Receiver<ExternalOffset>,
+    topology_rx: broadcast::Receiver<TopologyChange>,
+    excludes: GroupExcludes,
+    labels: GroupLabels,
+    absence_grace: Duration,
+    events_threshold: Duration,
     po_reg: Arc<PartitionOffsetsRegister>,
+    clock: Arc<dyn Clock>,
+    metrics: Arc<Registry>,
 ) -> LagRegister {
-    let l_reg = LagRegister::new(cg_rx, kod_rx, po_reg);
+    let l_reg = LagRegister::new(
+        cg_rx,
+        kod_rx,
+        eo_rx,
+        topology_rx,
+        excludes,
+        labels,
+        absence_grace,
+        events_threshold,
+        po_reg,
+        clock,
+        metrics,
+    );
 
     debug!("Initialized");
     l_reg
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use super::register::GroupWithLag;
+
+use crate::kafka_types::Interned;
+
+/// Consumer Groups tracked from their first committed offset, before they are listed by the Cluster.
+///
+/// Consumer Groups are listed only periodically: instead of ignoring the offsets committed
+/// by a new Consumer Group until then, it's tracked straight away, pending its description.
+/// If it's not listed within the timeout (e.g. it was deleted meanwhile), it's dropped.
+pub(super) struct PendingGroups {
+    timeout: Duration,
+    since: HashMap<Interned, Instant>,
+}
+
+impl PendingGroups {
+    pub(super) fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            since: HashMap::new(),
+        }
+    }
+
+    /// Whether any Consumer Group is currently pending its description.
+    pub(super) fn is_empty(&self) -> bool {
+        self.since.is_empty()
+    }
+
+    /// Mark the Consumer Group as pending its description, beginning its timeout.
+    pub(super) fn mark(&mut self, group: &Interned, now: Instant) {
+        debug!("Group '{group}' added, pending its description");
+        self.since.insert(group.clone(), now);
+    }
+
+    /// Mark the Consumer Group as described.
+    pub(super) fn clear(&mut self, group: &str) {
+        if let Some(since) = self.since.remove(group) {
+            debug!("Group '{group}' described, {:?} after being added", since.elapsed());
+        }
+    }
+
+    /// Drop the Consumer Groups still pending their description, once their timeout is over.
+    ///
+    /// Returns the Consumer Groups dropped.
+    pub(super) fn expire(
+        &mut self,
+        groups: &mut BTreeMap<Interned, GroupWithLag>,
+        now: Instant,
+    ) -> Vec<Interned> {
+        let mut removed = Vec::new();
+        self.since.retain(|group, since| {
+            if now.saturating_duration_since(*since) < self.timeout {
+                return true;
+            }
+
+            if groups.get(group).is_some_and(|gwl| gwl.pending_description) {
+                warn!("Group '{group}' not described within {:?}: removed", self.timeout);
+                groups.remove(group);
+                removed.push(group.clone());
+            }
+            false
+        });
+        removed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pending_group() -> GroupWithLag {
+        GroupWithLag {
+            pending_description: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn drops_groups_not_described_in_time() {
+        let timeout = Duration::from_secs(180);
+        let mut pending = PendingGroups::new(timeout);
+        let (gone, described) = (Interned::new("gone"), Interned::new("described"));
+        let mut groups =
+            BTreeMap::from([(gone.clone(), pending_group()), (described.clone(), pending_group())]);
+
+        let t0 = Instant::now();
+        pending.mark(&gone, t0);
+        pending.mark(&described, t0);
+
+        pending.expire(&mut groups, t0 + Duration::from_secs(60));
+        assert_eq!(groups.len(), 2);
+
+        pending.clear(&described);
+        groups.get_mut(&described).unwrap().pending_description = false;
+        assert_eq!(pending.expire(&mut groups, t0 + timeout), vec![gone.clone()]);
+        assert!(pending.is_empty());
+        assert!(!groups.contains_key(&gone));
+        assert!(groups.contains_key(&described));
+    }
+}
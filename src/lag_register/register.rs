@@ -1,18 +1,50 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{btree_map::Entry, BTreeMap, HashMap, HashSet},
+    fmt, mem,
     sync::Arc,
 };
 
 use chrono::{DateTime, Duration, Utc};
 use konsumer_offsets::{GroupMetadata, KonsumerOffsetsData, OffsetCommit};
 use log::Level::Trace;
-use tokio::sync::{mpsc, RwLock};
+use prometheus::{register_int_counter_vec_with_registry, IntCounterVec, Registry};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc, watch, RwLock,
+};
+use tokio::time::{interval, timeout_at, MissedTickBehavior};
+
+use super::absence::{Absence, AbsentGroups};
+use super::events::{LagEvent, LagEvents};
+use super::metadata;
+use super::pending::PendingGroups;
 
-use crate::constants::KOMMITTED_CONSUMER_OFFSETS_CONSUMER;
-use crate::consumer_groups::ConsumerGroups;
-use crate::internals::Awaitable;
-use crate::kafka_types::{Group, Member, TopicPartition};
+use crate::cluster_status::TopologyChange;
+use crate::consumer_groups::{ConsumerGroupsDiff, GroupExcludes, GroupLabels};
+use crate::external_offsets::{ExternalOffset, EXTERNAL_PROTOCOL_TYPE};
+use crate::internals::{Awaitable, Changes, ChangesTracker, Clock, Persistent, PersistentFuture};
+use crate::kafka_types::{Group, GroupProtocol, Interned, Member, TopicPartition};
+use crate::konsumer_offsets_data::is_next_gen_group_metadata;
 use crate::partition_offsets::PartitionOffsetsRegister;
+use crate::prometheus_metrics::{LABEL_GROUP, LABEL_TOPIC};
+
+const MET_RESETS_NAME: &str = "offset_reset_events_total";
+const MET_RESETS_HELP: &str =
+    "Committed offsets of consumer groups that moved backwards (e.g. manual reset, 'auto.offset.reset')";
+
+/// How often the grace period of absent Consumer Groups is checked.
+const ABSENCE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Max amount of [`KonsumerOffsetsData`] applied under a single acquisition of the write lock.
+const KOD_BATCH_MAX_SIZE: usize = 1_000;
+
+/// Max time spent gathering a batch of [`KonsumerOffsetsData`], after receiving the first.
+const KOD_BATCH_MAX_WAIT: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// How long a Consumer Group, added from its committed offsets, can wait to be described:
+/// a few times the interval Consumer Groups are listed at.
+const PENDING_DESCRIPTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(180);
 
 /// Describes the "lag" (or "latency"), and it's usually paired with a Consumer [`GroupWithMembers`].
 ///
@@ -33,6 +65,28 @@ pub struct Lag {
     pub(crate) time_lag: Duration,
 }
 
+impl Lag {
+    /// Offset that the Consumer Group is at.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// [`DateTime<Utc>`] that the offset was consumed (i.e. committed) by the Consumer Group.
+    pub fn offset_timestamp(&self) -> DateTime<Utc> {
+        self.offset_timestamp
+    }
+
+    /// Offset Lag of the Consumer Group.
+    pub fn offset_lag(&self) -> u64 {
+        self.offset_lag
+    }
+
+    /// Estimated Time Lag of the Consumer Group.
+    pub fn time_lag(&self) -> Duration {
+        self.time_lag
+    }
+}
+
 impl Default for Lag {
     fn default() -> Self {
         Lag {
@@ -44,61 +98,182 @@ impl Default for Lag {
     }
 }
 
+/// A [`Lag`] paired with the [`Member`] that owns the [`TopicPartition`] it refers to.
+///
+/// Either can be missing, while the information is still being collected.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct LagWithOwner {
     pub(crate) lag: Option<Lag>,
     pub(crate) owner: Option<Member>,
 }
 
+impl LagWithOwner {
+    pub fn lag(&self) -> Option<&Lag> {
+        self.lag.as_ref()
+    }
+
+    pub fn owner(&self) -> Option<&Member> {
+        self.owner.as_ref()
+    }
+}
+
 /// Describes the "lag" (or "latency") of a specific Consumer [`GroupWithMembers`] in respect to a collection of [`TopicPartition`] that it consumes.
 #[derive(Debug, Clone, Default)]
 pub struct GroupWithLag {
     pub(crate) group: Group,
     // TODO https://github.com/kafkesc/kommitted/issues/58
-    pub(crate) lag_by_topic_partition: HashMap<TopicPartition, LagWithOwner>,
+    pub(crate) lag_by_topic_partition: BTreeMap<TopicPartition, LagWithOwner>,
+
+    /// Added from its committed offsets, the Consumer Group was not listed by the Cluster yet:
+    /// its [`Group`] only carries the name and labels.
+    pub(crate) pending_description: bool,
+
+    /// Digest of the latest [`GroupMetadata`] applied, to skip unchanged ones (see [`metadata::digest`]).
+    pub(crate) metadata_digest: Option<u64>,
+}
+
+impl GroupWithLag {
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+
+    pub fn is_pending_description(&self) -> bool {
+        self.pending_description
+    }
+
+    pub fn lag_by_topic_partition(&self) -> &BTreeMap<TopicPartition, LagWithOwner> {
+        &self.lag_by_topic_partition
+    }
 }
 
-#[derive(Debug)]
 pub struct LagRegister {
-    pub(crate) lag_by_group: Arc<RwLock<HashMap<String, GroupWithLag>>>,
+    pub(crate) lag_by_group: Arc<RwLock<BTreeMap<Interned, GroupWithLag>>>,
+    po_reg: Arc<PartitionOffsetsRegister>,
+    changes: Arc<watch::Sender<()>>,
+    changed_groups: Arc<ChangesTracker<Interned>>,
+    events: broadcast::Sender<LagEvent>,
+}
+
+impl fmt::Debug for LagRegister {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LagRegister")
+            .field("lag_by_group", &self.lag_by_group)
+            .finish_non_exhaustive()
+    }
 }
 
 impl LagRegister {
-    pub fn new(
-        mut cg_rx: mpsc::Receiver<ConsumerGroups>,
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        mut cg_rx: mpsc::Receiver<ConsumerGroupsDiff>,
         mut kod_rx: mpsc::Receiver<KonsumerOffsetsData>,
+        mut eo_rx: mpsc::Receiver<ExternalOffset>,
+        mut topology_rx: broadcast::Receiver<TopologyChange>,
+        excludes: GroupExcludes,
+        labels: GroupLabels,
+        absence_grace: std::time::Duration,
+        events_threshold: std::time::Duration,
         po_reg: Arc<PartitionOffsetsRegister>,
+        clock: Arc<dyn Clock>,
+        metrics: Arc<Registry>,
     ) -> Self {
+        let metric_resets = register_int_counter_vec_with_registry!(
+            MET_RESETS_NAME,
+            MET_RESETS_HELP,
+            &[LABEL_GROUP, LABEL_TOPIC],
+            metrics
+        )
+        .unwrap_or_else(|_| panic!("Failed to create metric: {MET_RESETS_NAME}"));
+        let mut absent = AbsentGroups::new(absence_grace, metrics.clone());
+        let mut pending = PendingGroups::new(PENDING_DESCRIPTION_TIMEOUT);
+        let events = LagEvents::new(events_threshold, metrics.clone());
+
         let lr = LagRegister {
-            lag_by_group: Arc::new(RwLock::new(HashMap::default())),
+            lag_by_group: Arc::new(RwLock::new(BTreeMap::default())),
+            po_reg: po_reg.clone(),
+            changes: Arc::new(watch::Sender::new(())),
+            changed_groups: Arc::new(ChangesTracker::default()),
+            events: events.sender(),
         };
 
         let lag_by_group_clone = lr.lag_by_group.clone();
+        let changes = lr.changes.clone();
+        let changed_groups = lr.changed_groups.clone();
 
         tokio::spawn(async move {
+            let mut topology_open = true;
+            let mut absence_check = interval(ABSENCE_CHECK_INTERVAL);
+            absence_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
             loop {
                 tokio::select! {
-                    Some(cg) = cg_rx.recv() => {
-                        trace!("Processing {} reporting {} Groups", std::any::type_name::<ConsumerGroups>(), cg.groups.len());
-                        process_consumer_groups(cg, lag_by_group_clone.clone()).await;
+                    Some(cgd) = cg_rx.recv() => {
+                        trace!("Processing {} reporting {} Groups", std::any::type_name::<ConsumerGroupsDiff>(), cgd.len());
+                        let touched = cgd.added.keys().chain(cgd.changed.keys()).chain(cgd.removed.iter()).cloned().collect::<Vec<Interned>>();
+                        process_consumer_groups_diff(cgd, &labels, &mut absent, &mut pending, &events, clock.as_ref(), lag_by_group_clone.clone()).await;
+                        changed_groups.mark(touched);
+                    },
+                    _ = absence_check.tick(), if !absent.is_empty() || !pending.is_empty() => {
+                        let mut w_guard = lag_by_group_clone.write().await;
+                        let now = clock.instant();
+                        let mut removed = absent.expire(&mut w_guard, now);
+                        removed.extend(pending.expire(&mut w_guard, now));
+                        removed.iter().for_each(|g| events.group_disappeared(g));
+                        changed_groups.mark(removed);
                     },
+                    // Excluded Groups are already dropped by the emitters of committed offsets
                     Some(kod) = kod_rx.recv() => {
-                        match kod {
-                            KonsumerOffsetsData::OffsetCommit(oc) => {
-                                trace!("Processing {} of Group '{}' for Topic Partition '{}:{}'", std::any::type_name::<OffsetCommit>(), oc.group, oc.topic, oc.partition);
-                                process_offset_commit(oc, lag_by_group_clone.clone(), po_reg.clone()).await;
-                            },
-                            KonsumerOffsetsData::GroupMetadata(gm) => {
-                                debug!("Processing {} of Group '{}' with {} Members", std::any::type_name::<GroupMetadata>(), gm.group, gm.members.len());
-                                process_group_metadata(gm, lag_by_group_clone.clone()).await;
+                        let batch = recv_batch(&mut kod_rx, kod).await;
+                        trace!("Processing batch of {} {}", batch.len(), std::any::type_name::<KonsumerOffsetsData>());
+
+                        // Under heavy commit rates, apply them all with a single acquisition of the write lock
+                        let mut w_guard = lag_by_group_clone.write().await;
+                        let mut touched = HashSet::new();
+                        for kod in batch {
+                            match kod {
+                                KonsumerOffsetsData::OffsetCommit(oc) => {
+                                    touched.insert(Interned::from(oc.group.as_str()));
+                                    trace!("Processing {} of Group '{}' for Topic Partition '{}:{}'", std::any::type_name::<OffsetCommit>(), oc.group, oc.topic, oc.partition);
+                                    process_offset_commit(oc, &labels, &mut pending, &events, clock.as_ref(), &mut w_guard, &po_reg, &metric_resets).await;
+                                },
+                                KonsumerOffsetsData::GroupMetadata(gm) => {
+                                    debug!("Processing {} of Group '{}' with {} Members", std::any::type_name::<GroupMetadata>(), gm.group, gm.members.len());
+                                    touched.insert(Interned::from(gm.group.as_str()));
+                                    process_group_metadata(gm, &labels, &events, &mut w_guard);
+                                }
                             }
                         }
+                        drop(w_guard);
+                        changed_groups.mark(touched);
+                    },
+                    Some(eo) = eo_rx.recv() => {
+                        if excludes.is_excluded(&eo.group) {
+                            continue;
+                        }
+
+                        trace!("Processing {} of Group '{}' for Topic Partition '{}:{}'", std::any::type_name::<ExternalOffset>(), eo.group, eo.topic, eo.partition);
+                        let touched = Interned::from(eo.group.as_str());
+                        process_external_offset(eo, &labels, &events, clock.as_ref(), lag_by_group_clone.clone(), po_reg.clone(), &metric_resets).await;
+                        changed_groups.mark([touched]);
+                    },
+                    res = topology_rx.recv(), if topology_open => match res {
+                        Ok(change) => {
+                            debug!("Processing {} removing {} Partitions", std::any::type_name::<TopologyChange>(), change.removed_partitions.len());
+                            process_topology_change(change, lag_by_group_clone.clone()).await;
+                            changed_groups.mark_all();
+                        },
+                        Err(RecvError::Lagged(n)) => {
+                            warn!("Missed {n} topology changes: Lag of removed Partitions might be reported until restart");
+                        },
+                        Err(RecvError::Closed) => {
+                            topology_open = false;
+                        },
                     },
                     else => {
                         info!("Emitters stopping: breaking (internal) loop");
                         break;
                     }
                 }
+                changes.send_replace(());
 
                 if log_enabled!(Trace) {
                     let r_guard = lag_by_group_clone.read().await;
@@ -120,25 +295,154 @@ impl LagRegister {
 
         lr
     }
+
+    /// Subscribe to changes of [`Self`]: a notification is sent every time the Consumer Groups,
+    /// their Members or their committed offsets are updated.
+    ///
+    /// Notifications are coalesced: only the fact that something changed since the last
+    /// notification was seen is reported.
+    pub fn subscribe_changes(&self) -> watch::Receiver<()> {
+        self.changes.subscribe()
+    }
+
+    /// Take the Consumer Groups changed since last taken: those whose [`GroupWithLag`] was
+    /// added, updated or removed. All of them, the first time.
+    ///
+    /// Meant for a single reader, keeping a copy of the lag up to date (see [`super::LagSnapshot::update`]).
+    pub fn take_changed_groups(&self) -> Changes<Interned> {
+        self.changed_groups.take()
+    }
+
+    /// Subscribe to the [`LagEvent`]s, as they happen from now on.
+    ///
+    /// Subscribers that fall behind miss the oldest events (see [`broadcast::Receiver::recv`]).
+    pub fn subscribe_events(&self) -> broadcast::Receiver<LagEvent> {
+        self.events.subscribe()
+    }
+
+    /// Names of the Consumer Groups currently tracked.
+    pub async fn get_groups(&self) -> Vec<Interned> {
+        self.lag_by_group.read().await.keys().cloned().collect()
+    }
+
+    /// A copy of the [`GroupWithLag`] of a specific Consumer Group, if tracked.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - Name of the Consumer Group
+    pub async fn get_group_with_lag(&self, group: &str) -> Option<GroupWithLag> {
+        self.lag_by_group.read().await.get(group).cloned()
+    }
+
+    /// A copy of all the [`GroupWithLag`] currently tracked, indexed by Consumer Group name.
+    pub async fn get_groups_with_lag(&self) -> BTreeMap<Interned, GroupWithLag> {
+        self.lag_by_group.read().await.clone()
+    }
+
+    /// A copy of the [`GroupWithLag`] of the given Consumer Groups, those tracked among them.
+    pub async fn get_groups_with_lag_among(
+        &self,
+        groups: &HashSet<Interned>,
+    ) -> BTreeMap<Interned, GroupWithLag> {
+        let r_guard = self.lag_by_group.read().await;
+        groups
+            .iter()
+            .filter_map(|g| r_guard.get_key_value(g))
+            .map(|(g, gwl)| (g.clone(), gwl.clone()))
+            .collect()
+    }
+
+    /// Approximate memory (bytes) taken by the lag of all the Consumer Groups.
+    pub async fn estimate_memory(&self) -> usize {
+        self.lag_by_group
+            .read()
+            .await
+            .values()
+            .map(|gwl| {
+                mem::size_of::<(Interned, GroupWithLag)>()
+                    + gwl.lag_by_topic_partition.len()
+                        * mem::size_of::<(TopicPartition, LagWithOwner)>()
+            })
+            .sum()
+    }
 }
 
-async fn process_consumer_groups(
-    cg: ConsumerGroups,
-    lag_register_groups: Arc<RwLock<HashMap<String, GroupWithLag>>>,
+async fn process_topology_change(
+    change: TopologyChange,
+    lag_register_groups: Arc<RwLock<BTreeMap<Interned, GroupWithLag>>>,
 ) {
-    for (group_name, group_with_members) in cg.groups.into_iter() {
-        // Ignore own consumer of `__consumer_offsets` topic.
-        if group_name == KOMMITTED_CONSUMER_OFFSETS_CONSUMER {
-            continue;
+    if change.removed_partitions.is_empty() {
+        return;
+    }
+
+    let mut w_guard = lag_register_groups.write().await;
+    for gwl in w_guard.values_mut() {
+        for tp in change.removed_partitions.iter() {
+            gwl.lag_by_topic_partition.remove(tp);
         }
+    }
+}
 
+async fn process_consumer_groups_diff(
+    cgd: ConsumerGroupsDiff,
+    labels: &GroupLabels,
+    absent: &mut AbsentGroups,
+    pending: &mut PendingGroups,
+    events: &LagEvents,
+    clock: &dyn Clock,
+    lag_register_groups: Arc<RwLock<BTreeMap<Interned, GroupWithLag>>>,
+) {
+    // Forget removed Groups, or keep them until the end of their grace period
+    if !cgd.removed.is_empty() {
         let mut w_guard = lag_register_groups.write().await;
+        for group_name in cgd.removed.iter() {
+            if absent.is_enabled() {
+                if w_guard.contains_key(group_name) {
+                    absent.mark(group_name, Absence::Vanished, clock.instant());
+                }
+            } else if w_guard.remove(group_name).is_some() {
+                debug!("Group '{}' removed", group_name);
+                events.group_disappeared(group_name);
+            }
+        }
+    }
+
+    // Only added or changed Groups need updating: the others are left untouched
+    for (group_name, mut group_with_members) in cgd.added.into_iter().chain(cgd.changed) {
+        group_with_members.group.labels =
+            labels.labels_for(&group_name, &group_with_members.group.protocol_type);
+        pending.clear(&group_name);
+
+        let mut w_guard = lag_register_groups.write().await;
+
+        // Groups using the next-gen consumer rebalance protocol are listed with no Members:
+        // which Member owns which TopicPartition is only known from `__consumer_offsets`
+        if group_with_members.group.group_protocol == GroupProtocol::Consumer {
+            absent.clear(&group_name);
+            let group = group_with_members.group;
+            match w_guard.entry(group_name) {
+                Entry::Occupied(mut e) => {
+                    let gwl = e.get_mut();
+                    gwl.group = group;
+                    gwl.pending_description = false;
+                    gwl.metadata_digest = None;
+                },
+                Entry::Vacant(e) => {
+                    events.group_appeared(e.key());
+                    e.insert(GroupWithLag {
+                        group,
+                        ..Default::default()
+                    });
+                },
+            }
+            continue;
+        }
 
         // Organise all the Group Members by the TopicPartition they own
         let members_by_topic_partition = group_with_members
             .members
-            .into_iter()
-            .flat_map(|(_, mwa)| {
+            .into_values()
+            .flat_map(|mwa| {
                 mwa.assignment
                     .into_iter()
                     .map(|tp| (tp, mwa.member.clone()))
@@ -146,8 +450,30 @@ async fn process_consumer_groups(
             })
             .collect::<HashMap<TopicPartition, Member>>();
 
+        // A Group whose Members all left keeps its Lag until the end of its grace period,
+        // but no Topic Partition is owned anymore
+        if members_by_topic_partition.is_empty() && absent.is_enabled() {
+            if let Some(gwl) = w_guard.get_mut(&group_name) {
+                if !gwl.lag_by_topic_partition.is_empty() {
+                    absent.mark(&group_name, Absence::Empty, clock.instant());
+                    gwl.group = group_with_members.group;
+                    gwl.pending_description = false;
+                    gwl.metadata_digest = None;
+                    for (tp, lwo) in gwl.lag_by_topic_partition.iter_mut() {
+                        events.owner_changed(&group_name, tp, lwo.owner.take().as_ref(), None);
+                    }
+                    continue;
+                }
+            }
+        }
+        absent.clear(&group_name);
+
         // Insert or update "group name -> group with lag" map entries
         if let Entry::Vacant(e) = w_guard.entry(group_name.clone()) {
+            events.group_appeared(&group_name);
+            for (tp, m) in members_by_topic_partition.iter() {
+                events.owner_changed(&group_name, tp, None, Some(m));
+            }
             e.insert(GroupWithLag {
                 group: group_with_members.group,
                 // Given this is a new Group,
@@ -163,6 +489,7 @@ async fn process_consumer_groups(
                         )
                     })
                     .collect(),
+                ..Default::default()
             });
         } else {
             let gwl = w_guard.get_mut(&group_name).unwrap_or_else(|| {
@@ -173,19 +500,49 @@ async fn process_consumer_groups(
                 )
             });
 
-            // Set the Group (probably unchanged)
+            // Set the Group (probably unchanged, unless it was pending its description)
             gwl.group = group_with_members.group;
+            gwl.pending_description = false;
+
+            // Owners are about to be set from the listing: the next GroupMetadata must be applied
+            gwl.metadata_digest = None;
 
             // Remove from map of LagWithOwner the entries with key TopicPartition not owner by any member of this group
-            gwl.lag_by_topic_partition.retain(|tp, _| members_by_topic_partition.contains_key(tp));
+            gwl.lag_by_topic_partition.retain(|tp, lwo| {
+                let owned = members_by_topic_partition.contains_key(tp);
+                if !owned {
+                    events.owner_changed(&group_name, tp, lwo.owner.as_ref(), None);
+                }
+                owned
+            });
 
             // Create or Update a entries `TopicPartition -> LagWithOwner`:
             // either update the owner Member of an existing one,
             // or create a new entry with no Lag set.
             for (tp, m) in members_by_topic_partition.into_iter() {
+                let prev_owner =
+                    gwl.lag_by_topic_partition.get(&tp).and_then(|lwo| lwo.owner.as_ref());
+                events.owner_changed(&group_name, &tp, prev_owner, Some(&m));
+
                 gwl.lag_by_topic_partition
                     .entry(tp)
-                    .and_modify(|lwo| lwo.owner = Some(m.clone()))
+                    .and_modify(|lwo| {
+                        // Listing Consumer Groups doesn't report the `group.instance.id`:
+                        // keep the one already known for the same Member, if any.
+                        // A static Member gets a new (ephemeral) `id` when it restarts,
+                        // so it's recognised by its `client.id` and host instead.
+                        let group_instance_id = lwo
+                            .owner
+                            .as_ref()
+                            .filter(|o| {
+                                o.client_id == m.client_id && o.client_host == m.client_host
+                            })
+                            .and_then(|o| o.group_instance_id.clone());
+                        lwo.owner = Some(Member {
+                            group_instance_id,
+                            ..m.clone()
+                        });
+                    })
                     .or_insert_with(|| LagWithOwner {
                         owner: Some(m),
                         ..Default::default()
@@ -195,81 +552,264 @@ async fn process_consumer_groups(
     }
 }
 
+/// Receive a batch of [`KonsumerOffsetsData`], beginning with the one already received.
+///
+/// The batch is over once [`KOD_BATCH_MAX_SIZE`] is reached, or [`KOD_BATCH_MAX_WAIT`] is elapsed:
+/// whatever is already in the channel is received straight away.
+async fn recv_batch(
+    kod_rx: &mut mpsc::Receiver<KonsumerOffsetsData>,
+    first: KonsumerOffsetsData,
+) -> Vec<KonsumerOffsetsData> {
+    let deadline = tokio::time::Instant::now() + KOD_BATCH_MAX_WAIT;
+
+    let mut batch = Vec::with_capacity(KOD_BATCH_MAX_SIZE.min(kod_rx.len() + 1));
+    batch.push(first);
+    while batch.len() < KOD_BATCH_MAX_SIZE {
+        match timeout_at(deadline, kod_rx.recv()).await {
+            Ok(Some(kod)) => batch.push(kod),
+            Ok(None) | Err(_) => break,
+        }
+    }
+    batch
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn process_offset_commit(
     oc: OffsetCommit,
-    lag_register_groups: Arc<RwLock<HashMap<String, GroupWithLag>>>,
-    po_reg: Arc<PartitionOffsetsRegister>,
+    labels: &GroupLabels,
+    pending: &mut PendingGroups,
+    events: &LagEvents,
+    clock: &dyn Clock,
+    w_guard: &mut BTreeMap<Interned, GroupWithLag>,
+    po_reg: &PartitionOffsetsRegister,
+    metric_resets: &IntCounterVec,
 ) {
-    // Ignore own consumer of `__consumer_offsets` topic.
-    if oc.group == KOMMITTED_CONSUMER_OFFSETS_CONSUMER {
+    // A tombstone means the committed offset expired, or was deleted:
+    // forget the Lag, and the whole entry if no Member owns the Topic Partition either.
+    if oc.is_tombstone {
+        if let Some(gwl) = w_guard.get_mut(oc.group.as_str()) {
+            let tp = TopicPartition::new(oc.topic, oc.partition as u32);
+            if let Entry::Occupied(mut e) = gwl.lag_by_topic_partition.entry(tp) {
+                debug!("Committed offset of Group '{}' for '{}' expired", oc.group, e.key());
+                if e.get().owner.is_some() {
+                    e.get_mut().lag = None;
+                } else {
+                    e.remove();
+                }
+            }
+        }
         return;
     }
 
+    // A recent commit of an unknown Group is most likely from a new Group, not listed yet:
+    // track it straight away, pending its description, instead of waiting for the next listing.
+    // Older commits are (likely) of long gone Groups, read when consuming from the earliest offset.
+    if !w_guard.contains_key(oc.group.as_str()) {
+        if !is_recent_commit(oc.commit_timestamp, clock.now()) {
+            trace!(
+                "Received {} about unknown Group '{}' from {}: ignoring",
+                std::any::type_name::<OffsetCommit>(),
+                oc.group,
+                oc.commit_timestamp
+            );
+            return;
+        }
+
+        let name = Interned::from(oc.group.as_str());
+        pending.mark(&name, clock.instant());
+        events.group_appeared(&name);
+        w_guard.insert(
+            name.clone(),
+            GroupWithLag {
+                group: Group {
+                    labels: labels.labels_for(&name, ""),
+                    name: name.clone(),
+                    ..Default::default()
+                },
+                pending_description: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    if let Some(gwl) = w_guard.get_mut(oc.group.as_str()) {
+        let tp = TopicPartition::new(oc.topic, oc.partition as u32);
+        record_committed_offset(
+            gwl,
+            tp,
+            oc.offset as u64,
+            oc.commit_timestamp,
+            po_reg,
+            events,
+            metric_resets,
+        )
+        .await;
+    }
+}
+
+/// Whether an offset committed at the given time is recent enough to be of a Group
+/// that is just not listed yet, rather than of a long gone one.
+fn is_recent_commit(committed_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    let pending_timeout = Duration::from_std(PENDING_DESCRIPTION_TIMEOUT).unwrap_or_default();
+    now - committed_at <= pending_timeout
+}
+
+async fn process_external_offset(
+    eo: ExternalOffset,
+    labels: &GroupLabels,
+    events: &LagEvents,
+    clock: &dyn Clock,
+    lag_register_groups: Arc<RwLock<BTreeMap<Interned, GroupWithLag>>>,
+    po_reg: Arc<PartitionOffsetsRegister>,
+    metric_resets: &IntCounterVec,
+) {
+    let committed_at = eo
+        .committed_at_ms
+        .and_then(DateTime::<Utc>::from_timestamp_millis)
+        .unwrap_or_else(|| clock.now());
+
+    // Consumers committing outside of Kafka are not listed as Consumer Groups:
+    // they are tracked from their first offset on, and never removed
     let mut w_guard = lag_register_groups.write().await;
+    let gwl = w_guard.entry(eo.group.clone()).or_insert_with(|| {
+        debug!("Group '{}' added, from its external offsets", eo.group);
+        events.group_appeared(&eo.group);
+        GroupWithLag {
+            group: Group {
+                name: eo.group.clone(),
+                protocol_type: EXTERNAL_PROTOCOL_TYPE.to_string(),
+                labels: labels.labels_for(&eo.group, EXTERNAL_PROTOCOL_TYPE),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    });
 
-    match w_guard.get_mut(&oc.group) {
-        Some(gwl) => {
-            let tp = TopicPartition::new(oc.topic, oc.partition as u32);
+    let tp = TopicPartition::new(eo.topic, eo.partition);
+    record_committed_offset(gwl, tp, eo.offset, committed_at, &po_reg, events, metric_resets).await;
+}
 
-            // Prepare all the Lag fields
-            let l = Lag {
-                offset: oc.offset as u64,
-                offset_timestamp: oc.commit_timestamp,
-                offset_lag: po_reg.estimate_offset_lag(&tp, oc.offset as u64)
-                    .await
-                    .unwrap_or_else(|e| {
-                    debug!(
-                            "Failed to estimate Offset Lag of Group '{}' for Topic Partition '{}': {}",
-                            oc.group, tp, e
-                        );
-                    0
-                }),
-                time_lag: po_reg
-                    .estimate_time_lag(&tp, oc.offset as u64, oc.commit_timestamp)
-                    .await
-                    .unwrap_or_else(|e| {
-                    debug!(
-                            "Failed to estimate Time Lag of Group '{}' for Topic Partition '{}': {}",
-                            oc.group, tp, e
-                        );
-                    Duration::zero()
-                }),
-            };
-
-            // Create or update entry `TopicPartition -> LagWithOwner`:
-            // either update the Lag of an existing one,
-            // or create a new entry with no owner set.
-            gwl.lag_by_topic_partition
-                .entry(tp)
-                .and_modify(|lwo| lwo.lag = Some(l.clone()))
-                .or_insert_with(|| LagWithOwner {
-                    lag: Some(l),
-                    owner: None,
-                });
-        },
-        None => {
-            warn!(
-                "Received {} about unknown Group '{}': ignoring",
-                std::any::type_name::<OffsetCommit>(),
-                oc.group
+/// Record the offset committed by a Consumer Group for a [`TopicPartition`], estimating its [`Lag`].
+async fn record_committed_offset(
+    gwl: &mut GroupWithLag,
+    tp: TopicPartition,
+    offset: u64,
+    committed_at: DateTime<Utc>,
+    po_reg: &PartitionOffsetsRegister,
+    events: &LagEvents,
+    metric_resets: &IntCounterVec,
+) {
+    // A committed offset moving backwards means the Group was reset
+    // (e.g. manually, or via `auto.offset.reset` after falling behind retention)
+    let prev_offset =
+        gwl.lag_by_topic_partition.get(&tp).and_then(|lwo| lwo.lag()).map(Lag::offset);
+    if let Some(prev_offset) = prev_offset.filter(|po| *po > offset) {
+        info!(
+            "Committed offset of Group '{}' for '{}' reset: {} -> {}",
+            gwl.group.name, tp, prev_offset, offset
+        );
+        metric_resets.with_label_values(&[&gwl.group.name, &tp.topic]).inc();
+    }
+
+    // Prepare all the Lag fields
+    let l = estimate_lag(po_reg, &gwl.group.name, &tp, offset, committed_at).await;
+    let prev_lag = gwl.lag_by_topic_partition.get(&tp).and_then(|lwo| lwo.lag());
+    events.lag_changed(&gwl.group.name, &tp, prev_lag, &l);
+
+    // Create or update entry `TopicPartition -> LagWithOwner`:
+    // either update the Lag of an existing one,
+    // or create a new entry with no owner set.
+    gwl.lag_by_topic_partition
+        .entry(tp)
+        .and_modify(|lwo| lwo.lag = Some(l.clone()))
+        .or_insert_with(|| LagWithOwner {
+            lag: Some(l),
+            owner: None,
+        });
+}
+
+/// Estimate the [`Lag`] of a Consumer Group, given the offset it committed for a [`TopicPartition`].
+async fn estimate_lag(
+    po_reg: &PartitionOffsetsRegister,
+    group: &str,
+    tp: &TopicPartition,
+    offset: u64,
+    offset_timestamp: DateTime<Utc>,
+) -> Lag {
+    Lag {
+        offset,
+        offset_timestamp,
+        offset_lag: po_reg.estimate_offset_lag(tp, offset).await.unwrap_or_else(|e| {
+            debug!(
+                "Failed to estimate Offset Lag of Group '{}' for Topic Partition '{}': {}",
+                group, tp, e
             );
-        },
+            0
+        }),
+        time_lag: po_reg.estimate_time_lag(tp, offset, offset_timestamp).await.unwrap_or_else(
+            |e| {
+                debug!(
+                    "Failed to estimate Time Lag of Group '{}' for Topic Partition '{}': {}",
+                    group, tp, e
+                );
+                Duration::zero()
+            },
+        ),
     }
 }
 
-async fn process_group_metadata(
+fn process_group_metadata(
     gm: GroupMetadata,
-    lag_register_groups: Arc<RwLock<HashMap<String, GroupWithLag>>>,
+    labels: &GroupLabels,
+    events: &LagEvents,
+    w_guard: &mut BTreeMap<Interned, GroupWithLag>,
 ) {
-    // Ignore own consumer of `__consumer_offsets` topic.
-    if gm.group == KOMMITTED_CONSUMER_OFFSETS_CONSUMER {
+    // Skip the GroupMetadata identical to the latest applied
+    let digest = (!gm.is_tombstone).then(|| metadata::digest(&gm));
+    if digest.is_some()
+        && w_guard.get(gm.group.as_str()).is_some_and(|gwl| gwl.metadata_digest == digest)
+    {
+        trace!(
+            "Unchanged {} of Group '{}': skipping",
+            std::any::type_name::<GroupMetadata>(),
+            gm.group
+        );
         return;
     }
 
-    let mut w_guard = lag_register_groups.write().await;
+    // A tombstone means the Group was deleted
+    if gm.is_tombstone {
+        if let Some((name, _)) = w_guard.remove_entry(gm.group.as_str()) {
+            debug!("Group '{}' deleted", gm.group);
+            events.group_disappeared(&name);
+        }
+        return;
+    }
+
+    // Groups using the next-gen consumer rebalance protocol might not be listed as such (yet):
+    // this is the only source of their Members, so track them straight away
+    let next_gen = is_next_gen_group_metadata(&gm);
+    if next_gen {
+        let name = Interned::new(&gm.group);
+        let gwl = w_guard.entry(name.clone()).or_insert_with(|| {
+            events.group_appeared(&name);
+            GroupWithLag {
+                group: Group {
+                    name,
+                    protocol_type: gm.protocol_type.clone(),
+                    labels: labels.labels_for(&gm.group, &gm.protocol_type),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        });
+        gwl.group.group_protocol = GroupProtocol::Consumer;
+    }
 
-    match w_guard.get_mut(&gm.group) {
+    match w_guard.get_mut(gm.group.as_str()) {
         Some(gwl) => {
+            gwl.metadata_digest = digest;
+
             // New map of Topic Partition->Member (owner), that the Group is consuming
             let new_tp_to_owner = gm
                 .members
@@ -279,6 +819,7 @@ async fn process_group_metadata(
                         id: m.id,
                         client_id: m.client_id,
                         client_host: m.client_host,
+                        group_instance_id: Some(m.group_instance_id).filter(|gii| !gii.is_empty()),
                     };
 
                     // Collect all Group Coordinator Assigned Topic Partitions
@@ -310,12 +851,36 @@ async fn process_group_metadata(
             //
             // NOTE: The new ones that are NOT YET in the map, will be added when an
             // OffsetCommit for this Group and this Topic-Partition is received and Lag calculated.
-            gwl.lag_by_topic_partition.retain(|tp, _| new_tp_to_owner.contains_key(tp));
+            gwl.lag_by_topic_partition.retain(|tp, lwo| {
+                let owned = new_tp_to_owner.contains_key(tp);
+                if !owned {
+                    events.owner_changed(&gwl.group.name, tp, lwo.owner.as_ref(), None);
+                }
+                owned
+            });
 
-            // For all the Topic-Partition in the GroupMetadata, set the Member that owns it
+            // For all the Topic-Partition in the GroupMetadata, set the Member that owns it.
+            //
+            // Next-gen Groups have no other source of owners, so their new ones are added too.
             for (tp, owner) in new_tp_to_owner.into_iter() {
-                if let Some(lwo) = gwl.lag_by_topic_partition.get_mut(&tp) {
-                    lwo.owner = Some(owner)
+                match gwl.lag_by_topic_partition.entry(tp) {
+                    Entry::Occupied(mut e) => {
+                        events.owner_changed(
+                            &gwl.group.name,
+                            e.key(),
+                            e.get().owner.as_ref(),
+                            Some(&owner),
+                        );
+                        e.get_mut().owner = Some(owner);
+                    },
+                    Entry::Vacant(e) if next_gen => {
+                        events.owner_changed(&gwl.group.name, e.key(), None, Some(&owner));
+                        e.insert(LagWithOwner {
+                            owner: Some(owner),
+                            ..Default::default()
+                        });
+                    },
+                    Entry::Vacant(_) => {},
                 }
             }
         },
@@ -329,9 +894,608 @@ async fn process_group_metadata(
     }
 }
 
+/// Offset committed by a Consumer Group for a Topic Partition, as persisted by [`LagRegister`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommittedOffset {
+    pub group: Interned,
+    pub topic: Interned,
+    pub partition: u32,
+    pub offset: u64,
+
+    /// UTC timestamp in milliseconds, of when the offset was committed.
+    pub committed_at_ms: i64,
+}
+
+impl Persistent for LagRegister {
+    type State = Vec<CommittedOffset>;
+
+    fn state(&self) -> PersistentFuture<'_, Self::State> {
+        Box::pin(async move {
+            self.lag_by_group
+                .read()
+                .await
+                .iter()
+                .flat_map(|(g, gwl)| {
+                    gwl.lag_by_topic_partition.iter().filter_map(move |(tp, lwo)| {
+                        lwo.lag.as_ref().map(|l| CommittedOffset {
+                            group: g.clone(),
+                            topic: tp.topic.clone(),
+                            partition: tp.partition,
+                            offset: l.offset,
+                            committed_at_ms: l.offset_timestamp.timestamp_millis(),
+                        })
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Lag is estimated again for each restored [`CommittedOffset`], against the current
+    /// Topic Partitions offsets.
+    ///
+    /// Only Consumer Groups already tracked are restored, and only for the Topic Partitions
+    /// that have no [`Lag`] yet: anything else is either gone, or more recent than what is restored.
+    fn restore(&self, state: Self::State) -> PersistentFuture<'_, ()> {
+        Box::pin(async move {
+            let mut w_guard = self.lag_by_group.write().await;
+
+            let mut restored = 0;
+            for co in state {
+                let Some(gwl) = w_guard.get_mut(&co.group) else {
+                    continue;
+                };
+                let Some(committed_at) = DateTime::<Utc>::from_timestamp_millis(co.committed_at_ms)
+                else {
+                    continue;
+                };
+
+                let tp = TopicPartition::new(co.topic, co.partition);
+                let lwo = gwl.lag_by_topic_partition.entry(tp.clone()).or_default();
+                if lwo.lag.is_none() {
+                    lwo.lag = Some(
+                        estimate_lag(&self.po_reg, &co.group, &tp, co.offset, committed_at).await,
+                    );
+                    restored += 1;
+                }
+            }
+
+            drop(w_guard);
+            info!("Restored committed offsets of {restored} group partitions");
+            self.changed_groups.mark_all();
+            self.changes.send_replace(());
+        })
+    }
+}
+
 impl Awaitable for LagRegister {
     async fn is_ready(&self) -> bool {
         // TODO https://github.com/kafkesc/kommitted/issues/59
-        self.lag_by_group.read().await.len() > 0
+        !self.lag_by_group.read().await.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use prometheus::Opts;
+    use tokio::sync::broadcast;
+
+    use crate::cluster_status::ClusterStatusRegister;
+    use crate::internals::{persist_to, restore_from, ManualClock};
+    use crate::kafka_types::{GroupWithMembers, MemberWithAssignment};
+    use crate::lag_register::LagSnapshot;
+    use crate::partition_offsets::{HistoryRetention, LinearInterpolation};
+
+    use super::*;
+
+    /// A [`PartitionOffsetsRegister`] tracking no Topic Partition: every Lag is estimated as zero.
+    fn empty_po_reg(metrics: Arc<Registry>) -> PartitionOffsetsRegister {
+        let (_, cs_rx) = mpsc::channel(1);
+        let (_, po_rx) = mpsc::channel(1);
+        let (_, topology_rx) = broadcast::channel(1);
+
+        PartitionOffsetsRegister::new(
+            po_rx,
+            topology_rx,
+            10,
+            HistoryRetention::default(),
+            Arc::new(LinearInterpolation),
+            std::time::Duration::from_secs(60),
+            1_f64,
+            Arc::new(ClusterStatusRegister::new(None, cs_rx, metrics.clone())),
+            metrics,
+        )
+    }
+
+    /// A running [`LagRegister`], fed via the returned channels, estimating every Lag as zero.
+    fn lag_reg(
+        clock: Arc<ManualClock>,
+        metrics: Arc<Registry>,
+    ) -> (LagRegister, mpsc::Sender<ConsumerGroupsDiff>, mpsc::Sender<KonsumerOffsetsData>) {
+        let (cg_sx, cg_rx) = mpsc::channel(1);
+        let (kod_sx, kod_rx) = mpsc::channel(1);
+        let (_, eo_rx) = mpsc::channel(1);
+        let (_, topology_rx) = broadcast::channel(1);
+
+        let lag_reg = LagRegister::new(
+            cg_rx,
+            kod_rx,
+            eo_rx,
+            topology_rx,
+            GroupExcludes::default(),
+            GroupLabels::default(),
+            std::time::Duration::ZERO,
+            std::time::Duration::ZERO,
+            Arc::new(empty_po_reg(metrics.clone())),
+            clock,
+            metrics,
+        );
+        (lag_reg, cg_sx, kod_sx)
+    }
+
+    /// A [`ConsumerGroupsDiff`] adding the given Groups, with no Members.
+    fn groups_added(groups: &[&str]) -> ConsumerGroupsDiff {
+        ConsumerGroupsDiff {
+            added: groups
+                .iter()
+                .map(|g| {
+                    let group = GroupWithMembers {
+                        group: Group {
+                            name: (*g).into(),
+                            ..Default::default()
+                        },
+                        members: HashMap::new(),
+                    };
+                    ((*g).into(), group)
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn metric_resets() -> IntCounterVec {
+        IntCounterVec::new(Opts::new(MET_RESETS_NAME, MET_RESETS_HELP), &[LABEL_GROUP, LABEL_TOPIC])
+            .unwrap()
+    }
+
+    /// A [`ConsumerGroupsDiff`] changing Group `g`, with the given Members.
+    fn group_changed(members: Vec<MemberWithAssignment>) -> ConsumerGroupsDiff {
+        let group = GroupWithMembers {
+            group: Group {
+                name: "g".into(),
+                ..Default::default()
+            },
+            members: members.into_iter().map(|mwa| (mwa.member.id.clone(), mwa)).collect(),
+        };
+
+        ConsumerGroupsDiff {
+            changed: HashMap::from([("g".into(), group)]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn static_member_keeps_instance_id_across_restarts() {
+        let metrics = Arc::new(Registry::new());
+        let mut absent = AbsentGroups::new(std::time::Duration::ZERO, metrics.clone());
+        let mut pending = PendingGroups::new(PENDING_DESCRIPTION_TIMEOUT);
+        let events = LagEvents::new(std::time::Duration::ZERO, metrics);
+        let clock = ManualClock::default();
+        let lag_by_group = Arc::new(RwLock::new(BTreeMap::new()));
+
+        let tp = TopicPartition::new("t".to_string(), 0);
+        let member = |id: &str| MemberWithAssignment {
+            member: Member {
+                id: id.to_string(),
+                client_id: "consumer".to_string(),
+                client_host: "/10.0.0.1".to_string(),
+                group_instance_id: None,
+            },
+            assignment: HashSet::from([tp.clone()]),
+        };
+        let owner = |lag_by_group: &BTreeMap<Interned, GroupWithLag>| {
+            lag_by_group["g"].lag_by_topic_partition[&tp].owner.clone().unwrap()
+        };
+
+        // Listed, then described by its `GroupMetadata`: the `group.instance.id` is known
+        process_consumer_groups_diff(
+            group_changed(vec![member("consumer-1")]),
+            &GroupLabels::default(),
+            &mut absent,
+            &mut pending,
+            &events,
+            &clock,
+            lag_by_group.clone(),
+        )
+        .await;
+        lag_by_group
+            .write()
+            .await
+            .get_mut("g")
+            .unwrap()
+            .lag_by_topic_partition
+            .get_mut(&tp)
+            .unwrap()
+            .owner
+            .as_mut()
+            .unwrap()
+            .group_instance_id = Some("instance-1".to_string());
+
+        // Restarted: listed with a new (ephemeral) id, same `client.id` and host
+        process_consumer_groups_diff(
+            group_changed(vec![member("consumer-2")]),
+            &GroupLabels::default(),
+            &mut absent,
+            &mut pending,
+            &events,
+            &clock,
+            lag_by_group.clone(),
+        )
+        .await;
+        let restarted = owner(&*lag_by_group.read().await);
+        assert_eq!(restarted.id, "consumer-2");
+        assert_eq!(restarted.stable_id(), "instance-1");
+
+        // Replaced by a different Member: the `group.instance.id` is not inherited
+        let mut other = member("other-1");
+        other.member.client_host = "/10.0.0.2".to_string();
+        process_consumer_groups_diff(
+            group_changed(vec![other]),
+            &GroupLabels::default(),
+            &mut absent,
+            &mut pending,
+            &events,
+            &clock,
+            lag_by_group.clone(),
+        )
+        .await;
+        assert_eq!(owner(&*lag_by_group.read().await).stable_id(), "other-1");
+    }
+
+    #[tokio::test]
+    async fn counts_committed_offset_resets() {
+        let metrics = Arc::new(Registry::new());
+        let po_reg = empty_po_reg(metrics.clone());
+        let events = LagEvents::new(std::time::Duration::ZERO, metrics);
+        let metric_resets = metric_resets();
+        let clock = ManualClock::default();
+
+        let tp = TopicPartition::new("t".to_string(), 0);
+        let mut gwl = GroupWithLag {
+            group: Group {
+                name: "g".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        for offset in [100, 150, 150, 20, 30] {
+            clock.advance(std::time::Duration::from_secs(1));
+            record_committed_offset(
+                &mut gwl,
+                tp.clone(),
+                offset,
+                clock.now(),
+                &po_reg,
+                &events,
+                &metric_resets,
+            )
+            .await;
+        }
+
+        // Only moving backwards is a reset: the Lag is estimated from the latest commit regardless
+        assert_eq!(metric_resets.with_label_values(&["g", "t"]).get(), 1);
+        assert_eq!(gwl.lag_by_topic_partition[&tp].lag().map(Lag::offset), Some(30));
+    }
+
+    /// An [`OffsetCommit`] of Group `g` for Topic Partition `t:<partition>`.
+    fn offset_commit(partition: i32, offset: i64, committed_at: DateTime<Utc>) -> OffsetCommit {
+        OffsetCommit {
+            group: "g".to_string(),
+            topic: "t".to_string(),
+            partition,
+            offset,
+            commit_timestamp: committed_at,
+            ..Default::default()
+        }
+    }
+
+    /// The tombstone of the [`OffsetCommit`] of Group `g` for Topic Partition `t:<partition>`.
+    fn offset_commit_tombstone(partition: i32) -> OffsetCommit {
+        OffsetCommit {
+            is_tombstone: true,
+            ..offset_commit(partition, 0, DateTime::<Utc>::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn offset_commit_tombstones_expire_lag() {
+        let metrics = Arc::new(Registry::new());
+        let po_reg = empty_po_reg(metrics.clone());
+        let events = LagEvents::new(std::time::Duration::ZERO, metrics);
+        let mut pending = PendingGroups::new(PENDING_DESCRIPTION_TIMEOUT);
+        let metric_resets = metric_resets();
+        let clock = ManualClock::default();
+        let mut lag_by_group = BTreeMap::new();
+
+        for partition in [0, 1] {
+            process_offset_commit(
+                offset_commit(partition, 100, clock.now()),
+                &GroupLabels::default(),
+                &mut pending,
+                &events,
+                &clock,
+                &mut lag_by_group,
+                &po_reg,
+                &metric_resets,
+            )
+            .await;
+        }
+        let tp = |partition| TopicPartition::new("t".to_string(), partition);
+        lag_by_group.get_mut("g").unwrap().lag_by_topic_partition.get_mut(&tp(1)).unwrap().owner =
+            Some(Member::default());
+
+        clock.advance(std::time::Duration::from_secs(60));
+        for partition in [0, 1, 2] {
+            process_offset_commit(
+                offset_commit_tombstone(partition),
+                &GroupLabels::default(),
+                &mut pending,
+                &events,
+                &clock,
+                &mut lag_by_group,
+                &po_reg,
+                &metric_resets,
+            )
+            .await;
+        }
+
+        // Entries no Member owns are removed, the others only lose their Lag
+        let gwl = &lag_by_group["g"];
+        assert!(!gwl.lag_by_topic_partition.contains_key(&tp(0)));
+        assert!(gwl.lag_by_topic_partition[&tp(1)].lag().is_none());
+        assert!(gwl.lag_by_topic_partition[&tp(1)].owner().is_some());
+        assert!(!gwl.lag_by_topic_partition.contains_key(&tp(2)));
+    }
+
+    #[tokio::test]
+    async fn offset_commit_tombstones_of_unknown_groups_are_ignored() {
+        let metrics = Arc::new(Registry::new());
+        let po_reg = empty_po_reg(metrics.clone());
+        let events = LagEvents::new(std::time::Duration::ZERO, metrics);
+        let mut pending = PendingGroups::new(PENDING_DESCRIPTION_TIMEOUT);
+        let clock = ManualClock::default();
+        let mut lag_by_group = BTreeMap::new();
+
+        process_offset_commit(
+            offset_commit_tombstone(0),
+            &GroupLabels::default(),
+            &mut pending,
+            &events,
+            &clock,
+            &mut lag_by_group,
+            &po_reg,
+            &metric_resets(),
+        )
+        .await;
+        assert!(lag_by_group.is_empty());
+    }
+
+    #[tokio::test]
+    async fn group_metadata_tombstones_delete_groups() {
+        let metrics = Arc::new(Registry::new());
+        let po_reg = empty_po_reg(metrics.clone());
+        let events = LagEvents::new(std::time::Duration::ZERO, metrics);
+        let mut rx = events.sender().subscribe();
+        let mut pending = PendingGroups::new(PENDING_DESCRIPTION_TIMEOUT);
+        let clock = ManualClock::default();
+        let committed_at = clock.now();
+        let mut lag_by_group = BTreeMap::from([(
+            Interned::from("g"),
+            GroupWithLag {
+                group: Group {
+                    name: "g".into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )]);
+
+        for group in ["g", "unknown"] {
+            process_group_metadata(
+                GroupMetadata {
+                    group: group.to_string(),
+                    is_tombstone: true,
+                    ..Default::default()
+                },
+                &GroupLabels::default(),
+                &events,
+                &mut lag_by_group,
+            );
+        }
+
+        assert!(lag_by_group.is_empty());
+        let received: Vec<LagEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(
+            received,
+            vec![LagEvent::GroupDisappeared {
+                group: "g".into()
+            }]
+        );
+
+        // Offsets committed before the deletion, read late, don't bring the Group back
+        clock.advance(PENDING_DESCRIPTION_TIMEOUT + std::time::Duration::from_secs(1));
+        process_offset_commit(
+            offset_commit(0, 100, committed_at),
+            &GroupLabels::default(),
+            &mut pending,
+            &events,
+            &clock,
+            &mut lag_by_group,
+            &po_reg,
+            &metric_resets(),
+        )
+        .await;
+        assert!(lag_by_group.is_empty());
+    }
+
+    #[test]
+    fn tells_recent_commits_by_the_clock() {
+        let clock = ManualClock::default();
+        let committed_at = clock.now();
+        assert!(is_recent_commit(committed_at, clock.now()));
+
+        clock.advance(PENDING_DESCRIPTION_TIMEOUT);
+        assert!(is_recent_commit(committed_at, clock.now()));
+
+        clock.advance(std::time::Duration::from_secs(1));
+        assert!(!is_recent_commit(committed_at, clock.now()));
+    }
+
+    #[tokio::test]
+    async fn snapshot_reads_again_only_the_changed_groups() {
+        let metrics = Arc::new(Registry::new());
+        let clock = Arc::new(ManualClock::default());
+        let (lag_reg, cg_sx, kod_sx) = lag_reg(clock.clone(), metrics.clone());
+        let (_, cs_rx) = mpsc::channel(1);
+        let cs_reg = ClusterStatusRegister::new(None, cs_rx, Arc::new(Registry::new()));
+        let po_reg = empty_po_reg(Arc::new(Registry::new()));
+        let mut changes = lag_reg.subscribe_changes();
+
+        cg_sx.send(groups_added(&["a", "b"])).await.unwrap();
+        changes.changed().await.unwrap();
+        let update = |prev: LagSnapshot| {
+            let (lag_reg, cs_reg, po_reg) = (&lag_reg, &cs_reg, &po_reg);
+            async move {
+                let changed_groups = lag_reg.take_changed_groups();
+                let changed_partitions = po_reg.take_changed_partitions();
+                prev.update(cs_reg, po_reg, lag_reg, &changed_groups, &changed_partitions).await
+            }
+        };
+        let first = update(LagSnapshot::default()).await;
+        assert_eq!(first.groups.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+
+        // Nothing changed: nothing is read again
+        let second = update(first.clone()).await;
+        assert!(Arc::ptr_eq(&first.groups["a"], &second.groups["a"]));
+        assert!(Arc::ptr_eq(&first.groups["b"], &second.groups["b"]));
+
+        // Only the Group that committed is read again
+        let commit = OffsetCommit {
+            group: "a".to_string(),
+            topic: "t".to_string(),
+            partition: 0,
+            offset: 10,
+            commit_timestamp: clock.now(),
+            ..Default::default()
+        };
+        kod_sx.send(KonsumerOffsetsData::OffsetCommit(commit)).await.unwrap();
+        changes.changed().await.unwrap();
+        let third = update(second.clone()).await;
+        assert!(!Arc::ptr_eq(&second.groups["a"], &third.groups["a"]));
+        assert_eq!(third.groups["a"].lag_by_topic_partition.len(), 1);
+        assert!(Arc::ptr_eq(&second.groups["b"], &third.groups["b"]));
+    }
+
+    /// Commit `offset` for Topic Partition `t:0` on behalf of Group `group`, and await it's processed.
+    async fn commit(
+        lag_reg: &LagRegister,
+        kod_sx: &mpsc::Sender<KonsumerOffsetsData>,
+        group: &str,
+        offset: i64,
+        committed_at: DateTime<Utc>,
+    ) {
+        let mut changes = lag_reg.subscribe_changes();
+        let oc = OffsetCommit {
+            group: group.to_string(),
+            ..offset_commit(0, offset, committed_at)
+        };
+        kod_sx.send(KonsumerOffsetsData::OffsetCommit(oc)).await.unwrap();
+        changes.changed().await.unwrap();
+    }
+
+    /// A [`LagRegister`] tracking the given Groups, with no Lag yet.
+    async fn lag_reg_tracking(
+        groups: &[&str],
+        clock: Arc<ManualClock>,
+    ) -> (LagRegister, mpsc::Sender<ConsumerGroupsDiff>, mpsc::Sender<KonsumerOffsetsData>) {
+        let (lag_reg, cg_sx, kod_sx) = lag_reg(clock, Arc::new(Registry::new()));
+        let mut changes = lag_reg.subscribe_changes();
+        cg_sx.send(groups_added(groups)).await.unwrap();
+        changes.changed().await.unwrap();
+        (lag_reg, cg_sx, kod_sx)
+    }
+
+    fn lag_of(gwl: &GroupWithLag) -> Option<&Lag> {
+        gwl.lag_by_topic_partition[&TopicPartition::new("t", 0)].lag.as_ref()
+    }
+
+    #[tokio::test]
+    async fn state_is_restored() {
+        let clock = Arc::new(ManualClock::default());
+        let committed_at = DateTime::from_timestamp_millis(clock.now().timestamp_millis()).unwrap();
+        let (prev, _prev_cg_sx, prev_kod_sx) = lag_reg_tracking(&["g"], clock.clone()).await;
+        commit(&prev, &prev_kod_sx, "g", 10, committed_at).await;
+
+        let state = prev.state().await;
+        assert_eq!(
+            state,
+            vec![CommittedOffset {
+                group: "g".into(),
+                topic: "t".into(),
+                partition: 0,
+                offset: 10,
+                committed_at_ms: committed_at.timestamp_millis(),
+            }]
+        );
+
+        let (next, _next_cg_sx, _next_kod_sx) = lag_reg_tracking(&["g"], clock).await;
+        next.restore(state).await;
+        let gwl = next.get_group_with_lag("g").await.unwrap();
+        let lag = lag_of(&gwl).unwrap();
+        assert_eq!((lag.offset, lag.offset_timestamp), (10, committed_at));
+        assert_eq!(next.take_changed_groups(), Changes::All);
+    }
+
+    #[tokio::test]
+    async fn only_tracked_groups_are_restored() {
+        let clock = Arc::new(ManualClock::default());
+        let (prev, _prev_cg_sx, prev_kod_sx) =
+            lag_reg_tracking(&["tracked", "gone"], clock.clone()).await;
+        commit(&prev, &prev_kod_sx, "tracked", 10, clock.now()).await;
+        commit(&prev, &prev_kod_sx, "gone", 10, clock.now()).await;
+
+        let (next, _next_cg_sx, _next_kod_sx) = lag_reg_tracking(&["tracked"], clock).await;
+        next.restore(prev.state().await).await;
+        assert_eq!(next.get_groups().await, vec![Interned::from("tracked")]);
+        assert!(lag_of(&next.get_group_with_lag("tracked").await.unwrap()).is_some());
+    }
+
+    #[tokio::test]
+    async fn restore_keeps_newer_lag() {
+        let clock = Arc::new(ManualClock::default());
+        let (prev, _prev_cg_sx, prev_kod_sx) = lag_reg_tracking(&["g"], clock.clone()).await;
+        commit(&prev, &prev_kod_sx, "g", 10, clock.now()).await;
+
+        let (next, _next_cg_sx, next_kod_sx) = lag_reg_tracking(&["g"], clock.clone()).await;
+        clock.advance(std::time::Duration::from_secs(60));
+        commit(&next, &next_kod_sx, "g", 20, clock.now()).await;
+        next.restore(prev.state().await).await;
+        assert_eq!(lag_of(&next.get_group_with_lag("g").await.unwrap()).unwrap().offset, 20);
+    }
+
+    #[tokio::test]
+    async fn state_is_persisted_to_disk() {
+        let path = std::env::temp_dir()
+            .join(format!("kommitted-lag-register-{}.json", std::process::id()));
+        let clock = Arc::new(ManualClock::default());
+        let (prev, _prev_cg_sx, prev_kod_sx) = lag_reg_tracking(&["g"], clock.clone()).await;
+        commit(&prev, &prev_kod_sx, "g", 10, clock.now()).await;
+
+        let (next, _next_cg_sx, _next_kod_sx) = lag_reg_tracking(&["g"], clock).await;
+        assert!(!restore_from(&next, &path).await.unwrap());
+        persist_to(&prev, &path).await.unwrap();
+        assert!(restore_from(&next, &path).await.unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(next.state().await, prev.state().await);
     }
 }
@@ -0,0 +1,446 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::register::{GroupWithLag, LagRegister, LagWithOwner};
+
+use crate::cluster_status::ClusterStatusRegister;
+use crate::internals::Changes;
+use crate::kafka_types::{Interned, Member, TopicPartition};
+use crate::partition_offsets::{PartitionOffsetsRegister, PartitionOffsetsSnapshot};
+
+/// Lag of a Consumer Group, aggregated across all the partitions of a Topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupTopicLag {
+    /// Sum of the Offset Lag of all the partitions.
+    pub offset_lag: u64,
+
+    /// Max of the Time Lag of all the partitions.
+    pub time_lag: Duration,
+}
+
+impl Default for GroupTopicLag {
+    fn default() -> Self {
+        Self {
+            offset_lag: 0,
+            time_lag: Duration::zero(),
+        }
+    }
+}
+
+/// Lag aggregated across all the Consumer Groups of the Cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClusterLag {
+    /// Sum of the Offset Lag of all the Consumer Groups.
+    pub offset_lag: u64,
+
+    /// Amount of Consumer Groups whose Offset Lag is above the lagging threshold.
+    pub lagging_groups: usize,
+}
+
+/// Point-in-time copy of the lag of all Consumer Groups, and of the offsets
+/// of all the Topic Partitions that the lag was estimated against.
+///
+/// This is what gets handed to each [`crate::internals::LagSink`] at every reconcile tick.
+#[derive(Debug, Clone, Default)]
+pub struct LagSnapshot {
+    /// Identifier of the Kafka cluster.
+    pub cluster_id: String,
+
+    /// [`DateTime<Utc>`] when this snapshot was taken.
+    pub taken_at: DateTime<Utc>,
+
+    /// All the [`GroupWithLag`] tracked, indexed (and sorted) by Consumer Group name.
+    ///
+    /// Shared with the following snapshots, for as long as the Consumer Group doesn't change.
+    pub groups: BTreeMap<Interned, Arc<GroupWithLag>>,
+
+    /// All the Topic Partitions offsets, as known by the [`PartitionOffsetsRegister`].
+    pub partitions: Vec<PartitionOffsetsSnapshot>,
+}
+
+impl LagSnapshot {
+    /// Take a new [`LagSnapshot`] out of the given Registers.
+    pub async fn take(
+        cs_reg: &ClusterStatusRegister,
+        po_reg: &PartitionOffsetsRegister,
+        lag_reg: &LagRegister,
+    ) -> Self {
+        Self::default().update(cs_reg, po_reg, lag_reg, &Changes::All, &Changes::All).await
+    }
+
+    /// Take a new [`LagSnapshot`] out of the given Registers, reading again only the Consumer
+    /// Groups and Topic Partitions that changed since [`Self`] was taken: the others are
+    /// carried over from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `cs_reg` - Where to get the Cluster identifier, and its Topic Partitions from
+    /// * `po_reg` - Where to get the Topic Partitions offsets from
+    /// * `lag_reg` - Where to get the lag of the Consumer Groups from
+    /// * `changed_groups` - See [`LagRegister::take_changed_groups`]
+    /// * `changed_partitions` - See [`PartitionOffsetsRegister::take_changed_partitions`]
+    pub async fn update(
+        &self,
+        cs_reg: &ClusterStatusRegister,
+        po_reg: &PartitionOffsetsRegister,
+        lag_reg: &LagRegister,
+        changed_groups: &Changes<Interned>,
+        changed_partitions: &Changes<TopicPartition>,
+    ) -> Self {
+        let cluster_id = cs_reg.get_cluster_id().await;
+        let tps = cs_reg.get_topic_partitions().await;
+
+        let groups = match changed_groups {
+            Changes::All => lag_reg
+                .get_groups_with_lag()
+                .await
+                .into_iter()
+                .map(|(g, gwl)| (g, Arc::new(gwl)))
+                .collect(),
+            Changes::Some(changed) if changed.is_empty() => self.groups.clone(),
+            Changes::Some(changed) => {
+                let mut groups = self.groups.clone();
+                let mut updated = lag_reg.get_groups_with_lag_among(changed).await;
+                for g in changed {
+                    match updated.remove(g) {
+                        Some(gwl) => groups.insert(g.clone(), Arc::new(gwl)),
+                        None => groups.remove(g),
+                    };
+                }
+                groups
+            },
+        };
+
+        Self {
+            cluster_id,
+            taken_at: Utc::now(),
+            groups,
+            partitions: po_reg.update_snapshot(&self.partitions, &tps, changed_partitions).await,
+        }
+    }
+
+    /// Amount of (Consumer Group, Topic Partition) pairs in [`Self`].
+    pub fn group_partitions_count(&self) -> usize {
+        self.groups.values().map(|gwl| gwl.lag_by_topic_partition.len()).sum()
+    }
+
+    /// Lag of each (Consumer Group, Topic) pair, aggregated across partitions.
+    ///
+    /// Partitions whose lag is not known yet are ignored.
+    pub fn lag_by_group_topic(&self) -> BTreeMap<(&str, &str), GroupTopicLag> {
+        let mut res: BTreeMap<(&str, &str), GroupTopicLag> = BTreeMap::new();
+
+        for (group, tp, lwo) in self.iter_group_partitions() {
+            if let Some(lag) = lwo.lag() {
+                let agg = res.entry((group, tp.topic.as_str())).or_default();
+                agg.offset_lag += lag.offset_lag();
+                agg.time_lag = agg.time_lag.max(lag.time_lag());
+            }
+        }
+
+        res
+    }
+
+    /// Lag aggregated across all the Consumer Groups in [`Self`]: a Consumer Group is counted
+    /// as lagging, if the sum of its Offset Lag is above `lagging_threshold`.
+    ///
+    /// Partitions whose lag is not known yet are ignored.
+    pub fn cluster_lag(&self, lagging_threshold: u64) -> ClusterLag {
+        let mut res = ClusterLag::default();
+
+        for gwl in self.groups.values() {
+            let offset_lag: u64 = gwl
+                .lag_by_topic_partition
+                .values()
+                .filter_map(|lwo| lwo.lag())
+                .map(|lag| lag.offset_lag())
+                .sum();
+            res.offset_lag += offset_lag;
+            if offset_lag > lagging_threshold {
+                res.lagging_groups += 1;
+            }
+        }
+
+        res
+    }
+
+    /// Fraction (`[0, 1]`) of the (Consumer Group, Topic Partition) pairs in [`Self`]
+    /// whose lag is known: `1` if there are none.
+    pub fn data_coverage(&self) -> f64 {
+        let total = self.group_partitions_count();
+        if total == 0 {
+            return 1_f64;
+        }
+
+        let known = self.iter_group_partitions().filter(|(_, _, lwo)| lwo.lag().is_some()).count();
+        known as f64 / total as f64
+    }
+
+    /// Offset Lag of each (Consumer Group, Member) pair, summed across the Topic Partitions
+    /// the Member owns. Members are identified by their [`Member::stable_id`].
+    ///
+    /// Partitions whose lag is not known yet, or that no Member owns, are ignored.
+    pub fn lag_by_group_member(&self) -> BTreeMap<(&str, &str), (&Member, u64)> {
+        let mut res: BTreeMap<(&str, &str), (&Member, u64)> = BTreeMap::new();
+
+        for (group, _, lwo) in self.iter_group_partitions() {
+            if let Some(owner) = lwo.owner() {
+                let agg = res.entry((group, owner.stable_id())).or_insert((owner, 0));
+                agg.1 += lwo.lag().map(|l| l.offset_lag()).unwrap_or_default();
+            }
+        }
+
+        res
+    }
+
+    /// All the (Consumer Group, Topic Partition) pairs in [`Self`], with the amount of offsets
+    /// the Consumer Group can't consume anymore (i.e. the committed offset fell behind retention,
+    /// and those offsets were deleted before being consumed).
+    ///
+    /// Pairs whose committed offset, or earliest available offset, is not known yet are omitted.
+    pub fn unrecoverable_offsets(&self) -> Vec<(&Interned, &TopicPartition, &LagWithOwner, u64)> {
+        let available = self.available_offsets();
+
+        self.iter_group_partitions()
+            .filter_map(|(group, tp, lwo)| {
+                let lag = lwo.lag()?;
+                let (eao, _) = available.get(tp)?;
+                Some((group, tp, lwo, eao.saturating_sub(lag.offset())))
+            })
+            .collect()
+    }
+
+    /// All the (Consumer Group, Topic Partition) pairs in [`Self`], whose Topic Partition
+    /// doesn't exist in the Cluster anymore (e.g. the Topic was deleted, but the committed
+    /// offsets of the Consumer Group haven't expired yet).
+    ///
+    /// None is reported until the Topic Partitions of the Cluster are known.
+    pub fn orphaned_partitions(&self) -> Vec<(&Interned, &TopicPartition)> {
+        if self.partitions.is_empty() {
+            return Vec::new();
+        }
+        let existing: HashSet<&TopicPartition> =
+            self.partitions.iter().map(|ps| &ps.topic_partition).collect();
+
+        self.iter_group_partitions()
+            .filter(|(_, tp, _)| !existing.contains(tp))
+            .map(|(group, tp, _)| (group, tp))
+            .collect()
+    }
+
+    /// The (earliest, latest) offsets available of each Topic Partition in [`Self`].
+    ///
+    /// Topic Partitions whose available offsets are not known yet are omitted.
+    pub fn available_offsets(&self) -> HashMap<&TopicPartition, (u64, u64)> {
+        self.partitions
+            .iter()
+            .filter_map(|ps| match (&ps.earliest_available_offset, &ps.latest_available_offset) {
+                (Ok(eao), Ok(lao)) => Some((&ps.topic_partition, (*eao, *lao))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Iterate over all the (Consumer Group, Topic Partition) pairs in [`Self`], with their lag.
+    ///
+    /// Pairs are sorted by Consumer Group, then Topic Partition: so is everything rendered out
+    /// of [`Self`], and it's stable across snapshots.
+    pub fn iter_group_partitions(
+        &self,
+    ) -> impl Iterator<Item = (&Interned, &TopicPartition, &LagWithOwner)> {
+        self.groups.iter().flat_map(|(g, gwl)| {
+            gwl.lag_by_topic_partition.iter().map(move |(tp, lwo)| (g, tp, lwo))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lag_register::Lag;
+    use crate::partition_offsets::PartitionOffsetsError;
+
+    fn lwo(offset_lag: u64, time_lag_ms: i64) -> LagWithOwner {
+        LagWithOwner {
+            lag: Some(Lag {
+                offset_lag,
+                time_lag: Duration::milliseconds(time_lag_ms),
+                ..Default::default()
+            }),
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn lag_by_group_topic() {
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t1".to_string(), 0), lwo(10, 100));
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t1".to_string(), 1), lwo(5, 300));
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t2".to_string(), 0), lwo(1, 1));
+        gwl.lag_by_topic_partition
+            .insert(TopicPartition::new("t2".to_string(), 1), LagWithOwner::default());
+
+        let mut snapshot = LagSnapshot::default();
+        snapshot.groups.insert("g".into(), Arc::new(gwl));
+
+        let agg = snapshot.lag_by_group_topic();
+        assert_eq!(agg.len(), 2);
+        assert_eq!(agg[&("g", "t1")].offset_lag, 15);
+        assert_eq!(agg[&("g", "t1")].time_lag, Duration::milliseconds(300));
+        assert_eq!(agg[&("g", "t2")].offset_lag, 1);
+    }
+
+    #[test]
+    fn iter_group_partitions_is_sorted() {
+        let mut snapshot = LagSnapshot::default();
+        for g in ["g2", "g10", "g1"] {
+            let mut gwl = GroupWithLag::default();
+            for (t, p) in [("t2", 0), ("t1", 10), ("t1", 2)] {
+                gwl.lag_by_topic_partition.insert(TopicPartition::new(t, p), lwo(1, 1));
+            }
+            snapshot.groups.insert(g.into(), Arc::new(gwl));
+        }
+
+        let pairs: Vec<String> =
+            snapshot.iter_group_partitions().map(|(g, tp, _)| format!("{g}@{tp}")).collect();
+        assert_eq!(pairs[..4], ["g1@t1:2", "g1@t1:10", "g1@t2:0", "g10@t1:2"].map(String::from));
+        assert_eq!(pairs.len(), 9);
+    }
+
+    #[test]
+    fn lag_by_group_member() {
+        let owned = |offset_lag, member_id: &str| LagWithOwner {
+            owner: Some(Member {
+                id: member_id.to_string(),
+                ..Default::default()
+            }),
+            ..lwo(offset_lag, 0)
+        };
+
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition
+            .insert(TopicPartition::new("t1".to_string(), 0), owned(10, "m1"));
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t2".to_string(), 0), owned(5, "m1"));
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t1".to_string(), 1), owned(1, "m2"));
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t1".to_string(), 2), lwo(100, 0));
+
+        let mut snapshot = LagSnapshot::default();
+        snapshot.groups.insert("g".into(), Arc::new(gwl));
+
+        let agg = snapshot.lag_by_group_member();
+        assert_eq!(agg.len(), 2);
+        assert_eq!(agg[&("g", "m1")].1, 15);
+        assert_eq!(agg[&("g", "m2")].1, 1);
+    }
+
+    #[test]
+    fn unrecoverable_offsets() {
+        let committed = |offset| LagWithOwner {
+            lag: Some(Lag {
+                offset,
+                ..Default::default()
+            }),
+            owner: None,
+        };
+        let partition = |partition, earliest_available_offset| PartitionOffsetsSnapshot {
+            topic_partition: TopicPartition::new("t".to_string(), partition),
+            earliest_available_offset,
+            latest_available_offset: Ok(1000),
+            earliest_tracked_offset: Err(PartitionOffsetsError::LagEstimatorNotReady),
+            latest_tracked_offset: Err(PartitionOffsetsError::LagEstimatorNotReady),
+            produced_records_rate: Err(PartitionOffsetsError::LagEstimatorNotReady),
+        };
+
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t".to_string(), 0), committed(50));
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t".to_string(), 1), committed(150));
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t".to_string(), 2), committed(10));
+        gwl.lag_by_topic_partition
+            .insert(TopicPartition::new("t".to_string(), 3), LagWithOwner::default());
+
+        let mut snapshot = LagSnapshot::default();
+        snapshot.groups.insert("g".into(), Arc::new(gwl));
+        snapshot.partitions = vec![
+            partition(0, Ok(100)),
+            partition(1, Ok(100)),
+            partition(2, Err(PartitionOffsetsError::LagEstimatorNotReady)),
+            partition(3, Ok(100)),
+        ];
+
+        let mut unrecoverable: Vec<(u32, u64)> = snapshot
+            .unrecoverable_offsets()
+            .into_iter()
+            .map(|(_, tp, _, uo)| (tp.partition, uo))
+            .collect();
+        unrecoverable.sort();
+        assert_eq!(unrecoverable, vec![(0, 50), (1, 0)]);
+    }
+
+    #[test]
+    fn cluster_lag() {
+        let mut snapshot = LagSnapshot::default();
+        for (g, offset_lags) in [("a", vec![10, 5]), ("b", vec![100]), ("c", vec![])] {
+            let mut gwl = GroupWithLag::default();
+            for (p, offset_lag) in offset_lags.into_iter().enumerate() {
+                gwl.lag_by_topic_partition
+                    .insert(TopicPartition::new("t".to_string(), p as u32), lwo(offset_lag, 1));
+            }
+            gwl.lag_by_topic_partition
+                .insert(TopicPartition::new("u".to_string(), 0), LagWithOwner::default());
+            snapshot.groups.insert((*g).into(), Arc::new(gwl));
+        }
+
+        assert_eq!(
+            snapshot.cluster_lag(0),
+            ClusterLag {
+                offset_lag: 115,
+                lagging_groups: 2,
+            }
+        );
+        assert_eq!(snapshot.cluster_lag(15).lagging_groups, 1);
+        assert_eq!(snapshot.cluster_lag(100).lagging_groups, 0);
+    }
+
+    #[test]
+    fn data_coverage() {
+        let mut snapshot = LagSnapshot::default();
+        assert_eq!(snapshot.data_coverage(), 1_f64);
+
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t".to_string(), 0), lwo(1, 1));
+        gwl.lag_by_topic_partition
+            .insert(TopicPartition::new("t".to_string(), 1), LagWithOwner::default());
+        snapshot.groups.insert("a".into(), Arc::new(gwl.clone()));
+        snapshot.groups.insert("b".into(), Arc::new(gwl));
+
+        assert_eq!(snapshot.data_coverage(), 0.5_f64);
+    }
+
+    #[test]
+    fn orphaned_partitions() {
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t".to_string(), 0), lwo(1, 1));
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("deleted".to_string(), 0), lwo(1, 1));
+
+        let mut snapshot = LagSnapshot::default();
+        snapshot.groups.insert("g".into(), Arc::new(gwl));
+
+        // Until the Topic Partitions of the Cluster are known, none is orphaned
+        assert!(snapshot.orphaned_partitions().is_empty());
+
+        snapshot.partitions = vec![PartitionOffsetsSnapshot {
+            topic_partition: TopicPartition::new("t".to_string(), 0),
+            earliest_available_offset: Ok(0),
+            latest_available_offset: Ok(10),
+            earliest_tracked_offset: Err(PartitionOffsetsError::LagEstimatorNotReady),
+            latest_tracked_offset: Err(PartitionOffsetsError::LagEstimatorNotReady),
+            produced_records_rate: Err(PartitionOffsetsError::LagEstimatorNotReady),
+        }];
+        let orphaned = snapshot.orphaned_partitions();
+        assert_eq!(orphaned.len(), 1);
+        assert_eq!(orphaned[0].0, "g");
+        assert_eq!(orphaned[0].1.topic, "deleted");
+    }
+}
@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use super::history::GroupLagPoint;
+
+use crate::constants::{DEFAULT_LAG_SLO_OBJECTIVE, DEFAULT_LAG_TREND_WINDOW};
+
+/// How to evaluate the trend of the lag of each Consumer Group, from its lag history.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LagTrendConfig {
+    /// How far back in the lag history to evaluate the trend over.
+    pub window: Duration,
+
+    /// If set, the Time Lag each Consumer Group is expected to stay within:
+    /// the burn rate of the error budget is evaluated against it.
+    pub slo_target: Option<Duration>,
+
+    /// Share of the time each Consumer Group is expected to stay within [`Self::slo_target`]
+    /// (e.g. `0.99`): the rest is the error budget.
+    pub slo_objective: f64,
+}
+
+impl Default for LagTrendConfig {
+    fn default() -> Self {
+        Self {
+            window: humantime::parse_duration(DEFAULT_LAG_TREND_WINDOW).expect("Invalid default"),
+            slo_target: None,
+            slo_objective: DEFAULT_LAG_SLO_OBJECTIVE.parse().expect("Invalid default"),
+        }
+    }
+}
+
+/// The points of a lag history that fall within `window` from the latest one.
+fn within_window(points: &[GroupLagPoint], window: Duration) -> &[GroupLagPoint] {
+    let Some(latest) = points.last() else {
+        return points;
+    };
+
+    let since_ms = latest.timestamp_ms - window.as_millis() as i64;
+    let first = points.partition_point(|p| p.timestamp_ms < since_ms);
+    &points[first..]
+}
+
+/// Rate (offsets/second) at which the Offset Lag is growing (positive) or shrinking (negative),
+/// over the `window` of the given lag history (oldest point first).
+///
+/// It's the slope of the least-squares line fitting the points. Returns `None` if the window
+/// has less than 2 points, as there is no trend to speak of.
+pub fn offset_lag_trend(points: &[GroupLagPoint], window: Duration) -> Option<f64> {
+    let points = within_window(points, window);
+    if points.len() < 2 {
+        return None;
+    }
+
+    // Timestamps relative to the first point, to keep the values small
+    let t0 = points[0].timestamp_ms;
+    let xs = points.iter().map(|p| (p.timestamp_ms - t0) as f64 / 1000_f64);
+    let ys = points.iter().map(|p| p.offset_lag as f64);
+
+    let n = points.len() as f64;
+    let x_mean = xs.clone().sum::<f64>() / n;
+    let y_mean = ys.clone().sum::<f64>() / n;
+    let (num, den) = xs.zip(ys).fold((0_f64, 0_f64), |(num, den), (x, y)| {
+        (num + (x - x_mean) * (y - y_mean), den + (x - x_mean).powi(2))
+    });
+
+    (den > 0_f64).then(|| num / den)
+}
+
+/// Offset Lag forecast `horizon` from the latest point of the given lag history (oldest point first),
+/// assuming the [`offset_lag_trend`] over the `window` holds.
+///
+/// The Offset Lag trend is the difference between the production and consumption rates,
+/// so this assumes both stay the same. The forecast is never below zero.
+/// Returns `None` if there is no trend over the window.
+pub fn offset_lag_forecast(
+    points: &[GroupLagPoint],
+    window: Duration,
+    horizon: Duration,
+) -> Option<f64> {
+    let trend = offset_lag_trend(points, window)?;
+    let latest = points.last()?;
+
+    Some((latest.offset_lag as f64 + trend * horizon.as_secs_f64()).max(0_f64))
+}
+
+/// Rate at which the error budget of the Time Lag SLO is burnt, over the `window`
+/// of the given lag history (oldest point first).
+///
+/// It's the share of points where the Time Lag exceeded `target`, over the error budget
+/// (i.e. `1 - objective`): `1` means the budget is burnt exactly as fast as allowed,
+/// above `1` faster. Returns `None` if the window has no points.
+pub fn time_lag_burn_rate(
+    points: &[GroupLagPoint],
+    window: Duration,
+    target: Duration,
+    objective: f64,
+) -> Option<f64> {
+    let points = within_window(points, window);
+    if points.is_empty() {
+        return None;
+    }
+
+    let target_ms = target.as_millis() as i64;
+    let bad = points.iter().filter(|p| p.time_lag_ms > target_ms).count() as f64;
+    let budget = (1_f64 - objective).max(f64::EPSILON);
+
+    Some(bad / points.len() as f64 / budget)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn points(lags: &[(u64, i64)]) -> Vec<GroupLagPoint> {
+        lags.iter()
+            .enumerate()
+            .map(|(i, (offset_lag, time_lag_ms))| GroupLagPoint {
+                timestamp_ms: i as i64 * 10_000,
+                offset_lag: *offset_lag,
+                time_lag_ms: *time_lag_ms,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn trend() {
+        let window = Duration::from_secs(60);
+
+        // Growing by 100 offsets every 10s
+        let growing = points(&[(0, 0), (100, 0), (200, 0), (300, 0)]);
+        assert_eq!(offset_lag_trend(&growing, window), Some(10_f64));
+
+        // Shrinking, but only the last 20s are within the window
+        let shrinking =
+            points(&[(0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (400, 0), (300, 0), (200, 0)]);
+        assert_eq!(offset_lag_trend(&shrinking, Duration::from_secs(20)), Some(-10_f64));
+
+        assert_eq!(offset_lag_trend(&points(&[(5, 0), (5, 0)]), window), Some(0_f64));
+        assert_eq!(offset_lag_trend(&points(&[(5, 0)]), window), None);
+        assert_eq!(offset_lag_trend(&[], window), None);
+    }
+
+    #[test]
+    fn forecast() {
+        let window = Duration::from_secs(60);
+        let horizon = Duration::from_secs(15 * 60);
+
+        // Growing by 10 offsets/s
+        let growing = points(&[(0, 0), (100, 0), (200, 0), (300, 0)]);
+        assert_eq!(offset_lag_forecast(&growing, window, horizon), Some(9300_f64));
+
+        // Shrinking by 10 offsets/s: caught up before the horizon
+        let shrinking = points(&[(300, 0), (200, 0), (100, 0)]);
+        assert_eq!(offset_lag_forecast(&shrinking, window, horizon), Some(0_f64));
+
+        assert_eq!(offset_lag_forecast(&points(&[(5, 0)]), window, horizon), None);
+    }
+
+    #[test]
+    fn burn_rate() {
+        let window = Duration::from_secs(60);
+        let target = Duration::from_secs(1);
+
+        // 1 point out of 4 over the target, with a 25% error budget
+        let history = points(&[(0, 500), (0, 1500), (0, 1000), (0, 200)]);
+        assert_eq!(time_lag_burn_rate(&history, window, target, 0.75), Some(1_f64));
+
+        // Same share, but with a 5% error budget
+        let rate = time_lag_burn_rate(&history, window, target, 0.95).unwrap();
+        assert!((rate - 5_f64).abs() < 1e-9);
+
+        assert_eq!(time_lag_burn_rate(&[], window, target, 0.99), None);
+    }
+}
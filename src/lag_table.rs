@@ -0,0 +1,372 @@
+//! Lag of each Consumer Group and Topic, as a table: the report of the `snapshot` subcommand.
+//!
+//! It can be rendered as an aligned table, optionally colored by how much lag there is,
+//! or as JSON or CSV, to be consumed by other tools. It can also be rendered per partition,
+//! in the layout of `kafka-consumer-groups.sh --describe`, for the scripts that parse it.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::kafka_types::Interned;
+use crate::lag_register::LagSnapshot;
+
+/// Time Lag below which a row is colored as healthy.
+const TIME_LAG_OK: Duration = Duration::from_secs(10);
+
+/// Time Lag from which a row is colored as critical: in between, it's colored as a warning.
+const TIME_LAG_CRITICAL: Duration = Duration::from_secs(60);
+
+const ANSI_BOLD: &str = "\x1b[1m";
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_YELLOW: &str = "\x1b[33m";
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+const HEADERS: [&str; 5] = ["GROUP", "TOPIC", "PARTITIONS", "SUM LAG", "MAX TIME LAG"];
+
+const DESCRIBE_HEADERS: [&str; 9] = [
+    "GROUP",
+    "TOPIC",
+    "PARTITION",
+    "CURRENT-OFFSET",
+    "LOG-END-OFFSET",
+    "LAG",
+    "CONSUMER-ID",
+    "HOST",
+    "CLIENT-ID",
+];
+
+/// Minimum widths of the columns of [`DESCRIBE_HEADERS`], as `kafka-consumer-groups.sh` pads them:
+/// the text columns are as wide as their longest value instead.
+const DESCRIBE_MIN_WIDTHS: [usize; 9] = [0, 0, 10, 15, 15, 15, 0, 0, 0];
+
+/// Placeholder of a value that is not known, as `kafka-consumer-groups.sh` prints it.
+const DESCRIBE_MISSING: &str = "-";
+
+/// Format a [`LagTable`] is rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LagTableFormat {
+    /// Aligned columns, for humans.
+    Table,
+
+    /// Array of objects, one per row.
+    Json,
+
+    /// One line per row, with a header.
+    Csv,
+
+    /// One line per partition, in the layout of `kafka-consumer-groups.sh --describe`.
+    ConsumerGroups,
+}
+
+/// Render the lag of the given [`LagSnapshot`], in the given [`LagTableFormat`].
+///
+/// `colored` applies only to [`LagTableFormat::Table`]: it's meant for when writing to a terminal.
+pub fn render(snapshot: &LagSnapshot, format: LagTableFormat, colored: bool) -> io::Result<String> {
+    match format {
+        LagTableFormat::Table => Ok(LagTable::from_snapshot(snapshot).render_table(colored)),
+        LagTableFormat::Json => LagTable::from_snapshot(snapshot).to_json(),
+        LagTableFormat::Csv => LagTable::from_snapshot(snapshot).to_csv(),
+        LagTableFormat::ConsumerGroups => Ok(render_consumer_groups(snapshot)),
+    }
+}
+
+/// Lag of a Consumer Group, aggregated across all the partitions of a Topic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LagTableRow {
+    pub group: Interned,
+    pub topic: Interned,
+
+    /// Amount of partitions of the Topic the Consumer Group has committed offsets for.
+    pub partitions: usize,
+
+    /// Sum of the Offset Lag of the partitions whose lag is known.
+    pub offset_lag: u64,
+
+    /// Max of the Time Lag (milliseconds) of the partitions whose lag is known.
+    pub max_time_lag_ms: i64,
+}
+
+/// Lag of each (Consumer Group, Topic) pair of a [`LagSnapshot`], sorted by Consumer Group then Topic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LagTable {
+    pub rows: Vec<LagTableRow>,
+}
+
+impl LagTable {
+    /// Aggregate the lag of the given [`LagSnapshot`], by Consumer Group and Topic.
+    pub fn from_snapshot(snapshot: &LagSnapshot) -> Self {
+        let mut rows: BTreeMap<(&Interned, &Interned), LagTableRow> = BTreeMap::new();
+
+        for (group, tp, lwo) in snapshot.iter_group_partitions() {
+            let row = rows.entry((group, &tp.topic)).or_insert_with(|| LagTableRow {
+                group: group.clone(),
+                topic: tp.topic.clone(),
+                partitions: 0,
+                offset_lag: 0,
+                max_time_lag_ms: 0,
+            });
+            row.partitions += 1;
+            if let Some(lag) = lwo.lag() {
+                row.offset_lag += lag.offset_lag();
+                row.max_time_lag_ms = row.max_time_lag_ms.max(lag.time_lag().num_milliseconds());
+            }
+        }
+
+        Self {
+            rows: rows.into_values().collect(),
+        }
+    }
+
+    /// Array of the rows, as JSON objects.
+    pub fn to_json(&self) -> io::Result<String> {
+        Ok(serde_json::to_string_pretty(&self.rows)? + "\n")
+    }
+
+    /// The rows as CSV, with a header.
+    pub fn to_csv(&self) -> io::Result<String> {
+        let mut wrt = csv::Writer::from_writer(Vec::new());
+        for row in self.rows.iter() {
+            wrt.serialize(row)?;
+        }
+        let bytes = wrt.into_inner().map_err(|e| e.into_error())?;
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub(crate) fn render_table(&self, colored: bool) -> String {
+        let cells: Vec<[String; 5]> = self
+            .rows
+            .iter()
+            .map(|r| {
+                [
+                    r.group.to_string(),
+                    r.topic.to_string(),
+                    r.partitions.to_string(),
+                    r.offset_lag.to_string(),
+                    format_time_lag(r.max_time_lag_ms),
+                ]
+            })
+            .collect();
+
+        let mut widths = HEADERS.map(str::len);
+        for row in cells.iter() {
+            for (w, cell) in widths.iter_mut().zip(row.iter()) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+
+        let mut out = String::new();
+        let header = HEADERS.map(String::from);
+        write_line(&mut out, &header, &widths, colored.then_some(ANSI_BOLD));
+        for (row, cells) in self.rows.iter().zip(cells.iter()) {
+            write_line(&mut out, cells, &widths, colored.then(|| row_color(row)));
+        }
+        out
+    }
+}
+
+/// Write a line of the table: text columns (group, topic) are left-aligned, numeric ones right-aligned.
+///
+/// Padding is computed before coloring, so that escape codes don't break the alignment.
+fn write_line(out: &mut String, cells: &[String; 5], widths: &[usize; 5], color: Option<&str>) {
+    if let Some(c) = color {
+        out.push_str(c);
+    }
+    for (i, (cell, width)) in cells.iter().zip(widths.iter()).enumerate() {
+        if i > 0 {
+            out.push_str("  ");
+        }
+        let _ = if i < 2 {
+            write!(out, "{cell:<width$}")
+        } else {
+            write!(out, "{cell:>width$}")
+        };
+    }
+    if color.is_some() {
+        out.push_str(ANSI_RESET);
+    }
+    out.push('\n');
+}
+
+/// Render the lag of each (Consumer Group, Topic Partition) pair, in the layout of
+/// `kafka-consumer-groups.sh --describe`.
+///
+/// Like it, each Consumer Group gets its own block, preceded by an empty line: columns are
+/// left-aligned and separated by a space, and values that are not known are printed as `-`.
+fn render_consumer_groups(snapshot: &LagSnapshot) -> String {
+    let available = snapshot.available_offsets();
+
+    let mut blocks: BTreeMap<&Interned, Vec<[String; 9]>> = BTreeMap::new();
+    for (group, tp, lwo) in snapshot.iter_group_partitions() {
+        let missing = || DESCRIBE_MISSING.to_string();
+        let owner = lwo.owner();
+        blocks.entry(group).or_default().push([
+            group.to_string(),
+            tp.topic.to_string(),
+            tp.partition.to_string(),
+            lwo.lag().map_or_else(missing, |l| l.offset().to_string()),
+            available.get(tp).map_or_else(missing, |(_, latest)| latest.to_string()),
+            lwo.lag().map_or_else(missing, |l| l.offset_lag().to_string()),
+            owner.map_or_else(missing, |m| m.id.clone()),
+            owner.map_or_else(missing, |m| m.client_host.clone()),
+            owner.map_or_else(missing, |m| m.client_id.clone()),
+        ]);
+    }
+
+    let mut out = String::new();
+    for lines in blocks.values() {
+        let mut widths = DESCRIBE_HEADERS.map(str::len);
+        for (w, min) in widths.iter_mut().zip(DESCRIBE_MIN_WIDTHS) {
+            *w = (*w).max(min);
+        }
+        for line in lines {
+            for (w, cell) in widths.iter_mut().zip(line.iter()) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+
+        out.push('\n');
+        let header = DESCRIBE_HEADERS.map(String::from);
+        for line in std::iter::once(&header).chain(lines.iter()) {
+            let cells: Vec<String> = line
+                .iter()
+                .zip(widths.iter())
+                .enumerate()
+                // The last column is not padded
+                .map(|(i, (cell, width))| if i < 8 { format!("{cell:<width$}") } else { cell.clone() })
+                .collect();
+            out.push_str(&cells.join(" "));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Color of a row, by its Time Lag: a lagging partition is what needs attention.
+fn row_color(row: &LagTableRow) -> &'static str {
+    let time_lag = Duration::from_millis(row.max_time_lag_ms.max(0) as u64);
+    if row.offset_lag == 0 || time_lag < TIME_LAG_OK {
+        ANSI_GREEN
+    } else if time_lag < TIME_LAG_CRITICAL {
+        ANSI_YELLOW
+    } else {
+        ANSI_RED
+    }
+}
+
+/// Time Lag, as a human-readable duration: precise to the millisecond below a second,
+/// and to the second above it.
+fn format_time_lag(time_lag_ms: i64) -> String {
+    let ms = time_lag_ms.max(0) as u64;
+    let d = if ms < 1000 {
+        Duration::from_millis(ms)
+    } else {
+        Duration::from_secs(ms / 1000)
+    };
+    humantime::format_duration(d).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use chrono::Duration as ChronoDuration;
+
+    use super::*;
+    use crate::kafka_types::{Member, TopicPartition};
+    use crate::lag_register::{GroupWithLag, Lag, LagWithOwner};
+    use crate::partition_offsets::{PartitionOffsetsError, PartitionOffsetsSnapshot};
+
+    fn lwo(offset_lag: u64, time_lag_ms: i64) -> LagWithOwner {
+        LagWithOwner {
+            lag: Some(Lag {
+                offset_lag,
+                time_lag: ChronoDuration::milliseconds(time_lag_ms),
+                ..Default::default()
+            }),
+            owner: None,
+        }
+    }
+
+    fn table() -> LagTable {
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t1".to_string(), 0), lwo(10, 500));
+        gwl.lag_by_topic_partition.insert(TopicPartition::new("t1".to_string(), 1), lwo(5, 90_500));
+        gwl.lag_by_topic_partition
+            .insert(TopicPartition::new("orders".to_string(), 0), LagWithOwner::default());
+
+        let mut snapshot = LagSnapshot::default();
+        snapshot.groups.insert("g".into(), Arc::new(gwl));
+        LagTable::from_snapshot(&snapshot)
+    }
+
+    #[test]
+    fn renders() {
+        let table = table();
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[1].partitions, 2);
+        assert_eq!(table.rows[1].offset_lag, 15);
+        assert_eq!(table.rows[1].max_time_lag_ms, 90_500);
+
+        assert_eq!(
+            table.render_table(false),
+            "GROUP  TOPIC   PARTITIONS  SUM LAG  MAX TIME LAG\n\
+             g      orders           1        0            0s\n\
+             g      t1               2       15        1m 30s\n"
+        );
+        assert_eq!(
+            table.to_csv().unwrap(),
+            "group,topic,partitions,offset_lag,max_time_lag_ms\ng,orders,1,0,0\ng,t1,2,15,90500\n"
+        );
+
+        let colored = table.render_table(true);
+        assert!(colored.starts_with(ANSI_BOLD));
+        assert!(colored.lines().last().unwrap().starts_with(ANSI_RED));
+    }
+
+    #[test]
+    fn renders_consumer_groups() {
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition.insert(
+            TopicPartition::new("orders".to_string(), 0),
+            LagWithOwner {
+                lag: Some(Lag {
+                    offset: 90,
+                    offset_lag: 10,
+                    ..Default::default()
+                }),
+                owner: Some(Member {
+                    id: "consumer-1-abc".to_string(),
+                    client_id: "consumer-1".to_string(),
+                    client_host: "/10.0.0.1".to_string(),
+                    ..Default::default()
+                }),
+            },
+        );
+        gwl.lag_by_topic_partition
+            .insert(TopicPartition::new("orders".to_string(), 1), LagWithOwner::default());
+
+        let mut snapshot = LagSnapshot::default();
+        snapshot.groups.insert("g".into(), Arc::new(gwl));
+        snapshot.partitions = vec![PartitionOffsetsSnapshot {
+            topic_partition: TopicPartition::new("orders".to_string(), 0),
+            earliest_available_offset: Ok(0),
+            latest_available_offset: Ok(100),
+            earliest_tracked_offset: Err(PartitionOffsetsError::LagEstimatorNotReady),
+            latest_tracked_offset: Err(PartitionOffsetsError::LagEstimatorNotReady),
+            produced_records_rate: Err(PartitionOffsetsError::LagEstimatorNotReady),
+        }];
+
+        assert_eq!(
+            render(&snapshot, LagTableFormat::ConsumerGroups, false).unwrap(),
+            "\n\
+             GROUP TOPIC  PARTITION  CURRENT-OFFSET  LOG-END-OFFSET  LAG             CONSUMER-ID    HOST      CLIENT-ID\n\
+             g     orders 0          90              100             10              consumer-1-abc /10.0.0.1 consumer-1\n\
+             g     orders 1          -               -               -               -              -         -\n"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use rdkafka::{
+    admin::{AdminClient, AdminOptions, NewTopic, TopicReplication},
+    client::DefaultClientContext,
+    consumer::{Consumer, ConsumerContext, Rebalance, StreamConsumer},
+    types::RDKafkaErrorCode,
+    ClientConfig, ClientContext,
+};
+use tokio::{task::JoinHandle, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+use super::Leadership;
+
+const CREATE_TOPIC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Configuration of the high-availability mode: see [`LeaderElector`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighAvailabilityConfig {
+    /// Consumer Group (`group.id`) joined by all the instances coordinating leadership.
+    /// Like this service own consumer of `__consumer_offsets`, its lag is not monitored.
+    pub group: String,
+
+    /// Single-partition topic, whose partition gets assigned to the leader.
+    /// Created (compacted) if it doesn't exist.
+    pub topic: String,
+}
+
+/// Consumer context that keeps track of whether the leader election partition is assigned.
+struct ElectionContext {
+    leadership: Arc<Leadership>,
+}
+
+impl ClientContext for ElectionContext {}
+
+impl ConsumerContext for ElectionContext {
+    fn pre_rebalance(&self, rebalance: &Rebalance) {
+        // Step down before the partition is handed over: better no leader, than two
+        if let Rebalance::Revoke(_) = rebalance {
+            self.leadership.set_leader(false);
+        }
+    }
+
+    fn post_rebalance(&self, rebalance: &Rebalance) {
+        match rebalance {
+            Rebalance::Assign(tpl) => {
+                self.leadership.set_leader(tpl.count() > 0);
+            },
+            Rebalance::Revoke(_) => {
+                // No-op: already stepped down
+            },
+            Rebalance::Error(e) => {
+                warn!("Leader election rebalance failed: {e}");
+                self.leadership.set_leader(false);
+            },
+        }
+    }
+}
+
+/// Elects the leader among the instances monitoring the same cluster, via Kafka itself.
+///
+/// All the instances join the same Consumer Group, subscribed to a single-partition topic:
+/// the instance the partition is assigned to is the leader. When the leader leaves the group
+/// (or its session times out), the partition is reassigned to one of the standby instances.
+///
+/// Nothing is ever produced to, or consumed from, the topic: it's only used as a lock.
+pub struct LeaderElector {
+    client_config: ClientConfig,
+    config: HighAvailabilityConfig,
+    leadership: Arc<Leadership>,
+}
+
+impl LeaderElector {
+    /// Create a new [`LeaderElector`].
+    ///
+    /// # Arguments
+    ///
+    /// * `client_config` - Kafka client configuration, used to join the leader election group
+    /// * `config` - Leader election group and topic
+    /// * `leadership` - Where to record whether this instance is the leader
+    pub fn new(
+        client_config: ClientConfig,
+        config: HighAvailabilityConfig,
+        leadership: Arc<Leadership>,
+    ) -> Self {
+        Self {
+            client_config,
+            config,
+            leadership,
+        }
+    }
+
+    /// Spawn a new async task, taking part in the leader election until shutdown.
+    ///
+    /// # Arguments
+    ///
+    /// * `shutdown_token`: A [`CancellationToken`] that, when cancelled, will make this instance
+    ///   leave the election (handing leadership over, if it was the leader).
+    pub fn spawn(self, shutdown_token: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            self.create_topic().await;
+
+            let mut client_config = self.client_config.clone();
+            client_config.set("group.id", &self.config.group).set("enable.auto.commit", "false");
+
+            let consumer: StreamConsumer<ElectionContext> = client_config
+                .create_with_context(ElectionContext {
+                    leadership: self.leadership.clone(),
+                })
+                .expect("Failed to create Consumer Client");
+            if let Err(e) = consumer.subscribe(&[&self.config.topic]) {
+                error!("Failed to subscribe to leader election topic '{}': {e}", self.config.topic);
+            }
+
+            // Consuming (nothing) keeps this instance in the group, and serves the rebalances
+            loop {
+                tokio::select! {
+                    res = consumer.recv() => {
+                        if let Err(e) = res {
+                            warn!("Leader election consumer failed: {e}");
+                        }
+                    },
+                    _ = shutdown_token.cancelled() => {
+                        info!("Shutting down");
+                        break;
+                    },
+                }
+            }
+
+            consumer.unsubscribe();
+            self.leadership.set_leader(false);
+        })
+    }
+
+    /// Create the leader election topic, if it doesn't exist already.
+    ///
+    /// Failing that (e.g. for lack of permissions), the topic must be created beforehand.
+    async fn create_topic(&self) {
+        let admin_client: AdminClient<DefaultClientContext> = match self.client_config.create() {
+            Ok(ac) => ac,
+            Err(e) => {
+                warn!("Failed to allocate Admin Client, to create leader election topic: {e}");
+                return;
+            },
+        };
+
+        let topic = NewTopic::new(&self.config.topic, 1, TopicReplication::Fixed(-1))
+            .set("cleanup.policy", "compact");
+        let opts = AdminOptions::new().operation_timeout(Some(CREATE_TOPIC_TIMEOUT));
+
+        match admin_client.create_topics([&topic], &opts).await {
+            Ok(results) => {
+                for res in results {
+                    match res {
+                        Ok(t) => info!("Created leader election topic '{t}'"),
+                        Err((_, RDKafkaErrorCode::TopicAlreadyExists)) => {},
+                        Err((t, e)) => warn!(
+                            "Failed to create leader election topic '{t}' ({e}): create it, with a single partition"
+                        ),
+                    }
+                }
+            },
+            Err(e) => warn!(
+                "Failed to create leader election topic '{}' ({e}): create it, with a single partition",
+                self.config.topic
+            ),
+        }
+    }
+}
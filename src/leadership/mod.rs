@@ -0,0 +1,41 @@
+//! Active/standby high-availability, across multiple instances monitoring the same cluster.
+//!
+//! The instances coordinate leadership via Kafka itself: they all join the same Consumer Group,
+//! subscribed to a single-partition (compacted) topic, and whichever gets assigned the partition
+//! is the leader. When the leader goes away, the partition gets reassigned to a standby.
+//!
+//! Standby instances keep their registers warm, but neither serve metrics nor publish to sinks
+//! that push lag data elsewhere: this way, push-mode data is not duplicated.
+
+// Inner modules
+mod elector;
+mod sink;
+mod state;
+
+use std::sync::Arc;
+
+use prometheus::Registry;
+use rdkafka::ClientConfig;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+pub use elector::{HighAvailabilityConfig, LeaderElector};
+pub use sink::LeaderOnlySink;
+pub use state::Leadership;
+
+/// Initialize leadership: without a [`HighAvailabilityConfig`], this instance is always the leader.
+pub fn init(
+    admin_client_config: ClientConfig,
+    ha_config: Option<HighAvailabilityConfig>,
+    shutdown_token: CancellationToken,
+    metrics: Arc<Registry>,
+) -> (Arc<Leadership>, Option<JoinHandle<()>>) {
+    let leadership = Arc::new(Leadership::new(ha_config.is_some(), metrics));
+
+    let elector_join = ha_config.map(|hac| {
+        LeaderElector::new(admin_client_config, hac, leadership.clone()).spawn(shutdown_token)
+    });
+
+    debug!("Initialized");
+    (leadership, elector_join)
+}
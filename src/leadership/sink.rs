@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use tokio::time::Duration;
+
+use super::Leadership;
+
+use crate::internals::{LagSink, LagSinkFuture};
+use crate::lag_register::LagSnapshot;
+
+/// A [`LagSink`] that publishes to the wrapped one, only while this instance is the leader.
+///
+/// Meant for the sinks that push lag data elsewhere: while a standby, publishing
+/// is skipped, so that the data is not duplicated.
+pub struct LeaderOnlySink {
+    inner: Arc<dyn LagSink>,
+    leadership: Arc<Leadership>,
+}
+
+impl LeaderOnlySink {
+    pub fn new(inner: Arc<dyn LagSink>, leadership: Arc<Leadership>) -> Self {
+        Self {
+            inner,
+            leadership,
+        }
+    }
+}
+
+impl LagSink for LeaderOnlySink {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        self.inner.publish_interval()
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        if self.leadership.is_leader() {
+            self.inner.publish(snapshot)
+        } else {
+            trace!("Not publishing to '{}' sink: standby", self.inner.name());
+            Box::pin(async { Ok(()) })
+        }
+    }
+}
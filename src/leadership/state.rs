@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use prometheus::{register_int_gauge_with_registry, IntGauge, Registry};
+use tokio::sync::watch;
+
+const MET_LEADER_NAME: &str = "ha_leader";
+const MET_LEADER_HELP: &str =
+    "Whether this instance is the leader (1), or a standby (0), in high-availability mode";
+
+/// Whether this instance is the leader, among the ones monitoring the same cluster.
+///
+/// Unless in high-availability mode, this instance is always the leader.
+pub struct Leadership {
+    ha: bool,
+    leader_sx: watch::Sender<bool>,
+
+    // Prometheus Metrics
+    metric_leader: IntGauge,
+}
+
+impl Leadership {
+    /// Create a new [`Leadership`]: in high-availability mode, it starts as a standby.
+    ///
+    /// # Arguments
+    ///
+    /// * `ha` - Whether in high-availability mode
+    /// * `metrics` - Where to register the metric reporting whether this instance is the leader
+    pub fn new(ha: bool, metrics: Arc<Registry>) -> Self {
+        let metric_leader =
+            register_int_gauge_with_registry!(MET_LEADER_NAME, MET_LEADER_HELP, metrics)
+                .unwrap_or_else(|_| panic!("Failed to create metric: {MET_LEADER_NAME}"));
+        metric_leader.set(i64::from(!ha));
+
+        Self {
+            ha,
+            leader_sx: watch::Sender::new(!ha),
+            metric_leader,
+        }
+    }
+
+    /// Whether in high-availability mode.
+    pub fn is_ha(&self) -> bool {
+        self.ha
+    }
+
+    pub fn is_leader(&self) -> bool {
+        *self.leader_sx.borrow()
+    }
+
+    /// Subscribe to changes of leadership.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.leader_sx.subscribe()
+    }
+
+    /// Record whether this instance is (now) the leader.
+    pub(super) fn set_leader(&self, leader: bool) {
+        if self.leader_sx.send_replace(leader) != leader {
+            if leader {
+                info!("Became the leader: serving metrics and publishing to sinks");
+            } else {
+                info!("Became a standby: no longer serving metrics, nor publishing to sinks");
+            }
+            self.metric_leader.set(i64::from(leader));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn leader_unless_ha() {
+        let standalone = Leadership::new(false, Arc::new(Registry::new()));
+        assert!(standalone.is_leader());
+
+        let ha = Leadership::new(true, Arc::new(Registry::new()));
+        let leader_rx = ha.subscribe();
+        assert!(!ha.is_leader());
+        assert_eq!(ha.metric_leader.get(), 0);
+
+        ha.set_leader(true);
+        assert!(ha.is_leader());
+        assert!(*leader_rx.borrow());
+        assert_eq!(ha.metric_leader.get(), 1);
+    }
+}
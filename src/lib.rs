@@ -0,0 +1,105 @@
+//! # Kommitted
+//!
+//! Measure Kafka Consumer **Offset Lag** _and_ **Time Lag**.
+//!
+//! Besides being a standalone service, Kommitted can be embedded in other Rust services
+//! (e.g. to auto-scale consumers based on their lag): use [`Kommitted::builder`] to configure
+//! an instance, then [`Kommitted::start`] it to get access to its Registers via a [`KommittedHandle`].
+//!
+//! Only the types needed to configure, run and query an instance are exposed: the modules
+//! they come from are internal, and free to change.
+
+#[macro_use]
+extern crate log;
+
+mod alerts;
+mod app;
+mod capabilities;
+mod cert_reload;
+mod cli;
+mod cluster_status;
+mod committed_offsets;
+mod config;
+mod constants;
+mod consumer_groups;
+mod consumer_status;
+mod demo;
+mod external_offsets;
+mod failover;
+mod http;
+mod internals;
+mod kafka_types;
+mod konsumer_offsets_data;
+mod lag_register;
+mod lag_table;
+mod leadership;
+mod log_targets;
+mod logging;
+mod memory_guard;
+mod mirror;
+mod partition_offsets;
+mod preflight;
+mod presets;
+mod preview;
+mod prometheus_metrics;
+mod recording;
+mod report;
+mod service;
+mod sinks;
+mod top;
+mod validation;
+
+// Service
+pub use app::run_cli;
+pub use service::{
+    Kommitted, KommittedBuilder, KommittedError, KommittedHandle, KommittedResult, KommittedState,
+};
+
+// Configuration
+pub use alerts::{AlertRule, AlertsConfig, WebhookConfig, WebhookKind};
+pub use cli::KVPair;
+pub use committed_offsets::OffsetsSource;
+pub use config::ConfigError;
+pub use consumer_groups::{GroupExcludes, GroupLabels, GroupShard};
+pub use internals::{Clock, Jitter, ManualClock, PersistenceConfig, SystemClock};
+pub use konsumer_offsets_data::{
+    IsolationLevel, KonsumerOffsetsConsumerConfig, KonsumerOffsetsStart,
+};
+pub use lag_register::{LagHistoryConfig, LagTrendConfig};
+pub use leadership::HighAvailabilityConfig;
+pub use mirror::MirrorConfig;
+pub use partition_offsets::{
+    HistoryRetention, LinearInterpolation, NearestPoint, RateExtrapolation, TimeLagStrategy,
+    WatermarksPolling,
+};
+pub use presets::Preset;
+pub use prometheus_metrics::MetricsGranularity;
+pub use recording::RecordingConfig;
+#[cfg(feature = "cloudwatch")]
+pub use sinks::CloudWatchConfig;
+#[cfg(feature = "otlp")]
+pub use sinks::OtlpConfig;
+pub use sinks::{
+    DumpConfig, DumpFormat, GraphiteConfig, GroundTruthConfig, InfluxConfig, KafkaTopicConfig,
+    RecordSizesConfig, RemoteWriteConfig, StatsdConfig, StatsdFlavor,
+};
+
+// Extension points
+pub use external_offsets::{ExternalOffset, ExternalOffsetsError, ExternalOffsetsSource};
+pub use internals::{LagSink, LagSinkError, LagSinkFuture};
+
+// Registers, and what they hold
+pub use cluster_status::{ClusterStatus, ClusterStatusRegister, TopologyChange};
+pub use internals::{AwaitableError, Subsystem, Subsystems};
+pub use kafka_types::{
+    Broker, Group, GroupProtocol, Interned, Member, PartitionStatus, TopicConfig, TopicPartition,
+    TopicPartitionsStatus,
+};
+pub use lag_register::{
+    ClusterLag, GroupTopicLag, GroupWithLag, Lag, LagEvent, LagRegister, LagSnapshot, LagWithOwner,
+};
+pub use leadership::Leadership;
+pub use partition_offsets::{
+    PartitionOffsetsError, PartitionOffsetsRegister, PartitionOffsetsResult,
+    PartitionOffsetsSnapshot, TrackedOffset,
+};
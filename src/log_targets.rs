@@ -0,0 +1,237 @@
+//! Where to write logs to, besides `stderr`: a file (rotated), or the local syslog (and so journald).
+//!
+//! For bare-metal deployments, where nothing captures the output of the service.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+/// Sockets the local syslog daemon listens on (journald listens on the first, too).
+const SYSLOG_SOCKETS: [&str; 2] = ["/dev/log", "/var/run/syslog"];
+
+/// Syslog facility of the messages (i.e. `daemon`).
+const SYSLOG_FACILITY_DAEMON: u8 = 3;
+
+/// How often to rotate the log file, regardless of its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogRotation {
+    /// Only rotate when it grows too big.
+    Never,
+
+    /// At the start of every hour (UTC).
+    Hourly,
+
+    /// At the start of every day (UTC).
+    Daily,
+}
+
+impl LogRotation {
+    /// Identifier of the period the given time falls into: when it changes, it's time to rotate.
+    fn period(&self, at: DateTime<Utc>) -> i64 {
+        match self {
+            LogRotation::Never => 0,
+            LogRotation::Hourly => at.timestamp().div_euclid(3_600),
+            LogRotation::Daily => at.timestamp().div_euclid(86_400),
+        }
+    }
+}
+
+/// Configuration of [`RotatingFile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFileConfig {
+    /// File to write to: rotated files are next to it, suffixed by `.1`, `.2` and so on (most recent first).
+    pub path: PathBuf,
+
+    pub rotation: LogRotation,
+
+    /// Size the file is rotated at, before it grows any bigger.
+    pub max_size: Option<u64>,
+
+    /// How many rotated files to keep: the oldest are removed.
+    pub keep: usize,
+}
+
+/// Where to write logs to.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LogTarget {
+    #[default]
+    Stderr,
+
+    File(LogFileConfig),
+
+    /// The local syslog daemon (or journald).
+    Syslog,
+}
+
+/// A log file, rotated when it's time to (see [`LogRotation`]) or when it grows too big.
+///
+/// Each write is expected to be a whole log record, so that records are never split across files.
+pub struct RotatingFile {
+    config: LogFileConfig,
+    file: File,
+    size: u64,
+    period: i64,
+}
+
+impl RotatingFile {
+    /// Open (or create) the log file, appending to it.
+    pub fn open(config: LogFileConfig) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+        let size = file.metadata()?.len();
+        let period = config.rotation.period(Utc::now());
+        Ok(Self {
+            config,
+            file,
+            size,
+            period,
+        })
+    }
+
+    fn should_rotate(&self, at: DateTime<Utc>, incoming: usize) -> bool {
+        let too_big = self
+            .config
+            .max_size
+            .is_some_and(|max| self.size > 0 && self.size + incoming as u64 > max);
+        too_big || self.config.rotation.period(at) != self.period
+    }
+
+    /// Shift the rotated files by one (removing the oldest), and start over with an empty file.
+    fn rotate(&mut self, at: DateTime<Utc>) -> io::Result<()> {
+        self.file.flush()?;
+        if self.config.keep == 0 {
+            fs::remove_file(&self.config.path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(&self.config.path, self.config.keep));
+            for i in (1..self.config.keep).rev() {
+                let from = rotated_path(&self.config.path, i);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.config.path, i + 1))?;
+                }
+            }
+            fs::rename(&self.config.path, rotated_path(&self.config.path, 1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.config.path)?;
+        self.size = 0;
+        self.period = self.config.rotation.period(at);
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = Utc::now();
+        if self.should_rotate(now, buf.len()) {
+            self.rotate(now)?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{index}"));
+    PathBuf::from(rotated)
+}
+
+/// Sends each write, as a message, to the local syslog daemon (or journald).
+///
+/// Each write is expected to be a whole log record, already formatted as a syslog message
+/// (see [`syslog_priority`]).
+pub struct SyslogWriter {
+    socket: UnixDatagram,
+}
+
+impl SyslogWriter {
+    /// Connect to the first of the well-known sockets the local syslog daemon listens on.
+    pub fn connect() -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, "no syslog socket found");
+        for path in SYSLOG_SOCKETS {
+            match socket.connect(path) {
+                Ok(()) => {
+                    return Ok(Self {
+                        socket,
+                    })
+                },
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+impl Write for SyslogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let message = buf.strip_suffix(b"\n").unwrap_or(buf);
+        self.socket.send(message)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Syslog priority (`PRI`) of a message logged at the given level.
+pub fn syslog_priority(level: log::Level) -> u8 {
+    let severity = match level {
+        log::Level::Error => 3,
+        log::Level::Warn => 4,
+        log::Level::Info => 6,
+        log::Level::Debug | log::Level::Trace => 7,
+    };
+    SYSLOG_FACILITY_DAEMON * 8 + severity
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rotates_by_size_keeping_the_most_recent() {
+        let dir = std::env::temp_dir().join(format!("kommitted-logs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("kommitted.log");
+
+        let mut file = RotatingFile::open(LogFileConfig {
+            path: path.clone(),
+            rotation: LogRotation::Never,
+            max_size: Some(10),
+            keep: 2,
+        })
+        .unwrap();
+        for record in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(record.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 2)).unwrap(), "second\n");
+        assert!(!rotated_path(&path, 3).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_by_period() {
+        let at = DateTime::<Utc>::from_timestamp(86_400 * 2 + 3_600 * 5, 0).unwrap();
+        let later = at + chrono::Duration::minutes(90);
+        assert_ne!(LogRotation::Hourly.period(at), LogRotation::Hourly.period(later));
+        assert_eq!(LogRotation::Daily.period(at), LogRotation::Daily.period(later));
+        assert_eq!(LogRotation::Never.period(at), LogRotation::Never.period(later));
+
+        assert_eq!(syslog_priority(log::Level::Error), 27);
+        assert_eq!(syslog_priority(log::Level::Info), 30);
+    }
+}
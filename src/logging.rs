@@ -1,10 +1,20 @@
+use std::io::{self, Write};
+
+use env_logger::{Target, WriteStyle};
+
+use crate::http::current_request_id;
+use crate::log_targets::{syslog_priority, LogTarget, RotatingFile, SyslogWriter};
+
 pub const LOG_FILTER_ENV_VAR: &str = "KOMMITTED_LOG";
 
 /// Log level will be configured based on the given `verbosity_level`.
 ///
 /// If the env var `KOMMITTED_LOG` is set, that will take precedence and configuration
 /// will be based on the rules described [here](https://docs.rs/env_logger/latest/env_logger/#enabling-logging).
-pub fn init(verbosity_level: i8) {
+///
+/// Logs are written to the given [`LogTarget`]: fails if it can't be opened (or connected to).
+/// Those emitted while serving an HTTP request include its identifier (i.e. `request_id=...`).
+pub fn init(verbosity_level: i8, target: LogTarget) -> io::Result<()> {
     let default_log_level = match verbosity_level {
         i8::MIN..=-2 => "OFF",
         -1 => log::Level::Error.as_str(),
@@ -16,7 +26,50 @@ pub fn init(verbosity_level: i8) {
 
     let logger_env = env_logger::Env::default().filter_or(LOG_FILTER_ENV_VAR, default_log_level);
     let mut logger_builder = env_logger::Builder::from_env(logger_env);
+    logger_builder.format(|buf, record| {
+        let level_style = buf.default_level_style(record.level());
+        writeln!(
+            buf,
+            "[{} {level_style}{:<5}{level_style:#} {}{}] {}",
+            buf.timestamp(),
+            record.level(),
+            record.target(),
+            request_id_field(),
+            record.args()
+        )
+    });
+    match target {
+        LogTarget::Stderr => {},
+        LogTarget::File(config) => {
+            logger_builder
+                .target(Target::Pipe(Box::new(RotatingFile::open(config)?)))
+                .write_style(WriteStyle::Never);
+        },
+        LogTarget::Syslog => {
+            // Syslog timestamps messages on its own
+            let tag = format!("{}[{}]", env!("CARGO_PKG_NAME"), std::process::id());
+            logger_builder
+                .target(Target::Pipe(Box::new(SyslogWriter::connect()?)))
+                .write_style(WriteStyle::Never)
+                .format(move |buf, record| {
+                    writeln!(
+                        buf,
+                        "<{}>{tag}: {}{} {}",
+                        syslog_priority(record.level()),
+                        record.target(),
+                        request_id_field(),
+                        record.args()
+                    )
+                });
+        },
+    }
     logger_builder.init();
 
     info!("Configured log level: {}", log::max_level().as_str());
+    Ok(())
+}
+
+/// Identifier of the HTTP request being served, as a field to append to the log line header.
+fn request_id_field() -> String {
+    current_request_id().map(|id| format!(" request_id={id}")).unwrap_or_default()
 }
@@ -0,0 +1,253 @@
+//! Ceiling to the memory taken by the lag data, shedding detail instead of running out of memory.
+//!
+//! The memory taken by the Registers, and by the rendering of the metrics, is periodically
+//! estimated. When over the budget, detail is shed, one step per check:
+//!
+//! 1. The offsets history of the quietest Topics, down to the minimum needed to estimate
+//! 2. Then, the granularity of the lag metrics, one level at a time
+//!
+//! What was shed is logged, and counted by the `memory_shed_total` metric. Shedding is not
+//! reverted: once memory got tight, it's likely to again.
+
+use std::sync::Arc;
+
+use prometheus::{
+    register_int_counter_vec_with_registry, register_int_gauge_with_registry, IntCounterVec,
+    IntGauge, Registry,
+};
+use tokio::{task::JoinHandle, time::interval, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+use crate::kafka_types::Interned;
+use crate::lag_register::LagRegister;
+use crate::partition_offsets::PartitionOffsetsRegister;
+use crate::prometheus_metrics::{MetricsGranularity, PrometheusSink, LABEL_ACTION};
+
+/// How often the memory taken is estimated.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Once over budget, shed down to this ratio of it, so that it isn't exceeded again right away.
+const SHED_TARGET_RATIO: f64 = 0.9;
+
+const MET_ESTIMATED_NAME: &str = "memory_estimated_bytes";
+const MET_ESTIMATED_HELP: &str =
+    "Estimated memory (bytes) taken by the registers and the rendering of the metrics";
+const MET_SHED_NAME: &str = "memory_shed_total";
+const MET_SHED_HELP: &str =
+    "Detail shed to stay within the memory budget: topics whose offsets history was shed, and metrics granularity levels coarsened";
+
+const ACTION_OFFSETS_HISTORY: &str = "offsets_history";
+const ACTION_METRICS_GRANULARITY: &str = "metrics_granularity";
+
+/// A step of detail shed by [`shed_step`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ShedStep {
+    /// The offsets history of these Topics (the quietest) was shed.
+    OffsetsHistory(Vec<Interned>),
+
+    /// The granularity of the lag metrics was coarsened to this level.
+    MetricsGranularity(MetricsGranularity),
+
+    /// There was nothing left to shed.
+    Exhausted,
+}
+
+/// Keeps the memory taken by the lag data within a budget: see the [module](self) documentation.
+pub struct MemoryGuard {
+    max_memory: usize,
+    po_reg: Arc<PartitionOffsetsRegister>,
+    lag_reg: Arc<LagRegister>,
+    prom_sink: Arc<PrometheusSink>,
+
+    // Prometheus Metrics
+    metric_estimated: IntGauge,
+    metric_shed: IntCounterVec,
+}
+
+impl MemoryGuard {
+    /// Create a new [`MemoryGuard`].
+    ///
+    /// # Arguments
+    ///
+    /// * `max_memory` - Memory budget (bytes)
+    /// * `po_reg` - Whose offsets history can be shed
+    /// * `lag_reg` - Whose memory counts towards the budget
+    /// * `prom_sink` - Whose metrics granularity can be coarsened
+    /// * `metrics` - Where to register the `memory_*` metrics
+    pub fn new(
+        max_memory: usize,
+        po_reg: Arc<PartitionOffsetsRegister>,
+        lag_reg: Arc<LagRegister>,
+        prom_sink: Arc<PrometheusSink>,
+        metrics: Arc<Registry>,
+    ) -> Self {
+        Self {
+            max_memory,
+            po_reg,
+            lag_reg,
+            prom_sink,
+            metric_estimated: register_int_gauge_with_registry!(
+                MET_ESTIMATED_NAME,
+                MET_ESTIMATED_HELP,
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_ESTIMATED_NAME}")),
+            metric_shed: register_int_counter_vec_with_registry!(
+                MET_SHED_NAME,
+                MET_SHED_HELP,
+                &[LABEL_ACTION],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_SHED_NAME}")),
+        }
+    }
+
+    /// Spawn the task that periodically checks the memory taken, until `shutdown_token` is cancelled.
+    pub fn spawn(self, shutdown_token: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = interval(CHECK_INTERVAL);
+            let mut exhausted = false;
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let estimated = self.estimate_memory().await;
+                        self.metric_estimated.set(estimated as i64);
+                        trace!("Estimated memory: {estimated} bytes (budget: {} bytes)", self.max_memory);
+
+                        if estimated > self.max_memory && !exhausted {
+                            exhausted = !self.shed(estimated).await;
+                        }
+                    },
+                    _ = shutdown_token.cancelled() => {
+                        info!("Shutting down");
+                        break;
+                    },
+                }
+            }
+        })
+    }
+
+    async fn estimate_memory(&self) -> usize {
+        self.po_reg.estimate_memory().await
+            + self.lag_reg.estimate_memory().await
+            + self.prom_sink.estimate_memory().await
+    }
+
+    /// Shed one step of detail: see the [module](self) documentation.
+    ///
+    /// Returns `false` if there was nothing left to shed.
+    async fn shed(&self, estimated: usize) -> bool {
+        let target = (self.max_memory as f64 * SHED_TARGET_RATIO) as usize;
+
+        match shed_step(&self.po_reg, &self.prom_sink, estimated - target).await {
+            ShedStep::OffsetsHistory(topics) => {
+                warn!(
+                    "Estimated memory ({estimated} bytes) over budget ({} bytes): shed offsets history of the {} quietest topics: {}",
+                    self.max_memory,
+                    topics.len(),
+                    topics.iter().map(|t| t.as_str()).collect::<Vec<&str>>().join(", ")
+                );
+                self.metric_shed
+                    .with_label_values(&[ACTION_OFFSETS_HISTORY])
+                    .inc_by(topics.len() as u64);
+                true
+            },
+            ShedStep::MetricsGranularity(granularity) => {
+                warn!(
+                    "Estimated memory ({estimated} bytes) over budget ({} bytes): coarsened metrics granularity to '{granularity:?}'",
+                    self.max_memory
+                );
+                self.metric_shed.with_label_values(&[ACTION_METRICS_GRANULARITY]).inc();
+                true
+            },
+            ShedStep::Exhausted => {
+                error!(
+                    "Estimated memory ({estimated} bytes) over budget ({} bytes), with nothing left to shed: raise '--max-memory-mb'",
+                    self.max_memory
+                );
+                false
+            },
+        }
+    }
+}
+
+/// Shed the next step of detail, to free (at least) `bytes`: the offsets history of the quietest
+/// Topics, until there is none left to shed, then the granularity of the lag metrics.
+async fn shed_step(
+    po_reg: &PartitionOffsetsRegister,
+    prom_sink: &PrometheusSink,
+    bytes: usize,
+) -> ShedStep {
+    let topics = po_reg.shed_history(bytes).await;
+    if !topics.is_empty() {
+        return ShedStep::OffsetsHistory(topics);
+    }
+
+    match prom_sink.coarsen() {
+        Some(granularity) => ShedStep::MetricsGranularity(granularity),
+        None => ShedStep::Exhausted,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{TimeDelta, Utc};
+    use tokio::sync::{broadcast, mpsc};
+
+    use crate::cluster_status::ClusterStatusRegister;
+    use crate::partition_offsets::{HistoryRetention, LinearInterpolation, PartitionOffset};
+
+    #[tokio::test]
+    async fn sheds_quietest_topics_history_then_metrics_granularity() {
+        let (_, cs_rx) = mpsc::channel(1);
+        let (po_sx, po_rx) = mpsc::channel(1);
+        let (_, topology_rx) = broadcast::channel(1);
+        let po_reg = PartitionOffsetsRegister::new(
+            po_rx,
+            topology_rx,
+            100,
+            HistoryRetention::default(),
+            Arc::new(LinearInterpolation),
+            std::time::Duration::from_secs(60),
+            1_f64,
+            Arc::new(ClusterStatusRegister::new(None, cs_rx, Arc::new(Registry::new()))),
+            Arc::new(Registry::new()),
+        );
+        let prom_sink = PrometheusSink::new();
+
+        // "busy" produces 1000 offsets per second, "quiet" only 10
+        let mut changes = po_reg.subscribe_changes();
+        let started_at = Utc::now();
+        for (topic, rate) in [("busy", 1000), ("quiet", 10)] {
+            for secs in 0..2 {
+                let po = PartitionOffset {
+                    topic: topic.into(),
+                    partition: 0,
+                    earliest_offset: 0,
+                    latest_offset: rate * secs,
+                    read_datetime: started_at + TimeDelta::seconds(secs as i64),
+                };
+                po_sx.send(po).await.unwrap();
+                changes.changed().await.unwrap();
+            }
+        }
+
+        let mut steps = Vec::new();
+        for _ in 0..5 {
+            steps.push(shed_step(&po_reg, &prom_sink, 1).await);
+        }
+        assert_eq!(
+            steps,
+            vec![
+                ShedStep::OffsetsHistory(vec!["quiet".into()]),
+                ShedStep::OffsetsHistory(vec!["busy".into()]),
+                ShedStep::MetricsGranularity(MetricsGranularity::Topic),
+                ShedStep::MetricsGranularity(MetricsGranularity::Group),
+                ShedStep::Exhausted,
+            ]
+        );
+        assert_eq!(prom_sink.granularity(), MetricsGranularity::Group);
+    }
+}
@@ -0,0 +1,136 @@
+use rdkafka::{
+    config::RDKafkaLogLevel,
+    consumer::{Consumer, StreamConsumer},
+    error::{KafkaError, KafkaResult},
+    ClientConfig, Message, Offset, TopicPartitionList,
+};
+use tokio::{sync::mpsc, task::JoinHandle, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+use super::offset_sync::OffsetSync;
+
+use crate::constants::KOMMITTED_MIRROR_OFFSET_SYNCS_CONSUMER;
+use crate::internals::Emitter;
+
+const CHANNEL_SIZE: usize = 10_000;
+
+const FETCH_METADATA_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Configuration of the MirrorMaker 2 replication lag: see [`OffsetSyncsEmitter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirrorConfig {
+    /// Offset-syncs topic written by MirrorMaker 2 (i.e. `mm2-offset-syncs.<target>.internal`).
+    ///
+    /// It has to be in the monitored cluster: the source of the replication.
+    pub offset_syncs_topic: String,
+}
+
+/// Emits [`OffsetSync`]s via a provided [`mpsc::channel`].
+///
+/// It wraps a Kafka Client, that self-assigns all the partitions of the MirrorMaker 2
+/// offset-syncs topic, consumes them from the earliest offsets, and emits its records parsed
+/// into [`OffsetSync`]. Records that fail to be parsed are skipped.
+///
+/// It shuts down when the provided [`CancellationToken`] is cancelled.
+pub struct OffsetSyncsEmitter {
+    client_config: ClientConfig,
+    config: MirrorConfig,
+}
+
+impl OffsetSyncsEmitter {
+    /// Create a new [`OffsetSyncsEmitter`].
+    ///
+    /// # Arguments
+    ///
+    /// * `client_config` - Kafka client configuration, used to consume the offset-syncs topic
+    /// * `config` - Where MirrorMaker 2 writes its offset syncs
+    pub fn new(client_config: ClientConfig, config: MirrorConfig) -> Self {
+        Self {
+            client_config,
+            config,
+        }
+    }
+
+    /// Self-assign all partitions of `topic`, from the earliest offsets.
+    fn assign_all_partitions(consumer: &StreamConsumer, topic: &str) -> KafkaResult<usize> {
+        let meta = consumer.fetch_metadata(Some(topic), FETCH_METADATA_TIMEOUT)?;
+        let topic_meta = meta
+            .topics()
+            .first()
+            .filter(|t| t.error().is_none() && !t.partitions().is_empty())
+            .ok_or(KafkaError::Subscription(format!("Topic '{topic}' not found")))?;
+
+        let mut assignment = TopicPartitionList::with_capacity(topic_meta.partitions().len());
+        for p in topic_meta.partitions() {
+            assignment.add_partition_offset(topic, p.id(), Offset::Beginning)?;
+        }
+        consumer.assign(&assignment)?;
+
+        Ok(assignment.count())
+    }
+}
+
+impl Emitter for OffsetSyncsEmitter {
+    type Emitted = OffsetSync;
+
+    /// Spawn a new async task to run the business logic of this struct.
+    ///
+    /// When this emitter gets spawned, it returns a [`mpsc::Receiver`] for [`OffsetSync`],
+    /// and a [`JoinHandle`] to help join on the task spawned internally.
+    ///
+    /// # Arguments
+    ///
+    /// * `shutdown_token`: A [`CancellationToken`] that, when cancelled, will make the internal loop terminate.
+    fn spawn(
+        &self,
+        shutdown_token: CancellationToken,
+    ) -> (mpsc::Receiver<Self::Emitted>, JoinHandle<()>) {
+        let mut client_config = self.client_config.clone();
+        client_config
+            .set("group.id", KOMMITTED_MIRROR_OFFSET_SYNCS_CONSUMER)
+            .set("enable.auto.commit", "false")
+            .set("enable.partition.eof", "false")
+            .set_log_level(RDKafkaLogLevel::Warning);
+        let consumer: StreamConsumer =
+            client_config.create().expect("Failed to create Consumer Client");
+
+        let (sx, rx) = mpsc::channel::<OffsetSync>(CHANNEL_SIZE);
+        let topic = self.config.offset_syncs_topic.clone();
+
+        let join_handle = tokio::spawn(async move {
+            match Self::assign_all_partitions(&consumer, &topic) {
+                Ok(count) => info!("(Self) Assigned {count} partitions of '{topic}'"),
+                Err(e) => {
+                    error!("Failed to (self) assign '{topic}': no replication lag will be reported: {e}");
+                    shutdown_token.cancelled().await;
+                    info!("Shutting down");
+                    return;
+                },
+            }
+
+            loop {
+                tokio::select! {
+                    r_msg = consumer.recv() => match r_msg {
+                        Ok(m) => match OffsetSync::try_from_bytes(m.key(), m.payload()) {
+                            Ok(os) => {
+                                if let Err(e) = Self::emit(&sx, os).await {
+                                    error!("Failed to emit {}: {e}", std::any::type_name::<OffsetSync>());
+                                }
+                            },
+                            Err(e) => {
+                                debug!("Failed to parse record {}:{}@{}: {e}", topic, m.partition(), m.offset());
+                            },
+                        },
+                        Err(e) => error!("Failed to consume '{topic}': {e}"),
+                    },
+                    _ = shutdown_token.cancelled() => {
+                        info!("Shutting down");
+                        break;
+                    },
+                }
+            }
+        });
+
+        (rx, join_handle)
+    }
+}
@@ -0,0 +1,40 @@
+//! Replication lag of [MirrorMaker 2](https://cwiki.apache.org/confluence/display/KAFKA/KIP-382%3A+MirrorMaker+2.0).
+//!
+//! MirrorMaker 2 periodically records, in its offset-syncs topic, which offset of each source
+//! Topic Partition was replicated at which offset of the target cluster. Consuming it, and
+//! comparing against the latest offsets of the source Topic Partitions, gives the replication lag.
+
+// Inner modules
+mod emitter;
+mod offset_sync;
+mod sink;
+
+// Exports
+pub use emitter::{MirrorConfig, OffsetSyncsEmitter};
+pub use sink::MirrorReplicationSink;
+
+// Imports
+use std::sync::Arc;
+
+use prometheus::Registry;
+use rdkafka::ClientConfig;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::internals::Emitter;
+use crate::partition_offsets::PartitionOffsetsRegister;
+
+pub fn init(
+    admin_client_config: ClientConfig,
+    config: MirrorConfig,
+    po_reg: Arc<PartitionOffsetsRegister>,
+    shutdown_token: CancellationToken,
+    metrics: Arc<Registry>,
+) -> (MirrorReplicationSink, JoinHandle<()>) {
+    let (os_rx, ose_join) =
+        OffsetSyncsEmitter::new(admin_client_config, config).spawn(shutdown_token);
+    let mirror_sink = MirrorReplicationSink::new(os_rx, po_reg, metrics);
+
+    debug!("Initialized");
+    (mirror_sink, ose_join)
+}
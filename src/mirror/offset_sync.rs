@@ -0,0 +1,103 @@
+use thiserror::Error;
+
+use crate::kafka_types::{Interned, TopicPartition};
+
+/// Record of the MirrorMaker 2 offset-syncs topic (i.e. `mm2-offset-syncs.<target>.internal`).
+///
+/// It maps an offset of a source Topic Partition, to the offset it was replicated at in
+/// the target cluster. MirrorMaker 2 writes one every time the two drift apart, or after
+/// `offset.lag.max` records have been replicated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetSync {
+    /// Source Topic Partition.
+    pub topic_partition: TopicPartition,
+
+    /// Offset of the source Topic Partition.
+    pub upstream_offset: i64,
+
+    /// Offset the `upstream_offset` was replicated at, in the target cluster.
+    pub downstream_offset: i64,
+}
+
+/// Possible errors parsing an [`OffsetSync`].
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum OffsetSyncError {
+    #[error("Missing key")]
+    KeyMissing,
+
+    #[error("Missing payload (tombstone)")]
+    PayloadMissing,
+
+    #[error("Truncated bytes: expected at least {0}, got {1}")]
+    Truncated(usize, usize),
+
+    #[error("Topic is not valid UTF-8")]
+    InvalidTopic,
+}
+
+impl OffsetSync {
+    /// Parse an [`OffsetSync`] from the key and payload of a record.
+    ///
+    /// Both are encoded as Kafka protocol structs, with no version:
+    /// the key as `(topic: STRING, partition: INT32)`, the payload as
+    /// `(upstream_offset: INT64, downstream_offset: INT64)`.
+    pub fn try_from_bytes(
+        key: Option<&[u8]>,
+        payload: Option<&[u8]>,
+    ) -> Result<Self, OffsetSyncError> {
+        let key = key.ok_or(OffsetSyncError::KeyMissing)?;
+        let payload = payload.ok_or(OffsetSyncError::PayloadMissing)?;
+
+        let topic_len = i16::from_be_bytes(take::<2>(key, 0)?).max(0) as usize;
+        let topic = key
+            .get(2..2 + topic_len)
+            .ok_or(OffsetSyncError::Truncated(2 + topic_len, key.len()))?;
+        let topic = std::str::from_utf8(topic).map_err(|_| OffsetSyncError::InvalidTopic)?;
+        let partition = i32::from_be_bytes(take::<4>(key, 2 + topic_len)?);
+
+        Ok(Self {
+            topic_partition: TopicPartition::new(Interned::new(topic), partition as u32),
+            upstream_offset: i64::from_be_bytes(take::<8>(payload, 0)?),
+            downstream_offset: i64::from_be_bytes(take::<8>(payload, 8)?),
+        })
+    }
+}
+
+/// Take `N` bytes from `bytes`, starting at `at`.
+fn take<const N: usize>(bytes: &[u8], at: usize) -> Result<[u8; N], OffsetSyncError> {
+    bytes
+        .get(at..at + N)
+        .and_then(|b| b.try_into().ok())
+        .ok_or(OffsetSyncError::Truncated(at + N, bytes.len()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_offset_sync() {
+        let mut key = vec![0, 6];
+        key.extend_from_slice(b"events");
+        key.extend_from_slice(&3_i32.to_be_bytes());
+        let mut payload = 1200_i64.to_be_bytes().to_vec();
+        payload.extend_from_slice(&1100_i64.to_be_bytes());
+
+        assert_eq!(
+            OffsetSync::try_from_bytes(Some(&key), Some(&payload)),
+            Ok(OffsetSync {
+                topic_partition: TopicPartition::new("events", 3),
+                upstream_offset: 1200,
+                downstream_offset: 1100,
+            })
+        );
+        assert_eq!(
+            OffsetSync::try_from_bytes(Some(&key[..8]), Some(&payload)),
+            Err(OffsetSyncError::Truncated(12, 8))
+        );
+        assert_eq!(
+            OffsetSync::try_from_bytes(Some(&key), None),
+            Err(OffsetSyncError::PayloadMissing)
+        );
+    }
+}
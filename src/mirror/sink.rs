@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use prometheus::{register_int_gauge_vec_with_registry, IntGaugeVec, Registry};
+use tokio::sync::{mpsc::Receiver, RwLock};
+
+use super::offset_sync::OffsetSync;
+
+use crate::internals::{LagSink, LagSinkFuture};
+use crate::kafka_types::TopicPartition;
+use crate::lag_register::LagSnapshot;
+use crate::partition_offsets::PartitionOffsetsRegister;
+use crate::prometheus_metrics::{LABEL_PARTITION, LABEL_TOPIC};
+
+const MET_LAG_NAME: &str = "mirror_replication_offset_lag";
+const MET_LAG_HELP: &str =
+    "Offset lag of the MirrorMaker 2 replication of the topic partition, as of its latest offset sync";
+
+/// A [`LagSink`] that exports the replication lag of MirrorMaker 2.
+///
+/// It keeps the latest [`OffsetSync`] of each source Topic Partition: the replication lag is
+/// how far the latest offset of the Topic Partition is from the `upstream_offset` of it.
+/// As MirrorMaker 2 writes an offset sync only every `offset.lag.max` records (by default),
+/// the lag is an upper bound, off by up to that many records.
+///
+/// At every [`LagSnapshot`], the lag is recomputed against the [`PartitionOffsetsRegister`],
+/// as the latest offsets keep moving even when no new [`OffsetSync`] is received.
+pub struct MirrorReplicationSink {
+    upstream_offsets: Arc<RwLock<HashMap<TopicPartition, i64>>>,
+    po_reg: Arc<PartitionOffsetsRegister>,
+    metric_lag: IntGaugeVec,
+}
+
+impl MirrorReplicationSink {
+    /// Create a new [`MirrorReplicationSink`].
+    ///
+    /// # Arguments
+    ///
+    /// * `rx` - Channel [`Receiver`] for [`OffsetSync`]
+    /// * `po_reg` - Where to get the latest offsets of the source Topic Partitions from
+    /// * `metrics` - Where to register the `mirror_replication_offset_lag` metric
+    pub fn new(
+        mut rx: Receiver<OffsetSync>,
+        po_reg: Arc<PartitionOffsetsRegister>,
+        metrics: Arc<Registry>,
+    ) -> Self {
+        let sink = Self {
+            upstream_offsets: Arc::new(RwLock::new(HashMap::new())),
+            po_reg,
+            metric_lag: register_int_gauge_vec_with_registry!(
+                MET_LAG_NAME,
+                MET_LAG_HELP,
+                &[LABEL_TOPIC, LABEL_PARTITION],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_LAG_NAME}")),
+        };
+
+        // Terminates once the `Sender` is dropped
+        let upstream_offsets = sink.upstream_offsets.clone();
+        tokio::spawn(async move {
+            while let Some(os) = rx.recv().await {
+                trace!(
+                    "Offset sync of '{}': {} -> {}",
+                    os.topic_partition,
+                    os.upstream_offset,
+                    os.downstream_offset
+                );
+                upstream_offsets.write().await.insert(os.topic_partition, os.upstream_offset);
+            }
+        });
+
+        sink
+    }
+}
+
+impl LagSink for MirrorReplicationSink {
+    fn name(&self) -> &'static str {
+        "mirror_replication"
+    }
+
+    fn publish<'a>(&'a self, _snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let upstream_offsets: Vec<(TopicPartition, i64)> =
+                self.upstream_offsets.read().await.iter().map(|(tp, o)| (tp.clone(), *o)).collect();
+
+            for (tp, upstream_offset) in upstream_offsets {
+                let partition = tp.partition.to_string();
+                match self.po_reg.get_latest_available_offset(&tp).await {
+                    Ok(latest) => {
+                        let lag = (latest as i64 - upstream_offset).max(0);
+                        self.metric_lag.with_label_values(&[&tp.topic, &partition]).set(lag);
+                    },
+                    Err(e) => {
+                        // Not tracked (anymore), e.g. the Topic Partition was removed
+                        trace!("No replication lag of '{tp}': {e}");
+                        let _ = self.metric_lag.remove_label_values(&[&tp.topic, &partition]);
+                    },
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
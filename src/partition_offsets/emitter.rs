@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
@@ -5,35 +6,44 @@ use prometheus::{
     register_histogram_vec_with_registry, register_int_gauge_with_registry, HistogramVec, IntGauge,
     Registry,
 };
-use rdkafka::{admin::AdminClient, client::DefaultClientContext, ClientConfig};
+use rdkafka::{
+    consumer::{BaseConsumer, Consumer, ConsumerContext},
+    error::KafkaResult,
+    ClientConfig, Offset, TopicPartitionList,
+};
+use serde::{Deserialize, Serialize};
 use tokio::{
-    sync::mpsc,
+    sync::{broadcast::error::RecvError, mpsc},
     task::JoinHandle,
-    time::{interval, Duration},
+    time::{sleep_until, Duration, Instant},
 };
 use tokio_util::sync::CancellationToken;
 
+use super::polling::{PollingSchedule, WatermarksPolling};
+
 use crate::cluster_status::ClusterStatusRegister;
-use crate::internals::Emitter;
+use crate::internals::{ClientStats, ClientStatsContext, Clock, Emitter, Jitter, WatermarksClient};
+use crate::kafka_types::{Interned, TopicPartition};
 use crate::prometheus_metrics::{LABEL_PARTITION, LABEL_TOPIC};
 
 const CHANNEL_SIZE: usize = 10_000;
 
 const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
 const FETCH_INTERVAL: Duration = Duration::from_millis(10);
+const FETCH_MAX_WAIT: Duration = Duration::from_secs(1);
 
 const MET_FETCH_NAME: &str = "partition_offsets_emitter_fetch_time_milliseconds";
 const MET_FETCH_HELP: &str =
-    "Time (ms) taken to fetch earliest/latest (watermark) offsets of a specific topic partition in cluster";
+    "Time (ms) taken to fetch earliest/latest (watermark) offsets of the batch of a specific topic partition in cluster";
 const MET_CH_CAP_NAME: &str = "partition_offsets_emitter_channel_capacity";
 const MET_CH_CAP_HELP: &str =
     "Capacity of internal channel used to send partition watermark offsets to rest of the service";
 
 /// Offset information for a Topic Partition.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
 pub struct PartitionOffset {
     /// Topic of the Partition
-    pub topic: String,
+    pub topic: Interned,
     /// Partition
     pub partition: u32,
     /// Partition earliest available offset
@@ -46,7 +56,8 @@ pub struct PartitionOffset {
 
 /// Emits Topic Partitions offset watermarks as [`PartitionOffset`] instances.
 ///
-/// The watermarks are the "earliest" and "latest" known offset of a specific partition.
+/// The watermarks are the "earliest" and "latest" known offset of a specific partition,
+/// fetched via a Kafka Consumer (or any other [`WatermarksClient`]).
 /// Additionally, the "read time" wall clock is provided, so _when_ the watermarks were
 /// read is also known.
 ///
@@ -54,6 +65,11 @@ pub struct PartitionOffset {
 pub struct PartitionOffsetsEmitter {
     client_config: ClientConfig,
     cluster_register: Arc<ClusterStatusRegister>,
+    polling: WatermarksPolling,
+    jitter: Jitter,
+    client_stats: Arc<ClientStats>,
+    clock: Arc<dyn Clock>,
+    client: Option<Arc<dyn WatermarksClient>>,
 
     // Prometheus Metrics
     metric_fetch: HistogramVec,
@@ -66,14 +82,29 @@ impl PartitionOffsetsEmitter {
     /// # Arguments
     ///
     /// * `client_config` - Kafka client configuration, used to fetch the Topic Partitions offset watermarks (earliest, latest)
+    /// * `cluster_register` - Where to read the Topic Partitions to fetch the offset watermarks of
+    /// * `polling` - How often to fetch the offset watermarks of each Topic Partition
+    /// * `jitter` - Random jitter to apply to the wait between rounds of polling
+    /// * `client_stats` - Where to export the statistics of the Consumer
+    /// * `clock` - Source of the "read time" of the offset watermarks
+    /// * `metrics` - Where to register the metrics of the emitter
     pub fn new(
         client_config: ClientConfig,
         cluster_register: Arc<ClusterStatusRegister>,
+        polling: WatermarksPolling,
+        jitter: Jitter,
+        client_stats: Arc<ClientStats>,
+        clock: Arc<dyn Clock>,
         metrics: Arc<Registry>,
     ) -> Self {
         Self {
             client_config,
             cluster_register,
+            polling,
+            jitter,
+            client_stats,
+            clock,
+            client: None,
             metric_fetch: register_histogram_vec_with_registry!(
                 MET_FETCH_NAME,
                 MET_FETCH_HELP,
@@ -89,6 +120,13 @@ impl PartitionOffsetsEmitter {
             .unwrap_or_else(|_| panic!("Failed to create metric: {MET_CH_CAP_NAME}")),
         }
     }
+
+    /// Fetch the offset watermarks via the given [`WatermarksClient`],
+    /// instead of a Kafka Consumer created out of the configuration.
+    pub fn with_client(mut self, client: Arc<dyn WatermarksClient>) -> Self {
+        self.client = Some(client);
+        self
+    }
 }
 
 impl Emitter for PartitionOffsetsEmitter {
@@ -108,8 +146,13 @@ impl Emitter for PartitionOffsetsEmitter {
         &self,
         shutdown_token: CancellationToken,
     ) -> (mpsc::Receiver<Self::Emitted>, JoinHandle<()>) {
-        let admin_client: AdminClient<DefaultClientContext> =
-            self.client_config.create().expect("Failed to allocate Admin Client");
+        let client = self.client.clone().unwrap_or_else(|| {
+            let consumer: BaseConsumer<ClientStatsContext> = self
+                .client_stats
+                .create(&self.client_config, "partition_offsets")
+                .expect("Failed to allocate Consumer");
+            Arc::new(consumer)
+        });
 
         let (sx, rx) = mpsc::channel::<PartitionOffset>(CHANNEL_SIZE);
 
@@ -118,60 +161,124 @@ impl Emitter for PartitionOffsetsEmitter {
         let metric_cg_ch_cap = self.metric_ch_cap.clone();
 
         let csr = self.cluster_register.clone();
+        let polling = self.polling;
+        let jitter = self.jitter;
+        let clock = self.clock.clone();
+        let mut schedule = PollingSchedule::new(polling);
+        let mut topology_rx = csr.subscribe_topology_changes();
+        let mut topology_open = true;
         let join_handle = tokio::spawn(async move {
-            let mut interval = interval(FETCH_INTERVAL);
-
             'outer: loop {
+                // Collect the Partitions due to be polled
+                let now = Instant::now();
+                let mut due: Vec<TopicPartition> = Vec::new();
                 for t in csr.get_topics().await {
-                    trace!("Fetching earliest/latest offset for Partitions of Topic '{}'", t);
-
                     for p in csr.get_partitions_for_topic(&t).await.unwrap_or_default() {
-                        // Fetch Partition Watermarks and update timer metrics
-                        let timer =
-                            metric_cg_fetch.with_label_values(&[&t, &p.to_string()]).start_timer();
-                        let res_watermarks =
-                            admin_client.inner().fetch_watermarks(&t, p as i32, FETCH_TIMEOUT);
-                        timer.observe_duration();
-
-                        match res_watermarks {
-                            Ok((earliest, latest)) => {
-                                let po = PartitionOffset {
-                                    topic: t.clone(),
-                                    partition: p,
-                                    earliest_offset: earliest as u64,
-                                    latest_offset: latest as u64,
-                                    read_datetime: Utc::now(),
-                                };
-
-                                // Update channel capacity metric
-                                metric_cg_ch_cap.set(sx.capacity() as i64);
-
-                                tokio::select! {
-                                    res = Self::emit(&sx, po) => {
-                                        if let Err(e) = res {
-                                            error!("Failed to emit {}: {e}", std::any::type_name::<PartitionOffset>());
-                                        }
-                                    },
-                                    _ = shutdown_token.cancelled() => {
-                                        info!("Shutting down");
-                                        break 'outer;
-                                    },
-                                }
+                        let tp = TopicPartition::new(t.clone(), p);
+                        if schedule.is_due(&tp, now) {
+                            due.push(tp);
+                        }
+                    }
+                }
+                trace!("Fetching earliest/latest offset for {} Partitions", due.len());
+
+                // Fetch Partitions Watermarks, in batches, `concurrency` batches at a time
+                for batches in due.chunks(polling.batch_size.max(1) * polling.concurrency.max(1)) {
+                    let fetches: Vec<_> = batches
+                        .chunks(polling.batch_size.max(1))
+                        .map(|batch| {
+                            let client = client.clone();
+                            let batch = batch.to_vec();
+                            tokio::task::spawn_blocking(move || {
+                                let started_at = Instant::now();
+                                let res = client.fetch_watermarks(&batch, FETCH_TIMEOUT);
+                                (batch, res, started_at.elapsed())
+                            })
+                        })
+                        .collect();
+
+                    for fetch in fetches {
+                        let (batch, res_watermarks, elapsed) = match fetch.await {
+                            Ok(fetched) => fetched,
+                            Err(e) => {
+                                error!("Failed to fetch partitions begin/end offsets: {e}");
+                                continue;
                             },
+                        };
+
+                        // Update timer metrics: each Partition took as long as its batch
+                        for tp in batch.iter() {
+                            metric_cg_fetch
+                                .with_label_values(&[&tp.topic, &tp.partition.to_string()])
+                                .observe(elapsed.as_secs_f64());
+                        }
+
+                        let watermarks = match res_watermarks {
+                            Ok(watermarks) => watermarks,
                             Err(e) => {
                                 error!(
-                                    "Failed to fetch partition '{t}:{p}' begin/end offsets: {e}"
+                                    "Failed to fetch begin/end offsets of {} partitions: {e}",
+                                    batch.len()
                                 );
+                                continue;
                             },
+                        };
+
+                        let read_datetime = clock.now();
+                        for (tp, earliest, latest) in watermarks {
+                            schedule.polled(&tp, latest, Instant::now());
+                            let po = PartitionOffset {
+                                topic: tp.topic,
+                                partition: tp.partition,
+                                earliest_offset: earliest,
+                                latest_offset: latest,
+                                read_datetime,
+                            };
+
+                            // Update channel capacity metric
+                            metric_cg_ch_cap.set(sx.capacity() as i64);
+
+                            tokio::select! {
+                                res = Self::emit(&sx, po) => {
+                                    if let Err(e) = res {
+                                        error!("Failed to emit {}: {e}", std::any::type_name::<PartitionOffset>());
+                                    }
+                                },
+                                _ = shutdown_token.cancelled() => {
+                                    info!("Shutting down");
+                                    break 'outer;
+                                },
+                            }
                         }
                     }
                 }
 
-                // Wait for next "tick", or get interrupted by shutdown
+                // Wait until the next Partition is due (but no longer than `FETCH_MAX_WAIT`,
+                // to pick up new Partitions), or get interrupted by a topology change or shutdown
+                let now = Instant::now();
+                let next_due = schedule
+                    .next_due()
+                    .unwrap_or(now)
+                    .clamp(now + FETCH_INTERVAL, now + FETCH_MAX_WAIT);
+                let next_due = now + jitter.apply(next_due - now);
                 tokio::select! {
-                    _ = interval.tick() => {
+                    _ = sleep_until(next_due) => {
                         // No-op
                     },
+                    res = topology_rx.recv(), if topology_open => match res {
+                        Ok(change) => {
+                            // Start polling added Partitions straight away, forget the removed ones
+                            for tp in change.removed_partitions.iter() {
+                                schedule.forget(tp);
+                            }
+                        },
+                        Err(RecvError::Lagged(n)) => {
+                            warn!("Missed {n} topology changes");
+                        },
+                        Err(RecvError::Closed) => {
+                            topology_open = false;
+                        },
+                    },
                     _ = shutdown_token.cancelled() => {
                         info!("Shutting down");
                         break 'outer;
@@ -183,3 +290,173 @@ impl Emitter for PartitionOffsetsEmitter {
         (rx, join_handle)
     }
 }
+
+impl<C: ConsumerContext + 'static> WatermarksClient for BaseConsumer<C> {
+    /// Fetch the earliest and latest offset (watermarks) of a batch of Topic Partitions.
+    ///
+    /// Instead of a pair of requests per Topic Partition, this sends a single pair of `ListOffsets`
+    /// requests per broker, each covering all the Topic Partitions it leads.
+    /// Topic Partitions whose watermarks can't be fetched are omitted (and logged).
+    fn fetch_watermarks(
+        &self,
+        batch: &[TopicPartition],
+        timeout: Duration,
+    ) -> KafkaResult<Vec<(TopicPartition, u64, u64)>> {
+        let list_offsets = |offset: Offset| -> KafkaResult<HashMap<TopicPartition, u64>> {
+            let mut tpl = TopicPartitionList::with_capacity(batch.len());
+            for tp in batch {
+                tpl.add_partition_offset(&tp.topic, tp.partition as i32, offset)?;
+            }
+
+            // `OffsetsForTimes` with the special timestamps of `Beginning` and `End`,
+            // returns respectively the earliest and latest offsets
+            Ok(self
+                .offsets_for_times(tpl, timeout)?
+                .elements()
+                .iter()
+                .filter_map(|elem| {
+                    let tp = TopicPartition::new(elem.topic(), elem.partition() as u32);
+                    match (elem.error(), elem.offset()) {
+                        (Ok(()), Offset::Offset(o)) if o >= 0 => Some((tp, o as u64)),
+                        (Err(e), _) => {
+                            error!("Failed to fetch partition '{tp}' begin/end offsets: {e}");
+                            None
+                        },
+                        (Ok(()), o) => {
+                            error!("Failed to fetch partition '{tp}' begin/end offsets: got {o:?}");
+                            None
+                        },
+                    }
+                })
+                .collect())
+        };
+
+        let earliest = list_offsets(Offset::Beginning)?;
+        let latest = list_offsets(Offset::End)?;
+
+        Ok(pair_watermarks(earliest, latest))
+    }
+}
+
+/// Pair the earliest and latest offset of each Topic Partition:
+/// those missing either (e.g. failed to be fetched) are omitted.
+fn pair_watermarks(
+    earliest: HashMap<TopicPartition, u64>,
+    mut latest: HashMap<TopicPartition, u64>,
+) -> Vec<(TopicPartition, u64, u64)> {
+    earliest.into_iter().filter_map(|(tp, eo)| latest.remove(&tp).map(|lo| (tp, eo, lo))).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use crate::cluster_status::ClusterStatus;
+    use crate::internals::ManualClock;
+    use crate::kafka_types::{PartitionStatus, TopicPartitionsStatus};
+
+    /// Omits Topic Partition `t:3`, and tracks the batches fetched and how many at a time.
+    #[derive(Default)]
+    struct FakeWatermarksClient {
+        batches: Mutex<Vec<Vec<TopicPartition>>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl WatermarksClient for FakeWatermarksClient {
+        fn fetch_watermarks(
+            &self,
+            batch: &[TopicPartition],
+            _timeout: Duration,
+        ) -> KafkaResult<Vec<(TopicPartition, u64, u64)>> {
+            self.batches.lock().unwrap().push(batch.to_vec());
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(batch
+                .iter()
+                .filter(|tp| tp.partition != 3)
+                .map(|tp| (tp.clone(), 0, 100 + tp.partition as u64))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn fetches_in_concurrent_batches() {
+        let (cs_sx, cs_rx) = mpsc::channel(1);
+        let cs_reg = Arc::new(ClusterStatusRegister::new(None, cs_rx, Arc::new(Registry::new())));
+        let cs = ClusterStatus {
+            topics: vec![TopicPartitionsStatus {
+                name: Interned::new("t"),
+                partitions: (0..7)
+                    .map(|id| PartitionStatus {
+                        id,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        cs_sx.send(Arc::new(cs)).await.unwrap();
+        while cs_reg.get_partitions_count().await < 7 {
+            tokio::task::yield_now().await;
+        }
+
+        let metrics = Arc::new(Registry::new());
+        let client = Arc::new(FakeWatermarksClient::default());
+        let emitter = PartitionOffsetsEmitter::new(
+            ClientConfig::new(),
+            cs_reg,
+            WatermarksPolling {
+                batch_size: 2,
+                concurrency: 2,
+                ..Default::default()
+            },
+            Jitter::default(),
+            Arc::new(ClientStats::new(Duration::from_secs(60), metrics.clone())),
+            Arc::new(ManualClock::default()),
+            metrics,
+        )
+        .with_client(client.clone());
+
+        let shutdown_token = CancellationToken::new();
+        let (mut rx, join) = emitter.spawn(shutdown_token.clone());
+        let mut emitted = Vec::new();
+        for _ in 0..6 {
+            let po = rx.recv().await.expect("Partition offsets not emitted");
+            emitted.push((po.partition, po.latest_offset));
+        }
+        shutdown_token.cancel();
+        join.await.unwrap();
+
+        // All but the Partition missing from the fetched watermarks are emitted
+        emitted.sort();
+        assert_eq!(emitted, vec![(0, 100), (1, 101), (2, 102), (4, 104), (5, 105), (6, 106)]);
+
+        // Batches of 2 Partitions, 2 batches at a time
+        let batches = client.batches.lock().unwrap();
+        let mut sizes = batches[..4].iter().map(Vec::len).collect::<Vec<_>>();
+        sizes[2..].sort();
+        assert_eq!(sizes, vec![2, 2, 1, 2]);
+        let mut fetched = batches[..4].concat();
+        fetched.sort();
+        assert_eq!(fetched, (0..7).map(|p| TopicPartition::new("t", p)).collect::<Vec<_>>());
+        assert_eq!(client.max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn pairs_only_partitions_with_both_watermarks() {
+        let tp = |p| TopicPartition::new("t", p);
+        let earliest = HashMap::from([(tp(0), 1), (tp(1), 2), (tp(2), 3)]);
+        let latest = HashMap::from([(tp(0), 10), (tp(2), 30), (tp(3), 40)]);
+
+        let mut paired = pair_watermarks(earliest, latest);
+        paired.sort();
+        assert_eq!(paired, vec![(tp(0), 1, 10), (tp(2), 3, 30)]);
+    }
+}
@@ -1,7 +1,7 @@
 use thiserror::Error;
 
 /// Possible errors from the [`super::lag_estimator`] module.
-#[derive(Error, Debug, Eq, PartialEq)]
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
 pub enum PartitionOffsetsError {
     /// [`super::lag_estimator::PartitionLagEstimator`] has not received enough data points yet.
     /// It's not ready to estimate lag.
@@ -1,11 +1,17 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
+use std::sync::Arc;
 
 use chrono::{DateTime, Duration, Utc};
 
 use super::errors::{PartitionOffsetsError, PartitionOffsetsResult};
+use super::time_lag_strategy::{LinearInterpolation, TimeLagStrategy};
 use super::tracked_offset::TrackedOffset;
 use super::tracked_offset::{search, TrackedOffsetSearchRes};
 
+/// Max amount of ground truths held by a [`PartitionLagEstimator`].
+const GROUND_TRUTHS_CAPACITY: usize = 16;
+
 /// Estimates lag for a given Topic Partition.
 ///
 /// Bare in mind: this only contains the offset data of the partition,
@@ -35,6 +41,31 @@ pub struct PartitionLagEstimator {
     /// offset ever collected. Instead we keep a specific amount (`capacity`) that progresses
     /// towards newer offset information over time.
     latest_tracked_offsets: VecDeque<TrackedOffset>,
+
+    /// Max amount of [`TrackedOffset`]s to hold.
+    capacity: usize,
+
+    /// If set, [`TrackedOffset`]s older than this (compared to the latest tracked) are discarded.
+    ttl: Option<Duration>,
+
+    /// If set, once at capacity the older half of the [`TrackedOffset`]s is downsampled,
+    /// instead of discarding the earliest tracked.
+    downsampling: bool,
+
+    /// Actual production [`DateTime<Utc>`] of some offsets, as read from the Cluster.
+    ///
+    /// When they are closer to an offset than the [`TrackedOffset`]s around it,
+    /// they narrow down the interpolation used to estimate its production time.
+    ground_truths: BTreeMap<u64, DateTime<Utc>>,
+
+    /// Estimates the production time of offsets, on which Time Lag estimation is based.
+    strategy: Arc<dyn TimeLagStrategy>,
+
+    /// [`DateTime<Utc>`] of the latest [`Self::update`], even if it didn't track a new offset.
+    latest_update_at: Option<DateTime<Utc>>,
+
+    /// For how long the latest tracked offset has to stay unchanged, for the partition to be idle.
+    idle_window: Duration,
 }
 
 impl PartitionLagEstimator {
@@ -50,7 +81,65 @@ impl PartitionLagEstimator {
         PartitionLagEstimator {
             earliest_available_offset: None,
             latest_tracked_offsets: VecDeque::with_capacity(capacity),
+            capacity,
+            ttl: None,
+            downsampling: false,
+            ground_truths: BTreeMap::new(),
+            strategy: Arc::new(LinearInterpolation),
+            latest_update_at: None,
+            idle_window: Duration::zero(),
+        }
+    }
+
+    /// Discard [`TrackedOffset`]s older than `ttl`, compared to the latest tracked.
+    ///
+    /// The latest 2 [`TrackedOffset`]s are always kept, as they are needed for estimation.
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Once at capacity, downsample the older half of the [`TrackedOffset`]s,
+    /// instead of discarding the earliest tracked.
+    ///
+    /// This trades resolution of older history, for how far back in time it reaches.
+    pub fn with_downsampling(mut self, downsampling: bool) -> Self {
+        self.downsampling = downsampling;
+        self
+    }
+
+    /// Estimate the production time of offsets with the given [`TimeLagStrategy`],
+    /// instead of the default [`LinearInterpolation`].
+    pub fn with_strategy(mut self, strategy: Arc<dyn TimeLagStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Consider the partition idle once the latest tracked offset stays unchanged for `idle_window`,
+    /// instead of right away.
+    ///
+    /// See [`Self::is_idle`].
+    pub fn with_idle_window(mut self, idle_window: Duration) -> Self {
+        self.idle_window = idle_window;
+        self
+    }
+
+    /// Max amount of [`TrackedOffset`]s held.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the max amount of [`TrackedOffset`]s to hold.
+    ///
+    /// If currently holding more than the new `capacity`, the excess is discarded
+    /// (or downsampled) right away.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.latest_tracked_offsets.len() > self.capacity {
+            self.make_room();
         }
+        self.latest_tracked_offsets.shrink_to(self.capacity);
+        self.latest_tracked_offsets.make_contiguous();
     }
 
     /// Update estimator with a new data points.
@@ -85,6 +174,9 @@ impl PartitionLagEstimator {
             }
         }
         self.earliest_available_offset = Some(new_earliest_available);
+        if self.latest_update_at.is_none_or(|at| at < new_latest_tracked_datetime) {
+            self.latest_update_at = Some(new_latest_tracked_datetime);
+        }
 
         // Validate the input, comparing to the latest tracked offset
         if let Some(curr_latest) = self.latest_tracked_offsets.back() {
@@ -111,9 +203,9 @@ impl PartitionLagEstimator {
             }
         }
 
-        // If we have no more spare capacity, drop the front instead of letting capacity grow
+        // If we have no more spare capacity, make room instead of letting capacity grow
         if self.spare_capacity() == 0 {
-            self.latest_tracked_offsets.pop_front();
+            self.make_room();
         }
 
         // Append to the back
@@ -122,11 +214,86 @@ impl PartitionLagEstimator {
             at: new_latest_tracked_datetime,
         });
 
+        // Drop from the front what is past its time-to-live
+        if let Some(ttl) = self.ttl {
+            while self.latest_tracked_offsets.len() > 2
+                && self.latest_tracked_offsets[0].at < new_latest_tracked_datetime - ttl
+            {
+                self.latest_tracked_offsets.pop_front();
+            }
+        }
+
         // Ensure it's a contiguous slice, so that we can do binary search on it
         // when estimating.
         self.latest_tracked_offsets.make_contiguous();
     }
 
+    /// All the [`TrackedOffset`]s held, earliest first.
+    pub fn tracked_offsets(&self) -> impl Iterator<Item = &TrackedOffset> {
+        self.latest_tracked_offsets.iter()
+    }
+
+    /// Restore previously held data points (e.g. before a restart).
+    ///
+    /// The given [`TrackedOffset`]s are merged with the ones already held: only those
+    /// that precede the earliest tracked (both in offset and date-time) are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `earliest_available` - Earliest offset available in the cluster, used only if not known yet
+    /// * `tracked_offsets` - Previously held [`TrackedOffset`]s, earliest first
+    pub fn restore(
+        &mut self,
+        earliest_available: Option<u64>,
+        tracked_offsets: impl IntoIterator<Item = TrackedOffset>,
+    ) {
+        if self.earliest_available_offset.is_none() {
+            self.earliest_available_offset = earliest_available;
+        }
+
+        let mut restored: Vec<TrackedOffset> = Vec::new();
+        for to in tracked_offsets {
+            let follows_prev =
+                restored.last().is_none_or(|prev| prev.offset < to.offset && prev.at < to.at);
+            let precedes_held = self
+                .latest_tracked_offsets
+                .front()
+                .is_none_or(|front| to.offset < front.offset && to.at < front.at);
+
+            if follows_prev && precedes_held {
+                restored.push(to);
+            }
+        }
+
+        for to in restored.into_iter().rev() {
+            self.latest_tracked_offsets.push_front(to);
+        }
+        while self.latest_tracked_offsets.len() > self.capacity {
+            self.make_room();
+        }
+        self.latest_tracked_offsets.make_contiguous();
+    }
+
+    /// Free up space for at least 1 [`TrackedOffset`].
+    ///
+    /// If downsampling, every other [`TrackedOffset`] in the older half is discarded (except the
+    /// earliest tracked, to preserve how far back history reaches);
+    /// otherwise, the earliest tracked is discarded.
+    fn make_room(&mut self) {
+        let len = self.latest_tracked_offsets.len();
+
+        if self.downsampling && len >= 4 {
+            let mut idx = 0;
+            self.latest_tracked_offsets.retain(|_| {
+                let keep = idx >= len / 2 || idx % 2 == 0;
+                idx += 1;
+                keep
+            });
+        } else {
+            self.latest_tracked_offsets.pop_front();
+        }
+    }
+
     /// Estimate offset lag.
     ///
     /// Compares the given consumer group offset for this partition, with the last produced offset.
@@ -156,8 +323,7 @@ impl PartitionLagEstimator {
     /// Extrapolates the given consumer group offset and related read date time for this partition,
     /// a returns a [`Duration`] estimation of the time lag accumulated by the consumer group.
     ///
-    /// This estimation is done by a linear interpolation/extrapolation, where the fixed points
-    /// are the [`TrackedOffset`]s contained in the [`PartitionLagEstimator`] at the time of call.
+    /// The production time of the offset is estimated via [`Self::estimate_produced_at`].
     ///
     /// # Arguments
     ///
@@ -175,6 +341,44 @@ impl PartitionLagEstimator {
             return Ok(Duration::zero());
         }
 
+        // A consumed offset equal to the end offset means the consumer is caught up.
+        // On an idle partition, estimating against the production time of the latest record
+        // would make Time Lag grow for as long as nothing is produced: it's effectively zero.
+        if offset == lto && self.is_idle() {
+            return Ok(Duration::zero());
+        }
+
+        let estimated_produced_offset_datetime = self.estimate_produced_at(offset)?;
+
+        // It's infrequent, but when we receive a consumed offset datetime that is AHEAD
+        // of the estimated production datetime, we return zero.
+        //
+        // While it's not possible for an offset to be consumed before it's produced (obviously),
+        // it can happen that the linear interpolation done above, estimates the production time
+        // to be later then it ACTUALLY was.
+        //
+        // When that happen, is perfectly ok to consider the time lag to be EFFECTIVELY zero.
+        if offset_datetime < estimated_produced_offset_datetime {
+            Ok(Duration::zero())
+        } else {
+            Ok(offset_datetime - estimated_produced_offset_datetime)
+        }
+    }
+
+    /// Estimate the [`DateTime<Utc>`] at which the given offset was produced.
+    ///
+    /// Unless known exactly, this is estimated by the [`TimeLagStrategy`], where the fixed points
+    /// are the [`TrackedOffset`]s contained in the [`PartitionLagEstimator`] at the time of call,
+    /// narrowed down by the ground truths (see [`Self::add_ground_truth`]), if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Given offset we want to know the production time of
+    pub fn estimate_produced_at(&self, offset: u64) -> PartitionOffsetsResult<DateTime<Utc>> {
+        if let Some(produced_at) = self.ground_truths.get(&offset) {
+            return Ok(*produced_at);
+        }
+
         // NOTE: Please look up `VecDequeue::make_contiguous()` that we call every time we update
         // the internal collection, for this to make sense.
         //
@@ -185,45 +389,64 @@ impl PartitionLagEstimator {
 
         let search_res = search(offset, slice);
 
-        let estimated_produced_offset_datetime = match search_res {
-            TrackedOffsetSearchRes::Exact(found) => found.at,
-            TrackedOffsetSearchRes::Range(tracked_before, tracked_after) => {
-                interpolate_offset_to_datetime(&tracked_before, &tracked_after, offset)?
-            },
+        // Ground truths closer to the offset than the tracked ones, if any
+        let ground_truth_before =
+            self.ground_truths.range(..offset).next_back().map(|(o, at)| TrackedOffset {
+                offset: *o,
+                at: *at,
+            });
+        let ground_truth_after =
+            self.ground_truths.range(offset..).next().map(|(o, at)| TrackedOffset {
+                offset: *o,
+                at: *at,
+            });
+
+        let (before, after) = match search_res {
+            TrackedOffsetSearchRes::Exact(found) => return Ok(found.at),
+            TrackedOffsetSearchRes::Range(tracked_before, tracked_after) => (
+                ground_truth_before
+                    .filter(|gt| gt.offset > tracked_before.offset)
+                    .unwrap_or(tracked_before),
+                ground_truth_after
+                    .filter(|gt| gt.offset < tracked_after.offset)
+                    .unwrap_or(tracked_after),
+            ),
             TrackedOffsetSearchRes::None => {
+                // Offset precedes the earliest tracked (a ground truth might precede it)
                 let earliest_tracked = self.earliest_tracked_offset()?;
-                let latest_tracked = self.latest_tracked_offset()?;
-                let second_latest_tracked = self.nth_latest_tracked_offset(2)?;
-
-                // Estimate production time, considering widest range possible: earliest and latest tracked
-                let widest_estimate =
-                    interpolate_offset_to_datetime(earliest_tracked, latest_tracked, offset)?;
-
-                // Estimate production time, considering narrowest range possible: 2nd-latest and latest tracked
-                let narrowest_estimate =
-                    interpolate_offset_to_datetime(second_latest_tracked, latest_tracked, offset)?;
-
-                // Return the average of the 2 estimates
-                if widest_estimate < narrowest_estimate {
-                    widest_estimate + (narrowest_estimate - widest_estimate)
-                } else {
-                    narrowest_estimate + (widest_estimate - narrowest_estimate)
-                }
+                let after = ground_truth_after
+                    .filter(|gt| gt.offset < earliest_tracked.offset)
+                    .unwrap_or_else(|| earliest_tracked.clone());
+
+                return self.strategy.estimate_produced_at(
+                    offset,
+                    slice,
+                    ground_truth_before.as_ref(),
+                    Some(&after),
+                );
             },
         };
 
-        // It's infrequent, but when we receive a consumed offset datetime that is AHEAD
-        // of the estimated production datetime, we return zero.
-        //
-        // While it's not possible for an offset to be consumed before it's produced (obviously),
-        // it can happen that the linear interpolation done above, estimates the production time
-        // to be later then it ACTUALLY was.
-        //
-        // When that happen, is perfectly ok to consider the time lag to be EFFECTIVELY zero.
-        if offset_datetime < estimated_produced_offset_datetime {
-            Ok(Duration::zero())
-        } else {
-            Ok(offset_datetime - estimated_produced_offset_datetime)
+        self.strategy.estimate_produced_at(offset, slice, Some(&before), Some(&after))
+    }
+
+    /// Add a ground truth: the actual [`DateTime<Utc>`] an offset was produced at.
+    ///
+    /// Only the ground truths of the latest offsets are held, and only while the offsets
+    /// are still available in the Cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Offset of the record
+    /// * `produced_at` - Timestamp of the record
+    pub fn add_ground_truth(&mut self, offset: u64, produced_at: DateTime<Utc>) {
+        self.ground_truths.insert(offset, produced_at);
+
+        if let Some(eao) = self.earliest_available_offset {
+            self.ground_truths.retain(|o, _| *o >= eao);
+        }
+        while self.ground_truths.len() > GROUND_TRUTHS_CAPACITY {
+            self.ground_truths.pop_first();
         }
     }
 
@@ -232,19 +455,60 @@ impl PartitionLagEstimator {
         self.latest_tracked_offsets.len()
     }
 
+    /// Approximate memory (bytes) taken by [`Self`], including the allocated history.
+    pub fn memory(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.latest_tracked_offsets.capacity() * mem::size_of::<TrackedOffset>()
+            + self.ground_truths.len() * mem::size_of::<(u64, DateTime<Utc>)>()
+    }
+
     /// Given the constructor-time `capacity`, how much capacity is left spare, before
     /// a new [`PartitionLagEstimator::update()`] call will need to drop the earliest tracked?
     pub fn spare_capacity(&self) -> usize {
-        self.latest_tracked_offsets.capacity() - self.latest_tracked_offsets.len()
+        self.capacity.saturating_sub(self.latest_tracked_offsets.len())
     }
 
     /// Given the constructor-time `capacity`, at how much usage percent is it, before
     /// a new [`PartitionLagEstimator::update()`] call will need to drop the earliest tracked?
     ///
     /// This is useful to assess how "full" is the `PartitionLagEstimator`.
+    ///
+    /// When a `ttl` is set, history spanning the whole `ttl` is also considered "full",
+    /// as that is as much history as it will ever hold.
     pub fn usage_percent(&self) -> f64 {
-        self.latest_tracked_offsets.len() as f64 / self.latest_tracked_offsets.capacity() as f64
-            * 100_f64
+        let by_count = self.latest_tracked_offsets.len() as f64 / self.capacity as f64 * 100_f64;
+
+        match (self.ttl, self.earliest_tracked_offset(), self.latest_tracked_offset()) {
+            (Some(ttl), Ok(earliest), Ok(latest)) if ttl > Duration::zero() => {
+                let by_time = (latest.at - earliest.at).num_milliseconds() as f64
+                    / ttl.num_milliseconds() as f64
+                    * 100_f64;
+                by_count.max(by_time.min(100_f64))
+            },
+            _ => by_count,
+        }
+    }
+
+    /// Estimate the rate (records per second) at which records are produced to the partition.
+    ///
+    /// It's the average rate from the earliest [`TrackedOffset`] up to the latest update, so that
+    /// updates finding nothing new produced lower it. Once the partition [`Self::is_idle`], it's `0`.
+    pub fn estimate_produced_records_rate(&self) -> PartitionOffsetsResult<f64> {
+        let earliest = self.earliest_tracked_offset()?;
+        let latest = self.latest_tracked_offset()?;
+        let updated_at = self.latest_update_at.unwrap_or(latest.at);
+
+        // Nothing was produced since the latest tracked offset, for at least the idle window
+        if updated_at > latest.at && self.is_idle() {
+            return Ok(0_f64);
+        }
+
+        let elapsed_ms = (updated_at - earliest.at).num_milliseconds();
+        if elapsed_ms <= 0 {
+            return Err(PartitionOffsetsError::LagEstimatorNotReady);
+        }
+
+        Ok(latest.offset.saturating_sub(earliest.offset) as f64 * 1_000_f64 / elapsed_ms as f64)
     }
 
     /// Get the earliest offset available in the cluster
@@ -252,6 +516,17 @@ impl PartitionLagEstimator {
         self.earliest_available_offset.ok_or(PartitionOffsetsError::LagEstimatorNotReady)
     }
 
+    /// Whether nothing was produced to the partition, for at least the idle window.
+    ///
+    /// That is, if the latest tracked offset stayed unchanged across the updates
+    /// received during the idle window (see [`Self::with_idle_window`]).
+    pub fn is_idle(&self) -> bool {
+        match (self.latest_tracked_offsets.back(), self.latest_update_at) {
+            (Some(latest), Some(updated_at)) => updated_at - latest.at >= self.idle_window,
+            _ => false,
+        }
+    }
+
     /// Get the latest offset available in the cluster
     pub fn latest_available_offset(&self) -> PartitionOffsetsResult<u64> {
         self.latest_tracked_offset().map(|ko| ko.offset)
@@ -266,13 +541,6 @@ impl PartitionLagEstimator {
     pub fn latest_tracked_offset(&self) -> PartitionOffsetsResult<&TrackedOffset> {
         self.latest_tracked_offsets.back().ok_or(PartitionOffsetsError::LagEstimatorNotReady)
     }
-
-    /// Get a reference to the Nth latest [`TrackedOffset`]
-    pub fn nth_latest_tracked_offset(&self, pos: usize) -> PartitionOffsetsResult<&TrackedOffset> {
-        self.latest_tracked_offsets
-            .get(self.latest_tracked_offsets.len().wrapping_sub(pos))
-            .ok_or(PartitionOffsetsError::LagEstimatorNotReady)
-    }
 }
 
 /// Interpolate [`TrackedOffset`]s and Kafka Topic Partition offset, to get a [`DateTime<Utc>`].
@@ -284,7 +552,7 @@ impl PartitionLagEstimator {
 /// * `p1` - First point for the linear interpolation
 /// * `p2` - Second point for the linear interpolation
 /// * `y_offset` - The _y_ offset coordinate we want to find the _x_ [`DateTime<Utc>`] coordinate of.
-fn interpolate_offset_to_datetime(
+pub(super) fn interpolate_offset_to_datetime(
     p1: &TrackedOffset,
     p2: &TrackedOffset,
     y_offset: u64,
@@ -318,7 +586,7 @@ fn interpolate_offset_to_datetime(
 /// # Arguments
 ///
 /// * `utc_timestamp_ms` - Amount of milliseconds since UTC Epoch.
-fn utc_from_ms(utc_timestamp_ms: i64) -> PartitionOffsetsResult<DateTime<Utc>> {
+pub(super) fn utc_from_ms(utc_timestamp_ms: i64) -> PartitionOffsetsResult<DateTime<Utc>> {
     DateTime::<Utc>::from_timestamp_millis(utc_timestamp_ms)
         .ok_or(PartitionOffsetsError::UtcTimestampMillisInvalid(utc_timestamp_ms))
 }
@@ -327,6 +595,7 @@ fn utc_from_ms(utc_timestamp_ms: i64) -> PartitionOffsetsResult<DateTime<Utc>> {
 mod test {
     use chrono::Duration;
 
+    use crate::partition_offsets::errors::PartitionOffsetsError::LagEstimatorNotReady;
     use crate::partition_offsets::lag_estimator::{
         interpolate_offset_to_datetime, utc_from_ms, PartitionLagEstimator,
     };
@@ -396,6 +665,47 @@ mod test {
         assert_eq!(estimator.estimate_offset_lag(3000), Ok(0));
     }
 
+    #[test]
+    fn estimate_produced_records_rate() {
+        let (off, ts) = example_tracked_offsets();
+
+        // Not ready, until 2 offsets are tracked
+        let mut estimator = PartitionLagEstimator::new(10);
+        assert_eq!(estimator.estimate_produced_records_rate(), Err(LagEstimatorNotReady));
+        estimator.update(10, off[0], utc_from_ms(ts[0]).unwrap());
+        assert_eq!(estimator.estimate_produced_records_rate(), Err(LagEstimatorNotReady));
+
+        for (idx, offset) in off.iter().enumerate().skip(1) {
+            estimator.update(10, *offset, utc_from_ms(ts[idx]).unwrap());
+        }
+
+        // (2559 - 1346) records, over (1677707486068 - 1677706286068) ms
+        let rate = estimator.estimate_produced_records_rate().unwrap();
+        assert!((rate - 1213_f64 / 1200_f64).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn produced_records_rate_of_stopped_partition() {
+        let mut estimator =
+            PartitionLagEstimator::new(10).with_idle_window(Duration::milliseconds(100));
+        estimator.update(0, 0, utc_from_ms(0).unwrap());
+        estimator.update(0, 100, utc_from_ms(1_000).unwrap());
+        assert_eq!(estimator.estimate_produced_records_rate(), Ok(100_f64));
+
+        // Nothing new produced: the rate drops, until the partition is idle
+        estimator.update(0, 100, utc_from_ms(1_050).unwrap());
+        let rate = estimator.estimate_produced_records_rate().unwrap();
+        assert!(rate > 0_f64 && rate < 100_f64);
+        estimator.update(0, 100, utc_from_ms(1_100).unwrap());
+        assert_eq!(estimator.estimate_produced_records_rate(), Ok(0_f64));
+
+        // A partition that nothing was ever produced to
+        let mut estimator = PartitionLagEstimator::new(10);
+        estimator.update(0, 10, utc_from_ms(0).unwrap());
+        estimator.update(0, 10, utc_from_ms(1_000).unwrap());
+        assert_eq!(estimator.estimate_produced_records_rate(), Ok(0_f64));
+    }
+
     #[test]
     fn should_ignore_updates_that_tracked_datapoints() {
         let (off, ts) = example_tracked_offsets();
@@ -441,6 +751,40 @@ mod test {
         );
     }
 
+    #[test]
+    fn idle_partition_time_lag() {
+        let mut estimator =
+            PartitionLagEstimator::new(10).with_idle_window(Duration::milliseconds(100));
+        estimator.update(1, 5, utc_from_ms(10).unwrap());
+        estimator.update(1, 10, utc_from_ms(20).unwrap());
+
+        // Caught up, but not idle yet: estimated as usual
+        estimator.update(1, 10, utc_from_ms(50).unwrap());
+        assert!(!estimator.is_idle());
+        assert_eq!(
+            estimator.estimate_time_lag(10, utc_from_ms(60).unwrap()),
+            Ok(Duration::milliseconds(40))
+        );
+
+        // Caught up and idle: no more Time Lag
+        estimator.update(1, 10, utc_from_ms(120).unwrap());
+        assert!(estimator.is_idle());
+        assert_eq!(
+            estimator.estimate_time_lag(10, utc_from_ms(10_000).unwrap()),
+            Ok(Duration::zero())
+        );
+
+        // Not caught up: Time Lag keeps growing, even if idle
+        assert_eq!(
+            estimator.estimate_time_lag(5, utc_from_ms(10_000).unwrap()),
+            Ok(Duration::milliseconds(9_990))
+        );
+
+        // Production resumes: not idle anymore
+        estimator.update(1, 15, utc_from_ms(130).unwrap());
+        assert!(!estimator.is_idle());
+    }
+
     #[test]
     fn discard_old_tracked_offsets() {
         let mut estimator = PartitionLagEstimator::new(5);
@@ -488,6 +832,114 @@ mod test {
         );
     }
 
+    #[test]
+    fn discard_expired_tracked_offsets() {
+        let mut estimator =
+            PartitionLagEstimator::new(10).with_ttl(Some(Duration::milliseconds(20)));
+
+        estimator.update(1, 5, utc_from_ms(10).unwrap());
+        estimator.update(1, 10, utc_from_ms(20).unwrap());
+        estimator.update(1, 13, utc_from_ms(30).unwrap());
+        assert_eq!(estimator.usage(), 3);
+        assert_eq!(estimator.usage_percent(), 100_f64);
+
+        // Offsets tracked more than 20ms before the latest are discarded
+        estimator.update(1, 21, utc_from_ms(45).unwrap());
+        assert_eq!(estimator.usage(), 2);
+        assert_eq!(estimator.earliest_tracked_offset().unwrap().offset, 13);
+
+        // ... but the latest 2 are always kept
+        estimator.update(1, 33, utc_from_ms(100).unwrap());
+        assert_eq!(estimator.usage(), 2);
+        assert_eq!(estimator.earliest_tracked_offset().unwrap().offset, 21);
+    }
+
+    #[test]
+    fn downsample_old_tracked_offsets() {
+        let mut estimator = PartitionLagEstimator::new(8).with_downsampling(true);
+
+        for i in 0..8 {
+            estimator.update(1, i * 10, utc_from_ms(i as i64 * 100).unwrap());
+        }
+        assert_eq!(estimator.spare_capacity(), 0);
+
+        // Every other offset of the older half is dropped, but the earliest tracked is kept
+        estimator.update(1, 80, utc_from_ms(800).unwrap());
+        let offsets: Vec<u64> =
+            estimator.latest_tracked_offsets.iter().map(|to| to.offset).collect();
+        assert_eq!(offsets, vec![0, 20, 40, 50, 60, 70, 80]);
+
+        // Shrinking capacity downsamples right away
+        estimator.set_capacity(4);
+        assert!(estimator.usage() <= 4);
+        assert_eq!(estimator.earliest_tracked_offset().unwrap().offset, 0);
+        assert_eq!(estimator.latest_tracked_offset().unwrap().offset, 80);
+    }
+
+    #[test]
+    fn restore_tracked_offsets() {
+        let mut estimator = PartitionLagEstimator::new(4);
+        estimator.update(5, 30, utc_from_ms(300).unwrap());
+        estimator.update(5, 40, utc_from_ms(400).unwrap());
+
+        // Only what precedes the held offsets is restored, up to capacity
+        estimator.restore(
+            Some(1),
+            vec![
+                TrackedOffset {
+                    offset: 10,
+                    at: utc_from_ms(100).unwrap(),
+                },
+                TrackedOffset {
+                    offset: 20,
+                    at: utc_from_ms(200).unwrap(),
+                },
+                TrackedOffset {
+                    offset: 35,
+                    at: utc_from_ms(350).unwrap(),
+                },
+            ],
+        );
+
+        let offsets: Vec<u64> = estimator.tracked_offsets().map(|to| to.offset).collect();
+        assert_eq!(offsets, vec![10, 20, 30, 40]);
+        assert_eq!(estimator.earliest_available_offset(), Ok(5));
+
+        // Beyond capacity, the earliest restored are discarded
+        estimator.restore(
+            None,
+            vec![TrackedOffset {
+                offset: 5,
+                at: utc_from_ms(50).unwrap(),
+            }],
+        );
+        let offsets: Vec<u64> = estimator.tracked_offsets().map(|to| to.offset).collect();
+        assert_eq!(offsets, vec![10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn ground_truths_narrow_estimates() {
+        let mut estimator = PartitionLagEstimator::new(4);
+        estimator.update(0, 100, utc_from_ms(1_000).unwrap());
+        estimator.update(0, 200, utc_from_ms(2_000).unwrap());
+        assert_eq!(estimator.estimate_produced_at(150), Ok(utc_from_ms(1_500).unwrap()));
+
+        // Offsets between 100 and 150 were actually produced in a burst
+        estimator.add_ground_truth(150, utc_from_ms(1_100).unwrap());
+        assert_eq!(estimator.estimate_produced_at(150), Ok(utc_from_ms(1_100).unwrap()));
+        assert_eq!(estimator.estimate_produced_at(125), Ok(utc_from_ms(1_050).unwrap()));
+        assert_eq!(estimator.estimate_produced_at(175), Ok(utc_from_ms(1_550).unwrap()));
+
+        // Preceding the earliest tracked, a ground truth replaces extrapolation
+        estimator.add_ground_truth(50, utc_from_ms(500).unwrap());
+        assert_eq!(estimator.estimate_produced_at(75), Ok(utc_from_ms(750).unwrap()));
+
+        // Ground truths of offsets no longer available are discarded
+        estimator.update(60, 300, utc_from_ms(3_000).unwrap());
+        estimator.add_ground_truth(250, utc_from_ms(2_500).unwrap());
+        assert_eq!(estimator.ground_truths.keys().copied().collect::<Vec<_>>(), vec![150, 250]);
+    }
+
     #[test]
     fn use_percent() {
         let (off, ts) = example_tracked_offsets();
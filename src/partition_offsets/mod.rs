@@ -2,39 +2,77 @@
 mod emitter;
 mod errors;
 mod lag_estimator;
+mod polling;
 mod register;
+mod time_lag_strategy;
 mod tracked_offset;
 
 // Exports
-pub use emitter::PartitionOffsetsEmitter;
-pub use register::PartitionOffsetsRegister;
+pub use emitter::{PartitionOffset, PartitionOffsetsEmitter};
+pub use errors::{PartitionOffsetsError, PartitionOffsetsResult};
+pub use polling::WatermarksPolling;
+pub use register::{HistoryRetention, PartitionOffsetsRegister, PartitionOffsetsSnapshot};
+pub use time_lag_strategy::{
+    LinearInterpolation, NearestPoint, RateExtrapolation, TimeLagStrategy, TimeLagStrategyKind,
+};
+pub use tracked_offset::TrackedOffset;
 
 // Imports
 use prometheus::Registry;
 use std::sync::Arc;
+use std::time::Duration;
 
 use rdkafka::ClientConfig;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
 use crate::cluster_status::ClusterStatusRegister;
-use crate::internals::Emitter;
+use crate::internals::{ClientStats, Clock, Emitter, Jitter, WatermarksClient};
+use crate::recording::Recording;
 
+#[allow(clippy::too_many_arguments)]
 pub fn init(
     admin_client_config: ClientConfig,
+    emitter_polling: WatermarksPolling,
+    emitter_jitter: Jitter,
     register_offsets_history: usize,
+    register_retention: HistoryRetention,
+    register_strategy: Arc<dyn TimeLagStrategy>,
+    register_idle_window: Duration,
     register_ready_at_pct: f64,
     cluster_status_register: Arc<ClusterStatusRegister>,
+    client_stats: Arc<ClientStats>,
+    clock: Arc<dyn Clock>,
+    client: Option<Arc<dyn WatermarksClient>>,
+    recording: &Recording,
     shutdown_token: CancellationToken,
     metrics: Arc<Registry>,
 ) -> (PartitionOffsetsRegister, JoinHandle<()>) {
-    let (po_rx, poe_join) =
-        PartitionOffsetsEmitter::new(admin_client_config, cluster_status_register, metrics.clone())
-            .spawn(shutdown_token);
+    let topology_rx = cluster_status_register.subscribe_topology_changes();
+    let (po_rx, poe_join) = recording.emitted(|| {
+        let mut emitter = PartitionOffsetsEmitter::new(
+            admin_client_config,
+            cluster_status_register.clone(),
+            emitter_polling,
+            emitter_jitter,
+            client_stats,
+            clock,
+            metrics.clone(),
+        );
+        if let Some(client) = client {
+            emitter = emitter.with_client(client);
+        }
+        emitter.spawn(shutdown_token)
+    });
     let po_reg = PartitionOffsetsRegister::new(
         po_rx,
+        topology_rx,
         register_offsets_history,
+        register_retention,
+        register_strategy,
+        register_idle_window,
         register_ready_at_pct,
+        cluster_status_register.clone(),
         metrics,
     );
 
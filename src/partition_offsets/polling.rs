@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use tokio::time::{Duration, Instant};
+
+use crate::constants::{DEFAULT_WATERMARKS_BATCH_SIZE, DEFAULT_WATERMARKS_CONCURRENCY};
+use crate::kafka_types::TopicPartition;
+
+/// Shortest interval a quiet Topic Partition backs off to, when `min_interval` is zero.
+const MIN_BACKOFF: Duration = Duration::from_millis(100);
+
+/// How often to poll the offset watermarks of each Topic Partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatermarksPolling {
+    /// Interval at which "hot" Topic Partitions (i.e. whose latest offset moved since last poll)
+    /// are polled. Zero means: at every round of polling.
+    pub min_interval: Duration,
+
+    /// If set, "quiet" Topic Partitions (i.e. whose latest offset did not move since last poll)
+    /// are polled less and less frequently, doubling the interval up to this.
+    pub max_interval: Option<Duration>,
+
+    /// Max amount of Topic Partitions to fetch the offset watermarks of, in a single batch
+    /// (i.e. a single `ListOffsets` request per broker).
+    pub batch_size: usize,
+
+    /// Max amount of batches to fetch concurrently.
+    pub concurrency: usize,
+}
+
+impl Default for WatermarksPolling {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::ZERO,
+            max_interval: None,
+            batch_size: DEFAULT_WATERMARKS_BATCH_SIZE.parse().expect("Invalid default"),
+            concurrency: DEFAULT_WATERMARKS_CONCURRENCY.parse().expect("Invalid default"),
+        }
+    }
+}
+
+/// When a Topic Partition was last polled, and how long to wait before polling it again.
+#[derive(Debug, Clone, Copy)]
+struct PollState {
+    latest_offset: u64,
+    polled_at: Instant,
+    interval: Duration,
+}
+
+/// Schedules the polling of the offset watermarks of each Topic Partition,
+/// adapting the frequency to how much the latest offset is moving.
+#[derive(Debug)]
+pub(super) struct PollingSchedule {
+    config: WatermarksPolling,
+    states: HashMap<TopicPartition, PollState>,
+}
+
+impl PollingSchedule {
+    pub(super) fn new(config: WatermarksPolling) -> Self {
+        Self {
+            config,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Whether the Topic Partition is due to be polled, at `now`.
+    ///
+    /// Topic Partitions never polled before are always due.
+    pub(super) fn is_due(&self, tp: &TopicPartition, now: Instant) -> bool {
+        self.states.get(tp).is_none_or(|s| now.duration_since(s.polled_at) >= s.interval)
+    }
+
+    /// Record that the Topic Partition was polled at `now`, and what its latest offset was.
+    ///
+    /// If the latest offset moved, it goes back to being polled every `min_interval`;
+    /// otherwise, the interval doubles (up to `max_interval`, if set).
+    pub(super) fn polled(&mut self, tp: &TopicPartition, latest_offset: u64, now: Instant) {
+        let min = self.config.min_interval;
+        let max = self.config.max_interval.unwrap_or(min).max(min);
+
+        let interval = match self.states.get(tp) {
+            Some(s) if s.latest_offset == latest_offset => {
+                (s.interval * 2).max(min).max(MIN_BACKOFF).min(max)
+            },
+            _ => min,
+        };
+
+        self.states.insert(
+            tp.clone(),
+            PollState {
+                latest_offset,
+                polled_at: now,
+                interval,
+            },
+        );
+    }
+
+    /// Stop scheduling the Topic Partition (e.g. because it was removed from the cluster).
+    pub(super) fn forget(&mut self, tp: &TopicPartition) {
+        self.states.remove(tp);
+    }
+
+    /// When the earliest of the Topic Partitions polled so far is due to be polled again.
+    pub(super) fn next_due(&self) -> Option<Instant> {
+        self.states.values().map(|s| s.polled_at + s.interval).min()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tp() -> TopicPartition {
+        TopicPartition::new("t".to_string(), 0)
+    }
+
+    #[test]
+    fn fixed_interval_by_default() {
+        let mut schedule = PollingSchedule::new(WatermarksPolling::default());
+        let now = Instant::now();
+
+        assert!(schedule.is_due(&tp(), now));
+        schedule.polled(&tp(), 10, now);
+        assert!(schedule.is_due(&tp(), now));
+        schedule.polled(&tp(), 10, now);
+        assert!(schedule.is_due(&tp(), now));
+    }
+
+    #[test]
+    fn quiet_partitions_back_off() {
+        let mut schedule = PollingSchedule::new(WatermarksPolling {
+            min_interval: Duration::from_secs(1),
+            max_interval: Some(Duration::from_secs(5)),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let at = |secs| now + Duration::from_secs(secs);
+
+        // Hot: polled every `min_interval`
+        schedule.polled(&tp(), 10, at(0));
+        assert!(!schedule.is_due(&tp(), at(0)));
+        assert!(schedule.is_due(&tp(), at(1)));
+
+        // Quiet: interval doubles, up to `max_interval`
+        schedule.polled(&tp(), 10, at(1));
+        assert!(!schedule.is_due(&tp(), at(2)));
+        assert!(schedule.is_due(&tp(), at(3)));
+        schedule.polled(&tp(), 10, at(3));
+        assert!(!schedule.is_due(&tp(), at(6)));
+        assert!(schedule.is_due(&tp(), at(7)));
+        schedule.polled(&tp(), 10, at(7));
+        assert!(!schedule.is_due(&tp(), at(11)));
+        assert!(schedule.is_due(&tp(), at(12)));
+
+        // Hot again: back to `min_interval`
+        schedule.polled(&tp(), 11, at(12));
+        assert!(schedule.is_due(&tp(), at(13)));
+
+        // Forgotten: due straight away
+        schedule.forget(&tp());
+        assert!(schedule.is_due(&tp(), at(12)));
+        assert_eq!(schedule.next_due(), None);
+    }
+}
@@ -1,30 +1,101 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    sync::Arc,
+};
 
 use chrono::{DateTime, Duration, Utc};
-use prometheus::{register_int_gauge_vec_with_registry, IntGaugeVec, Registry};
-use tokio::sync::{mpsc::Receiver, RwLock};
+use prometheus::{
+    register_gauge_with_registry, register_int_gauge_vec_with_registry, Gauge, IntGaugeVec,
+    Registry,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc::Receiver,
+    watch, RwLock,
+};
 
 use super::emitter::PartitionOffset;
 use super::errors::{PartitionOffsetsError, PartitionOffsetsResult};
 use super::lag_estimator::PartitionLagEstimator;
+use super::time_lag_strategy::TimeLagStrategy;
 
-use crate::internals::Awaitable;
-use crate::kafka_types::TopicPartition;
+use crate::cluster_status::{ClusterStatusRegister, TopologyChange};
+use crate::internals::{Awaitable, Changes, ChangesTracker, Persistent, PersistentFuture};
+use crate::kafka_types::{Interned, TopicPartition};
 use crate::partition_offsets::tracked_offset::TrackedOffset;
 use crate::prometheus_metrics::{LABEL_PARTITION, LABEL_TOPIC};
 
 const MET_USAGE_NAME: &str = "partition_offsets_register_usage";
 const MET_USAGE_HELP: &str = "Amount of offsets tracked per topic partition";
+const MET_COVERAGE_NAME: &str = "partition_offsets_register_coverage_ratio";
+const MET_COVERAGE_HELP: &str =
+    "Ratio of the topic partitions in the cluster, that have their offsets tracked";
+
+/// Capacity of the [`PartitionLagEstimator`]s of the Topics whose offsets history was shed:
+/// the minimum for estimating.
+const SHED_CAPACITY: usize = 2;
+
+/// Point-in-time copy of the offsets tracked for a specific [`TopicPartition`].
+///
+/// Each offset is a [`PartitionOffsetsResult`], as not every [`TopicPartition`]
+/// has necessarily been tracked yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionOffsetsSnapshot {
+    pub topic_partition: TopicPartition,
+    pub earliest_available_offset: PartitionOffsetsResult<u64>,
+    pub latest_available_offset: PartitionOffsetsResult<u64>,
+    pub earliest_tracked_offset: PartitionOffsetsResult<TrackedOffset>,
+    pub latest_tracked_offset: PartitionOffsetsResult<TrackedOffset>,
+    pub produced_records_rate: PartitionOffsetsResult<f64>,
+}
+
+/// Optional limits to the offsets history held by [`PartitionOffsetsRegister`],
+/// on top of the amount of offsets held per Topic Partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HistoryRetention {
+    /// Offsets tracked earlier than this, compared to the latest tracked, are discarded.
+    pub ttl: Option<std::time::Duration>,
+
+    /// Overall memory budget (in bytes) for the offsets history of all Topic Partitions.
+    ///
+    /// The budget is split equally among all Topic Partitions: once at capacity, older
+    /// offsets history is downsampled, instead of discarded.
+    pub memory_budget: Option<usize>,
+}
+
+impl HistoryRetention {
+    /// Amount of offsets to hold per Topic Partition, given the amount of Topic Partitions.
+    ///
+    /// Never more than `offsets_history`, and never less than 2 (the minimum for estimating).
+    fn capacity(&self, offsets_history: usize, partitions_count: usize) -> usize {
+        match self.memory_budget {
+            Some(budget) => (budget / mem::size_of::<TrackedOffset>() / partitions_count.max(1))
+                .clamp(2, offsets_history.max(2)),
+            None => offsets_history,
+        }
+    }
+}
 
 /// Holds the offset of all Topic Partitions in the Kafka Cluster, and can estimate lag of Consumers.
 ///
 /// This is where a tracked Consumer Group, at a tracked offset in time, can get it's lag estimated.
 pub struct PartitionOffsetsRegister {
     estimators: Arc<RwLock<HashMap<TopicPartition, RwLock<PartitionLagEstimator>>>>,
+    offsets_history: usize,
+    retention: HistoryRetention,
+    strategy: Arc<dyn TimeLagStrategy>,
+    idle_window: Duration,
     ready_at: f64,
+    changes: Arc<watch::Sender<()>>,
+    changed_partitions: Arc<ChangesTracker<TopicPartition>>,
+    cluster_status_register: Arc<ClusterStatusRegister>,
+    shed_topics: Arc<RwLock<HashSet<Interned>>>,
 
     // Prometheus Metrics
     metric_usage: IntGaugeVec,
+    metric_coverage: Gauge,
 }
 
 impl PartitionOffsetsRegister {
@@ -33,21 +104,45 @@ impl PartitionOffsetsRegister {
     /// # Arguments
     ///
     /// * `rx` - Channel [`Receiver`] for [`PartitionOffset`]
+    /// * `topology_rx` - Channel [`broadcast::Receiver`] for [`TopologyChange`]:
+    ///   removed Topic Partitions stop being tracked
     /// * `offsets_history` - For each Topic Partition, how much offset history to hold.
     ///   History for each (`Topic, Partition`) pair is kept in a queue-like structure of this
     ///   size. Each entry in the structure is the pair (`Offset, UTC TS`): each pair represents
     ///   at what moment in time that particular offset was valid.
+    /// * `retention` - Further limits to the offset history held.
+    /// * `strategy` - Estimates the production time of offsets, on which Time Lag estimation is based.
+    /// * `idle_window` - For how long a Topic Partition has to receive no new offsets, to be idle:
+    ///   the Time Lag of Consumers caught up with an idle Topic Partition is zero.
     /// * `ready_at` - Percentage at which [`Self`] can be considered ready.
     ///   NOTE: [`Self`] is an [`Awaitable`].
-    pub fn new(
+    /// * `cluster_status_register` - The Topic Partitions known to it are those expected to be
+    ///   tracked: see [`Self::get_coverage`].
+    /// * `metrics` - Where to register the metrics of [`Self`]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
         mut rx: Receiver<PartitionOffset>,
+        mut topology_rx: broadcast::Receiver<TopologyChange>,
         offsets_history: usize,
+        retention: HistoryRetention,
+        strategy: Arc<dyn TimeLagStrategy>,
+        idle_window: std::time::Duration,
         ready_at: f64,
+        cluster_status_register: Arc<ClusterStatusRegister>,
         metrics: Arc<Registry>,
     ) -> Self {
+        let idle_window = Duration::from_std(idle_window).expect("Idle window out of range");
         let por = Self {
             estimators: Arc::new(RwLock::new(HashMap::new())),
+            offsets_history,
+            retention,
+            strategy: strategy.clone(),
+            idle_window,
             ready_at,
+            changes: Arc::new(watch::Sender::new(())),
+            changed_partitions: Arc::new(ChangesTracker::default()),
+            cluster_status_register: cluster_status_register.clone(),
+            shed_topics: Arc::new(RwLock::new(HashSet::new())),
             metric_usage: register_int_gauge_vec_with_registry!(
                 MET_USAGE_NAME,
                 MET_USAGE_HELP,
@@ -55,6 +150,12 @@ impl PartitionOffsetsRegister {
                 metrics
             )
             .unwrap_or_else(|_| panic!("Failed to create metric: {MET_USAGE_NAME}")),
+            metric_coverage: register_gauge_with_registry!(
+                MET_COVERAGE_NAME,
+                MET_COVERAGE_HELP,
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_COVERAGE_NAME}")),
         };
 
         // A clone of the `por.estimator` will be moved into the async task
@@ -63,6 +164,10 @@ impl PartitionOffsetsRegister {
 
         // Clone metrics so they can be used in the spawned future
         let metric_usage = por.metric_usage.clone();
+        let metric_coverage = por.metric_coverage.clone();
+        let changes = por.changes.clone();
+        let changed_partitions = por.changed_partitions.clone();
+        let shed_topics = por.shed_topics.clone();
 
         // The Register is essentially "self updating" its data, by listening
         // on a channel for updates.
@@ -73,6 +178,7 @@ impl PartitionOffsetsRegister {
         tokio::spawn(async move {
             debug!("Begin receiving PartitionOffset updates");
 
+            let mut topology_open = true;
             loop {
                 tokio::select! {
                     Some(po) = rx.recv() => {
@@ -82,14 +188,11 @@ impl PartitionOffsetsRegister {
                         };
 
                         // First, check if we need to create the estimator for this Key
+                        let shed_guard = shed_topics.read().await;
                         let mut w_guard = estimators_clone.write().await;
-                        if !w_guard.contains_key(&k) {
-                            w_guard.insert(
-                                k.clone(),
-                                RwLock::new(PartitionLagEstimator::new(
-                                    offsets_history,
-                                )),
-                            );
+                        if ensure_estimator(&mut w_guard, &k, offsets_history, &retention, &strategy, idle_window, &shed_guard) {
+                            let known = cluster_status_register.get_partitions_count().await;
+                            metric_coverage.set(coverage_ratio(w_guard.len(), known));
                         }
 
                         trace!("Updating Partition: {:?}", k);
@@ -101,17 +204,54 @@ impl PartitionOffsetsRegister {
                             .get(&k)
                             .unwrap_or_else(|| panic!("{} for {:#?} could not be found (fatal)", std::any::type_name::<PartitionLagEstimator>(), k));
 
-                        // Update the PartitionLagEstimator
-                        estimator_rwlock
-                            .write()
-                            .await
-                            .update(po.earliest_offset, po.latest_offset, po.read_datetime);
+                        // Update the PartitionLagEstimator, notifying if the available offsets moved
+                        let mut estimator = estimator_rwlock.write().await;
+
+                        // With a memory budget, each gets its share as it's updated (see `ensure_estimator`)
+                        if retention.memory_budget.is_some() && !shed_guard.contains(&k.topic) {
+                            let capacity = retention.capacity(offsets_history, r_guard.len());
+                            if estimator.capacity() != capacity {
+                                estimator.set_capacity(capacity);
+                            }
+                        }
+                        let prev_offsets = (estimator.earliest_available_offset().ok(), estimator.latest_available_offset().ok());
+                        estimator.update(po.earliest_offset, po.latest_offset, po.read_datetime);
+                        let moved = prev_offsets != (estimator.earliest_available_offset().ok(), estimator.latest_available_offset().ok());
+                        drop(estimator);
+                        changed_partitions.mark([k.clone()]);
+                        if moved {
+                            changes.send_replace(());
+                        }
+                        drop(shed_guard);
 
                         // Update usage metrics
                         metric_usage
                             .with_label_values(&[&k.topic, &k.partition.to_string()])
                             .set(estimator_rwlock.read().await.usage() as i64);
                     },
+                    res = topology_rx.recv(), if topology_open => match res {
+                        Ok(change) => {
+                            let mut w_guard = estimators_clone.write().await;
+                            for tp in change.removed_partitions.iter() {
+                                if w_guard.remove(tp).is_some() {
+                                    debug!("Stopped tracking removed Partition: {:?}", tp);
+                                    changed_partitions.mark([tp.clone()]);
+                                    changes.send_replace(());
+                                    let _ = metric_usage.remove_label_values(&[&tp.topic, &tp.partition.to_string()]);
+                                }
+                            }
+
+                            // Partitions known to the cluster changed, even if none was tracked
+                            let known = cluster_status_register.get_partitions_count().await;
+                            metric_coverage.set(coverage_ratio(w_guard.len(), known));
+                        },
+                        Err(RecvError::Lagged(n)) => {
+                            warn!("Missed {n} topology changes: removed Partitions might be tracked until restart");
+                        },
+                        Err(RecvError::Closed) => {
+                            topology_open = false;
+                        },
+                    },
                     else => {
                         info!("Emitters stopping: breaking (internal) loop");
                         break;
@@ -125,6 +265,24 @@ impl PartitionOffsetsRegister {
 }
 
 impl PartitionOffsetsRegister {
+    /// Subscribe to changes of [`Self`]: a notification is sent every time the available offsets
+    /// of a Topic Partition move, or a Topic Partition stops being tracked.
+    ///
+    /// Notifications are coalesced: only the fact that something changed since the last
+    /// notification was seen is reported.
+    pub fn subscribe_changes(&self) -> watch::Receiver<()> {
+        self.changes.subscribe()
+    }
+
+    /// Take the Topic Partitions changed since last taken: those whose offsets were updated
+    /// (even if the available ones didn't move), or that stopped being tracked.
+    /// All of them, the first time.
+    ///
+    /// Meant for a single reader, keeping a copy of the offsets up to date (see [`Self::update_snapshot`]).
+    pub fn take_changed_partitions(&self) -> Changes<TopicPartition> {
+        self.changed_partitions.take()
+    }
+
     /// Estimate offset lag for consumer of specific [`TopicPartition`], given it's current `consumed_offset`.
     ///
     /// # Arguments
@@ -175,6 +333,61 @@ impl PartitionOffsetsRegister {
             .estimate_time_lag(consumed_offset, consumed_offset_datetime)
     }
 
+    /// Estimate the [`DateTime<Utc>`] at which an offset of a specific [`TopicPartition`] was produced.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic_partition` - Topic Partition the offset belongs to
+    /// * `offset` - Offset we want to know the production time of
+    pub async fn estimate_produced_at(
+        &self,
+        topic_partition: &TopicPartition,
+        offset: u64,
+    ) -> PartitionOffsetsResult<DateTime<Utc>> {
+        self.estimators
+            .read()
+            .await
+            .get(topic_partition)
+            .ok_or(PartitionOffsetsError::LagEstimatorNotFound(
+                topic_partition.topic.to_string(),
+                topic_partition.partition,
+            ))?
+            .read()
+            .await
+            .estimate_produced_at(offset)
+    }
+
+    /// Add the actual [`DateTime<Utc>`] an offset of a specific [`TopicPartition`] was produced at.
+    ///
+    /// Following estimates are narrowed down by it: see [`Self::estimate_produced_at`].
+    ///
+    /// # Arguments
+    ///
+    /// * `topic_partition` - Topic Partition the offset belongs to
+    /// * `offset` - Offset of the record
+    /// * `produced_at` - Timestamp of the record
+    pub async fn add_ground_truth(
+        &self,
+        topic_partition: &TopicPartition,
+        offset: u64,
+        produced_at: DateTime<Utc>,
+    ) -> PartitionOffsetsResult<()> {
+        self.estimators
+            .read()
+            .await
+            .get(topic_partition)
+            .ok_or(PartitionOffsetsError::LagEstimatorNotFound(
+                topic_partition.topic.to_string(),
+                topic_partition.partition,
+            ))?
+            .write()
+            .await
+            .add_ground_truth(offset, produced_at);
+        self.changed_partitions.mark([topic_partition.clone()]);
+
+        Ok(())
+    }
+
     /// Get the earliest tracked offset of specific [`TopicPartition`].
     ///
     /// # Arguments
@@ -261,6 +474,166 @@ impl PartitionOffsetsRegister {
             .latest_available_offset()
     }
 
+    /// Estimate the rate (records per second) at which records are produced to specific [`TopicPartition`].
+    ///
+    /// # Arguments
+    ///
+    /// * `topic_partition` - Topic Partition we want to know the produced records rate of
+    pub async fn estimate_produced_records_rate(
+        &self,
+        topic_partition: &TopicPartition,
+    ) -> PartitionOffsetsResult<f64> {
+        self.estimators
+            .read()
+            .await
+            .get(topic_partition)
+            .ok_or(PartitionOffsetsError::LagEstimatorNotFound(
+                topic_partition.topic.to_string(),
+                topic_partition.partition,
+            ))?
+            .read()
+            .await
+            .estimate_produced_records_rate()
+    }
+
+    /// Get a [`PartitionOffsetsSnapshot`] for each of the given [`TopicPartition`]s.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic_partitions` - Topic Partitions we want the snapshot of
+    pub async fn get_snapshot(
+        &self,
+        topic_partitions: &[TopicPartition],
+    ) -> Vec<PartitionOffsetsSnapshot> {
+        self.update_snapshot(&[], topic_partitions, &Changes::All).await
+    }
+
+    /// Get a [`PartitionOffsetsSnapshot`] for each of the given [`TopicPartition`]s, reading
+    /// again only those that changed since the `prev` ones were got: the others are copied over.
+    ///
+    /// # Arguments
+    ///
+    /// * `prev` - Previous snapshots (see [`Self::get_snapshot`])
+    /// * `topic_partitions` - Topic Partitions we want the snapshot of
+    /// * `changes` - Topic Partitions changed since `prev` (see [`Self::take_changed_partitions`])
+    pub async fn update_snapshot(
+        &self,
+        prev: &[PartitionOffsetsSnapshot],
+        topic_partitions: &[TopicPartition],
+        changes: &Changes<TopicPartition>,
+    ) -> Vec<PartitionOffsetsSnapshot> {
+        let prev_by_tp: HashMap<&TopicPartition, &PartitionOffsetsSnapshot> =
+            prev.iter().map(|ps| (&ps.topic_partition, ps)).collect();
+        let r_guard = self.estimators.read().await;
+
+        let mut res = Vec::with_capacity(topic_partitions.len());
+        for tp in topic_partitions {
+            if !changes.contains(tp) {
+                if let Some(ps) = prev_by_tp.get(tp) {
+                    res.push((*ps).clone());
+                    continue;
+                }
+            }
+
+            let snapshot = match r_guard.get(tp) {
+                Some(est_rwlock) => {
+                    let est = est_rwlock.read().await;
+                    PartitionOffsetsSnapshot {
+                        topic_partition: tp.clone(),
+                        earliest_available_offset: est.earliest_available_offset(),
+                        latest_available_offset: est.latest_available_offset(),
+                        earliest_tracked_offset: est.earliest_tracked_offset().cloned(),
+                        latest_tracked_offset: est.latest_tracked_offset().cloned(),
+                        produced_records_rate: est.estimate_produced_records_rate(),
+                    }
+                },
+                None => {
+                    let err = PartitionOffsetsError::LagEstimatorNotFound(
+                        tp.topic.to_string(),
+                        tp.partition,
+                    );
+                    PartitionOffsetsSnapshot {
+                        topic_partition: tp.clone(),
+                        earliest_available_offset: Err(err.clone()),
+                        latest_available_offset: Err(err.clone()),
+                        earliest_tracked_offset: Err(err.clone()),
+                        latest_tracked_offset: Err(err.clone()),
+                        produced_records_rate: Err(err),
+                    }
+                },
+            };
+            res.push(snapshot);
+        }
+
+        res
+    }
+
+    /// Ratio of the [`TopicPartition`]s currently in the cluster, that are tracked by [`Self`].
+    ///
+    /// A ratio below `1.0` means that offsets are yet to be fetched (e.g. right after launch),
+    /// or keep failing to be fetched, for some Topic Partitions: their lag can't be estimated.
+    pub async fn get_coverage(&self) -> f64 {
+        let tracked = self.estimators.read().await.len();
+        let known = self.cluster_status_register.get_partitions_count().await;
+        coverage_ratio(tracked, known)
+    }
+
+    /// Approximate memory (bytes) taken by the offsets history of all the Topic Partitions.
+    pub async fn estimate_memory(&self) -> usize {
+        let r_guard = self.estimators.read().await;
+
+        let mut res = r_guard.capacity() * mem::size_of::<TopicPartition>();
+        for est_rwlock in r_guard.values() {
+            res += est_rwlock.read().await.memory();
+        }
+        res
+    }
+
+    /// Shed the offsets history of the quietest Topics (i.e. those producing the fewest
+    /// offsets per second), until at least `bytes` of memory are freed.
+    ///
+    /// Their [`PartitionLagEstimator`]s keep only the minimum needed to estimate, from now on:
+    /// Time Lag estimates for them get coarser, but are still provided.
+    ///
+    /// Returns the Topics whose offsets history was shed: none, if there is nothing left to shed.
+    pub async fn shed_history(&self, bytes: usize) -> Vec<Interned> {
+        let mut shed_guard = self.shed_topics.write().await;
+        let r_guard = self.estimators.read().await;
+
+        // Offsets per second, and freeable memory, of each Topic not shed yet
+        let mut by_topic: HashMap<&Interned, (f64, usize)> = HashMap::new();
+        for (tp, est_rwlock) in r_guard.iter().filter(|(tp, _)| !shed_guard.contains(&tp.topic)) {
+            let est = est_rwlock.read().await;
+            let rate = match (est.earliest_tracked_offset(), est.latest_tracked_offset()) {
+                (Ok(e), Ok(l)) if l.at > e.at => {
+                    (l.offset - e.offset) as f64 / (l.at - e.at).num_milliseconds() as f64
+                        * 1000_f64
+                },
+                _ => 0_f64,
+            };
+            let freeable = est.memory().saturating_sub(
+                mem::size_of::<PartitionLagEstimator>()
+                    + SHED_CAPACITY * mem::size_of::<TrackedOffset>(),
+            );
+
+            let entry = by_topic.entry(&tp.topic).or_default();
+            entry.0 += rate;
+            entry.1 += freeable;
+        }
+
+        let shed = quietest_topics(
+            by_topic.into_iter().map(|(t, (rate, freeable))| (t.clone(), rate, freeable)).collect(),
+            bytes,
+        );
+        for (_, est_rwlock) in r_guard.iter().filter(|(tp, _)| shed.contains(&tp.topic)) {
+            est_rwlock.write().await.set_capacity(SHED_CAPACITY);
+        }
+        shed_guard.extend(shed.iter().cloned());
+        self.changed_partitions.mark(r_guard.keys().filter(|tp| shed.contains(&tp.topic)).cloned());
+
+        shed
+    }
+
     /// Get some basic registry usage stats.
     ///
     /// Returns the usage of the internal [`PartitionLagEstimator`]s, as `(min, max, avg, count)` tuple.
@@ -299,19 +672,241 @@ impl PartitionOffsetsRegister {
     }
 }
 
+/// Ratio of `known` Topic Partitions that are `tracked`, capped at `1.0`.
+///
+/// With no Topic Partitions known, there is nothing left to track: coverage is full.
+fn coverage_ratio(tracked: usize, known: usize) -> f64 {
+    if known == 0 {
+        return 1_f64;
+    }
+    (tracked as f64 / known as f64).min(1_f64)
+}
+
+/// The quietest Topics (i.e. lowest rate), whose freeable memory adds up to at least `bytes`.
+///
+/// Each candidate is a `(topic, rate, freeable_bytes)` tuple: those with nothing to free are skipped.
+/// If all the candidates together can't free `bytes`, they are all returned.
+fn quietest_topics(mut candidates: Vec<(Interned, f64, usize)>, bytes: usize) -> Vec<Interned> {
+    candidates.retain(|(_, _, freeable)| *freeable > 0);
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut freed = 0;
+    candidates
+        .into_iter()
+        .take_while(|(_, _, freeable)| {
+            let take = freed < bytes;
+            freed += freeable;
+            take
+        })
+        .map(|(topic, _, _)| topic)
+        .collect()
+}
+
+/// Create the [`PartitionLagEstimator`] for the given [`TopicPartition`], if not there yet.
+///
+/// With a memory budget, it's created with its share of the budget: the existing
+/// [`PartitionLagEstimator`]s get their capacity reduced as they are updated next
+/// (see [`HistoryRetention::capacity`]). Those of the `shed_topics` are created with
+/// [`SHED_CAPACITY`].
+///
+/// Returns `true` if the [`PartitionLagEstimator`] was created.
+fn ensure_estimator(
+    estimators: &mut HashMap<TopicPartition, RwLock<PartitionLagEstimator>>,
+    tp: &TopicPartition,
+    offsets_history: usize,
+    retention: &HistoryRetention,
+    strategy: &Arc<dyn TimeLagStrategy>,
+    idle_window: Duration,
+    shed_topics: &HashSet<Interned>,
+) -> bool {
+    if estimators.contains_key(tp) {
+        return false;
+    }
+
+    let capacity = if shed_topics.contains(&tp.topic) {
+        SHED_CAPACITY
+    } else {
+        retention.capacity(offsets_history, estimators.len() + 1)
+    };
+
+    // Out of range (rejected by `Cli::validate`), it's as if offsets history never expired
+    let ttl = retention.ttl.and_then(|ttl| Duration::from_std(ttl).ok());
+    estimators.insert(
+        tp.clone(),
+        RwLock::new(
+            PartitionLagEstimator::new(capacity)
+                .with_ttl(ttl)
+                .with_downsampling(retention.memory_budget.is_some())
+                .with_strategy(strategy.clone())
+                .with_idle_window(idle_window),
+        ),
+    );
+    true
+}
+
+/// Offsets history of a Topic Partition, as persisted by [`PartitionOffsetsRegister`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionOffsetsHistory {
+    pub topic: Interned,
+    pub partition: u32,
+    pub earliest_available_offset: Option<u64>,
+
+    /// Pairs of (`offset`, `UTC timestamp in milliseconds`), earliest first.
+    pub tracked_offsets: Vec<(u64, i64)>,
+}
+
+impl Persistent for PartitionOffsetsRegister {
+    type State = Vec<PartitionOffsetsHistory>;
+
+    fn state(&self) -> PersistentFuture<'_, Self::State> {
+        Box::pin(async move {
+            let r_guard = self.estimators.read().await;
+
+            let mut res = Vec::with_capacity(r_guard.len());
+            for (tp, est_rwlock) in r_guard.iter() {
+                let est = est_rwlock.read().await;
+                res.push(PartitionOffsetsHistory {
+                    topic: tp.topic.clone(),
+                    partition: tp.partition,
+                    earliest_available_offset: est.earliest_available_offset().ok(),
+                    tracked_offsets: est
+                        .tracked_offsets()
+                        .map(|to| (to.offset, to.at.timestamp_millis()))
+                        .collect(),
+                });
+            }
+
+            res
+        })
+    }
+
+    fn restore(&self, state: Self::State) -> PersistentFuture<'_, ()> {
+        Box::pin(async move {
+            let shed_guard = self.shed_topics.read().await;
+            let mut w_guard = self.estimators.write().await;
+
+            for poh in state {
+                let tp = TopicPartition::new(poh.topic, poh.partition);
+                ensure_estimator(
+                    &mut w_guard,
+                    &tp,
+                    self.offsets_history,
+                    &self.retention,
+                    &self.strategy,
+                    self.idle_window,
+                    &shed_guard,
+                );
+
+                let tracked_offsets = poh.tracked_offsets.into_iter().filter_map(|(offset, ms)| {
+                    DateTime::<Utc>::from_timestamp_millis(ms).map(|at| TrackedOffset {
+                        offset,
+                        at,
+                    })
+                });
+                if let Some(est_rwlock) = w_guard.get_mut(&tp) {
+                    est_rwlock.get_mut().restore(poh.earliest_available_offset, tracked_offsets);
+                }
+            }
+
+            info!("Restored offsets history of {} partitions", w_guard.len());
+            self.changed_partitions.mark_all();
+        })
+    }
+}
+
 impl Awaitable for PartitionOffsetsRegister {
     async fn is_ready(&self) -> bool {
         let (min, max, avg, count) = self.get_usage().await;
+        let coverage = self.get_coverage().await;
+        self.metric_coverage.set(coverage);
         let is_ready = avg >= self.ready_at;
 
         info!(
             "
 Tracked:
-* Partitions: {count}
+* Partitions: {count} ({:3.3}% of the cluster)
 * Offsets/Partition: min={min:3.3}% / max={max:3.3}% / avg={avg:3.3}%
-* Ready: {is_ready}"
+* Ready: {is_ready}",
+            coverage * 100_f64
         );
 
         is_ready
     }
 }
+
+#[cfg(test)]
+mod test {
+    use tokio::sync::mpsc;
+
+    use crate::partition_offsets::LinearInterpolation;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_budget_is_shared_as_partitions_are_updated() {
+        let metrics = Arc::new(Registry::new());
+        let (_, cs_rx) = mpsc::channel(1);
+        let (po_sx, po_rx) = mpsc::channel(1);
+        let (_, topology_rx) = broadcast::channel(1);
+        let offsets_history = 100;
+        let retention = HistoryRetention {
+            memory_budget: Some(40 * mem::size_of::<TrackedOffset>()),
+            ..Default::default()
+        };
+        let po_reg = PartitionOffsetsRegister::new(
+            po_rx,
+            topology_rx,
+            offsets_history,
+            retention,
+            Arc::new(LinearInterpolation),
+            std::time::Duration::from_secs(60),
+            1_f64,
+            Arc::new(ClusterStatusRegister::new(None, cs_rx, Arc::new(Registry::new()))),
+            metrics,
+        );
+        let mut changes = po_reg.subscribe_changes();
+        let update = |partition: u32, latest_offset: u64| PartitionOffset {
+            topic: "t".into(),
+            partition,
+            earliest_offset: 0,
+            latest_offset,
+            read_datetime: Utc::now(),
+        };
+        let capacities = || async {
+            let r_guard = po_reg.estimators.read().await;
+            let mut capacities = Vec::new();
+            for partition in 0..r_guard.len() as u32 {
+                let tp = TopicPartition::new("t", partition);
+                capacities.push(r_guard[&tp].read().await.capacity());
+            }
+            capacities
+        };
+
+        po_sx.send(update(0, 10)).await.unwrap();
+        changes.changed().await.unwrap();
+        assert_eq!(capacities().await, vec![40]);
+
+        // Created with its share, while the other gets it once updated
+        po_sx.send(update(1, 10)).await.unwrap();
+        changes.changed().await.unwrap();
+        assert_eq!(capacities().await, vec![40, 20]);
+        po_sx.send(update(0, 20)).await.unwrap();
+        changes.changed().await.unwrap();
+        assert_eq!(capacities().await, vec![20, 20]);
+    }
+
+    #[test]
+    fn quietest_topics_first() {
+        let candidates = vec![
+            (Interned::from("busy"), 1000_f64, 4096),
+            (Interned::from("quiet"), 0.5_f64, 1024),
+            (Interned::from("idle"), 0_f64, 2048),
+            (Interned::from("already-shed"), 0_f64, 0),
+        ];
+
+        assert_eq!(quietest_topics(candidates.clone(), 1), vec!["idle"]);
+        assert_eq!(quietest_topics(candidates.clone(), 2049), vec!["idle", "quiet"]);
+        assert_eq!(quietest_topics(candidates.clone(), usize::MAX), vec!["idle", "quiet", "busy"]);
+        assert!(quietest_topics(candidates, 0).is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::errors::{PartitionOffsetsError, PartitionOffsetsResult};
+use super::lag_estimator::interpolate_offset_to_datetime;
+use super::tracked_offset::TrackedOffset;
+
+/// Strategy to estimate the [`DateTime<Utc>`] at which an offset was produced,
+/// on which Time Lag estimation is based.
+///
+/// It's used by [`super::PartitionLagEstimator`], when the production time of an offset
+/// is not known exactly (i.e. it's neither a [`TrackedOffset`], nor a ground truth).
+///
+/// Different traffic patterns are better served by different strategies: steady traffic
+/// suits interpolation and extrapolation, while bursty traffic can be better served by
+/// simply picking the nearest known point.
+pub trait TimeLagStrategy: Send + Sync {
+    /// Name of [`Self`], used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Estimate the [`DateTime<Utc>`] at which `offset` was produced.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Offset we want to know the production time of
+    /// * `tracked` - All the [`TrackedOffset`]s, sorted by offset: at least 2
+    /// * `before` - Closest known point (tracked or ground truth) preceding `offset`, if any
+    /// * `after` - Closest known point (tracked or ground truth) following `offset`, if any
+    fn estimate_produced_at(
+        &self,
+        offset: u64,
+        tracked: &[TrackedOffset],
+        before: Option<&TrackedOffset>,
+        after: Option<&TrackedOffset>,
+    ) -> PartitionOffsetsResult<DateTime<Utc>>;
+}
+
+/// Selectable [`TimeLagStrategy`] implementations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TimeLagStrategyKind {
+    /// See [`NearestPoint`].
+    Nearest,
+
+    /// See [`LinearInterpolation`].
+    #[default]
+    Linear,
+
+    /// See [`RateExtrapolation`].
+    Rate,
+}
+
+impl TimeLagStrategyKind {
+    /// Create the [`TimeLagStrategy`] of this kind.
+    pub fn strategy(&self) -> Arc<dyn TimeLagStrategy> {
+        match self {
+            Self::Nearest => Arc::new(NearestPoint),
+            Self::Linear => Arc::new(LinearInterpolation),
+            Self::Rate => Arc::new(RateExtrapolation),
+        }
+    }
+}
+
+/// Production time of the closest known point, by offset.
+///
+/// Suits bursty traffic, where offsets are produced in bulk, and interpolating between
+/// points far apart in time spreads a burst over a time it did not happen in.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NearestPoint;
+
+impl TimeLagStrategy for NearestPoint {
+    fn name(&self) -> &'static str {
+        "nearest"
+    }
+
+    fn estimate_produced_at(
+        &self,
+        offset: u64,
+        _tracked: &[TrackedOffset],
+        before: Option<&TrackedOffset>,
+        after: Option<&TrackedOffset>,
+    ) -> PartitionOffsetsResult<DateTime<Utc>> {
+        match (before, after) {
+            (Some(b), Some(a)) if offset - b.offset <= a.offset - offset => Ok(b.at),
+            (_, Some(a)) => Ok(a.at),
+            (Some(b), None) => Ok(b.at),
+            (None, None) => Err(PartitionOffsetsError::LagEstimatorNotReady),
+        }
+    }
+}
+
+/// Linear interpolation between the known points around the offset.
+///
+/// When the offset is not between known points, it's extrapolated: the average of the
+/// extrapolations over the widest (earliest to latest tracked) and narrowest
+/// (2nd-latest to latest tracked) ranges.
+///
+/// Suits steady traffic: this is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinearInterpolation;
+
+impl TimeLagStrategy for LinearInterpolation {
+    fn name(&self) -> &'static str {
+        "linear"
+    }
+
+    fn estimate_produced_at(
+        &self,
+        offset: u64,
+        tracked: &[TrackedOffset],
+        before: Option<&TrackedOffset>,
+        after: Option<&TrackedOffset>,
+    ) -> PartitionOffsetsResult<DateTime<Utc>> {
+        if let (Some(b), Some(a)) = (before, after) {
+            return interpolate_offset_to_datetime(b, a, offset);
+        }
+
+        let (earliest_tracked, latest_tracked, second_latest_tracked) =
+            match (tracked.first(), tracked.last(), tracked.len().checked_sub(2)) {
+                (Some(e), Some(l), Some(sl)) => (e, l, &tracked[sl]),
+                _ => return Err(PartitionOffsetsError::LagEstimatorNotReady),
+            };
+
+        // Estimate production time, considering widest range possible: earliest and latest tracked
+        let widest_estimate =
+            interpolate_offset_to_datetime(earliest_tracked, latest_tracked, offset)?;
+
+        // Estimate production time, considering narrowest range possible: 2nd-latest and latest tracked
+        let narrowest_estimate =
+            interpolate_offset_to_datetime(second_latest_tracked, latest_tracked, offset)?;
+
+        // Return the average of the 2 estimates
+        if widest_estimate < narrowest_estimate {
+            Ok(widest_estimate + (narrowest_estimate - widest_estimate))
+        } else {
+            Ok(narrowest_estimate + (widest_estimate - narrowest_estimate))
+        }
+    }
+}
+
+/// Extrapolation from the closest known point, at the average production rate
+/// over the whole tracked history.
+///
+/// Suits steady traffic with noisy tracking: the local irregularities between
+/// tracked points are smoothed out by the average rate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateExtrapolation;
+
+impl TimeLagStrategy for RateExtrapolation {
+    fn name(&self) -> &'static str {
+        "rate"
+    }
+
+    fn estimate_produced_at(
+        &self,
+        offset: u64,
+        tracked: &[TrackedOffset],
+        before: Option<&TrackedOffset>,
+        after: Option<&TrackedOffset>,
+    ) -> PartitionOffsetsResult<DateTime<Utc>> {
+        let (earliest_tracked, latest_tracked) = match (tracked.first(), tracked.last()) {
+            (Some(e), Some(l)) if l.offset > e.offset => (e, l),
+            _ => return Err(PartitionOffsetsError::LagEstimatorNotReady),
+        };
+
+        // Average production rate, in offsets per millisecond
+        let rate = (latest_tracked.offset - earliest_tracked.offset) as f64
+            / (latest_tracked.at - earliest_tracked.at).num_milliseconds().max(1) as f64;
+
+        let anchor = match (before, after) {
+            (Some(b), Some(a)) if offset - b.offset <= a.offset - offset => b,
+            (_, Some(a)) => a,
+            (Some(b), None) => b,
+            (None, None) => latest_tracked,
+        };
+
+        let offsets_delta = offset as f64 - anchor.offset as f64;
+        Ok(anchor.at + Duration::milliseconds((offsets_delta / rate).round() as i64))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::partition_offsets::lag_estimator::utc_from_ms;
+
+    fn to(offset: u64, at_ms: i64) -> TrackedOffset {
+        TrackedOffset {
+            offset,
+            at: utc_from_ms(at_ms).unwrap(),
+        }
+    }
+
+    #[test]
+    fn strategies_between_tracked() {
+        // 100 offsets were produced in a burst, between 2000ms and 2100ms
+        let tracked = vec![to(0, 0), to(100, 1000), to(200, 2000), to(300, 2100)];
+        let (before, after) = (&tracked[1], &tracked[2]);
+        let estimate = |kind: TimeLagStrategyKind, offset| {
+            kind.strategy().estimate_produced_at(offset, &tracked, Some(before), Some(after))
+        };
+
+        assert_eq!(estimate(TimeLagStrategyKind::Nearest, 130), Ok(utc_from_ms(1000).unwrap()));
+        assert_eq!(estimate(TimeLagStrategyKind::Nearest, 170), Ok(utc_from_ms(2000).unwrap()));
+        assert_eq!(estimate(TimeLagStrategyKind::Linear, 130), Ok(utc_from_ms(1300).unwrap()));
+        assert_eq!(estimate(TimeLagStrategyKind::Rate, 130), Ok(utc_from_ms(1210).unwrap()));
+        assert_eq!(estimate(TimeLagStrategyKind::Rate, 170), Ok(utc_from_ms(1790).unwrap()));
+    }
+
+    #[test]
+    fn strategies_before_tracked() {
+        let tracked = vec![to(100, 1000), to(200, 2000)];
+        let after = &tracked[0];
+        let estimate = |kind: TimeLagStrategyKind| {
+            kind.strategy().estimate_produced_at(50, &tracked, None, Some(after))
+        };
+
+        assert_eq!(estimate(TimeLagStrategyKind::Nearest), Ok(utc_from_ms(1000).unwrap()));
+        assert_eq!(estimate(TimeLagStrategyKind::Linear), Ok(utc_from_ms(500).unwrap()));
+        assert_eq!(estimate(TimeLagStrategyKind::Rate), Ok(utc_from_ms(500).unwrap()));
+    }
+}
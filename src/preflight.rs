@@ -0,0 +1,308 @@
+//! Checks of connectivity and permissions (ACLs) against the Kafka cluster.
+//!
+//! They are run at startup, before any module is initialized, and by the `check` subcommand:
+//! each failed check comes with a hint on how to remedy it.
+
+use std::fmt;
+use std::time::Duration;
+
+use rdkafka::{
+    config::RDKafkaLogLevel,
+    consumer::{BaseConsumer, Consumer},
+    error::KafkaError,
+    metadata::Metadata,
+    types::RDKafkaErrorCode,
+    ClientConfig, Offset, TopicPartitionList,
+};
+
+use crate::cluster_status::describe_cluster;
+use crate::committed_offsets::OffsetsSource;
+use crate::constants::{KOMMITTED_CONSUMER_OFFSETS_CONSUMER, KONSUMER_OFFSETS_DATA_TOPIC};
+use crate::internals::{error_code, is_unauthorized};
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CREDENTIALS_HINT: &str =
+    "check the credentials (e.g. 'sasl.mechanism', 'sasl.username', 'sasl.password') and that the principal exists";
+const CONNECTIVITY_HINT: &str =
+    "check '--brokers', that they are reachable from here, and the security configuration (e.g. 'security.protocol', 'sasl.*', 'ssl.*')";
+
+/// Outcome of a [`Check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// The check passed, with some details.
+    Passed(String),
+
+    /// The check failed, with the error and a hint on how to remedy it.
+    Failed {
+        error: String,
+        hint: String,
+    },
+
+    /// The check was not run, with the reason why.
+    Skipped(String),
+}
+
+/// A single preflight check, and its [`CheckOutcome`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: CheckOutcome,
+}
+
+impl Check {
+    pub fn is_failed(&self) -> bool {
+        matches!(self.outcome, CheckOutcome::Failed { .. })
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            CheckOutcome::Passed(details) => write!(f, "[ OK ] {}: {details}", self.name),
+            CheckOutcome::Failed {
+                error,
+                hint,
+            } => {
+                write!(f, "[FAIL] {}: {error}\n       Hint: {hint}", self.name)
+            },
+            CheckOutcome::Skipped(reason) => write!(f, "[SKIP] {}: {reason}", self.name),
+        }
+    }
+}
+
+/// Run all the preflight checks, in order: those that depend on a failed one are skipped.
+///
+/// This is blocking: call it via [`tokio::task::spawn_blocking`] from async code.
+///
+/// # Arguments
+///
+/// * `client_config` - Kafka client configuration, as used by the rest of the service
+/// * `offsets_source` - Where committed offsets are sourced from: it determines which
+///   permissions are required
+pub fn run(client_config: &ClientConfig, offsets_source: OffsetsSource) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let mut client_config = client_config.clone();
+    client_config.set("group.id", KOMMITTED_CONSUMER_OFFSETS_CONSUMER);
+    client_config.set("enable.auto.commit", "false");
+    client_config.set_log_level(RDKafkaLogLevel::Error);
+
+    let consumer: BaseConsumer = match client_config.create() {
+        Ok(c) => c,
+        Err(e) => {
+            checks.push(failed(
+                "Kafka client",
+                e,
+                "check the '--kafka-conf' configuration keys and values",
+            ));
+            return checks;
+        },
+    };
+
+    // Without connectivity, there is no point in checking anything else
+    let metadata = match consumer.fetch_metadata(None, CHECK_TIMEOUT) {
+        Ok(m) => {
+            checks.push(passed(
+                "Broker reachability",
+                format!("{} brokers, {} topics", m.brokers().len(), m.topics().len()),
+            ));
+            m
+        },
+        Err(e) => {
+            checks.push(failed("Broker reachability", e, CONNECTIVITY_HINT));
+            for name in [
+                "Describe cluster",
+                "List consumer groups",
+                "Fetch watermarks",
+                "Read committed offsets",
+            ] {
+                checks.push(skipped(name, "brokers not reachable"));
+            }
+            return checks;
+        },
+    };
+
+    checks.push(match describe_cluster(consumer.client(), CHECK_TIMEOUT) {
+        Ok(_) => passed("Describe cluster", "controller and brokers rack known".to_string()),
+        Err(e) => failed(
+            "Describe cluster",
+            e,
+            "grant DESCRIBE on the cluster (e.g. 'kafka-acls --add --allow-principal User:<PRINCIPAL> --operation Describe --cluster')",
+        ),
+    });
+
+    let list_groups_hint = "grant DESCRIBE on the cluster, or on the consumer groups (e.g. 'kafka-acls --add --allow-principal User:<PRINCIPAL> --operation Describe --group '*'')";
+    let groups_listed = match consumer.fetch_group_list(None, CHECK_TIMEOUT) {
+        Ok(gl) => {
+            checks.push(passed("List consumer groups", format!("{} groups", gl.groups().len())));
+            true
+        },
+        Err(e) => {
+            checks.push(failed("List consumer groups", e, list_groups_hint));
+            false
+        },
+    };
+
+    checks.push(check_watermarks(&consumer, &metadata));
+
+    checks.push(match offsets_source {
+        OffsetsSource::KonsumerOffsets => check_konsumer_offsets(&consumer),
+        OffsetsSource::AdminApi if groups_listed => passed(
+            "Read committed offsets",
+            "fetched via the Admin API, for each listed consumer group".to_string(),
+        ),
+        OffsetsSource::AdminApi => {
+            skipped("Read committed offsets", "consumer groups can't be listed")
+        },
+    });
+
+    checks
+}
+
+/// Check that the watermarks of a Topic Partition can be fetched.
+fn check_watermarks(consumer: &BaseConsumer, metadata: &Metadata) -> Check {
+    let name = "Fetch watermarks";
+    let Some(topic) = metadata
+        .topics()
+        .iter()
+        .find(|t| !t.name().starts_with("__") && !t.partitions().is_empty())
+    else {
+        return skipped(name, "no topics in the cluster");
+    };
+
+    match consumer.fetch_watermarks(topic.name(), topic.partitions()[0].id(), CHECK_TIMEOUT) {
+        Ok(_) => passed(name, format!("fetched for '{}'", topic.name())),
+        Err(e) => failed(
+            name,
+            e,
+            "grant DESCRIBE on the topics (e.g. 'kafka-acls --add --allow-principal User:<PRINCIPAL> --operation Describe --topic '*'')",
+        ),
+    }
+}
+
+/// Check that `__consumer_offsets` can be read, by fetching (at most) a single record of it.
+fn check_konsumer_offsets(consumer: &BaseConsumer) -> Check {
+    let name = "Read committed offsets";
+    let hint = "grant READ on '__consumer_offsets' (e.g. 'kafka-acls --add --allow-principal User:<PRINCIPAL> --operation Read --topic __consumer_offsets'), or use '--source admin-api'";
+
+    let (low, high) = match consumer.fetch_watermarks(KONSUMER_OFFSETS_DATA_TOPIC, 0, CHECK_TIMEOUT)
+    {
+        Ok(wm) => wm,
+        Err(e) => return failed(name, e, hint),
+    };
+    if low >= high {
+        return passed(name, format!("nothing to read yet in '{KONSUMER_OFFSETS_DATA_TOPIC}'"));
+    }
+
+    let mut tpl = TopicPartitionList::new();
+    if let Err(e) = tpl
+        .add_partition_offset(KONSUMER_OFFSETS_DATA_TOPIC, 0, Offset::Offset(low))
+        .and_then(|_| consumer.assign(&tpl))
+    {
+        return failed(name, e, hint);
+    }
+
+    match consumer.poll(CHECK_TIMEOUT) {
+        Some(Ok(_)) => passed(name, format!("consumed from '{KONSUMER_OFFSETS_DATA_TOPIC}'")),
+        Some(Err(e)) => failed(name, e, hint),
+        None => failed(
+            name,
+            KafkaError::NoMessageReceived,
+            "check that the brokers leading '__consumer_offsets' are reachable",
+        ),
+    }
+}
+
+fn passed(name: &'static str, details: String) -> Check {
+    Check {
+        name,
+        outcome: CheckOutcome::Passed(details),
+    }
+}
+
+/// A failed [`Check`], with the hint to remedy the given [`KafkaError`].
+///
+/// `hint` is how to remedy the check failing for lack of permissions (ACLs):
+/// it's used unless the error code points at the credentials or the connectivity instead.
+fn failed(name: &'static str, e: KafkaError, hint: &str) -> Check {
+    Check {
+        name,
+        outcome: CheckOutcome::Failed {
+            hint: remediation_hint(&e, hint).to_string(),
+            error: e.to_string(),
+        },
+    }
+}
+
+/// How to remedy the [`KafkaError`], based on its error code: see [`failed`].
+fn remediation_hint<'a>(e: &KafkaError, hint: &'a str) -> &'a str {
+    if is_unauthorized(e) {
+        return hint;
+    }
+    match error_code(e) {
+        Some(RDKafkaErrorCode::Authentication | RDKafkaErrorCode::SaslAuthenticationFailed) => {
+            CREDENTIALS_HINT
+        },
+        Some(
+            RDKafkaErrorCode::AllBrokersDown
+            | RDKafkaErrorCode::BrokerTransportFailure
+            | RDKafkaErrorCode::Resolve,
+        ) => CONNECTIVITY_HINT,
+        _ => hint,
+    }
+}
+
+fn skipped(name: &'static str, reason: &str) -> Check {
+    Check {
+        name,
+        outcome: CheckOutcome::Skipped(reason.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn remediation_hint_by_error_code() {
+        let acl_hint = "grant READ";
+        for (e, expected) in [
+            (KafkaError::MessageConsumption(RDKafkaErrorCode::TopicAuthorizationFailed), acl_hint),
+            (KafkaError::GroupListFetch(RDKafkaErrorCode::GroupAuthorizationFailed), acl_hint),
+            (KafkaError::AdminOp(RDKafkaErrorCode::ClusterAuthorizationFailed), acl_hint),
+            (
+                KafkaError::MetadataFetch(RDKafkaErrorCode::SaslAuthenticationFailed),
+                CREDENTIALS_HINT,
+            ),
+            (KafkaError::MetadataFetch(RDKafkaErrorCode::Authentication), CREDENTIALS_HINT),
+            (KafkaError::MetadataFetch(RDKafkaErrorCode::AllBrokersDown), CONNECTIVITY_HINT),
+            (
+                KafkaError::MessageConsumption(RDKafkaErrorCode::BrokerTransportFailure),
+                CONNECTIVITY_HINT,
+            ),
+            (KafkaError::MetadataFetch(RDKafkaErrorCode::OperationTimedOut), acl_hint),
+            (KafkaError::NoMessageReceived, acl_hint),
+        ] {
+            assert_eq!(remediation_hint(&e, acl_hint), expected, "for {e}");
+        }
+    }
+
+    #[test]
+    fn failed_check_carries_the_remediation_hint() {
+        let check = failed(
+            "Read committed offsets",
+            KafkaError::MessageConsumption(RDKafkaErrorCode::SaslAuthenticationFailed),
+            "grant READ",
+        );
+        assert!(check.is_failed());
+        assert_eq!(
+            check.outcome,
+            CheckOutcome::Failed {
+                error: KafkaError::MessageConsumption(RDKafkaErrorCode::SaslAuthenticationFailed)
+                    .to_string(),
+                hint: CREDENTIALS_HINT.to_string(),
+            }
+        );
+    }
+}
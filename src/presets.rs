@@ -0,0 +1,202 @@
+//! Kafka client configuration presets, for the most common managed Kafka services and distributions.
+//!
+//! Each preset expands into the configuration the service requires, so that it doesn't have
+//! to be assembled by hand via `--kafka-conf`: what's set via `--kafka-conf` still takes precedence.
+
+use crate::cluster_status::ClusterStatus;
+use crate::committed_offsets::OffsetsSource;
+
+/// Kafka distributions (or managed services) that are only partially compatible with Apache Kafka,
+/// or that require some tuning to connect to.
+///
+/// All of them imply `--lenient`: subsystems that fail for lack of permissions, or because
+/// the brokers don't support them, are disabled instead of failing over and over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Preset {
+    /// Aiven for Apache Kafka: authenticate via client certificates, and work with
+    /// the limited ACLs granted to service users by default.
+    Aiven,
+
+    /// Redpanda: source committed offsets via the Kafka API, and work without
+    /// the Admin APIs not supported by older versions (e.g. `DescribeCluster`).
+    Redpanda,
+
+    /// Strimzi (Kafka on Kubernetes): cope with the brokers changing address,
+    /// as pods are rescheduled.
+    Strimzi,
+}
+
+impl Preset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Aiven => "aiven",
+            Preset::Redpanda => "redpanda",
+            Preset::Strimzi => "strimzi",
+        }
+    }
+
+    /// Kafka client configuration of the [`Preset`].
+    pub fn client_config(&self) -> Vec<(&'static str, String)> {
+        let mut config = vec![
+            ("socket.keepalive.enable", "true".to_string()),
+            ("socket.timeout.ms", "60000".to_string()),
+        ];
+        match self {
+            Preset::Aiven => {
+                config.push(("security.protocol", "SSL".to_string()));
+            },
+            Preset::Redpanda => {
+                config.push(("api.version.request", "true".to_string()));
+            },
+            Preset::Strimzi => {
+                config.push(("client.dns.lookup", "use_all_dns_ips".to_string()));
+                config.push(("reconnect.backoff.max.ms", "10000".to_string()));
+            },
+        }
+        config
+    }
+
+    /// Where to source committed offsets from, if `__consumer_offsets` can't be relied upon.
+    pub fn offsets_source(&self) -> Option<OffsetsSource> {
+        match self {
+            // Service users are not granted `READ` on internal topics by default
+            Preset::Aiven => Some(OffsetsSource::AdminApi),
+            // Older versions don't keep committed offsets in `__consumer_offsets`
+            Preset::Redpanda => Some(OffsetsSource::AdminApi),
+            Preset::Strimzi => None,
+        }
+    }
+
+    /// Detect the [`Preset`] that fits the cluster, from its identifier and the hosts of its brokers.
+    pub fn detect(cs: &ClusterStatus) -> Option<Self> {
+        if cs.id.starts_with("redpanda.") {
+            Some(Preset::Redpanda)
+        } else if cs.brokers.iter().any(|b| b.host.ends_with(".aivencloud.com")) {
+            Some(Preset::Aiven)
+        } else if cs.brokers.iter().any(|b| b.host.contains("-kafka-") && b.host.contains(".svc")) {
+            Some(Preset::Strimzi)
+        } else {
+            None
+        }
+    }
+
+    /// Log the [`Preset`] detected for the cluster, and whether it differs from the one in use.
+    pub fn log_detected(in_use: Option<Self>, cs: &ClusterStatus) {
+        match (Self::detect(cs), in_use) {
+            (Some(detected), Some(in_use)) if detected == in_use => {
+                info!("Detected {} cluster, as per preset", detected.name());
+            },
+            (Some(detected), Some(in_use)) => {
+                warn!(
+                    "Detected {} cluster, but using preset '{}': consider '--preset {}'",
+                    detected.name(),
+                    in_use.name(),
+                    detected.name()
+                );
+            },
+            (Some(detected), None) => {
+                info!(
+                    "Detected {} cluster: consider '--preset {}', if it doesn't work as expected",
+                    detected.name(),
+                    detected.name()
+                );
+            },
+            (None, Some(in_use)) => {
+                debug!("Could not detect a {} cluster, but using its preset", in_use.name());
+            },
+            (None, None) => {},
+        }
+    }
+}
+
+/// Kafka client configuration for Confluent Cloud, authenticating with an API key and secret.
+///
+/// Besides authentication, it follows the client configuration recommended by Confluent
+/// (e.g. connecting to all the IPs a broker hostname resolves to).
+pub fn confluent_cloud(api_key: &str, api_secret: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("security.protocol", "SASL_SSL".to_string()),
+        ("sasl.mechanisms", "PLAIN".to_string()),
+        ("sasl.username", api_key.to_string()),
+        ("sasl.password", api_secret.to_string()),
+        ("client.dns.lookup", "use_all_dns_ips".to_string()),
+        ("socket.keepalive.enable", "true".to_string()),
+        ("session.timeout.ms", "45000".to_string()),
+    ]
+}
+
+/// Validate that the given bootstrap brokers (format: 'HOST:PORT,...') are those of a Confluent Cloud cluster.
+///
+/// Returns a description of the first issue found, if any.
+pub fn validate_confluent_cloud_brokers(brokers: &str) -> Result<(), String> {
+    for broker in brokers.split(',').map(str::trim) {
+        if let Some((scheme, _)) = broker.split_once("://") {
+            return Err(format!("'{broker}' should not have the '{scheme}://' prefix"));
+        }
+        let Some((host, port)) = broker.rsplit_once(':') else {
+            return Err(format!("'{broker}' should be in the format 'HOST:PORT'"));
+        };
+        if !host.ends_with(".confluent.cloud") {
+            return Err(format!(
+                "'{host}' is not a Confluent Cloud host (e.g. 'pkc-12345.us-east-1.aws.confluent.cloud')"
+            ));
+        }
+        if port.parse::<u16>().is_err() {
+            return Err(format!("'{port}' is not a valid port (e.g. '9092')"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kafka_types::Broker;
+
+    fn cluster_status(id: &str, host: &str) -> ClusterStatus {
+        ClusterStatus {
+            id: id.to_string(),
+            brokers: vec![Broker {
+                host: host.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn detects_presets() {
+        assert_eq!(
+            Preset::detect(&cluster_status("redpanda.0ab1c2d3", "redpanda-0")),
+            Some(Preset::Redpanda)
+        );
+        assert_eq!(
+            Preset::detect(&cluster_status("abc", "kafka-1a2b3c.aivencloud.com")),
+            Some(Preset::Aiven)
+        );
+        assert_eq!(
+            Preset::detect(&cluster_status(
+                "abc",
+                "my-cluster-kafka-0.my-cluster-kafka-brokers.kafka.svc"
+            )),
+            Some(Preset::Strimzi)
+        );
+        assert_eq!(Preset::detect(&cluster_status("abc", "localhost")), None);
+    }
+
+    #[test]
+    fn validates_confluent_cloud_brokers() {
+        assert!(validate_confluent_cloud_brokers("pkc-12345.us-east-1.aws.confluent.cloud:9092")
+            .is_ok());
+        assert!(validate_confluent_cloud_brokers(
+            "SASL_SSL://pkc-12345.us-east-1.aws.confluent.cloud:9092"
+        )
+        .is_err());
+        assert!(
+            validate_confluent_cloud_brokers("pkc-12345.us-east-1.aws.confluent.cloud").is_err()
+        );
+        assert!(validate_confluent_cloud_brokers("localhost:9092").is_err());
+        assert!(validate_confluent_cloud_brokers("pkc-1.confluent.cloud:90x2").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Preview of the metrics the service would serve, to predict their cardinality before deploying.
+//!
+//! It's the report of the `preview-metrics` subcommand: after warming up, the metrics
+//! (as served by `/metrics`) are counted by family, and by Consumer Group and Topic.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::prometheus_metrics::{LABEL_GROUP, LABEL_TOPIC};
+
+/// How many of the Consumer Groups and Topics with the most time series are listed.
+const TOP_LEN: usize = 10;
+
+/// Time series of the metrics, counted by family, and by Consumer Group and Topic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsPreview {
+    pub by_family: BTreeMap<String, usize>,
+    pub by_group: BTreeMap<String, usize>,
+    pub by_topic: BTreeMap<String, usize>,
+}
+
+impl MetricsPreview {
+    /// Count the time series of the given metrics, in Prometheus Exposition format.
+    ///
+    /// Each sample belongs to the family of the `# TYPE` header preceding it
+    /// (e.g. the `_bucket`, `_sum` and `_count` samples of a histogram).
+    pub fn from_exposition(exposition: &str) -> Self {
+        let mut res = Self::default();
+
+        let mut family: Option<&str> = None;
+        for line in exposition.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if let Some(header) = line.strip_prefix("# TYPE ") {
+                family = header.split_whitespace().next();
+                if let Some(f) = family {
+                    res.by_family.entry(f.to_string()).or_default();
+                }
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let name = family.unwrap_or_else(|| {
+                line.split(|c: char| c == '{' || c.is_whitespace()).next().unwrap_or_default()
+            });
+            *res.by_family.entry(name.to_string()).or_default() += 1;
+
+            if let Some(group) = label_value(line, LABEL_GROUP) {
+                *res.by_group.entry(group.to_string()).or_default() += 1;
+            }
+            if let Some(topic) = label_value(line, LABEL_TOPIC) {
+                *res.by_topic.entry(topic.to_string()).or_default() += 1;
+            }
+        }
+
+        res
+    }
+
+    /// Total amount of time series.
+    pub fn series_count(&self) -> usize {
+        self.by_family.values().sum()
+    }
+}
+
+/// Value of the label of the given name, in a sample line, if the sample has it.
+///
+/// Escaped characters are left escaped.
+fn label_value<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let labels = &line[line.find('{')? + 1..];
+
+    let mut rest = labels;
+    loop {
+        let (label, after) = rest.split_once("=\"")?;
+        let end = closing_quote(after)?;
+        if label.trim_start_matches(',') == name {
+            return Some(&after[..end]);
+        }
+        rest = &after[end + 1..];
+    }
+}
+
+/// Index of the first unescaped `"`.
+fn closing_quote(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// The `limit` entries with the highest counts, highest first.
+fn top(counts: &BTreeMap<String, usize>, limit: usize) -> Vec<(&str, usize)> {
+    let mut top: Vec<(&str, usize)> = counts.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    top.truncate(limit);
+    top
+}
+
+impl fmt::Display for MetricsPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Metric families: {}", self.by_family.len())?;
+        writeln!(f, "Estimated time series: {}", self.series_count())?;
+
+        writeln!(f, "\nBy metric:")?;
+        for (family, count) in top(&self.by_family, usize::MAX) {
+            writeln!(f, "  {count:>10}  {family}")?;
+        }
+
+        for (what, counts) in [("group", &self.by_group), ("topic", &self.by_topic)] {
+            writeln!(f, "\nBy {what} (top {TOP_LEN} of {}):", counts.len())?;
+            for (name, count) in top(counts, TOP_LEN) {
+                writeln!(f, "  {count:>10}  {name}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counts_series() {
+        let exposition = r#"# HELP kmtd_kafka_consumer_partition_lag_offset Lag.
+# TYPE kmtd_kafka_consumer_partition_lag_offset gauge
+kmtd_kafka_consumer_partition_lag_offset{cluster_id="c",group="g1",topic="t1",partition="0"} 10 1000
+kmtd_kafka_consumer_partition_lag_offset{cluster_id="c",group="g1",topic="t1",partition="1"} 10 1000
+kmtd_kafka_consumer_partition_lag_offset{cluster_id="c",group="g\"2",topic="t2",partition="0"} -1
+# HELP kmtd_sinks_publish_time_seconds Time.
+# TYPE kmtd_sinks_publish_time_seconds histogram
+kmtd_sinks_publish_time_seconds_bucket{cluster_id="c",sink="prometheus",le="+Inf"} 1
+kmtd_sinks_publish_time_seconds_sum{cluster_id="c",sink="prometheus"} 0.1
+kmtd_sinks_publish_time_seconds_count{cluster_id="c",sink="prometheus"} 1
+"#;
+
+        let preview = MetricsPreview::from_exposition(exposition);
+        assert_eq!(preview.series_count(), 6);
+        assert_eq!(preview.by_family["kmtd_kafka_consumer_partition_lag_offset"], 3);
+        assert_eq!(preview.by_family["kmtd_sinks_publish_time_seconds"], 3);
+        assert_eq!(preview.by_group["g1"], 2);
+        assert_eq!(preview.by_group["g\\\"2"], 1);
+        assert_eq!(preview.by_topic["t1"], 2);
+        assert_eq!(top(&preview.by_topic, 1), vec![("t1", 2)]);
+    }
+}
@@ -0,0 +1,26 @@
+use std::fmt::Write;
+
+use const_format::formatcp;
+
+use super::super::{LABEL_CLUSTER_ID, NAMESPACE};
+use super::{HEADER_HELP, HEADER_TYPE, TYPE_GAUGE};
+
+pub(crate) const NAME: &str = formatcp!("{NAMESPACE}_cluster_lagging_groups");
+const HELP: &str =
+    formatcp!("{HEADER_HELP} {NAME} Amount of consumer groups of the cluster whose offset lag is above the lagging threshold.");
+const TYPE: &str = formatcp!("{HEADER_TYPE} {NAME} {TYPE_GAUGE}");
+
+pub(crate) fn append_headers(res: &mut String) {
+    let _ = writeln!(res, "{HELP}\n{TYPE}");
+}
+
+pub(crate) fn append_metric(cluster_id: &str, lagging_groups: usize, res: &mut String) {
+    let _ = writeln!(
+        res,
+        "{NAME}\
+        {{\
+            {LABEL_CLUSTER_ID}=\"{cluster_id}\"\
+        }} \
+        {lagging_groups}"
+    );
+}
@@ -0,0 +1,27 @@
+use std::fmt::Write;
+
+use const_format::formatcp;
+
+use super::super::{LABEL_CLUSTER_ID, NAMESPACE};
+use super::{HEADER_HELP, HEADER_TYPE, TYPE_GAUGE};
+
+pub(crate) const NAME: &str = formatcp!("{NAMESPACE}_cluster_total_offset_lag");
+const HELP: &str = formatcp!(
+    "{HEADER_HELP} {NAME} Sum of the offset lag of all the consumer groups of the cluster."
+);
+const TYPE: &str = formatcp!("{HEADER_TYPE} {NAME} {TYPE_GAUGE}");
+
+pub(crate) fn append_headers(res: &mut String) {
+    let _ = writeln!(res, "{HELP}\n{TYPE}");
+}
+
+pub(crate) fn append_metric(cluster_id: &str, offset_lag: u64, res: &mut String) {
+    let _ = writeln!(
+        res,
+        "{NAME}\
+        {{\
+            {LABEL_CLUSTER_ID}=\"{cluster_id}\"\
+        }} \
+        {offset_lag}"
+    );
+}
@@ -0,0 +1,32 @@
+use std::fmt::Write;
+
+use const_format::formatcp;
+
+use crate::kafka_types::Group;
+
+use super::super::{LABEL_CLUSTER_ID, LABEL_GROUP, NAMESPACE};
+use super::{render_group_labels, HEADER_HELP, HEADER_TYPE, TYPE_GAUGE};
+
+pub(crate) const NAME: &str = formatcp!("{NAMESPACE}_kafka_consumer_group_lag_milliseconds");
+const HELP: &str =
+    formatcp!("{HEADER_HELP} {NAME} Max of the estimated time lag (ms) of all the topic partitions consumed by the consumer group.");
+const TYPE: &str = formatcp!("{HEADER_TYPE} {NAME} {TYPE_GAUGE}");
+
+pub(crate) fn append_headers(res: &mut String) {
+    let _ = writeln!(res, "{HELP}\n{TYPE}");
+}
+
+pub(crate) fn append_metric(cluster_id: &str, group: &Group, time_lag_ms: i64, res: &mut String) {
+    let group_labels = render_group_labels(group);
+    let group = &group.name;
+
+    let _ = writeln!(
+        res,
+        "{NAME}\
+        {{\
+            {LABEL_CLUSTER_ID}=\"{cluster_id}\",\
+            {LABEL_GROUP}=\"{group}\"{group_labels}\
+        }} \
+        {time_lag_ms}"
+    );
+}
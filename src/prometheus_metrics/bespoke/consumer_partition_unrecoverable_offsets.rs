@@ -0,0 +1,52 @@
+use std::fmt::Write;
+
+use const_format::formatcp;
+
+use crate::kafka_types::{Group, Member};
+
+use super::super::{
+    LABEL_CLUSTER_ID, LABEL_GROUP, LABEL_GROUP_INSTANCE_ID, LABEL_MEMBER_CLIENT_ID,
+    LABEL_MEMBER_HOST, LABEL_MEMBER_ID, LABEL_PARTITION, LABEL_TOPIC, NAMESPACE,
+};
+use super::{normalize_owner_data, render_group_labels, HEADER_HELP, HEADER_TYPE, TYPE_GAUGE};
+
+pub(crate) const NAME: &str =
+    formatcp!("{NAMESPACE}_kafka_consumer_partition_unrecoverable_offsets");
+const HELP: &str =
+    formatcp!("{HEADER_HELP} {NAME} Amount of offsets of the topic partition that were deleted by retention, before the consumer could consume them.");
+const TYPE: &str = formatcp!("{HEADER_TYPE} {NAME} {TYPE_GAUGE}");
+
+pub(crate) fn append_headers(res: &mut String) {
+    let _ = writeln!(res, "{HELP}\n{TYPE}");
+}
+
+pub(crate) fn append_metric(
+    cluster_id: &str,
+    group: &Group,
+    topic: &str,
+    partition: u32,
+    owner: Option<&Member>,
+    unrecoverable_offsets: u64,
+    res: &mut String,
+) {
+    let (member_id, member_host, member_client_id, group_instance_id) = normalize_owner_data(owner);
+
+    let group_labels = render_group_labels(group);
+    let group = &group.name;
+
+    let _ = writeln!(
+        res,
+        "{NAME}\
+        {{\
+            {LABEL_CLUSTER_ID}=\"{cluster_id}\",\
+            {LABEL_GROUP}=\"{group}\",\
+            {LABEL_TOPIC}=\"{topic}\",\
+            {LABEL_PARTITION}=\"{partition}\",\
+            {LABEL_MEMBER_ID}=\"{member_id}\",\
+            {LABEL_MEMBER_HOST}=\"{member_host}\",\
+            {LABEL_MEMBER_CLIENT_ID}=\"{member_client_id}\",\
+            {LABEL_GROUP_INSTANCE_ID}=\"{group_instance_id}\"{group_labels}\
+        }} \
+        {unrecoverable_offsets}"
+    );
+}
@@ -1,13 +1,27 @@
+pub mod cluster_lagging_groups;
+pub mod cluster_total_offset_lag;
+pub mod consumer_group_lag_milliseconds;
+pub mod consumer_group_lag_offset;
+pub mod consumer_member_offset_lag_sum;
+pub mod consumer_partition_behind_retention;
+pub mod consumer_partition_lag_bytes;
 pub mod consumer_partition_lag_milliseconds;
 pub mod consumer_partition_lag_offset;
+pub mod consumer_partition_never_committed;
 pub mod consumer_partition_offset;
+pub mod consumer_partition_unrecoverable_offsets;
+pub mod consumer_topic_lag_milliseconds;
+pub mod consumer_topic_lag_offset;
 pub mod partition_earliest_available_offset;
 pub mod partition_earliest_tracked_offset;
 pub mod partition_latest_available_offset;
 pub mod partition_latest_tracked_offset;
+pub mod partition_produced_records_rate;
 
-use crate::kafka_types::Member;
-use crate::lag_register::{Lag, LagRegister};
+use std::fmt::{self, Display, Formatter};
+
+use crate::kafka_types::{Group, Member};
+use crate::lag_register::{Lag, LagSnapshot};
 
 use super::UNKNOWN_VAL;
 
@@ -18,36 +32,92 @@ const TYPE_GAUGE: &str = "gauge";
 const HEADER_HELP: &str = "# HELP";
 const HEADER_TYPE: &str = "# TYPE";
 
-fn normalize_owner_data(opt_owner: Option<&Member>) -> (&str, &str, &str) {
+/// Owner labels values: member id, host, client id and `group.instance.id`.
+///
+/// The member id is the [`Member::stable_id`], so that static Members don't churn time series
+/// when they restart; the `group.instance.id` is empty, unless the Member is static.
+fn normalize_owner_data(opt_owner: Option<&Member>) -> (&str, &str, &str, &str) {
     if let Some(o) = opt_owner {
-        (o.id.as_ref(), o.client_host.as_ref(), o.client_id.as_ref())
+        (
+            o.stable_id(),
+            o.client_host.as_ref(),
+            o.client_id.as_ref(),
+            o.group_instance_id.as_deref().unwrap_or_default(),
+        )
     } else {
-        (UNKNOWN_VAL, UNKNOWN_VAL, UNKNOWN_VAL)
+        (UNKNOWN_VAL, UNKNOWN_VAL, UNKNOWN_VAL, UNKNOWN_VAL)
+    }
+}
+
+/// Extra labels of the Consumer Group (see [`Group::labels`]), each as `,name="value"`,
+/// to follow the other labels.
+///
+/// They are written straight into the output when formatted, instead of being allocated.
+fn render_group_labels(group: &Group) -> GroupLabels<'_> {
+    GroupLabels(group)
+}
+
+struct GroupLabels<'a>(&'a Group);
+
+impl Display for GroupLabels<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.0.labels.iter() {
+            write!(f, ",{name}=\"")?;
+            write_escaped_label_value(f, value)?;
+            f.write_str("\"")?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a label value, escaped as the Prometheus text format requires.
+fn write_escaped_label_value(f: &mut Formatter<'_>, value: &str) -> fmt::Result {
+    let mut rest = value;
+    while let Some(i) = rest.find(['\\', '"', '\n']) {
+        f.write_str(&rest[..i])?;
+        f.write_str(match rest.as_bytes()[i] {
+            b'\\' => "\\\\",
+            b'"' => "\\\"",
+            _ => "\\n",
+        })?;
+        rest = &rest[i + 1..];
     }
+    f.write_str(rest)
 }
 
-type IterLagRegisterFn = fn(
+/// A sample value followed by its timestamp, or `-1` (i.e. unknown) if there is none.
+struct ValueAndTimestamp<V>(Option<(V, i64)>);
+
+impl<V: Display> Display for ValueAndTimestamp<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some((value, ts)) => write!(f, "{value} {ts}"),
+            None => f.write_str("-1"),
+        }
+    }
+}
+
+type IterLagSnapshotFn = fn(
     cluster_id: &str,
-    group: &str,
+    group: &Group,
     topic: &str,
     partition: u32,
     owner: Option<&Member>,
     lag: Option<&Lag>,
-    res: &mut Vec<String>,
+    res: &mut String,
 );
 
-/// Helper to iterate over the content of a [`LagRegister`], to apply a given [`IterLagRegisterFn`].
-pub async fn iter_lag_reg(
-    lag_reg: &LagRegister,
-    metrics_vec: &mut Vec<String>,
-    cluster_id: &str,
-    ilrf: IterLagRegisterFn,
+/// Helper to iterate over the content of a [`LagSnapshot`], to apply a given [`IterLagSnapshotFn`].
+pub fn iter_lag_snapshot(
+    snapshot: &LagSnapshot,
+    metrics_vec: &mut String,
+    ilsf: IterLagSnapshotFn,
 ) {
-    for (g, gwl) in lag_reg.lag_by_group.read().await.iter() {
+    for gwl in snapshot.groups.values() {
         for (tp, lwo) in gwl.lag_by_topic_partition.iter() {
-            ilrf(
-                cluster_id,
-                g,
+            ilsf(
+                &snapshot.cluster_id,
+                &gwl.group,
                 tp.topic.as_ref(),
                 tp.partition,
                 lwo.owner.as_ref(),
@@ -57,3 +127,22 @@ pub async fn iter_lag_reg(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_escaped_group_labels() {
+        let group = Group {
+            labels: [("team", "pay\"ments"), ("tier", "1\\2\n")]
+                .into_iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(render_group_labels(&group).to_string(), r#",team="pay\"ments",tier="1\\2\n""#);
+        assert_eq!(ValueAndTimestamp(Some((10_u64, 1000))).to_string(), "10 1000");
+        assert_eq!(ValueAndTimestamp::<u64>(None).to_string(), "-1");
+    }
+}
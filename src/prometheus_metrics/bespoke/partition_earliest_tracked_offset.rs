@@ -1,16 +1,17 @@
+use std::fmt::Write;
+
 use const_format::formatcp;
 
 use super::super::{LABEL_CLUSTER_ID, LABEL_PARTITION, LABEL_TOPIC, NAMESPACE};
 use super::{HEADER_HELP, HEADER_TYPE, TYPE_GAUGE};
 
-const NAME: &str = formatcp!("{NAMESPACE}_kafka_partition_earliest_tracked_offset");
+pub(crate) const NAME: &str = formatcp!("{NAMESPACE}_kafka_partition_earliest_tracked_offset");
 const HELP: &str =
     formatcp!("{HEADER_HELP} {NAME} Earliest offset tracked to estimate the lag of consumers of the topic partition.");
 const TYPE: &str = formatcp!("{HEADER_TYPE} {NAME} {TYPE_GAUGE}");
 
-pub(crate) fn append_headers(res: &mut Vec<String>) {
-    res.push(HELP.into());
-    res.push(TYPE.into());
+pub(crate) fn append_headers(res: &mut String) {
+    let _ = writeln!(res, "{HELP}\n{TYPE}");
 }
 
 pub(crate) fn append_metric(
@@ -19,9 +20,10 @@ pub(crate) fn append_metric(
     partition: u32,
     offset: u64,
     offset_timestamp_utc_ms: i64,
-    res: &mut Vec<String>,
+    res: &mut String,
 ) {
-    res.push(format!(
+    let _ = writeln!(
+        res,
         "{NAME}\
         {{\
             {LABEL_CLUSTER_ID}=\"{cluster_id}\",\
@@ -30,5 +32,5 @@ pub(crate) fn append_metric(
         }} \
         {offset} \
         {offset_timestamp_utc_ms}"
-    ));
+    );
 }
@@ -1,16 +1,17 @@
+use std::fmt::Write;
+
 use const_format::formatcp;
 
 use super::super::{LABEL_CLUSTER_ID, LABEL_PARTITION, LABEL_TOPIC, NAMESPACE};
 use super::{HEADER_HELP, HEADER_TYPE, TYPE_GAUGE};
 
-const NAME: &str = formatcp!("{NAMESPACE}_kafka_partition_latest_available_offset");
+pub(crate) const NAME: &str = formatcp!("{NAMESPACE}_kafka_partition_latest_available_offset");
 const HELP: &str =
     formatcp!("{HEADER_HELP} {NAME} Latest offset available to consumers of the topic partition.");
 const TYPE: &str = formatcp!("{HEADER_TYPE} {NAME} {TYPE_GAUGE}");
 
-pub(crate) fn append_headers(res: &mut Vec<String>) {
-    res.push(HELP.into());
-    res.push(TYPE.into());
+pub(crate) fn append_headers(res: &mut String) {
+    let _ = writeln!(res, "{HELP}\n{TYPE}");
 }
 
 pub(crate) fn append_metric(
@@ -18,9 +19,10 @@ pub(crate) fn append_metric(
     topic: &str,
     partition: u32,
     offset: u64,
-    res: &mut Vec<String>,
+    res: &mut String,
 ) {
-    res.push(format!(
+    let _ = writeln!(
+        res,
         "{NAME}\
         {{\
             {LABEL_CLUSTER_ID}=\"{cluster_id}\",\
@@ -28,5 +30,5 @@ pub(crate) fn append_metric(
             {LABEL_PARTITION}=\"{partition}\"\
         }} \
         {offset}"
-    ));
+    );
 }
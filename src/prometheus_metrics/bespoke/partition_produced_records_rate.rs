@@ -0,0 +1,34 @@
+use std::fmt::Write;
+
+use const_format::formatcp;
+
+use super::super::{LABEL_CLUSTER_ID, LABEL_PARTITION, LABEL_TOPIC, NAMESPACE};
+use super::{HEADER_HELP, HEADER_TYPE, TYPE_GAUGE};
+
+pub(crate) const NAME: &str = formatcp!("{NAMESPACE}_kafka_partition_produced_records_rate");
+const HELP: &str =
+    formatcp!("{HEADER_HELP} {NAME} Estimated rate (records per second) at which records are produced to the topic partition, over the tracked offsets.");
+const TYPE: &str = formatcp!("{HEADER_TYPE} {NAME} {TYPE_GAUGE}");
+
+pub(crate) fn append_headers(res: &mut String) {
+    let _ = writeln!(res, "{HELP}\n{TYPE}");
+}
+
+pub(crate) fn append_metric(
+    cluster_id: &str,
+    topic: &str,
+    partition: u32,
+    rate: f64,
+    res: &mut String,
+) {
+    let _ = writeln!(
+        res,
+        "{NAME}\
+        {{\
+            {LABEL_CLUSTER_ID}=\"{cluster_id}\",\
+            {LABEL_TOPIC}=\"{topic}\",\
+            {LABEL_PARTITION}=\"{partition}\"\
+        }} \
+        {rate}"
+    );
+}
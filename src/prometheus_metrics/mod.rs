@@ -1,4 +1,5 @@
 pub mod bespoke;
+mod sink;
 
 use std::collections::HashMap;
 
@@ -10,6 +11,8 @@ use tokio::time::Duration;
 
 use crate::constants::DEFAULT_CLUSTER_ID;
 
+pub use sink::{MetricsGranularity, PrometheusSink};
+
 pub const NAMESPACE: &str = "kmtd";
 
 pub const LABEL_CLUSTER_ID: &str = "cluster_id";
@@ -19,6 +22,21 @@ pub const LABEL_PARTITION: &str = "partition";
 pub const LABEL_MEMBER_ID: &str = "member_id";
 pub const LABEL_MEMBER_HOST: &str = "member_host";
 pub const LABEL_MEMBER_CLIENT_ID: &str = "member_client_id";
+pub const LABEL_GROUP_INSTANCE_ID: &str = "group_instance_id";
+pub const LABEL_PROTOCOL_TYPE: &str = "protocol_type";
+pub const LABEL_PROTOCOL: &str = "protocol";
+pub const LABEL_SINK: &str = "sink";
+pub const LABEL_SUBSYSTEM: &str = "subsystem";
+pub const LABEL_RULE: &str = "rule";
+pub const LABEL_KIND: &str = "kind";
+pub const LABEL_BROKER_ID: &str = "broker_id";
+pub const LABEL_BROKER_HOST: &str = "broker_host";
+pub const LABEL_BROKER_RACK: &str = "broker_rack";
+pub const LABEL_CONTROLLER: &str = "controller";
+pub const LABEL_CLIENT: &str = "client";
+pub const LABEL_QUANTILE: &str = "quantile";
+pub const LABEL_CONNECTOR: &str = "connector";
+pub const LABEL_ACTION: &str = "action";
 
 pub const UNKNOWN_VAL: &str = "UNKNOWN";
 
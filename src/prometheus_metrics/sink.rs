@@ -0,0 +1,757 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::RwLock;
+
+use super::bespoke::*;
+
+use crate::internals::{LagSink, LagSinkFuture};
+use crate::lag_register::{GroupTopicLag, LagSnapshot};
+use crate::sinks::RecordSizes;
+
+/// Estimated length of a rendered series (i.e. metric name, labels, value and timestamp),
+/// to size the rendering buffer before the first [`LagSnapshot`] is rendered.
+const EST_SERIES_LEN: usize = 256;
+
+/// Finest level of detail of the lag metrics rendered by [`PrometheusSink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum MetricsGranularity {
+    /// Lag of each (Consumer Group, Topic Partition) pair, and offsets of each Topic Partition.
+    #[default]
+    Partition,
+
+    /// Lag of each (Consumer Group, Topic) pair, aggregated across partitions.
+    Topic,
+
+    /// Lag of each Consumer Group, aggregated across all its Topic Partitions.
+    Group,
+}
+
+impl MetricsGranularity {
+    /// The next coarser level of detail, if any.
+    pub fn coarser(&self) -> Option<Self> {
+        match self {
+            MetricsGranularity::Partition => Some(MetricsGranularity::Topic),
+            MetricsGranularity::Topic => Some(MetricsGranularity::Group),
+            MetricsGranularity::Group => None,
+        }
+    }
+}
+
+/// A [`LagSink`] that renders each [`LagSnapshot`] in Prometheus Exposition format.
+///
+/// The latest rendering is kept in memory, ready to be served by the `/metrics` HTTP endpoint.
+/// The buffer of the one before it is reused for the next rendering.
+///
+/// It holds off serving it until the first [`LagSnapshot`] is rendered and, optionally,
+/// until warmed up: see [`Self::is_warmed_up`].
+#[derive(Debug, Default)]
+pub struct PrometheusSink {
+    latest: RwLock<String>,
+    spare: Mutex<String>,
+    never_committed_lag_span: bool,
+    granularity: MetricsGranularity,
+    coarsened: Mutex<Option<MetricsGranularity>>,
+    shrink_buffers: AtomicBool,
+    lagging_group_threshold: u64,
+    warm_up: Option<WarmUp>,
+    warmed_up: AtomicBool,
+    rendered: AtomicBool,
+    record_sizes: Option<Arc<RecordSizes>>,
+}
+
+/// Gate that keeps [`PrometheusSink`] from serving lag metrics, until enough of them are known.
+#[derive(Debug)]
+struct WarmUp {
+    min_coverage_pct: f64,
+    timeout: Duration,
+    since: Instant,
+}
+
+impl PrometheusSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report the Offset Lag of consumers that never committed an offset for a Topic Partition,
+    /// as the whole span of offsets available in it (i.e. latest - earliest), instead of unknown.
+    pub fn with_never_committed_lag_span(mut self, never_committed_lag_span: bool) -> Self {
+        self.never_committed_lag_span = never_committed_lag_span;
+        self
+    }
+
+    /// Render the lag metrics only down to the given [`MetricsGranularity`]: coarser levels
+    /// reduce the amount of series by orders of magnitude, on very large installations.
+    pub fn with_granularity(mut self, granularity: MetricsGranularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    /// Count a Consumer Group as lagging, in the cluster-wide metrics, if its Offset Lag
+    /// is above the given threshold.
+    pub fn with_lagging_group_threshold(mut self, lagging_group_threshold: u64) -> Self {
+        self.lagging_group_threshold = lagging_group_threshold;
+        self
+    }
+
+    /// Also render the estimated lag in bytes of each (Consumer Group, Topic Partition) pair,
+    /// based on the given [`RecordSizes`], if any.
+    pub fn with_record_sizes(mut self, record_sizes: Option<Arc<RecordSizes>>) -> Self {
+        self.record_sizes = record_sizes;
+        self
+    }
+
+    /// Consider the sink warmed up only once the lag of at least `min_coverage_pct` percent
+    /// of the known (Consumer Group, Topic Partition) pairs is known, or after `timeout`.
+    ///
+    /// Until then, serving the lag metrics would report misleading zero lag: see
+    /// [`Self::is_warmed_up`]. A `min_coverage_pct` of zero disables this.
+    pub fn with_warm_up(mut self, min_coverage_pct: f64, timeout: Duration) -> Self {
+        self.warm_up = (min_coverage_pct > 0_f64).then(|| WarmUp {
+            min_coverage_pct,
+            timeout,
+            since: Instant::now(),
+        });
+        self
+    }
+
+    /// Coarsen the [`MetricsGranularity`] by one level, to shed memory: the rendering buffers
+    /// are shrunk at the next rendering.
+    ///
+    /// Returns the new [`MetricsGranularity`], or `None` if already at the coarsest.
+    pub fn coarsen(&self) -> Option<MetricsGranularity> {
+        let mut coarsened = self.coarsened.lock().expect("Coarsened granularity lock poisoned");
+        let next = coarsened.unwrap_or(self.granularity).coarser()?;
+        *coarsened = Some(next);
+
+        *self.spare.lock().expect("Spare buffer lock poisoned") = String::new();
+        self.shrink_buffers.store(true, Ordering::Relaxed);
+        Some(next)
+    }
+
+    /// The [`MetricsGranularity`] lag metrics are rendered at: the configured one,
+    /// unless coarsened since.
+    pub fn granularity(&self) -> MetricsGranularity {
+        self.coarsened
+            .lock()
+            .expect("Coarsened granularity lock poisoned")
+            .unwrap_or(self.granularity)
+    }
+
+    /// Approximate memory (bytes) taken by the rendering buffers.
+    pub async fn estimate_memory(&self) -> usize {
+        self.latest.read().await.capacity()
+            + self.spare.lock().expect("Spare buffer lock poisoned").capacity()
+    }
+
+    /// Whether enough lag data is known, for the lag metrics to be served: the first
+    /// [`LagSnapshot`] was rendered and, if configured via [`Self::with_warm_up`], it had
+    /// enough lag data.
+    pub fn is_warmed_up(&self) -> bool {
+        self.rendered.load(Ordering::Relaxed)
+            && (self.warm_up.is_none() || self.warmed_up.load(Ordering::Relaxed))
+    }
+
+    /// Open the warm-up gate, if the [`LagSnapshot`] has enough lag data, or if it timed out.
+    fn warm_up(&self, snapshot: &LagSnapshot) {
+        let Some(wu) = self.warm_up.as_ref() else {
+            return;
+        };
+        if self.warmed_up.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let coverage_pct = snapshot.data_coverage() * 100_f64;
+        if coverage_pct >= wu.min_coverage_pct {
+            info!("Warmed up: lag known for {coverage_pct:.1}% of group partitions");
+        } else if wu.since.elapsed() >= wu.timeout {
+            warn!(
+                "Warm-up timed out after {:?}: lag known for only {coverage_pct:.1}% of group partitions, serving anyway",
+                wu.timeout
+            );
+        } else {
+            debug!("Warming up: lag known for {coverage_pct:.1}% of group partitions");
+            return;
+        }
+        self.warmed_up.store(true, Ordering::Relaxed);
+    }
+
+    /// The latest [`LagSnapshot`], rendered in Prometheus Exposition format.
+    ///
+    /// It will be empty until the first [`LagSnapshot`] is published.
+    pub async fn latest(&self) -> String {
+        self.latest.read().await.clone()
+    }
+
+    /// Render the [`LagSnapshot`] into `body`, clearing it first.
+    fn render(&self, snapshot: &LagSnapshot, body: &mut String) {
+        body.clear();
+
+        // Reserve enough capacity upfront, to do as little mem-alloc as possible while rendering:
+        // once the buffer has been used before, it's usually big enough already.
+        //
+        // The capacity is necessarily a function of the number of series produced,
+        // at the configured granularity.
+        let granularity = self.granularity();
+        let series_count = match granularity {
+            MetricsGranularity::Partition => {
+                let per_group_partition = if self.record_sizes.is_some() {
+                    6
+                } else {
+                    5
+                };
+                snapshot.group_partitions_count() * per_group_partition
+                    + snapshot.partitions.len() * 4
+            },
+            MetricsGranularity::Topic => snapshot.group_partitions_count() * 2,
+            MetricsGranularity::Group => snapshot.groups.len() * 2,
+        };
+        body.reserve(series_count * EST_SERIES_LEN);
+
+        match granularity {
+            MetricsGranularity::Partition => self.render_partitions(snapshot, body),
+            MetricsGranularity::Topic => Self::render_topics(snapshot, body),
+            MetricsGranularity::Group => Self::render_groups(snapshot, body),
+        }
+
+        // --- CLUSTER METRICS ---
+        //
+        // TODO https://github.com/kafkesc/kommitted/issues/54
+        //
+        // Aggregated here, once per snapshot, so that the headline numbers don't require
+        // expensive queries across all the other series.
+        let cluster_lag = snapshot.cluster_lag(self.lagging_group_threshold);
+
+        // ------------------------------------------------------- METRIC: cluster_total_offset_lag
+        cluster_total_offset_lag::append_headers(body);
+        cluster_total_offset_lag::append_metric(&snapshot.cluster_id, cluster_lag.offset_lag, body);
+
+        // --------------------------------------------------------- METRIC: cluster_lagging_groups
+        cluster_lagging_groups::append_headers(body);
+        cluster_lagging_groups::append_metric(
+            &snapshot.cluster_id,
+            cluster_lag.lagging_groups,
+            body,
+        );
+
+        // --- KOMMITTED INTERNAL METRICS ---
+        //
+        // TODO https://github.com/kafkesc/kommitted/issues/56
+        // TODO https://github.com/kafkesc/kommitted/issues/57
+    }
+
+    /// Render the lag of each (Consumer Group, Topic Partition) pair, and the offsets
+    /// of each Topic Partition.
+    fn render_partitions(&self, snapshot: &LagSnapshot, body: &mut String) {
+        // ------------------------------------------------------- METRIC: consumer_partition_offset
+        consumer_partition_offset::append_headers(body);
+        iter_lag_snapshot(snapshot, body, consumer_partition_offset::append_metric);
+
+        // --------------------------------------------------- METRIC: consumer_partition_lag_offset
+        consumer_partition_lag_offset::append_headers(body);
+        if self.never_committed_lag_span {
+            let available = snapshot.available_offsets();
+            for (g, tp, lwo) in snapshot.iter_group_partitions() {
+                match (lwo.lag(), available.get(tp)) {
+                    (None, Some((eao, lao))) => {
+                        consumer_partition_lag_offset::append_metric_never_committed(
+                            &snapshot.cluster_id,
+                            &snapshot.groups[g].group,
+                            &tp.topic,
+                            tp.partition,
+                            lwo.owner(),
+                            lao.saturating_sub(*eao),
+                            body,
+                        )
+                    },
+                    (lag, _) => consumer_partition_lag_offset::append_metric(
+                        &snapshot.cluster_id,
+                        &snapshot.groups[g].group,
+                        &tp.topic,
+                        tp.partition,
+                        lwo.owner(),
+                        lag,
+                        body,
+                    ),
+                }
+            }
+        } else {
+            iter_lag_snapshot(snapshot, body, consumer_partition_lag_offset::append_metric);
+        }
+
+        // --------------------------------------------- METRIC: consumer_partition_lag_milliseconds
+        consumer_partition_lag_milliseconds::append_headers(body);
+        iter_lag_snapshot(snapshot, body, consumer_partition_lag_milliseconds::append_metric);
+
+        // ---------------------------------------------------- METRIC: consumer_partition_lag_bytes
+        if let Some(rs) = self.record_sizes.as_ref() {
+            let bytes_per_offset = rs.get_bytes_per_offset();
+            consumer_partition_lag_bytes::append_headers(body);
+            for (g, tp, lwo) in snapshot.iter_group_partitions() {
+                let Some(lag) = lwo.lag() else {
+                    continue;
+                };
+                if let Some(lag_bytes) =
+                    RecordSizes::estimate_bytes(&bytes_per_offset, &tp.topic, lag.offset_lag)
+                {
+                    consumer_partition_lag_bytes::append_metric(
+                        &snapshot.cluster_id,
+                        &snapshot.groups[g].group,
+                        &tp.topic,
+                        tp.partition,
+                        lwo.owner(),
+                        lag_bytes,
+                        lag.offset_timestamp.timestamp_millis(),
+                        body,
+                    );
+                }
+            }
+        }
+
+        // ---------------------------------------------- METRIC: consumer_partition_never_committed
+        consumer_partition_never_committed::append_headers(body);
+        iter_lag_snapshot(snapshot, body, consumer_partition_never_committed::append_metric);
+
+        // --------------------------------------------- METRIC: consumer_partition_behind_retention
+        let unrecoverable_offsets = snapshot.unrecoverable_offsets();
+        consumer_partition_behind_retention::append_headers(body);
+        for (g, tp, lwo, uo) in unrecoverable_offsets.iter() {
+            consumer_partition_behind_retention::append_metric(
+                &snapshot.cluster_id,
+                &snapshot.groups[*g].group,
+                &tp.topic,
+                tp.partition,
+                lwo.owner(),
+                *uo,
+                body,
+            );
+        }
+
+        // ---------------------------------------- METRIC: consumer_partition_unrecoverable_offsets
+        consumer_partition_unrecoverable_offsets::append_headers(body);
+        for (g, tp, lwo, uo) in unrecoverable_offsets.iter() {
+            consumer_partition_unrecoverable_offsets::append_metric(
+                &snapshot.cluster_id,
+                &snapshot.groups[*g].group,
+                &tp.topic,
+                tp.partition,
+                lwo.owner(),
+                *uo,
+                body,
+            );
+        }
+
+        // -------------------------------------------------- METRIC: consumer_member_offset_lag_sum
+        consumer_member_offset_lag_sum::append_headers(body);
+        for ((g, _), (member, offset_lag)) in snapshot.lag_by_group_member() {
+            consumer_member_offset_lag_sum::append_metric(
+                &snapshot.cluster_id,
+                &snapshot.groups[g].group,
+                member,
+                offset_lag,
+                body,
+            );
+        }
+
+        // --------------------------------------------- METRIC: partition_earliest_available_offset
+        partition_earliest_available_offset::append_headers(body);
+        for ps in snapshot.partitions.iter() {
+            match &ps.earliest_available_offset {
+                Ok(eao) => {
+                    partition_earliest_available_offset::append_metric(
+                        &snapshot.cluster_id,
+                        &ps.topic_partition.topic,
+                        ps.topic_partition.partition,
+                        *eao,
+                        body,
+                    );
+                },
+                Err(e) => {
+                    warn!("Unable to generate 'partition_earliest_available_offset': {e}");
+                },
+            }
+        }
+
+        // ----------------------------------------------- METRIC: partition_latest_available_offset
+        partition_latest_available_offset::append_headers(body);
+        for ps in snapshot.partitions.iter() {
+            match &ps.latest_available_offset {
+                Ok(lao) => {
+                    partition_latest_available_offset::append_metric(
+                        &snapshot.cluster_id,
+                        &ps.topic_partition.topic,
+                        ps.topic_partition.partition,
+                        *lao,
+                        body,
+                    );
+                },
+                Err(e) => {
+                    warn!("Unable to generate 'partition_latest_available_offset': {e}");
+                },
+            }
+        }
+
+        // ----------------------------------------------- METRIC: partition_earliest_tracked_offset
+        partition_earliest_tracked_offset::append_headers(body);
+        for ps in snapshot.partitions.iter() {
+            match &ps.earliest_tracked_offset {
+                Ok(eto) => {
+                    partition_earliest_tracked_offset::append_metric(
+                        &snapshot.cluster_id,
+                        &ps.topic_partition.topic,
+                        ps.topic_partition.partition,
+                        eto.offset,
+                        eto.at.timestamp_millis(),
+                        body,
+                    );
+                },
+                Err(e) => {
+                    warn!("Unable to generate 'partition_earliest_tracked_offset': {e}");
+                },
+            }
+        }
+
+        // ------------------------------------------------- METRIC: partition_latest_tracked_offset
+        partition_latest_tracked_offset::append_headers(body);
+        for ps in snapshot.partitions.iter() {
+            match &ps.latest_tracked_offset {
+                Ok(lto) => {
+                    partition_latest_tracked_offset::append_metric(
+                        &snapshot.cluster_id,
+                        &ps.topic_partition.topic,
+                        ps.topic_partition.partition,
+                        lto.offset,
+                        lto.at.timestamp_millis(),
+                        body,
+                    );
+                },
+                Err(e) => {
+                    warn!("Unable to generate 'partition_latest_tracked_offset': {e}");
+                },
+            }
+        }
+
+        // -------------------------------------------------- METRIC: partition_produced_records_rate
+        partition_produced_records_rate::append_headers(body);
+        for ps in snapshot.partitions.iter() {
+            match &ps.produced_records_rate {
+                Ok(rate) => {
+                    partition_produced_records_rate::append_metric(
+                        &snapshot.cluster_id,
+                        &ps.topic_partition.topic,
+                        ps.topic_partition.partition,
+                        *rate,
+                        body,
+                    );
+                },
+                // Until the offsets of the partition are fetched at least twice, the rate is not known
+                Err(e) => {
+                    debug!("Unable to generate 'partition_produced_records_rate': {e}");
+                },
+            }
+        }
+    }
+
+    /// Render the lag of each (Consumer Group, Topic) pair, aggregated across partitions.
+    fn render_topics(snapshot: &LagSnapshot, body: &mut String) {
+        let lag_by_group_topic = snapshot.lag_by_group_topic();
+
+        // --------------------------------------------------------- METRIC: consumer_topic_lag_offset
+        consumer_topic_lag_offset::append_headers(body);
+        for ((g, t), gtl) in lag_by_group_topic.iter() {
+            consumer_topic_lag_offset::append_metric(
+                &snapshot.cluster_id,
+                &snapshot.groups[*g].group,
+                t,
+                gtl.offset_lag,
+                body,
+            );
+        }
+
+        // --------------------------------------------------- METRIC: consumer_topic_lag_milliseconds
+        consumer_topic_lag_milliseconds::append_headers(body);
+        for ((g, t), gtl) in lag_by_group_topic.iter() {
+            consumer_topic_lag_milliseconds::append_metric(
+                &snapshot.cluster_id,
+                &snapshot.groups[*g].group,
+                t,
+                gtl.time_lag.num_milliseconds(),
+                body,
+            );
+        }
+    }
+
+    /// Render the lag of each Consumer Group, aggregated across all its Topic Partitions.
+    fn render_groups(snapshot: &LagSnapshot, body: &mut String) {
+        let mut lag_by_group: BTreeMap<&str, GroupTopicLag> = BTreeMap::new();
+        for ((g, _), gtl) in snapshot.lag_by_group_topic() {
+            let agg = lag_by_group.entry(g).or_default();
+            agg.offset_lag += gtl.offset_lag;
+            agg.time_lag = agg.time_lag.max(gtl.time_lag);
+        }
+
+        // --------------------------------------------------------- METRIC: consumer_group_lag_offset
+        consumer_group_lag_offset::append_headers(body);
+        for (g, gl) in lag_by_group.iter() {
+            consumer_group_lag_offset::append_metric(
+                &snapshot.cluster_id,
+                &snapshot.groups[*g].group,
+                gl.offset_lag,
+                body,
+            );
+        }
+
+        // --------------------------------------------------- METRIC: consumer_group_lag_milliseconds
+        consumer_group_lag_milliseconds::append_headers(body);
+        for (g, gl) in lag_by_group.iter() {
+            consumer_group_lag_milliseconds::append_metric(
+                &snapshot.cluster_id,
+                &snapshot.groups[*g].group,
+                gl.time_lag.num_milliseconds(),
+                body,
+            );
+        }
+    }
+}
+
+impl LagSink for PrometheusSink {
+    fn name(&self) -> &'static str {
+        "prometheus"
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let mut body =
+                std::mem::take(&mut *self.spare.lock().expect("Spare buffer lock poisoned"));
+            self.render(snapshot, &mut body);
+
+            // The latest rendering is replaced, and its buffer kept for the next one
+            let prev = std::mem::replace(&mut *self.latest.write().await, body);
+            if !self.shrink_buffers.swap(false, Ordering::Relaxed) {
+                *self.spare.lock().expect("Spare buffer lock poisoned") = prev;
+            }
+            self.rendered.store(true, Ordering::Relaxed);
+            self.warm_up(snapshot);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kafka_types::{Member, TopicPartition};
+    use crate::lag_register::{GroupWithLag, Lag, LagWithOwner};
+    use crate::partition_offsets::{PartitionOffsetsError, PartitionOffsetsSnapshot};
+    use std::collections::BTreeSet;
+
+    /// Snapshot of Groups `g` and `h`, lagging on partitions of Topics `t1` and `t2`.
+    fn snapshot() -> LagSnapshot {
+        let lwo = |offset_lag, time_lag_ms| LagWithOwner {
+            lag: Some(Lag {
+                offset_lag,
+                time_lag: chrono::Duration::milliseconds(time_lag_ms),
+                ..Default::default()
+            }),
+            owner: None,
+        };
+
+        let mut snapshot = LagSnapshot {
+            cluster_id: "c".to_string(),
+            ..Default::default()
+        };
+        for (g, lags) in [
+            ("g", vec![("t1", 0, lwo(10, 100)), ("t1", 1, lwo(5, 300)), ("t2", 0, lwo(1, 1))]),
+            ("h", vec![("t1", 0, lwo(2, 50)), ("t2", 0, LagWithOwner::default())]),
+        ] {
+            let mut gwl = GroupWithLag::default();
+            gwl.group.name = g.into();
+            for (t, p, lwo) in lags {
+                gwl.lag_by_topic_partition.insert(TopicPartition::new(t, p), lwo);
+            }
+            snapshot.groups.insert(g.into(), Arc::new(gwl));
+        }
+        snapshot
+    }
+
+    /// Names of the series in the rendered `body`.
+    fn series_names(body: &str) -> BTreeSet<&str> {
+        body.lines().filter(|l| !l.starts_with('#')).filter_map(|l| l.split('{').next()).collect()
+    }
+
+    /// Snapshot of Group `g`, whose Member `m1` never committed for `t:0` (offsets 10 to 70
+    /// available), but did for `t:1`.
+    fn never_committed_snapshot() -> LagSnapshot {
+        let owner = Some(Member {
+            id: "m1".to_string(),
+            client_id: "client".to_string(),
+            client_host: "host".to_string(),
+            group_instance_id: None,
+        });
+
+        let mut gwl = GroupWithLag::default();
+        gwl.group.name = "g".into();
+        gwl.lag_by_topic_partition.insert(
+            TopicPartition::new("t", 0),
+            LagWithOwner {
+                lag: None,
+                owner: owner.clone(),
+            },
+        );
+        gwl.lag_by_topic_partition.insert(
+            TopicPartition::new("t", 1),
+            LagWithOwner {
+                lag: Some(Lag {
+                    offset_lag: 5,
+                    offset_timestamp: chrono::DateTime::from_timestamp_millis(1000).unwrap(),
+                    ..Default::default()
+                }),
+                owner,
+            },
+        );
+
+        let mut snapshot = LagSnapshot {
+            cluster_id: "c".to_string(),
+            ..Default::default()
+        };
+        snapshot.groups.insert("g".into(), Arc::new(gwl));
+        snapshot.partitions.push(PartitionOffsetsSnapshot {
+            topic_partition: TopicPartition::new("t", 0),
+            earliest_available_offset: Ok(10),
+            latest_available_offset: Ok(70),
+            earliest_tracked_offset: Err(PartitionOffsetsError::LagEstimatorNotReady),
+            latest_tracked_offset: Err(PartitionOffsetsError::LagEstimatorNotReady),
+            produced_records_rate: Err(PartitionOffsetsError::LagEstimatorNotReady),
+        });
+        snapshot
+    }
+
+    /// Value (and timestamp, if any) of the series `name` for Topic Partition `t:{partition}`.
+    fn partition_value<'a>(body: &'a str, name: &str, partition: u32) -> &'a str {
+        let labels = format!(
+            "{{cluster_id=\"c\",group=\"g\",topic=\"t\",partition=\"{partition}\",member_id=\"m1\",member_host=\"host\",member_client_id=\"client\",group_instance_id=\"\"}} "
+        );
+        body.lines()
+            .find_map(|l| l.strip_prefix(name)?.strip_prefix(labels.as_str()))
+            .unwrap_or_else(|| panic!("No {name} series for partition {partition} in {body}"))
+    }
+
+    #[test]
+    fn renders_never_committed_partitions() {
+        let snapshot = never_committed_snapshot();
+        let render = |never_committed_lag_span| {
+            let sink =
+                PrometheusSink::new().with_never_committed_lag_span(never_committed_lag_span);
+            let mut body = String::new();
+            sink.render(&snapshot, &mut body);
+            body
+        };
+
+        for never_committed_lag_span in [false, true] {
+            let body = render(never_committed_lag_span);
+            assert_eq!(partition_value(&body, consumer_partition_never_committed::NAME, 0), "1");
+            assert_eq!(partition_value(&body, consumer_partition_never_committed::NAME, 1), "0");
+            assert_eq!(partition_value(&body, consumer_partition_lag_offset::NAME, 1), "5 1000");
+        }
+
+        // Unknown lag, unless reported as the whole span of offsets available
+        let body = render(false);
+        assert_eq!(partition_value(&body, consumer_partition_lag_offset::NAME, 0), "-1");
+        let body = render(true);
+        assert_eq!(partition_value(&body, consumer_partition_lag_offset::NAME, 0), "60");
+    }
+
+    #[tokio::test]
+    async fn renders_at_each_granularity() {
+        let snapshot = snapshot();
+        let render = |granularity| {
+            let sink = PrometheusSink::new().with_granularity(granularity);
+            let mut body = String::new();
+            sink.render(&snapshot, &mut body);
+            body
+        };
+        let cluster_series = [
+            format!("{}{{cluster_id=\"c\"}} 18", cluster_total_offset_lag::NAME),
+            format!("{}{{cluster_id=\"c\"}} 2", cluster_lagging_groups::NAME),
+        ];
+
+        let body = render(MetricsGranularity::Partition);
+        let names = series_names(&body);
+        assert!(names.contains(consumer_partition_lag_offset::NAME));
+        assert!(names.contains(consumer_partition_lag_milliseconds::NAME));
+        assert!(!names.contains(consumer_topic_lag_offset::NAME));
+        assert!(!names.contains(consumer_group_lag_offset::NAME));
+        assert_eq!(
+            body.lines().filter(|l| l.starts_with(consumer_partition_lag_offset::NAME)).count(),
+            5
+        );
+
+        let body = render(MetricsGranularity::Topic);
+        assert_eq!(
+            series_names(&body),
+            BTreeSet::from([
+                consumer_topic_lag_offset::NAME,
+                consumer_topic_lag_milliseconds::NAME,
+                cluster_total_offset_lag::NAME,
+                cluster_lagging_groups::NAME,
+            ])
+        );
+        for (g, t, offset_lag, time_lag_ms) in
+            [("g", "t1", 15, 300), ("g", "t2", 1, 1), ("h", "t1", 2, 50)]
+        {
+            let labels = format!("{{cluster_id=\"c\",group=\"{g}\",topic=\"{t}\"}}");
+            let lines = [
+                format!("{}{labels} {offset_lag}", consumer_topic_lag_offset::NAME),
+                format!("{}{labels} {time_lag_ms}", consumer_topic_lag_milliseconds::NAME),
+            ];
+            assert!(lines.iter().all(|l| body.lines().any(|bl| bl == l)), "{lines:?} in {body}");
+        }
+        assert!(!body.contains("group=\"h\",topic=\"t2\""));
+        assert!(cluster_series.iter().all(|l| body.lines().any(|bl| bl == l)));
+
+        let body = render(MetricsGranularity::Group);
+        assert_eq!(
+            series_names(&body),
+            BTreeSet::from([
+                consumer_group_lag_offset::NAME,
+                consumer_group_lag_milliseconds::NAME,
+                cluster_total_offset_lag::NAME,
+                cluster_lagging_groups::NAME,
+            ])
+        );
+        for (g, offset_lag, time_lag_ms) in [("g", 16, 300), ("h", 2, 50)] {
+            let labels = format!("{{cluster_id=\"c\",group=\"{g}\"}}");
+            let lines = [
+                format!("{}{labels} {offset_lag}", consumer_group_lag_offset::NAME),
+                format!("{}{labels} {time_lag_ms}", consumer_group_lag_milliseconds::NAME),
+            ];
+            assert!(lines.iter().all(|l| body.lines().any(|bl| bl == l)), "{lines:?} in {body}");
+        }
+        assert!(cluster_series.iter().all(|l| body.lines().any(|bl| bl == l)));
+    }
+
+    #[tokio::test]
+    async fn not_warmed_up_until_first_rendered() {
+        let sink = PrometheusSink::new();
+        assert!(!sink.is_warmed_up());
+
+        sink.publish(&LagSnapshot::default()).await.unwrap();
+        assert!(sink.is_warmed_up());
+    }
+
+    #[tokio::test]
+    async fn not_warmed_up_until_enough_lag_is_known() {
+        let sink = PrometheusSink::new().with_warm_up(50_f64, Duration::from_secs(3600));
+
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition
+            .insert(TopicPartition::new("t".to_string(), 0), LagWithOwner::default());
+        let mut snapshot = LagSnapshot::default();
+        snapshot.groups.insert("g".into(), Arc::new(gwl));
+
+        sink.publish(&snapshot).await.unwrap();
+        assert!(!sink.is_warmed_up());
+    }
+}
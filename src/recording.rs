@@ -0,0 +1,478 @@
+//! Record what the Emitters emit (including the `__consumer_offsets` records), to a
+//! [JSON Lines](https://jsonlines.org/) file, and replay it later without connecting to Kafka.
+//!
+//! Meant to reproduce issues reported by users, and to develop against realistic data:
+//! a replay drives the Registers (and so the HTTP endpoints) exactly as the recorded cluster did,
+//! at the same pace.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use konsumer_offsets::KonsumerOffsetsData;
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::cluster_status::ClusterStatus;
+use crate::consumer_groups::ConsumerGroupsDiff;
+use crate::internals::{Clock, ManualClock};
+use crate::partition_offsets::PartitionOffset;
+
+/// Capacity of the channels replayed records are sent over.
+const REPLAY_CHANNEL_SIZE: usize = 10_000;
+
+/// Whether to record to a file, or replay from one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordingConfig {
+    /// Record to the given file, replacing it if it exists.
+    Record(PathBuf),
+
+    /// Replay from the given file, instead of connecting to Kafka.
+    Replay(PathBuf),
+}
+
+/// Something emitted by one of the Emitters, as recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum Recorded {
+    ClusterStatus(Arc<ClusterStatus>),
+    ConsumerGroups(ConsumerGroupsDiff),
+    PartitionOffset(PartitionOffset),
+    KonsumerOffsets(KonsumerOffsetsData),
+}
+
+/// A line of the recording: what was emitted, and when.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Line {
+    at: DateTime<Utc>,
+
+    #[serde(flatten)]
+    recorded: Recorded,
+}
+
+/// Type emitted by one of the Emitters, that can be recorded and replayed.
+pub trait Recordable: Clone + Send + 'static {
+    fn into_recorded(self) -> Recorded;
+
+    /// Take the [`mpsc::Receiver`] this type is replayed over (only the first time).
+    fn take_replayed(receivers: &mut ReplayReceivers) -> Option<mpsc::Receiver<Self>>;
+}
+
+impl Recordable for Arc<ClusterStatus> {
+    fn into_recorded(self) -> Recorded {
+        Recorded::ClusterStatus(self)
+    }
+
+    fn take_replayed(receivers: &mut ReplayReceivers) -> Option<mpsc::Receiver<Self>> {
+        receivers.cluster_status.take()
+    }
+}
+
+impl Recordable for ConsumerGroupsDiff {
+    fn into_recorded(self) -> Recorded {
+        Recorded::ConsumerGroups(self)
+    }
+
+    fn take_replayed(receivers: &mut ReplayReceivers) -> Option<mpsc::Receiver<Self>> {
+        receivers.consumer_groups.take()
+    }
+}
+
+impl Recordable for PartitionOffset {
+    fn into_recorded(self) -> Recorded {
+        Recorded::PartitionOffset(self)
+    }
+
+    fn take_replayed(receivers: &mut ReplayReceivers) -> Option<mpsc::Receiver<Self>> {
+        receivers.partition_offset.take()
+    }
+}
+
+impl Recordable for KonsumerOffsetsData {
+    fn into_recorded(self) -> Recorded {
+        Recorded::KonsumerOffsets(self)
+    }
+
+    fn take_replayed(receivers: &mut ReplayReceivers) -> Option<mpsc::Receiver<Self>> {
+        receivers.konsumer_offsets.take()
+    }
+}
+
+/// Recording in use by the service, if any.
+///
+/// Each Emitter is spawned via [`Recording::emitted`], that records what it emits,
+/// or replaces it with the replay.
+#[derive(Clone, Default)]
+pub enum Recording {
+    #[default]
+    Off,
+    Record(Arc<Recorder>),
+    Replay(Arc<Replay>),
+}
+
+impl Recording {
+    /// Open the file to record to, or to replay from.
+    ///
+    /// Returns the [`JoinHandle`] of the task writing the recording, or dispatching the replay.
+    pub async fn open(
+        config: Option<&RecordingConfig>,
+        shutdown_token: CancellationToken,
+    ) -> io::Result<(Self, Option<JoinHandle<()>>)> {
+        match config {
+            None => Ok((Self::Off, None)),
+            Some(RecordingConfig::Record(path)) => {
+                let (recorder, join) =
+                    Recorder::create(path.clone()).await.map_err(|e| with_path(e, path))?;
+                info!("Recording to '{}'", path.display());
+                Ok((Self::Record(Arc::new(recorder)), Some(join)))
+            },
+            Some(RecordingConfig::Replay(path)) => {
+                let (replay, join) = Replay::open(path.clone(), shutdown_token)
+                    .await
+                    .map_err(|e| with_path(e, path))?;
+                info!("Replaying from '{}': not connecting to Kafka", path.display());
+                Ok((Self::Replay(Arc::new(replay)), Some(join)))
+            },
+        }
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self, Self::Replay(_))
+    }
+
+    /// Spawn an Emitter (via `spawn`), recording what it emits; or, when replaying,
+    /// don't spawn it at all, and replay what it emitted instead.
+    pub fn emitted<T: Recordable>(
+        &self,
+        spawn: impl FnOnce() -> (mpsc::Receiver<T>, JoinHandle<()>),
+    ) -> (mpsc::Receiver<T>, JoinHandle<()>) {
+        match self {
+            Self::Off => spawn(),
+            Self::Record(recorder) => {
+                let (rx, join) = spawn();
+                (recorder.tee(rx), join)
+            },
+            Self::Replay(replay) => replay.take(),
+        }
+    }
+}
+
+fn with_path(e: io::Error, path: &Path) -> io::Error {
+    io::Error::new(e.kind(), format!("'{}': {e}", path.display()))
+}
+
+/// Writes what is emitted to the recording, one [`Line`] at a time.
+pub struct Recorder {
+    lines: mpsc::UnboundedSender<String>,
+}
+
+impl Recorder {
+    async fn create(path: PathBuf) -> io::Result<(Self, JoinHandle<()>)> {
+        let mut writer = BufWriter::new(File::create(&path).await?);
+        let (lines_tx, mut lines_rx) = mpsc::unbounded_channel::<String>();
+
+        // Terminates once all the recorded Emitters have: they hold the sender side
+        let join = tokio::spawn(async move {
+            while let Some(line) = lines_rx.recv().await {
+                let mut res = writer.write_all(line.as_bytes()).await;
+                if res.is_ok() && lines_rx.is_empty() {
+                    res = writer.flush().await;
+                }
+                if let Err(e) = res {
+                    error!("Failed to write to recording '{}': {e}", path.display());
+                    return;
+                }
+            }
+            if let Err(e) = writer.flush().await {
+                error!("Failed to write to recording '{}': {e}", path.display());
+            }
+            debug!("Recording to '{}' complete", path.display());
+        });
+
+        Ok((
+            Self {
+                lines: lines_tx,
+            },
+            join,
+        ))
+    }
+
+    /// Forward what is received from `rx`, recording it on the way.
+    fn tee<T: Recordable>(&self, mut rx: mpsc::Receiver<T>) -> mpsc::Receiver<T> {
+        let (tee_tx, tee_rx) = mpsc::channel(rx.max_capacity());
+        let lines = self.lines.clone();
+
+        tokio::spawn(async move {
+            while let Some(emitted) = rx.recv().await {
+                let line = Line {
+                    at: Utc::now(),
+                    recorded: emitted.clone().into_recorded(),
+                };
+                match serde_json::to_string(&line) {
+                    Ok(json) => {
+                        let _ = lines.send(json + "\n");
+                    },
+                    Err(e) => warn!("Failed to record {}: {e}", std::any::type_name::<T>()),
+                }
+                if tee_tx.send(emitted).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        tee_rx
+    }
+}
+
+/// Receivers of the replayed records, one per [`Recordable`] type.
+pub struct ReplayReceivers {
+    cluster_status: Option<mpsc::Receiver<Arc<ClusterStatus>>>,
+    consumer_groups: Option<mpsc::Receiver<ConsumerGroupsDiff>>,
+    partition_offset: Option<mpsc::Receiver<PartitionOffset>>,
+    konsumer_offsets: Option<mpsc::Receiver<KonsumerOffsetsData>>,
+}
+
+struct ReplaySenders {
+    cluster_status: mpsc::Sender<Arc<ClusterStatus>>,
+    consumer_groups: mpsc::Sender<ConsumerGroupsDiff>,
+    partition_offset: mpsc::Sender<PartitionOffset>,
+    konsumer_offsets: mpsc::Sender<KonsumerOffsetsData>,
+}
+
+impl ReplaySenders {
+    async fn send(&self, recorded: Recorded) -> bool {
+        match recorded {
+            Recorded::ClusterStatus(cs) => self.cluster_status.send(cs).await.is_ok(),
+            Recorded::ConsumerGroups(cg) => self.consumer_groups.send(cg).await.is_ok(),
+            Recorded::PartitionOffset(po) => self.partition_offset.send(po).await.is_ok(),
+            Recorded::KonsumerOffsets(kod) => self.konsumer_offsets.send(kod).await.is_ok(),
+        }
+    }
+}
+
+fn replay_channels() -> (ReplaySenders, ReplayReceivers) {
+    let (cs_tx, cs_rx) = mpsc::channel(REPLAY_CHANNEL_SIZE);
+    let (cg_tx, cg_rx) = mpsc::channel(REPLAY_CHANNEL_SIZE);
+    let (po_tx, po_rx) = mpsc::channel(REPLAY_CHANNEL_SIZE);
+    let (kod_tx, kod_rx) = mpsc::channel(REPLAY_CHANNEL_SIZE);
+
+    (
+        ReplaySenders {
+            cluster_status: cs_tx,
+            consumer_groups: cg_tx,
+            partition_offset: po_tx,
+            konsumer_offsets: kod_tx,
+        },
+        ReplayReceivers {
+            cluster_status: Some(cs_rx),
+            consumer_groups: Some(cg_rx),
+            partition_offset: Some(po_rx),
+            konsumer_offsets: Some(kod_rx),
+        },
+    )
+}
+
+/// Replays a recording, at the pace it was recorded at.
+///
+/// The time of the recording is reflected by [`Replay::clock`], to be used in place of
+/// the system clock.
+pub struct Replay {
+    cluster_id: Option<String>,
+    clock: Arc<ManualClock>,
+    receivers: Mutex<ReplayReceivers>,
+    shutdown_token: CancellationToken,
+}
+
+impl Replay {
+    async fn open(
+        path: PathBuf,
+        shutdown_token: CancellationToken,
+    ) -> io::Result<(Self, JoinHandle<()>)> {
+        // Find out when the recording starts, and the identifier of the cluster recorded
+        let mut lines = BufReader::new(File::open(&path).await?).lines();
+        let mut started_at = None;
+        let mut cluster_id = None;
+        while let Some(line) = lines.next_line().await? {
+            let Ok(line) = serde_json::from_str::<Line>(&line) else {
+                continue;
+            };
+            started_at.get_or_insert(line.at);
+            if let Recorded::ClusterStatus(cs) = line.recorded {
+                cluster_id = Some(cs.id.clone());
+                break;
+            }
+        }
+        let started_at = started_at.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "no records to replay in the recording")
+        })?;
+
+        let clock = Arc::new(ManualClock::new(started_at));
+        let (senders, receivers) = replay_channels();
+        let lines = BufReader::new(File::open(&path).await?).lines();
+        let join = tokio::spawn(dispatch(
+            path,
+            lines,
+            started_at,
+            senders,
+            clock.clone(),
+            shutdown_token.clone(),
+        ));
+
+        Ok((
+            Self {
+                cluster_id,
+                clock,
+                receivers: Mutex::new(receivers),
+                shutdown_token,
+            },
+            join,
+        ))
+    }
+
+    /// Identifier of the cluster recorded, as reported by its first [`ClusterStatus`].
+    pub fn cluster_id(&self) -> Option<String> {
+        self.cluster_id.clone()
+    }
+
+    /// Clock moving along with the recording: it reads the time the record being replayed was emitted at.
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Take the [`mpsc::Receiver`] of the given type, and a [`JoinHandle`] standing in for
+    /// the Emitter it replaces (i.e. terminating at shutdown).
+    fn take<T: Recordable>(&self) -> (mpsc::Receiver<T>, JoinHandle<()>) {
+        let rx = T::take_replayed(&mut self.receivers.lock().expect("Replay lock poisoned"))
+            .unwrap_or_else(|| panic!("{} replayed more than once", std::any::type_name::<T>()));
+        (rx, tokio::spawn(self.shutdown_token.clone().cancelled_owned()))
+    }
+}
+
+/// Send each line of the recording over the channel of its type, at the time it was recorded at
+/// (relative to the start of the recording).
+async fn dispatch(
+    path: PathBuf,
+    mut lines: tokio::io::Lines<BufReader<File>>,
+    started_at: DateTime<Utc>,
+    senders: ReplaySenders,
+    clock: Arc<ManualClock>,
+    shutdown_token: CancellationToken,
+) {
+    let replay_started_at = Instant::now();
+    let mut replayed_until = Duration::ZERO;
+    let mut line_num = 0;
+
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line,
+            _ = shutdown_token.cancelled() => return,
+        };
+        let line = match line {
+            Ok(Some(line)) => {
+                line_num += 1;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                line
+            },
+            Ok(None) => break,
+            Err(e) => {
+                error!("Failed to read recording '{}': {e}", path.display());
+                break;
+            },
+        };
+        let line = match serde_json::from_str::<Line>(&line) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Skipping line {line_num} of recording '{}': {e}", path.display());
+                continue;
+            },
+        };
+
+        let offset = (line.at - started_at).to_std().unwrap_or_default().max(replayed_until);
+        tokio::select! {
+            _ = tokio::time::sleep_until(replay_started_at + offset) => {},
+            _ = shutdown_token.cancelled() => return,
+        }
+        clock.advance(offset - replayed_until);
+        replayed_until = offset;
+
+        if !senders.send(line.recorded).await {
+            debug!("Replayed records no longer received");
+            return;
+        }
+    }
+
+    // Keep the channels open: the Registers would otherwise treat it as a shutdown
+    info!("Replay of '{}' complete: {line_num} lines", path.display());
+    shutdown_token.cancelled().await;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lines_round_trip() {
+        let line = Line {
+            at: DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap(),
+            recorded: Recorded::PartitionOffset(PartitionOffset {
+                topic: "orders".into(),
+                partition: 3,
+                earliest_offset: 10,
+                latest_offset: 42,
+                read_datetime: DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap(),
+            }),
+        };
+
+        let json = serde_json::to_string(&line).unwrap();
+        assert!(json.contains(r#""kind":"partition_offset""#));
+        assert_eq!(serde_json::from_str::<Line>(&json).unwrap(), line);
+    }
+
+    #[tokio::test]
+    async fn replays_what_was_recorded() {
+        let path =
+            std::env::temp_dir().join(format!("kommitted-recording-{}.jsonl", std::process::id()));
+        let token = CancellationToken::new();
+
+        // Record a couple of Cluster Statuses
+        let (recorder, recorder_join) = Recorder::create(path.clone()).await.unwrap();
+        let (tx, rx) = mpsc::channel(2);
+        let (mut teed, _) =
+            Recording::Record(Arc::new(recorder)).emitted(|| (rx, tokio::spawn(async {})));
+        for id in ["a", "b"] {
+            let cs = Arc::new(ClusterStatus {
+                id: id.to_string(),
+                ..Default::default()
+            });
+            tx.send(cs.clone()).await.unwrap();
+            assert_eq!(teed.recv().await.unwrap(), cs);
+        }
+        drop(tx);
+        recorder_join.await.unwrap();
+
+        // Replay them
+        let (recording, _) =
+            Recording::open(Some(&RecordingConfig::Replay(path.clone())), token.clone())
+                .await
+                .unwrap();
+        let Recording::Replay(replay) = &recording else {
+            panic!("Not replaying");
+        };
+        assert_eq!(replay.cluster_id().as_deref(), Some("a"));
+        let (mut rx, _) = recording.emitted::<Arc<ClusterStatus>>(|| unreachable!());
+        assert_eq!(rx.recv().await.unwrap().id, "a");
+        assert_eq!(rx.recv().await.unwrap().id, "b");
+
+        token.cancel();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
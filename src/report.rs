@@ -0,0 +1,285 @@
+//! Summary of the lag of each Consumer Group over a time window: the report of the `report` subcommand.
+//!
+//! It's generated out of the lag history persisted as CSV files by the `--dump-dir` sink
+//! (see [`crate::sinks::DumpSink`]): for each Consumer Group, its lag at each dumped snapshot
+//! is aggregated across Topic Partitions (sum of the Offset Lag, max of the Time Lag), then
+//! summarized over the window. Useful for periodic SLO reviews.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Format a [`LagReport`] is rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// Markdown table, e.g. to paste into a review document.
+    Markdown,
+
+    /// JSON object, to be consumed by other tools.
+    Json,
+}
+
+/// A row of the CSV lag dumps: only the columns the report needs.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DumpedLag {
+    pub group: String,
+    pub offset_lag: Option<u64>,
+    pub time_lag_ms: Option<i64>,
+    pub taken_at_ms: i64,
+}
+
+/// Summary of the lag of a Consumer Group over the window of a [`LagReport`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupReport {
+    pub group: String,
+
+    /// Amount of snapshots of the Consumer Group in the window.
+    pub samples: usize,
+
+    /// Highest Offset Lag (summed across Topic Partitions) in the window.
+    pub peak_offset_lag: u64,
+
+    /// Highest Time Lag (ms, max across Topic Partitions) in the window.
+    pub peak_time_lag_ms: i64,
+
+    /// When the highest Time Lag was reached.
+    pub peak_at: DateTime<Utc>,
+
+    /// For how long (ms) the Time Lag was above the threshold.
+    pub time_over_threshold_ms: i64,
+
+    /// Percentage of the time observed, with the Time Lag not above the threshold.
+    pub availability_pct: f64,
+}
+
+/// Summary of the lag of each Consumer Group, over a time window.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LagReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+
+    /// Time Lag (ms) above which a Consumer Group is considered unavailable.
+    pub threshold_ms: i64,
+
+    /// Sorted by Consumer Group name.
+    pub groups: Vec<GroupReport>,
+}
+
+impl LagReport {
+    /// Summarize the given lag records, taken in `[from, to]`: records outside of it are ignored.
+    ///
+    /// Each snapshot of a Consumer Group is considered to last until the next one:
+    /// the time over the threshold, and the availability, are measured on that basis.
+    pub fn from_records(
+        records: &[DumpedLag],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        threshold: Duration,
+    ) -> Self {
+        let (from_ms, to_ms) = (from.timestamp_millis(), to.timestamp_millis());
+        let threshold_ms = threshold.as_millis() as i64;
+
+        // Lag of each Consumer Group, at each snapshot: (sum of offset lag, max of time lag)
+        let mut by_group: BTreeMap<&str, BTreeMap<i64, (u64, i64)>> = BTreeMap::new();
+        for r in records.iter().filter(|r| (from_ms..=to_ms).contains(&r.taken_at_ms)) {
+            let sample = by_group.entry(&r.group).or_default().entry(r.taken_at_ms).or_default();
+            sample.0 += r.offset_lag.unwrap_or_default();
+            sample.1 = sample.1.max(r.time_lag_ms.unwrap_or_default());
+        }
+
+        let groups = by_group
+            .into_iter()
+            .map(|(group, samples)| {
+                let samples: Vec<(i64, (u64, i64))> = samples.into_iter().collect();
+                summarize(group, &samples, threshold_ms)
+            })
+            .collect();
+
+        Self {
+            from,
+            to,
+            threshold_ms,
+            groups,
+        }
+    }
+
+    /// Render in the given [`ReportFormat`].
+    pub fn render(&self, format: ReportFormat) -> io::Result<String> {
+        match format {
+            ReportFormat::Markdown => Ok(self.to_markdown()),
+            ReportFormat::Json => Ok(serde_json::to_string_pretty(self)? + "\n"),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut out = String::from("# Lag report\n\n");
+        let _ = writeln!(
+            out,
+            "From `{}` to `{}`, with a time lag threshold of {}.\n",
+            self.from.to_rfc3339(),
+            self.to.to_rfc3339(),
+            format_ms(self.threshold_ms)
+        );
+
+        if self.groups.is_empty() {
+            out.push_str("No lag data in the window.\n");
+            return out;
+        }
+
+        out.push_str(
+            "| Group | Samples | Peak offset lag | Peak time lag | Peak at | Time over threshold | Availability |\n\
+             |-------|--------:|----------------:|--------------:|---------|--------------------:|-------------:|\n",
+        );
+        for g in self.groups.iter() {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {:.2}% |",
+                g.group.replace('|', "\\|"),
+                g.samples,
+                g.peak_offset_lag,
+                format_ms(g.peak_time_lag_ms),
+                g.peak_at.to_rfc3339(),
+                format_ms(g.time_over_threshold_ms),
+                g.availability_pct
+            );
+        }
+        out
+    }
+}
+
+/// Summarize the samples of a Consumer Group, sorted by time: `(taken_at_ms, (offset_lag, time_lag_ms))`.
+fn summarize(group: &str, samples: &[(i64, (u64, i64))], threshold_ms: i64) -> GroupReport {
+    let peak_offset_lag = samples.iter().map(|(_, (ol, _))| *ol).max().unwrap_or_default();
+    let (peak_at_ms, peak_time_lag_ms) = samples
+        .iter()
+        .map(|(at, (_, tl))| (*at, *tl))
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .unwrap_or_default();
+
+    let mut observed_ms = 0;
+    let mut time_over_threshold_ms = 0;
+    for pair in samples.windows(2) {
+        let (at, (_, time_lag_ms)) = pair[0];
+        let lasted_ms = pair[1].0 - at;
+        observed_ms += lasted_ms;
+        if time_lag_ms > threshold_ms {
+            time_over_threshold_ms += lasted_ms;
+        }
+    }
+
+    let availability_pct = if observed_ms > 0 {
+        100_f64 * (observed_ms - time_over_threshold_ms) as f64 / observed_ms as f64
+    } else if peak_time_lag_ms > threshold_ms {
+        // A single sample: it's all there is to go by
+        0_f64
+    } else {
+        100_f64
+    };
+
+    GroupReport {
+        group: group.to_string(),
+        samples: samples.len(),
+        peak_offset_lag,
+        peak_time_lag_ms,
+        peak_at: DateTime::from_timestamp_millis(peak_at_ms).unwrap_or_default(),
+        time_over_threshold_ms,
+        availability_pct,
+    }
+}
+
+fn format_ms(ms: i64) -> String {
+    humantime::format_duration(Duration::from_secs(ms.max(0) as u64 / 1000)).to_string()
+}
+
+/// Read the lag records of the CSV files in the dump directory, that cover `[from, to]`.
+///
+/// Files are named after the day (UTC) they cover (see [`crate::sinks::DumpSink`]): only those
+/// of the days in the window are read. Parquet dumps are not supported.
+pub fn read_dump_dir(
+    dir: &Path,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> io::Result<Vec<DumpedLag>> {
+    let (first_day, last_day) = (from.date_naive(), to.date_naive());
+
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("lag-")?.strip_suffix(".csv"))
+                .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+                .is_some_and(|day| (first_day..=last_day).contains(&day))
+        })
+        .collect();
+    paths.sort();
+
+    let mut records = Vec::new();
+    for path in paths {
+        debug!("Reading lag records from '{}'", path.display());
+        let mut rdr = csv::Reader::from_path(&path)?;
+        for r in rdr.deserialize() {
+            records.push(r?);
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(group: &str, offset_lag: u64, time_lag_ms: i64, taken_at_s: i64) -> DumpedLag {
+        DumpedLag {
+            group: group.to_string(),
+            offset_lag: Some(offset_lag),
+            time_lag_ms: Some(time_lag_ms),
+            taken_at_ms: taken_at_s * 1000,
+        }
+    }
+
+    #[test]
+    fn summarizes_window() {
+        let records = vec![
+            // Two partitions of 'g', at each of 4 snapshots, 60s apart
+            record("g", 10, 1_000, 0),
+            record("g", 5, 2_000, 0),
+            record("g", 100, 90_000, 60),
+            record("g", 50, 30_000, 60),
+            record("g", 200, 150_000, 120),
+            record("g", 0, 0, 120),
+            record("g", 1, 500, 180),
+            // Outside of the window
+            record("g", 1_000_000, 1_000_000, 240),
+            record("single", 1, 70_000, 0),
+        ];
+
+        let from = DateTime::from_timestamp(0, 0).unwrap();
+        let to = DateTime::from_timestamp(180, 0).unwrap();
+        let report = LagReport::from_records(&records, from, to, Duration::from_secs(60));
+
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(
+            report.groups[0],
+            GroupReport {
+                group: "g".to_string(),
+                samples: 4,
+                peak_offset_lag: 200,
+                peak_time_lag_ms: 150_000,
+                peak_at: DateTime::from_timestamp(120, 0).unwrap(),
+                time_over_threshold_ms: 120_000,
+                availability_pct: 100_f64 / 3_f64,
+            }
+        );
+        assert_eq!(report.groups[1].availability_pct, 0_f64);
+
+        let md = report.render(ReportFormat::Markdown).unwrap();
+        assert!(md.contains("| g | 4 | 200 | 2m 30s | 1970-01-01T00:02:00+00:00 | 2m | 33.33% |"));
+    }
+}
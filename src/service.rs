@@ -0,0 +1,1354 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use prometheus::{Registry, TextEncoder};
+use rdkafka::ClientConfig;
+use regex::Regex;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::alerts::{AlertsConfig, AlertsEngine};
+use crate::capabilities::Capabilities;
+use crate::cluster_status::{self, ClusterStatusRegister, TopologyAudit};
+use crate::committed_offsets::{self, OffsetsSource};
+use crate::constants::{
+    DEFAULT_ADMIN_API_INTERVAL, DEFAULT_CLUSTER_ID, DEFAULT_GROUP_ABSENCE_GRACE,
+    DEFAULT_IDLE_WINDOW, DEFAULT_INTERVAL_JITTER, DEFAULT_LAGGING_GROUP_THRESHOLD,
+    DEFAULT_LAG_EVENTS_THRESHOLD, DEFAULT_MIN_DATA_COVERAGE, DEFAULT_MIN_DATA_COVERAGE_TIMEOUT,
+    DEFAULT_OFFSETS_HISTORY, DEFAULT_OFFSETS_HISTORY_READY_AT, DEFAULT_RDKAFKA_STATS_INTERVAL,
+    DEFAULT_STATUS_WINDOW, DEFAULT_STOPPED_COMMIT_INTERVALS,
+};
+use crate::consumer_groups::{self, GroupExcludes, GroupLabels};
+use crate::consumer_status::ConsumerStatusSink;
+use crate::demo::{DemoCluster, DemoCommitsEmitter, DEMO_CLUSTER_ID};
+use crate::external_offsets::{self, ExternalOffsetsSource};
+use crate::http::{self, HttpServer};
+use crate::internals::{
+    restore_from, spawn_persisting, Awaitable, AwaitableError, ClientStats, Clock, Emitter,
+    GroupsClient, Jitter, LagSink, LagSinkError, MetadataClient, PersistenceConfig, Persistent,
+    Subsystems, SystemClock, WatermarksClient,
+};
+use crate::konsumer_offsets_data::{self, KonsumerOffsetsConsumerConfig, KonsumerOffsetsStart};
+use crate::lag_register::{self, LagHistoryConfig, LagHistorySink, LagRegister, LagTrendConfig};
+use crate::leadership::{self, HighAvailabilityConfig, LeaderOnlySink, Leadership};
+use crate::memory_guard::MemoryGuard;
+use crate::mirror::{self, MirrorConfig};
+use crate::partition_offsets::{
+    self, HistoryRetention, LinearInterpolation, PartitionOffsetsRegister, TimeLagStrategy,
+    WatermarksPolling,
+};
+use crate::preflight;
+use crate::presets::Preset;
+use crate::prometheus_metrics::{self, MetricsGranularity, PrometheusSink};
+use crate::recording::{Recording, RecordingConfig};
+use crate::sinks::{
+    self, DumpConfig, DumpSink, GraphiteConfig, GraphiteSink, GroundTruthConfig, GroundTruthSink,
+    InfluxConfig, InfluxSink, KafkaTopicConfig, KafkaTopicSink, RecordSizesConfig, RecordSizesSink,
+    RemoteWriteConfig, RemoteWriteSink, SnapshotDiffSink, StatsdConfig, StatsdSink,
+};
+#[cfg(feature = "cloudwatch")]
+use crate::sinks::{CloudWatchConfig, CloudWatchSink};
+#[cfg(feature = "otlp")]
+use crate::sinks::{OtlpConfig, OtlpSink};
+
+/// Possible errors when building or starting [`Kommitted`].
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum KommittedError {
+    /// [`KommittedBuilder`] was not given the mandatory Kafka client configuration.
+    #[error("Kafka client configuration was not provided")]
+    MissingClientConfig,
+
+    /// One of the Registers was not ready before the service was told to shutdown.
+    #[error("Failed to start: {0}")]
+    NotReady(#[from] AwaitableError),
+
+    /// One of the configured sinks could not be created.
+    #[error("Failed to create sink: {0}")]
+    Sink(#[from] LagSinkError),
+
+    /// The file to record to, or to replay from, could not be opened.
+    #[error("Failed to open recording: {0}")]
+    Recording(String),
+}
+
+pub type KommittedResult<T> = Result<T, KommittedError>;
+
+/// Builder for [`Kommitted`].
+///
+/// Only the Kafka client configuration is mandatory: everything else comes with the same defaults
+/// as the command line.
+#[derive(Clone)]
+pub struct KommittedBuilder {
+    client_config: Option<ClientConfig>,
+    cluster_id: Option<String>,
+    group_excludes: GroupExcludes,
+    group_labels: GroupLabels,
+    group_absence_grace: Duration,
+    lag_events_threshold: Duration,
+    offsets_history: usize,
+    offsets_history_retention: HistoryRetention,
+    offsets_polling: WatermarksPolling,
+    offsets_history_persistence: Option<PersistenceConfig>,
+    lag_persistence: Option<PersistenceConfig>,
+    cluster_changes_persistence: Option<PersistenceConfig>,
+    handover: Option<KommittedState>,
+    preset: Option<Preset>,
+    time_lag_strategy: Arc<dyn TimeLagStrategy>,
+    clock: Arc<dyn Clock>,
+    recording: Option<RecordingConfig>,
+    demo: bool,
+    idle_window: Duration,
+    offsets_history_ready_at: f64,
+    status_window: usize,
+    lag_history: LagHistoryConfig,
+    lag_trend: LagTrendConfig,
+    forget_offset_resets: bool,
+    stopped_commit_intervals: u32,
+    never_committed_lag_span: bool,
+    metrics_granularity: MetricsGranularity,
+    lagging_group_threshold: u64,
+    min_data_coverage: f64,
+    min_data_coverage_timeout: Duration,
+    ground_truth: Option<GroundTruthConfig>,
+    lag_bytes: Option<RecordSizesConfig>,
+    max_memory: Option<usize>,
+    mirror: Option<MirrorConfig>,
+    konsumer_offsets_start: KonsumerOffsetsStart,
+    konsumer_offsets_consumer: KonsumerOffsetsConsumerConfig,
+    offsets_source: OffsetsSource,
+    admin_api_interval: Duration,
+    lenient: bool,
+    rdkafka_stats_interval: Duration,
+    interval_jitter: Jitter,
+    listen_on: Option<SocketAddr>,
+    http_server: Option<HttpServer>,
+    external_offsets_endpoint: bool,
+    ready_requires_groups: Vec<Regex>,
+    remote_write: Option<RemoteWriteConfig>,
+    statsd: Option<StatsdConfig>,
+    influx: Option<InfluxConfig>,
+    graphite: Option<GraphiteConfig>,
+    kafka_topic: Option<KafkaTopicConfig>,
+    dump: Option<DumpConfig>,
+    snapshot_diff: Option<u64>,
+    high_availability: Option<HighAvailabilityConfig>,
+    alerts: AlertsConfig,
+    #[cfg(feature = "otlp")]
+    otlp: Option<OtlpConfig>,
+    #[cfg(feature = "cloudwatch")]
+    cloudwatch: Option<CloudWatchConfig>,
+    sinks: Vec<Arc<dyn LagSink>>,
+    external_offsets_sources: Vec<Arc<dyn ExternalOffsetsSource>>,
+}
+
+impl Default for KommittedBuilder {
+    fn default() -> Self {
+        Self {
+            client_config: None,
+            cluster_id: None,
+            group_excludes: GroupExcludes::default(),
+            group_labels: GroupLabels::default(),
+            group_absence_grace: humantime::parse_duration(DEFAULT_GROUP_ABSENCE_GRACE)
+                .expect("Invalid default"),
+            lag_events_threshold: humantime::parse_duration(DEFAULT_LAG_EVENTS_THRESHOLD)
+                .expect("Invalid default"),
+            offsets_history: DEFAULT_OFFSETS_HISTORY.parse().expect("Invalid default"),
+            offsets_history_retention: HistoryRetention::default(),
+            offsets_polling: WatermarksPolling::default(),
+            offsets_history_persistence: None,
+            lag_persistence: None,
+            cluster_changes_persistence: None,
+            handover: None,
+            preset: None,
+            time_lag_strategy: Arc::new(LinearInterpolation),
+            clock: Arc::new(SystemClock),
+            recording: None,
+            demo: false,
+            idle_window: humantime::parse_duration(DEFAULT_IDLE_WINDOW).expect("Invalid default"),
+            offsets_history_ready_at: DEFAULT_OFFSETS_HISTORY_READY_AT
+                .parse()
+                .expect("Invalid default"),
+            status_window: DEFAULT_STATUS_WINDOW.parse().expect("Invalid default"),
+            lag_history: LagHistoryConfig::default(),
+            lag_trend: LagTrendConfig::default(),
+            forget_offset_resets: false,
+            stopped_commit_intervals: DEFAULT_STOPPED_COMMIT_INTERVALS
+                .parse()
+                .expect("Invalid default"),
+            never_committed_lag_span: false,
+            metrics_granularity: MetricsGranularity::default(),
+            lagging_group_threshold: DEFAULT_LAGGING_GROUP_THRESHOLD
+                .parse()
+                .expect("Invalid default"),
+            min_data_coverage: DEFAULT_MIN_DATA_COVERAGE.parse().expect("Invalid default"),
+            min_data_coverage_timeout: humantime::parse_duration(DEFAULT_MIN_DATA_COVERAGE_TIMEOUT)
+                .expect("Invalid default"),
+            ground_truth: None,
+            lag_bytes: None,
+            max_memory: None,
+            mirror: None,
+            konsumer_offsets_start: KonsumerOffsetsStart::default(),
+            konsumer_offsets_consumer: KonsumerOffsetsConsumerConfig::default(),
+            offsets_source: OffsetsSource::default(),
+            admin_api_interval: humantime::parse_duration(DEFAULT_ADMIN_API_INTERVAL)
+                .expect("Invalid default"),
+            lenient: false,
+            rdkafka_stats_interval: humantime::parse_duration(DEFAULT_RDKAFKA_STATS_INTERVAL)
+                .expect("Invalid default"),
+            interval_jitter: Jitter::new(DEFAULT_INTERVAL_JITTER.parse().expect("Invalid default")),
+            listen_on: None,
+            http_server: None,
+            external_offsets_endpoint: false,
+            ready_requires_groups: Vec::new(),
+            remote_write: None,
+            statsd: None,
+            influx: None,
+            graphite: None,
+            kafka_topic: None,
+            dump: None,
+            snapshot_diff: None,
+            high_availability: None,
+            alerts: AlertsConfig::default(),
+            #[cfg(feature = "otlp")]
+            otlp: None,
+            #[cfg(feature = "cloudwatch")]
+            cloudwatch: None,
+            sinks: Vec::new(),
+            external_offsets_sources: Vec::new(),
+        }
+    }
+}
+
+impl KommittedBuilder {
+    /// Kafka client configuration, used by all the internal Kafka clients.
+    pub fn client_config(mut self, client_config: ClientConfig) -> Self {
+        self.client_config = Some(client_config);
+        self
+    }
+
+    /// Override identifier of the monitored Kafka Cluster.
+    pub fn cluster_id(mut self, cluster_id: Option<String>) -> Self {
+        self.cluster_id = cluster_id;
+        self
+    }
+
+    /// Consumer Groups to exclude from monitoring (by default, those internal to Kafka and its ecosystem).
+    pub fn group_excludes(mut self, group_excludes: GroupExcludes) -> Self {
+        self.group_excludes = group_excludes;
+        self
+    }
+
+    /// Extra labels (e.g. team, service, tier) to attach to Consumer Groups, in lag metrics and JSON responses.
+    pub fn group_labels(mut self, group_labels: GroupLabels) -> Self {
+        self.group_labels = group_labels;
+        self
+    }
+
+    /// How long to keep the lag of a Consumer Group that vanished, or whose Members all left,
+    /// in case it comes back (zero to drop it straight away).
+    pub fn group_absence_grace(mut self, group_absence_grace: Duration) -> Self {
+        self.group_absence_grace = group_absence_grace;
+        self
+    }
+
+    /// Time Lag of a Consumer Group, for a Topic Partition, whose crossing is reported as a
+    /// [`crate::lag_register::LagEvent`].
+    pub fn lag_events_threshold(mut self, lag_events_threshold: Duration) -> Self {
+        self.lag_events_threshold = lag_events_threshold;
+        self
+    }
+
+    /// For each Topic Partition, how much history of offsets to track in memory.
+    pub fn offsets_history(mut self, offsets_history: usize) -> Self {
+        self.offsets_history = offsets_history;
+        self
+    }
+
+    /// Further limits to the offsets history tracked in memory: by time, and by overall memory.
+    pub fn offsets_history_retention(mut self, retention: HistoryRetention) -> Self {
+        self.offsets_history_retention = retention;
+        self
+    }
+
+    /// How often to poll the offset watermarks of each Topic Partition: by default, continuously.
+    pub fn offsets_polling(mut self, polling: WatermarksPolling) -> Self {
+        self.offsets_polling = polling;
+        self
+    }
+
+    /// Persist the offsets history to disk, and restore it from there at startup.
+    pub fn offsets_history_persistence(mut self, persistence: PersistenceConfig) -> Self {
+        self.offsets_history_persistence = Some(persistence);
+        self
+    }
+
+    /// Persist the committed offsets of Consumer Groups to disk, and restore them from there at startup.
+    pub fn lag_persistence(mut self, persistence: PersistenceConfig) -> Self {
+        self.lag_persistence = Some(persistence);
+        self
+    }
+
+    /// Persist the audit log of cluster topology changes to disk, and restore it from there at startup.
+    pub fn cluster_changes_persistence(mut self, persistence: PersistenceConfig) -> Self {
+        self.cluster_changes_persistence = Some(persistence);
+        self
+    }
+
+    /// Kafka distribution preset in use, if any: the one detected is logged, if it differs.
+    pub fn preset(mut self, preset: Option<Preset>) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    /// Start from the state of a previous instance, instead of restoring it from disk
+    /// (see [`KommittedHandle::state`]).
+    pub fn handover(mut self, state: KommittedState) -> Self {
+        self.handover = Some(state);
+        self
+    }
+
+    /// How to estimate the production time of offsets, on which Time Lag estimation is based.
+    ///
+    /// See [`crate::partition_offsets::TimeLagStrategyKind`] for the available ones.
+    pub fn time_lag_strategy(mut self, strategy: Arc<dyn TimeLagStrategy>) -> Self {
+        self.time_lag_strategy = strategy;
+        self
+    }
+
+    /// Source of the current time, for the logic depending on it (e.g. expiring absent Groups).
+    ///
+    /// Defaults to [`SystemClock`]: only meant to be replaced when testing.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record what the Emitters emit to a file, or replay it from one instead of connecting to Kafka
+    /// (see [`crate::recording`]).
+    ///
+    /// When replaying, the Kafka client configuration is only used by the features that can't do
+    /// without Kafka, if enabled (e.g. high-availability).
+    pub fn recording(mut self, recording: RecordingConfig) -> Self {
+        self.recording = Some(recording);
+        self
+    }
+
+    /// Simulate a Kafka cluster (see [`crate::demo`]), instead of connecting to one.
+    ///
+    /// When simulating, the Kafka client configuration is only used by the features that can't do
+    /// without Kafka, if enabled (e.g. high-availability).
+    pub fn demo(mut self, demo: bool) -> Self {
+        self.demo = demo;
+        self
+    }
+
+    /// For how long a Topic Partition has to receive no new offsets, to be considered idle.
+    ///
+    /// The Time Lag of Consumer Groups caught up with an idle Topic Partition is zero.
+    pub fn idle_window(mut self, idle_window: Duration) -> Self {
+        self.idle_window = idle_window;
+        self
+    }
+
+    /// How full the offsets history has to be (on average) for the service to be ready.
+    pub fn offsets_history_ready_at(mut self, offsets_history_ready_at: f64) -> Self {
+        self.offsets_history_ready_at = offsets_history_ready_at;
+        self
+    }
+
+    /// For each Topic Partition, how many commits of a Consumer Group to evaluate its status over.
+    pub fn status_window(mut self, status_window: usize) -> Self {
+        self.status_window = status_window;
+        self
+    }
+
+    /// How much history of the aggregate lag of each Consumer Group to keep in memory,
+    /// served at `/groups/{name}/history`.
+    pub fn lag_history(mut self, lag_history: LagHistoryConfig) -> Self {
+        self.lag_history = lag_history;
+        self
+    }
+
+    /// How to evaluate the trend of the lag of each Consumer Group, over its lag history.
+    pub fn lag_trend(mut self, lag_trend: LagTrendConfig) -> Self {
+        self.lag_trend = lag_trend;
+        self
+    }
+
+    /// Restart the status evaluation of a Consumer Group for a Topic Partition, when its committed
+    /// offset is reset (i.e. moves backwards).
+    ///
+    /// Only the status evaluation is restarted: the lag history of the Consumer Group
+    /// keeps the lag from before the reset (see [`Self::lag_history`]).
+    pub fn forget_offset_resets(mut self, forget_offset_resets: bool) -> Self {
+        self.forget_offset_resets = forget_offset_resets;
+        self
+    }
+
+    /// How many of its usual commit intervals a Topic Partition has to receive no commits for,
+    /// for the consumer to be considered stopped, if that's the case for all its Topic Partitions.
+    pub fn stopped_commit_intervals(mut self, stopped_commit_intervals: u32) -> Self {
+        self.stopped_commit_intervals = stopped_commit_intervals;
+        self
+    }
+
+    /// Report the Offset Lag of Consumer Groups that never committed an offset for a Topic Partition
+    /// they are assigned, as the whole span of offsets available in it, instead of unknown.
+    pub fn never_committed_lag_span(mut self, never_committed_lag_span: bool) -> Self {
+        self.never_committed_lag_span = never_committed_lag_span;
+        self
+    }
+
+    /// Finest level of detail of the lag metrics served at `/metrics`.
+    pub fn metrics_granularity(mut self, metrics_granularity: MetricsGranularity) -> Self {
+        self.metrics_granularity = metrics_granularity;
+        self
+    }
+
+    /// Offset Lag above which a Consumer Group is counted as lagging, by the cluster-wide metrics.
+    pub fn lagging_group_threshold(mut self, lagging_group_threshold: u64) -> Self {
+        self.lagging_group_threshold = lagging_group_threshold;
+        self
+    }
+
+    /// Percentage of group partitions whose lag must be known, before serving metrics
+    /// (and being ready): after `timeout`, metrics are served anyway. Zero disables this.
+    pub fn min_data_coverage(mut self, min_data_coverage: f64, timeout: Duration) -> Self {
+        self.min_data_coverage = min_data_coverage;
+        self.min_data_coverage_timeout = timeout;
+        self
+    }
+
+    /// Periodically ground-truth the Time Lag estimates of the laggiest Topic Partitions.
+    pub fn ground_truth(mut self, ground_truth: GroundTruthConfig) -> Self {
+        self.ground_truth = Some(ground_truth);
+        self
+    }
+
+    /// Estimate the lag in bytes, periodically sampling the size of the latest records of the consumed Topics.
+    pub fn lag_bytes(mut self, lag_bytes: RecordSizesConfig) -> Self {
+        self.lag_bytes = Some(lag_bytes);
+        self
+    }
+
+    /// Memory budget (bytes) for the lag data: once over it, detail is shed (offsets history
+    /// of the quietest Topics first, then metrics granularity) instead of running out of memory.
+    pub fn max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+
+    /// Export the replication lag of MirrorMaker 2, from its offset-syncs topic in the monitored cluster.
+    pub fn mirror(mut self, mirror: MirrorConfig) -> Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
+    /// Where to start consuming `__consumer_offsets` from, at startup.
+    ///
+    /// The service is ready only once it has consumed up to where `__consumer_offsets` was at startup.
+    pub fn konsumer_offsets_start(mut self, start: KonsumerOffsetsStart) -> Self {
+        self.konsumer_offsets_start = start;
+        self
+    }
+
+    /// Settings of the Kafka Consumer of `__consumer_offsets` (e.g. `group.id`, `client.id`).
+    pub fn konsumer_offsets_consumer(
+        mut self,
+        consumer_config: KonsumerOffsetsConsumerConfig,
+    ) -> Self {
+        self.konsumer_offsets_consumer = consumer_config;
+        self
+    }
+
+    /// Where to source the committed offsets of Consumer Groups from.
+    pub fn offsets_source(mut self, offsets_source: OffsetsSource) -> Self {
+        self.offsets_source = offsets_source;
+        self
+    }
+
+    /// How often to fetch committed offsets, when sourcing them via [`OffsetsSource::AdminApi`].
+    pub fn admin_api_interval(mut self, admin_api_interval: Duration) -> Self {
+        self.admin_api_interval = admin_api_interval;
+        self
+    }
+
+    /// Disable the subsystems lacking the permissions (ACLs) they need, instead of failing
+    /// them over and over: the service carries on without them, degraded.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// How often librdkafka emits the statistics of the Kafka clients, exported as metrics:
+    /// zero disables them.
+    pub fn rdkafka_stats_interval(mut self, rdkafka_stats_interval: Duration) -> Self {
+        self.rdkafka_stats_interval = rdkafka_stats_interval;
+        self
+    }
+
+    /// Random jitter to apply to the intervals of the periodic work (e.g. fetching from the
+    /// cluster, taking lag snapshots), so that many instances don't do it in lockstep.
+    pub fn interval_jitter(mut self, interval_jitter: Jitter) -> Self {
+        self.interval_jitter = interval_jitter;
+        self
+    }
+
+    /// Address to serve HTTP requests on (i.e. `/metrics`).
+    ///
+    /// If not set, no HTTP server is launched: useful when embedding.
+    pub fn listen_on(mut self, listen_on: SocketAddr) -> Self {
+        self.listen_on = Some(listen_on);
+        self
+    }
+
+    /// Serve HTTP requests via the given, already listening, [`HttpServer`], instead of
+    /// [`Self::listen_on`]: so it keeps serving while the instance is re-created.
+    pub(crate) fn http_server(mut self, http_server: HttpServer) -> Self {
+        self.http_server = Some(http_server);
+        self
+    }
+
+    /// Accept offsets committed outside of Kafka, via the `POST /external-offsets` HTTP endpoint.
+    ///
+    /// Requires [`Self::listen_on`].
+    pub fn external_offsets_endpoint(mut self, enabled: bool) -> Self {
+        self.external_offsets_endpoint = enabled;
+        self
+    }
+
+    /// Consumer Groups that must have lag data, for the service to be ready (i.e. `/ready`).
+    ///
+    /// Each regex must match at least one Consumer Group with known lag. Requires [`Self::listen_on`].
+    pub fn ready_requires_groups(mut self, ready_requires_groups: Vec<Regex>) -> Self {
+        self.ready_requires_groups = ready_requires_groups;
+        self
+    }
+
+    /// Push metrics to a Prometheus remote-write endpoint.
+    pub fn remote_write(mut self, remote_write: RemoteWriteConfig) -> Self {
+        self.remote_write = Some(remote_write);
+        self
+    }
+
+    /// Emit lag metrics to a StatsD / DogStatsD server.
+    pub fn statsd(mut self, statsd: StatsdConfig) -> Self {
+        self.statsd = Some(statsd);
+        self
+    }
+
+    /// Write lag measurements to InfluxDB.
+    pub fn influx(mut self, influx: InfluxConfig) -> Self {
+        self.influx = Some(influx);
+        self
+    }
+
+    /// Write lag measurements to Graphite.
+    pub fn graphite(mut self, graphite: GraphiteConfig) -> Self {
+        self.graphite = Some(graphite);
+        self
+    }
+
+    /// Produce lag records to a Kafka topic.
+    pub fn kafka_topic(mut self, kafka_topic: KafkaTopicConfig) -> Self {
+        self.kafka_topic = Some(kafka_topic);
+        self
+    }
+
+    /// Dump lag snapshots to local files.
+    pub fn dump(mut self, dump: DumpConfig) -> Self {
+        self.dump = Some(dump);
+        self
+    }
+
+    /// Log what changed between consecutive lag snapshots, for debugging lag anomalies.
+    ///
+    /// Reported are the Consumer Groups added and removed, and the Topic Partitions
+    /// whose Offset Lag changed by more than `threshold`.
+    pub fn snapshot_diff(mut self, threshold: u64) -> Self {
+        self.snapshot_diff = Some(threshold);
+        self
+    }
+
+    /// Run in active/standby high-availability mode, coordinating leadership with the other
+    /// instances configured with the same [`HighAvailabilityConfig`].
+    ///
+    /// Only the leader serves metrics and publishes to the sinks that push lag data elsewhere
+    /// (including those added via [`Self::sink`]): standby instances keep their registers warm.
+    pub fn high_availability(mut self, high_availability: HighAvailabilityConfig) -> Self {
+        self.high_availability = Some(high_availability);
+        self
+    }
+
+    /// Alerting rules to evaluate, and the webhooks they notify.
+    pub fn alerts(mut self, alerts: AlertsConfig) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// Export metrics to an OpenTelemetry Protocol (OTLP) endpoint.
+    #[cfg(feature = "otlp")]
+    pub fn otlp(mut self, otlp: OtlpConfig) -> Self {
+        self.otlp = Some(otlp);
+        self
+    }
+
+    /// Publish aggregated lag metrics to AWS CloudWatch.
+    #[cfg(feature = "cloudwatch")]
+    pub fn cloudwatch(mut self, cloudwatch: CloudWatchConfig) -> Self {
+        self.cloudwatch = Some(cloudwatch);
+        self
+    }
+
+    /// Additional [`LagSink`] to publish lag data to, at every reconcile tick.
+    ///
+    /// Can be called multiple times, to add multiple sinks.
+    pub fn sink(mut self, sink: Arc<dyn LagSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Additional [`ExternalOffsetsSource`] to periodically fetch offsets committed outside of Kafka from.
+    ///
+    /// Can be called multiple times, to add multiple sources.
+    pub fn external_offsets_source(mut self, source: Arc<dyn ExternalOffsetsSource>) -> Self {
+        self.external_offsets_sources.push(source);
+        self
+    }
+
+    pub fn build(self) -> KommittedResult<Kommitted> {
+        Ok(Kommitted {
+            client_config: self.client_config.ok_or(KommittedError::MissingClientConfig)?,
+            cluster_id: self.cluster_id,
+            group_excludes: self.group_excludes,
+            group_labels: self.group_labels,
+            group_absence_grace: self.group_absence_grace,
+            lag_events_threshold: self.lag_events_threshold,
+            offsets_history: self.offsets_history,
+            offsets_history_retention: self.offsets_history_retention,
+            offsets_polling: self.offsets_polling,
+            offsets_history_persistence: self.offsets_history_persistence,
+            lag_persistence: self.lag_persistence,
+            cluster_changes_persistence: self.cluster_changes_persistence,
+            handover: self.handover,
+            preset: self.preset,
+            time_lag_strategy: self.time_lag_strategy,
+            clock: self.clock,
+            recording: self.recording,
+            demo: self.demo,
+            idle_window: self.idle_window,
+            offsets_history_ready_at: self.offsets_history_ready_at,
+            status_window: self.status_window,
+            lag_history: self.lag_history,
+            lag_trend: self.lag_trend,
+            forget_offset_resets: self.forget_offset_resets,
+            stopped_commit_intervals: self.stopped_commit_intervals,
+            never_committed_lag_span: self.never_committed_lag_span,
+            metrics_granularity: self.metrics_granularity,
+            lagging_group_threshold: self.lagging_group_threshold,
+            min_data_coverage: self.min_data_coverage,
+            min_data_coverage_timeout: self.min_data_coverage_timeout,
+            ground_truth: self.ground_truth,
+            lag_bytes: self.lag_bytes,
+            max_memory: self.max_memory,
+            mirror: self.mirror,
+            konsumer_offsets_start: self.konsumer_offsets_start,
+            konsumer_offsets_consumer: self.konsumer_offsets_consumer,
+            offsets_source: self.offsets_source,
+            admin_api_interval: self.admin_api_interval,
+            lenient: self.lenient,
+            rdkafka_stats_interval: self.rdkafka_stats_interval,
+            interval_jitter: self.interval_jitter,
+            listen_on: self.listen_on,
+            http_server: self.http_server,
+            external_offsets_endpoint: self.external_offsets_endpoint,
+            ready_requires_groups: self.ready_requires_groups,
+            remote_write: self.remote_write,
+            statsd: self.statsd,
+            influx: self.influx,
+            graphite: self.graphite,
+            kafka_topic: self.kafka_topic,
+            dump: self.dump,
+            snapshot_diff: self.snapshot_diff,
+            high_availability: self.high_availability,
+            alerts: self.alerts,
+            #[cfg(feature = "otlp")]
+            otlp: self.otlp,
+            #[cfg(feature = "cloudwatch")]
+            cloudwatch: self.cloudwatch,
+            sinks: self.sinks,
+            external_offsets_sources: self.external_offsets_sources,
+        })
+    }
+}
+
+/// A configured, but not yet running, instance of the service.
+#[derive(Clone)]
+pub struct Kommitted {
+    client_config: ClientConfig,
+    cluster_id: Option<String>,
+    group_excludes: GroupExcludes,
+    group_labels: GroupLabels,
+    group_absence_grace: Duration,
+    lag_events_threshold: Duration,
+    offsets_history: usize,
+    offsets_history_retention: HistoryRetention,
+    offsets_polling: WatermarksPolling,
+    offsets_history_persistence: Option<PersistenceConfig>,
+    lag_persistence: Option<PersistenceConfig>,
+    cluster_changes_persistence: Option<PersistenceConfig>,
+    handover: Option<KommittedState>,
+    preset: Option<Preset>,
+    time_lag_strategy: Arc<dyn TimeLagStrategy>,
+    clock: Arc<dyn Clock>,
+    recording: Option<RecordingConfig>,
+    demo: bool,
+    idle_window: Duration,
+    offsets_history_ready_at: f64,
+    status_window: usize,
+    lag_history: LagHistoryConfig,
+    lag_trend: LagTrendConfig,
+    forget_offset_resets: bool,
+    stopped_commit_intervals: u32,
+    never_committed_lag_span: bool,
+    metrics_granularity: MetricsGranularity,
+    lagging_group_threshold: u64,
+    min_data_coverage: f64,
+    min_data_coverage_timeout: Duration,
+    ground_truth: Option<GroundTruthConfig>,
+    lag_bytes: Option<RecordSizesConfig>,
+    max_memory: Option<usize>,
+    mirror: Option<MirrorConfig>,
+    konsumer_offsets_start: KonsumerOffsetsStart,
+    konsumer_offsets_consumer: KonsumerOffsetsConsumerConfig,
+    offsets_source: OffsetsSource,
+    admin_api_interval: Duration,
+    lenient: bool,
+    rdkafka_stats_interval: Duration,
+    interval_jitter: Jitter,
+    listen_on: Option<SocketAddr>,
+    http_server: Option<HttpServer>,
+    external_offsets_endpoint: bool,
+    ready_requires_groups: Vec<Regex>,
+    remote_write: Option<RemoteWriteConfig>,
+    statsd: Option<StatsdConfig>,
+    influx: Option<InfluxConfig>,
+    graphite: Option<GraphiteConfig>,
+    kafka_topic: Option<KafkaTopicConfig>,
+    dump: Option<DumpConfig>,
+    snapshot_diff: Option<u64>,
+    high_availability: Option<HighAvailabilityConfig>,
+    alerts: AlertsConfig,
+    #[cfg(feature = "otlp")]
+    otlp: Option<OtlpConfig>,
+    #[cfg(feature = "cloudwatch")]
+    cloudwatch: Option<CloudWatchConfig>,
+    sinks: Vec<Arc<dyn LagSink>>,
+    external_offsets_sources: Vec<Arc<dyn ExternalOffsetsSource>>,
+}
+
+impl Kommitted {
+    pub fn builder() -> KommittedBuilder {
+        KommittedBuilder::default()
+    }
+
+    /// Initializes all the modules, awaiting for each Register to be ready before moving on.
+    ///
+    /// Returns a [`KommittedHandle`] to access the Registers, and join on the internal tasks.
+    /// Those tasks terminate once the given [`CancellationToken`] is cancelled,
+    /// or straight away if starting fails.
+    pub async fn start(
+        self,
+        shutdown_token: CancellationToken,
+    ) -> KommittedResult<KommittedHandle> {
+        let run_token = shutdown_token.child_token();
+        let res = self.init_modules(run_token.clone()).await;
+        if res.is_err() {
+            run_token.cancel();
+        }
+        res
+    }
+
+    /// Initializes all the modules: see [`Self::start`].
+    async fn init_modules(
+        self,
+        shutdown_token: CancellationToken,
+    ) -> KommittedResult<KommittedHandle> {
+        let admin_client_config = self.client_config;
+        let mut handover = self.handover;
+
+        // Open the recording, if any: when replaying, the cluster is never connected to
+        let (recording, recording_join) =
+            Recording::open(self.recording.as_ref(), shutdown_token.clone())
+                .await
+                .map_err(|e| KommittedError::Recording(e.to_string()))?;
+        let (cluster_id, clock) = match &recording {
+            Recording::Replay(replay) => (
+                Some(
+                    self.cluster_id
+                        .clone()
+                        .or_else(|| replay.cluster_id())
+                        .unwrap_or_else(|| DEFAULT_CLUSTER_ID.to_string()),
+                ),
+                replay.clock(),
+            ),
+            _ if self.demo => (
+                Some(self.cluster_id.clone().unwrap_or_else(|| DEMO_CLUSTER_ID.to_string())),
+                self.clock,
+            ),
+            _ => (self.cluster_id.clone(), self.clock),
+        };
+
+        // Simulate the cluster, if requested: the Emitters fetch from the simulation instead
+        let demo = self.demo.then(|| Arc::new(DemoCluster::new(clock.clone())));
+        let connected = !recording.is_replay() && demo.is_none();
+
+        // Check connectivity and permissions, so that what's missing is reported upfront
+        if connected {
+            let checks = {
+                let client_config = admin_client_config.clone();
+                let offsets_source = self.offsets_source;
+                tokio::task::spawn_blocking(move || preflight::run(&client_config, offsets_source))
+                    .await
+                    .expect("Task running preflight checks panicked")
+            };
+            for c in checks {
+                if c.is_failed() {
+                    warn!("Preflight check {c}");
+                } else {
+                    info!("Preflight check {c}");
+                }
+            }
+        }
+
+        // Init `prometheus_metrics` module
+        let prom_reg = prometheus_metrics::init(admin_client_config.clone(), cluster_id);
+        let prom_reg_arc = Arc::new(prom_reg);
+
+        // Subsystems that might get disabled, for lack of permissions
+        let subsystems_arc = Arc::new(Subsystems::new(self.lenient, prom_reg_arc.clone()));
+
+        // Disable upfront the subsystems depending on capabilities the cluster doesn't have
+        if connected {
+            let capabilities = {
+                let client_config = admin_client_config.clone();
+                tokio::task::spawn_blocking(move || Capabilities::detect(&client_config))
+                    .await
+                    .expect("Task detecting capabilities panicked")
+            };
+            capabilities.log();
+            capabilities.gate(&subsystems_arc);
+        }
+
+        // Create the sinks before spawning anything, so that nothing is left running if one
+        // can't be: always including the sinks that render for `/metrics` and `/groups`
+        let record_sizes_sink = self
+            .lag_bytes
+            .map(|lbc| RecordSizesSink::new(lbc, &admin_client_config))
+            .transpose()?;
+        let prom_sink_arc = Arc::new(
+            PrometheusSink::new()
+                .with_record_sizes(record_sizes_sink.as_ref().map(RecordSizesSink::record_sizes))
+                .with_never_committed_lag_span(self.never_committed_lag_span)
+                .with_granularity(self.metrics_granularity)
+                .with_lagging_group_threshold(self.lagging_group_threshold)
+                .with_warm_up(self.min_data_coverage, self.min_data_coverage_timeout),
+        );
+        let status_sink_arc = Arc::new(
+            ConsumerStatusSink::new(self.status_window, prom_reg_arc.clone())
+                .with_forget_resets(self.forget_offset_resets)
+                .with_stopped_intervals(self.stopped_commit_intervals),
+        );
+        let history_sink_arc =
+            Arc::new(LagHistorySink::new(self.lag_history, self.lag_trend, prom_reg_arc.clone()));
+        let mut sinks: Vec<Arc<dyn LagSink>> =
+            vec![prom_sink_arc.clone(), status_sink_arc.clone(), history_sink_arc.clone()];
+        let ground_truth = self
+            .ground_truth
+            .map(|gtc| GroundTruthSink::create_consumer(&admin_client_config).map(|c| (gtc, c)))
+            .transpose()?;
+        if let Some(rss) = record_sizes_sink {
+            sinks.push(Arc::new(rss));
+        }
+        if let Some(dc) = self.dump {
+            sinks.push(Arc::new(DumpSink::new(dc)?));
+        }
+        if let Some(threshold) = self.snapshot_diff {
+            sinks.push(Arc::new(SnapshotDiffSink::new(threshold)));
+        }
+
+        // Sinks that push lag data elsewhere: in high-availability mode, only the leader does
+        let mut push_sinks = self.sinks;
+        if let Some(rwc) = self.remote_write {
+            push_sinks.push(Arc::new(RemoteWriteSink::new(rwc, prom_reg_arc.clone())?));
+        }
+        if let Some(sc) = self.statsd {
+            push_sinks.push(Arc::new(StatsdSink::new(sc)));
+        }
+        if let Some(ic) = self.influx {
+            push_sinks.push(Arc::new(InfluxSink::new(ic)?));
+        }
+        if let Some(gc) = self.graphite {
+            push_sinks.push(Arc::new(GraphiteSink::new(gc)));
+        }
+        if let Some(ktc) = self.kafka_topic {
+            push_sinks.push(Arc::new(KafkaTopicSink::new(ktc, &admin_client_config)?));
+        }
+        if !self.alerts.rules.is_empty() {
+            push_sinks.push(Arc::new(AlertsEngine::new(self.alerts, prom_reg_arc.clone())?));
+        }
+        #[cfg(feature = "otlp")]
+        if let Some(oc) = self.otlp {
+            push_sinks.push(Arc::new(OtlpSink::new(oc, prom_reg_arc.clone())?));
+        }
+        #[cfg(feature = "cloudwatch")]
+        if let Some(cwc) = self.cloudwatch {
+            push_sinks.push(Arc::new(CloudWatchSink::new(cwc).await));
+        }
+
+        // Init `http` module, if requested: listening already, it responds unavailable until started
+        let mut http_join = None;
+        let http_server = self.http_server.or_else(|| {
+            self.listen_on.map(|listen_on| {
+                let (http_server, join) = http::init(listen_on, shutdown_token.clone());
+                http_join = Some(join);
+                http_server
+            })
+        });
+
+        // Init `leadership` module: unless in high-availability mode, always the leader
+        let ha_group = self.high_availability.as_ref().map(|ha| ha.group.clone());
+        let (leadership_arc, ha_join) = leadership::init(
+            admin_client_config.clone(),
+            self.high_availability,
+            shutdown_token.clone(),
+            prom_reg_arc.clone(),
+        );
+
+        // Statistics of the Kafka clients, exported as metrics
+        let client_stats_arc =
+            Arc::new(ClientStats::new(self.rdkafka_stats_interval, prom_reg_arc.clone()));
+
+        // Init `cluster_status` module, and await registry to be ready
+        let (cs_reg, cs_join) = cluster_status::init(
+            admin_client_config.clone(),
+            self.cluster_id.clone(),
+            subsystems_arc.clone(),
+            client_stats_arc.clone(),
+            self.interval_jitter,
+            demo.clone().map(|dc| dc as Arc<dyn MetadataClient>),
+            &recording,
+            shutdown_token.clone(),
+            prom_reg_arc.clone(),
+        );
+        cs_reg.await_ready(shutdown_token.clone()).await?;
+        if let Some(cs) = cs_reg.get_status().await {
+            Preset::log_detected(self.preset, &cs);
+        }
+        let cs_reg_arc = Arc::new(cs_reg);
+
+        // Audit log of the cluster topology changes, served at `/cluster/changes`
+        let topology_audit_arc = Arc::new(TopologyAudit::default());
+        if let Some(h) = handover.as_mut() {
+            topology_audit_arc.restore(std::mem::take(&mut h.cluster_changes)).await;
+        } else if let Some(pc) = &self.cluster_changes_persistence {
+            match restore_from(topology_audit_arc.as_ref(), &pc.path).await {
+                Ok(true) => info!("Restored cluster topology changes from '{}'", pc.path.display()),
+                Ok(false) => {
+                    info!("No cluster topology changes to restore at '{}'", pc.path.display())
+                },
+                Err(e) => {
+                    warn!(
+                        "Failed to restore cluster topology changes from '{}': {e}",
+                        pc.path.display()
+                    )
+                },
+            }
+        }
+        let topology_audit_join = topology_audit_arc.spawn(&cs_reg_arc, shutdown_token.clone());
+        let topology_audit_persist_join = self
+            .cluster_changes_persistence
+            .map(|pc| spawn_persisting(topology_audit_arc.clone(), pc, shutdown_token.clone()));
+
+        // Init `partition_offsets` module, and await registry to be ready
+        let (po_reg, po_join) = partition_offsets::init(
+            admin_client_config.clone(),
+            self.offsets_polling,
+            self.interval_jitter,
+            self.offsets_history,
+            self.offsets_history_retention,
+            self.time_lag_strategy,
+            self.idle_window,
+            self.offsets_history_ready_at,
+            cs_reg_arc.clone(),
+            client_stats_arc.clone(),
+            clock.clone(),
+            demo.clone().map(|dc| dc as Arc<dyn WatermarksClient>),
+            &recording,
+            shutdown_token.clone(),
+            prom_reg_arc.clone(),
+        );
+        if let Some(h) = handover.as_mut() {
+            po_reg.restore(std::mem::take(&mut h.offsets_history)).await;
+            info!("Handed over offsets history");
+        } else if let Some(pc) = &self.offsets_history_persistence {
+            match restore_from(&po_reg, &pc.path).await {
+                Ok(true) => info!("Restored offsets history from '{}'", pc.path.display()),
+                Ok(false) => info!("No offsets history to restore at '{}'", pc.path.display()),
+                Err(e) => {
+                    warn!("Failed to restore offsets history from '{}': {e}", pc.path.display())
+                },
+            }
+        }
+        po_reg.await_ready(shutdown_token.clone()).await?;
+        let po_reg_arc = Arc::new(po_reg);
+        let po_persist_join = self
+            .offsets_history_persistence
+            .map(|pc| spawn_persisting(po_reg_arc.clone(), pc, shutdown_token.clone()));
+
+        // This service own consumer of `__consumer_offsets` is ignored, whatever its `group.id`,
+        // and so is the leader election one (if in high-availability mode)
+        let mut group_excludes = self
+            .group_excludes
+            .with_ignored(self.konsumer_offsets_consumer.effective_group_id(&admin_client_config));
+        if let Some(ha_group) = ha_group {
+            group_excludes = group_excludes.with_ignored(ha_group);
+        }
+
+        // Init either `konsumer_offsets_data` or `committed_offsets` module,
+        // depending on where committed offsets are sourced from (unless simulating the cluster)
+        let mut kod_backfill = None;
+        let (kod_rx, kod_join) = recording.emitted(|| match (&demo, self.offsets_source) {
+            (Some(dc), _) => DemoCommitsEmitter::new(dc.clone(), self.interval_jitter)
+                .spawn(shutdown_token.clone()),
+            (None, OffsetsSource::KonsumerOffsets) => {
+                let (kod_rx, backfill, kod_join) = konsumer_offsets_data::init(
+                    admin_client_config.clone(),
+                    self.konsumer_offsets_start,
+                    self.konsumer_offsets_consumer.clone(),
+                    group_excludes.clone(),
+                    cs_reg_arc.clone(),
+                    subsystems_arc.clone(),
+                    client_stats_arc.clone(),
+                    shutdown_token.clone(),
+                    prom_reg_arc.clone(),
+                );
+                kod_backfill = Some(backfill);
+                (kod_rx, kod_join)
+            },
+            (None, OffsetsSource::AdminApi) => committed_offsets::init(
+                admin_client_config.clone(),
+                self.admin_api_interval,
+                self.interval_jitter,
+                group_excludes.clone(),
+                cs_reg_arc.clone(),
+                subsystems_arc.clone(),
+                shutdown_token.clone(),
+                prom_reg_arc.clone(),
+            ),
+        });
+
+        // Init `consumer_groups` module
+        let (cg_rx, cg_join) = recording.emitted(|| {
+            consumer_groups::init(
+                admin_client_config.clone(),
+                group_excludes.clone(),
+                subsystems_arc.clone(),
+                client_stats_arc,
+                self.interval_jitter,
+                demo.map(|dc| dc as Arc<dyn GroupsClient>),
+                shutdown_token.clone(),
+                prom_reg_arc.clone(),
+            )
+        });
+
+        // Init `external_offsets` module: the HTTP endpoint, if enabled, feeds it too
+        let (eo_sx, eo_rx, eo_joins) = external_offsets::init(
+            self.external_offsets_sources,
+            self.interval_jitter,
+            shutdown_token.clone(),
+        );
+        let eo_sx = self.external_offsets_endpoint.then_some(eo_sx);
+
+        // Init `lag_register` module, and await registry to be ready
+        // (only after `__consumer_offsets` has been backfilled, if consumed)
+        let lag_reg = lag_register::init(
+            cg_rx,
+            kod_rx,
+            eo_rx,
+            cs_reg_arc.subscribe_topology_changes(),
+            group_excludes,
+            self.group_labels,
+            self.group_absence_grace,
+            self.lag_events_threshold,
+            po_reg_arc.clone(),
+            clock,
+            prom_reg_arc.clone(),
+        );
+        if let Some(kod_backfill) = kod_backfill {
+            kod_backfill.await_ready(shutdown_token.clone()).await?;
+        }
+        lag_reg.await_ready(shutdown_token.clone()).await?;
+        if let Some(h) = handover.as_mut() {
+            lag_reg.restore(std::mem::take(&mut h.committed_offsets)).await;
+            info!("Handed over committed offsets");
+        } else if let Some(pc) = &self.lag_persistence {
+            match restore_from(&lag_reg, &pc.path).await {
+                Ok(true) => info!("Restored committed offsets from '{}'", pc.path.display()),
+                Ok(false) => info!("No committed offsets to restore at '{}'", pc.path.display()),
+                Err(e) => {
+                    warn!("Failed to restore committed offsets from '{}': {e}", pc.path.display())
+                },
+            }
+        }
+        let lag_reg_arc = Arc::new(lag_reg);
+        let lag_persist_join = self
+            .lag_persistence
+            .map(|pc| spawn_persisting(lag_reg_arc.clone(), pc, shutdown_token.clone()));
+
+        // Init `sinks` module, adding the sinks that need the Registers
+        if let Some((gtc, consumer)) = ground_truth {
+            sinks.push(Arc::new(GroundTruthSink::new(
+                gtc,
+                consumer,
+                po_reg_arc.clone(),
+                prom_reg_arc.clone(),
+            )));
+        }
+        let mirror_join = self.mirror.map(|mc| {
+            let (mirror_sink, mirror_join) = mirror::init(
+                admin_client_config.clone(),
+                mc,
+                po_reg_arc.clone(),
+                shutdown_token.clone(),
+                prom_reg_arc.clone(),
+            );
+            sinks.push(Arc::new(mirror_sink));
+            mirror_join
+        });
+        if leadership_arc.is_ha() {
+            sinks.extend(push_sinks.into_iter().map(|s| {
+                Arc::new(LeaderOnlySink::new(s, leadership_arc.clone())) as Arc<dyn LagSink>
+            }));
+        } else {
+            sinks.extend(push_sinks);
+        }
+        let sinks_join = sinks::init(
+            sinks,
+            cs_reg_arc.clone(),
+            po_reg_arc.clone(),
+            lag_reg_arc.clone(),
+            self.interval_jitter,
+            shutdown_token.clone(),
+            prom_reg_arc.clone(),
+        );
+
+        let mut joins = vec![cs_join, po_join, kod_join, cg_join, sinks_join];
+        joins.extend(po_persist_join);
+        joins.extend(lag_persist_join);
+        joins.push(topology_audit_join);
+        joins.extend(topology_audit_persist_join);
+        joins.extend(ha_join);
+        joins.extend(eo_joins);
+        joins.extend(mirror_join);
+        joins.extend(recording_join);
+        joins.extend(http_join);
+
+        // Init `memory_guard` module, if requested
+        if let Some(max_memory) = self.max_memory {
+            joins.push(
+                MemoryGuard::new(
+                    max_memory,
+                    po_reg_arc.clone(),
+                    lag_reg_arc.clone(),
+                    prom_sink_arc.clone(),
+                    prom_reg_arc.clone(),
+                )
+                .spawn(shutdown_token.clone()),
+            );
+        }
+
+        // Serve the HTTP endpoints of this instance, now that it's started
+        if let Some(http_server) = http_server {
+            http_server.set_state(
+                cs_reg_arc.clone(),
+                topology_audit_arc.clone(),
+                prom_sink_arc.clone(),
+                status_sink_arc,
+                history_sink_arc,
+                eo_sx,
+                self.ready_requires_groups,
+                subsystems_arc.clone(),
+                leadership_arc.clone(),
+                prom_reg_arc.clone(),
+            );
+        }
+
+        Ok(KommittedHandle {
+            cs_reg: cs_reg_arc,
+            po_reg: po_reg_arc,
+            lag_reg: lag_reg_arc,
+            topology_audit: topology_audit_arc,
+            subsystems: subsystems_arc,
+            leadership: leadership_arc,
+            metrics: prom_reg_arc,
+            prom_sink: prom_sink_arc,
+            joins,
+        })
+    }
+
+    /// Convenience method that [`Self::start`]s, then [`KommittedHandle::join`]s.
+    pub async fn run(self, shutdown_token: CancellationToken) -> KommittedResult<()> {
+        self.start(shutdown_token).await?.join().await;
+        Ok(())
+    }
+}
+
+/// State of a running instance of [`Kommitted`], handed over to the next instance
+/// (e.g. when re-created to renew its Kafka clients), as if persisted and restored.
+#[derive(Debug, Clone, Default)]
+pub struct KommittedState {
+    offsets_history: <PartitionOffsetsRegister as Persistent>::State,
+    committed_offsets: <LagRegister as Persistent>::State,
+    cluster_changes: <TopologyAudit as Persistent>::State,
+}
+
+/// Handle to a running instance of [`Kommitted`].
+pub struct KommittedHandle {
+    cs_reg: Arc<ClusterStatusRegister>,
+    po_reg: Arc<PartitionOffsetsRegister>,
+    lag_reg: Arc<LagRegister>,
+    topology_audit: Arc<TopologyAudit>,
+    subsystems: Arc<Subsystems>,
+    leadership: Arc<Leadership>,
+    metrics: Arc<Registry>,
+    prom_sink: Arc<PrometheusSink>,
+    joins: Vec<JoinHandle<()>>,
+}
+
+impl KommittedHandle {
+    /// The [`ClusterStatusRegister`], tracking the status of the Kafka cluster.
+    pub fn cluster_status_register(&self) -> Arc<ClusterStatusRegister> {
+        self.cs_reg.clone()
+    }
+
+    /// The [`PartitionOffsetsRegister`], tracking the offsets of all Topic Partitions.
+    pub fn partition_offsets_register(&self) -> Arc<PartitionOffsetsRegister> {
+        self.po_reg.clone()
+    }
+
+    /// The [`LagRegister`], tracking the lag of all Consumer Groups.
+    pub fn lag_register(&self) -> Arc<LagRegister> {
+        self.lag_reg.clone()
+    }
+
+    /// Take a copy of the current [`KommittedState`], to hand over to the next instance
+    /// (see [`KommittedBuilder::handover`]).
+    pub async fn state(&self) -> KommittedState {
+        KommittedState {
+            offsets_history: self.po_reg.state().await,
+            committed_offsets: self.lag_reg.state().await,
+            cluster_changes: self.topology_audit.state().await,
+        }
+    }
+
+    /// The [`Subsystems`], tracking which ones are enabled.
+    pub fn subsystems(&self) -> Arc<Subsystems> {
+        self.subsystems.clone()
+    }
+
+    /// The [`Leadership`], tracking whether this instance is the leader.
+    pub fn leadership(&self) -> Arc<Leadership> {
+        self.leadership.clone()
+    }
+
+    /// The Prometheus [`Registry`] where the internal metrics are registered.
+    pub fn metrics(&self) -> Arc<Registry> {
+        self.metrics.clone()
+    }
+
+    /// All the metrics, in Prometheus Exposition format, as served by the `/metrics` HTTP endpoint:
+    /// the lag metrics rendered at the latest reconcile tick, followed by the internal metrics.
+    ///
+    /// Unlike the HTTP endpoint, they are rendered even if not warmed up, or not the leader.
+    pub async fn render_metrics(&self) -> prometheus::Result<String> {
+        let mut body = self.prom_sink.latest().await;
+        TextEncoder.encode_utf8(&self.metrics.gather(), &mut body)?;
+        Ok(body)
+    }
+
+    /// Wait for all the internal tasks to terminate.
+    pub async fn join(self) {
+        for join in self.joins {
+            if let Err(e) = join.await {
+                error!("Internal task failed: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_requires_client_config() {
+        assert_eq!(Kommitted::builder().build().err(), Some(KommittedError::MissingClientConfig));
+        assert!(Kommitted::builder().client_config(ClientConfig::new()).build().is_ok());
+    }
+
+    #[tokio::test]
+    async fn start_fails_if_replay_is_missing() {
+        let path = std::env::temp_dir()
+            .join(format!("kommitted-missing-replay-{}.jsonl", std::process::id()));
+        let kommitted = Kommitted::builder()
+            .client_config(ClientConfig::new())
+            .recording(RecordingConfig::Replay(path))
+            .build()
+            .unwrap();
+
+        let res = kommitted.start(CancellationToken::new()).await;
+        assert!(matches!(res, Err(KommittedError::Recording(_))));
+    }
+
+    #[tokio::test]
+    async fn start_fails_if_shutdown_before_ready() {
+        let kommitted =
+            Kommitted::builder().client_config(ClientConfig::new()).demo(true).build().unwrap();
+
+        let shutdown_token = CancellationToken::new();
+        shutdown_token.cancel();
+        let res = kommitted.start(shutdown_token).await;
+        assert!(matches!(res, Err(KommittedError::NotReady(_))));
+    }
+
+    #[tokio::test]
+    async fn start_fails_if_sink_cannot_be_created() {
+        // A directory can't be created inside a file
+        let file = std::env::temp_dir().join(format!("kommitted-not-a-dir-{}", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let kommitted = Kommitted::builder()
+            .client_config(ClientConfig::new())
+            .demo(true)
+            .dump(DumpConfig {
+                dir: file.join("dump"),
+                format: sinks::DumpFormat::Csv,
+                interval: Duration::from_secs(60),
+                retention: None,
+            })
+            .build()
+            .unwrap();
+
+        // Fails before spawning anything, nor awaiting any Register to be ready
+        let shutdown_token = CancellationToken::new();
+        let res =
+            tokio::time::timeout(Duration::from_secs(1), kommitted.start(shutdown_token.clone()))
+                .await
+                .expect("Start should fail straight away");
+        std::fs::remove_file(&file).unwrap();
+        assert!(matches!(res, Err(KommittedError::Sink(_))));
+        assert!(!shutdown_token.is_cancelled());
+    }
+}
@@ -0,0 +1,122 @@
+use aws_sdk_cloudwatch::primitives::DateTime;
+use aws_sdk_cloudwatch::types::{Dimension, MetricDatum, StandardUnit};
+use aws_sdk_cloudwatch::Client;
+use tokio::time::Duration;
+
+use crate::internals::{LagSink, LagSinkError, LagSinkFuture};
+use crate::lag_register::LagSnapshot;
+
+/// Max amount of [`MetricDatum`] that can be sent with a single `PutMetricData` request.
+const MAX_DATUMS_PER_REQUEST: usize = 1000;
+
+const METRIC_OFFSET_LAG: &str = "OffsetLag";
+const METRIC_TIME_LAG: &str = "TimeLag";
+const DIMENSION_CLUSTER: &str = "Cluster";
+const DIMENSION_GROUP: &str = "Group";
+const DIMENSION_TOPIC: &str = "Topic";
+
+/// Configuration of [`CloudWatchSink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloudWatchConfig {
+    /// CloudWatch namespace to publish metrics under.
+    pub namespace: String,
+
+    /// AWS region to publish to.
+    ///
+    /// If `None`, it's resolved via the standard AWS configuration chain
+    /// (e.g. `AWS_REGION` environment variable, `~/.aws/config`, instance metadata).
+    pub region: Option<String>,
+
+    /// How often to publish.
+    pub interval: Duration,
+}
+
+/// A [`LagSink`] that publishes lag metrics to AWS CloudWatch, via `PutMetricData`.
+///
+/// To keep the amount of (billed) metrics reasonable, lag is aggregated by Consumer Group and
+/// Topic, with dimensions `Cluster`, `Group` and `Topic`:
+///
+/// * `OffsetLag` - Sum of the Offset Lag of all the partitions of the topic
+/// * `TimeLag` - Max of the Time Lag (ms) of all the partitions of the topic
+///
+/// Credentials are resolved via the standard AWS configuration chain.
+pub struct CloudWatchSink {
+    config: CloudWatchConfig,
+    client: Client,
+}
+
+impl CloudWatchSink {
+    pub async fn new(config: CloudWatchConfig) -> Self {
+        let mut loader = aws_config::from_env();
+        if let Some(region) = &config.region {
+            loader = loader.region(aws_config::Region::new(region.clone()));
+        }
+        let client = Client::new(&loader.load().await);
+
+        Self {
+            config,
+            client,
+        }
+    }
+}
+
+impl LagSink for CloudWatchSink {
+    fn name(&self) -> &'static str {
+        "cloudwatch"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let timestamp = DateTime::from_millis(snapshot.taken_at.timestamp_millis());
+
+            let datums: Vec<MetricDatum> = snapshot
+                .lag_by_group_topic()
+                .into_iter()
+                .flat_map(|((group, topic), gtl)| {
+                    let dimensions = vec![
+                        dimension(DIMENSION_CLUSTER, &snapshot.cluster_id),
+                        dimension(DIMENSION_GROUP, group),
+                        dimension(DIMENSION_TOPIC, topic),
+                    ];
+
+                    [
+                        MetricDatum::builder()
+                            .metric_name(METRIC_OFFSET_LAG)
+                            .set_dimensions(Some(dimensions.clone()))
+                            .value(gtl.offset_lag as f64)
+                            .unit(StandardUnit::Count)
+                            .timestamp(timestamp)
+                            .build(),
+                        MetricDatum::builder()
+                            .metric_name(METRIC_TIME_LAG)
+                            .set_dimensions(Some(dimensions))
+                            .value(gtl.time_lag.num_milliseconds() as f64)
+                            .unit(StandardUnit::Milliseconds)
+                            .timestamp(timestamp)
+                            .build(),
+                    ]
+                })
+                .collect();
+
+            for chunk in datums.chunks(MAX_DATUMS_PER_REQUEST) {
+                self.client
+                    .put_metric_data()
+                    .namespace(&self.config.namespace)
+                    .set_metric_data(Some(chunk.to_vec()))
+                    .send()
+                    .await
+                    .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+fn dimension(name: &str, value: &str) -> Dimension {
+    Dimension::builder().name(name).value(value).build()
+}
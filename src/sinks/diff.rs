@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::internals::{LagSink, LagSinkFuture};
+use crate::kafka_types::{Interned, TopicPartition};
+use crate::lag_register::LagSnapshot;
+
+/// Offset Lag of each (Consumer Group, Topic Partition) pair, whose lag is known.
+type OffsetLags = HashMap<Interned, HashMap<TopicPartition, u64>>;
+
+/// What changed between 2 consecutive [`LagSnapshot`]s.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct SnapshotDiff {
+    added: BTreeSet<Interned>,
+    removed: BTreeSet<Interned>,
+
+    /// `(from, to)` Offset Lag of the (Consumer Group, Topic Partition) pairs that jumped.
+    jumped: BTreeMap<(Interned, TopicPartition), (u64, u64)>,
+}
+
+impl SnapshotDiff {
+    /// Diff `prev` and `next`, reporting the Offset Lags that moved by more than `threshold`.
+    fn between(prev: &OffsetLags, next: &OffsetLags, threshold: u64) -> Self {
+        let mut diff = Self {
+            added: next.keys().filter(|g| !prev.contains_key(*g)).cloned().collect(),
+            removed: prev.keys().filter(|g| !next.contains_key(*g)).cloned().collect(),
+            ..Default::default()
+        };
+
+        for (g, next_lags) in next.iter() {
+            let Some(prev_lags) = prev.get(g) else {
+                continue;
+            };
+            for (tp, to) in next_lags.iter() {
+                if let Some(from) = prev_lags.get(tp) {
+                    if from.abs_diff(*to) > threshold {
+                        diff.jumped.insert((g.clone(), tp.clone()), (*from, *to));
+                    }
+                }
+            }
+        }
+
+        diff
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.jumped.is_empty()
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |groups: &BTreeSet<Interned>| {
+            groups.iter().map(Interned::as_str).collect::<Vec<_>>().join(",")
+        };
+
+        write!(
+            f,
+            "+{} groups [{}], -{} groups [{}]",
+            self.added.len(),
+            join(&self.added),
+            self.removed.len(),
+            join(&self.removed)
+        )?;
+        for ((g, tp), (from, to)) in self.jumped.iter() {
+            write!(f, ", {g}@{tp}: {from} -> {to}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A [`LagSink`] that logs a compact diff of what changed since the previous [`LagSnapshot`]:
+/// Consumer Groups added and removed, and Topic Partitions whose Offset Lag jumped by more
+/// than a threshold.
+///
+/// Meant for debugging: it leaves a breadcrumb trail in the logs, when investigating
+/// sudden lag spikes. Nothing is logged when nothing changed.
+pub struct SnapshotDiffSink {
+    threshold: u64,
+    prev: Mutex<Option<OffsetLags>>,
+}
+
+impl SnapshotDiffSink {
+    /// Create a new [`SnapshotDiffSink`].
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Offset Lag change, above which a Topic Partition is reported as jumped
+    pub fn new(threshold: u64) -> Self {
+        Self {
+            threshold,
+            prev: Mutex::new(None),
+        }
+    }
+}
+
+impl LagSink for SnapshotDiffSink {
+    fn name(&self) -> &'static str {
+        "snapshot_diff"
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let next: OffsetLags = snapshot
+                .groups
+                .iter()
+                .map(|(g, gwl)| {
+                    let lags = gwl
+                        .lag_by_topic_partition
+                        .iter()
+                        .filter_map(|(tp, lwo)| {
+                            lwo.lag.as_ref().map(|l| (tp.clone(), l.offset_lag))
+                        })
+                        .collect();
+                    (g.clone(), lags)
+                })
+                .collect();
+
+            let mut prev = self.prev.lock().expect("Snapshot diff lock poisoned");
+            if let Some(prev) = prev.as_ref() {
+                let diff = SnapshotDiff::between(prev, &next, self.threshold);
+                if !diff.is_empty() {
+                    info!("Lag snapshot changed: {diff}");
+                }
+            }
+            *prev = Some(next);
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn offset_lags(groups: &[(&str, &[(u32, u64)])]) -> OffsetLags {
+        groups
+            .iter()
+            .map(|(g, lags)| {
+                let lags = lags
+                    .iter()
+                    .map(|(p, l)| (TopicPartition::new("t".to_string(), *p), *l))
+                    .collect();
+                (Interned::new(g), lags)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diff() {
+        let prev = offset_lags(&[("a", &[(0, 10), (1, 10)]), ("b", &[(0, 10)])]);
+        let next = offset_lags(&[("a", &[(0, 5000), (1, 50), (2, 9000)]), ("c", &[(0, 10)])]);
+
+        let diff = SnapshotDiff::between(&prev, &next, 100);
+        assert_eq!(diff.added, BTreeSet::from([Interned::new("c")]));
+        assert_eq!(diff.removed, BTreeSet::from([Interned::new("b")]));
+        assert_eq!(
+            diff.jumped,
+            BTreeMap::from([((Interned::new("a"), TopicPartition::new("t", 0)), (10, 5000))])
+        );
+
+        assert!(SnapshotDiff::between(&next, &next, 0).is_empty());
+    }
+}
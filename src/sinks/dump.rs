@@ -0,0 +1,322 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use tokio::{fs, io::AsyncWriteExt, time::Duration};
+
+use super::records::{lag_records, LagRecord};
+
+use crate::internals::{LagSink, LagSinkError, LagSinkFuture, LagSinkResult};
+use crate::lag_register::LagSnapshot;
+
+const FILE_PREFIX: &str = "lag";
+const CSV_DAY_FORMAT: &str = "%Y-%m-%d";
+#[cfg(feature = "parquet")]
+const PARQUET_TIME_FORMAT: &str = "%Y-%m-%dT%H-%M-%S%.3fZ";
+
+/// Format of the files written by [`DumpSink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DumpFormat {
+    /// One CSV file per day (UTC), with snapshots appended to it.
+    Csv,
+
+    /// One Parquet file per snapshot (as Parquet files can't be appended to):
+    /// bound how many are kept via [`DumpConfig::retention`].
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+/// Configuration of [`DumpSink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpConfig {
+    /// Directory to write files into. Created if it doesn't exist.
+    pub dir: PathBuf,
+
+    /// Format of the files.
+    pub format: DumpFormat,
+
+    /// How often to dump.
+    pub interval: Duration,
+
+    /// How long to keep the files for: older ones are deleted. If `None`, they are kept forever.
+    pub retention: Option<Duration>,
+}
+
+/// A [`LagSink`] that dumps [`LagRecord`]s to local files, for offline analysis
+/// (e.g. with pandas or DuckDB), without having to run a time series database.
+///
+/// Files are named after the time they refer to, so they rotate naturally:
+/// see [`DumpFormat`] for details.
+pub struct DumpSink {
+    config: DumpConfig,
+}
+
+impl DumpSink {
+    pub fn new(config: DumpConfig) -> LagSinkResult<Self> {
+        std::fs::create_dir_all(&config.dir).map_err(|e| {
+            LagSinkError::Delivery(format!("Unable to create '{}': {e}", config.dir.display()))
+        })?;
+
+        Ok(Self {
+            config,
+        })
+    }
+
+    fn path(&self, taken_at: &DateTime<Utc>) -> PathBuf {
+        let file_name = match self.config.format {
+            DumpFormat::Csv => format!("{FILE_PREFIX}-{}.csv", taken_at.format(CSV_DAY_FORMAT)),
+            #[cfg(feature = "parquet")]
+            DumpFormat::Parquet => {
+                format!("{FILE_PREFIX}-{}.parquet", taken_at.format(PARQUET_TIME_FORMAT))
+            },
+        };
+
+        self.config.dir.join(file_name)
+    }
+
+    /// Delete the files holding only snapshots taken before the retention, as of `now`.
+    async fn enforce_retention(&self, now: DateTime<Utc>) -> LagSinkResult<()> {
+        let Some(cutoff) = self
+            .config
+            .retention
+            .and_then(|r| chrono::Duration::from_std(r).ok())
+            .and_then(|r| now.checked_sub_signed(r))
+        else {
+            return Ok(());
+        };
+
+        let dir = &self.config.dir;
+        let to_delivery_err =
+            |e: std::io::Error| LagSinkError::Delivery(format!("'{}': {e}", dir.display()));
+        let mut entries = fs::read_dir(dir).await.map_err(to_delivery_err)?;
+        while let Some(entry) = entries.next_entry().await.map_err(to_delivery_err)? {
+            let expired = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| written_until(name, self.config.format))
+                .is_some_and(|until| until < cutoff);
+            if expired {
+                debug!("Deleting expired dump '{}'", entry.path().display());
+                fs::remove_file(entry.path()).await.map_err(to_delivery_err)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Time of the latest snapshot that the file of the given name can hold, if dumped by
+/// [`DumpSink`] in the given [`DumpFormat`]: CSV files are appended to until the end of their day.
+fn written_until(file_name: &str, format: DumpFormat) -> Option<DateTime<Utc>> {
+    let name = file_name.strip_prefix(FILE_PREFIX)?.strip_prefix('-')?;
+
+    match format {
+        DumpFormat::Csv => NaiveDate::parse_from_str(name.strip_suffix(".csv")?, CSV_DAY_FORMAT)
+            .ok()?
+            .succ_opt()?
+            .and_hms_opt(0, 0, 0)
+            .map(|dt| dt.and_utc()),
+        #[cfg(feature = "parquet")]
+        DumpFormat::Parquet => chrono::NaiveDateTime::parse_from_str(
+            name.strip_suffix(".parquet")?,
+            PARQUET_TIME_FORMAT,
+        )
+        .ok()
+        .map(|dt| dt.and_utc()),
+    }
+}
+
+impl LagSink for DumpSink {
+    fn name(&self) -> &'static str {
+        "dump"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let records = lag_records(snapshot);
+            let path = self.path(&snapshot.taken_at);
+            let to_delivery_err =
+                |e: std::io::Error| LagSinkError::Delivery(format!("'{}': {e}", path.display()));
+
+            match self.config.format {
+                DumpFormat::Csv => {
+                    // The header is written only once, when the file is created
+                    let with_header =
+                        fs::metadata(&path).await.map(|m| m.len() == 0).unwrap_or(true);
+                    let bytes = encode_csv(&records, with_header)?;
+
+                    let mut file = fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&path)
+                        .await
+                        .map_err(to_delivery_err)?;
+                    file.write_all(&bytes).await.map_err(to_delivery_err)?;
+                    file.flush().await.map_err(to_delivery_err)?;
+                },
+                #[cfg(feature = "parquet")]
+                DumpFormat::Parquet => {
+                    let bytes = encode_parquet(&records)?;
+                    fs::write(&path, bytes).await.map_err(to_delivery_err)?;
+                },
+            }
+
+            self.enforce_retention(snapshot.taken_at).await
+        })
+    }
+}
+
+fn encode_csv(records: &[LagRecord], with_header: bool) -> LagSinkResult<Vec<u8>> {
+    let mut wrt = csv::WriterBuilder::new().has_headers(with_header).from_writer(Vec::new());
+    for r in records {
+        wrt.serialize(r).map_err(|e| LagSinkError::Encode(e.to_string()))?;
+    }
+
+    wrt.into_inner().map_err(|e| LagSinkError::Encode(e.to_string()))
+}
+
+#[cfg(feature = "parquet")]
+fn encode_parquet(records: &[LagRecord]) -> LagSinkResult<Vec<u8>> {
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray, UInt32Array, UInt64Array};
+    use arrow_schema::{DataType, Field, Schema};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("cluster_id", DataType::Utf8, false),
+        Field::new("group", DataType::Utf8, false),
+        Field::new("topic", DataType::Utf8, false),
+        Field::new("partition", DataType::UInt32, false),
+        Field::new("member_id", DataType::Utf8, true),
+        Field::new("member_host", DataType::Utf8, true),
+        Field::new("member_client_id", DataType::Utf8, true),
+        Field::new("offset", DataType::UInt64, true),
+        Field::new("offset_timestamp_ms", DataType::Int64, true),
+        Field::new("offset_lag", DataType::UInt64, true),
+        Field::new("time_lag_ms", DataType::Int64, true),
+        Field::new("taken_at_ms", DataType::Int64, false),
+    ]));
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(records.iter().map(|r| r.cluster_id))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|r| r.group))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|r| r.topic))),
+        Arc::new(UInt32Array::from_iter_values(records.iter().map(|r| r.partition))),
+        Arc::new(StringArray::from_iter(records.iter().map(|r| r.member_id))),
+        Arc::new(StringArray::from_iter(records.iter().map(|r| r.member_host))),
+        Arc::new(StringArray::from_iter(records.iter().map(|r| r.member_client_id))),
+        Arc::new(UInt64Array::from_iter(records.iter().map(|r| r.offset))),
+        Arc::new(Int64Array::from_iter(records.iter().map(|r| r.offset_timestamp_ms))),
+        Arc::new(UInt64Array::from_iter(records.iter().map(|r| r.offset_lag))),
+        Arc::new(Int64Array::from_iter(records.iter().map(|r| r.time_lag_ms))),
+        Arc::new(Int64Array::from_iter_values(records.iter().map(|r| r.taken_at_ms))),
+    ];
+
+    let to_encode_err = |e: &dyn std::error::Error| LagSinkError::Encode(e.to_string());
+    let batch = RecordBatch::try_new(schema.clone(), columns).map_err(|e| to_encode_err(&e))?;
+    let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+
+    let mut wrt =
+        ArrowWriter::try_new(Vec::new(), schema, Some(props)).map_err(|e| to_encode_err(&e))?;
+    wrt.write(&batch).map_err(|e| to_encode_err(&e))?;
+    wrt.into_inner().map_err(|e| to_encode_err(&e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(partition: u32) -> LagRecord<'static> {
+        LagRecord {
+            cluster_id: "c",
+            group: "g",
+            topic: "t",
+            partition,
+            member_id: None,
+            member_host: None,
+            member_client_id: None,
+            offset: Some(10),
+            offset_timestamp_ms: Some(1),
+            offset_lag: Some(2),
+            time_lag_ms: Some(3),
+            taken_at_ms: 4,
+        }
+    }
+
+    #[test]
+    fn csv_header_only_when_requested() {
+        let records = vec![record(0), record(1)];
+
+        let with = String::from_utf8(encode_csv(&records, true).unwrap()).unwrap();
+        assert_eq!(with.lines().count(), 3);
+        assert!(with.starts_with("cluster_id,group,topic,partition,"));
+        assert_eq!(with.lines().nth(1), Some("c,g,t,0,,,,10,1,2,3,4"));
+
+        let without = String::from_utf8(encode_csv(&records, false).unwrap()).unwrap();
+        assert_eq!(without.lines().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn deletes_files_past_retention() {
+        let dir = std::env::temp_dir().join(format!("kommitted-dump-{}", std::process::id()));
+        let sink = DumpSink::new(DumpConfig {
+            dir: dir.clone(),
+            format: DumpFormat::Csv,
+            interval: Duration::from_secs(60),
+            retention: Some(Duration::from_secs(2 * 24 * 60 * 60)),
+        })
+        .unwrap();
+        for name in ["lag-2024-06-01.csv", "lag-2024-06-02.csv", "lag-2024-06-03.csv", "notes.csv"]
+        {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+
+        let now = DateTime::parse_from_rfc3339("2024-06-04T12:00:00Z").unwrap().to_utc();
+        sink.enforce_retention(now).await.unwrap();
+
+        let mut left: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(left, vec!["lag-2024-06-02.csv", "lag-2024-06-03.csv", "notes.csv"]);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_files_are_written_at_snapshot_time() {
+        let sink = DumpSink {
+            config: DumpConfig {
+                dir: PathBuf::new(),
+                format: DumpFormat::Parquet,
+                interval: Duration::from_secs(60),
+                retention: None,
+            },
+        };
+        let taken_at = DateTime::parse_from_rfc3339("2024-06-04T12:34:56.789Z").unwrap().to_utc();
+
+        let path = sink.path(&taken_at);
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap();
+        assert_eq!(written_until(name, DumpFormat::Parquet), Some(taken_at));
+        assert_eq!(written_until(name, DumpFormat::Csv), None);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_roundtrip() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let bytes = encode_parquet(&[record(0), record(1)]).unwrap();
+        let reader = SerializedFileReader::new(bytes::Bytes::from(bytes)).unwrap();
+
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        assert_eq!(reader.metadata().file_metadata().schema_descr().num_columns(), 12);
+    }
+}
@@ -0,0 +1,126 @@
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+use super::samples::{lag_samples, Sample};
+
+use crate::internals::{LagSink, LagSinkError, LagSinkFuture};
+use crate::lag_register::LagSnapshot;
+
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Configuration of [`GraphiteSink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphiteConfig {
+    /// Address of the Graphite plaintext receiver (e.g. `graphite:2003`).
+    pub address: String,
+
+    /// Prefix prepended to each metric name (e.g. `kafka.lag`).
+    pub prefix: Option<String>,
+
+    /// How often to write.
+    pub interval: Duration,
+}
+
+/// A [`LagSink`] that writes lag measurements to Graphite, via the
+/// [plaintext protocol](https://graphite.readthedocs.io/en/latest/feeding-carbon.html#the-plaintext-protocol).
+///
+/// Labels are written as [tags](https://graphite.readthedocs.io/en/latest/tags.html),
+/// so Graphite `>= 1.1` is required.
+pub struct GraphiteSink {
+    config: GraphiteConfig,
+}
+
+impl GraphiteSink {
+    pub fn new(config: GraphiteConfig) -> Self {
+        Self {
+            config,
+        }
+    }
+}
+
+impl LagSink for GraphiteSink {
+    fn name(&self) -> &'static str {
+        "graphite"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let body: String = lag_samples(snapshot)
+                .iter()
+                .map(|s| format_line(s, self.config.prefix.as_deref()))
+                .collect();
+
+            let send = async {
+                let mut stream = TcpStream::connect(&self.config.address).await?;
+                stream.write_all(body.as_bytes()).await?;
+                stream.shutdown().await
+            };
+
+            timeout(PUSH_TIMEOUT, send)
+                .await
+                .map_err(|_| {
+                    LagSinkError::Delivery(format!("'{}' timed out", self.config.address))
+                })?
+                .map_err(|e| LagSinkError::Delivery(e.to_string()))
+        })
+    }
+}
+
+/// Replaces the characters that are not allowed in Graphite tag names and values.
+fn sanitize(s: &str) -> String {
+    s.replace([';', '!', '^', '=', '~', ' ', '\n'], "_")
+}
+
+/// Formats a [`Sample`] as a single, tagged, plaintext protocol line (timestamp in seconds).
+fn format_line(sample: &Sample, prefix: Option<&str>) -> String {
+    let mut path = match prefix {
+        Some(p) => format!("{}.{}", sanitize(p), sanitize(&sample.name)),
+        None => sanitize(&sample.name),
+    };
+    for (k, v) in sample.labels.iter() {
+        // Empty tag values are not allowed
+        if v.is_empty() {
+            continue;
+        }
+        path.push(';');
+        path.push_str(&sanitize(k));
+        path.push('=');
+        path.push_str(&sanitize(v));
+    }
+    format!("{path} {} {}\n", sample.value, sample.timestamp_ms / 1000)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_tagged_line() {
+        let sample = Sample {
+            name: "kmtd_consumer_partition_lag_offset".to_string(),
+            labels: vec![
+                ("group".to_string(), "my group".to_string()),
+                ("topic".to_string(), "my-topic".to_string()),
+                ("member_host".to_string(), "".to_string()),
+            ],
+            value: 42_f64,
+            timestamp_ms: 1_500,
+        };
+
+        assert_eq!(
+            format_line(&sample, Some("kafka")),
+            "kafka.kmtd_consumer_partition_lag_offset;group=my_group;topic=my-topic 42 1\n"
+        );
+    }
+
+    #[test]
+    fn sanitize_reserved_characters() {
+        assert_eq!(sanitize("a;b!c^d=e~f g\nh"), "a_b_c_d_e_f_g_h");
+        assert_eq!(sanitize("my-topic.v1"), "my-topic.v1");
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use prometheus::{
+    exponential_buckets, register_gauge_vec_with_registry, register_histogram_with_registry,
+    GaugeVec, Histogram, Registry,
+};
+use rdkafka::{
+    consumer::{BaseConsumer, Consumer},
+    error::KafkaResult,
+    ClientConfig, Offset, TopicPartitionList,
+};
+use tokio::time::Duration;
+
+use crate::internals::{LagSink, LagSinkError, LagSinkFuture, LagSinkResult};
+use crate::kafka_types::TopicPartition;
+use crate::lag_register::LagSnapshot;
+use crate::partition_offsets::PartitionOffsetsRegister;
+use crate::prometheus_metrics::LABEL_QUANTILE;
+
+const OFFSETS_FOR_TIMES_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MET_ERROR_NAME: &str = "time_lag_estimation_error_milliseconds";
+const MET_ERROR_HELP: &str =
+    "Difference (ms) between estimated and actual production time of committed offsets";
+const MET_ERROR_QUANTILES_NAME: &str = "time_lag_estimation_error_seconds";
+const MET_ERROR_QUANTILES_HELP: &str =
+    "Quantiles of the difference (s) between estimated and actual production time of committed offsets, over the latest ground truths";
+
+/// How many of the latest estimation errors the quantiles are computed over.
+const ERROR_WINDOW_LEN: usize = 1024;
+
+/// Quantiles of the estimation error that are exported.
+const ERROR_QUANTILES: [(f64, &str); 3] = [(0.5, "0.5"), (0.9, "0.9"), (0.99, "0.99")];
+
+/// Configuration of [`GroundTruthSink`].
+#[derive(Debug, Clone)]
+pub struct GroundTruthConfig {
+    /// How many of the laggiest Topic Partitions to ground-truth, each time.
+    pub partitions: usize,
+
+    /// How often to ground-truth.
+    pub interval: Duration,
+}
+
+/// A [`LagSink`] that ground-truths the Time Lag estimates of the laggiest Topic Partitions.
+///
+/// For each, it finds the actual production time of the committed offset, bisecting timestamps
+/// with `OffsetsForTimes` requests (all Topic Partitions are bisected together, in a single
+/// request per step). The actual production time is then handed to the
+/// [`PartitionOffsetsRegister`], to narrow down the following estimates, and the difference
+/// with the estimated one is tracked by the `time_lag_estimation_error_milliseconds` metric.
+/// Quantiles of the latest [`ERROR_WINDOW_LEN`] errors are also exported, as the
+/// `time_lag_estimation_error_seconds` metric.
+pub struct GroundTruthSink {
+    config: GroundTruthConfig,
+    consumer: Arc<BaseConsumer>,
+    po_reg: Arc<PartitionOffsetsRegister>,
+    errors: Mutex<VecDeque<f64>>,
+    metric_error: Histogram,
+    metric_error_quantiles: GaugeVec,
+}
+
+impl GroundTruthSink {
+    /// Create a new [`GroundTruthSink`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration of the sink
+    /// * `consumer` - Used to send `OffsetsForTimes` requests (see [`Self::create_consumer`])
+    /// * `po_reg` - Where to estimate production times, and to hand ground truths to
+    /// * `metrics` - Where to register the `time_lag_estimation_error_*` metrics
+    pub fn new(
+        config: GroundTruthConfig,
+        consumer: BaseConsumer,
+        po_reg: Arc<PartitionOffsetsRegister>,
+        metrics: Arc<Registry>,
+    ) -> Self {
+        Self {
+            config,
+            consumer: Arc::new(consumer),
+            po_reg,
+            errors: Mutex::new(VecDeque::with_capacity(ERROR_WINDOW_LEN)),
+            metric_error: register_histogram_with_registry!(
+                MET_ERROR_NAME,
+                MET_ERROR_HELP,
+                exponential_buckets(10_f64, 4_f64, 10).expect("Invalid buckets"),
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_ERROR_NAME}")),
+            metric_error_quantiles: register_gauge_vec_with_registry!(
+                MET_ERROR_QUANTILES_NAME,
+                MET_ERROR_QUANTILES_HELP,
+                &[LABEL_QUANTILE],
+                metrics
+            )
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_ERROR_QUANTILES_NAME}")),
+        }
+    }
+
+    /// Create the [`BaseConsumer`] to send `OffsetsForTimes` requests with.
+    ///
+    /// Separate from [`Self::new`], so it can be created before the [`PartitionOffsetsRegister`].
+    pub fn create_consumer(client_config: &ClientConfig) -> LagSinkResult<BaseConsumer> {
+        client_config.create().map_err(|e| LagSinkError::Delivery(e.to_string()))
+    }
+
+    /// Record the given estimation errors (ms), and update the quantiles over the latest ones.
+    fn record_errors(&self, new_errors: &[f64]) {
+        if new_errors.is_empty() {
+            return;
+        }
+
+        let mut sorted = {
+            let mut errors = self.errors.lock().expect("Errors window lock poisoned");
+            for e in new_errors {
+                if errors.len() == ERROR_WINDOW_LEN {
+                    errors.pop_front();
+                }
+                errors.push_back(*e);
+                self.metric_error.observe(*e);
+            }
+            errors.iter().copied().collect::<Vec<f64>>()
+        };
+        sorted.sort_by(f64::total_cmp);
+
+        for (q, label) in ERROR_QUANTILES {
+            self.metric_error_quantiles
+                .with_label_values(&[label])
+                .set(quantile(&sorted, q) / 1000_f64);
+        }
+    }
+}
+
+/// The `q` quantile (nearest-rank) of the given, sorted, values: `0.0` if there are none.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0_f64;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// The [`TopicPartition`]s with the highest Offset Lag in the given [`LagSnapshot`], up to `limit`.
+///
+/// Each is paired with the committed offset of the laggiest Consumer Group consuming it.
+fn laggiest_partitions(snapshot: &LagSnapshot, limit: usize) -> Vec<(TopicPartition, u64)> {
+    let mut laggiest: HashMap<&TopicPartition, (u64, u64)> = HashMap::new();
+    for (_, tp, lwo) in snapshot.iter_group_partitions() {
+        if let Some(lag) = lwo.lag().filter(|l| l.offset_lag() > 0) {
+            let entry = laggiest.entry(tp).or_insert((lag.offset(), lag.offset_lag()));
+            if lag.offset_lag() > entry.1 {
+                *entry = (lag.offset(), lag.offset_lag());
+            }
+        }
+    }
+
+    let mut laggiest: Vec<(&TopicPartition, (u64, u64))> = laggiest.into_iter().collect();
+    laggiest.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(b.0)));
+    laggiest.into_iter().take(limit).map(|(tp, (offset, _))| (tp.clone(), offset)).collect()
+}
+
+/// Bisection of timestamps (ms), to find the one of the record at `offset`.
+///
+/// `OffsetsForTimes` returns, for a given timestamp, the earliest offset with an equal or
+/// greater timestamp. So, for the record at `offset`, the timestamp is the greatest one
+/// for which `OffsetsForTimes` returns an offset equal or lower than `offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bisection {
+    offset: u64,
+
+    /// Greatest timestamp known to return an offset `<= offset`.
+    lo: i64,
+
+    /// Lowest timestamp known to return an offset `> offset` (or none at all).
+    hi: i64,
+}
+
+impl Bisection {
+    /// Timestamp to send the next `OffsetsForTimes` request for, if not converged yet.
+    fn next_timestamp(&self) -> Option<i64> {
+        (self.hi - self.lo > 1).then(|| self.lo + (self.hi - self.lo) / 2)
+    }
+
+    /// Narrow down, given the result of the `OffsetsForTimes` request for `timestamp`.
+    fn narrow(&mut self, timestamp: i64, found: Offset) {
+        match found {
+            Offset::Offset(o) if o as u64 <= self.offset => self.lo = timestamp,
+            _ => self.hi = timestamp,
+        }
+    }
+}
+
+/// Find the actual production time of the committed offset of each Topic Partition.
+///
+/// Topic Partitions for which it can't be found (e.g. offset no longer available) are omitted.
+///
+/// This is blocking: call it via [`tokio::task::spawn_blocking`].
+fn fetch_produced_at(
+    consumer: &BaseConsumer,
+    targets: &[(TopicPartition, u64)],
+    now: DateTime<Utc>,
+) -> KafkaResult<Vec<(TopicPartition, u64, DateTime<Utc>)>> {
+    let (lo, hi) = (0, now.timestamp_millis() + 1);
+    let mut bisections: HashMap<TopicPartition, Bisection> = targets
+        .iter()
+        .map(|(tp, offset)| {
+            (
+                tp.clone(),
+                Bisection {
+                    offset: *offset,
+                    lo,
+                    hi,
+                },
+            )
+        })
+        .collect();
+
+    // Validate the bounds first: the earliest record must not follow the offset,
+    // and the latest must not be timestamped in the future
+    let precedes = |found: Option<&Offset>, offset: u64| matches!(found, Some(Offset::Offset(o)) if *o as u64 <= offset);
+    let found = offsets_for_times(consumer, bisections.keys().map(|tp| (tp, lo)))?;
+    bisections.retain(|tp, b| precedes(found.get(tp), b.offset));
+    let found = offsets_for_times(consumer, bisections.keys().map(|tp| (tp, hi)))?;
+    bisections.retain(|tp, b| !precedes(found.get(tp), b.offset));
+
+    loop {
+        let next: Vec<(TopicPartition, i64)> = bisections
+            .iter()
+            .filter_map(|(tp, b)| b.next_timestamp().map(|ts| (tp.clone(), ts)))
+            .collect();
+        if next.is_empty() {
+            break;
+        }
+
+        let found = offsets_for_times(consumer, next.iter().map(|(tp, ts)| (tp, *ts)))?;
+        for (tp, ts) in next {
+            if let Some(b) = bisections.get_mut(&tp) {
+                b.narrow(ts, found.get(&tp).copied().unwrap_or(Offset::End));
+            }
+        }
+    }
+
+    Ok(bisections
+        .into_iter()
+        .filter_map(|(tp, b)| DateTime::from_timestamp_millis(b.lo).map(|at| (tp, b.offset, at)))
+        .collect())
+}
+
+/// Send a single `OffsetsForTimes` request, for all the given (Topic Partition, timestamp) pairs.
+fn offsets_for_times<'a>(
+    consumer: &BaseConsumer,
+    timestamps: impl Iterator<Item = (&'a TopicPartition, i64)>,
+) -> KafkaResult<HashMap<TopicPartition, Offset>> {
+    let mut tpl = TopicPartitionList::new();
+    for (tp, ts) in timestamps {
+        tpl.add_partition_offset(&tp.topic, tp.partition as i32, Offset::Offset(ts))?;
+    }
+    if tpl.count() == 0 {
+        return Ok(HashMap::new());
+    }
+
+    Ok(consumer
+        .offsets_for_times(tpl, OFFSETS_FOR_TIMES_TIMEOUT)?
+        .elements()
+        .iter()
+        .map(|elem| (TopicPartition::new(elem.topic(), elem.partition() as u32), elem.offset()))
+        .collect())
+}
+
+impl LagSink for GroundTruthSink {
+    fn name(&self) -> &'static str {
+        "ground_truth"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let targets = laggiest_partitions(snapshot, self.config.partitions);
+            if targets.is_empty() {
+                return Ok(());
+            }
+
+            let consumer = self.consumer.clone();
+            let now = snapshot.taken_at;
+            let ground_truths =
+                tokio::task::spawn_blocking(move || fetch_produced_at(&consumer, &targets, now))
+                    .await
+                    .map_err(|e| LagSinkError::Delivery(e.to_string()))?
+                    .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+            let mut errors = Vec::with_capacity(ground_truths.len());
+            for (tp, offset, produced_at) in ground_truths {
+                // Measure the error of the estimate, before it gets narrowed down by the ground truth
+                match self.po_reg.estimate_produced_at(&tp, offset).await {
+                    Ok(estimated) => {
+                        let error = (estimated - produced_at).num_milliseconds().abs();
+                        trace!("Production time of '{tp}' offset {offset} estimate is off by {error}ms");
+                        errors.push(error as f64);
+                    },
+                    Err(e) => {
+                        debug!("Failed to estimate production time of '{tp}' offset {offset}: {e}")
+                    },
+                }
+
+                if let Err(e) = self.po_reg.add_ground_truth(&tp, offset, produced_at).await {
+                    debug!("Failed to add ground truth of '{tp}' offset {offset}: {e}");
+                }
+            }
+            self.record_errors(&errors);
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// `OffsetsForTimes` of a partition with records `0..10`, produced at `1000 + offset * 100`ms.
+    fn offsets_for_time(ts: i64) -> Offset {
+        match (ts - 1000 + 99).div_euclid(100).max(0) {
+            o if o < 10 => Offset::Offset(o),
+            _ => Offset::End,
+        }
+    }
+
+    #[test]
+    fn bisect_production_time() {
+        for offset in 0..10_u64 {
+            let mut b = Bisection {
+                offset,
+                lo: 0,
+                hi: 10_000,
+            };
+            while let Some(ts) = b.next_timestamp() {
+                b.narrow(ts, offsets_for_time(ts));
+            }
+            assert_eq!(b.lo, 1000 + offset as i64 * 100);
+        }
+    }
+
+    #[test]
+    fn nearest_rank_quantiles() {
+        let sorted: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(quantile(&sorted, 0.5), 50_f64);
+        assert_eq!(quantile(&sorted, 0.99), 99_f64);
+        assert_eq!(quantile(&sorted, 0_f64), 1_f64);
+        assert_eq!(quantile(&sorted[..1], 0.9), 1_f64);
+        assert_eq!(quantile(&[], 0.9), 0_f64);
+    }
+}
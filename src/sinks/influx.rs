@@ -0,0 +1,187 @@
+use std::fmt;
+
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use tokio::time::Duration;
+
+use super::samples::{lag_samples, Sample};
+
+use crate::internals::{LagSink, LagSinkError, LagSinkFuture, LagSinkResult, REDACTED};
+use crate::lag_register::LagSnapshot;
+
+const WRITE_PATH: &str = "/api/v2/write";
+const FIELD_VALUE: &str = "value";
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Configuration of [`InfluxSink`].
+///
+/// When formatted via [`fmt::Debug`], the token is redacted.
+#[derive(Clone, PartialEq, Eq)]
+pub struct InfluxConfig {
+    /// Base URL of the InfluxDB server (e.g. `http://influxdb:8086`).
+    pub url: String,
+
+    /// Organization to write to.
+    pub org: String,
+
+    /// Bucket to write to.
+    pub bucket: String,
+
+    /// API token to authenticate with, if any.
+    pub token: Option<String>,
+
+    /// How often to write.
+    pub interval: Duration,
+}
+
+impl fmt::Debug for InfluxConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InfluxConfig")
+            .field("url", &self.url)
+            .field("org", &self.org)
+            .field("bucket", &self.bucket)
+            .field("token", &self.token.as_ref().map(|_| REDACTED))
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+/// A [`LagSink`] that writes lag measurements to InfluxDB, via the
+/// [v2 HTTP API](https://docs.influxdata.com/influxdb/v2/api/#operation/PostWrite)
+/// and the [line protocol](https://docs.influxdata.com/influxdb/v2/reference/syntax/line-protocol/).
+///
+/// Each metric rendered at `/metrics` becomes a measurement, with its labels as tags,
+/// and its value in the `value` field.
+pub struct InfluxSink {
+    config: InfluxConfig,
+    client: reqwest::Client,
+}
+
+impl InfluxSink {
+    pub fn new(config: InfluxConfig) -> LagSinkResult<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain; charset=utf-8"));
+        if let Some(token) = &config.token {
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::try_from(format!("Token {token}"))
+                    .map_err(|e| LagSinkError::Config(e.to_string()))?,
+            );
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(PUSH_TIMEOUT)
+            .build()
+            .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+        Ok(Self {
+            config,
+            client,
+        })
+    }
+}
+
+impl LagSink for InfluxSink {
+    fn name(&self) -> &'static str {
+        "influx"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let body =
+                lag_samples(snapshot).iter().map(format_line).collect::<Vec<String>>().join("\n");
+
+            let url = format!("{}{WRITE_PATH}", self.config.url.trim_end_matches('/'));
+            let res = self
+                .client
+                .post(&url)
+                .query(&[
+                    ("org", self.config.org.as_str()),
+                    ("bucket", self.config.bucket.as_str()),
+                    ("precision", "ms"),
+                ])
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+            if !res.status().is_success() {
+                return Err(LagSinkError::Delivery(format!("'{url}' responded {}", res.status())));
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Escapes the characters that are special in line protocol measurements, tag keys and tag values.
+fn escape(s: &str, chars: &[char]) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '\\' || chars.contains(&c) {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Formats a [`Sample`] as a single line protocol line, with milliseconds precision.
+fn format_line(sample: &Sample) -> String {
+    let mut line = escape(&sample.name, &[',', ' ']);
+    for (k, v) in sample.labels.iter() {
+        // Empty tag values are not allowed
+        if v.is_empty() {
+            continue;
+        }
+        line.push(',');
+        line.push_str(&escape(k, &[',', '=', ' ']));
+        line.push('=');
+        line.push_str(&escape(v, &[',', '=', ' ']));
+    }
+    format!("{line} {FIELD_VALUE}={} {}", sample.value, sample.timestamp_ms)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_token() {
+        let config = InfluxConfig {
+            url: "http://influxdb:8086".to_string(),
+            org: "org".to_string(),
+            bucket: "bucket".to_string(),
+            token: Some("s3cr3t".to_string()),
+            interval: Duration::from_secs(10),
+        };
+
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("s3cr3t"));
+        assert!(debug.contains(REDACTED));
+        assert!(debug.contains("http://influxdb:8086"));
+    }
+
+    #[test]
+    fn format_line_escapes() {
+        let sample = Sample {
+            name: "kmtd_consumer_partition_lag_offset".to_string(),
+            labels: vec![
+                ("group".to_string(), "my group".to_string()),
+                ("topic".to_string(), "a,b=c".to_string()),
+                ("member_host".to_string(), "".to_string()),
+            ],
+            value: 42_f64,
+            timestamp_ms: 1000,
+        };
+
+        assert_eq!(
+            format_line(&sample),
+            r"kmtd_consumer_partition_lag_offset,group=my\ group,topic=a\,b\=c value=42 1000"
+        );
+    }
+}
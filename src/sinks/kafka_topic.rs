@@ -0,0 +1,144 @@
+use std::fmt;
+
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::ClientConfig;
+use serde::Serialize;
+use tokio::time::Duration;
+
+use super::records::lag_records;
+
+use crate::internals::{
+    redacted_client_config, LagSink, LagSinkError, LagSinkFuture, LagSinkResult,
+};
+use crate::lag_register::LagSnapshot;
+
+/// Configuration of [`KafkaTopicSink`].
+///
+/// When formatted via [`fmt::Debug`], the secrets in the Kafka Producer configuration are redacted.
+#[derive(Clone)]
+pub struct KafkaTopicConfig {
+    /// Topic to produce lag records to.
+    pub topic: String,
+
+    /// If `true`, records are keyed by (group, topic, partition): useful for compacted topics.
+    pub keyed: bool,
+
+    /// Configuration of the Kafka Producer.
+    ///
+    /// If `None`, the same configuration used to monitor the Kafka cluster is used:
+    /// that is, lag records are produced to the monitored cluster.
+    pub client_config: Option<ClientConfig>,
+
+    /// How often to produce.
+    pub interval: Duration,
+}
+
+impl fmt::Debug for KafkaTopicConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KafkaTopicConfig")
+            .field("topic", &self.topic)
+            .field("keyed", &self.keyed)
+            .field("client_config", &self.client_config.as_ref().map(redacted_client_config))
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+/// Key of a [`LagRecord`](super::LagRecord), when [`KafkaTopicConfig::keyed`] is `true`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LagRecordKey<'a> {
+    pub group: &'a str,
+    pub topic: &'a str,
+    pub partition: u32,
+}
+
+/// A [`LagSink`] that produces a JSON [`LagRecord`](super::LagRecord) per Consumer Group and Topic Partition
+/// to a Kafka topic, so that lag data can be further processed downstream.
+pub struct KafkaTopicSink {
+    config: KafkaTopicConfig,
+    producer: FutureProducer,
+}
+
+impl KafkaTopicSink {
+    /// Create a new [`KafkaTopicSink`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration of the sink
+    /// * `default_client_config` - Used when [`KafkaTopicConfig::client_config`] is `None`
+    pub fn new(
+        config: KafkaTopicConfig,
+        default_client_config: &ClientConfig,
+    ) -> LagSinkResult<Self> {
+        let producer = config
+            .client_config
+            .as_ref()
+            .unwrap_or(default_client_config)
+            .create()
+            .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+        Ok(Self {
+            config,
+            producer,
+        })
+    }
+}
+
+impl LagSink for KafkaTopicSink {
+    fn name(&self) -> &'static str {
+        "kafka_topic"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let records = lag_records(snapshot);
+            let mut deliveries = Vec::with_capacity(records.len());
+
+            // Enqueue all records first, then await their delivery
+            for record in records.iter() {
+                let payload =
+                    serde_json::to_vec(record).map_err(|e| LagSinkError::Encode(e.to_string()))?;
+
+                let key = if self.config.keyed {
+                    Some(
+                        serde_json::to_vec(&LagRecordKey {
+                            group: record.group,
+                            topic: record.topic,
+                            partition: record.partition,
+                        })
+                        .map_err(|e| LagSinkError::Encode(e.to_string()))?,
+                    )
+                } else {
+                    None
+                };
+
+                let mut fr = FutureRecord::to(&self.config.topic).payload(&payload);
+                if let Some(k) = key.as_ref() {
+                    fr = fr.key(k);
+                }
+
+                deliveries.push(
+                    self.producer
+                        .send_result(fr)
+                        .map_err(|(e, _)| LagSinkError::Delivery(e.to_string()))?,
+                );
+            }
+
+            for d in deliveries {
+                match d.await {
+                    Ok(Ok(_)) => {},
+                    Ok(Err((e, _))) => return Err(LagSinkError::Delivery(e.to_string())),
+                    Err(_) => {
+                        return Err(LagSinkError::Delivery("Delivery was cancelled".to_string()))
+                    },
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
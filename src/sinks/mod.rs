@@ -0,0 +1,205 @@
+//! Publishing of lag data to [`LagSink`]s.
+//!
+//! A [`LagSnapshot`] is taken every time the [`LagRegister`] or the [`PartitionOffsetsRegister`]
+//! notify a change, and then handed to each of the configured [`LagSink`]s. Only the Consumer
+//! Groups and Topic Partitions that changed are read again: the rest is carried over from
+//! the previous snapshot. Bursts of changes are
+//! coalesced into a single snapshot every [`RECONCILE_MIN_INTERVAL`], while a snapshot is taken
+//! anyway every [`RECONCILE_MAX_INTERVAL`], as the Time Lag grows even when nothing changes.
+//! Both are subject to the configured [`Jitter`], if any.
+//!
+//! Each sink runs in its own task, so that a slow one doesn't hold back the others:
+//! if a sink is still busy publishing when a new snapshot is taken, it will skip straight to the
+//! latest one.
+
+#[cfg(feature = "cloudwatch")]
+mod cloudwatch;
+mod diff;
+mod dump;
+mod graphite;
+mod ground_truth;
+mod influx;
+mod kafka_topic;
+#[cfg(feature = "otlp")]
+mod otlp;
+mod record_sizes;
+mod records;
+mod remote_write;
+mod samples;
+mod statsd;
+
+use std::sync::Arc;
+
+use prometheus::{
+    register_histogram_vec_with_registry, register_histogram_with_registry,
+    register_int_counter_vec_with_registry, Histogram, HistogramVec, IntCounterVec, Registry,
+};
+use tokio::{
+    sync::watch,
+    task::JoinHandle,
+    time::{sleep, Duration, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::cluster_status::ClusterStatusRegister;
+use crate::internals::{Jitter, LagSink};
+use crate::lag_register::{LagRegister, LagSnapshot};
+use crate::partition_offsets::PartitionOffsetsRegister;
+use crate::prometheus_metrics::LABEL_SINK;
+
+#[cfg(feature = "cloudwatch")]
+pub use cloudwatch::{CloudWatchConfig, CloudWatchSink};
+pub use diff::SnapshotDiffSink;
+pub use dump::{DumpConfig, DumpFormat, DumpSink};
+pub use graphite::{GraphiteConfig, GraphiteSink};
+pub use ground_truth::{GroundTruthConfig, GroundTruthSink};
+pub use influx::{InfluxConfig, InfluxSink};
+pub use kafka_topic::{KafkaTopicConfig, KafkaTopicSink};
+#[cfg(feature = "otlp")]
+pub use otlp::{OtlpConfig, OtlpProtocol, OtlpSink};
+pub use record_sizes::{RecordSizes, RecordSizesConfig, RecordSizesSink};
+pub use remote_write::{RemoteWriteConfig, RemoteWriteSink};
+pub use statsd::{StatsdConfig, StatsdFlavor, StatsdSink};
+
+/// Min interval between 2 consecutive snapshots.
+const RECONCILE_MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Max interval between 2 consecutive snapshots, when no change is notified.
+const RECONCILE_MAX_INTERVAL: Duration = Duration::from_secs(10);
+
+const MET_SNAPSHOT_NAME: &str = "sinks_snapshot_time_seconds";
+const MET_SNAPSHOT_HELP: &str = "Time (s) taken to take a snapshot of the lag data";
+const MET_PUBLISH_NAME: &str = "sinks_publish_time_seconds";
+const MET_PUBLISH_HELP: &str = "Time (s) taken by a sink to publish a snapshot of the lag data";
+const MET_ERRORS_NAME: &str = "sinks_publish_errors_total";
+const MET_ERRORS_HELP: &str = "Failures of a sink to publish a snapshot of the lag data";
+
+pub fn init(
+    sinks: Vec<Arc<dyn LagSink>>,
+    cs_reg: Arc<ClusterStatusRegister>,
+    po_reg: Arc<PartitionOffsetsRegister>,
+    lag_reg: Arc<LagRegister>,
+    jitter: Jitter,
+    shutdown_token: CancellationToken,
+    metrics: Arc<Registry>,
+) -> JoinHandle<()> {
+    let metric_snapshot: Histogram =
+        register_histogram_with_registry!(MET_SNAPSHOT_NAME, MET_SNAPSHOT_HELP, metrics)
+            .unwrap_or_else(|_| panic!("Failed to create metric: {MET_SNAPSHOT_NAME}"));
+    let metric_publish: HistogramVec = register_histogram_vec_with_registry!(
+        MET_PUBLISH_NAME,
+        MET_PUBLISH_HELP,
+        &[LABEL_SINK],
+        metrics
+    )
+    .unwrap_or_else(|_| panic!("Failed to create metric: {MET_PUBLISH_NAME}"));
+    let metric_errors: IntCounterVec = register_int_counter_vec_with_registry!(
+        MET_ERRORS_NAME,
+        MET_ERRORS_HELP,
+        &[LABEL_SINK],
+        metrics
+    )
+    .unwrap_or_else(|_| panic!("Failed to create metric: {MET_ERRORS_NAME}"));
+
+    let (snapshot_sx, snapshot_rx) = watch::channel(Arc::new(LagSnapshot::default()));
+
+    // One task per sink, each waiting for the next snapshot to publish
+    let sink_joins = sinks
+        .into_iter()
+        .map(|sink| {
+            let mut snapshot_rx = snapshot_rx.clone();
+            let metric_publish = metric_publish.with_label_values(&[sink.name()]);
+            let metric_errors = metric_errors.with_label_values(&[sink.name()]);
+
+            tokio::spawn(async move {
+                debug!("Begin publishing to '{}' sink", sink.name());
+                let mut last_published: Option<Instant> = None;
+
+                // Terminates once the `Sender` is dropped
+                while snapshot_rx.changed().await.is_ok() {
+                    let snapshot = snapshot_rx.borrow_and_update().clone();
+
+                    // Skip this snapshot, if the sink is not due to publish yet
+                    if let (Some(pi), Some(lp)) = (sink.publish_interval(), last_published) {
+                        if lp.elapsed() < pi {
+                            continue;
+                        }
+                    }
+                    last_published = Some(Instant::now());
+
+                    let timer = metric_publish.start_timer();
+                    if let Err(e) = sink.publish(&snapshot).await {
+                        error!("Failed to publish to '{}' sink: {e}", sink.name());
+                        metric_errors.inc();
+                    }
+                    timer.observe_duration();
+                }
+
+                debug!("Stopped publishing to '{}' sink", sink.name());
+            })
+        })
+        .collect::<Vec<JoinHandle<()>>>();
+
+    tokio::spawn(async move {
+        let mut lag_changes = lag_reg.subscribe_changes();
+        let mut lag_changes_open = true;
+        let mut po_changes = po_reg.subscribe_changes();
+        let mut po_changes_open = true;
+        let mut heartbeat = jitter.interval(RECONCILE_MAX_INTERVAL);
+
+        loop {
+            tokio::select! {
+                res = lag_changes.changed(), if lag_changes_open => {
+                    if res.is_err() {
+                        lag_changes_open = false;
+                        continue;
+                    }
+                },
+                res = po_changes.changed(), if po_changes_open => {
+                    if res.is_err() {
+                        po_changes_open = false;
+                        continue;
+                    }
+                },
+                _ = heartbeat.tick() => {},
+                _ = shutdown_token.cancelled() => {
+                    info!("Shutting down");
+                    break;
+                },
+            }
+
+            // Whatever changed so far is going to be in this snapshot: only that is read again
+            lag_changes.borrow_and_update();
+            po_changes.borrow_and_update();
+            let changed_groups = lag_reg.take_changed_groups();
+            let changed_partitions = po_reg.take_changed_partitions();
+
+            let timer = metric_snapshot.start_timer();
+            let prev = snapshot_sx.borrow().clone();
+            let snapshot =
+                prev.update(&cs_reg, &po_reg, &lag_reg, &changed_groups, &changed_partitions).await;
+            timer.observe_duration();
+
+            trace!("Publishing snapshot of {} groups", snapshot.groups.len());
+            snapshot_sx.send_replace(Arc::new(snapshot));
+            heartbeat.reset();
+
+            // Let changes accumulate, before taking the next snapshot
+            tokio::select! {
+                _ = sleep(jitter.apply(RECONCILE_MIN_INTERVAL)) => {},
+                _ = shutdown_token.cancelled() => {
+                    info!("Shutting down");
+                    break;
+                },
+            }
+        }
+
+        // Dropping the `Sender` lets all the sinks' tasks terminate
+        drop(snapshot_sx);
+        for join in sink_joins {
+            if let Err(e) = join.await {
+                error!("Sink task failed: {e}");
+            }
+        }
+    })
+}
@@ -0,0 +1,419 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chrono::Utc;
+use opentelemetry_proto::tonic::collector::metrics::v1::{
+    metrics_service_client::MetricsServiceClient, ExportMetricsServiceRequest,
+};
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue};
+use opentelemetry_proto::tonic::metrics::v1::{
+    metric::Data, number_data_point, AggregationTemporality, Gauge, Histogram, HistogramDataPoint,
+    Metric, NumberDataPoint, ResourceMetrics, ScopeMetrics, Sum,
+};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use prometheus::proto::{MetricFamily, MetricType};
+use prometheus::Registry;
+use prost::Message;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use tokio::time::Duration;
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use tonic::transport::{Channel, ClientTlsConfig};
+
+use super::samples::{lag_samples, Sample};
+
+use crate::internals::{redact, LagSink, LagSinkError, LagSinkFuture, LagSinkResult};
+use crate::lag_register::LagSnapshot;
+
+const HTTP_METRICS_PATH: &str = "/v1/metrics";
+const RESOURCE_SERVICE_NAME: &str = "service.name";
+const RESOURCE_SERVICE_VERSION: &str = "service.version";
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Transport protocol used to reach the OTLP endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OtlpProtocol {
+    /// OTLP/gRPC (usually on port `4317`).
+    Grpc,
+
+    /// OTLP/HTTP, with binary protobuf payload (usually on port `4318`).
+    HttpProtobuf,
+}
+
+/// Configuration of [`OtlpSink`].
+///
+/// When formatted via [`fmt::Debug`], the values of headers holding secrets are redacted.
+#[derive(Clone, PartialEq, Eq)]
+pub struct OtlpConfig {
+    /// Base URL of the OTLP endpoint (e.g. `http://otel-collector:4317`).
+    ///
+    /// When using [`OtlpProtocol::HttpProtobuf`], `/v1/metrics` is appended to it.
+    pub endpoint: String,
+
+    /// Transport protocol to use.
+    pub protocol: OtlpProtocol,
+
+    /// How often to export.
+    pub interval: Duration,
+
+    /// Additional headers (or gRPC metadata) to set on each export (e.g. `Authorization`).
+    pub headers: Vec<(String, String)>,
+}
+
+impl fmt::Debug for OtlpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OtlpConfig")
+            .field("endpoint", &self.endpoint)
+            .field("protocol", &self.protocol)
+            .field("interval", &self.interval)
+            .field(
+                "headers",
+                &self.headers.iter().map(|(k, v)| (k, redact(k, v))).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+enum OtlpClient {
+    Grpc(Box<MetricsServiceClient<Channel>>, MetadataMap),
+    Http(reqwest::Client, String),
+}
+
+/// A [`LagSink`] that exports lag and internal metrics to an
+/// [OpenTelemetry Protocol](https://opentelemetry.io/docs/specs/otlp/) endpoint,
+/// for example an OpenTelemetry Collector.
+///
+/// Metrics are exported with the same names as `/metrics`, and labels (e.g. `group`, `topic`,
+/// `partition`) become data point attributes.
+pub struct OtlpSink {
+    config: OtlpConfig,
+    client: OtlpClient,
+    metrics: Arc<Registry>,
+    start_time_unix_nano: u64,
+}
+
+impl OtlpSink {
+    /// Create a new [`OtlpSink`].
+    ///
+    /// The gRPC connection is established lazily, at the first export:
+    /// this needs to be called within a Tokio runtime.
+    pub fn new(config: OtlpConfig, metrics: Arc<Registry>) -> LagSinkResult<Self> {
+        let client = match config.protocol {
+            OtlpProtocol::Grpc => {
+                let mut metadata = MetadataMap::new();
+                for (k, v) in config.headers.iter() {
+                    metadata.insert(
+                        MetadataKey::from_str(&k.to_lowercase())
+                            .map_err(|e| LagSinkError::Config(e.to_string()))?,
+                        MetadataValue::try_from(v)
+                            .map_err(|e| LagSinkError::Config(e.to_string()))?,
+                    );
+                }
+
+                let mut endpoint = Channel::from_shared(config.endpoint.clone())
+                    .map_err(|e| LagSinkError::Config(e.to_string()))?
+                    .timeout(PUSH_TIMEOUT);
+                if config.endpoint.starts_with("https://") {
+                    endpoint = endpoint
+                        .tls_config(ClientTlsConfig::new().with_native_roots())
+                        .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+                }
+                let channel = endpoint.connect_lazy();
+
+                OtlpClient::Grpc(Box::new(MetricsServiceClient::new(channel)), metadata)
+            },
+            OtlpProtocol::HttpProtobuf => {
+                let mut headers = HeaderMap::new();
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/x-protobuf"));
+                for (k, v) in config.headers.iter() {
+                    headers.insert(
+                        HeaderName::try_from(k).map_err(|e| LagSinkError::Config(e.to_string()))?,
+                        HeaderValue::try_from(v)
+                            .map_err(|e| LagSinkError::Config(e.to_string()))?,
+                    );
+                }
+
+                let client = reqwest::Client::builder()
+                    .default_headers(headers)
+                    .timeout(PUSH_TIMEOUT)
+                    .build()
+                    .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+                let url = format!("{}{HTTP_METRICS_PATH}", config.endpoint.trim_end_matches('/'));
+
+                OtlpClient::Http(client, url)
+            },
+        };
+
+        Ok(Self {
+            config,
+            client,
+            metrics,
+            start_time_unix_nano: ms_to_ns(Utc::now().timestamp_millis()),
+        })
+    }
+}
+
+impl LagSink for OtlpSink {
+    fn name(&self) -> &'static str {
+        "otlp"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let time_unix_nano = ms_to_ns(snapshot.taken_at.timestamp_millis());
+
+            let mut metrics = gauges_from_samples(lag_samples(snapshot));
+            metrics.extend(self.metrics.gather().iter().filter_map(|mf| {
+                metric_from_family(mf, self.start_time_unix_nano, time_unix_nano)
+            }));
+
+            let req = export_request(metrics);
+
+            match &self.client {
+                OtlpClient::Grpc(client, metadata) => {
+                    let mut req = tonic::Request::new(req);
+                    *req.metadata_mut() = metadata.clone();
+
+                    // Cloning the client is cheap, and shares the underlying connection
+                    client
+                        .as_ref()
+                        .clone()
+                        .export(req)
+                        .await
+                        .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+                },
+                OtlpClient::Http(client, url) => {
+                    let res = client
+                        .post(url)
+                        .body(req.encode_to_vec())
+                        .send()
+                        .await
+                        .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+                    if !res.status().is_success() {
+                        return Err(LagSinkError::Delivery(format!(
+                            "'{url}' responded {}",
+                            res.status()
+                        )));
+                    }
+                },
+            }
+
+            Ok(())
+        })
+    }
+}
+
+fn ms_to_ns(ms: i64) -> u64 {
+    (ms.max(0) as u64) * 1_000_000
+}
+
+fn attributes<'a>(labels: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<KeyValue> {
+    labels
+        .into_iter()
+        .map(|(k, v)| KeyValue {
+            key: k.to_string(),
+            value: Some(AnyValue {
+                value: Some(any_value::Value::StringValue(v.to_string())),
+            }),
+        })
+        .collect()
+}
+
+fn export_request(metrics: Vec<Metric>) -> ExportMetricsServiceRequest {
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            resource: Some(Resource {
+                attributes: attributes([
+                    (RESOURCE_SERVICE_NAME, env!("CARGO_PKG_NAME")),
+                    (RESOURCE_SERVICE_VERSION, env!("CARGO_PKG_VERSION")),
+                ]),
+                dropped_attributes_count: 0,
+            }),
+            scope_metrics: vec![ScopeMetrics {
+                scope: Some(InstrumentationScope {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    ..Default::default()
+                }),
+                metrics,
+                schema_url: String::new(),
+            }],
+            schema_url: String::new(),
+        }],
+    }
+}
+
+/// Groups [`Sample`]s by name, each group becoming a [`Gauge`] [`Metric`].
+fn gauges_from_samples(samples: Vec<Sample>) -> Vec<Metric> {
+    let mut by_name: BTreeMap<String, Vec<NumberDataPoint>> = BTreeMap::new();
+
+    for s in samples {
+        by_name.entry(s.name).or_default().push(NumberDataPoint {
+            attributes: attributes(s.labels.iter().map(|(k, v)| (k.as_str(), v.as_str()))),
+            time_unix_nano: ms_to_ns(s.timestamp_ms),
+            value: Some(number_data_point::Value::AsDouble(s.value)),
+            ..Default::default()
+        });
+    }
+
+    by_name
+        .into_iter()
+        .map(|(name, data_points)| Metric {
+            name,
+            data: Some(Data::Gauge(Gauge {
+                data_points,
+            })),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Maps a Prometheus [`MetricFamily`] to the equivalent OTLP [`Metric`]:
+/// counters become monotonic cumulative [`Sum`]s, gauges become [`Gauge`]s and
+/// histograms become cumulative [`Histogram`]s. Other types are not exported.
+fn metric_from_family(
+    mf: &MetricFamily,
+    start_time_unix_nano: u64,
+    time_unix_nano: u64,
+) -> Option<Metric> {
+    let number_data_points = |value: fn(&prometheus::proto::Metric) -> f64| {
+        mf.get_metric()
+            .iter()
+            .map(|m| NumberDataPoint {
+                attributes: attributes(
+                    m.get_label().iter().map(|lp| (lp.get_name(), lp.get_value())),
+                ),
+                start_time_unix_nano,
+                time_unix_nano,
+                value: Some(number_data_point::Value::AsDouble(value(m))),
+                ..Default::default()
+            })
+            .collect::<Vec<NumberDataPoint>>()
+    };
+
+    let data = match mf.get_field_type() {
+        MetricType::COUNTER => Data::Sum(Sum {
+            data_points: number_data_points(|m| m.get_counter().get_value()),
+            aggregation_temporality: AggregationTemporality::Cumulative as i32,
+            is_monotonic: true,
+        }),
+        MetricType::GAUGE => Data::Gauge(Gauge {
+            data_points: number_data_points(|m| m.get_gauge().get_value()),
+        }),
+        MetricType::HISTOGRAM => Data::Histogram(Histogram {
+            data_points: mf
+                .get_metric()
+                .iter()
+                .map(|m| {
+                    let h = m.get_histogram();
+
+                    // Prometheus buckets are cumulative, while OTLP ones are not:
+                    // the last OTLP bucket is the implicit `+Inf` one
+                    let mut bucket_counts = Vec::with_capacity(h.get_bucket().len() + 1);
+                    let mut prev = 0;
+                    for b in h.get_bucket() {
+                        bucket_counts.push(b.get_cumulative_count() - prev);
+                        prev = b.get_cumulative_count();
+                    }
+                    bucket_counts.push(h.get_sample_count() - prev);
+
+                    HistogramDataPoint {
+                        attributes: attributes(
+                            m.get_label().iter().map(|lp| (lp.get_name(), lp.get_value())),
+                        ),
+                        start_time_unix_nano,
+                        time_unix_nano,
+                        count: h.get_sample_count(),
+                        sum: Some(h.get_sample_sum()),
+                        bucket_counts,
+                        explicit_bounds: h
+                            .get_bucket()
+                            .iter()
+                            .map(|b| b.get_upper_bound())
+                            .collect(),
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            aggregation_temporality: AggregationTemporality::Cumulative as i32,
+        }),
+        MetricType::SUMMARY | MetricType::UNTYPED => return None,
+    };
+
+    Some(Metric {
+        name: mf.get_name().to_string(),
+        description: mf.get_help().to_string(),
+        data: Some(data),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use prometheus::{register_histogram_with_registry, register_int_counter_with_registry};
+
+    use super::*;
+
+    #[test]
+    fn gauges_are_grouped_by_name() {
+        let sample = |name: &str, partition: &str| Sample {
+            name: name.to_string(),
+            labels: vec![("partition".to_string(), partition.to_string())],
+            value: 1_f64,
+            timestamp_ms: 1,
+        };
+
+        let metrics = gauges_from_samples(vec![
+            sample("kmtd_b", "0"),
+            sample("kmtd_a", "0"),
+            sample("kmtd_a", "1"),
+        ]);
+
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[0].name, "kmtd_a");
+        match &metrics[0].data {
+            Some(Data::Gauge(g)) => {
+                assert_eq!(g.data_points.len(), 2);
+                assert_eq!(g.data_points[1].attributes[0].key, "partition");
+                assert_eq!(g.data_points[1].time_unix_nano, 1_000_000);
+            },
+            _ => panic!("Expected a gauge"),
+        }
+    }
+
+    #[test]
+    fn registry_metrics_keep_their_type() {
+        let reg = Registry::new();
+        let counter = register_int_counter_with_registry!("counter", "help", reg).unwrap();
+        let histogram = register_histogram_with_registry!(
+            prometheus::histogram_opts!("histogram", "help", vec![1.0, 2.0]),
+            reg
+        )
+        .unwrap();
+        counter.inc();
+        histogram.observe(0.5);
+        histogram.observe(1.5);
+        histogram.observe(5.0);
+
+        let metrics: Vec<Metric> =
+            reg.gather().iter().filter_map(|mf| metric_from_family(mf, 0, 1)).collect();
+
+        match &metrics[0].data {
+            Some(Data::Sum(s)) => assert!(s.is_monotonic),
+            _ => panic!("Expected a sum"),
+        }
+        match &metrics[1].data {
+            Some(Data::Histogram(h)) => {
+                assert_eq!(h.data_points[0].bucket_counts, vec![1, 1, 1]);
+                assert_eq!(h.data_points[0].explicit_bounds, vec![1.0, 2.0]);
+            },
+            _ => panic!("Expected a histogram"),
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use rdkafka::{
+    config::RDKafkaLogLevel,
+    consumer::{BaseConsumer, Consumer},
+    error::KafkaResult,
+    message::{Headers, Message},
+    ClientConfig, Offset, TopicPartitionList,
+};
+use tokio::time::Duration;
+
+use crate::constants::KOMMITTED_RECORD_SIZES_CONSUMER;
+use crate::internals::{LagSink, LagSinkError, LagSinkFuture, LagSinkResult};
+use crate::kafka_types::{Interned, TopicPartition};
+use crate::lag_register::LagSnapshot;
+
+/// How long to wait for the next record, before considering a sampling complete.
+const SAMPLE_POLL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Upper bound to how long a sampling can take.
+const SAMPLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Configuration of [`RecordSizesSink`].
+#[derive(Debug, Clone)]
+pub struct RecordSizesConfig {
+    /// How many of the latest records to sample, for each Topic.
+    pub records: usize,
+
+    /// How often to sample.
+    pub interval: Duration,
+}
+
+/// Average size (bytes) of the records of each Topic, per offset.
+///
+/// Sampled by [`RecordSizesSink`], it converts Offset Lag into an estimated lag in bytes.
+#[derive(Debug, Default)]
+pub struct RecordSizes {
+    bytes_per_offset: RwLock<HashMap<Interned, f64>>,
+}
+
+impl RecordSizes {
+    /// Average size (bytes) of the records of each Topic, per offset, as of the latest sampling.
+    pub fn get_bytes_per_offset(&self) -> HashMap<Interned, f64> {
+        self.bytes_per_offset.read().expect("Record sizes lock poisoned").clone()
+    }
+
+    /// Estimated size (bytes) of `offset_lag` offsets of `topic`, if its records were sampled.
+    pub fn estimate_bytes(
+        bytes_per_offset: &HashMap<Interned, f64>,
+        topic: &str,
+        offset_lag: u64,
+    ) -> Option<u64> {
+        bytes_per_offset.get(topic).map(|bpo| (bpo * offset_lag as f64).round() as u64)
+    }
+
+    fn update(&self, sampled: HashMap<Interned, f64>) {
+        self.bytes_per_offset.write().expect("Record sizes lock poisoned").extend(sampled);
+    }
+}
+
+/// A [`LagSink`] that samples the size of the latest records of the consumed Topics.
+///
+/// For each Topic consumed by at least one Consumer Group, the latest records of its partition
+/// with the most offsets available are fetched: their size (key, payload and headers) divided
+/// by the delta of their offsets gives the average size per offset. Dividing by the offsets delta,
+/// instead of the records count, accounts for gaps (e.g. compaction, transaction markers) the same
+/// way the Offset Lag does.
+///
+/// The results are held in [`RecordSizes`], for other sinks to convert Offset Lag into bytes.
+/// Fetching records requires `READ` access to the Topics.
+pub struct RecordSizesSink {
+    config: RecordSizesConfig,
+    consumer: Arc<BaseConsumer>,
+    record_sizes: Arc<RecordSizes>,
+}
+
+impl RecordSizesSink {
+    /// Create a new [`RecordSizesSink`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration of the sink
+    /// * `client_config` - Kafka client configuration, used to fetch the sampled records
+    pub fn new(config: RecordSizesConfig, client_config: &ClientConfig) -> LagSinkResult<Self> {
+        let mut client_config = client_config.clone();
+        client_config
+            .set("group.id", KOMMITTED_RECORD_SIZES_CONSUMER)
+            .set("enable.auto.commit", "false")
+            .set_log_level(RDKafkaLogLevel::Warning);
+        let consumer: BaseConsumer =
+            client_config.create().map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+        Ok(Self {
+            config,
+            consumer: Arc::new(consumer),
+            record_sizes: Arc::new(RecordSizes::default()),
+        })
+    }
+
+    /// The [`RecordSizes`] sampled by this sink.
+    pub fn record_sizes(&self) -> Arc<RecordSizes> {
+        self.record_sizes.clone()
+    }
+}
+
+/// Range of offsets `[start, end)` to sample, for each consumed Topic of the [`LagSnapshot`].
+///
+/// For each Topic, the partition with the most offsets available is picked.
+fn sample_targets(snapshot: &LagSnapshot, records: usize) -> Vec<(TopicPartition, u64, u64)> {
+    let consumed: BTreeSet<&str> =
+        snapshot.iter_group_partitions().map(|(_, tp, _)| tp.topic.as_ref()).collect();
+
+    let mut widest: HashMap<&str, (&TopicPartition, u64, u64)> = HashMap::new();
+    for ps in snapshot.partitions.iter().filter(|ps| consumed.contains(&*ps.topic_partition.topic))
+    {
+        let (Ok(earliest), Ok(latest)) =
+            (&ps.earliest_available_offset, &ps.latest_available_offset)
+        else {
+            continue;
+        };
+        if latest <= earliest {
+            continue;
+        }
+
+        let entry = widest.entry(&ps.topic_partition.topic).or_insert((
+            &ps.topic_partition,
+            *earliest,
+            *latest,
+        ));
+        if latest - earliest > entry.2 - entry.1 {
+            *entry = (&ps.topic_partition, *earliest, *latest);
+        }
+    }
+
+    widest
+        .into_values()
+        .map(|(tp, earliest, latest)| {
+            (tp.clone(), earliest.max(latest.saturating_sub(records as u64)), latest)
+        })
+        .collect()
+}
+
+/// Bytes sampled from a Topic Partition, and the offsets they span.
+#[derive(Debug, Default)]
+struct Sample {
+    bytes: u64,
+    last_offset: Option<u64>,
+}
+
+impl Sample {
+    /// Average size (bytes) per offset, from `start` to the last offset sampled.
+    fn bytes_per_offset(&self, start: u64) -> Option<f64> {
+        self.last_offset
+            .filter(|last| *last >= start)
+            .map(|last| self.bytes as f64 / (last - start + 1) as f64)
+    }
+}
+
+/// Fetch the records in the given ranges of offsets, and measure their average size per offset.
+///
+/// Ranges that can't be fetched in time are measured for what was fetched of them.
+///
+/// This is blocking: call it via [`tokio::task::spawn_blocking`].
+fn sample_bytes_per_offset(
+    consumer: &BaseConsumer,
+    targets: &[(TopicPartition, u64, u64)],
+) -> KafkaResult<HashMap<Interned, f64>> {
+    let mut tpl = TopicPartitionList::with_capacity(targets.len());
+    for (tp, start, _) in targets {
+        tpl.add_partition_offset(&tp.topic, tp.partition as i32, Offset::Offset(*start as i64))?;
+    }
+    consumer.assign(&tpl)?;
+
+    let mut samples: HashMap<TopicPartition, Sample> =
+        targets.iter().map(|(tp, _, _)| (tp.clone(), Sample::default())).collect();
+    let ends: HashMap<&TopicPartition, u64> =
+        targets.iter().map(|(tp, _, end)| (tp, *end)).collect();
+    let mut pending = targets.len();
+
+    let deadline = Instant::now() + SAMPLE_TIMEOUT;
+    while pending > 0 && Instant::now() < deadline {
+        let m = match consumer.poll(SAMPLE_POLL_TIMEOUT) {
+            Some(Ok(m)) => m,
+            Some(Err(e)) => {
+                debug!("Failed to fetch record to sample: {e}");
+                continue;
+            },
+            None => break,
+        };
+
+        let tp = TopicPartition::new(m.topic(), m.partition() as u32);
+        let (Some(sample), Some(end)) = (samples.get_mut(&tp), ends.get(&tp)) else {
+            continue;
+        };
+        if sample.last_offset.is_some_and(|last| last + 1 >= *end) {
+            continue;
+        }
+
+        let offset = m.offset() as u64;
+        sample.bytes += (m.key_len() + m.payload_len()) as u64;
+        if let Some(headers) = m.headers() {
+            sample.bytes += headers
+                .iter()
+                .map(|h| (h.key.len() + h.value.map_or(0, <[u8]>::len)) as u64)
+                .sum::<u64>();
+        }
+        sample.last_offset = Some(offset);
+        if offset + 1 >= *end {
+            pending -= 1;
+        }
+    }
+    consumer.unassign()?;
+
+    Ok(targets
+        .iter()
+        .filter_map(|(tp, start, _)| {
+            samples[tp].bytes_per_offset(*start).map(|bpo| (tp.topic.clone(), bpo))
+        })
+        .collect())
+}
+
+impl LagSink for RecordSizesSink {
+    fn name(&self) -> &'static str {
+        "record_sizes"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let targets = sample_targets(snapshot, self.config.records);
+            if targets.is_empty() {
+                return Ok(());
+            }
+
+            let consumer = self.consumer.clone();
+            let sampled =
+                tokio::task::spawn_blocking(move || sample_bytes_per_offset(&consumer, &targets))
+                    .await
+                    .map_err(|e| LagSinkError::Delivery(e.to_string()))?
+                    .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+            trace!("Sampled record sizes of {} topics", sampled.len());
+            self.record_sizes.update(sampled);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bytes_per_offset() {
+        // 3 records of 100 bytes, spanning 4 offsets (e.g. one was compacted away)
+        let sample = Sample {
+            bytes: 300,
+            last_offset: Some(13),
+        };
+        assert_eq!(sample.bytes_per_offset(10), Some(75_f64));
+        assert_eq!(Sample::default().bytes_per_offset(10), None);
+
+        let bpo = HashMap::from([(Interned::from("t"), 75_f64)]);
+        assert_eq!(RecordSizes::estimate_bytes(&bpo, "t", 1000), Some(75_000));
+        assert_eq!(RecordSizes::estimate_bytes(&bpo, "other", 1000), None);
+    }
+}
@@ -0,0 +1,79 @@
+use serde::Serialize;
+
+use crate::lag_register::LagSnapshot;
+
+/// Lag of a Consumer Group, for a single Topic Partition, in a flat shape
+/// that is convenient to serialize (e.g. as JSON or CSV).
+///
+/// Values that are not known yet are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LagRecord<'a> {
+    pub cluster_id: &'a str,
+    pub group: &'a str,
+    pub topic: &'a str,
+    pub partition: u32,
+    pub member_id: Option<&'a str>,
+    pub member_host: Option<&'a str>,
+    pub member_client_id: Option<&'a str>,
+    pub offset: Option<u64>,
+    pub offset_timestamp_ms: Option<i64>,
+    pub offset_lag: Option<u64>,
+    pub time_lag_ms: Option<i64>,
+    pub taken_at_ms: i64,
+}
+
+/// Converts a [`LagSnapshot`] into [`LagRecord`]s, one per Consumer Group and Topic Partition.
+pub fn lag_records(snapshot: &LagSnapshot) -> Vec<LagRecord<'_>> {
+    let taken_at_ms = snapshot.taken_at.timestamp_millis();
+
+    snapshot
+        .iter_group_partitions()
+        .map(|(group, tp, lwo)| {
+            let lag = lwo.lag();
+            let owner = lwo.owner();
+
+            LagRecord {
+                cluster_id: &snapshot.cluster_id,
+                group,
+                topic: &tp.topic,
+                partition: tp.partition,
+                member_id: owner.map(|m| m.id.as_str()),
+                member_host: owner.map(|m| m.client_host.as_str()),
+                member_client_id: owner.map(|m| m.client_id.as_str()),
+                offset: lag.map(|l| l.offset()),
+                offset_timestamp_ms: lag.map(|l| l.offset_timestamp().timestamp_millis()),
+                offset_lag: lag.map(|l| l.offset_lag()),
+                time_lag_ms: lag.map(|l| l.time_lag().num_milliseconds()),
+                taken_at_ms,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_unknowns_are_null() {
+        let record = LagRecord {
+            cluster_id: "c",
+            group: "g",
+            topic: "t",
+            partition: 1,
+            member_id: None,
+            member_host: None,
+            member_client_id: None,
+            offset: None,
+            offset_timestamp_ms: None,
+            offset_lag: None,
+            time_lag_ms: None,
+            taken_at_ms: 1000,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"cluster_id":"c","group":"g","topic":"t","partition":1,"member_id":null,"member_host":null,"member_client_id":null,"offset":null,"offset_timestamp_ms":null,"offset_lag":null,"time_lag_ms":null,"taken_at_ms":1000}"#
+        );
+    }
+}
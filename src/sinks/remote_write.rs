@@ -0,0 +1,267 @@
+use std::fmt;
+use std::sync::Arc;
+
+use prometheus::Registry;
+use prost::Message;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_TYPE};
+use tokio::time::Duration;
+
+use super::samples::{lag_samples, registry_samples, Sample};
+
+use crate::internals::{redact, LagSink, LagSinkError, LagSinkFuture, LagSinkResult};
+use crate::lag_register::LagSnapshot;
+
+const LABEL_NAME: &str = "__name__";
+const REMOTE_WRITE_VERSION_HEADER: &str = "x-prometheus-remote-write-version";
+const REMOTE_WRITE_VERSION: &str = "0.1.0";
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Configuration of [`RemoteWriteSink`].
+///
+/// When formatted via [`fmt::Debug`], the values of headers holding secrets are redacted.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RemoteWriteConfig {
+    /// URL of the Prometheus remote-write endpoint (e.g. `http://prometheus:9090/api/v1/write`).
+    pub url: String,
+
+    /// How often to push.
+    pub interval: Duration,
+
+    /// Additional HTTP headers to set on each push (e.g. `Authorization`).
+    pub headers: Vec<(String, String)>,
+}
+
+impl fmt::Debug for RemoteWriteConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteWriteConfig")
+            .field("url", &self.url)
+            .field("interval", &self.interval)
+            .field(
+                "headers",
+                &self.headers.iter().map(|(k, v)| (k, redact(k, v))).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// A [`LagSink`] that pushes lag and internal metrics to a
+/// [Prometheus remote-write](https://prometheus.io/docs/concepts/remote_write_spec/) endpoint.
+///
+/// Useful where scraping `/metrics` is not an option.
+pub struct RemoteWriteSink {
+    config: RemoteWriteConfig,
+    client: reqwest::Client,
+    metrics: Arc<Registry>,
+}
+
+impl RemoteWriteSink {
+    pub fn new(config: RemoteWriteConfig, metrics: Arc<Registry>) -> LagSinkResult<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/x-protobuf"));
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static("snappy"));
+        headers.insert(REMOTE_WRITE_VERSION_HEADER, HeaderValue::from_static(REMOTE_WRITE_VERSION));
+        for (k, v) in config.headers.iter() {
+            headers.insert(
+                HeaderName::try_from(k).map_err(|e| LagSinkError::Config(e.to_string()))?,
+                HeaderValue::try_from(v).map_err(|e| LagSinkError::Config(e.to_string()))?,
+            );
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(PUSH_TIMEOUT)
+            .build()
+            .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+        Ok(Self {
+            config,
+            client,
+            metrics,
+        })
+    }
+}
+
+impl LagSink for RemoteWriteSink {
+    fn name(&self) -> &'static str {
+        "remote_write"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let mut samples = lag_samples(snapshot);
+            samples.extend(registry_samples(
+                &self.metrics.gather(),
+                snapshot.taken_at.timestamp_millis(),
+            ));
+
+            let body = encode(samples)?;
+
+            let res = self
+                .client
+                .post(&self.config.url)
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+            if !res.status().is_success() {
+                return Err(LagSinkError::Delivery(format!(
+                    "'{}' responded {}",
+                    self.config.url,
+                    res.status()
+                )));
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Encodes [`Sample`]s as a remote-write `WriteRequest`: protobuf, then snappy (block format).
+fn encode(samples: Vec<Sample>) -> LagSinkResult<Vec<u8>> {
+    let req = WriteRequest {
+        timeseries: samples.into_iter().map(TimeSeries::from).collect(),
+    };
+
+    snap::raw::Encoder::new()
+        .compress_vec(&req.encode_to_vec())
+        .map_err(|e| LagSinkError::Encode(e.to_string()))
+}
+
+// Subset of the remote-write protobuf definitions.
+// See: https://github.com/prometheus/prometheus/blob/main/prompb/remote.proto
+
+#[derive(Clone, PartialEq, Message)]
+struct WriteRequest {
+    #[prost(message, repeated, tag = "1")]
+    timeseries: Vec<TimeSeries>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TimeSeries {
+    #[prost(message, repeated, tag = "1")]
+    labels: Vec<Label>,
+    #[prost(message, repeated, tag = "2")]
+    samples: Vec<RemoteSample>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Label {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(string, tag = "2")]
+    value: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RemoteSample {
+    #[prost(double, tag = "1")]
+    value: f64,
+    #[prost(int64, tag = "2")]
+    timestamp: i64,
+}
+
+impl From<Sample> for TimeSeries {
+    fn from(s: Sample) -> Self {
+        let mut labels: Vec<Label> = std::iter::once((LABEL_NAME.to_string(), s.name))
+            .chain(s.labels)
+            .map(|(name, value)| Label {
+                name,
+                value,
+            })
+            .collect();
+
+        // The spec requires labels to be sorted by name
+        labels.sort_by(|a, b| a.name.cmp(&b.name));
+
+        TimeSeries {
+            labels,
+            samples: vec![RemoteSample {
+                value: s.value,
+                timestamp: s.timestamp_ms,
+            }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_secret_headers() {
+        let config = RemoteWriteConfig {
+            url: "http://mimir:9009/api/v1/push".to_string(),
+            interval: Duration::from_secs(10),
+            headers: vec![
+                ("Authorization".to_string(), "Bearer s3cr3t".to_string()),
+                ("X-Scope-OrgID".to_string(), "tenant-1".to_string()),
+            ],
+        };
+
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("s3cr3t"));
+        assert!(debug.contains("tenant-1"));
+    }
+
+    #[test]
+    fn time_series_labels_are_sorted_and_named() {
+        let ts = TimeSeries::from(Sample {
+            name: "kmtd_metric".to_string(),
+            labels: vec![
+                ("topic".to_string(), "t".to_string()),
+                ("group".to_string(), "g".to_string()),
+            ],
+            value: 42_f64,
+            timestamp_ms: 1000,
+        });
+
+        let names: Vec<&str> = ts.labels.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["__name__", "group", "topic"]);
+        assert_eq!(ts.labels[0].value, "kmtd_metric");
+        assert_eq!(
+            ts.samples,
+            vec![RemoteSample {
+                value: 42_f64,
+                timestamp: 1000
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_headers_are_a_config_error() {
+        let config = |name: &str, value: &str| RemoteWriteConfig {
+            url: "http://localhost:9090/api/v1/write".to_string(),
+            interval: Duration::from_secs(10),
+            headers: vec![(name.to_string(), value.to_string())],
+        };
+        let metrics = Arc::new(Registry::new());
+
+        for (name, value) in [("Bad Name", "v"), ("X-Good-Name", "bad\nvalue")] {
+            let res = RemoteWriteSink::new(config(name, value), metrics.clone());
+            assert!(matches!(res, Err(LagSinkError::Config(_))));
+        }
+        assert!(RemoteWriteSink::new(config("X-Good-Name", "v"), metrics).is_ok());
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        let samples = vec![Sample {
+            name: "kmtd_metric".to_string(),
+            labels: vec![],
+            value: 1_f64,
+            timestamp_ms: 1,
+        }];
+
+        let encoded = encode(samples).unwrap();
+        let decompressed = snap::raw::Decoder::new().decompress_vec(&encoded).unwrap();
+        let decoded = WriteRequest::decode(decompressed.as_slice()).unwrap();
+
+        assert_eq!(decoded.timeseries.len(), 1);
+        assert_eq!(decoded.timeseries[0].samples[0].value, 1_f64);
+    }
+}
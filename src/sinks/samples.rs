@@ -0,0 +1,294 @@
+use prometheus::proto::{MetricFamily, MetricType};
+
+use crate::kafka_types::{Group, Member};
+use crate::lag_register::{Lag, LagSnapshot};
+use crate::prometheus_metrics::bespoke::*;
+use crate::prometheus_metrics::{
+    LABEL_CLUSTER_ID, LABEL_GROUP, LABEL_GROUP_INSTANCE_ID, LABEL_MEMBER_CLIENT_ID,
+    LABEL_MEMBER_HOST, LABEL_MEMBER_ID, LABEL_PARTITION, LABEL_TOPIC, UNKNOWN_VAL,
+};
+
+const LABEL_LE: &str = "le";
+
+/// A single data point, in a format-agnostic shape.
+///
+/// Push-based [`crate::internals::LagSink`]s convert these into their own wire format,
+/// so that the naming and labelling of what is published stays consistent with `/metrics`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+    pub value: f64,
+    pub timestamp_ms: i64,
+}
+
+impl Sample {
+    fn new(name: &str, labels: Vec<(&str, String)>, value: f64, timestamp_ms: i64) -> Self {
+        Self {
+            name: name.to_string(),
+            labels: labels.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+            value,
+            timestamp_ms,
+        }
+    }
+}
+
+/// Converts a [`LagSnapshot`] into [`Sample`]s, one per each metric rendered at `/metrics`.
+///
+/// Each is timestamped with the time the snapshot was taken, instead of the time the value
+/// refers to (e.g. when the offset was committed) like `/metrics` does: receivers of pushed samples
+/// reject those older than their out-of-order window, as those of idle Consumer Groups would be.
+pub fn lag_samples(snapshot: &LagSnapshot) -> Vec<Sample> {
+    let taken_at_ms = snapshot.taken_at.timestamp_millis();
+    let mut res =
+        Vec::with_capacity(snapshot.group_partitions_count() * 3 + snapshot.partitions.len() * 4);
+
+    for gwl in snapshot.groups.values() {
+        for (tp, lwo) in gwl.lag_by_topic_partition.iter() {
+            let labels = consumer_labels(
+                &snapshot.cluster_id,
+                &gwl.group,
+                &tp.topic,
+                tp.partition,
+                lwo.owner.as_ref(),
+            );
+            let value_or_unknown = |f: fn(&Lag) -> f64| lwo.lag.as_ref().map(f).unwrap_or(-1_f64);
+
+            res.push(Sample::new(
+                consumer_partition_offset::NAME,
+                labels.clone(),
+                value_or_unknown(|l| l.offset as f64),
+                taken_at_ms,
+            ));
+            res.push(Sample::new(
+                consumer_partition_lag_offset::NAME,
+                labels.clone(),
+                value_or_unknown(|l| l.offset_lag as f64),
+                taken_at_ms,
+            ));
+            res.push(Sample::new(
+                consumer_partition_lag_milliseconds::NAME,
+                labels,
+                value_or_unknown(|l| l.time_lag.num_milliseconds() as f64),
+                taken_at_ms,
+            ));
+        }
+    }
+
+    for ps in snapshot.partitions.iter() {
+        let labels = vec![
+            (LABEL_CLUSTER_ID, snapshot.cluster_id.clone()),
+            (LABEL_TOPIC, ps.topic_partition.topic.to_string()),
+            (LABEL_PARTITION, ps.topic_partition.partition.to_string()),
+        ];
+
+        if let Ok(eao) = ps.earliest_available_offset {
+            res.push(Sample::new(
+                partition_earliest_available_offset::NAME,
+                labels.clone(),
+                eao as f64,
+                taken_at_ms,
+            ));
+        }
+        if let Ok(lao) = ps.latest_available_offset {
+            res.push(Sample::new(
+                partition_latest_available_offset::NAME,
+                labels.clone(),
+                lao as f64,
+                taken_at_ms,
+            ));
+        }
+        if let Ok(eto) = &ps.earliest_tracked_offset {
+            res.push(Sample::new(
+                partition_earliest_tracked_offset::NAME,
+                labels.clone(),
+                eto.offset as f64,
+                taken_at_ms,
+            ));
+        }
+        if let Ok(lto) = &ps.latest_tracked_offset {
+            res.push(Sample::new(
+                partition_latest_tracked_offset::NAME,
+                labels.clone(),
+                lto.offset as f64,
+                taken_at_ms,
+            ));
+        }
+        if let Ok(rate) = &ps.produced_records_rate {
+            res.push(Sample::new(
+                partition_produced_records_rate::NAME,
+                labels,
+                *rate,
+                taken_at_ms,
+            ));
+        }
+    }
+
+    res
+}
+
+fn consumer_labels<'a>(
+    cluster_id: &str,
+    group: &'a Group,
+    topic: &str,
+    partition: u32,
+    owner: Option<&Member>,
+) -> Vec<(&'a str, String)> {
+    let (member_id, member_host, member_client_id, group_instance_id) = match owner {
+        Some(o) => (
+            o.stable_id().to_string(),
+            o.client_host.clone(),
+            o.client_id.clone(),
+            o.group_instance_id.clone().unwrap_or_default(),
+        ),
+        None => (
+            UNKNOWN_VAL.to_string(),
+            UNKNOWN_VAL.to_string(),
+            UNKNOWN_VAL.to_string(),
+            UNKNOWN_VAL.to_string(),
+        ),
+    };
+
+    let mut labels = vec![
+        (LABEL_CLUSTER_ID, cluster_id.to_string()),
+        (LABEL_GROUP, group.name.to_string()),
+        (LABEL_TOPIC, topic.to_string()),
+        (LABEL_PARTITION, partition.to_string()),
+        (LABEL_MEMBER_ID, member_id),
+        (LABEL_MEMBER_HOST, member_host),
+        (LABEL_MEMBER_CLIENT_ID, member_client_id),
+        (LABEL_GROUP_INSTANCE_ID, group_instance_id),
+    ];
+    labels.extend(group.labels.iter().map(|(k, v)| (k.as_str(), v.clone())));
+
+    labels
+}
+
+/// Converts the [`MetricFamily`]s gathered from a [`prometheus::Registry`] into [`Sample`]s.
+///
+/// Histograms are flattened in the same way the Prometheus text format does:
+/// `_bucket` (with `le` label), `_sum` and `_count`.
+///
+/// # Arguments
+///
+/// * `families` - The gathered [`MetricFamily`]s
+/// * `timestamp_ms` - Timestamp assigned to all the returned [`Sample`]s
+pub fn registry_samples(families: &[MetricFamily], timestamp_ms: i64) -> Vec<Sample> {
+    let mut res = Vec::new();
+
+    for mf in families {
+        let name = mf.get_name();
+
+        for m in mf.get_metric() {
+            let labels: Vec<(String, String)> = m
+                .get_label()
+                .iter()
+                .map(|lp| (lp.get_name().to_string(), lp.get_value().to_string()))
+                .collect();
+            let sample = |name: String, labels: Vec<(String, String)>, value: f64| Sample {
+                name,
+                labels,
+                value,
+                timestamp_ms,
+            };
+
+            match mf.get_field_type() {
+                MetricType::COUNTER => {
+                    res.push(sample(name.to_string(), labels, m.get_counter().get_value()));
+                },
+                MetricType::GAUGE => {
+                    res.push(sample(name.to_string(), labels, m.get_gauge().get_value()));
+                },
+                MetricType::UNTYPED => {
+                    res.push(sample(name.to_string(), labels, m.get_untyped().get_value()));
+                },
+                MetricType::HISTOGRAM => {
+                    let h = m.get_histogram();
+                    for b in h.get_bucket() {
+                        let mut bucket_labels = labels.clone();
+                        bucket_labels.push((LABEL_LE.to_string(), b.get_upper_bound().to_string()));
+                        res.push(sample(
+                            format!("{name}_bucket"),
+                            bucket_labels,
+                            b.get_cumulative_count() as f64,
+                        ));
+                    }
+                    let mut inf_labels = labels.clone();
+                    inf_labels.push((LABEL_LE.to_string(), "+Inf".to_string()));
+                    res.push(sample(
+                        format!("{name}_bucket"),
+                        inf_labels,
+                        h.get_sample_count() as f64,
+                    ));
+                    res.push(sample(format!("{name}_sum"), labels.clone(), h.get_sample_sum()));
+                    res.push(sample(format!("{name}_count"), labels, h.get_sample_count() as f64));
+                },
+                MetricType::SUMMARY => {
+                    let s = m.get_summary();
+                    res.push(sample(format!("{name}_sum"), labels.clone(), s.get_sample_sum()));
+                    res.push(sample(format!("{name}_count"), labels, s.get_sample_count() as f64));
+                },
+            }
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::kafka_types::TopicPartition;
+    use crate::lag_register::{GroupWithLag, LagWithOwner};
+    use crate::partition_offsets::{
+        PartitionOffsetsError, PartitionOffsetsSnapshot, TrackedOffset,
+    };
+
+    #[test]
+    fn samples_are_timestamped_when_taken() {
+        let taken_at = Utc::now();
+        let committed_at = taken_at - Duration::days(1);
+        let tp = TopicPartition::new("t".to_string(), 0);
+
+        // A Consumer Group that last committed a day ago
+        let mut gwl = GroupWithLag::default();
+        gwl.lag_by_topic_partition.insert(
+            tp.clone(),
+            LagWithOwner {
+                lag: Some(Lag {
+                    offset: 10,
+                    offset_timestamp: committed_at,
+                    ..Default::default()
+                }),
+                owner: None,
+            },
+        );
+        let mut snapshot = LagSnapshot {
+            taken_at,
+            ..Default::default()
+        };
+        snapshot.groups.insert("g".into(), Arc::new(gwl));
+        snapshot.partitions = vec![PartitionOffsetsSnapshot {
+            topic_partition: tp,
+            earliest_available_offset: Ok(0),
+            latest_available_offset: Ok(10),
+            earliest_tracked_offset: Ok(TrackedOffset {
+                offset: 10,
+                at: committed_at,
+            }),
+            latest_tracked_offset: Ok(TrackedOffset {
+                offset: 10,
+                at: committed_at,
+            }),
+            produced_records_rate: Err(PartitionOffsetsError::LagEstimatorNotReady),
+        }];
+
+        let samples = lag_samples(&snapshot);
+        assert_eq!(samples.len(), 7);
+        assert!(samples.iter().all(|s| s.timestamp_ms == taken_at.timestamp_millis()));
+    }
+}
@@ -0,0 +1,237 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use tokio::net::{lookup_host, UdpSocket};
+use tokio::time::Duration;
+
+use super::samples::{lag_samples, Sample};
+
+use crate::internals::{LagSink, LagSinkError, LagSinkFuture};
+use crate::lag_register::LagSnapshot;
+use crate::prometheus_metrics::{LABEL_CLUSTER_ID, LABEL_GROUP, LABEL_PARTITION, LABEL_TOPIC};
+
+/// Max size of a single UDP datagram, as recommended by Datadog to avoid fragmentation.
+const MAX_DATAGRAM_SIZE: usize = 1432;
+
+/// Labels whose values are appended to the metric name, in plain StatsD.
+const NAME_LABELS: [&str; 4] = [LABEL_CLUSTER_ID, LABEL_GROUP, LABEL_TOPIC, LABEL_PARTITION];
+
+/// Dialect of the StatsD protocol to emit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatsdFlavor {
+    /// Plain StatsD: as tags are not supported, the values of the cluster, group, topic and
+    /// partition labels are appended to the metric name
+    /// (e.g. `kmtd_consumer_partition_lag_offset.my-cluster.my-group.my-topic.0`).
+    ///
+    /// Other labels (e.g. the member ones) are left out, as they would create a new series
+    /// at every rebalance.
+    Statsd,
+
+    /// DogStatsD: labels are emitted as tags (e.g. `|#group:my-group,topic:my-topic`).
+    Dogstatsd,
+}
+
+/// Configuration of [`StatsdSink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsdConfig {
+    /// Address of the StatsD server / Datadog Agent (e.g. `localhost:8125`).
+    pub address: String,
+
+    /// Dialect of the StatsD protocol to emit.
+    pub flavor: StatsdFlavor,
+
+    /// How often to emit.
+    pub interval: Duration,
+
+    /// Additional tags to set on each metric (DogStatsD only, e.g. `env:prod`).
+    pub tags: Vec<(String, String)>,
+}
+
+/// A [`LagSink`] that emits lag metrics as gauges, via StatsD or DogStatsD over UDP.
+///
+/// Metrics are emitted with the same names as `/metrics`.
+pub struct StatsdSink {
+    config: StatsdConfig,
+}
+
+impl StatsdSink {
+    pub fn new(config: StatsdConfig) -> Self {
+        Self {
+            config,
+        }
+    }
+}
+
+impl LagSink for StatsdSink {
+    fn name(&self) -> &'static str {
+        "statsd"
+    }
+
+    fn publish_interval(&self) -> Option<Duration> {
+        Some(self.config.interval)
+    }
+
+    fn publish<'a>(&'a self, snapshot: &'a LagSnapshot) -> LagSinkFuture<'a> {
+        Box::pin(async move {
+            let lines = format_lines(&lag_samples(snapshot), self.config.flavor, &self.config.tags);
+
+            // Resolved at every publishing, so DNS changes are picked up
+            let target = lookup_host(&self.config.address)
+                .await
+                .map_err(|e| LagSinkError::Delivery(e.to_string()))?
+                .next()
+                .ok_or_else(|| {
+                    LagSinkError::Delivery(format!("'{}' did not resolve", self.config.address))
+                })?;
+            let bind_addr: SocketAddr = if target.is_ipv4() {
+                (Ipv4Addr::UNSPECIFIED, 0).into()
+            } else {
+                (Ipv6Addr::UNSPECIFIED, 0).into()
+            };
+            let socket = UdpSocket::bind(bind_addr)
+                .await
+                .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+
+            for datagram in into_datagrams(lines) {
+                socket
+                    .send_to(datagram.as_bytes(), target)
+                    .await
+                    .map_err(|e| LagSinkError::Delivery(e.to_string()))?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Replaces the characters that are reserved by the (Dog)StatsD protocol.
+fn sanitize(s: &str) -> String {
+    s.replace([':', '|', '@', '#', ',', '\n'], "_")
+}
+
+/// Formats [`Sample`]s as (Dog)StatsD gauge lines, skipping those whose value is unknown.
+///
+/// Unknown values are `-1` (see [`lag_samples`]), but plain StatsD reads a signed gauge value
+/// as a change relative to the current one: sending it would make the gauge drift.
+fn format_lines(
+    samples: &[Sample],
+    flavor: StatsdFlavor,
+    tags: &[(String, String)],
+) -> Vec<String> {
+    samples.iter().filter(|s| s.value >= 0_f64).map(|s| format_line(s, flavor, tags)).collect()
+}
+
+/// Formats a [`Sample`] as a single (Dog)StatsD gauge line.
+fn format_line(sample: &Sample, flavor: StatsdFlavor, tags: &[(String, String)]) -> String {
+    match flavor {
+        StatsdFlavor::Statsd => {
+            let mut name = sanitize(&sample.name);
+            for (_, v) in sample.labels.iter().filter(|(k, _)| NAME_LABELS.contains(&k.as_str())) {
+                name.push('.');
+                name.push_str(&sanitize(&v.replace('.', "_")));
+            }
+            format!("{name}:{}|g", sample.value)
+        },
+        StatsdFlavor::Dogstatsd => {
+            let tags = tags
+                .iter()
+                .chain(sample.labels.iter())
+                .map(|(k, v)| format!("{}:{}", sanitize(k), v.replace(['|', ',', '\n'], "_")))
+                .collect::<Vec<String>>()
+                .join(",");
+            format!("{}:{}|g|#{tags}", sanitize(&sample.name), sample.value)
+        },
+    }
+}
+
+/// Packs lines into newline-separated datagrams, each no larger than [`MAX_DATAGRAM_SIZE`]
+/// (unless a single line is larger than that).
+fn into_datagrams(lines: Vec<String>) -> Vec<String> {
+    let mut datagrams = Vec::new();
+    let mut current = String::with_capacity(MAX_DATAGRAM_SIZE);
+
+    for line in lines {
+        if !current.is_empty() && current.len() + 1 + line.len() > MAX_DATAGRAM_SIZE {
+            datagrams
+                .push(std::mem::replace(&mut current, String::with_capacity(MAX_DATAGRAM_SIZE)));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        datagrams.push(current);
+    }
+
+    datagrams
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> Sample {
+        Sample {
+            name: "kmtd_consumer_partition_lag_offset".to_string(),
+            labels: vec![
+                ("group".to_string(), "my-group".to_string()),
+                ("topic".to_string(), "my.topic".to_string()),
+                ("partition".to_string(), "0".to_string()),
+                ("member_id".to_string(), "consumer-1-abc".to_string()),
+            ],
+            value: 42_f64,
+            timestamp_ms: 1,
+        }
+    }
+
+    #[test]
+    fn format_statsd() {
+        assert_eq!(
+            format_line(&sample(), StatsdFlavor::Statsd, &[]),
+            "kmtd_consumer_partition_lag_offset.my-group.my_topic.0:42|g"
+        );
+    }
+
+    #[test]
+    fn format_dogstatsd() {
+        assert_eq!(
+            format_line(
+                &sample(),
+                StatsdFlavor::Dogstatsd,
+                &[("env".to_string(), "prod".to_string())]
+            ),
+            "kmtd_consumer_partition_lag_offset:42|g|#env:prod,group:my-group,topic:my.topic,partition:0,member_id:consumer-1-abc"
+        );
+    }
+
+    #[test]
+    fn unknown_values_are_skipped() {
+        let unknown = Sample {
+            value: -1_f64,
+            ..sample()
+        };
+        let samples = vec![sample(), unknown];
+
+        for flavor in [StatsdFlavor::Statsd, StatsdFlavor::Dogstatsd] {
+            let lines = format_lines(&samples, flavor, &[]);
+            assert_eq!(lines.len(), 1);
+
+            // A signed value would be read as a relative change of the gauge
+            for line in lines {
+                let value = line.split_once(':').unwrap().1;
+                assert!(!value.starts_with(['-', '+']), "'{line}' has a signed value");
+            }
+        }
+    }
+
+    #[test]
+    fn datagrams_are_bounded() {
+        let line = "x".repeat(500);
+        let datagrams = into_datagrams(vec![line.clone(); 5]);
+
+        assert_eq!(datagrams.len(), 3);
+        assert_eq!(datagrams[0], format!("{line}\n{line}"));
+        assert_eq!(datagrams[2], line);
+        assert!(datagrams.iter().all(|d| d.len() <= MAX_DATAGRAM_SIZE));
+    }
+}
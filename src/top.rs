@@ -0,0 +1,392 @@
+//! Continuously refreshed terminal view of the lag: the `top` subcommand.
+//!
+//! Every refresh, a [`LagSnapshot`] is taken out of the Registers, and rendered as a [`LagTable`]:
+//! sorted, filtered and truncated to fit the terminal. Keys (no need to press Enter):
+//!
+//! * `s` = sort by the next column
+//! * `r` = reverse the sort order
+//! * `/` = filter Consumer Groups and Topics, by substring (Enter to confirm, Esc to clear)
+//! * `c` = clear the filter
+//! * `q` = quit
+
+use std::io::{self, Read, Write};
+use std::mem::MaybeUninit;
+
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::lag_register::LagSnapshot;
+use crate::lag_table::{LagTable, LagTableRow};
+use crate::KommittedHandle;
+
+/// Lines of the view that are not table rows: title, status, blank, table header, footer.
+const CHROME_LINES: usize = 6;
+
+const KEYS_HELP: &str = "[s] sort  [r] reverse  [/] filter  [c] clear filter  [q] quit";
+
+const ANSI_ALT_SCREEN_ENTER: &str = "\x1b[?1049h\x1b[?25l";
+const ANSI_ALT_SCREEN_LEAVE: &str = "\x1b[?25h\x1b[?1049l";
+const ANSI_CLEAR: &str = "\x1b[H\x1b[2J";
+
+const KEY_ESC: u8 = 0x1b;
+const KEY_BACKSPACE: u8 = 0x7f;
+const KEY_CTRL_H: u8 = 0x08;
+
+/// Column the rows of the view are sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TopSort {
+    Group,
+    Topic,
+    Lag,
+    TimeLag,
+}
+
+impl TopSort {
+    fn next(self) -> Self {
+        match self {
+            TopSort::Group => TopSort::Topic,
+            TopSort::Topic => TopSort::Lag,
+            TopSort::Lag => TopSort::TimeLag,
+            TopSort::TimeLag => TopSort::Group,
+        }
+    }
+
+    /// Lag is sorted highest first, names alphabetically.
+    fn descending_by_default(self) -> bool {
+        matches!(self, TopSort::Lag | TopSort::TimeLag)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            TopSort::Group => "group",
+            TopSort::Topic => "topic",
+            TopSort::Lag => "lag",
+            TopSort::TimeLag => "time-lag",
+        }
+    }
+}
+
+/// What a key press did to the [`TopView`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOutcome {
+    /// The view changed: it has to be drawn again.
+    Redraw,
+
+    /// The key is not bound to anything.
+    Ignored,
+
+    /// Time to quit.
+    Quit,
+}
+
+/// State of the view: how rows are sorted and filtered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopView {
+    sort: TopSort,
+    descending: bool,
+    filter: String,
+    editing_filter: bool,
+}
+
+impl TopView {
+    pub fn new(sort: TopSort) -> Self {
+        Self {
+            sort,
+            descending: sort.descending_by_default(),
+            filter: String::new(),
+            editing_filter: false,
+        }
+    }
+
+    /// Apply a key press: see the [module](self) documentation.
+    ///
+    /// While editing the filter, it applies as it's typed.
+    pub fn handle_key(&mut self, key: u8) -> KeyOutcome {
+        if self.editing_filter {
+            match key {
+                b'\r' | b'\n' => self.editing_filter = false,
+                KEY_ESC => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                },
+                KEY_BACKSPACE | KEY_CTRL_H => {
+                    self.filter.pop();
+                },
+                k if k.is_ascii_graphic() || k == b' ' => self.filter.push(k as char),
+                _ => return KeyOutcome::Ignored,
+            }
+            return KeyOutcome::Redraw;
+        }
+
+        match key {
+            b'q' | b'Q' => return KeyOutcome::Quit,
+            b's' => {
+                self.sort = self.sort.next();
+                self.descending = self.sort.descending_by_default();
+            },
+            b'r' => self.descending = !self.descending,
+            b'/' => {
+                self.filter.clear();
+                self.editing_filter = true;
+            },
+            b'c' => self.filter.clear(),
+            _ => return KeyOutcome::Ignored,
+        }
+        KeyOutcome::Redraw
+    }
+
+    /// Filter and sort the rows of the given [`LagTable`].
+    ///
+    /// Ties are broken by Consumer Group then Topic, so that rows don't jump around across refreshes.
+    pub fn apply(&self, mut table: LagTable) -> LagTable {
+        let filter = self.filter.to_lowercase();
+        if !filter.is_empty() {
+            table.rows.retain(|r| {
+                r.group.to_lowercase().contains(&filter) || r.topic.to_lowercase().contains(&filter)
+            });
+        }
+
+        let sort = self.sort;
+        let descending = self.descending;
+        table.rows.sort_by(|a, b| {
+            let ord = sort_key(sort, a).cmp(&sort_key(sort, b));
+            let ord = if descending {
+                ord.reverse()
+            } else {
+                ord
+            };
+            ord.then_with(|| (&a.group, &a.topic).cmp(&(&b.group, &b.topic)))
+        });
+        table
+    }
+
+    /// Render the view of the given [`LagSnapshot`], showing at most `max_rows` rows.
+    pub fn render(&self, snapshot: &LagSnapshot, max_rows: usize, colored: bool) -> String {
+        let mut table = self.apply(LagTable::from_snapshot(snapshot));
+        let total = table.rows.len();
+        table.rows.truncate(max_rows);
+
+        let mut out = format!(
+            "kommitted top - cluster '{}' - {} - {} groups\n",
+            snapshot.cluster_id,
+            snapshot.taken_at.format("%H:%M:%S"),
+            snapshot.groups.len()
+        );
+        out.push_str(&format!(
+            "Sort: {} ({})  Filter: {}{}\n\n",
+            self.sort.name(),
+            if self.descending {
+                "desc"
+            } else {
+                "asc"
+            },
+            if self.filter.is_empty() && !self.editing_filter {
+                "-"
+            } else {
+                &self.filter
+            },
+            if self.editing_filter {
+                "_"
+            } else {
+                ""
+            }
+        ));
+        out.push_str(&table.render_table(colored));
+        if total > table.rows.len() {
+            out.push_str(&format!("... and {} more\n", total - table.rows.len()));
+        }
+        out.push_str(KEYS_HELP);
+        out
+    }
+}
+
+fn sort_key(sort: TopSort, row: &LagTableRow) -> (i128, &str) {
+    match sort {
+        TopSort::Group => (0, &row.group),
+        TopSort::Topic => (0, &row.topic),
+        TopSort::Lag => (row.offset_lag as i128, ""),
+        TopSort::TimeLag => (row.max_time_lag_ms as i128, ""),
+    }
+}
+
+/// Terminal in "raw" mode: key presses are read as they happen, and not echoed.
+///
+/// It draws on the alternate screen, and restores the terminal when dropped.
+/// Signals (e.g. Ctrl-C) are still delivered, so that the shutdown is handled as usual.
+struct Terminal {
+    original: Option<libc::termios>,
+}
+
+impl Terminal {
+    fn enter() -> Self {
+        let mut original = MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: `tcgetattr` initializes `original` when it succeeds, and it's only read if so
+        let original = unsafe {
+            (libc::tcgetattr(libc::STDIN_FILENO, original.as_mut_ptr()) == 0)
+                .then(|| original.assume_init())
+        };
+
+        if let Some(orig) = original {
+            let mut raw = orig;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            // SAFETY: `raw` is a valid `termios`, derived from the current one
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+                warn!("Failed to read keys: {}", io::Error::last_os_error());
+            }
+        } else {
+            warn!("Standard input is not a terminal: keys are disabled");
+        }
+
+        print!("{ANSI_ALT_SCREEN_ENTER}");
+        Self {
+            original,
+        }
+    }
+
+    /// Rows of the terminal: `24` if it can't be determined.
+    fn rows() -> usize {
+        let mut ws = MaybeUninit::<libc::winsize>::zeroed();
+        // SAFETY: `TIOCGWINSZ` writes a `winsize`, and it's zeroed if it fails
+        let ws = unsafe {
+            libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, ws.as_mut_ptr());
+            ws.assume_init()
+        };
+        if ws.ws_row > 0 {
+            ws.ws_row as usize
+        } else {
+            24
+        }
+    }
+
+    fn draw(&self, view: &str) {
+        let mut stdout = io::stdout().lock();
+        let _ = write!(stdout, "{ANSI_CLEAR}{view}");
+        let _ = stdout.flush();
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if let Some(orig) = self.original.as_ref() {
+            // SAFETY: `orig` is the `termios` read when entering
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, orig) };
+        }
+        print!("{ANSI_ALT_SCREEN_LEAVE}");
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Read the key presses from the standard input, on a dedicated thread.
+///
+/// The thread is not joined: it's blocked reading until the next key, or the process exits.
+fn read_keys() -> mpsc::UnboundedReceiver<u8> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for key in io::stdin().lock().bytes() {
+            match key {
+                Ok(k) if tx.send(k).is_ok() => {},
+                _ => break,
+            }
+        }
+    });
+    rx
+}
+
+/// Run the view, refreshing it every `refresh_interval`, until quit or `shutdown_token` is cancelled.
+pub async fn run(
+    handle: &KommittedHandle,
+    sort: TopSort,
+    refresh_interval: Duration,
+    colored: bool,
+    shutdown_token: CancellationToken,
+) {
+    let (cs_reg, po_reg, lag_reg) = (
+        handle.cluster_status_register(),
+        handle.partition_offsets_register(),
+        handle.lag_register(),
+    );
+
+    let terminal = Terminal::enter();
+    let mut keys = read_keys();
+    let mut view = TopView::new(sort);
+    let mut snapshot = LagSnapshot::default();
+
+    let mut refresh = interval(refresh_interval);
+    refresh.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = refresh.tick() => {
+                snapshot = LagSnapshot::take(&cs_reg, &po_reg, &lag_reg).await;
+            },
+            Some(key) = keys.recv() => {
+                match view.handle_key(key) {
+                    KeyOutcome::Redraw => {},
+                    KeyOutcome::Ignored => continue,
+                    KeyOutcome::Quit => break,
+                }
+            },
+            _ = shutdown_token.cancelled() => break,
+        }
+
+        let max_rows = Terminal::rows().saturating_sub(CHROME_LINES).max(1);
+        terminal.draw(&view.render(&snapshot, max_rows, colored));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn row(group: &str, topic: &str, offset_lag: u64, max_time_lag_ms: i64) -> LagTableRow {
+        LagTableRow {
+            group: group.into(),
+            topic: topic.into(),
+            partitions: 1,
+            offset_lag,
+            max_time_lag_ms,
+        }
+    }
+
+    fn groups(table: &LagTable) -> Vec<&str> {
+        table.rows.iter().map(|r| r.group.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_and_filters() {
+        let table = LagTable {
+            rows: vec![
+                row("billing", "invoices", 10, 1_000),
+                row("orders", "orders", 500, 90_000),
+                row("audit", "orders", 0, 0),
+            ],
+        };
+
+        let mut view = TopView::new(TopSort::TimeLag);
+        assert_eq!(groups(&view.apply(table.clone())), vec!["orders", "billing", "audit"]);
+
+        assert_eq!(view.handle_key(b'r'), KeyOutcome::Redraw);
+        assert_eq!(groups(&view.apply(table.clone())), vec!["audit", "billing", "orders"]);
+
+        // Cycles to Group, ascending
+        assert_eq!(view.handle_key(b's'), KeyOutcome::Redraw);
+        assert_eq!(groups(&view.apply(table.clone())), vec!["audit", "billing", "orders"]);
+
+        for k in b"/ORD" {
+            assert_eq!(view.handle_key(*k), KeyOutcome::Redraw);
+        }
+        // While editing the filter, keys are typed into it
+        assert_eq!(view.handle_key(b'q'), KeyOutcome::Redraw);
+        assert_eq!(view.handle_key(KEY_BACKSPACE), KeyOutcome::Redraw);
+        assert_eq!(view.handle_key(b'\n'), KeyOutcome::Redraw);
+        assert_eq!(groups(&view.apply(table.clone())), vec!["audit", "orders"]);
+
+        assert_eq!(view.handle_key(b'c'), KeyOutcome::Redraw);
+        assert_eq!(view.apply(table.clone()).rows.len(), 3);
+        assert_eq!(view.handle_key(b'x'), KeyOutcome::Ignored);
+        assert_eq!(view.handle_key(b'q'), KeyOutcome::Quit);
+    }
+}
@@ -0,0 +1,163 @@
+//! Validation of the configuration as a whole: command line, environment and configuration file.
+//!
+//! Each argument is validated on its own while parsing: this catches what's valid on its own,
+//! but not in combination with the rest (e.g. contradictory options), or what's silently ignored
+//! (e.g. misspelled environment variables).
+
+use std::fmt;
+use std::time::Duration;
+
+use rdkafka::{error::KafkaError, ClientConfig};
+
+/// Prefix of the environment variables that set command line arguments.
+pub const ENV_PREFIX: &str = "KOMMITTED_";
+
+/// Environment variables with the [`ENV_PREFIX`] that are not for command line arguments
+/// (e.g. the logging filter).
+const NON_ARGUMENT_ENV_VARS: [&str; 1] = ["KOMMITTED_LOG"];
+
+/// Min interval of periodic work: any shorter, and it would spin.
+pub const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How to handle the [`ConfigIssue`]s found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ValidationMode {
+    /// Refuse to start.
+    Strict,
+
+    /// Log each as a warning, and start anyway.
+    #[default]
+    Permissive,
+}
+
+/// An issue with the configuration, that would make the service behave differently than intended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// Option (or environment variable, or Kafka client configuration key) the issue is with.
+    pub option: String,
+
+    pub problem: String,
+}
+
+impl ConfigIssue {
+    pub fn new(option: impl Into<String>, problem: impl Into<String>) -> Self {
+        Self {
+            option: option.into(),
+            problem: problem.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}': {}", self.option, self.problem)
+    }
+}
+
+/// Environment variables with the [`ENV_PREFIX`] that don't set any command line argument
+/// (e.g. misspelled).
+///
+/// # Arguments
+///
+/// * `known` - Environment variables that set a command line argument
+/// * `vars` - Names of the environment variables set
+pub fn unknown_env_vars(known: &[String], vars: impl Iterator<Item = String>) -> Vec<ConfigIssue> {
+    let mut unknown = vars
+        .filter(|v| v.starts_with(ENV_PREFIX) && !known.contains(v))
+        .filter(|v| !NON_ARGUMENT_ENV_VARS.contains(&v.as_str()))
+        .map(|v| ConfigIssue::new(v, "unknown environment variable, ignored"))
+        .collect::<Vec<_>>();
+    unknown.sort_by(|a, b| a.option.cmp(&b.option));
+    unknown
+}
+
+/// Kafka client configuration keys unknown to `librdkafka`, or with invalid values.
+///
+/// Validated without connecting to the cluster, so it's usable before starting.
+pub fn invalid_kafka_config<'a>(
+    option: &str,
+    config: impl Iterator<Item = (&'a String, &'a String)>,
+) -> Vec<ConfigIssue> {
+    config
+        .filter_map(|(k, v)| {
+            let mut client_config = ClientConfig::new();
+            client_config.set(k, v);
+            client_config.create_native_config().err().map(|e| {
+                let problem = match e {
+                    KafkaError::ClientConfig(_, desc, _, _) => desc,
+                    e => e.to_string(),
+                };
+                ConfigIssue::new(format!("{option} {k}"), problem)
+            })
+        })
+        .collect()
+}
+
+/// Check that the interval set for an option is within the given range.
+pub fn out_of_range(
+    option: &str,
+    interval: Duration,
+    min: Duration,
+    max: Option<Duration>,
+) -> Option<ConfigIssue> {
+    let fmt = humantime::format_duration;
+    if interval < min {
+        return Some(ConfigIssue::new(
+            option,
+            format!("{} is shorter than the minimum of {}", fmt(interval), fmt(min)),
+        ));
+    }
+    max.filter(|max| interval > *max).map(|max| {
+        ConfigIssue::new(
+            option,
+            format!("{} is longer than the maximum of {}", fmt(interval), fmt(max)),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_unknown_env_vars() {
+        let known = vec!["KOMMITTED_API_KEY".to_string(), "KOMMITTED_API_SECRET".to_string()];
+        let vars = [
+            "KOMMITTED_API_SECRETS",
+            "KOMMITTED_API_KEY",
+            "KOMMITTED_LOG",
+            "PATH",
+            "KOMMITTED_APIKEY",
+        ];
+        let issues = unknown_env_vars(&known, vars.into_iter().map(String::from));
+        assert_eq!(
+            issues.iter().map(|i| i.option.as_str()).collect::<Vec<_>>(),
+            vec!["KOMMITTED_APIKEY", "KOMMITTED_API_SECRETS"]
+        );
+    }
+
+    #[test]
+    fn finds_invalid_kafka_config() {
+        let config = [
+            ("socket.timeout.ms".to_string(), "60000".to_string()),
+            ("socket.timeout.mss".to_string(), "60000".to_string()),
+            ("socket.keepalive.enable".to_string(), "maybe".to_string()),
+        ];
+        let issues = invalid_kafka_config("--kafka-conf", config.iter().map(|(k, v)| (k, v)));
+        assert_eq!(
+            issues.iter().map(|i| i.option.as_str()).collect::<Vec<_>>(),
+            vec!["--kafka-conf socket.timeout.mss", "--kafka-conf socket.keepalive.enable"]
+        );
+    }
+
+    #[test]
+    fn checks_intervals_range() {
+        let s = Duration::from_secs;
+        assert!(out_of_range("--x", s(10), s(1), Some(s(60))).is_none());
+        assert!(out_of_range("--x", s(0), s(1), None).is_some());
+        assert_eq!(
+            out_of_range("--x", s(120), s(1), Some(s(60))).unwrap().to_string(),
+            "'--x': 2m is longer than the maximum of 1m"
+        );
+    }
+}